@@ -45,6 +45,18 @@ pub mod model {
 pub enum ConvertError {
     #[error("DICOM JSON conversion error: {0}")]
     Json(String),
+    #[error("Unresolvable DICOM JSON key: {0}")]
+    UnknownKey(String),
+}
+
+/// How identifier keys that are neither hex tags nor dictionary keywords are handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyMode {
+    /// Leave them as-is (conversion fails later if the key is used)
+    #[default]
+    Lenient,
+    /// Reject them with [`ConvertError::UnknownKey`]
+    Strict,
 }
 
 pub type Result<T> = std::result::Result<T, ConvertError>;
@@ -55,9 +67,108 @@ pub fn identifier_to_json_value(obj: &dicom_object::mem::InMemDicomObject) -> Re
     Ok(v)
 }
 
+/// Trim DICOM value padding in place: trailing NUL/space on UI, leading and trailing spaces on
+/// AE and CS (PS3.5 6.2). Descends into sequence items.
+pub fn trim_padding(v: &mut Value) {
+    let Some(map) = v.as_object_mut() else {
+        return;
+    };
+    for entry in map.values_mut() {
+        let vr = entry
+            .get("vr")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let Some(values) = entry.get_mut("Value").and_then(|v| v.as_array_mut()) else {
+            continue;
+        };
+        match vr.as_str() {
+            "UI" | "AE" | "CS" => {
+                for value in values.iter_mut() {
+                    if let Value::String(s) = value {
+                        let trimmed = if vr == "UI" {
+                            s.trim_end_matches(['\0', ' '])
+                        } else {
+                            s.trim_matches(['\0', ' '])
+                        };
+                        if trimmed.len() != s.len() {
+                            *s = trimmed.to_string();
+                        }
+                    }
+                }
+            }
+            "SQ" => {
+                for item in values.iter_mut() {
+                    trim_padding(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Resolve a DICOM JSON key to 8-digit uppercase hex: hex tags in any case, or dictionary
+/// keywords such as `PatientID`
+fn canonical_tag_key(key: &str) -> Option<String> {
+    use dicom_core::dictionary::DataDictionary;
+    use dicom_dictionary_std::StandardDataDictionary;
+
+    if key.len() == 8 && key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(key.to_ascii_uppercase());
+    }
+    StandardDataDictionary.by_name(key).map(|entry| {
+        let tag = entry.tag.inner();
+        format!("{:08X}", (tag.0 as u32) << 16 | tag.1 as u32)
+    })
+}
+
+/// Canonicalize identifier keys to uppercase hex tags, descending into sequence items
+///
+/// When a keyword and its hex tag are both present, the hex-keyed element wins.
+pub fn normalize_tag_keys(v: &Value, mode: KeyMode) -> Result<Value> {
+    let Some(map) = v.as_object() else {
+        return Ok(v.clone());
+    };
+    let mut out = serde_json::Map::with_capacity(map.len());
+    for (key, entry) in map {
+        let mut entry = entry.clone();
+        let is_sequence = entry.get("vr").and_then(|vr| vr.as_str()) == Some("SQ");
+        if is_sequence {
+            if let Some(items) = entry.get_mut("Value").and_then(|v| v.as_array_mut()) {
+                for item in items.iter_mut() {
+                    *item = normalize_tag_keys(item, mode)?;
+                }
+            }
+        }
+
+        match canonical_tag_key(key) {
+            Some(canonical) if canonical.eq_ignore_ascii_case(key) => {
+                out.insert(canonical, entry);
+            }
+            Some(canonical) => {
+                out.entry(canonical).or_insert(entry);
+            }
+            None if mode == KeyMode::Strict => return Err(ConvertError::UnknownKey(key.clone())),
+            None => {
+                out.insert(key.clone(), entry);
+            }
+        }
+    }
+    Ok(Value::Object(out))
+}
+
+/// Build an identifier dataset, canonicalizing keys leniently first
 pub fn json_value_to_identifier(v: &Value) -> Result<dicom_object::mem::InMemDicomObject> {
+    json_value_to_identifier_with(v, KeyMode::Lenient)
+}
+
+pub fn json_value_to_identifier_with(
+    v: &Value,
+    mode: KeyMode,
+) -> Result<dicom_object::mem::InMemDicomObject> {
+    let normalized = normalize_tag_keys(v, mode)?;
     let obj =
-        dicom_json::from_value(v.clone()).map_err(|e| ConvertError::Json(format!("{}", e)))?;
+        dicom_json::from_value(normalized).map_err(|e| ConvertError::Json(format!("{}", e)))?;
     Ok(obj)
 }
 
@@ -105,6 +216,8 @@ pub fn write_part10(path: &Path, obj: &dicom_object::mem::InMemDicomObject) -> R
 }
 
 /// Try to parse a wrapper from a JSON value; if it's not a wrapper, treat it as a raw identifier
+///
+/// Identifier keys are canonicalized leniently (see [`normalize_tag_keys`]).
 pub fn parse_wrapper_or_identifier(
     v: &Value,
 ) -> (
@@ -113,8 +226,11 @@ pub fn parse_wrapper_or_identifier(
     Option<model::QueryMetadata>,
 ) {
     // Attempt to deserialize as a wrapper first
+    let normalize = |ident: &Value| {
+        normalize_tag_keys(ident, KeyMode::Lenient).unwrap_or_else(|_| ident.clone())
+    };
     if let Ok(w) = serde_json::from_value::<model::Wrapper>(v.clone()) {
-        return (w.command, w.identifier, w.query_metadata);
+        return (w.command, normalize(&w.identifier), w.query_metadata);
     }
-    (None, v.clone(), None)
+    (None, normalize(v), None)
 }
@@ -27,6 +27,9 @@ enum Cmd {
         input: PathBuf,
         #[arg(short, long)]
         output: PathBuf,
+        /// Reject identifier keys that are neither hex tags nor dictionary keywords
+        #[arg(long)]
+        strict_keys: bool,
     },
 }
 
@@ -44,13 +47,22 @@ fn main() -> anyhow::Result<()> {
                 Err(e) => Err(anyhow::anyhow!(e)),
             }
         }
-        Cmd::FromJson { input, output } => {
+        Cmd::FromJson {
+            input,
+            output,
+            strict_keys,
+        } => {
             let text = std::fs::read_to_string(&input)?;
             let v: serde_json::Value = serde_json::from_str(&text)?;
             // Accept either wrapper or raw identifier JSON
             let (_cmd, identifier, _qmeta) = tool::parse_wrapper_or_identifier(&v);
-            let obj: InMemDicomObject =
-                tool::json_value_to_identifier(&identifier).map_err(|e| anyhow::anyhow!(e))?;
+            let mode = if strict_keys {
+                tool::KeyMode::Strict
+            } else {
+                tool::KeyMode::Lenient
+            };
+            let obj: InMemDicomObject = tool::json_value_to_identifier_with(&identifier, mode)
+                .map_err(|e| anyhow::anyhow!(e))?;
             tool::write_part10(&output, &obj).map_err(|e| anyhow::anyhow!(e))?;
             eprintln!("Wrote Part 10 file to {}", output.display());
             Ok(())
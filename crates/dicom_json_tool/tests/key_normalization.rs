@@ -0,0 +1,50 @@
+use dicom_dictionary_std::tags;
+use dicom_json_tool as tool;
+use serde_json::json;
+
+#[test]
+fn keyword_keyed_identifier_is_converted() {
+    let ident = json!({
+        "PatientID": { "vr": "LO", "Value": ["PID-001"] },
+        "0020000d": { "vr": "UI", "Value": ["1.2.3.4"] },
+        "ReferencedSeriesSequence": { "vr": "SQ", "Value": [
+            { "SeriesInstanceUID": { "vr": "UI", "Value": ["1.2.3.4.5"] } }
+        ]}
+    });
+
+    let normalized = tool::normalize_tag_keys(&ident, tool::KeyMode::Lenient).unwrap();
+    assert_eq!(normalized["00100020"]["Value"][0], "PID-001");
+    assert_eq!(normalized["0020000D"]["Value"][0], "1.2.3.4");
+    assert_eq!(
+        normalized["00081115"]["Value"][0]["0020000E"]["Value"][0],
+        "1.2.3.4.5"
+    );
+
+    let obj = tool::json_value_to_identifier(&ident).expect("json->obj");
+    assert_eq!(
+        obj.element(tags::PATIENT_ID).unwrap().to_str().unwrap(),
+        "PID-001"
+    );
+    assert_eq!(
+        obj.element(tags::STUDY_INSTANCE_UID)
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        "1.2.3.4"
+    );
+}
+
+#[test]
+fn strict_mode_rejects_unresolvable_keys() {
+    let ident = json!({
+        "PatientID": { "vr": "LO", "Value": ["PID-001"] },
+        "NotADicomKeyword": { "vr": "LO", "Value": ["x"] }
+    });
+
+    let err = tool::json_value_to_identifier_with(&ident, tool::KeyMode::Strict).unwrap_err();
+    assert!(matches!(err, tool::ConvertError::UnknownKey(ref k) if k == "NotADicomKeyword"));
+
+    let (_cmd, lenient, _meta) = tool::parse_wrapper_or_identifier(&json!({ "identifier": ident }));
+    assert!(lenient.get("00100020").is_some());
+    assert!(lenient.get("NotADicomKeyword").is_some());
+}
@@ -0,0 +1,43 @@
+use dicom_core::{DataElement, PrimitiveValue, VR};
+use dicom_dictionary_std::tags;
+use dicom_json_tool as tool;
+use dicom_object::InMemDicomObject;
+use serde_json::json;
+
+#[test]
+fn padded_study_uid_is_normalized() {
+    let study_uid = |padded: &str| {
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::STUDY_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(padded),
+        ));
+        let mut json = tool::identifier_to_json_value(&obj).expect("obj->json");
+        tool::trim_padding(&mut json);
+        json["0020000D"]["Value"][0].as_str().unwrap().to_string()
+    };
+
+    assert_eq!(study_uid("1.2.840.113619.2.55 "), "1.2.840.113619.2.55");
+    assert_eq!(study_uid("1.2.840.113619.2.55\0"), "1.2.840.113619.2.55");
+    assert_eq!(study_uid("1.2.840.113619.2.55"), "1.2.840.113619.2.55");
+}
+
+#[test]
+fn trims_ae_and_cs_but_leaves_other_vrs() {
+    let mut json = json!({
+        "00020016": { "vr": "AE", "Value": [" STORESCU  "] },
+        "00080060": { "vr": "CS", "Value": ["CT "] },
+        "00081030": { "vr": "LO", "Value": ["Head "] },
+        "00081115": { "vr": "SQ", "Value": [
+            { "0020000E": { "vr": "UI", "Value": ["1.2.3\0"] } }
+        ]}
+    });
+
+    tool::trim_padding(&mut json);
+
+    assert_eq!(json["00020016"]["Value"][0], "STORESCU");
+    assert_eq!(json["00080060"]["Value"][0], "CT");
+    assert_eq!(json["00081030"]["Value"][0], "Head ");
+    assert_eq!(json["00081115"]["Value"][0]["0020000E"]["Value"][0], "1.2.3");
+}
@@ -0,0 +1,1160 @@
+//! Association negotiation helpers (A-ASSOCIATE-RQ inspection, A-ASSOCIATE-AC/RJ, release and
+//! abort PDUs, maximum PDU length and transfer syntax negotiation, P-DATA-TF fragmentation and
+//! reassembly)
+//!
+//! Mostly used by the SCP; the SCU's native C-GET also builds its A-ASSOCIATE-RQ here, since
+//! it needs SCP/SCU Role Selection.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::config::RoleSelection;
+
+/// PDU type of an A-ASSOCIATE-RQ
+pub const PDU_ASSOCIATE_RQ: u8 = 0x01;
+/// PDU type of an A-ASSOCIATE-AC
+pub const PDU_ASSOCIATE_AC: u8 = 0x02;
+/// PDU type of an A-ASSOCIATE-RJ
+pub const PDU_ASSOCIATE_RJ: u8 = 0x03;
+/// PDU type of a P-DATA-TF
+pub const PDU_P_DATA_TF: u8 = 0x04;
+/// PDU type of an A-RELEASE-RQ
+pub const PDU_RELEASE_RQ: u8 = 0x05;
+/// PDU type of an A-RELEASE-RP
+pub const PDU_RELEASE_RP: u8 = 0x06;
+/// PDU type of an A-ABORT
+pub const PDU_ABORT: u8 = 0x07;
+
+/// Bytes of the type/reserved/length prefix shared by every PDU
+pub const PDU_HEADER_LEN: usize = 6;
+
+/// Bytes of an A-ASSOCIATE-RQ needed to read the called and calling AE titles
+pub const ASSOCIATE_RQ_HEADER_LEN: usize = 42;
+
+/// Body bytes of an A-ASSOCIATE-RQ before its variable items (version, AE titles, reserved)
+pub const ASSOCIATE_RQ_FIXED_LEN: u32 = 68;
+
+/// Largest A-ASSOCIATE-RQ body the SCP will buffer; real requests, even with every storage
+/// SOP class proposed, are a fraction of this
+pub const MAX_ASSOCIATE_RQ_LEN: u32 = 64 * 1024;
+
+/// DICOM Application Context Name, the only one defined
+pub const APPLICATION_CONTEXT_NAME: &str = "1.2.840.10008.3.1.1.1";
+/// Implementation Class UID sent in the A-ASSOCIATE-AC and written to file meta information
+pub const IMPLEMENTATION_CLASS_UID: &str = "2.25.40933331557690084209821879966974462659";
+/// Implementation Version Name accompanying [`IMPLEMENTATION_CLASS_UID`]
+pub const IMPLEMENTATION_VERSION_NAME: &str = "HARMONY";
+
+/// Presentation context result: acceptance
+pub const CONTEXT_ACCEPTED: u8 = 0;
+/// Presentation context result: abstract-syntax-not-supported (provider rejection)
+pub const CONTEXT_ABSTRACT_SYNTAX_NOT_SUPPORTED: u8 = 3;
+/// Presentation context result: transfer-syntaxes-not-supported (provider rejection)
+pub const CONTEXT_TRANSFER_SYNTAXES_NOT_SUPPORTED: u8 = 4;
+
+/// Variable item type of the Application Context item
+const ITEM_APPLICATION_CONTEXT: u8 = 0x10;
+/// Variable item type of a Presentation Context item in an A-ASSOCIATE-RQ
+const ITEM_PRESENTATION_CONTEXT_RQ: u8 = 0x20;
+/// Variable item type of a Presentation Context item in an A-ASSOCIATE-AC
+const ITEM_PRESENTATION_CONTEXT_AC: u8 = 0x21;
+/// Presentation Context sub-item type carrying the Abstract Syntax
+const SUB_ITEM_ABSTRACT_SYNTAX: u8 = 0x30;
+/// Presentation Context sub-item type carrying one proposed Transfer Syntax
+const SUB_ITEM_TRANSFER_SYNTAX: u8 = 0x40;
+/// Variable item type of the User Information item in an A-ASSOCIATE-RQ/AC
+const ITEM_USER_INFORMATION: u8 = 0x50;
+/// User Information sub-item type carrying the Maximum Length
+const SUB_ITEM_MAX_LENGTH: u8 = 0x51;
+/// User Information sub-item type carrying the Implementation Class UID
+const SUB_ITEM_IMPLEMENTATION_CLASS_UID: u8 = 0x52;
+/// User Information sub-item type carrying an SCP/SCU Role Selection
+const SUB_ITEM_ROLE_SELECTION: u8 = 0x54;
+/// User Information sub-item type carrying the Implementation Version Name
+const SUB_ITEM_IMPLEMENTATION_VERSION_NAME: u8 = 0x55;
+/// User Information sub-item type carrying a SOP Class Extended Negotiation
+const SUB_ITEM_EXTENDED_NEGOTIATION: u8 = 0x56;
+
+/// Bytes a PDV adds to its fragment: item length, presentation context ID, control header
+pub const PDV_HEADER_LEN: usize = 6;
+/// PDV control header bit set on command fragments (clear for data set fragments)
+pub const PDV_COMMAND: u8 = 0x01;
+/// PDV control header bit set on the last fragment of a command or data set
+pub const PDV_LAST: u8 = 0x02;
+
+/// A-ABORT source for aborts initiated by the SCP as UL service-provider
+pub const ABORT_SOURCE_PROVIDER: u8 = 2;
+/// A-ABORT reason: unexpected-PDU
+pub const ABORT_UNEXPECTED_PDU: u8 = 2;
+/// A-ABORT reason: invalid-PDU-parameter-value
+pub const ABORT_INVALID_PARAMETER: u8 = 6;
+
+/// Why the SCP is rejecting an association
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// Called AE title does not match the SCP's local AE title
+    UnknownCalledAet,
+    /// Calling AE title is not on the SCP's allow list
+    AclDenied,
+    /// The SCP is already serving `max_associations` associations
+    AssociationLimit,
+}
+
+/// Result/source/reason triple sent in an A-ASSOCIATE-RJ (PS3.8 Table 9-21)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssociateRejection {
+    /// 1 = rejected-permanent, 2 = rejected-transient
+    pub result: u8,
+    /// 1 = service-user, 2 = service-provider (ACSE), 3 = service-provider (presentation)
+    pub source: u8,
+    /// Diagnostic, interpreted relative to `source`
+    pub reason: u8,
+}
+
+impl AssociateRejection {
+    pub const fn new(result: u8, source: u8, reason: u8) -> Self {
+        Self {
+            result,
+            source,
+            reason,
+        }
+    }
+
+    /// Encode as an A-ASSOCIATE-RJ PDU
+    pub fn to_pdu(&self) -> [u8; 10] {
+        [
+            PDU_ASSOCIATE_RJ,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x04, // PDU length
+            0x00,
+            self.result,
+            self.source,
+            self.reason,
+        ]
+    }
+
+    /// Check the triple is a combination allowed by PS3.8
+    pub fn validate(&self) -> Result<(), String> {
+        if !matches!(self.result, 1 | 2) {
+            return Err(format!(
+                "Invalid A-ASSOCIATE-RJ result {} (expected 1 or 2)",
+                self.result
+            ));
+        }
+        let valid_reasons: &[u8] = match self.source {
+            1 => &[1, 2, 3, 7],
+            2 => &[1, 2],
+            3 => &[1, 2],
+            other => {
+                return Err(format!(
+                    "Invalid A-ASSOCIATE-RJ source {} (expected 1, 2 or 3)",
+                    other
+                ))
+            }
+        };
+        if !valid_reasons.contains(&self.reason) {
+            return Err(format!(
+                "Invalid A-ASSOCIATE-RJ reason {} for source {} (expected one of {:?})",
+                self.reason, self.source, valid_reasons
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Rejection codes used for each [`RejectReason`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectionCodes {
+    /// Default: permanent / service-user / called-AE-title-not-recognized
+    #[serde(default = "default_unknown_called_aet")]
+    pub unknown_called_aet: AssociateRejection,
+    /// Default: permanent / service-user / calling-AE-title-not-recognized
+    #[serde(default = "default_acl_denied")]
+    pub acl_denied: AssociateRejection,
+    /// Default: transient / service-provider (presentation) / local-limit-exceeded
+    #[serde(default = "default_association_limit")]
+    pub association_limit: AssociateRejection,
+}
+
+impl Default for RejectionCodes {
+    fn default() -> Self {
+        Self {
+            unknown_called_aet: default_unknown_called_aet(),
+            acl_denied: default_acl_denied(),
+            association_limit: default_association_limit(),
+        }
+    }
+}
+
+impl RejectionCodes {
+    pub fn for_reason(&self, reason: RejectReason) -> AssociateRejection {
+        match reason {
+            RejectReason::UnknownCalledAet => self.unknown_called_aet,
+            RejectReason::AclDenied => self.acl_denied,
+            RejectReason::AssociationLimit => self.association_limit,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.unknown_called_aet
+            .validate()
+            .map_err(|e| format!("unknown_called_aet: {}", e))?;
+        self.acl_denied
+            .validate()
+            .map_err(|e| format!("acl_denied: {}", e))?;
+        self.association_limit
+            .validate()
+            .map_err(|e| format!("association_limit: {}", e))
+    }
+}
+
+fn default_unknown_called_aet() -> AssociateRejection {
+    AssociateRejection::new(1, 1, 7)
+}
+
+fn default_acl_denied() -> AssociateRejection {
+    AssociateRejection::new(1, 1, 3)
+}
+
+fn default_association_limit() -> AssociateRejection {
+    AssociateRejection::new(2, 3, 2)
+}
+
+/// Encode an A-RELEASE-RP PDU
+pub fn release_rp_pdu() -> [u8; 10] {
+    [
+        PDU_RELEASE_RP,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        0x04,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+    ]
+}
+
+/// Encode an A-RELEASE-RQ PDU
+pub fn release_rq_pdu() -> [u8; 10] {
+    let mut pdu = release_rp_pdu();
+    pdu[0] = PDU_RELEASE_RQ;
+    pdu
+}
+
+/// Encode an A-ABORT PDU (PS3.8 Table 9-26)
+///
+/// `source` is 0 for the UL service-user and 2 for the UL service-provider; `reason` is only
+/// meaningful for provider-initiated aborts.
+pub fn abort_pdu(source: u8, reason: u8) -> [u8; 10] {
+    [
+        PDU_ABORT, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, source, reason,
+    ]
+}
+
+/// Read the PDU type and body length from a 6-byte PDU header
+pub fn parse_pdu_header(bytes: &[u8; PDU_HEADER_LEN]) -> (u8, u32) {
+    (
+        bytes[0],
+        u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+    )
+}
+
+/// Whether a declared A-ASSOCIATE-RQ body length is plausible, checked before buffering it
+pub fn associate_rq_len_ok(len: u32) -> bool {
+    (ASSOCIATE_RQ_FIXED_LEN..=MAX_ASSOCIATE_RQ_LEN).contains(&len)
+}
+
+/// Whether `items` is a sequence of type/reserved/length items that ends exactly at the buffer end
+pub fn variable_items_well_formed(items: &[u8]) -> bool {
+    let mut rest = items;
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return false;
+        }
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let Some(next) = rest.get(4 + len..) else {
+            return false;
+        };
+        rest = next;
+    }
+    true
+}
+
+/// Maximum Length a peer advertised in the User Information item of an A-ASSOCIATE-RQ/AC
+///
+/// `items` are the variable items following the fixed fields. `Some(0)` means the peer set no
+/// limit; `None` means the item is missing.
+pub fn advertised_max_pdu(items: &[u8]) -> Option<u32> {
+    let mut rest = items;
+    while rest.len() >= 4 {
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let body = rest.get(4..4 + len)?;
+        if rest[0] == ITEM_USER_INFORMATION {
+            let mut sub = body;
+            while sub.len() >= 4 {
+                let sub_len = u16::from_be_bytes([sub[2], sub[3]]) as usize;
+                let value = sub.get(4..4 + sub_len)?;
+                if sub[0] == SUB_ITEM_MAX_LENGTH && sub_len == 4 {
+                    return Some(u32::from_be_bytes([value[0], value[1], value[2], value[3]]));
+                }
+                sub = &sub[4 + sub_len..];
+            }
+            return None;
+        }
+        rest = &rest[4 + len..];
+    }
+    None
+}
+
+/// A presentation context proposed in an A-ASSOCIATE-RQ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedContext {
+    pub id: u8,
+    pub abstract_syntax: String,
+    /// Transfer syntaxes in the order the peer listed them
+    pub transfer_syntaxes: Vec<String>,
+}
+
+/// Presentation contexts proposed in the variable items of an A-ASSOCIATE-RQ
+///
+/// Malformed context items are skipped; run [`variable_items_well_formed`] first to reject
+/// requests whose item lengths do not add up.
+pub fn proposed_contexts(items: &[u8]) -> Vec<ProposedContext> {
+    let uid = |bytes: &[u8]| {
+        String::from_utf8_lossy(bytes)
+            .trim_end_matches(['\0', ' '])
+            .to_string()
+    };
+    let mut contexts = Vec::new();
+    let mut rest = items;
+    while rest.len() >= 4 {
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let Some(body) = rest.get(4..4 + len) else {
+            break;
+        };
+        // Context ID and three reserved bytes precede the sub-items
+        if rest[0] == ITEM_PRESENTATION_CONTEXT_RQ && body.len() >= 4 {
+            let mut context = ProposedContext {
+                id: body[0],
+                abstract_syntax: String::new(),
+                transfer_syntaxes: Vec::new(),
+            };
+            let mut sub = &body[4..];
+            while sub.len() >= 4 {
+                let sub_len = u16::from_be_bytes([sub[2], sub[3]]) as usize;
+                let Some(value) = sub.get(4..4 + sub_len) else {
+                    break;
+                };
+                match sub[0] {
+                    SUB_ITEM_ABSTRACT_SYNTAX => context.abstract_syntax = uid(value),
+                    SUB_ITEM_TRANSFER_SYNTAX => context.transfer_syntaxes.push(uid(value)),
+                    _ => {}
+                }
+                sub = &sub[4 + sub_len..];
+            }
+            contexts.push(context);
+        }
+        rest = &rest[4 + len..];
+    }
+    contexts
+}
+
+/// Transfer syntax to accept for a presentation context: the first the peer proposed that is in
+/// `allowed`, or `None` to reject the context (result 4, transfer-syntaxes-not-supported)
+pub fn select_transfer_syntax<'a>(proposed: &'a [String], allowed: &[String]) -> Option<&'a str> {
+    proposed
+        .iter()
+        .find(|ts| allowed.contains(ts))
+        .map(String::as_str)
+}
+
+/// How the SCP answered one proposed presentation context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextResult {
+    pub id: u8,
+    pub abstract_syntax: String,
+    /// One of the `CONTEXT_*` result codes
+    pub result: u8,
+    /// Transfer syntax accepted for the context (`None` unless `result` is acceptance)
+    pub transfer_syntax: Option<String>,
+}
+
+/// Encode an A-ASSOCIATE-AC answering `header` with the given context results
+///
+/// `negotiation` answers the requestor's SCP/SCU Role Selection (see [`negotiate_roles`]) and
+/// SOP Class Extended Negotiation items. `max_pdu` is the Maximum Length this side can
+/// receive.
+pub fn encode_associate_ac(
+    header: &AssociateRequestHeader,
+    contexts: &[ContextResult],
+    negotiation: &Negotiation,
+    max_pdu: u32,
+) -> Vec<u8> {
+    let mut items = Vec::new();
+    push_item(
+        &mut items,
+        ITEM_APPLICATION_CONTEXT,
+        APPLICATION_CONTEXT_NAME.as_bytes(),
+    );
+    for context in contexts {
+        // The transfer syntax sub-item is required even when the context is rejected, but its
+        // value is not significant then
+        let transfer_syntax = context
+            .transfer_syntax
+            .as_deref()
+            .unwrap_or(crate::config::IMPLICIT_VR_LITTLE_ENDIAN);
+        let mut body = vec![context.id, 0x00, context.result, 0x00];
+        push_item(
+            &mut body,
+            SUB_ITEM_TRANSFER_SYNTAX,
+            transfer_syntax.as_bytes(),
+        );
+        push_item(&mut items, ITEM_PRESENTATION_CONTEXT_AC, &body);
+    }
+    push_item(
+        &mut items,
+        ITEM_USER_INFORMATION,
+        &user_information(max_pdu, negotiation),
+    );
+    encode_associate(
+        PDU_ASSOCIATE_AC,
+        &header.called_aet,
+        &header.calling_aet,
+        &items,
+    )
+}
+
+/// Encode an A-ASSOCIATE-RQ proposing `contexts`
+///
+/// The role selection and extended negotiation items in `negotiation` are proposed for the SOP
+/// classes among the contexts, such as the SCP role a C-GET needs for the storage SOP classes
+/// it retrieves. `max_pdu` is the Maximum Length this side can receive.
+pub fn encode_associate_rq(
+    calling_aet: &str,
+    called_aet: &str,
+    contexts: &[ProposedContext],
+    negotiation: &Negotiation,
+    max_pdu: u32,
+) -> Vec<u8> {
+    let mut items = Vec::new();
+    push_item(
+        &mut items,
+        ITEM_APPLICATION_CONTEXT,
+        APPLICATION_CONTEXT_NAME.as_bytes(),
+    );
+    for context in contexts {
+        let mut body = vec![context.id, 0x00, 0x00, 0x00];
+        push_item(
+            &mut body,
+            SUB_ITEM_ABSTRACT_SYNTAX,
+            context.abstract_syntax.as_bytes(),
+        );
+        for transfer_syntax in &context.transfer_syntaxes {
+            push_item(
+                &mut body,
+                SUB_ITEM_TRANSFER_SYNTAX,
+                transfer_syntax.as_bytes(),
+            );
+        }
+        push_item(&mut items, ITEM_PRESENTATION_CONTEXT_RQ, &body);
+    }
+    // Roles and extended negotiation only apply to abstract syntaxes with a presentation context
+    let proposed = |uid: &str| contexts.iter().any(|c| c.abstract_syntax == uid);
+    let negotiation = Negotiation {
+        roles: negotiation
+            .roles
+            .iter()
+            .filter(|role| proposed(&role.sop_class_uid))
+            .cloned()
+            .collect(),
+        extended: negotiation
+            .extended
+            .iter()
+            .filter(|extended| proposed(&extended.sop_class_uid))
+            .cloned()
+            .collect(),
+    };
+    push_item(
+        &mut items,
+        ITEM_USER_INFORMATION,
+        &user_information(max_pdu, &negotiation),
+    );
+    encode_associate(PDU_ASSOCIATE_RQ, called_aet, calling_aet, &items)
+}
+
+/// Results of the presentation contexts in the variable items of an A-ASSOCIATE-AC
+///
+/// Each result is matched to the `proposed` context with the same ID to fill in its abstract
+/// syntax; results for IDs that were never proposed are dropped.
+pub fn accepted_contexts(items: &[u8], proposed: &[ProposedContext]) -> Vec<ContextResult> {
+    let mut results = Vec::new();
+    let mut rest = items;
+    while rest.len() >= 4 {
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let Some(body) = rest.get(4..4 + len) else {
+            break;
+        };
+        // Context ID, reserved, result/reason and reserved precede the transfer syntax
+        if rest[0] == ITEM_PRESENTATION_CONTEXT_AC && body.len() >= 4 {
+            if let Some(context) = proposed.iter().find(|c| c.id == body[0]) {
+                let mut transfer_syntax = None;
+                let mut sub = &body[4..];
+                while sub.len() >= 4 {
+                    let sub_len = u16::from_be_bytes([sub[2], sub[3]]) as usize;
+                    let Some(value) = sub.get(4..4 + sub_len) else {
+                        break;
+                    };
+                    if sub[0] == SUB_ITEM_TRANSFER_SYNTAX {
+                        transfer_syntax = Some(
+                            String::from_utf8_lossy(value)
+                                .trim_end_matches(['\0', ' '])
+                                .to_string(),
+                        );
+                    }
+                    sub = &sub[4 + sub_len..];
+                }
+                results.push(ContextResult {
+                    id: body[0],
+                    abstract_syntax: context.abstract_syntax.clone(),
+                    result: body[2],
+                    transfer_syntax: transfer_syntax.filter(|_| body[2] == CONTEXT_ACCEPTED),
+                });
+            }
+        }
+        rest = &rest[4 + len..];
+    }
+    results
+}
+
+/// SOP Class Extended Negotiation for one SOP class (PS3.7 D.3.3.5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedNegotiation {
+    pub sop_class_uid: String,
+    /// Service-class-application-information, defined by the SOP class's service class
+    pub application_information: Vec<u8>,
+}
+
+/// User Information sub-items an A-ASSOCIATE-RQ proposes besides the fixed ones
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Negotiation {
+    /// SCP/SCU Role Selection items
+    pub roles: Vec<RoleSelection>,
+    /// SOP Class Extended Negotiation items
+    pub extended: Vec<ExtendedNegotiation>,
+}
+
+impl Negotiation {
+    /// Role Selection items only
+    pub fn roles(roles: &[RoleSelection]) -> Self {
+        Self {
+            roles: roles.to_vec(),
+            extended: Vec::new(),
+        }
+    }
+}
+
+/// Type and value of each sub-item in the User Information item of an A-ASSOCIATE-RQ or -AC
+fn user_information_sub_items(items: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut sub_items = Vec::new();
+    let mut rest = items;
+    while rest.len() >= 4 {
+        let len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let Some(body) = rest.get(4..4 + len) else {
+            break;
+        };
+        if rest[0] == ITEM_USER_INFORMATION {
+            let mut sub = body;
+            while sub.len() >= 4 {
+                let sub_len = u16::from_be_bytes([sub[2], sub[3]]) as usize;
+                let Some(value) = sub.get(4..4 + sub_len) else {
+                    break;
+                };
+                sub_items.push((sub[0], value));
+                sub = &sub[4 + sub_len..];
+            }
+        }
+        rest = &rest[4 + len..];
+    }
+    sub_items
+}
+
+/// Split a sub-item value made of a UID length, the UID and the sub-item's own fields
+fn split_sop_class_uid(value: &[u8]) -> Option<(String, &[u8])> {
+    let uid_len = u16::from_be_bytes([*value.first()?, *value.get(1)?]) as usize;
+    let uid = value.get(2..2 + uid_len)?;
+    let uid = String::from_utf8_lossy(uid)
+        .trim_end_matches(['\0', ' '])
+        .to_string();
+    Some((uid, &value[2 + uid_len..]))
+}
+
+/// Encode a sub-item value made of a UID length, the UID and `fields`
+fn sop_class_uid_value(uid: &str, fields: &[u8]) -> Vec<u8> {
+    let mut value = (uid.len() as u16).to_be_bytes().to_vec();
+    value.extend_from_slice(uid.as_bytes());
+    value.extend_from_slice(fields);
+    value
+}
+
+/// SCP/SCU Role Selection items in the User Information item of an A-ASSOCIATE-RQ or -AC
+///
+/// The roles always describe the association requestor: in a request the roles it proposes
+/// to take, in an acceptance the ones the acceptor agreed to.
+pub fn role_selections(items: &[u8]) -> Vec<RoleSelection> {
+    user_information_sub_items(items)
+        .into_iter()
+        .filter(|(item_type, _)| *item_type == SUB_ITEM_ROLE_SELECTION)
+        .filter_map(|(_, value)| match split_sop_class_uid(value)? {
+            (uid, [scu, scp]) => Some(RoleSelection::new(uid, *scu == 1, *scp == 1)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// SOP Class Extended Negotiation items in the User Information item of an A-ASSOCIATE-RQ or
+/// -AC: the features proposed, or in an acceptance the ones the acceptor agreed to
+pub fn extended_negotiations(items: &[u8]) -> Vec<ExtendedNegotiation> {
+    user_information_sub_items(items)
+        .into_iter()
+        .filter(|(item_type, _)| *item_type == SUB_ITEM_EXTENDED_NEGOTIATION)
+        .filter_map(|(_, value)| {
+            let (sop_class_uid, information) = split_sop_class_uid(value)?;
+            Some(ExtendedNegotiation {
+                sop_class_uid,
+                application_information: information.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Answer a requestor's proposed SCP/SCU Role Selection items from the `local` roles
+///
+/// The requestor's SCU role is accepted when this side may act as SCP for the class, and its
+/// SCP role when this side may act as SCU. Classes without a local entry keep the default
+/// roles: the requestor is SCU only.
+pub fn negotiate_roles(proposed: &[RoleSelection], local: &[RoleSelection]) -> Vec<RoleSelection> {
+    proposed
+        .iter()
+        .map(|role| {
+            let (scu, scp) = match local.iter().find(|l| l.sop_class_uid == role.sop_class_uid) {
+                Some(local) => (role.scu && local.scp, role.scp && local.scu),
+                None => (role.scu, false),
+            };
+            RoleSelection::new(role.sop_class_uid.clone(), scu, scp)
+        })
+        .collect()
+}
+
+/// User Information item value: Maximum Length, implementation identification and the role
+/// selection and extended negotiation items of `negotiation`
+fn user_information(max_pdu: u32, negotiation: &Negotiation) -> Vec<u8> {
+    let mut user_information = Vec::new();
+    push_item(
+        &mut user_information,
+        SUB_ITEM_MAX_LENGTH,
+        &max_pdu.to_be_bytes(),
+    );
+    push_item(
+        &mut user_information,
+        SUB_ITEM_IMPLEMENTATION_CLASS_UID,
+        IMPLEMENTATION_CLASS_UID.as_bytes(),
+    );
+    for role in &negotiation.roles {
+        // UID length and UID, then the SCU and SCP roles (1 offered or accepted, 0 not)
+        let value = sop_class_uid_value(&role.sop_class_uid, &[role.scu as u8, role.scp as u8]);
+        push_item(&mut user_information, SUB_ITEM_ROLE_SELECTION, &value);
+    }
+    push_item(
+        &mut user_information,
+        SUB_ITEM_IMPLEMENTATION_VERSION_NAME,
+        IMPLEMENTATION_VERSION_NAME.as_bytes(),
+    );
+    for extended in &negotiation.extended {
+        let value = sop_class_uid_value(&extended.sop_class_uid, &extended.application_information);
+        push_item(&mut user_information, SUB_ITEM_EXTENDED_NEGOTIATION, &value);
+    }
+    user_information
+}
+
+/// Encode an A-ASSOCIATE-RQ or -AC around its variable items
+fn encode_associate(pdu_type: u8, called_aet: &str, calling_aet: &str, items: &[u8]) -> Vec<u8> {
+    let aet = |aet: &str| format!("{:<16}", aet).into_bytes();
+    let body_len = ASSOCIATE_RQ_FIXED_LEN as usize + items.len();
+    let mut pdu = Vec::with_capacity(PDU_HEADER_LEN + body_len);
+    pdu.extend_from_slice(&[pdu_type, 0x00]);
+    pdu.extend_from_slice(&(body_len as u32).to_be_bytes());
+    // Protocol version 1, then two reserved bytes
+    pdu.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+    pdu.extend_from_slice(&aet(called_aet));
+    pdu.extend_from_slice(&aet(calling_aet));
+    pdu.extend_from_slice(&[0u8; 32]);
+    pdu.extend_from_slice(items);
+    pdu
+}
+
+/// Append a type/reserved/length item holding `value`
+fn push_item(buf: &mut Vec<u8>, item_type: u8, value: &[u8]) {
+    buf.extend_from_slice(&[item_type, 0x00]);
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value);
+}
+
+/// One presentation data value from a P-DATA-TF PDU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pdv<'a> {
+    pub context_id: u8,
+    pub is_command: bool,
+    pub is_last: bool,
+    pub data: &'a [u8],
+}
+
+/// Split a P-DATA-TF body into its PDVs, or `None` if an item length does not fit
+pub fn parse_p_data(body: &[u8]) -> Option<Vec<Pdv<'_>>> {
+    let mut pdvs = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        // Context ID and control header are part of the item length
+        if len < 2 {
+            return None;
+        }
+        let item = rest.get(4..4 + len)?;
+        pdvs.push(Pdv {
+            context_id: item[0],
+            is_command: item[1] & PDV_COMMAND != 0,
+            is_last: item[1] & PDV_LAST != 0,
+            data: &item[2..],
+        });
+        rest = &rest[4 + len..];
+    }
+    Some(pdvs)
+}
+
+/// Largest P-DATA-TF PDU to use on an association: the smaller of the two advertised maximums,
+/// where a peer advertising 0 (or nothing) sets no limit of its own
+pub fn negotiate_max_pdu(local: u32, remote: Option<u32>) -> u32 {
+    match remote {
+        Some(remote) if remote > 0 => local.min(remote),
+        _ => local,
+    }
+}
+
+/// Encode a command or data set as P-DATA-TF PDUs no longer than `max_pdu`
+///
+/// `max_pdu` bounds the PDU body, as the Maximum Length sub-item does, so each PDU carries one
+/// PDV holding at most `max_pdu - PDV_HEADER_LEN` bytes. Only the final fragment has the last
+/// bit set.
+pub fn encode_p_data(context_id: u8, is_command: bool, data: &[u8], max_pdu: u32) -> Vec<Vec<u8>> {
+    let fragment_len = (max_pdu as usize).saturating_sub(PDV_HEADER_LEN).max(1);
+    let mut fragments: Vec<&[u8]> = data.chunks(fragment_len).collect();
+    if fragments.is_empty() {
+        fragments.push(&[]);
+    }
+    let count = fragments.len();
+    fragments
+        .into_iter()
+        .enumerate()
+        .map(|(i, fragment)| {
+            let mut control = if is_command { PDV_COMMAND } else { 0 };
+            if i + 1 == count {
+                control |= PDV_LAST;
+            }
+            let pdv_len = (fragment.len() + 2) as u32;
+            let mut pdu = Vec::with_capacity(PDU_HEADER_LEN + PDV_HEADER_LEN + fragment.len());
+            pdu.extend_from_slice(&[PDU_P_DATA_TF, 0x00]);
+            pdu.extend_from_slice(&(pdv_len + 4).to_be_bytes());
+            pdu.extend_from_slice(&pdv_len.to_be_bytes());
+            pdu.extend_from_slice(&[context_id, control]);
+            pdu.extend_from_slice(fragment);
+            pdu
+        })
+        .collect()
+}
+
+/// What was agreed for an association, for operators checking negotiation with a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssociationInfo {
+    /// Generated when the association is accepted and logged with everything done on it
+    pub correlation_id: Uuid,
+    pub calling_aet: String,
+    pub called_aet: String,
+    /// Maximum Length this side advertised
+    pub local_max_pdu: u32,
+    /// Maximum Length the peer advertised (`None` when it sent none, 0 for no limit)
+    pub remote_max_pdu: Option<u32>,
+    /// Largest P-DATA-TF PDU sent on the association, see [`negotiate_max_pdu`]
+    pub max_pdu_length: u32,
+}
+
+impl AssociationInfo {
+    pub fn new(
+        calling_aet: impl Into<String>,
+        called_aet: impl Into<String>,
+        local_max_pdu: u32,
+        remote_max_pdu: Option<u32>,
+    ) -> Self {
+        Self {
+            correlation_id: Uuid::new_v4(),
+            calling_aet: calling_aet.into(),
+            called_aet: called_aet.into(),
+            local_max_pdu,
+            remote_max_pdu,
+            max_pdu_length: negotiate_max_pdu(local_max_pdu, remote_max_pdu),
+        }
+    }
+}
+
+/// AE titles carried in the fixed part of an A-ASSOCIATE-RQ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssociateRequestHeader {
+    pub called_aet: String,
+    pub calling_aet: String,
+}
+
+impl AssociateRequestHeader {
+    /// Parse the first [`ASSOCIATE_RQ_HEADER_LEN`] bytes of an A-ASSOCIATE-RQ
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < ASSOCIATE_RQ_HEADER_LEN || bytes[0] != PDU_ASSOCIATE_RQ {
+            return None;
+        }
+        let aet = |range: std::ops::Range<usize>| {
+            String::from_utf8_lossy(&bytes[range]).trim().to_string()
+        };
+        Some(Self {
+            called_aet: aet(10..26),
+            calling_aet: aet(26..42),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_codes_are_valid() {
+        let codes = RejectionCodes::default();
+        assert!(codes.validate().is_ok());
+        assert_eq!(
+            codes.for_reason(RejectReason::AssociationLimit),
+            AssociateRejection::new(2, 3, 2)
+        );
+    }
+
+    #[test]
+    fn test_invalid_source_reason_combination_rejected() {
+        // Reason 7 is only defined for service-user rejections
+        assert!(AssociateRejection::new(1, 3, 7).validate().is_err());
+        assert!(AssociateRejection::new(3, 1, 1).validate().is_err());
+    }
+
+    #[test]
+    fn test_encode_rj_pdu() {
+        let pdu = AssociateRejection::new(2, 1, 3).to_pdu();
+        assert_eq!(pdu, [0x03, 0, 0, 0, 0, 4, 0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn test_encode_abort_and_release_pdus() {
+        assert_eq!(abort_pdu(0, 0), [0x07, 0, 0, 0, 0, 4, 0, 0, 0, 0]);
+        assert_eq!(release_rp_pdu()[0], PDU_RELEASE_RP);
+        assert_eq!(
+            parse_pdu_header(&[0x04, 0, 0, 0, 0x01, 0x02]),
+            (0x04, 0x0102)
+        );
+    }
+
+    #[test]
+    fn test_parse_rq_header() {
+        let mut rq = vec![0x01, 0x00, 0, 0, 0, 0x44, 0x00, 0x01, 0x00, 0x00];
+        rq.extend_from_slice(b"HARMONY_SCP     ");
+        rq.extend_from_slice(b"MODALITY1       ");
+        let header = AssociateRequestHeader::parse(&rq).unwrap();
+        assert_eq!(header.called_aet, "HARMONY_SCP");
+        assert_eq!(header.calling_aet, "MODALITY1");
+        assert!(AssociateRequestHeader::parse(&rq[..20]).is_none());
+    }
+
+    #[test]
+    fn test_associate_rq_bounds_and_items() {
+        assert!(associate_rq_len_ok(ASSOCIATE_RQ_FIXED_LEN));
+        assert!(!associate_rq_len_ok(ASSOCIATE_RQ_FIXED_LEN - 1));
+        assert!(!associate_rq_len_ok(u32::MAX));
+
+        // Application context item followed by an empty user information item
+        let mut items = vec![0x10, 0x00, 0x00, 0x15];
+        items.extend_from_slice(b"1.2.840.10008.3.1.1.1");
+        items.extend_from_slice(&[0x50, 0x00, 0x00, 0x00]);
+        assert!(variable_items_well_formed(&items));
+        assert!(variable_items_well_formed(&[]));
+        assert!(!variable_items_well_formed(&items[..items.len() - 1]));
+        assert!(!variable_items_well_formed(&[0x20, 0x00, 0xFF, 0xFF, 0x01]));
+    }
+
+    #[test]
+    fn test_max_pdu_negotiation() {
+        // Application context, then user information holding a 4096 byte Maximum Length
+        let mut items = vec![0x10, 0x00, 0x00, 0x15];
+        items.extend_from_slice(b"1.2.840.10008.3.1.1.1");
+        items.extend_from_slice(&[0x50, 0x00, 0x00, 0x08, 0x51, 0x00, 0x00, 0x04]);
+        items.extend_from_slice(&4096u32.to_be_bytes());
+        assert_eq!(advertised_max_pdu(&items), Some(4096));
+        assert_eq!(advertised_max_pdu(&items[..25]), None);
+
+        let info = AssociationInfo::new("MODALITY1", "HARMONY_SCP", 16384, Some(4096));
+        assert_eq!(info.max_pdu_length, 4096);
+        assert_eq!(negotiate_max_pdu(16384, Some(0)), 16384);
+        assert_eq!(negotiate_max_pdu(16384, None), 16384);
+    }
+
+    fn context_item(id: u8, abstract_syntax: &str, transfer_syntaxes: &[&str]) -> Vec<u8> {
+        let mut body = vec![id, 0x00, 0x00, 0x00];
+        let mut sub_item = |item_type: u8, uid: &str| {
+            body.extend_from_slice(&[item_type, 0x00]);
+            body.extend_from_slice(&(uid.len() as u16).to_be_bytes());
+            body.extend_from_slice(uid.as_bytes());
+        };
+        sub_item(0x30, abstract_syntax);
+        for ts in transfer_syntaxes {
+            sub_item(0x40, ts);
+        }
+        let mut item = vec![0x20, 0x00];
+        item.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        item.extend_from_slice(&body);
+        item
+    }
+
+    #[test]
+    fn test_proposed_contexts_and_transfer_syntax_selection() {
+        const IMPLICIT_VR_LE: &str = "1.2.840.10008.1.2";
+        const EXPLICIT_VR_LE: &str = "1.2.840.10008.1.2.1";
+        const JPEG_BASELINE: &str = "1.2.840.10008.1.2.4.50";
+
+        // The SCU proposes its preferences with Implicit VR Little Endian appended
+        let items = context_item(
+            1,
+            "1.2.840.10008.5.1.4.1.1.2",
+            &[JPEG_BASELINE, EXPLICIT_VR_LE, IMPLICIT_VR_LE],
+        );
+        assert!(variable_items_well_formed(&items));
+        let contexts = proposed_contexts(&items);
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].id, 1);
+        assert_eq!(contexts[0].abstract_syntax, "1.2.840.10008.5.1.4.1.1.2");
+
+        let proposed = &contexts[0].transfer_syntaxes;
+        let implicit_only = vec![IMPLICIT_VR_LE.to_string()];
+        assert_eq!(
+            select_transfer_syntax(proposed, &implicit_only),
+            Some(IMPLICIT_VR_LE)
+        );
+        // The peer's order wins when several proposed syntaxes are allowed
+        let uncompressed = vec![IMPLICIT_VR_LE.to_string(), EXPLICIT_VR_LE.to_string()];
+        assert_eq!(
+            select_transfer_syntax(proposed, &uncompressed),
+            Some(EXPLICIT_VR_LE)
+        );
+        assert_eq!(select_transfer_syntax(&proposed[..2], &implicit_only), None);
+    }
+
+    #[test]
+    fn test_encode_associate_ac() {
+        let header = AssociateRequestHeader {
+            called_aet: "HARMONY_SCP".to_string(),
+            calling_aet: "MODALITY1".to_string(),
+        };
+        let contexts = vec![
+            ContextResult {
+                id: 1,
+                abstract_syntax: "1.2.840.10008.5.1.4.1.1.2".to_string(),
+                result: CONTEXT_ACCEPTED,
+                transfer_syntax: Some("1.2.840.10008.1.2.1".to_string()),
+            },
+            ContextResult {
+                id: 3,
+                abstract_syntax: "1.2.840.10008.5.1.4.1.2.2.1".to_string(),
+                result: CONTEXT_ABSTRACT_SYNTAX_NOT_SUPPORTED,
+                transfer_syntax: None,
+            },
+        ];
+        let pdu = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+
+        let (pdu_type, len) = parse_pdu_header(pdu[..PDU_HEADER_LEN].try_into().unwrap());
+        assert_eq!(pdu_type, PDU_ASSOCIATE_AC);
+        assert_eq!(len as usize, pdu.len() - PDU_HEADER_LEN);
+        assert_eq!(&pdu[10..26], b"HARMONY_SCP     ");
+        assert_eq!(&pdu[26..42], b"MODALITY1       ");
+
+        let items = &pdu[PDU_HEADER_LEN + ASSOCIATE_RQ_FIXED_LEN as usize..];
+        assert!(variable_items_well_formed(items));
+        assert_eq!(advertised_max_pdu(items), Some(16384));
+        // Application context (4 + 21 bytes), then the first context item: ID 1, accepted
+        assert_eq!(&items[25..26], &[ITEM_PRESENTATION_CONTEXT_AC]);
+        assert_eq!(items[29], 1);
+        assert_eq!(items[31], CONTEXT_ACCEPTED);
+    }
+
+    #[test]
+    fn test_encode_associate_rq_with_scp_role_and_read_ac() {
+        const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        let proposed = vec![
+            ProposedContext {
+                id: 1,
+                abstract_syntax: "1.2.840.10008.5.1.4.1.2.2.3".to_string(),
+                transfer_syntaxes: vec!["1.2.840.10008.1.2".to_string()],
+            },
+            ProposedContext {
+                id: 3,
+                abstract_syntax: CT_IMAGE_STORAGE.to_string(),
+                transfer_syntaxes: vec!["1.2.840.10008.1.2".to_string()],
+            },
+        ];
+        let rq = encode_associate_rq(
+            "HARMONY_SCU",
+            "PACS",
+            &proposed,
+            &Negotiation::roles(&[
+                RoleSelection::new(CT_IMAGE_STORAGE, false, true),
+                RoleSelection::new("1.2.840.10008.5.1.4.1.1.4", false, true),
+            ]),
+            16384,
+        );
+        let header = AssociateRequestHeader::parse(&rq).unwrap();
+        assert_eq!(header.called_aet, "PACS");
+        assert_eq!(header.calling_aet, "HARMONY_SCU");
+
+        let items = &rq[PDU_HEADER_LEN + ASSOCIATE_RQ_FIXED_LEN as usize..];
+        assert!(variable_items_well_formed(items));
+        assert_eq!(proposed_contexts(items), proposed);
+        assert_eq!(advertised_max_pdu(items), Some(16384));
+        // Role selection: UID, SCU role not offered, SCP role offered
+        let mut role = vec![SUB_ITEM_ROLE_SELECTION, 0x00, 0x00, 29, 0x00, 25];
+        role.extend_from_slice(CT_IMAGE_STORAGE.as_bytes());
+        role.extend_from_slice(&[0x00, 0x01]);
+        assert!(items.windows(role.len()).any(|w| w == role.as_slice()));
+        // MR Image has no presentation context, so no role is proposed for it
+        assert_eq!(
+            role_selections(items),
+            vec![RoleSelection::new(CT_IMAGE_STORAGE, false, true)]
+        );
+
+        // The peer accepts the storage context and rejects the query/retrieve one
+        let contexts = vec![
+            ContextResult {
+                id: 1,
+                abstract_syntax: String::new(),
+                result: CONTEXT_ABSTRACT_SYNTAX_NOT_SUPPORTED,
+                transfer_syntax: None,
+            },
+            ContextResult {
+                id: 3,
+                abstract_syntax: String::new(),
+                result: CONTEXT_ACCEPTED,
+                transfer_syntax: Some("1.2.840.10008.1.2".to_string()),
+            },
+        ];
+        let ac = encode_associate_ac(&header, &contexts, &Default::default(), 4096);
+        let results = accepted_contexts(
+            &ac[PDU_HEADER_LEN + ASSOCIATE_RQ_FIXED_LEN as usize..],
+            &proposed,
+        );
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].result, CONTEXT_ABSTRACT_SYNTAX_NOT_SUPPORTED);
+        assert_eq!(results[0].transfer_syntax, None);
+        assert_eq!(results[1].abstract_syntax, CT_IMAGE_STORAGE);
+        assert_eq!(
+            results[1].transfer_syntax.as_deref(),
+            Some("1.2.840.10008.1.2")
+        );
+
+        assert_eq!(release_rq_pdu(), [0x05, 0, 0, 0, 0, 4, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_role_selection_answered_from_local_roles() {
+        const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        const MR_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.4";
+        const COMMITMENT: &str = "1.2.840.10008.1.20.1";
+        let proposed = vec![
+            RoleSelection::new(CT_IMAGE_STORAGE, true, true),
+            RoleSelection::new(MR_IMAGE_STORAGE, false, true),
+            RoleSelection::new(COMMITMENT, true, true),
+        ];
+        let local = vec![
+            RoleSelection::new(CT_IMAGE_STORAGE, false, true),
+            RoleSelection::new(MR_IMAGE_STORAGE, true, false),
+        ];
+
+        let answered = negotiate_roles(&proposed, &local);
+        assert_eq!(
+            answered,
+            vec![
+                // This side only stores CT, so the requestor may not take the SCP role
+                RoleSelection::new(CT_IMAGE_STORAGE, true, false),
+                // This side may send MR, so the requestor may receive it
+                RoleSelection::new(MR_IMAGE_STORAGE, false, true),
+                // Unconfigured classes keep the default roles
+                RoleSelection::new(COMMITMENT, true, false),
+            ]
+        );
+
+        let header = AssociateRequestHeader {
+            called_aet: "HARMONY_SCP".to_string(),
+            calling_aet: "MODALITY".to_string(),
+        };
+        let ac = encode_associate_ac(&header, &[], &Negotiation::roles(&answered), 16384);
+        let items = &ac[PDU_HEADER_LEN + ASSOCIATE_RQ_FIXED_LEN as usize..];
+        assert!(variable_items_well_formed(items));
+        assert_eq!(role_selections(items), answered);
+        assert_eq!(advertised_max_pdu(items), Some(16384));
+    }
+
+    #[test]
+    fn test_parse_p_data_round_trips_encoded_fragments() {
+        let pdus = encode_p_data(5, true, b"command bytes", 4096);
+        let pdvs = parse_p_data(&pdus[0][PDU_HEADER_LEN..]).unwrap();
+        assert_eq!(
+            pdvs,
+            vec![Pdv {
+                context_id: 5,
+                is_command: true,
+                is_last: true,
+                data: b"command bytes",
+            }]
+        );
+
+        // Item length running past the end of the PDU
+        assert!(parse_p_data(&[0, 0, 0, 9, 5, 3, 0]).is_none());
+        assert!(parse_p_data(&[0, 0, 0, 1, 5]).is_none());
+    }
+
+    #[test]
+    fn test_dataset_fragmented_to_negotiated_max_pdu() {
+        let dataset: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let pdus = encode_p_data(1, false, &dataset, 4096);
+        assert_eq!(pdus.len(), 3);
+
+        let mut reassembled = Vec::new();
+        for (i, pdu) in pdus.iter().enumerate() {
+            let (pdu_type, len) = parse_pdu_header(pdu[..PDU_HEADER_LEN].try_into().unwrap());
+            assert_eq!(pdu_type, PDU_P_DATA_TF);
+            assert!(len <= 4096, "PDU {} is {} bytes", i, len);
+            assert_eq!(len as usize, pdu.len() - PDU_HEADER_LEN);
+
+            let control = pdu[PDU_HEADER_LEN + 5];
+            assert_eq!(control & PDV_COMMAND, 0);
+            assert_eq!(control & PDV_LAST != 0, i == pdus.len() - 1);
+            reassembled.extend_from_slice(&pdu[PDU_HEADER_LEN + PDV_HEADER_LEN..]);
+        }
+        assert_eq!(reassembled, dataset);
+
+        let command = encode_p_data(3, true, &[], 4096);
+        assert_eq!(command.len(), 1);
+        assert_eq!(command[0][PDU_HEADER_LEN + 5], PDV_COMMAND | PDV_LAST);
+    }
+}
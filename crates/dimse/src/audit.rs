@@ -0,0 +1,410 @@
+//! DICOM audit trail (ATNA) records for associations and DIMSE operations
+//!
+//! With `audit` configured, the SCP and SCU describe each association and operation as a
+//! DICOM Audit Message (PS3.15 A.5, the DICOM form of RFC 3881) and send it to a file, one
+//! record per line, or to a syslog collector over UDP with the RFC 5424 framing of IHE ATNA.
+//!
+//! | Event | EventID | EventTypeCode |
+//! |---|---|---|
+//! | Association opened | 110108 Network Entry | 110124 Attach |
+//! | Association closed | 110108 Network Entry | 110125 Detach |
+//! | C-FIND | 110112 Query | |
+//! | C-MOVE, C-GET | 110104 DICOM Instances Transferred | |
+//! | C-STORE | 110104 DICOM Instances Transferred | |
+//!
+//! Emitting is best effort: a destination that cannot be written is logged and the
+//! operation carries on.
+
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use base64::Engine;
+use dicom_dictionary_std::tags;
+use dicom_object::InMemDicomObject;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::types::DimseStatus;
+
+/// Where audit records go and how the audit source is named
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Destination of the records
+    pub destination: AuditDestination,
+
+    /// AuditSourceID of every record (default: the local AE title)
+    #[serde(default)]
+    pub source_id: Option<String>,
+}
+
+/// Audit record destination
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditDestination {
+    /// Append each record as one line of XML
+    File { path: PathBuf },
+    /// Send each record as an RFC 5424 message over UDP
+    Syslog {
+        host: String,
+        #[serde(default = "default_syslog_port")]
+        port: u16,
+    },
+}
+
+/// What an audit record describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventKind {
+    AssociationOpened,
+    AssociationClosed,
+    Query,
+    InstancesTransferred,
+}
+
+/// EventOutcomeIndicator of a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Success,
+    MinorFailure,
+    SeriousFailure,
+}
+
+/// One Application Entity taking part in an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditParticipant {
+    pub ae_title: String,
+    /// Host name or IP address, when known
+    pub host: Option<String>,
+    /// Whether this participant started the association or operation
+    pub requestor: bool,
+}
+
+/// An event to record
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    pub outcome: AuditOutcome,
+    /// Source first (the sender of the query or instances), destination second
+    pub participants: Vec<AuditParticipant>,
+    /// SOP class of the query or the instances, when known
+    pub sop_class_uid: Option<String>,
+    /// Encoded query identifier, for `Query` events
+    pub query: Option<Vec<u8>>,
+    pub patient_id: Option<String>,
+    pub study_uids: Vec<String>,
+}
+
+/// Coded value as `csd-code`, `codeSystemName` and `originalText`
+type Code = (&'static str, &'static str, &'static str);
+
+const NETWORK_ENTRY: Code = ("110108", "DCM", "Network Entry");
+const ATTACH: Code = ("110124", "DCM", "Attach");
+const DETACH: Code = ("110125", "DCM", "Detach");
+const QUERY: Code = ("110112", "DCM", "Query");
+const INSTANCES_TRANSFERRED: Code = ("110104", "DCM", "DICOM Instances Transferred");
+const SOURCE_ROLE: Code = ("110153", "DCM", "Source Role ID");
+const DESTINATION_ROLE: Code = ("110152", "DCM", "Destination Role ID");
+const STUDY_INSTANCE_UID: Code = ("110180", "DCM", "Study Instance UID");
+const SOP_CLASS_UID: Code = ("110181", "DCM", "SOP Class UID");
+const PATIENT_NUMBER: Code = ("2", "RFC-3881", "Patient Number");
+
+impl AuditOutcome {
+    /// Outcome of an operation that ended with DIMSE `status`
+    pub fn from_status(status: u16) -> Self {
+        match DimseStatus::from_code(status) {
+            DimseStatus::Success | DimseStatus::Pending => AuditOutcome::Success,
+            DimseStatus::Warning(_) | DimseStatus::Cancel => AuditOutcome::MinorFailure,
+            DimseStatus::Failure(_) => AuditOutcome::SeriousFailure,
+        }
+    }
+}
+
+impl AuditEvent {
+    /// An event without participants yet
+    pub fn new(kind: AuditEventKind, outcome: AuditOutcome) -> Self {
+        Self {
+            kind,
+            outcome,
+            participants: Vec::new(),
+            sop_class_uid: None,
+            query: None,
+            patient_id: None,
+            study_uids: Vec::new(),
+        }
+    }
+
+    /// Add a participant; the first added is the source, the second the destination
+    pub fn with_participant(mut self, ae_title: &str, host: Option<&str>, requestor: bool) -> Self {
+        self.participants.push(AuditParticipant {
+            ae_title: ae_title.trim().to_string(),
+            host: host.map(str::to_string),
+            requestor,
+        });
+        self
+    }
+
+    /// Record the SOP class the event concerns
+    pub fn with_sop_class(mut self, sop_class_uid: Option<&str>) -> Self {
+        self.sop_class_uid = sop_class_uid.map(|uid| uid.trim_end_matches('\0').to_string());
+        self
+    }
+
+    /// Record the encoded query identifier
+    pub fn with_query(mut self, query: Vec<u8>) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    /// Record the patient and study the event concerns; empty values are skipped
+    pub fn with_subject(mut self, patient_id: Option<&str>, study_uid: Option<&str>) -> Self {
+        let clean = |s: &str| s.trim_end_matches(['\0', ' ']).to_string();
+        self.patient_id = patient_id.map(clean).filter(|s| !s.is_empty());
+        self.study_uids
+            .extend(study_uid.map(clean).filter(|s| !s.is_empty()));
+        self
+    }
+
+    /// Record the patient and study named in a data set
+    pub fn with_subject_of(self, object: &InMemDicomObject) -> Self {
+        let text = |tag| {
+            object
+                .element_opt(tag)
+                .ok()
+                .flatten()
+                .and_then(|e| e.to_str().ok())
+                .map(|s| s.to_string())
+        };
+        let patient_id = text(tags::PATIENT_ID);
+        let study_uid = text(tags::STUDY_INSTANCE_UID);
+        self.with_subject(patient_id.as_deref(), study_uid.as_deref())
+    }
+
+    /// The record as a DICOM Audit Message
+    pub fn to_xml(&self, source_id: &str) -> String {
+        let (event_id, type_code, action) = match self.kind {
+            AuditEventKind::AssociationOpened => (NETWORK_ENTRY, Some(ATTACH), "E"),
+            AuditEventKind::AssociationClosed => (NETWORK_ENTRY, Some(DETACH), "E"),
+            AuditEventKind::Query => (QUERY, None, "E"),
+            AuditEventKind::InstancesTransferred => (INSTANCES_TRANSFERRED, None, "R"),
+        };
+        let outcome = match self.outcome {
+            AuditOutcome::Success => 0,
+            AuditOutcome::MinorFailure => 4,
+            AuditOutcome::SeriousFailure => 8,
+        };
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?><AuditMessage>");
+        xml.push_str(&format!(
+            "<EventIdentification EventActionCode=\"{}\" EventDateTime=\"{}\" EventOutcomeIndicator=\"{}\">{}",
+            action,
+            now,
+            outcome,
+            code_element("EventID", event_id)
+        ));
+        if let Some(type_code) = type_code {
+            xml.push_str(&code_element("EventTypeCode", type_code));
+        }
+        xml.push_str("</EventIdentification>");
+
+        for (i, participant) in self.participants.iter().enumerate() {
+            xml.push_str(&format!(
+                "<ActiveParticipant UserID=\"{ae}\" AlternativeUserID=\"AETITLES={ae}\" UserIsRequestor=\"{}\"",
+                participant.requestor,
+                ae = escape(&participant.ae_title)
+            ));
+            if let Some(host) = &participant.host {
+                let type_code = if host.parse::<IpAddr>().is_ok() { 2 } else { 1 };
+                xml.push_str(&format!(
+                    " NetworkAccessPointID=\"{}\" NetworkAccessPointTypeCode=\"{}\"",
+                    escape(host),
+                    type_code
+                ));
+            }
+            // Further participants (a C-MOVE requestor) carry no role
+            match i {
+                0 => xml.push_str(&format!(
+                    ">{}</ActiveParticipant>",
+                    code_element("RoleIDCode", SOURCE_ROLE)
+                )),
+                1 => xml.push_str(&format!(
+                    ">{}</ActiveParticipant>",
+                    code_element("RoleIDCode", DESTINATION_ROLE)
+                )),
+                _ => xml.push_str("/>"),
+            }
+        }
+
+        xml.push_str(&format!(
+            "<AuditSourceIdentification AuditSourceID=\"{}\"/>",
+            escape(source_id)
+        ));
+
+        if let Some(patient_id) = &self.patient_id {
+            xml.push_str(&participant_object(patient_id, 1, 1, PATIENT_NUMBER, None));
+        }
+        for study_uid in &self.study_uids {
+            xml.push_str(&participant_object(
+                study_uid,
+                2,
+                3,
+                STUDY_INSTANCE_UID,
+                None,
+            ));
+        }
+        if let (AuditEventKind::Query, Some(sop_class)) = (self.kind, &self.sop_class_uid) {
+            let query = self
+                .query
+                .as_deref()
+                .map(|q| base64::engine::general_purpose::STANDARD.encode(q));
+            xml.push_str(&participant_object(sop_class, 2, 3, SOP_CLASS_UID, query));
+        }
+        xml.push_str("</AuditMessage>");
+        xml
+    }
+}
+
+fn code_element(name: &str, (code, system, text): Code) -> String {
+    format!(
+        "<{} csd-code=\"{}\" codeSystemName=\"{}\" originalText=\"{}\"/>",
+        name, code, system, text
+    )
+}
+
+fn participant_object(
+    id: &str,
+    type_code: u8,
+    role: u8,
+    id_type: Code,
+    query: Option<String>,
+) -> String {
+    let query = query
+        .map(|q| format!("<ParticipantObjectQuery>{}</ParticipantObjectQuery>", q))
+        .unwrap_or_default();
+    format!(
+        "<ParticipantObjectIdentification ParticipantObjectID=\"{}\" ParticipantObjectTypeCode=\"{}\" ParticipantObjectTypeCodeRole=\"{}\">{}{}</ParticipantObjectIdentification>",
+        escape(id),
+        type_code,
+        role,
+        code_element("ParticipantObjectIDTypeCode", id_type),
+        query
+    )
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Send `event` to the configured destination
+///
+/// `local_aet` names the audit source unless `source_id` is configured.
+pub async fn emit(config: &AuditConfig, local_aet: &str, event: &AuditEvent) {
+    let source_id = config.source_id.as_deref().unwrap_or(local_aet);
+    let xml = event.to_xml(source_id);
+    let result = match &config.destination {
+        AuditDestination::File { path } => append(path, &xml).await,
+        AuditDestination::Syslog { host, port } => send_syslog(host, *port, source_id, &xml).await,
+    };
+    if let Err(e) = result {
+        warn!("Failed to write audit record: {}", e);
+    }
+}
+
+async fn append(path: &std::path::Path, xml: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(format!("{}\n", xml).as_bytes()).await
+}
+
+/// RFC 5424 message with facility 10 (security/authorization), severity 5 (notice) and the
+/// IHE ATNA message ID
+async fn send_syslog(host: &str, port: u16, app_name: &str, xml: &str) -> std::io::Result<()> {
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string());
+    let message = format!(
+        "<85>1 {} {} {} {} IHE+RFC-3881 - {}",
+        timestamp,
+        hostname,
+        app_name.replace(' ', "_"),
+        std::process::id(),
+        xml
+    );
+    let socket = tokio::net::UdpSocket::bind(("0.0.0.0", 0)).await?;
+    socket.send_to(message.as_bytes(), (host, port)).await?;
+    Ok(())
+}
+
+fn default_syslog_port() -> u16 {
+    514
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_record_names_participants_and_study() {
+        let event = AuditEvent::new(
+            AuditEventKind::InstancesTransferred,
+            AuditOutcome::from_status(0x0000),
+        )
+        .with_participant("MODALITY", Some("10.0.0.5"), true)
+        .with_participant("HARMONY", None, false)
+        .with_subject(Some("P<1>"), Some("1.2.3\0"));
+
+        let xml = event.to_xml("HARMONY");
+
+        assert!(xml.contains("csd-code=\"110104\""));
+        assert!(xml.contains("EventActionCode=\"R\""));
+        assert!(xml.contains(
+            "UserID=\"MODALITY\" AlternativeUserID=\"AETITLES=MODALITY\" UserIsRequestor=\"true\" NetworkAccessPointID=\"10.0.0.5\" NetworkAccessPointTypeCode=\"2\""
+        ));
+        assert!(xml.contains(
+            "UserID=\"HARMONY\" AlternativeUserID=\"AETITLES=HARMONY\" UserIsRequestor=\"false\">"
+        ));
+        assert!(xml.contains("ParticipantObjectID=\"P&lt;1&gt;\""));
+        assert!(xml.contains("ParticipantObjectID=\"1.2.3\""));
+        assert!(xml.contains("<AuditSourceIdentification AuditSourceID=\"HARMONY\"/>"));
+        assert_eq!(
+            AuditOutcome::from_status(0xA700),
+            AuditOutcome::SeriousFailure
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_destination_appends_one_record_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit").join("atna.log");
+        let config = AuditConfig {
+            destination: AuditDestination::File { path: path.clone() },
+            source_id: Some("GATEWAY".to_string()),
+        };
+        for kind in [
+            AuditEventKind::AssociationOpened,
+            AuditEventKind::AssociationClosed,
+        ] {
+            let event = AuditEvent::new(kind, AuditOutcome::Success)
+                .with_participant("A", None, true)
+                .with_participant("B", None, false);
+            emit(&config, "B", &event).await;
+        }
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("csd-code=\"110124\""));
+        assert!(lines[1].contains("csd-code=\"110125\""));
+        assert!(lines[1].contains("AuditSourceID=\"GATEWAY\""));
+    }
+}
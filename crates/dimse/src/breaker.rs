@@ -0,0 +1,336 @@
+//! Per-node circuit breaker for SCU operations
+//!
+//! While a peer is down every operation still waits out a full connect timeout. With
+//! `circuit_breaker` configured, `failure_threshold` consecutive association failures against
+//! a node open its breaker: operations then fail at once with [`DimseError::CircuitOpen`] until
+//! the cooldown has passed, after which a single trial operation is let through (half-open).
+//! A successful trial closes the breaker, a failed one opens it for another cooldown.
+//!
+//! Only failures to reach the peer count: [`DimseError::is_recoverable`] errors. A refusal
+//! status or an invalid query says nothing about availability. Like the association pool the
+//! state is process-wide, since the services build a fresh `DimseScu` per request.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info, warn};
+
+use crate::config::RemoteNode;
+use crate::{DimseError, Result};
+
+/// Thresholds of the circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed operations that open the breaker
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// How long an open breaker fails operations before letting a trial through, in milliseconds
+    #[serde(default = "default_cooldown")]
+    pub cooldown_ms: u64,
+}
+
+/// State of a node's breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Operations run normally
+    Closed,
+    /// Operations fail without a connection attempt
+    Open,
+    /// The cooldown has passed; the next operation is a trial
+    HalfOpen,
+}
+
+impl std::fmt::Display for BreakerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        })
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            cooldown_ms: default_cooldown(),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Get the cooldown as Duration
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_millis(self.cooldown_ms)
+    }
+
+    /// Validate the thresholds
+    pub fn validate(&self) -> Result<()> {
+        if self.failure_threshold == 0 || self.cooldown_ms == 0 {
+            return Err(DimseError::config(
+                "Circuit breaker failure_threshold and cooldown_ms must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Peer a breaker guards
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BreakerKey {
+    ae_title: String,
+    host: String,
+    port: u16,
+}
+
+impl BreakerKey {
+    fn new(node: &RemoteNode) -> Self {
+        Self {
+            ae_title: node.ae_title.clone(),
+            host: node.host.clone(),
+            port: node.port,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    /// Set while open: operations are refused until this instant
+    open_until: Option<Instant>,
+    /// A half-open trial is running; other operations keep failing fast
+    trial_in_flight: bool,
+}
+
+impl Breaker {
+    fn state(&self) -> BreakerState {
+        match self.open_until {
+            None => BreakerState::Closed,
+            Some(until) if Instant::now() < until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+}
+
+type Breakers = Mutex<HashMap<BreakerKey, Breaker>>;
+
+fn breakers() -> &'static Breakers {
+    static BREAKERS: OnceLock<Breakers> = OnceLock::new();
+    BREAKERS.get_or_init(Default::default)
+}
+
+/// Current state of the breaker for `node`; nodes never called are closed
+pub fn state(node: &RemoteNode) -> BreakerState {
+    breakers()
+        .lock()
+        .expect("circuit breaker mutex")
+        .get(&BreakerKey::new(node))
+        .map(Breaker::state)
+        .unwrap_or(BreakerState::Closed)
+}
+
+/// Permission to run one operation against a node, see [`admit`]
+///
+/// Dropping a permit without [`Permit::record`] leaves the breaker as it was, apart from
+/// freeing the trial slot of a half-open breaker.
+#[derive(Debug)]
+pub struct Permit {
+    key: BreakerKey,
+    config: CircuitBreakerConfig,
+    trial: bool,
+    recorded: bool,
+}
+
+/// Ask the breaker for `node` whether an operation may run
+///
+/// Fails with [`DimseError::CircuitOpen`] while the breaker is open, or half-open with its
+/// trial still running.
+pub fn admit(config: &CircuitBreakerConfig, node: &RemoteNode) -> Result<Permit> {
+    let key = BreakerKey::new(node);
+    let mut breakers = breakers().lock().expect("circuit breaker mutex");
+    let breaker = breakers.entry(key.clone()).or_default();
+    let trial = match breaker.state() {
+        BreakerState::Closed => false,
+        BreakerState::Open => {
+            let remaining = breaker
+                .open_until
+                .map(|until| until.saturating_duration_since(Instant::now()))
+                .unwrap_or_default();
+            return Err(DimseError::CircuitOpen(format!(
+                "{}@{}:{} is unavailable, retrying in {:?}",
+                key.ae_title, key.host, key.port, remaining
+            )));
+        }
+        BreakerState::HalfOpen if breaker.trial_in_flight => {
+            return Err(DimseError::CircuitOpen(format!(
+                "{}@{}:{} is unavailable, a trial operation is running",
+                key.ae_title, key.host, key.port
+            )));
+        }
+        BreakerState::HalfOpen => {
+            breaker.trial_in_flight = true;
+            true
+        }
+    };
+    Ok(Permit {
+        key,
+        config: *config,
+        trial,
+        recorded: false,
+    })
+}
+
+impl Permit {
+    /// Record how the operation ended: `error` is `None` for success
+    pub fn record(mut self, error: Option<&DimseError>) {
+        self.recorded = true;
+        let failed = error.is_some_and(DimseError::is_recoverable);
+        let mut breakers = breakers().lock().expect("circuit breaker mutex");
+        let breaker = breakers.entry(self.key.clone()).or_default();
+        if self.trial {
+            breaker.trial_in_flight = false;
+        }
+        if !failed {
+            if breaker.open_until.take().is_some() {
+                info!("Circuit breaker for {} closed", self.key.ae_title);
+            }
+            breaker.consecutive_failures = 0;
+            return;
+        }
+        breaker.consecutive_failures = breaker.consecutive_failures.saturating_add(1);
+        if self.trial || breaker.consecutive_failures >= self.config.failure_threshold {
+            if breaker.open_until.is_none() || self.trial {
+                warn!(
+                    "Circuit breaker for {} opened after {} consecutive failures; failing fast for {:?}",
+                    self.key.ae_title,
+                    breaker.consecutive_failures,
+                    self.config.cooldown()
+                );
+            }
+            breaker.open_until = Some(Instant::now() + self.config.cooldown());
+        }
+    }
+
+    /// Record the outcome of an operation whose result arrives as a stream
+    ///
+    /// An error returned up front is recorded at once. Otherwise the stream is forwarded and
+    /// the first error it yields, or its successful end, is recorded.
+    pub fn record_stream<T: Send + 'static>(
+        self,
+        result: Result<ReceiverStream<Result<T>>>,
+    ) -> Result<ReceiverStream<Result<T>>> {
+        let mut inner = match result {
+            Ok(stream) => stream.into_inner(),
+            Err(e) => {
+                self.record(Some(&e));
+                return Err(e);
+            }
+        };
+        let (tx, rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            let mut permit = Some(self);
+            while let Some(item) = inner.recv().await {
+                if let Err(e) = &item {
+                    if let Some(permit) = permit.take() {
+                        permit.record(Some(e));
+                    }
+                }
+                if tx.send(item).await.is_err() {
+                    break;
+                }
+            }
+            if let Some(permit) = permit {
+                permit.record(None);
+            }
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.recorded || !self.trial {
+            return;
+        }
+        if let Some(breaker) = breakers()
+            .lock()
+            .expect("circuit breaker mutex")
+            .get_mut(&self.key)
+        {
+            breaker.trial_in_flight = false;
+        }
+    }
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cooldown() -> u64 {
+    30_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(port: u16) -> RemoteNode {
+        RemoteNode::new("BREAKER_TEST", "127.0.0.1", port)
+    }
+
+    fn refused() -> DimseError {
+        DimseError::Network(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+    }
+
+    #[test]
+    fn test_opens_after_threshold_and_half_opens_after_cooldown() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 3,
+            cooldown_ms: 50,
+        };
+        let node = node(1);
+        for _ in 0..3 {
+            assert_eq!(state(&node), BreakerState::Closed);
+            admit(&config, &node).unwrap().record(Some(&refused()));
+        }
+        assert_eq!(state(&node), BreakerState::Open);
+        let err = admit(&config, &node).expect_err("open breaker admits nothing");
+        assert!(matches!(err, DimseError::CircuitOpen(_)), "{:?}", err);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(state(&node), BreakerState::HalfOpen);
+        let trial = admit(&config, &node).unwrap();
+        assert!(admit(&config, &node).is_err(), "one trial at a time");
+        trial.record(None);
+        assert_eq!(state(&node), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_failed_trial_reopens_and_other_errors_do_not_count() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown_ms: 30,
+        };
+        let node = node(2);
+        let status = DimseError::operation_failed("C-FIND failed with status 0xA700");
+        admit(&config, &node).unwrap().record(Some(&status));
+        assert_eq!(state(&node), BreakerState::Closed);
+
+        admit(&config, &node).unwrap().record(Some(&refused()));
+        std::thread::sleep(Duration::from_millis(40));
+        admit(&config, &node).unwrap().record(Some(&refused()));
+        assert_eq!(state(&node), BreakerState::Open);
+
+        // A trial dropped without an outcome frees the slot for the next one
+        std::thread::sleep(Duration::from_millis(40));
+        drop(admit(&config, &node).unwrap());
+        assert!(admit(&config, &node).is_ok());
+    }
+}
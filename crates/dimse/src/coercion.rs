@@ -0,0 +1,236 @@
+//! Tag coercion for outgoing C-STORE
+//!
+//! Relays often need to stamp instances before passing them on: set InstitutionName, add a
+//! private "routed-by" tag, or map PatientID into the receiving site's domain. A list of
+//! [`CoercionRule`]s is applied in order to each dataset just before it is sent.
+//!
+//! SOP Instance UID is part of the instance's identity, so the only rule that may touch it is
+//! an explicit `map`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use dicom_core::dictionary::{DataDictionary, VirtualVr};
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::{tags, StandardDataDictionary};
+use dicom_object::InMemDicomObject;
+use serde::{Deserialize, Serialize};
+
+/// A single coercion applied to one tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoercionRule {
+    /// Tag keyword (`InstitutionName`) or eight hex digits (`00080080`)
+    pub tag: String,
+
+    /// What to do with the tag
+    #[serde(flatten)]
+    pub action: CoercionAction,
+
+    /// VR for tags missing from the standard dictionary, such as private tags (default LO)
+    #[serde(default)]
+    pub vr: Option<String>,
+}
+
+/// Change made to a tag's value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CoercionAction {
+    /// Add the value only when the tag is absent
+    Set { value: String },
+    /// Write the value, replacing any existing one
+    Overwrite { value: String },
+    /// Replace the current value with its entry in `mapping`; unmapped values are kept
+    Map { mapping: HashMap<String, String> },
+    /// Delete the tag
+    Remove,
+}
+
+impl CoercionRule {
+    /// Check that the tag and VR resolve and that SOP Instance UID is only remapped
+    pub fn validate(&self) -> Result<(), String> {
+        let tag = self.resolve_tag()?;
+        self.resolve_vr(tag)?;
+        if tag == tags::SOP_INSTANCE_UID && !matches!(self.action, CoercionAction::Map { .. }) {
+            return Err(format!(
+                "Coercion of '{}' must use the 'map' action; SOP Instance UID is preserved otherwise",
+                self.tag
+            ));
+        }
+        Ok(())
+    }
+
+    /// Apply this rule to `obj`
+    pub fn apply(&self, obj: &mut InMemDicomObject) -> Result<(), String> {
+        let tag = self.resolve_tag()?;
+        let vr = self.resolve_vr(tag)?;
+        match &self.action {
+            CoercionAction::Set { value } => {
+                if obj.element_opt(tag).ok().flatten().is_none() {
+                    obj.put(DataElement::new(
+                        tag,
+                        vr,
+                        PrimitiveValue::from(value.as_str()),
+                    ));
+                }
+            }
+            CoercionAction::Overwrite { value } => {
+                obj.put(DataElement::new(
+                    tag,
+                    vr,
+                    PrimitiveValue::from(value.as_str()),
+                ));
+            }
+            CoercionAction::Map { mapping } => {
+                let current = obj
+                    .element_opt(tag)
+                    .ok()
+                    .flatten()
+                    .and_then(|e| e.to_str().ok())
+                    .map(|s| s.trim_end_matches('\0').trim().to_string());
+                if let Some(mapped) = current.and_then(|c| mapping.get(&c)) {
+                    let vr = obj.element(tag).map(|e| e.vr()).unwrap_or(vr);
+                    obj.put(DataElement::new(
+                        tag,
+                        vr,
+                        PrimitiveValue::from(mapped.as_str()),
+                    ));
+                }
+            }
+            CoercionAction::Remove => {
+                obj.remove_element(tag);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_tag(&self) -> Result<Tag, String> {
+        let name = self.tag.trim();
+        if name.len() == 8 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+            let group = u16::from_str_radix(&name[0..4], 16).map_err(|e| e.to_string())?;
+            let element = u16::from_str_radix(&name[4..8], 16).map_err(|e| e.to_string())?;
+            return Ok(Tag(group, element));
+        }
+        StandardDataDictionary
+            .by_name(name)
+            .map(|entry| entry.tag.inner())
+            .ok_or_else(|| format!("Unknown tag '{}' in coercion rule", self.tag))
+    }
+
+    fn resolve_vr(&self, tag: Tag) -> Result<VR, String> {
+        if let Some(vr) = &self.vr {
+            return VR::from_str(vr.trim())
+                .map_err(|_| format!("Invalid VR '{}' in coercion rule for '{}'", vr, self.tag));
+        }
+        // Context-dependent VRs (e.g. US/SS) have no single answer; fall back to LO like
+        // tags missing from the dictionary
+        let entry = StandardDataDictionary.by_tag(tag);
+        Ok(match entry.map(|entry| entry.vr) {
+            Some(VirtualVr::Exact(vr)) => vr,
+            _ => VR::LO,
+        })
+    }
+}
+
+/// Apply `rules` to `obj` in order
+pub fn apply_rules(rules: &[CoercionRule], obj: &mut InMemDicomObject) -> Result<(), String> {
+    for rule in rules {
+        rule.apply(obj)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(tag: &str, action: CoercionAction) -> CoercionRule {
+        CoercionRule {
+            tag: tag.to_string(),
+            action,
+            vr: None,
+        }
+    }
+
+    fn text(obj: &InMemDicomObject, tag: Tag) -> Option<String> {
+        obj.element_opt(tag)
+            .ok()
+            .flatten()
+            .and_then(|e| e.to_str().ok())
+            .map(|s| s.trim_end_matches('\0').trim().to_string())
+    }
+
+    #[test]
+    fn test_rules_set_overwrite_map_and_remove() {
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::PATIENT_ID,
+            VR::LO,
+            PrimitiveValue::from("LOCAL-1"),
+        ));
+        obj.put(DataElement::new(
+            tags::REFERRING_PHYSICIAN_NAME,
+            VR::PN,
+            PrimitiveValue::from("Doe^Jane"),
+        ));
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4"),
+        ));
+
+        let rules = vec![
+            rule(
+                "PatientID",
+                CoercionAction::Set {
+                    value: "ignored".to_string(),
+                },
+            ),
+            rule(
+                "PatientID",
+                CoercionAction::Map {
+                    mapping: HashMap::from([("LOCAL-1".to_string(), "REMOTE-9".to_string())]),
+                },
+            ),
+            rule("ReferringPhysicianName", CoercionAction::Remove),
+            CoercionRule {
+                tag: "00091010".to_string(),
+                action: CoercionAction::Overwrite {
+                    value: "harmony".to_string(),
+                },
+                vr: Some("LO".to_string()),
+            },
+        ];
+        apply_rules(&rules, &mut obj).unwrap();
+
+        assert_eq!(text(&obj, tags::PATIENT_ID).as_deref(), Some("REMOTE-9"));
+        assert!(text(&obj, tags::REFERRING_PHYSICIAN_NAME).is_none());
+        assert_eq!(text(&obj, Tag(0x0009, 0x1010)).as_deref(), Some("harmony"));
+        assert_eq!(
+            text(&obj, tags::SOP_INSTANCE_UID).as_deref(),
+            Some("1.2.3.4")
+        );
+    }
+
+    #[test]
+    fn test_sop_instance_uid_only_remapped_explicitly() {
+        let overwrite = rule(
+            "SOPInstanceUID",
+            CoercionAction::Overwrite {
+                value: "9.9.9".to_string(),
+            },
+        );
+        assert!(overwrite.validate().is_err());
+        assert!(rule("SOPInstanceUID", CoercionAction::Remove)
+            .validate()
+            .is_err());
+
+        let remap = rule(
+            "SOPInstanceUID",
+            CoercionAction::Map {
+                mapping: HashMap::new(),
+            },
+        );
+        assert!(remap.validate().is_ok());
+        assert!(rule("NotATag", CoercionAction::Remove).validate().is_err());
+    }
+}
@@ -0,0 +1,465 @@
+//! DIMSE command sets
+//!
+//! Command sets are always encoded in Implicit VR Little Endian, whatever transfer syntax was
+//! negotiated for the data set, and only use a handful of group 0000 elements. They are
+//! encoded and decoded here directly rather than through a full data set parser. Data sets
+//! use the negotiated transfer syntax and go through dicom-object instead, see
+//! [`decode_data_set`] and [`encode_data_set`].
+
+use dicom_encoding::transfer_syntax::TransferSyntaxIndex;
+use dicom_object::InMemDicomObject;
+use dicom_transfer_syntax_registry::TransferSyntaxRegistry;
+
+/// Command Field of a C-STORE-RQ
+pub const C_STORE_RQ: u16 = 0x0001;
+/// Command Field of a C-GET-RQ
+pub const C_GET_RQ: u16 = 0x0010;
+/// Command Field of a C-FIND-RQ
+pub const C_FIND_RQ: u16 = 0x0020;
+/// Command Field of a C-ECHO-RQ
+pub const C_ECHO_RQ: u16 = 0x0030;
+/// Command Field of a C-CANCEL-RQ
+pub const C_CANCEL_RQ: u16 = 0x0FFF;
+/// Command Field of an N-EVENT-REPORT-RQ
+pub const N_EVENT_REPORT_RQ: u16 = 0x0100;
+/// Command Field of an N-SET-RQ
+pub const N_SET_RQ: u16 = 0x0120;
+/// Command Field of an N-ACTION-RQ
+pub const N_ACTION_RQ: u16 = 0x0130;
+/// Command Field of an N-CREATE-RQ
+pub const N_CREATE_RQ: u16 = 0x0140;
+/// Bit set in the Command Field of every response
+pub const RESPONSE_BIT: u16 = 0x8000;
+
+/// Command Data Set Type meaning no data set follows
+pub const NO_DATA_SET: u16 = 0x0101;
+
+/// Priority: medium
+pub const PRIORITY_MEDIUM: u16 = 0x0000;
+
+/// Status: success
+pub const STATUS_SUCCESS: u16 = 0x0000;
+/// Status: pending, a C-FIND match follows
+pub const STATUS_PENDING: u16 = 0xFF00;
+/// Status: failure, C-FIND identifier does not match SOP class
+pub const STATUS_IDENTIFIER_DOES_NOT_MATCH: u16 = 0xA900;
+/// Status: failure, C-FIND unable to process
+pub const STATUS_UNABLE_TO_PROCESS: u16 = 0xC000;
+/// Status: failure, invalid attribute value
+pub const STATUS_INVALID_ATTRIBUTE_VALUE: u16 = 0x0106;
+/// Status: failure, processing failure
+pub const STATUS_PROCESSING_FAILURE: u16 = 0x0110;
+/// Status: refused, SOP class not supported
+pub const STATUS_SOP_CLASS_NOT_SUPPORTED: u16 = 0x0122;
+/// Status: failure, no such action type
+pub const STATUS_NO_SUCH_ACTION_TYPE: u16 = 0x0123;
+/// Status: failure, unrecognized operation
+pub const STATUS_UNRECOGNIZED_OPERATION: u16 = 0x0211;
+
+const AFFECTED_SOP_CLASS_UID: u16 = 0x0002;
+const REQUESTED_SOP_CLASS_UID: u16 = 0x0003;
+const COMMAND_FIELD: u16 = 0x0100;
+const MESSAGE_ID: u16 = 0x0110;
+const MESSAGE_ID_BEING_RESPONDED_TO: u16 = 0x0120;
+const PRIORITY: u16 = 0x0700;
+const COMMAND_DATA_SET_TYPE: u16 = 0x0800;
+const STATUS: u16 = 0x0900;
+const AFFECTED_SOP_INSTANCE_UID: u16 = 0x1000;
+const REQUESTED_SOP_INSTANCE_UID: u16 = 0x1001;
+const EVENT_TYPE_ID: u16 = 0x1002;
+const ACTION_TYPE_ID: u16 = 0x1008;
+const REMAINING_SUB_OPERATIONS: u16 = 0x1020;
+const COMPLETED_SUB_OPERATIONS: u16 = 0x1021;
+const FAILED_SUB_OPERATIONS: u16 = 0x1022;
+const WARNING_SUB_OPERATIONS: u16 = 0x1023;
+
+/// The parts of a DIMSE command set the SCP acts on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Command {
+    pub command_field: u16,
+    /// Message ID of a request, or Message ID Being Responded To of a response
+    pub message_id: u16,
+    pub affected_sop_class_uid: Option<String>,
+    pub affected_sop_instance_uid: Option<String>,
+    /// SOP class and instance an N-ACTION (or other N-service request) is addressed to
+    pub requested_sop_class_uid: Option<String>,
+    pub requested_sop_instance_uid: Option<String>,
+    /// Priority of a C-STORE, C-FIND, C-GET or C-MOVE request
+    pub priority: Option<u16>,
+    /// Whether a data set follows the command
+    pub has_data_set: bool,
+    pub status: Option<u16>,
+    pub event_type_id: Option<u16>,
+    pub action_type_id: Option<u16>,
+    /// Sub-operation counts of a C-GET or C-MOVE response
+    pub sub_operations: Option<SubOperations>,
+}
+
+/// Number of Remaining/Completed/Failed/Warning Sub-operations in a C-GET or C-MOVE response
+///
+/// Elements a response leaves out count as zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubOperations {
+    pub remaining: u16,
+    pub completed: u16,
+    pub failed: u16,
+    pub warning: u16,
+}
+
+impl Command {
+    /// Parse an Implicit VR Little Endian command set, or `None` if it is truncated or lacks a
+    /// Command Field
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut command_field = None;
+        let mut message_id = 0;
+        let mut affected_sop_class_uid = None;
+        let mut affected_sop_instance_uid = None;
+        let mut requested_sop_class_uid = None;
+        let mut requested_sop_instance_uid = None;
+        let mut has_data_set = false;
+        let mut status = None;
+        let mut event_type_id = None;
+        let mut action_type_id = None;
+        let mut priority = None;
+        // Remaining, completed, failed and warning sub-operations, in element order
+        let mut counts = [None; 4];
+
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let header = rest.get(..8)?;
+            let group = u16::from_le_bytes([header[0], header[1]]);
+            let element = u16::from_le_bytes([header[2], header[3]]);
+            let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let value = rest.get(8..8 + len)?;
+            rest = &rest[8 + len..];
+            if group != 0x0000 {
+                return None;
+            }
+            let us = || value.get(..2).map(|v| u16::from_le_bytes([v[0], v[1]]));
+            let ui = || {
+                String::from_utf8_lossy(value)
+                    .trim_end_matches(['\0', ' '])
+                    .to_string()
+            };
+            match element {
+                AFFECTED_SOP_CLASS_UID => affected_sop_class_uid = Some(ui()),
+                REQUESTED_SOP_CLASS_UID => requested_sop_class_uid = Some(ui()),
+                COMMAND_FIELD => command_field = us(),
+                MESSAGE_ID | MESSAGE_ID_BEING_RESPONDED_TO => message_id = us()?,
+                COMMAND_DATA_SET_TYPE => has_data_set = us()? != NO_DATA_SET,
+                STATUS => status = us(),
+                AFFECTED_SOP_INSTANCE_UID => affected_sop_instance_uid = Some(ui()),
+                REQUESTED_SOP_INSTANCE_UID => requested_sop_instance_uid = Some(ui()),
+                EVENT_TYPE_ID => event_type_id = us(),
+                ACTION_TYPE_ID => action_type_id = us(),
+                PRIORITY => priority = us(),
+                REMAINING_SUB_OPERATIONS..=WARNING_SUB_OPERATIONS => {
+                    counts[(element - REMAINING_SUB_OPERATIONS) as usize] = us()
+                }
+                _ => {}
+            }
+        }
+
+        let sub_operations = counts.iter().any(Option::is_some).then(|| SubOperations {
+            remaining: counts[0].unwrap_or(0),
+            completed: counts[1].unwrap_or(0),
+            failed: counts[2].unwrap_or(0),
+            warning: counts[3].unwrap_or(0),
+        });
+        Some(Self {
+            command_field: command_field?,
+            message_id,
+            affected_sop_class_uid,
+            affected_sop_instance_uid,
+            requested_sop_class_uid,
+            requested_sop_instance_uid,
+            priority,
+            has_data_set,
+            status,
+            event_type_id,
+            action_type_id,
+            sub_operations,
+        })
+    }
+
+    /// The response to this request: same message and SOP instance, no data set
+    ///
+    /// The requested SOP class and instance of an N-service request become the affected ones
+    /// of its response.
+    pub fn response(&self, status: u16) -> Self {
+        Self {
+            command_field: self.command_field | RESPONSE_BIT,
+            message_id: self.message_id,
+            affected_sop_class_uid: self
+                .affected_sop_class_uid
+                .clone()
+                .or_else(|| self.requested_sop_class_uid.clone()),
+            affected_sop_instance_uid: self
+                .affected_sop_instance_uid
+                .clone()
+                .or_else(|| self.requested_sop_instance_uid.clone()),
+            requested_sop_class_uid: None,
+            requested_sop_instance_uid: None,
+            priority: None,
+            has_data_set: false,
+            status: Some(status),
+            event_type_id: self.event_type_id,
+            action_type_id: self.action_type_id,
+            sub_operations: None,
+        }
+    }
+
+    /// Encode as an Implicit VR Little Endian command set
+    ///
+    /// Responses and C-CANCEL-RQ carry `message_id` as the Message ID Being Responded To.
+    pub fn encode(&self) -> Vec<u8> {
+        let message_id =
+            if self.command_field & RESPONSE_BIT != 0 || self.command_field == C_CANCEL_RQ {
+                MESSAGE_ID_BEING_RESPONDED_TO
+            } else {
+                MESSAGE_ID
+            };
+        // A request's data set type only has to differ from NO_DATA_SET
+        let data_set_type = if self.has_data_set {
+            0x0000
+        } else {
+            NO_DATA_SET
+        };
+
+        let mut elements = Vec::new();
+        if let Some(uid) = &self.affected_sop_class_uid {
+            push_uid(&mut elements, AFFECTED_SOP_CLASS_UID, uid);
+        }
+        if let Some(uid) = &self.requested_sop_class_uid {
+            push_uid(&mut elements, REQUESTED_SOP_CLASS_UID, uid);
+        }
+        push_us(&mut elements, COMMAND_FIELD, self.command_field);
+        push_us(&mut elements, message_id, self.message_id);
+        if let Some(priority) = self.priority {
+            push_us(&mut elements, PRIORITY, priority);
+        }
+        push_us(&mut elements, COMMAND_DATA_SET_TYPE, data_set_type);
+        if let Some(status) = self.status {
+            push_us(&mut elements, STATUS, status);
+        }
+        if let Some(uid) = &self.affected_sop_instance_uid {
+            push_uid(&mut elements, AFFECTED_SOP_INSTANCE_UID, uid);
+        }
+        if let Some(uid) = &self.requested_sop_instance_uid {
+            push_uid(&mut elements, REQUESTED_SOP_INSTANCE_UID, uid);
+        }
+        if let Some(id) = self.event_type_id {
+            push_us(&mut elements, EVENT_TYPE_ID, id);
+        }
+        if let Some(id) = self.action_type_id {
+            push_us(&mut elements, ACTION_TYPE_ID, id);
+        }
+        if let Some(counts) = self.sub_operations {
+            push_us(&mut elements, REMAINING_SUB_OPERATIONS, counts.remaining);
+            push_us(&mut elements, COMPLETED_SUB_OPERATIONS, counts.completed);
+            push_us(&mut elements, FAILED_SUB_OPERATIONS, counts.failed);
+            push_us(&mut elements, WARNING_SUB_OPERATIONS, counts.warning);
+        }
+
+        // Command Group Length counts everything after itself
+        let mut command = Vec::with_capacity(12 + elements.len());
+        push_element(&mut command, 0x0000, &(elements.len() as u32).to_le_bytes());
+        command.extend_from_slice(&elements);
+        command
+    }
+}
+
+/// A complete DIMSE message: presentation context, command and data set (if any)
+pub type Message = (u8, Command, Option<Vec<u8>>);
+
+/// Reassembles DIMSE messages from presentation data value fragments
+#[derive(Debug, Default)]
+pub struct MessageAssembler {
+    command_bytes: Vec<u8>,
+    command: Option<Command>,
+    data_set: Vec<u8>,
+}
+
+impl MessageAssembler {
+    /// Whether no message is partly received
+    pub fn is_idle(&self) -> bool {
+        self.command_bytes.is_empty() && self.command.is_none()
+    }
+
+    /// Add one PDV and return the message it completes, if any
+    ///
+    /// Fails when a command set does not parse or fragments arrive out of order.
+    pub fn push(
+        &mut self,
+        context_id: u8,
+        is_command: bool,
+        is_last: bool,
+        data: &[u8],
+    ) -> Result<Option<Message>, String> {
+        if is_command {
+            if self.command.is_some() {
+                return Err("command fragment while a data set is pending".to_string());
+            }
+            self.command_bytes.extend_from_slice(data);
+            if !is_last {
+                return Ok(None);
+            }
+            let command = Command::parse(&self.command_bytes)
+                .ok_or_else(|| "malformed command set".to_string())?;
+            self.command_bytes.clear();
+            if command.has_data_set {
+                self.command = Some(command);
+                return Ok(None);
+            }
+            return Ok(Some((context_id, command, None)));
+        }
+
+        if self.command.is_none() {
+            return Err("data set fragment without a command".to_string());
+        }
+        self.data_set.extend_from_slice(data);
+        if !is_last {
+            return Ok(None);
+        }
+        let command = self.command.take().expect("checked above");
+        let data_set = std::mem::take(&mut self.data_set);
+        Ok(Some((context_id, command, Some(data_set))))
+    }
+}
+
+/// Parse a data set received in `transfer_syntax`
+pub fn decode_data_set(data: &[u8], transfer_syntax: &str) -> Result<InMemDicomObject, String> {
+    let ts = TransferSyntaxRegistry
+        .get(transfer_syntax)
+        .ok_or_else(|| format!("unsupported transfer syntax {}", transfer_syntax))?;
+    InMemDicomObject::read_dataset_with_ts(data, ts).map_err(|e| e.to_string())
+}
+
+/// Encode a data set to send in `transfer_syntax`
+pub fn encode_data_set(
+    object: &InMemDicomObject,
+    transfer_syntax: &str,
+) -> Result<Vec<u8>, String> {
+    let ts = TransferSyntaxRegistry
+        .get(transfer_syntax)
+        .ok_or_else(|| format!("unsupported transfer syntax {}", transfer_syntax))?;
+    let mut data = Vec::new();
+    object
+        .write_dataset_with_ts(&mut data, ts)
+        .map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
+fn push_element(buf: &mut Vec<u8>, element: u16, value: &[u8]) {
+    buf.extend_from_slice(&0x0000u16.to_le_bytes());
+    buf.extend_from_slice(&element.to_le_bytes());
+    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    buf.extend_from_slice(value);
+}
+
+fn push_us(buf: &mut Vec<u8>, element: u16, value: u16) {
+    push_element(buf, element, &value.to_le_bytes());
+}
+
+/// UIDs are padded to an even length with a trailing NUL
+fn push_uid(buf: &mut Vec<u8>, element: u16, uid: &str) {
+    let mut value = uid.as_bytes().to_vec();
+    if value.len() % 2 == 1 {
+        value.push(0);
+    }
+    push_element(buf, element, &value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_request_and_response_round_trip() {
+        let request = Command {
+            command_field: C_STORE_RQ,
+            message_id: 7,
+            affected_sop_class_uid: Some("1.2.840.10008.5.1.4.1.1.2".to_string()),
+            affected_sop_instance_uid: Some("1.2.3.4.5".to_string()),
+            has_data_set: true,
+            ..Default::default()
+        };
+        assert_eq!(Command::parse(&request.encode()), Some(request.clone()));
+
+        let encoded = request.response(STATUS_SUCCESS).encode();
+        let response = Command::parse(&encoded).unwrap();
+        assert_eq!(response.command_field, C_STORE_RQ | RESPONSE_BIT);
+        assert_eq!(response.message_id, 7);
+        assert_eq!(
+            response.affected_sop_instance_uid.as_deref(),
+            Some("1.2.3.4.5")
+        );
+        assert_eq!(response.status, Some(STATUS_SUCCESS));
+        assert!(!response.has_data_set);
+
+        // Truncated value and missing Command Field
+        assert!(Command::parse(&encoded[..encoded.len() - 1]).is_none());
+        assert!(Command::parse(&encoded[..12]).is_none());
+    }
+
+    #[test]
+    fn test_n_action_response_addresses_requested_instance() {
+        let request = Command {
+            command_field: N_ACTION_RQ,
+            message_id: 3,
+            requested_sop_class_uid: Some("1.2.840.10008.1.20.1".to_string()),
+            requested_sop_instance_uid: Some("1.2.840.10008.1.20.1.1".to_string()),
+            has_data_set: true,
+            action_type_id: Some(1),
+            ..Default::default()
+        };
+        let encoded = request.encode();
+        assert_eq!(Command::parse(&encoded), Some(request.clone()));
+
+        // Fragmented command followed by a one-fragment data set
+        let mut assembler = MessageAssembler::default();
+        let (head, tail) = encoded.split_at(10);
+        assert_eq!(assembler.push(1, true, false, head), Ok(None));
+        assert_eq!(assembler.push(1, true, true, tail), Ok(None));
+        let (context_id, command, data_set) =
+            assembler.push(1, false, true, b"DATA").unwrap().unwrap();
+        assert_eq!(context_id, 1);
+        assert_eq!(command, request);
+        assert_eq!(data_set.as_deref(), Some(&b"DATA"[..]));
+        assert!(assembler.push(1, false, true, b"DATA").is_err());
+
+        let response = Command::parse(&request.response(STATUS_SUCCESS).encode()).unwrap();
+        assert_eq!(
+            response.affected_sop_instance_uid.as_deref(),
+            Some("1.2.840.10008.1.20.1.1")
+        );
+        assert_eq!(response.requested_sop_instance_uid, None);
+        assert_eq!(response.action_type_id, Some(1));
+    }
+
+    #[test]
+    fn test_c_get_response_carries_sub_operation_counts() {
+        let response = Command {
+            command_field: C_GET_RQ | RESPONSE_BIT,
+            message_id: 1,
+            status: Some(STATUS_SUCCESS),
+            sub_operations: Some(SubOperations {
+                completed: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(Command::parse(&response.encode()), Some(response));
+
+        let request = Command {
+            command_field: C_GET_RQ,
+            message_id: 1,
+            priority: Some(PRIORITY_MEDIUM),
+            has_data_set: true,
+            ..Default::default()
+        };
+        let parsed = Command::parse(&request.encode()).unwrap();
+        assert_eq!(parsed.priority, Some(PRIORITY_MEDIUM));
+        assert_eq!(parsed.sub_operations, None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Storage Commitment Push Model
+//!
+//! An SCU asks for commitment with an N-ACTION naming a transaction and the instances it sent;
+//! the SCP answers later with an N-EVENT-REPORT listing the instances it committed and those
+//! it could not. The report may come back on the requesting association or on a new one
+//! opened by the SCP, so SCU requests still waiting for a report are kept in a process-wide
+//! registry which the SCP completes when a report arrives.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use dicom_core::value::DataSetSequence;
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::tags;
+use dicom_object::InMemDicomObject;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+/// Storage Commitment Push Model SOP Class UID
+pub const STORAGE_COMMITMENT_PUSH: &str = "1.2.840.10008.1.20.1";
+/// Well-known SOP Instance UID every commitment request is addressed to
+pub const STORAGE_COMMITMENT_PUSH_INSTANCE: &str = "1.2.840.10008.1.20.1.1";
+
+/// N-ACTION Action Type ID: request storage commitment
+pub const ACTION_REQUEST_COMMIT: u16 = 1;
+/// N-EVENT-REPORT Event Type ID: every instance was committed
+pub const EVENT_ALL_COMMITTED: u16 = 1;
+/// N-EVENT-REPORT Event Type ID: at least one instance failed
+pub const EVENT_FAILURES_EXIST: u16 = 2;
+
+/// Failure Reason: the SCP does not hold the instance
+pub const FAILURE_NO_SUCH_OBJECT_INSTANCE: u16 = 0x0112;
+/// Failure Reason: the instance is held under a different SOP class
+pub const FAILURE_CLASS_INSTANCE_CONFLICT: u16 = 0x0119;
+
+/// The N-ACTION data set: a transaction and the instances to commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentRequest {
+    pub transaction_uid: String,
+    /// (SOP Class UID, SOP Instance UID) pairs
+    pub references: Vec<(String, String)>,
+}
+
+/// An instance the SCP could not commit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedReference {
+    pub sop_class_uid: String,
+    pub sop_instance_uid: String,
+    /// One of the `FAILURE_*` reasons
+    pub failure_reason: u16,
+}
+
+/// The N-EVENT-REPORT data set answering a [`CommitmentRequest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentResult {
+    pub transaction_uid: String,
+    /// (SOP Class UID, SOP Instance UID) pairs the SCP committed
+    pub committed: Vec<(String, String)>,
+    pub failed: Vec<FailedReference>,
+}
+
+impl CommitmentRequest {
+    /// Create a request for `references` under a new transaction UID
+    pub fn new(references: Vec<(String, String)>) -> Self {
+        Self {
+            transaction_uid: format!("2.25.{}", uuid::Uuid::new_v4().as_u128()),
+            references,
+        }
+    }
+
+    /// Build the N-ACTION data set
+    pub fn to_object(&self) -> InMemDicomObject {
+        let mut object = InMemDicomObject::new_empty();
+        object.put(uid(tags::TRANSACTION_UID, &self.transaction_uid));
+        object.put(references(tags::REFERENCED_SOP_SEQUENCE, &self.references));
+        object
+    }
+
+    /// Read an N-ACTION data set
+    pub fn from_object(object: &InMemDicomObject) -> Result<Self, String> {
+        Ok(Self {
+            transaction_uid: transaction_uid(object)?,
+            references: read_references(object, tags::REFERENCED_SOP_SEQUENCE)?
+                .into_iter()
+                .map(|(class, instance, _)| (class, instance))
+                .collect(),
+        })
+    }
+}
+
+impl CommitmentResult {
+    /// Event Type ID of the N-EVENT-REPORT carrying this result
+    pub fn event_type_id(&self) -> u16 {
+        if self.failed.is_empty() {
+            EVENT_ALL_COMMITTED
+        } else {
+            EVENT_FAILURES_EXIST
+        }
+    }
+
+    /// Build the N-EVENT-REPORT data set
+    pub fn to_object(&self) -> InMemDicomObject {
+        let mut object = InMemDicomObject::new_empty();
+        object.put(uid(tags::TRANSACTION_UID, &self.transaction_uid));
+        if !self.committed.is_empty() {
+            object.put(references(tags::REFERENCED_SOP_SEQUENCE, &self.committed));
+        }
+        if !self.failed.is_empty() {
+            let items = self
+                .failed
+                .iter()
+                .map(|f| {
+                    let mut item = reference(&f.sop_class_uid, &f.sop_instance_uid);
+                    item.put(DataElement::new(
+                        tags::FAILURE_REASON,
+                        VR::US,
+                        PrimitiveValue::from(f.failure_reason),
+                    ));
+                    item
+                })
+                .collect::<Vec<_>>();
+            object.put(DataElement::new(
+                tags::FAILED_SOP_SEQUENCE,
+                VR::SQ,
+                DataSetSequence::from(items),
+            ));
+        }
+        object
+    }
+
+    /// Read an N-EVENT-REPORT data set
+    pub fn from_object(object: &InMemDicomObject) -> Result<Self, String> {
+        Ok(Self {
+            transaction_uid: transaction_uid(object)?,
+            committed: read_references(object, tags::REFERENCED_SOP_SEQUENCE)?
+                .into_iter()
+                .map(|(class, instance, _)| (class, instance))
+                .collect(),
+            failed: read_references(object, tags::FAILED_SOP_SEQUENCE)?
+                .into_iter()
+                .map(
+                    |(sop_class_uid, sop_instance_uid, reason)| FailedReference {
+                        sop_class_uid,
+                        sop_instance_uid,
+                        failure_reason: reason.unwrap_or(FAILURE_NO_SUCH_OBJECT_INSTANCE),
+                    },
+                )
+                .collect(),
+        })
+    }
+}
+
+fn uid(tag: Tag, value: &str) -> DataElement<InMemDicomObject> {
+    DataElement::new(tag, VR::UI, PrimitiveValue::from(value))
+}
+
+fn reference(sop_class_uid: &str, sop_instance_uid: &str) -> InMemDicomObject {
+    let mut item = InMemDicomObject::new_empty();
+    item.put(uid(tags::REFERENCED_SOP_CLASS_UID, sop_class_uid));
+    item.put(uid(tags::REFERENCED_SOP_INSTANCE_UID, sop_instance_uid));
+    item
+}
+
+fn references(tag: Tag, refs: &[(String, String)]) -> DataElement<InMemDicomObject> {
+    let items = refs
+        .iter()
+        .map(|(class, instance)| reference(class, instance))
+        .collect::<Vec<_>>();
+    DataElement::new(tag, VR::SQ, DataSetSequence::from(items))
+}
+
+fn text(object: &InMemDicomObject, tag: Tag) -> Option<String> {
+    object
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .and_then(|e| e.to_str().ok())
+        .map(|s| s.trim_end_matches(['\0', ' ']).to_string())
+}
+
+fn transaction_uid(object: &InMemDicomObject) -> Result<String, String> {
+    text(object, tags::TRANSACTION_UID).ok_or_else(|| "missing Transaction UID".to_string())
+}
+
+/// Items of a reference sequence as (class, instance, failure reason); an absent sequence is
+/// empty
+fn read_references(
+    object: &InMemDicomObject,
+    tag: Tag,
+) -> Result<Vec<(String, String, Option<u16>)>, String> {
+    let Some(items) = object
+        .element_opt(tag)
+        .ok()
+        .flatten()
+        .and_then(|e| e.items())
+    else {
+        return Ok(vec![]);
+    };
+    items
+        .iter()
+        .map(|item| {
+            let class = text(item, tags::REFERENCED_SOP_CLASS_UID);
+            let instance = text(item, tags::REFERENCED_SOP_INSTANCE_UID);
+            let reason = item
+                .element_opt(tags::FAILURE_REASON)
+                .ok()
+                .flatten()
+                .and_then(|e| e.to_int::<u16>().ok());
+            match (class, instance) {
+                (Some(class), Some(instance)) => Ok((class, instance, reason)),
+                _ => Err(format!("incomplete SOP reference in {}", tag)),
+            }
+        })
+        .collect()
+}
+
+type Waiters = Mutex<HashMap<String, oneshot::Sender<CommitmentResult>>>;
+
+fn waiters() -> &'static Waiters {
+    static WAITERS: OnceLock<Waiters> = OnceLock::new();
+    WAITERS.get_or_init(Default::default)
+}
+
+/// Wait for the result of `transaction_uid`, wherever its N-EVENT-REPORT arrives
+pub(crate) fn await_result(transaction_uid: &str) -> oneshot::Receiver<CommitmentResult> {
+    let (tx, rx) = oneshot::channel();
+    waiters()
+        .lock()
+        .expect("commitment waiters mutex")
+        .insert(transaction_uid.to_string(), tx);
+    rx
+}
+
+/// Stop waiting for `transaction_uid`
+pub(crate) fn forget(transaction_uid: &str) {
+    waiters()
+        .lock()
+        .expect("commitment waiters mutex")
+        .remove(transaction_uid);
+}
+
+/// Hand a received result to the request waiting for it; `false` if nothing was waiting
+pub(crate) fn deliver(result: CommitmentResult) -> bool {
+    let waiter = waiters()
+        .lock()
+        .expect("commitment waiters mutex")
+        .remove(&result.transaction_uid);
+    match waiter {
+        Some(tx) => tx.send(result).is_ok(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{decode_data_set, encode_data_set};
+
+    const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+    const EXPLICIT_VR_LE: &str = "1.2.840.10008.1.2.1";
+
+    #[test]
+    fn test_request_and_result_round_trip_through_data_sets() {
+        let request = CommitmentRequest::new(vec![
+            (CT_IMAGE.to_string(), "1.2.3.1".to_string()),
+            (CT_IMAGE.to_string(), "1.2.3.2".to_string()),
+        ]);
+        assert!(request.transaction_uid.starts_with("2.25."));
+        let data = encode_data_set(&request.to_object(), EXPLICIT_VR_LE).unwrap();
+        let decoded =
+            CommitmentRequest::from_object(&decode_data_set(&data, EXPLICIT_VR_LE).unwrap())
+                .unwrap();
+        assert_eq!(decoded, request);
+
+        let result = CommitmentResult {
+            transaction_uid: request.transaction_uid.clone(),
+            committed: vec![(CT_IMAGE.to_string(), "1.2.3.1".to_string())],
+            failed: vec![FailedReference {
+                sop_class_uid: CT_IMAGE.to_string(),
+                sop_instance_uid: "1.2.3.2".to_string(),
+                failure_reason: FAILURE_NO_SUCH_OBJECT_INSTANCE,
+            }],
+        };
+        assert_eq!(result.event_type_id(), EVENT_FAILURES_EXIST);
+        let data = encode_data_set(&result.to_object(), EXPLICIT_VR_LE).unwrap();
+        let decoded =
+            CommitmentResult::from_object(&decode_data_set(&data, EXPLICIT_VR_LE).unwrap())
+                .unwrap();
+        assert_eq!(decoded, result);
+
+        assert!(CommitmentRequest::from_object(&InMemDicomObject::new_empty()).is_err());
+    }
+}
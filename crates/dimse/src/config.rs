@@ -1,12 +1,58 @@
 //! Configuration types for DIMSE services
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::association::RejectionCodes;
+use crate::audit::AuditConfig;
+use crate::breaker::CircuitBreakerConfig;
+use crate::coercion::CoercionRule;
+use crate::types::DimseStatus;
 use crate::DEFAULT_DIMSE_PORT;
 
+/// Presentation context IDs are the odd numbers 1-255, so at most 128 fit in one association
+pub const MAX_PRESENTATION_CONTEXTS: usize = 128;
+
+/// Smallest configurable maximum PDU length; some legacy modalities accept nothing larger
+pub const MIN_MAX_PDU: u32 = 4096;
+/// Largest configurable maximum PDU length
+pub const MAX_MAX_PDU: u32 = 131072;
+/// Maximum PDU length used unless configured, which is also DCMTK's default
+pub const DEFAULT_MAX_PDU: u32 = 16384;
+
+/// Implicit VR Little Endian, the default transfer syntax every DICOM application supports
+pub const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+
+/// Storage SOP classes given the SCP role by default, so a C-GET's sub-operations can be
+/// received on the same association
+const GET_STORAGE_SOP_CLASSES: &[&str] = &[
+    "1.2.840.10008.5.1.4.1.1.1",     // CR Image
+    "1.2.840.10008.5.1.4.1.1.1.1",   // Digital X-Ray Image - For Presentation
+    "1.2.840.10008.5.1.4.1.1.1.2",   // Digital Mammography X-Ray Image - For Presentation
+    "1.2.840.10008.5.1.4.1.1.2",     // CT Image
+    "1.2.840.10008.5.1.4.1.1.2.1",   // Enhanced CT Image
+    "1.2.840.10008.5.1.4.1.1.3.1",   // Ultrasound Multi-frame Image
+    "1.2.840.10008.5.1.4.1.1.4",     // MR Image
+    "1.2.840.10008.5.1.4.1.1.4.1",   // Enhanced MR Image
+    "1.2.840.10008.5.1.4.1.1.6.1",   // Ultrasound Image
+    "1.2.840.10008.5.1.4.1.1.7",     // Secondary Capture Image
+    "1.2.840.10008.5.1.4.1.1.11.1",  // Grayscale Softcopy Presentation State
+    "1.2.840.10008.5.1.4.1.1.12.1",  // X-Ray Angiographic Image
+    "1.2.840.10008.5.1.4.1.1.12.2",  // X-Ray Radiofluoroscopic Image
+    "1.2.840.10008.5.1.4.1.1.20",    // Nuclear Medicine Image
+    "1.2.840.10008.5.1.4.1.1.88.11", // Basic Text SR
+    "1.2.840.10008.5.1.4.1.1.88.22", // Enhanced SR
+    "1.2.840.10008.5.1.4.1.1.104.1", // Encapsulated PDF
+    "1.2.840.10008.5.1.4.1.1.128",   // Positron Emission Tomography Image
+    "1.2.840.10008.5.1.4.1.1.481.1", // RT Image
+    "1.2.840.10008.5.1.4.1.1.481.2", // RT Dose
+    "1.2.840.10008.5.1.4.1.1.481.3", // RT Structure Set
+    "1.2.840.10008.5.1.4.1.1.481.5", // RT Plan
+];
+
 /// Configuration for DIMSE services
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DimseConfig {
@@ -26,11 +72,33 @@ pub struct DimseConfig {
     #[serde(default = "default_incoming_store_port")]
     pub incoming_store_port: u16,
 
-    /// Maximum PDU size in bytes
-    #[serde(default = "default_max_pdu")]
+    /// Inclusive range of ports to listen on for incoming C-STORE during C-MOVE
+    ///
+    /// Each C-MOVE takes a free port from the range for its transient Store SCP, so concurrent
+    /// moves do not collide; `incoming_store_port` is used when unset. The QR SCP must know
+    /// a destination AE at every port in the range, see `incoming_store_aets`.
+    #[serde(default)]
+    pub incoming_store_port_range: Option<(u16, u16)>,
+
+    /// AE title the QR SCP knows at each port of `incoming_store_port_range`
+    ///
+    /// A C-MOVE to this node names the AE title paired with the port it leased as its move
+    /// destination, so the QR SCP sends the instances to the port that move listens on. Ports
+    /// without an entry use `<local_aet>_<port>`, with the local AE title shortened so the
+    /// result fits in 16 characters.
+    #[serde(default)]
+    pub incoming_store_aets: HashMap<u16, String>,
+
+    /// Maximum PDU length in bytes, advertised in the A-ASSOCIATE User Information item
+    ///
+    /// The SCP refuses larger incoming PDUs, and PDUs sent on an association are clamped to
+    /// the smaller of this and the peer's advertised maximum.
+    #[serde(default = "default_max_pdu", alias = "max_pdu_length")]
     pub max_pdu: u32,
 
     /// Connection timeout in milliseconds
+    ///
+    /// Bounds both the TCP connect and the A-ASSOCIATE negotiation of SCU associations.
     #[serde(default = "default_connect_timeout")]
     pub connect_timeout_ms: u64,
 
@@ -38,6 +106,15 @@ pub struct DimseConfig {
     #[serde(default = "default_association_timeout")]
     pub association_timeout_ms: u64,
 
+    /// Longest the SCU waits for each DIMSE response, in milliseconds (unset waits indefinitely)
+    #[serde(default)]
+    pub dimse_timeout_ms: Option<u64>,
+
+    /// ARTIM timer: how long the SCP waits for an A-ASSOCIATE-RQ on a new connection, in
+    /// milliseconds (unset falls back to `association_timeout_ms`)
+    #[serde(default)]
+    pub artim_timeout_ms: Option<u64>,
+
     /// Storage directory for temporary DICOM files
     #[serde(default = "default_storage_dir")]
     pub storage_dir: PathBuf,
@@ -45,7 +122,12 @@ pub struct DimseConfig {
     /// TLS configuration (optional)
     pub tls: Option<TlsConfig>,
 
-    /// Preferred transfer syntaxes (in order of preference)
+    /// TLS settings for SCU connections to nodes with TLS enabled and no settings of their own
+    #[serde(default)]
+    pub client_tls: Option<ClientTlsConfig>,
+
+    /// Transfer syntax UIDs in priority order: proposed by the SCU for each presentation
+    /// context and accepted by the SCP. Implicit VR Little Endian is always the last resort.
     #[serde(default = "default_transfer_syntaxes")]
     pub preferred_transfer_syntaxes: Vec<String>,
 
@@ -71,6 +153,198 @@ pub struct DimseConfig {
     /// HostTable or DicomModalities).
     #[serde(default)]
     pub external_store_scp: bool,
+
+    /// Calling AE titles allowed to associate with the SCP (empty allows any)
+    #[serde(default)]
+    pub allowed_calling_aets: Vec<String>,
+
+    /// A-ASSOCIATE-RJ result/source/reason codes sent for each rejection reason
+    #[serde(default)]
+    pub association_rejections: RejectionCodes,
+
+    /// Longest an association may go without activity before it is aborted, in milliseconds
+    ///
+    /// Unset keeps the previous behaviour: the SCP falls back to `association_timeout_ms`
+    /// and SCU operations run until the remote side finishes.
+    #[serde(default)]
+    pub max_association_lifetime_ms: Option<u64>,
+
+    /// C-STORE handling per SOP Class UID; classes not listed use `default_store_policy`
+    #[serde(default)]
+    pub store_policies: HashMap<String, StorePolicy>,
+
+    /// C-STORE handling for SOP classes without an entry in `store_policies`
+    #[serde(default)]
+    pub default_store_policy: StorePolicy,
+
+    /// Warn when a transfer's throughput stays below a minimum (unset disables tracking)
+    #[serde(default)]
+    pub slow_transfer: Option<SlowTransferConfig>,
+
+    /// What the SCU does when a result stream is dropped before the operation completes
+    #[serde(default)]
+    pub drop_behavior: DropBehavior,
+
+    /// How long `cancel_then_release` waits for the association to wind down, in milliseconds
+    #[serde(default = "default_drop_grace")]
+    pub drop_grace_ms: u64,
+
+    /// How long the SCP lets open associations finish their current operation once shutdown
+    /// is requested, in milliseconds, before closing them
+    #[serde(default = "default_shutdown_grace")]
+    pub shutdown_grace_ms: u64,
+
+    /// Tag coercions applied, in order, to every outgoing C-STORE dataset
+    #[serde(default)]
+    pub store_coercion: Vec<CoercionRule>,
+
+    /// Keep SCU associations idle for this long, in milliseconds, so later C-FINDs to the same
+    /// peer reuse them (unset opens an association per query)
+    #[serde(default)]
+    pub association_pool_ttl_ms: Option<u64>,
+
+    /// Send a C-ECHO on pooled associations idle for this long, in milliseconds, evicting any
+    /// the peer no longer answers (unset sends none)
+    #[serde(default)]
+    pub association_keepalive_ms: Option<u64>,
+
+    /// Retries for remote nodes without a policy of their own (default: no retries)
+    #[serde(default)]
+    pub retry: RetryPolicy,
+
+    /// Fail SCU operations fast against nodes that keep failing (unset disables the breaker)
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+
+    /// Write DICOM audit trail records for associations and operations (unset disables auditing)
+    #[serde(default)]
+    pub audit: Option<AuditConfig>,
+
+    /// SOP classes negotiated through SCP/SCU Role Selection and the roles this node takes for
+    /// them (default: the SCP role for common storage SOP classes, as C-GET needs)
+    #[serde(default = "default_role_selection")]
+    pub role_selection: Vec<RoleSelection>,
+}
+
+/// Roles this node takes for a SOP class, negotiated with SCP/SCU Role Selection
+///
+/// As association requestor the SCU proposes these roles and, for C-GET, a storage
+/// presentation context for each class with the SCP role. As acceptor the SCP agrees to the
+/// requestor's SCU role only for classes it may serve as SCP, and to the requestor's SCP role
+/// only for classes it may act as SCU for; classes without an entry keep the default roles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleSelection {
+    /// SOP Class UID the roles apply to
+    pub sop_class_uid: String,
+
+    /// This node may invoke operations of the class
+    #[serde(default)]
+    pub scu: bool,
+
+    /// This node may perform operations of the class, e.g. receive C-STORE sub-operations
+    #[serde(default)]
+    pub scp: bool,
+}
+
+impl RoleSelection {
+    /// Roles for `sop_class_uid`
+    pub fn new(sop_class_uid: impl Into<String>, scu: bool, scp: bool) -> Self {
+        Self {
+            sop_class_uid: sop_class_uid.into(),
+            scu,
+            scp,
+        }
+    }
+}
+
+/// SCU handling of a result stream dropped mid-operation
+///
+/// DCMTK tools cannot be sent a C-CANCEL once running, so the behaviours differ in how long
+/// the tool is left to finish on its own before its connection is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropBehavior {
+    /// Kill the tool at once; the peer sees the association aborted
+    Abort,
+    /// Let the tool finish and release normally, discarding its results
+    Release,
+    /// Stop delivering results and give the tool `drop_grace_ms` to release, then abort
+    #[default]
+    CancelThenRelease,
+}
+
+/// Slow-transfer detection thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlowTransferConfig {
+    /// Throughput below this many bytes per second counts as slow
+    pub min_bytes_per_sec: u64,
+
+    /// Length of the sliding window the rate is averaged over, in milliseconds
+    #[serde(default = "default_throughput_window")]
+    pub window_ms: u64,
+
+    /// How long throughput must stay slow before the warning is logged, in milliseconds
+    #[serde(default = "default_slow_transfer_sustained")]
+    pub sustained_ms: u64,
+}
+
+/// What the SCP does with an incoming C-STORE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum StorePolicy {
+    /// Accept the instance and write it to storage
+    #[default]
+    Store,
+    /// Accept the instance and hand it to the pipeline without keeping a local copy
+    Forward,
+    /// Refuse the instance with the given C-STORE-RSP status
+    Refuse {
+        #[serde(default = "default_refusal_status")]
+        status: u16,
+    },
+}
+
+/// Retries for SCU operations that could not establish an association
+///
+/// Only the association is retried: a failure once the peer has accepted it, such as a
+/// refusal status or a C-MOVE cut off half way, is returned as is, so no operation runs twice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first; 1 disables retries
+    #[serde(default = "default_retry_attempts")]
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, in milliseconds; it doubles for each retry after that
+    #[serde(default = "default_retry_backoff")]
+    pub base_backoff_ms: u64,
+
+    /// Longest delay between two attempts, in milliseconds
+    #[serde(default = "default_retry_max_backoff")]
+    pub max_backoff_ms: u64,
+
+    /// Fraction of each delay that is random, from 0.0 (fixed delays) to 1.0
+    #[serde(default = "default_retry_jitter")]
+    pub jitter: f64,
+
+    /// Failures that are retried
+    #[serde(default = "default_retry_on")]
+    pub retry_on: Vec<RetryOn>,
+
+    /// No retry is started once this many milliseconds have passed since the first attempt
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,
+}
+
+/// Kind of association failure a [`RetryPolicy`] can retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryOn {
+    /// The connection was refused, reset or closed before the association was accepted
+    Network,
+    /// Connecting or negotiating the association timed out
+    Timeout,
+    /// The peer answered with an A-ASSOCIATE-RJ, e.g. while it is out of resources
+    AssociationRejected,
 }
 
 /// Configuration for a remote DICOM node
@@ -94,6 +368,19 @@ pub struct RemoteNode {
 
     /// Maximum PDU size for this node (overrides global setting)
     pub max_pdu: Option<u32>,
+
+    /// Most presentation contexts to propose in one A-ASSOCIATE-RQ, for peers that reject
+    /// larger proposals (default: the protocol limit of 128)
+    #[serde(default)]
+    pub max_presentation_contexts: Option<usize>,
+
+    /// Retries for this node (overrides the global policy)
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+
+    /// TLS settings for this node (overrides the global client TLS settings)
+    #[serde(default)]
+    pub tls: Option<ClientTlsConfig>,
 }
 
 /// TLS configuration
@@ -113,6 +400,25 @@ pub struct TlsConfig {
     pub require_client_cert: bool,
 }
 
+/// TLS settings for outgoing SCU connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientTlsConfig {
+    /// CA bundle the server certificate is verified against (PEM format)
+    pub ca_bundle_path: PathBuf,
+
+    /// Client certificate chain presented for mutual TLS (PEM format, optional)
+    #[serde(default)]
+    pub cert_path: Option<PathBuf>,
+
+    /// Private key of the client certificate (PEM format, optional)
+    #[serde(default)]
+    pub key_path: Option<PathBuf>,
+
+    /// Name the server certificate must match (default: the node's host)
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
 impl Default for DimseConfig {
     fn default() -> Self {
         Self {
@@ -120,18 +426,100 @@ impl Default for DimseConfig {
             bind_addr: default_bind_addr(),
             port: default_port(),
             incoming_store_port: default_incoming_store_port(),
+            incoming_store_port_range: None,
+            incoming_store_aets: HashMap::new(),
             max_pdu: default_max_pdu(),
             connect_timeout_ms: default_connect_timeout(),
             association_timeout_ms: default_association_timeout(),
+            dimse_timeout_ms: None,
+            artim_timeout_ms: None,
             storage_dir: default_storage_dir(),
             tls: None,
+            client_tls: None,
             preferred_transfer_syntaxes: default_transfer_syntaxes(),
             max_associations: default_max_associations(),
             enable_echo: true,
             enable_find: true,
             enable_move: true,
             external_store_scp: false,
+            allowed_calling_aets: Vec::new(),
+            association_rejections: RejectionCodes::default(),
+            max_association_lifetime_ms: None,
+            store_policies: HashMap::new(),
+            default_store_policy: StorePolicy::Store,
+            slow_transfer: None,
+            drop_behavior: DropBehavior::default(),
+            drop_grace_ms: default_drop_grace(),
+            shutdown_grace_ms: default_shutdown_grace(),
+            store_coercion: Vec::new(),
+            association_pool_ttl_ms: None,
+            association_keepalive_ms: None,
+            retry: RetryPolicy::default(),
+            circuit_breaker: None,
+            audit: None,
+            role_selection: default_role_selection(),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_attempts(),
+            base_backoff_ms: default_retry_backoff(),
+            max_backoff_ms: default_retry_max_backoff(),
+            jitter: default_retry_jitter(),
+            retry_on: default_retry_on(),
+            deadline_ms: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a failed association attempt that ended in `error` may be retried
+    pub fn retries(&self, error: &crate::error::DimseError) -> bool {
+        use crate::error::DimseError;
+        let kind = match error {
+            DimseError::Network(_) => RetryOn::Network,
+            DimseError::Timeout(_) => RetryOn::Timeout,
+            DimseError::AssociationRejected(_) => RetryOn::AssociationRejected,
+            _ => return false,
+        };
+        self.retry_on.contains(&kind)
+    }
+
+    /// Delay before retry number `retry` (1 for the first), with jitter applied
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let delay = self
+            .base_backoff_ms
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff_ms) as f64;
+        // Random fraction in [0, 1) without pulling in an RNG for a few retries
+        let bits = uuid::Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1);
+        let random = bits as f64 / (1u64 << 53) as f64;
+        let jittered = delay * (1.0 - self.jitter * random);
+        Duration::from_millis(jittered as u64)
+    }
+
+    /// Time after the first attempt past which no retry is started, if limited
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline_ms.map(Duration::from_millis)
+    }
+
+    /// Validate the policy
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.max_attempts == 0 {
+            return Err(crate::error::DimseError::config(
+                "Retry max_attempts must be at least 1",
+            ));
         }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(crate::error::DimseError::config(
+                "Retry jitter must be between 0.0 and 1.0",
+            ));
+        }
+        Ok(())
     }
 }
 
@@ -146,6 +534,75 @@ impl DimseConfig {
         Duration::from_millis(self.association_timeout_ms)
     }
 
+    /// Get the DIMSE response timeout as Duration, if one is configured
+    pub fn dimse_timeout(&self) -> Option<Duration> {
+        self.dimse_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Get the ARTIM timeout as Duration
+    pub fn artim_timeout(&self) -> Duration {
+        self.artim_timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or_else(|| self.association_timeout())
+    }
+
+    /// Get the maximum association lifetime as Duration, if one is configured
+    pub fn max_association_lifetime(&self) -> Option<Duration> {
+        self.max_association_lifetime_ms.map(Duration::from_millis)
+    }
+
+    /// Get the association pool TTL as Duration, if pooling is enabled
+    pub fn association_pool_ttl(&self) -> Option<Duration> {
+        self.association_pool_ttl_ms.map(Duration::from_millis)
+    }
+
+    /// Get the pooled association keep-alive interval as Duration, if keep-alive is enabled
+    pub fn association_keepalive(&self) -> Option<Duration> {
+        self.association_keepalive_ms
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis)
+    }
+
+    /// Policy applied to a C-STORE of the given SOP class
+    pub fn store_policy(&self, sop_class_uid: Option<&str>) -> StorePolicy {
+        sop_class_uid
+            .and_then(|uid| self.store_policies.get(uid.trim_end_matches('\0')))
+            .copied()
+            .unwrap_or(self.default_store_policy)
+    }
+
+    /// Get the stream-drop grace period as Duration
+    pub fn drop_grace(&self) -> Duration {
+        Duration::from_millis(self.drop_grace_ms)
+    }
+
+    /// Get the SCP shutdown grace period as Duration
+    pub fn shutdown_grace(&self) -> Duration {
+        Duration::from_millis(self.shutdown_grace_ms)
+    }
+
+    /// Transfer syntaxes to propose, or accept, for a presentation context
+    ///
+    /// `preferred_transfer_syntaxes` in order, followed by Implicit VR Little Endian when the
+    /// list does not already include it, so a peer lacking every preferred syntax still
+    /// negotiates.
+    pub fn transfer_syntax_proposal(&self) -> Vec<String> {
+        let mut syntaxes = self.preferred_transfer_syntaxes.clone();
+        if !syntaxes.iter().any(|ts| ts == IMPLICIT_VR_LITTLE_ENDIAN) {
+            syntaxes.push(IMPLICIT_VR_LITTLE_ENDIAN.to_string());
+        }
+        syntaxes
+    }
+
+    /// SOP classes this node takes the SCP role for, in configured order
+    pub fn scp_role_sop_classes(&self) -> Vec<String> {
+        self.role_selection
+            .iter()
+            .filter(|role| role.scp)
+            .map(|role| role.sop_class_uid.clone())
+            .collect()
+    }
+
     /// Check if TLS is enabled
     pub fn tls_enabled(&self) -> bool {
         self.tls.is_some()
@@ -167,13 +624,121 @@ impl DimseConfig {
             ));
         }
 
+        if let Some((first, last)) = self.incoming_store_port_range {
+            if first == 0 || first > last {
+                return Err(crate::error::DimseError::config(
+                    "Incoming store port range must be two ports greater than 0, lowest first",
+                ));
+            }
+        }
+        for (port, ae_title) in &self.incoming_store_aets {
+            if !self
+                .incoming_store_port_range
+                .is_some_and(|(first, last)| (first..=last).contains(port))
+            {
+                return Err(crate::error::DimseError::config(format!(
+                    "Incoming store AE title for port {} is outside the incoming store port range",
+                    port
+                )));
+            }
+            if ae_title.is_empty() || ae_title.len() > 16 {
+                return Err(crate::error::DimseError::config(format!(
+                    "Incoming store AE title for port {} must be 1-16 characters",
+                    port
+                )));
+            }
+        }
+
         // Validate PDU size
-        if self.max_pdu < 16384 || self.max_pdu > 131072 {
+        if !(MIN_MAX_PDU..=MAX_MAX_PDU).contains(&self.max_pdu) {
+            return Err(crate::error::DimseError::config(format!(
+                "Max PDU size must be between {} and {} bytes",
+                MIN_MAX_PDU, MAX_MAX_PDU
+            )));
+        }
+
+        if self.connect_timeout_ms == 0
+            || self.association_timeout_ms == 0
+            || self.dimse_timeout_ms == Some(0)
+            || self.artim_timeout_ms == Some(0)
+        {
             return Err(crate::error::DimseError::config(
-                "Max PDU size must be between 16384 and 131072 bytes",
+                "Connect, association, DIMSE and ARTIM timeouts must be greater than 0",
             ));
         }
 
+        if self
+            .preferred_transfer_syntaxes
+            .iter()
+            .any(|ts| ts.is_empty() || ts.len() > 64)
+        {
+            return Err(crate::error::DimseError::config(
+                "Preferred transfer syntaxes must be UIDs of 1-64 characters",
+            ));
+        }
+
+        if self
+            .role_selection
+            .iter()
+            .any(|role| role.sop_class_uid.is_empty() || role.sop_class_uid.len() > 64)
+        {
+            return Err(crate::error::DimseError::config(
+                "Role selection SOP classes must be UIDs of 1-64 characters",
+            ));
+        }
+
+        if self.max_association_lifetime_ms == Some(0) {
+            return Err(crate::error::DimseError::config(
+                "Max association lifetime must be greater than 0",
+            ));
+        }
+
+        self.association_rejections
+            .validate()
+            .map_err(crate::error::DimseError::config)?;
+
+        if let Some(slow) = &self.slow_transfer {
+            if slow.min_bytes_per_sec == 0 || slow.window_ms == 0 {
+                return Err(crate::error::DimseError::config(
+                    "Slow transfer min_bytes_per_sec and window_ms must be greater than 0",
+                ));
+            }
+        }
+
+        let policies = self
+            .store_policies
+            .iter()
+            .map(|(uid, policy)| (uid.as_str(), policy))
+            .chain(std::iter::once(("default", &self.default_store_policy)));
+        for (uid, policy) in policies {
+            if let StorePolicy::Refuse { status } = policy {
+                if !matches!(DimseStatus::from_code(*status), DimseStatus::Failure(_)) {
+                    return Err(crate::error::DimseError::config(format!(
+                        "Store policy for {} refuses with 0x{:04X}, which is not a failure status",
+                        uid, status
+                    )));
+                }
+            }
+        }
+
+        for rule in &self.store_coercion {
+            rule.validate().map_err(crate::error::DimseError::config)?;
+        }
+
+        self.retry.validate()?;
+
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.validate()?;
+        }
+
+        if let Some(tls) = &self.tls {
+            if tls.require_client_cert && tls.ca_bundle_path.is_none() {
+                return Err(crate::error::DimseError::config(
+                    "require_client_cert needs a ca_bundle_path to verify client certificates",
+                ));
+            }
+        }
+
         // Validate storage directory
         if !self.storage_dir.exists() {
             std::fs::create_dir_all(&self.storage_dir).map_err(|e| {
@@ -198,6 +763,9 @@ impl RemoteNode {
             use_tls: false,
             connect_timeout_ms: None,
             max_pdu: None,
+            max_presentation_contexts: None,
+            retry: None,
+            tls: None,
         }
     }
 
@@ -207,12 +775,31 @@ impl RemoteNode {
         self
     }
 
+    /// Enable TLS for this node with its own CA bundle and, for mutual TLS, client certificate
+    pub fn with_client_tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.use_tls = true;
+        self.tls = Some(tls);
+        self
+    }
+
     /// Set connection timeout for this node
     pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
         self.connect_timeout_ms = Some(timeout_ms);
         self
     }
 
+    /// Cap the number of presentation contexts proposed to this node
+    pub fn with_max_presentation_contexts(mut self, max: usize) -> Self {
+        self.max_presentation_contexts = Some(max);
+        self
+    }
+
+    /// Retry failed associations with this node according to `policy`
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
     /// Validate the remote node configuration
     pub fn validate(&self) -> crate::error::Result<()> {
         if self.ae_title.is_empty() || self.ae_title.len() > 16 {
@@ -233,6 +820,28 @@ impl RemoteNode {
             ));
         }
 
+        if let Some(max) = self.max_presentation_contexts {
+            if !(1..=MAX_PRESENTATION_CONTEXTS).contains(&max) {
+                return Err(crate::error::DimseError::config(format!(
+                    "max_presentation_contexts must be between 1 and {}",
+                    MAX_PRESENTATION_CONTEXTS
+                )));
+            }
+        }
+
+        if let Some(max) = self.max_pdu {
+            if !(MIN_MAX_PDU..=MAX_MAX_PDU).contains(&max) {
+                return Err(crate::error::DimseError::config(format!(
+                    "max_pdu must be between {} and {}",
+                    MIN_MAX_PDU, MAX_MAX_PDU
+                )));
+            }
+        }
+
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+
         Ok(())
     }
 }
@@ -251,7 +860,7 @@ fn default_incoming_store_port() -> u16 {
 }
 
 fn default_max_pdu() -> u32 {
-    65536
+    DEFAULT_MAX_PDU
 }
 
 fn default_connect_timeout() -> u64 {
@@ -274,10 +883,58 @@ fn default_transfer_syntaxes() -> Vec<String> {
     ]
 }
 
+fn default_role_selection() -> Vec<RoleSelection> {
+    GET_STORAGE_SOP_CLASSES
+        .iter()
+        .map(|uid| RoleSelection::new(*uid, false, true))
+        .collect()
+}
+
 fn default_max_associations() -> u32 {
     10
 }
 
+fn default_throughput_window() -> u64 {
+    10_000
+}
+
+fn default_slow_transfer_sustained() -> u64 {
+    30_000
+}
+
+fn default_drop_grace() -> u64 {
+    5_000
+}
+
+fn default_shutdown_grace() -> u64 {
+    10_000
+}
+
+fn default_retry_attempts() -> u32 {
+    1
+}
+
+fn default_retry_backoff() -> u64 {
+    500
+}
+
+fn default_retry_max_backoff() -> u64 {
+    10_000
+}
+
+fn default_retry_jitter() -> f64 {
+    0.2
+}
+
+fn default_retry_on() -> Vec<RetryOn> {
+    vec![RetryOn::Network, RetryOn::Timeout]
+}
+
+/// Refused: Out of Resources
+fn default_refusal_status() -> u16 {
+    0xA700
+}
+
 fn default_true() -> bool {
     true
 }
@@ -308,6 +965,18 @@ mod tests {
         assert_eq!(node.port, 11112);
         assert!(node.use_tls);
         assert_eq!(node.connect_timeout_ms, Some(10_000));
+
+        assert!(node
+            .clone()
+            .with_max_presentation_contexts(16)
+            .validate()
+            .is_ok());
+        assert!(node
+            .clone()
+            .with_max_presentation_contexts(0)
+            .validate()
+            .is_err());
+        assert!(node.with_max_presentation_contexts(129).validate().is_err());
     }
 
     #[test]
@@ -321,5 +990,159 @@ mod tests {
 
         config.local_aet = "A".repeat(17);
         assert!(config.validate().is_err());
+
+        config.local_aet = "HARMONY_SCP".to_string();
+        config.max_pdu = 4096;
+        assert!(config.validate().is_ok());
+        config.max_pdu = 2048;
+        assert!(config.validate().is_err());
+        config.max_pdu = 16384;
+
+        config.incoming_store_port_range = Some((11_130, 11_120));
+        assert!(config.validate().is_err());
+        config.incoming_store_port_range = Some((11_120, 11_120));
+        assert!(config.validate().is_ok());
+        config.incoming_store_aets = HashMap::from([(11_121, "HARMONY_B".to_string())]);
+        assert!(config.validate().is_err());
+        config.incoming_store_aets = HashMap::from([(11_120, "HARMONY_MOVE_11120".to_string())]);
+        assert!(config.validate().is_err());
+        config.incoming_store_aets = HashMap::from([(11_120, "HARMONY_A".to_string())]);
+        assert!(config.validate().is_ok());
+
+        config.max_association_lifetime_ms = Some(0);
+        assert!(config.validate().is_err());
+        config.max_association_lifetime_ms = Some(5_000);
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.max_association_lifetime(),
+            Some(Duration::from_millis(5_000))
+        );
+
+        config
+            .store_policies
+            .insert("1.2.3".to_string(), StorePolicy::Refuse { status: 0x0000 });
+        assert!(config.validate().is_err());
+        config
+            .store_policies
+            .insert("1.2.3".to_string(), StorePolicy::Refuse { status: 0xA700 });
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.store_policy(Some("1.2.3")),
+            StorePolicy::Refuse { status: 0xA700 }
+        );
+        assert_eq!(config.store_policy(None), StorePolicy::Store);
+    }
+
+    #[test]
+    fn test_timeouts_default_when_absent() {
+        let config: DimseConfig = toml::from_str(r#"local_aet = "HARMONY_SCU""#).unwrap();
+        assert_eq!(config.connect_timeout(), Duration::from_secs(30));
+        assert_eq!(config.association_timeout(), Duration::from_secs(300));
+        assert_eq!(config.dimse_timeout(), None);
+        assert_eq!(config.artim_timeout(), config.association_timeout());
+
+        let config: DimseConfig = toml::from_str(
+            r#"
+            local_aet = "HARMONY_SCU"
+            dimse_timeout_ms = 60000
+            artim_timeout_ms = 10000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.dimse_timeout(), Some(Duration::from_secs(60)));
+        assert_eq!(config.artim_timeout(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_role_selection_defaults_and_parses() {
+        let config: DimseConfig = toml::from_str(r#"local_aet = "HARMONY_SCU""#).unwrap();
+        let scp_roles = config.scp_role_sop_classes();
+        assert!(scp_roles.contains(&"1.2.840.10008.5.1.4.1.1.2".to_string()));
+        assert_eq!(scp_roles.len(), config.role_selection.len());
+        assert!(config.role_selection.iter().all(|role| !role.scu));
+
+        let mut config: DimseConfig = toml::from_str(
+            r#"
+            local_aet = "HARMONY_SCU"
+
+            [[role_selection]]
+            sop_class_uid = "1.2.840.10008.5.1.4.1.1.4"
+            scp = true
+
+            [[role_selection]]
+            sop_class_uid = "1.2.840.10008.1.20.1"
+            scu = true
+            scp = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.role_selection,
+            vec![
+                RoleSelection::new("1.2.840.10008.5.1.4.1.1.4", false, true),
+                RoleSelection::new("1.2.840.10008.1.20.1", true, true),
+            ]
+        );
+        assert!(config.validate().is_ok());
+        config
+            .role_selection
+            .push(RoleSelection::new("", false, true));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_transfer_syntax_proposal_falls_back_to_implicit_vr() {
+        use crate::association::select_transfer_syntax;
+
+        let mut config = DimseConfig::default();
+        config.preferred_transfer_syntaxes = vec![
+            "1.2.840.10008.1.2.4.50".to_string(), // JPEG Baseline
+            "1.2.840.10008.1.2.1".to_string(),    // Explicit VR Little Endian
+        ];
+        let proposal = config.transfer_syntax_proposal();
+        assert_eq!(proposal.len(), 3);
+        assert_eq!(proposal[2], IMPLICIT_VR_LITTLE_ENDIAN);
+
+        // A peer that only supports Implicit VR accepts the fallback
+        let peer = vec![IMPLICIT_VR_LITTLE_ENDIAN.to_string()];
+        assert_eq!(
+            select_transfer_syntax(&proposal, &peer),
+            Some(IMPLICIT_VR_LITTLE_ENDIAN)
+        );
+
+        config.preferred_transfer_syntaxes.clear();
+        assert_eq!(config.transfer_syntax_proposal(), peer);
+    }
+
+    #[test]
+    fn test_retry_policy_backoff_and_retryable_errors() {
+        use crate::error::DimseError;
+
+        let policy: RetryPolicy = toml::from_str(
+            r#"
+            max_attempts = 4
+            base_backoff_ms = 100
+            max_backoff_ms = 250
+            jitter = 0.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(policy.retry_on, vec![RetryOn::Network, RetryOn::Timeout]);
+        for retry in 1..=3 {
+            let full = Duration::from_millis([100, 200, 250][retry as usize - 1]);
+            let delay = policy.backoff(retry);
+            assert!(delay <= full && delay >= full / 2, "{:?}", delay);
+        }
+
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert!(policy.retries(&DimseError::Network(refused)));
+        assert!(!policy.retries(&DimseError::AssociationRejected("busy".into())));
+        assert!(!policy.retries(&DimseError::operation_failed("no matching SOP class")));
+
+        let invalid = RetryPolicy {
+            jitter: 1.5,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
     }
 }
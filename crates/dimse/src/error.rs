@@ -59,6 +59,9 @@ pub enum DimseError {
 
     #[error("Operation not supported: {0}")]
     NotSupported(String),
+
+    #[error("Circuit breaker open: {0}")]
+    CircuitOpen(String),
 }
 
 impl DimseError {
@@ -0,0 +1,490 @@
+//! Query provider answering from DICOM files on disk
+//!
+//! [`FileIndexQueryProvider`] scans a storage root once and keeps an in-memory index of the
+//! key attributes of every instance it finds. C-FIND is matched against the index and answered
+//! with one identifier per matching patient, study, series or instance; C-MOVE and C-GET
+//! locate the matching files. Instances received by C-STORE are written under the root and
+//! added to the index from the `on_store` callback, so a standalone archive needs no pipeline.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use dicom_core::dictionary::{DataDictionary, VirtualVr};
+use dicom_core::{Tag, VR};
+use dicom_dictionary_std::{tags, StandardDataDictionary};
+use dicom_object::{InMemDicomObject, OpenFileOptions};
+use tracing::{debug, info, warn};
+
+use crate::scp::QueryProvider;
+use crate::types::{DatasetStream, QueryLevel};
+use crate::worklist::{element, resolve_tag};
+use crate::{DimseError, Result};
+
+/// Attributes kept in the index and available for matching and return keys
+const INDEXED_TAGS: &[Tag] = &[
+    tags::PATIENT_ID,
+    tags::PATIENT_NAME,
+    tags::PATIENT_BIRTH_DATE,
+    tags::PATIENT_SEX,
+    tags::STUDY_INSTANCE_UID,
+    tags::STUDY_DATE,
+    tags::STUDY_TIME,
+    tags::STUDY_ID,
+    tags::STUDY_DESCRIPTION,
+    tags::ACCESSION_NUMBER,
+    tags::REFERRING_PHYSICIAN_NAME,
+    tags::SERIES_INSTANCE_UID,
+    tags::SERIES_NUMBER,
+    tags::SERIES_DESCRIPTION,
+    tags::MODALITY,
+    tags::SOP_INSTANCE_UID,
+    tags::SOP_CLASS_UID,
+    tags::INSTANCE_NUMBER,
+];
+
+/// An indexed instance: where it is stored and its key attributes
+#[derive(Debug, Clone)]
+struct IndexedInstance {
+    path: PathBuf,
+    attributes: HashMap<Tag, String>,
+}
+
+impl IndexedInstance {
+    fn get(&self, tag: Tag) -> &str {
+        self.attributes.get(&tag).map(String::as_str).unwrap_or("")
+    }
+}
+
+/// Query provider serving C-FIND, C-MOVE and C-GET from an index of the files under a root
+pub struct FileIndexQueryProvider {
+    root: PathBuf,
+    /// Indexed instances by SOP Instance UID
+    index: RwLock<HashMap<String, IndexedInstance>>,
+}
+
+impl FileIndexQueryProvider {
+    /// Create a provider over `root` with an empty index; see [`Self::rebuild`]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            index: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Create a provider over `root` and index the files already stored there
+    pub async fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let provider = Self::new(root);
+        provider.rebuild().await?;
+        Ok(provider)
+    }
+
+    /// Rescan the root and replace the index, returning the number of indexed instances
+    ///
+    /// Files that are not DICOM or lack a SOP Instance UID are skipped.
+    pub async fn rebuild(&self) -> Result<usize> {
+        let root = self.root.clone();
+        let scanned = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+            let mut index = HashMap::new();
+            for path in files_under(&root)? {
+                if let Some(instance) = read_instance(&path) {
+                    index.insert(instance.get(tags::SOP_INSTANCE_UID).to_string(), instance);
+                }
+            }
+            Ok(index)
+        })
+        .await
+        .map_err(|e| DimseError::operation_failed(format!("Index scan panicked: {}", e)))??;
+
+        let count = scanned.len();
+        *self.index.write().expect("file index poisoned") = scanned;
+        info!(
+            "Indexed {} instance(s) under {}",
+            count,
+            self.root.display()
+        );
+        Ok(count)
+    }
+
+    /// Add or replace the file at `path` in the index; `false` when it cannot be indexed
+    pub fn index_file(&self, path: &Path) -> bool {
+        let Some(instance) = read_instance(path) else {
+            return false;
+        };
+        let uid = instance.get(tags::SOP_INSTANCE_UID).to_string();
+        debug!("Indexed {} from {}", uid, path.display());
+        self.index
+            .write()
+            .expect("file index poisoned")
+            .insert(uid, instance);
+        true
+    }
+
+    /// Number of indexed instances
+    pub fn len(&self) -> usize {
+        self.index.read().expect("file index poisoned").len()
+    }
+
+    /// Whether the index is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Where a received instance is written
+    fn instance_path(&self, dataset: &DatasetStream) -> PathBuf {
+        let metadata = dataset.metadata();
+        let name = metadata
+            .sop_instance_uid
+            .clone()
+            .filter(|uid| !uid.is_empty() && uid.chars().all(|c| c.is_ascii_digit() || c == '.'))
+            .unwrap_or_else(|| metadata.id.to_string());
+        self.root.join(format!("{}.dcm", name))
+    }
+
+    /// Indexed instances matching every key in `parameters`, ordered by SOP Instance UID
+    fn matching(&self, parameters: &HashMap<String, String>) -> Result<Vec<IndexedInstance>> {
+        let keys = parse_keys(parameters)?;
+        let index = self.index.read().expect("file index poisoned");
+        let mut matches: Vec<IndexedInstance> = index
+            .values()
+            .filter(|instance| {
+                keys.iter().all(|(tag, key)| {
+                    // Keys that are not indexed cannot be matched and are only returned
+                    !INDEXED_TAGS.contains(tag) || matches_key(*tag, instance.get(*tag), key)
+                })
+            })
+            .cloned()
+            .collect();
+        matches.sort_by(|a, b| {
+            a.get(tags::SOP_INSTANCE_UID)
+                .cmp(b.get(tags::SOP_INSTANCE_UID))
+        });
+        Ok(matches)
+    }
+}
+
+#[async_trait]
+impl QueryProvider for FileIndexQueryProvider {
+    async fn find(
+        &self,
+        query_level: QueryLevel,
+        parameters: &HashMap<String, String>,
+        max_results: u32,
+    ) -> Result<Vec<DatasetStream>> {
+        let keys = parse_keys(parameters)?;
+        let unique_key = level_key(query_level);
+
+        // One response per distinct entity at the query level, in index order
+        let mut entities: BTreeMap<String, IndexedInstance> = BTreeMap::new();
+        for instance in self.matching(parameters)? {
+            entities
+                .entry(instance.get(unique_key).to_string())
+                .or_insert(instance);
+        }
+
+        let mut results = Vec::new();
+        for instance in entities.into_values() {
+            if max_results > 0 && results.len() >= max_results as usize {
+                break;
+            }
+            let mut identifier = InMemDicomObject::new_empty();
+            identifier.put(element(
+                tags::QUERY_RETRIEVE_LEVEL,
+                &query_level.to_string(),
+            ));
+            identifier.put(element(unique_key, instance.get(unique_key)));
+            for (tag, _) in &keys {
+                if *tag != tags::QUERY_RETRIEVE_LEVEL {
+                    identifier.put(element(*tag, instance.get(*tag)));
+                }
+            }
+            results.push(DatasetStream::from_object(identifier));
+        }
+        debug!(
+            "File index C-FIND at {} level matched {} entities",
+            query_level,
+            results.len()
+        );
+        Ok(results)
+    }
+
+    async fn locate(
+        &self,
+        _query_level: QueryLevel,
+        parameters: &HashMap<String, String>,
+    ) -> Result<Vec<DatasetStream>> {
+        // Every instance under a matching patient, study or series matches the same keys
+        Ok(self
+            .matching(parameters)?
+            .into_iter()
+            .map(|instance| DatasetStream::from_received_file(instance.path, false))
+            .collect())
+    }
+
+    async fn store(&self, dataset: DatasetStream) -> Result<()> {
+        let path = self.instance_path(&dataset);
+        let bytes = dataset.to_bytes().await?;
+        tokio::fs::create_dir_all(&self.root).await?;
+        tokio::fs::write(&path, &bytes).await?;
+        debug!("Stored instance to {}", path.display());
+        Ok(())
+    }
+
+    async fn on_store(&self, instance: DatasetStream) -> Result<()> {
+        let path = match &instance {
+            DatasetStream::File { path, .. } if path.starts_with(&self.root) => path.clone(),
+            _ => self.instance_path(&instance),
+        };
+        if !self.index_file(&path) {
+            warn!("Stored instance {} could not be indexed", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Query keys by tag, skipping the Query/Retrieve Level
+fn parse_keys(parameters: &HashMap<String, String>) -> Result<Vec<(Tag, String)>> {
+    let mut keys = Vec::with_capacity(parameters.len());
+    for (name, value) in parameters {
+        let tag = resolve_tag(name).map_err(DimseError::config)?;
+        if tag != tags::QUERY_RETRIEVE_LEVEL {
+            keys.push((tag, value.clone()));
+        }
+    }
+    keys.sort();
+    Ok(keys)
+}
+
+/// Unique key of the entities at `level`
+fn level_key(level: QueryLevel) -> Tag {
+    match level {
+        QueryLevel::Patient => tags::PATIENT_ID,
+        QueryLevel::Study => tags::STUDY_INSTANCE_UID,
+        QueryLevel::Series => tags::SERIES_INSTANCE_UID,
+        QueryLevel::Image => tags::SOP_INSTANCE_UID,
+    }
+}
+
+/// Whether `value` matches the C-FIND key `key` (PS3.4 C.2.2.2)
+///
+/// Empty keys match everything, backslash-separated keys match any of their values, `*` and
+/// `?` are wildcards and `from-to` is a range for dates and times.
+fn matches_key(tag: Tag, value: &str, key: &str) -> bool {
+    if key.is_empty() || key == "*" {
+        return true;
+    }
+    if key.contains('\\') {
+        return key.split('\\').any(|key| matches_key(tag, value, key));
+    }
+    if is_date_or_time(tag) {
+        if let Some((from, to)) = key.split_once('-') {
+            return !value.is_empty()
+                && (from.is_empty() || value >= from)
+                && (to.is_empty() || value <= to);
+        }
+    }
+    if key.contains(['*', '?']) {
+        return wildcard_match(key.as_bytes(), value.as_bytes());
+    }
+    value == key
+}
+
+fn is_date_or_time(tag: Tag) -> bool {
+    matches!(
+        StandardDataDictionary.by_tag(tag).map(|entry| entry.vr),
+        Some(VirtualVr::Exact(VR::DA | VR::TM | VR::DT))
+    )
+}
+
+fn wildcard_match(pattern: &[u8], value: &[u8]) -> bool {
+    match (pattern.split_first(), value.split_first()) {
+        (None, _) => value.is_empty(),
+        (Some((b'*', rest)), _) => {
+            wildcard_match(rest, value)
+                || (!value.is_empty() && wildcard_match(pattern, &value[1..]))
+        }
+        (Some((b'?', rest)), Some((_, value_rest))) => wildcard_match(rest, value_rest),
+        (Some((p, rest)), Some((v, value_rest))) => p == v && wildcard_match(rest, value_rest),
+        (Some(_), None) => false,
+    }
+}
+
+/// Every regular file below `dir`
+fn files_under(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Read the key attributes of a DICOM file, stopping before the pixel data
+fn read_instance(path: &Path) -> Option<IndexedInstance> {
+    let object = OpenFileOptions::new()
+        .read_until(tags::PIXEL_DATA)
+        .open_file(path)
+        .ok()?;
+    let attributes: HashMap<Tag, String> = INDEXED_TAGS
+        .iter()
+        .filter_map(|tag| {
+            let value = object.element(*tag).ok()?.to_str().ok()?;
+            let value = value.trim_end_matches(['\0', ' ']).to_string();
+            Some((*tag, value))
+        })
+        .collect();
+    attributes
+        .get(&tags::SOP_INSTANCE_UID)
+        .filter(|uid| !uid.is_empty())?;
+    Some(IndexedInstance {
+        path: path.to_path_buf(),
+        attributes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue};
+    use dicom_object::meta::FileMetaTableBuilder;
+
+    const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+    /// Write a CT instance with the given study, series and instance UIDs under `dir`
+    fn write_instance(dir: &Path, study: &str, series: &str, sop: &str, date: &str) {
+        let mut object = InMemDicomObject::new_empty();
+        let mut put = |tag, vr, value: &str| {
+            object.put(DataElement::new(tag, vr, PrimitiveValue::from(value)));
+        };
+        put(tags::SOP_CLASS_UID, VR::UI, CT_IMAGE);
+        put(tags::SOP_INSTANCE_UID, VR::UI, sop);
+        put(tags::PATIENT_ID, VR::LO, "P1");
+        put(tags::PATIENT_NAME, VR::PN, "DOE^JANE");
+        put(tags::STUDY_INSTANCE_UID, VR::UI, study);
+        put(tags::STUDY_DATE, VR::DA, date);
+        put(tags::STUDY_DESCRIPTION, VR::LO, "CHEST CT");
+        put(tags::SERIES_INSTANCE_UID, VR::UI, series);
+        put(tags::MODALITY, VR::CS, "CT");
+        let file = object
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid(CT_IMAGE)
+                    .media_storage_sop_instance_uid(sop)
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap();
+        file.write_to_file(dir.join(format!("{}.dcm", sop)))
+            .unwrap();
+    }
+
+    async fn archive() -> (tempfile::TempDir, FileIndexQueryProvider) {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("dimse/batch");
+        std::fs::create_dir_all(&nested).unwrap();
+        write_instance(dir.path(), "1.2.3", "1.2.3.1", "1.2.3.1.1", "20230105");
+        write_instance(&nested, "1.2.3", "1.2.3.1", "1.2.3.1.2", "20230105");
+        write_instance(&nested, "1.2.4", "1.2.4.1", "1.2.4.1.1", "20240301");
+        std::fs::write(dir.path().join("notes.txt"), b"not DICOM").unwrap();
+        let provider = FileIndexQueryProvider::open(dir.path()).await.unwrap();
+        (dir, provider)
+    }
+
+    fn keys(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn text(dataset: &DatasetStream, tag: Tag) -> String {
+        let DatasetStream::Object { object, .. } = dataset else {
+            panic!("expected an identifier object");
+        };
+        object.element(tag).unwrap().to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_find_known_study_returns_indexed_attributes() {
+        let (_dir, provider) = archive().await;
+        assert_eq!(provider.len(), 3);
+
+        let query = keys(&[
+            ("0020000D", "1.2.3"),
+            ("00081030", ""),
+            ("00100010", ""),
+            ("00080020", ""),
+        ]);
+        let found = provider.find(QueryLevel::Study, &query, 0).await.unwrap();
+
+        assert_eq!(found.len(), 1, "two instances, one study");
+        assert_eq!(text(&found[0], tags::STUDY_INSTANCE_UID), "1.2.3");
+        assert_eq!(text(&found[0], tags::STUDY_DESCRIPTION), "CHEST CT");
+        assert_eq!(text(&found[0], tags::PATIENT_NAME), "DOE^JANE");
+        assert_eq!(text(&found[0], tags::STUDY_DATE), "20230105");
+        assert_eq!(text(&found[0], tags::QUERY_RETRIEVE_LEVEL), "STUDY");
+    }
+
+    #[tokio::test]
+    async fn test_find_matches_ranges_wildcards_and_lists() {
+        let (_dir, provider) = archive().await;
+
+        let in_2023 = keys(&[("StudyDate", "20230101-20231231")]);
+        let found = provider.find(QueryLevel::Study, &in_2023, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(text(&found[0], tags::STUDY_INSTANCE_UID), "1.2.3");
+
+        let by_name = keys(&[("PatientName", "DOE^*")]);
+        let found = provider.find(QueryLevel::Image, &by_name, 0).await.unwrap();
+        assert_eq!(found.len(), 3);
+        let found = provider.find(QueryLevel::Image, &by_name, 2).await.unwrap();
+        assert_eq!(found.len(), 2, "max_results caps the responses");
+
+        let listed = keys(&[("0020000D", "1.2.4\\1.2.9")]);
+        let found = provider.find(QueryLevel::Series, &listed, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(text(&found[0], tags::SERIES_INSTANCE_UID), "1.2.4.1");
+    }
+
+    #[tokio::test]
+    async fn test_locate_returns_files_and_store_updates_index() {
+        let (dir, provider) = archive().await;
+        let study = keys(&[("0020000D", "1.2.3")]);
+        let located = provider.locate(QueryLevel::Study, &study).await.unwrap();
+        assert_eq!(located.len(), 2);
+        assert!(located
+            .iter()
+            .all(|ds| matches!(ds, DatasetStream::File { path, .. } if path.exists())));
+
+        // A received instance is written under the root and indexed by on_store
+        let incoming = tempfile::tempdir().unwrap();
+        write_instance(incoming.path(), "1.2.5", "1.2.5.1", "1.2.5.1.1", "20240401");
+        let bytes = std::fs::read(incoming.path().join("1.2.5.1.1.dcm")).unwrap();
+        let mut instance = DatasetStream::from_bytes(bytes.into());
+        instance.metadata_mut().sop_instance_uid = Some("1.2.5.1.1".to_string());
+        provider.store(instance.clone()).await.unwrap();
+        provider.on_store(instance).await.unwrap();
+
+        assert!(dir.path().join("1.2.5.1.1.dcm").exists());
+        let new_study = keys(&[("0020000D", "1.2.5")]);
+        let found = provider
+            .find(QueryLevel::Study, &new_study, 0)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(provider.rebuild().await.unwrap(), 4);
+    }
+}
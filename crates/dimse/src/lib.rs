@@ -4,29 +4,56 @@
 //! implementations for DICOM networking using the DIMSE protocol.
 //!
 //! # Features
-//! - Inbound DIMSE services (SCP): C-ECHO, C-FIND, C-MOVE
-//! - Outbound DIMSE services (SCU): C-ECHO, C-FIND, C-MOVE  
+//! - Inbound DIMSE services (SCP): C-ECHO, C-FIND, C-MOVE, C-STORE, Storage Commitment,
+//!   MPPS (N-CREATE/N-SET)
+//! - Outbound DIMSE services (SCU): C-ECHO, C-FIND, C-GET, C-MOVE, Storage Commitment
+//! - Per-node circuit breaker that fails SCU operations fast while a peer is down
+//! - DICOM audit trail (ATNA) records for associations, queries and transfers
 //! - TLS support (optional, feature = "tls")
 //! - Binary stream handling with minimal file I/O
 //! - Integration with harmony proxy via internal router
 
+pub mod association;
+pub mod audit;
+pub mod breaker;
+pub mod coercion;
+pub mod command;
+pub mod commitment;
 pub mod config;
 pub mod error;
+pub mod file_index;
+pub mod metrics;
+pub mod mpps;
+pub mod pool;
 pub mod router;
 pub mod scp;
 pub mod scu;
+pub mod store_port;
+pub mod throughput;
+mod transport;
 pub mod types;
+pub mod worklist;
 
 #[cfg(feature = "tls")]
 pub mod tls;
 
 // Re-export commonly used types
-pub use config::{DimseConfig, RemoteNode};
+pub use audit::{AuditConfig, AuditDestination};
+pub use breaker::{BreakerState, CircuitBreakerConfig};
+pub use coercion::{CoercionAction, CoercionRule};
+pub use commitment::{CommitmentRequest, CommitmentResult};
+pub use config::{
+    ClientTlsConfig, DimseConfig, DropBehavior, RemoteNode, RetryOn, RetryPolicy, RoleSelection,
+    SlowTransferConfig, StorePolicy, TlsConfig,
+};
 pub use error::{DimseError, Result};
-pub use router::{DimseRequest, DimseResponse, InMemoryRouter, Router};
+pub use file_index::FileIndexQueryProvider;
+pub use mpps::{MppsEvent, MppsStatus};
+pub use router::{AetRouter, DimseRequest, DimseResponse, InMemoryRouter, Router};
 pub use scp::DimseScp;
 pub use scu::DimseScu;
-pub use types::{DatasetStream, DimseCommand};
+pub use types::{DatasetStream, DimseCommand, GetReport, MoveStream, QueryFeatures};
+pub use worklist::WorklistQuery;
 
 /// DIMSE protocol version
 pub const DIMSE_VERSION: &str = "0.1.0";
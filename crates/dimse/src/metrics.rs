@@ -0,0 +1,312 @@
+//! Operational metrics for the SCU and SCP
+//!
+//! With feature "metrics", each function records through the `metrics` facade into whatever
+//! recorder the application installed; Harmony installs a Prometheus one. Without the feature
+//! they compile to nothing, so callers need no `cfg` of their own.
+//!
+//! | Metric | Kind | Labels |
+//! |---|---|---|
+//! | `dimse_associations_opened_total` | counter | `role` |
+//! | `dimse_find_matches_total` | counter | |
+//! | `dimse_retrieved_instances_total` | counter | `operation` |
+//! | `dimse_store_bytes_total` | counter | `role` |
+//! | `dimse_operation_duration_seconds` | histogram | `operation`, `role` |
+//! | `dimse_failures_total` | counter | `operation`, `status` |
+//! | `dimse_transfer_throughput_bytes_per_second` | histogram | `role` |
+//!
+//! `role` is `scu` or `scp`. `status` is the DIMSE status as `0xA700`, or `none` for failures
+//! that never got a status, such as a refused association.
+//!
+//! Each metric records the labels above unless [`set_label_sets`] names the ones to keep, so an
+//! operator can drop a dimension to hold down the number of series.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
+
+use crate::command::{
+    C_ECHO_RQ, C_FIND_RQ, C_GET_RQ, C_STORE_RQ, N_ACTION_RQ, N_CREATE_RQ, N_EVENT_REPORT_RQ,
+    N_SET_RQ,
+};
+
+/// Associations established, as SCU or accepted as SCP
+pub const ASSOCIATIONS_OPENED: &str = "dimse_associations_opened_total";
+/// C-FIND matches returned to the caller
+pub const FIND_MATCHES: &str = "dimse_find_matches_total";
+/// Instances retrieved by C-MOVE or C-GET
+pub const RETRIEVED_INSTANCES: &str = "dimse_retrieved_instances_total";
+/// Data set bytes carried by C-STORE
+pub const STORE_BYTES: &str = "dimse_store_bytes_total";
+/// Time from request to final response
+pub const OPERATION_DURATION: &str = "dimse_operation_duration_seconds";
+/// Operations that ended in a failure
+pub const FAILURES: &str = "dimse_failures_total";
+/// Windowed rate of a transfer watched by `slow_transfer`, sampled as it is judged
+pub const TRANSFER_THROUGHPUT: &str = "dimse_transfer_throughput_bytes_per_second";
+
+/// Labels each `dimse_*` metric can carry
+pub const METRIC_LABELS: &[(&str, &[&str])] = &[
+    (ASSOCIATIONS_OPENED, &["role"]),
+    (FIND_MATCHES, &[]),
+    (RETRIEVED_INSTANCES, &["operation"]),
+    (STORE_BYTES, &["role"]),
+    (OPERATION_DURATION, &["operation", "role"]),
+    (FAILURES, &["operation", "status"]),
+    (TRANSFER_THROUGHPUT, &["role"]),
+];
+
+/// Labels to record by metric name, once configured
+static LABEL_SETS: RwLock<Option<HashMap<String, Vec<String>>>> = RwLock::new(None);
+
+/// SCU side of an association
+pub const SCU: &str = "scu";
+/// SCP side of an association
+pub const SCP: &str = "scp";
+
+/// Name of the operation a request Command Field stands for
+pub fn operation_name(command_field: u16) -> &'static str {
+    match command_field {
+        C_STORE_RQ => "C-STORE",
+        C_GET_RQ => "C-GET",
+        C_FIND_RQ => "C-FIND",
+        C_ECHO_RQ => "C-ECHO",
+        N_EVENT_REPORT_RQ => "N-EVENT-REPORT",
+        N_SET_RQ => "N-SET",
+        N_ACTION_RQ => "N-ACTION",
+        N_CREATE_RQ => "N-CREATE",
+        _ => "other",
+    }
+}
+
+/// Record only the listed labels of each metric named in `sets`
+///
+/// Metrics not named keep their default labels, and an empty list records a metric without
+/// labels. Applies to every metric recorded through [`labels`], including the application's.
+pub fn set_label_sets(sets: HashMap<String, Vec<String>>) {
+    *LABEL_SETS.write().unwrap_or_else(|e| e.into_inner()) = Some(sets);
+}
+
+/// The labels to record for one data point of `metric`
+///
+/// `values` holds every label the metric can carry. Those in `optional` are left out unless
+/// configured, which keeps high-cardinality labels such as a request path off by default.
+#[cfg(feature = "metrics")]
+pub fn labels(
+    metric: &str,
+    values: Vec<(&'static str, String)>,
+    optional: &[&str],
+) -> Vec<::metrics::Label> {
+    let sets = LABEL_SETS.read().unwrap_or_else(|e| e.into_inner());
+    let configured = sets.as_ref().and_then(|sets| sets.get(metric));
+    values
+        .into_iter()
+        .filter(|(name, _)| match configured {
+            Some(set) => set.iter().any(|label| label == name),
+            None => !optional.contains(name),
+        })
+        .map(|(name, value)| ::metrics::Label::new(name, value))
+        .collect()
+}
+
+pub(crate) fn association_opened(role: &'static str) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(
+        ASSOCIATIONS_OPENED,
+        labels(ASSOCIATIONS_OPENED, vec![("role", role.into())], &[])
+    )
+    .increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = role;
+}
+
+pub(crate) fn find_matches(count: u64) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(FIND_MATCHES).increment(count);
+    #[cfg(not(feature = "metrics"))]
+    let _ = count;
+}
+
+pub(crate) fn retrieved_instances(operation: &'static str, count: u64) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(
+        RETRIEVED_INSTANCES,
+        labels(
+            RETRIEVED_INSTANCES,
+            vec![("operation", operation.into())],
+            &[]
+        )
+    )
+    .increment(count);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (operation, count);
+}
+
+pub(crate) fn store_bytes(role: &'static str, bytes: u64) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!(
+        STORE_BYTES,
+        labels(STORE_BYTES, vec![("role", role.into())], &[])
+    )
+    .increment(bytes);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (role, bytes);
+}
+
+/// Record how long an operation started at `started` took
+pub(crate) fn operation(operation: &'static str, role: &'static str, started: Instant) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(
+        OPERATION_DURATION,
+        labels(
+            OPERATION_DURATION,
+            vec![("operation", operation.into()), ("role", role.into())],
+            &[]
+        )
+    )
+    .record(started.elapsed().as_secs_f64());
+    #[cfg(not(feature = "metrics"))]
+    let _ = (operation, role, started);
+}
+
+/// Count a failed operation, with its DIMSE status when it got as far as one
+pub(crate) fn failure(operation: &'static str, status: Option<u16>) {
+    #[cfg(feature = "metrics")]
+    {
+        let status = match status {
+            Some(code) => format!("0x{:04X}", code),
+            None => "none".to_string(),
+        };
+        ::metrics::counter!(
+            FAILURES,
+            labels(
+                FAILURES,
+                vec![("operation", operation.into()), ("status", status)],
+                &[]
+            )
+        )
+        .increment(1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (operation, status);
+}
+
+/// Record one windowed throughput sample of a transfer
+pub(crate) fn transfer_throughput(role: &'static str, bytes_per_sec: f64) {
+    #[cfg(feature = "metrics")]
+    ::metrics::histogram!(
+        TRANSFER_THROUGHPUT,
+        labels(TRANSFER_THROUGHPUT, vec![("role", role.into())], &[])
+    )
+    .record(bytes_per_sec);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (role, bytes_per_sec);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[test]
+    fn test_failures_are_labelled_with_dimse_status() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            failure("C-STORE", Some(0xA700));
+            failure("C-ECHO", None);
+            association_opened(SCP);
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let counter = |name: &str, label: (&str, &str)| {
+            snapshot.iter().find_map(|(key, _, _, value)| {
+                let key = key.key();
+                let labelled = key
+                    .labels()
+                    .any(|l| l.key() == label.0 && l.value() == label.1);
+                match value {
+                    DebugValue::Counter(n) if key.name() == name && labelled => Some(*n),
+                    _ => None,
+                }
+            })
+        };
+        assert_eq!(counter(FAILURES, ("status", "0xA700")), Some(1));
+        assert_eq!(counter(FAILURES, ("status", "none")), Some(1));
+        assert_eq!(counter(ASSOCIATIONS_OPENED, ("role", "scp")), Some(1));
+    }
+
+    #[test]
+    fn test_throughput_monitor_records_windowed_rate() {
+        use crate::config::SlowTransferConfig;
+        use crate::throughput::ThroughputMonitor;
+        use std::time::Duration;
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, || {
+            let config = SlowTransferConfig {
+                min_bytes_per_sec: 1_000,
+                window_ms: 1_000,
+                sustained_ms: 5_000,
+            };
+            let start = Instant::now();
+            let mut monitor = ThroughputMonitor::starting_at("C-GET", config, start).with_role(SCP);
+            // 200 bytes per 100ms; judged, and so sampled, from the 10th tick on
+            for i in 1..=12 {
+                monitor.record_at(start + Duration::from_millis(i * 100), 200);
+            }
+        });
+
+        let samples = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .find_map(|(key, _, _, value)| {
+                let key = key.key();
+                let scp = key.labels().any(|l| l.key() == "role" && l.value() == SCP);
+                match value {
+                    DebugValue::Histogram(samples) if key.name() == TRANSFER_THROUGHPUT && scp => {
+                        Some(samples)
+                    }
+                    _ => None,
+                }
+            })
+            .expect("throughput recorded");
+        assert_eq!(samples.len(), 3);
+        assert!(samples
+            .iter()
+            .all(|rate| (rate.into_inner() - 2_000.0).abs() < 1.0));
+    }
+
+    #[test]
+    fn test_configured_label_set_drops_other_labels() {
+        // A metric of its own, so other tests keep their default labels
+        const METRIC: &str = "dimse_test_labels_total";
+        let values = || {
+            vec![
+                ("operation", "C-FIND".to_string()),
+                ("status", "0xA700".to_string()),
+                ("remote_ae", "PACS".to_string()),
+            ]
+        };
+        let names = |labels: Vec<::metrics::Label>| {
+            labels
+                .iter()
+                .map(|l| l.key().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(labels(METRIC, values(), &["remote_ae"])),
+            ["operation", "status"]
+        );
+        set_label_sets(HashMap::from([(
+            METRIC.to_string(),
+            vec!["operation".to_string(), "remote_ae".to_string()],
+        )]));
+        assert_eq!(
+            names(labels(METRIC, values(), &["remote_ae"])),
+            ["operation", "remote_ae"]
+        );
+    }
+}
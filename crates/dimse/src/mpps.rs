@@ -0,0 +1,140 @@
+//! Modality Performed Procedure Step
+//!
+//! Modalities report a procedure step with an N-CREATE when it starts, then N-SETs as it
+//! progresses and when it is completed or discontinued. Each message is handed to the query
+//! provider as an [`MppsEvent`] with its attributes in DICOM JSON, ready to be forwarded.
+
+use dicom_dictionary_std::tags;
+use dicom_object::InMemDicomObject;
+use serde::{Deserialize, Serialize};
+
+/// Modality Performed Procedure Step SOP Class UID
+pub const MODALITY_PERFORMED_PROCEDURE_STEP: &str = "1.2.840.10008.3.1.2.3.3";
+
+/// Performed Procedure Step Status (0040,0252)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MppsStatus {
+    #[serde(rename = "IN PROGRESS")]
+    InProgress,
+    #[serde(rename = "COMPLETED")]
+    Completed,
+    #[serde(rename = "DISCONTINUED")]
+    Discontinued,
+}
+
+impl MppsStatus {
+    /// Parse a Performed Procedure Step Status code string
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim() {
+            "IN PROGRESS" => Some(Self::InProgress),
+            "COMPLETED" => Some(Self::Completed),
+            "DISCONTINUED" => Some(Self::Discontinued),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InProgress => "IN PROGRESS",
+            Self::Completed => "COMPLETED",
+            Self::Discontinued => "DISCONTINUED",
+        }
+    }
+}
+
+/// Which N-service carried an MPPS message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MppsOperation {
+    /// N-CREATE: the step has started
+    Create,
+    /// N-SET: the step was updated, completed or discontinued
+    Set,
+}
+
+/// One MPPS message received by the SCP
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MppsEvent {
+    pub operation: MppsOperation,
+    /// SOP Instance UID of the performed procedure step
+    pub sop_instance_uid: String,
+    /// Step status, if the message set one (an N-SET may only update other attributes)
+    pub status: Option<MppsStatus>,
+    /// Attributes sent with the message, as DICOM JSON
+    pub attributes: serde_json::Value,
+}
+
+impl MppsEvent {
+    /// Build the event for an N-CREATE or N-SET data set
+    ///
+    /// Fails if the data set cannot be converted to JSON or carries a status other than the
+    /// three defined ones; an N-CREATE must also start the step as IN PROGRESS.
+    pub fn from_object(
+        operation: MppsOperation,
+        sop_instance_uid: impl Into<String>,
+        object: &InMemDicomObject,
+    ) -> Result<Self, String> {
+        let status = match object
+            .element_opt(tags::PERFORMED_PROCEDURE_STEP_STATUS)
+            .ok()
+            .flatten()
+        {
+            Some(element) => {
+                let code = element.to_str().map_err(|e| e.to_string())?;
+                let status = MppsStatus::from_code(&code)
+                    .ok_or_else(|| format!("unknown Performed Procedure Step Status '{}'", code))?;
+                Some(status)
+            }
+            None => None,
+        };
+        if operation == MppsOperation::Create && status != Some(MppsStatus::InProgress) {
+            return Err("N-CREATE must set Performed Procedure Step Status to IN PROGRESS".into());
+        }
+
+        Ok(Self {
+            operation,
+            sop_instance_uid: sop_instance_uid.into(),
+            status,
+            attributes: dicom_json::to_value(object).map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+
+    fn step(status: &str) -> InMemDicomObject {
+        let mut object = InMemDicomObject::new_empty();
+        object.put(DataElement::new(
+            tags::PERFORMED_PROCEDURE_STEP_STATUS,
+            VR::CS,
+            PrimitiveValue::from(status),
+        ));
+        object.put(DataElement::new(
+            tags::PERFORMED_PROCEDURE_STEP_ID,
+            VR::SH,
+            PrimitiveValue::from("PPS1"),
+        ));
+        object
+    }
+
+    #[test]
+    fn test_event_carries_status_and_json_attributes() {
+        let event =
+            MppsEvent::from_object(MppsOperation::Set, "1.2.3", &step("COMPLETED")).unwrap();
+        assert_eq!(event.status, Some(MppsStatus::Completed));
+        assert_eq!(event.attributes["00400253"]["Value"][0], "PPS1");
+        assert_eq!(serde_json::to_value(&event).unwrap()["status"], "COMPLETED");
+
+        assert!(
+            MppsEvent::from_object(MppsOperation::Create, "1.2.3", &step("COMPLETED")).is_err()
+        );
+        assert!(MppsEvent::from_object(MppsOperation::Set, "1.2.3", &step("PAUSED")).is_err());
+        let update =
+            MppsEvent::from_object(MppsOperation::Set, "1.2.3", &InMemDicomObject::new_empty())
+                .unwrap();
+        assert_eq!(update.status, None);
+    }
+}
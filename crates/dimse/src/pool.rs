@@ -0,0 +1,484 @@
+//! Native SCU associations and a process-wide pool of idle ones
+//!
+//! Each native SCU operation negotiates an [`ScuAssociation`]. With `association_pool_ttl_ms`
+//! set, a C-FIND hands its association back once the final response arrives, and the next
+//! query to the same peer from the same calling AE title picks it up instead of negotiating
+//! again. The pool is process-wide because the services build a fresh `DimseScu` per request.
+//! With `association_keepalive_ms` also set, idle associations get a C-ECHO every interval so
+//! peers with short idle timeouts keep them open, and ones the peer stops answering are evicted.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncReadExt;
+use tracing::debug;
+
+use crate::association::{
+    accepted_contexts, advertised_max_pdu, encode_associate_rq, extended_negotiations,
+    negotiate_max_pdu, parse_pdu_header, release_rq_pdu, role_selections, ContextResult,
+    ExtendedNegotiation, Negotiation, ProposedContext, ASSOCIATE_RQ_FIXED_LEN, CONTEXT_ACCEPTED,
+    PDU_ASSOCIATE_AC, PDU_ASSOCIATE_RJ, PDU_HEADER_LEN, PDU_P_DATA_TF, PDU_RELEASE_RP,
+};
+use crate::command::{Command, MessageAssembler, C_ECHO_RQ, RESPONSE_BIT, STATUS_SUCCESS};
+use crate::config::{ClientTlsConfig, RemoteNode, RoleSelection, MAX_PRESENTATION_CONTEXTS};
+use crate::metrics;
+use crate::scp::{read_pdu, reassemble, send_message, write_pdu, VERIFICATION};
+use crate::transport::{self, Connection};
+use crate::{DimseError, Result};
+
+/// An established association opened by the SCU
+#[derive(Debug)]
+pub(crate) struct ScuAssociation {
+    stream: Connection,
+    /// Bytes read from the peer that do not complete a PDU yet
+    pending: Vec<u8>,
+    /// Results for every proposed presentation context
+    pub contexts: Vec<ContextResult>,
+    /// SCP/SCU Role Selection answers from the peer, describing this side's roles
+    pub roles: Vec<RoleSelection>,
+    /// SOP Class Extended Negotiation proposed, so pooled associations are only reused for
+    /// operations asking for the same features
+    proposed_extended: Vec<ExtendedNegotiation>,
+    /// SOP Class Extended Negotiation answers from the peer
+    pub extended: Vec<ExtendedNegotiation>,
+    /// Maximum Length this side advertised, which bounds the PDUs it reads
+    pub local_max_pdu: u32,
+    /// Largest PDU to send, see [`negotiate_max_pdu`]
+    pub max_pdu: u32,
+    next_message_id: u16,
+}
+
+impl ScuAssociation {
+    /// Connect to `node` and negotiate the `proposed` contexts
+    ///
+    /// Only the first `max_presentation_contexts` of the node are proposed, so callers list the
+    /// contexts they cannot do without first. `negotiation` is passed to
+    /// [`encode_associate_rq`]. `timeout` bounds the connect, TLS handshake included, and the
+    /// wait for the A-ASSOCIATE-AC separately. `tls` is used when the node has TLS enabled.
+    pub async fn open(
+        local_aet: &str,
+        node: &RemoteNode,
+        proposed: &[ProposedContext],
+        negotiation: &Negotiation,
+        max_pdu: u32,
+        timeout: Duration,
+        tls: Option<&ClientTlsConfig>,
+    ) -> Result<Self> {
+        let timed_out =
+            |what: &str| DimseError::Timeout(format!("{} from {}", what, node.ae_title));
+        let cap = node
+            .max_presentation_contexts
+            .unwrap_or(MAX_PRESENTATION_CONTEXTS);
+        if proposed.len() > cap {
+            tracing::warn!(
+                "Proposing {} of {} presentation context(s) to {} (max_presentation_contexts={})",
+                cap,
+                proposed.len(),
+                node.ae_title,
+                cap
+            );
+        }
+        let proposed = &proposed[..proposed.len().min(cap)];
+
+        let mut stream = tokio::time::timeout(timeout, transport::connect(node, tls))
+            .await
+            .map_err(|_| timed_out("No connection"))??;
+        let rq = encode_associate_rq(local_aet, &node.ae_title, proposed, negotiation, max_pdu);
+        write_pdu(&mut stream, &rq).await?;
+
+        let (pdu_type, body) = tokio::time::timeout(timeout, read_pdu(&mut stream, max_pdu))
+            .await
+            .map_err(|_| timed_out("No A-ASSOCIATE-AC"))??;
+        match pdu_type {
+            PDU_ASSOCIATE_AC => {}
+            PDU_ASSOCIATE_RJ => {
+                return Err(DimseError::AssociationRejected(format!(
+                    "{} rejected the association (result/source/reason {:?})",
+                    node.ae_title,
+                    body.get(1..4).unwrap_or_default()
+                )))
+            }
+            other => {
+                return Err(DimseError::AssociationRejected(format!(
+                    "Unexpected PDU type 0x{:02X} in answer to the A-ASSOCIATE-RQ",
+                    other
+                )))
+            }
+        }
+        let items = body
+            .get(ASSOCIATE_RQ_FIXED_LEN as usize..)
+            .unwrap_or_default();
+        metrics::association_opened(metrics::SCU);
+        Ok(Self {
+            stream,
+            pending: Vec::new(),
+            contexts: accepted_contexts(items, proposed),
+            roles: role_selections(items),
+            proposed_extended: negotiation.extended.clone(),
+            extended: extended_negotiations(items),
+            local_max_pdu: max_pdu,
+            max_pdu: negotiate_max_pdu(max_pdu, advertised_max_pdu(items)),
+            next_message_id: 1,
+        })
+    }
+
+    /// Context ID and transfer syntax of the context accepted for `abstract_syntax`
+    pub fn accepted(&self, abstract_syntax: &str) -> Option<(u8, &str)> {
+        self.contexts
+            .iter()
+            .filter(|c| c.result == CONTEXT_ACCEPTED && c.abstract_syntax == abstract_syntax)
+            .find_map(|c| Some((c.id, c.transfer_syntax.as_deref()?)))
+    }
+
+    /// Extended negotiation information the peer accepted for `sop_class_uid`, if it answered
+    pub fn extended_negotiation(&self, sop_class_uid: &str) -> Option<&[u8]> {
+        find_extended(&self.extended, sop_class_uid)
+    }
+
+    /// Transfer syntax accepted for `context_id`, if the context was accepted
+    pub fn transfer_syntax(&self, context_id: u8) -> Option<&str> {
+        self.contexts
+            .iter()
+            .find(|c| c.id == context_id && c.result == CONTEXT_ACCEPTED)
+            .and_then(|c| c.transfer_syntax.as_deref())
+    }
+
+    /// Message ID for the next request sent on this association
+    pub fn next_message_id(&mut self) -> u16 {
+        let id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1).max(1);
+        id
+    }
+
+    /// Send a command and its data set, if any
+    pub async fn send(
+        &mut self,
+        context_id: u8,
+        command: &Command,
+        data_set: Option<&[u8]>,
+    ) -> std::io::Result<()> {
+        send_message(
+            &mut self.stream,
+            context_id,
+            command,
+            data_set,
+            self.max_pdu,
+        )
+        .await
+    }
+
+    /// Read the next PDU
+    ///
+    /// Cancel safe: bytes read before the future is dropped are kept for the next call, so a
+    /// receive can be raced against a cancellation or a timeout without losing the PDU framing.
+    pub async fn receive(&mut self) -> std::io::Result<(u8, Vec<u8>)> {
+        loop {
+            if let Some(header) = self.pending.get(..PDU_HEADER_LEN) {
+                let (pdu_type, len) =
+                    parse_pdu_header(header.try_into().expect("PDU header length"));
+                if len > self.local_max_pdu {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "PDU type 0x{:02X} declares {} bytes (max {})",
+                            pdu_type, len, self.local_max_pdu
+                        ),
+                    ));
+                }
+                let end = PDU_HEADER_LEN + len as usize;
+                if self.pending.len() >= end {
+                    let body = self.pending[PDU_HEADER_LEN..end].to_vec();
+                    self.pending.drain(..end);
+                    return Ok((pdu_type, body));
+                }
+                self.pending.reserve(end - self.pending.len());
+            }
+            if self.stream.read_buf(&mut self.pending).await? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Write a raw PDU, such as an A-RELEASE-RP
+    pub async fn write_pdu(&mut self, pdu: &[u8]) -> std::io::Result<()> {
+        write_pdu(&mut self.stream, pdu).await
+    }
+
+    /// Send a C-ECHO-RQ on the Verification context and wait up to `timeout` for a successful
+    /// C-ECHO-RSP
+    pub async fn echo(&mut self, timeout: Duration) -> Result<()> {
+        let Some((context_id, _)) = self.accepted(VERIFICATION) else {
+            return Err(DimseError::AssociationRejected(format!(
+                "{} not accepted",
+                VERIFICATION
+            )));
+        };
+        let request = Command {
+            command_field: C_ECHO_RQ,
+            message_id: self.next_message_id(),
+            affected_sop_class_uid: Some(VERIFICATION.to_string()),
+            ..Default::default()
+        };
+        self.send(context_id, &request, None).await?;
+        let mut assembler = MessageAssembler::default();
+        loop {
+            let (pdu_type, body) = tokio::time::timeout(timeout, self.receive())
+                .await
+                .map_err(|_| {
+                    DimseError::Timeout(format!("No C-ECHO response within {:?}", timeout))
+                })??;
+            if pdu_type != PDU_P_DATA_TF {
+                return Err(DimseError::operation_failed(format!(
+                    "Unexpected PDU type 0x{:02X} in answer to the C-ECHO-RQ",
+                    pdu_type
+                )));
+            }
+            let messages =
+                reassemble(&body, &mut assembler).map_err(DimseError::operation_failed)?;
+            for (_, command, _) in messages {
+                if command.command_field != C_ECHO_RQ | RESPONSE_BIT {
+                    continue;
+                }
+                return match command.status.unwrap_or(STATUS_SUCCESS) {
+                    STATUS_SUCCESS => Ok(()),
+                    status => Err(DimseError::operation_failed(format!(
+                        "C-ECHO failed with status 0x{:04X}",
+                        status
+                    ))),
+                };
+            }
+        }
+    }
+
+    /// Cheap check that an idle association is still usable: the peer has neither closed the
+    /// connection nor sent anything (an idle peer only ever sends a release or abort)
+    ///
+    /// The probe reads the TCP socket directly, below any TLS, and a byte it consumes would
+    /// corrupt the stream; it only ever gets one from a peer that is ending the association.
+    fn is_open(&self) -> bool {
+        let mut probe = [0u8; 1];
+        self.pending.is_empty()
+            && matches!(
+                self.stream.tcp().try_read(&mut probe),
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock
+            )
+    }
+
+    /// Release the association, waiting up to `timeout` for the A-RELEASE-RP
+    pub async fn release(mut self, timeout: Duration) {
+        if self.write_pdu(&release_rq_pdu()).await.is_err() {
+            return;
+        }
+        match tokio::time::timeout(timeout, self.receive()).await {
+            Ok(Ok((PDU_RELEASE_RP, _))) => {}
+            Ok(Ok((pdu_type, _))) => {
+                debug!("Expected A-RELEASE-RP, got PDU type 0x{:02X}", pdu_type)
+            }
+            _ => debug!("No A-RELEASE-RP before closing the association"),
+        }
+    }
+}
+
+fn find_extended<'a>(items: &'a [ExtendedNegotiation], sop_class_uid: &str) -> Option<&'a [u8]> {
+    items
+        .iter()
+        .find(|e| e.sop_class_uid == sop_class_uid)
+        .map(|e| e.application_information.as_slice())
+}
+
+/// Calling AE title and peer an idle association was opened between
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    local_aet: String,
+    ae_title: String,
+    host: String,
+    port: u16,
+    use_tls: bool,
+}
+
+impl PoolKey {
+    fn new(local_aet: &str, node: &RemoteNode) -> Self {
+        Self {
+            local_aet: local_aet.to_string(),
+            ae_title: node.ae_title.clone(),
+            host: node.host.clone(),
+            port: node.port,
+            use_tls: node.use_tls,
+        }
+    }
+}
+
+/// How often the pool had an idle association to hand out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// An idle association and when it went back to the pool
+#[derive(Debug)]
+struct Pooled {
+    /// Identifies the entry to its keep-alive task
+    id: u64,
+    association: ScuAssociation,
+    since: Instant,
+}
+
+type Idle = Mutex<HashMap<PoolKey, Vec<Pooled>>>;
+
+fn idle() -> &'static Idle {
+    static IDLE: OnceLock<Idle> = OnceLock::new();
+    IDLE.get_or_init(Default::default)
+}
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Pool hits and misses since the process started
+pub fn stats() -> PoolStats {
+    PoolStats {
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+    }
+}
+
+/// Take an idle association to `node` that accepted `abstract_syntax` and proposed the
+/// `extended` negotiation information for it (`None` for no extended negotiation)
+///
+/// Associations idle for longer than `ttl` are released and ones the peer has closed are
+/// dropped on the way.
+pub(crate) fn checkout(
+    local_aet: &str,
+    node: &RemoteNode,
+    abstract_syntax: &str,
+    extended: Option<&[u8]>,
+    ttl: Duration,
+) -> Option<ScuAssociation> {
+    let key = PoolKey::new(local_aet, node);
+    let (found, expired) = {
+        let mut idle = idle().lock().expect("association pool mutex");
+        let entries = idle.entry(key.clone()).or_default();
+        let (live, expired): (Vec<_>, Vec<_>) = std::mem::take(entries)
+            .into_iter()
+            .partition(|entry| entry.since.elapsed() < ttl);
+        *entries = live;
+        let mut found = None;
+        while let Some(position) = entries.iter().position(|entry| {
+            entry.association.accepted(abstract_syntax).is_some()
+                && find_extended(&entry.association.proposed_extended, abstract_syntax) == extended
+        }) {
+            let Pooled { association, .. } = entries.remove(position);
+            if association.is_open() {
+                found = Some(association);
+                break;
+            }
+            debug!(
+                "Discarding pooled association to {}: peer closed it",
+                key.ae_title
+            );
+        }
+        (found, expired)
+    };
+
+    for entry in expired {
+        debug!(
+            "Releasing pooled association to {}: idle past {:?}",
+            key.ae_title, ttl
+        );
+        tokio::spawn(entry.association.release(Duration::from_secs(5)));
+    }
+    match &found {
+        Some(_) => {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            debug!(
+                "Association pool hit for {}@{}:{}",
+                key.ae_title, key.host, key.port
+            );
+        }
+        None => {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            debug!(
+                "Association pool miss for {}@{}:{}",
+                key.ae_title, key.host, key.port
+            );
+        }
+    }
+    found
+}
+
+/// Keep `association` for reuse by later operations between the same AE titles
+///
+/// With a `keepalive` interval and the Verification context accepted, a task sends a C-ECHO
+/// on the association every interval while it stays idle, for at most `ttl`.
+pub(crate) fn checkin(
+    local_aet: &str,
+    node: &RemoteNode,
+    association: ScuAssociation,
+    ttl: Duration,
+    keepalive: Option<Duration>,
+) {
+    let key = PoolKey::new(local_aet, node);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let keepalive = keepalive.filter(|_| association.accepted(VERIFICATION).is_some());
+    idle()
+        .lock()
+        .expect("association pool mutex")
+        .entry(key.clone())
+        .or_default()
+        .push(Pooled {
+            id,
+            association,
+            since: Instant::now(),
+        });
+    if let Some(interval) = keepalive {
+        tokio::spawn(keep_alive(key, id, interval, ttl));
+    }
+}
+
+/// Shortest wait for a keep-alive C-ECHO response
+const ECHO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Echo the pooled entry `id` every `interval` until it leaves the pool
+///
+/// The entry is taken out of the pool for the echo, so no operation picks it up mid-exchange,
+/// and only goes back if the peer answered. Entries idle past `ttl` are released instead.
+async fn keep_alive(key: PoolKey, id: u64, interval: Duration, ttl: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let Some(mut entry) = take(&key, id) else {
+            return;
+        };
+        if entry.since.elapsed() >= ttl {
+            debug!(
+                "Releasing pooled association to {}: idle past {:?}",
+                key.ae_title, ttl
+            );
+            entry.association.release(Duration::from_secs(5)).await;
+            return;
+        }
+        if let Err(e) = entry.association.echo(interval.max(ECHO_TIMEOUT)).await {
+            debug!(
+                "Evicting pooled association to {}: keep-alive C-ECHO failed: {}",
+                key.ae_title, e
+            );
+            return;
+        }
+        idle()
+            .lock()
+            .expect("association pool mutex")
+            .entry(key.clone())
+            .or_default()
+            .push(entry);
+    }
+}
+
+/// Remove the entry `id` from the pool, if it is still there
+fn take(key: &PoolKey, id: u64) -> Option<Pooled> {
+    let mut idle = idle().lock().expect("association pool mutex");
+    let entries = idle.get_mut(key)?;
+    let position = entries.iter().position(|entry| entry.id == id)?;
+    Some(entries.remove(position))
+}
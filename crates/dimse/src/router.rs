@@ -1,12 +1,15 @@
 //! Internal router for decoupling DIMSE operations from HTTP layer
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use futures::stream::BoxStream;
 // use serde::{Deserialize, Serialize}; // TODO: Used when implementing actual message serialization
 use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::types::{DatasetStream, DimseCommand, FindQuery, MoveQuery};
+use crate::types::{DatasetStream, DimseCommand, DimseStatus, FindQuery, MoveQuery};
 use crate::{DimseError, RemoteNode, Result};
 
 /// Request sent to the DIMSE router
@@ -15,12 +18,18 @@ pub struct DimseRequest {
     /// Unique request ID for correlation
     pub id: Uuid,
 
+    /// ID shared by everything done for one association or SCU operation, for log correlation
+    pub correlation_id: Uuid,
+
     /// The DIMSE command to execute
     pub command: DimseCommand,
 
     /// Target remote node (for SCU operations)
     pub remote_node: Option<RemoteNode>,
 
+    /// AE title of the peer that sent the request (for SCP operations)
+    pub calling_aet: Option<String>,
+
     /// Request payload
     pub payload: DimseRequestPayload,
 
@@ -37,6 +46,9 @@ pub struct DimseResponse {
     /// Request ID this response correlates to
     pub request_id: Uuid,
 
+    /// Correlation ID of the request, nil until set with [`DimseResponse::with_correlation_id`]
+    pub correlation_id: Uuid,
+
     /// The response payload
     pub payload: DimseResponsePayload,
 
@@ -78,8 +90,8 @@ pub enum DimseResponsePayload {
         warning: u32,
     },
 
-    /// C-STORE response (success/failure)
-    Store { success: bool },
+    /// C-STORE response (success/failure) with the C-STORE-RSP status code
+    Store { success: bool, status: u16 },
 
     /// Error response
     Error { error: String },
@@ -256,13 +268,157 @@ impl Router for RouterReceiver {
     }
 }
 
+/// Router that dispatches each request to a handler chosen by the calling AE title
+///
+/// An exact AE title match wins over a pattern, patterns (`*` and `?` wildcards) are tried in
+/// the order they were added, and requests matching neither go to the default handler. Like
+/// [`RouterSender`], it only sends requests; handlers receive and answer them.
+#[derive(Default)]
+pub struct AetRouter {
+    exact: HashMap<String, Arc<dyn Router>>,
+    patterns: Vec<(String, Arc<dyn Router>)>,
+    default: Option<Arc<dyn Router>>,
+}
+
+impl AetRouter {
+    /// Create a router without routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route requests from AE titles matching `pattern` to `handler`
+    pub fn route(mut self, pattern: impl Into<String>, handler: Arc<dyn Router>) -> Self {
+        let pattern = pattern.into();
+        if pattern.contains(['*', '?']) {
+            self.patterns.push((pattern, handler));
+        } else {
+            self.exact.insert(pattern, handler);
+        }
+        self
+    }
+
+    /// Route requests from any other AE title, or without one, to `handler`
+    pub fn with_default(mut self, handler: Arc<dyn Router>) -> Self {
+        self.default = Some(handler);
+        self
+    }
+
+    /// Build a router from a map of calling AE title (or pattern) to handler name
+    ///
+    /// `handler` resolves each name, such as a pipeline, to the router serving it. The key
+    /// `"*"` sets the default handler. A map has no order, so patterns are tried most
+    /// specific first: more literal characters, then fewer `*`, then alphabetically.
+    pub fn from_config<F>(routes: &HashMap<String, String>, mut handler: F) -> Result<Self>
+    where
+        F: FnMut(&str) -> Result<Arc<dyn Router>>,
+    {
+        let mut routes: Vec<(&String, &String)> = routes.iter().collect();
+        routes.sort_by_cached_key(|(aet, _)| {
+            let literals = aet.chars().filter(|c| !matches!(c, '*' | '?')).count();
+            let stars = aet.chars().filter(|&c| c == '*').count();
+            (std::cmp::Reverse(literals), stars, aet.to_string())
+        });
+        let mut router = Self::new();
+        for (aet, name) in routes {
+            let target = handler(name)?;
+            router = if aet == "*" {
+                router.with_default(target)
+            } else {
+                router.route(aet.clone(), target)
+            };
+        }
+        Ok(router)
+    }
+
+    /// The handler for requests from `calling_aet`
+    pub fn resolve(&self, calling_aet: Option<&str>) -> Option<&Arc<dyn Router>> {
+        let routed = calling_aet.and_then(|aet| {
+            self.exact.get(aet).or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| matches_aet(pattern, aet))
+                    .map(|(_, handler)| handler)
+            })
+        });
+        routed.or(self.default.as_ref())
+    }
+
+    fn handler_for(&self, request: &DimseRequest) -> Result<&Arc<dyn Router>> {
+        self.resolve(request.calling_aet.as_deref()).ok_or_else(|| {
+            DimseError::router(format!(
+                "No route for calling AE title {}",
+                request.calling_aet.as_deref().unwrap_or("<none>")
+            ))
+        })
+    }
+}
+
+/// Match an AE title against a pattern where `*` is any run of characters and `?` any one
+fn matches_aet(pattern: &str, aet: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let aet: Vec<char> = aet.chars().collect();
+    let (mut p, mut a) = (0, 0);
+    let mut backtrack = None;
+    while a < aet.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, a));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == aet[a] => {
+                p += 1;
+                a += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    a = matched + 1;
+                    backtrack = Some((star, a));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[async_trait]
+impl Router for AetRouter {
+    async fn send_request(&self, request: DimseRequest) -> Result<DimseResponse> {
+        let handler = self.handler_for(&request)?;
+        handler.send_request(request).await
+    }
+
+    async fn send_streaming_request(
+        &self,
+        request: DimseRequest,
+    ) -> Result<BoxStream<'static, DimseResponse>> {
+        let handler = self.handler_for(&request)?;
+        handler.send_streaming_request(request).await
+    }
+
+    async fn next_request(&mut self) -> Result<DimseRequest> {
+        Err(DimseError::operation_failed(
+            "AetRouter cannot receive requests",
+        ))
+    }
+
+    async fn send_response(&self, _response: DimseResponse) -> Result<()> {
+        Err(DimseError::operation_failed(
+            "AetRouter cannot send responses",
+        ))
+    }
+}
+
 impl DimseRequest {
     /// Create a new C-ECHO request
     pub fn echo(remote_node: RemoteNode) -> Self {
         Self {
             id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
             command: DimseCommand::Echo,
             remote_node: Some(remote_node),
+            calling_aet: None,
             payload: DimseRequestPayload::Echo,
             response_tx: None,
             stream_tx: None,
@@ -273,8 +429,10 @@ impl DimseRequest {
     pub fn find(remote_node: RemoteNode, query: FindQuery) -> Self {
         Self {
             id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
             command: DimseCommand::Find,
             remote_node: Some(remote_node),
+            calling_aet: None,
             payload: DimseRequestPayload::Find(query),
             response_tx: None,
             stream_tx: None,
@@ -285,8 +443,10 @@ impl DimseRequest {
     pub fn move_request(remote_node: RemoteNode, query: MoveQuery) -> Self {
         Self {
             id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
             command: DimseCommand::Move,
             remote_node: Some(remote_node),
+            calling_aet: None,
             payload: DimseRequestPayload::Move(query),
             response_tx: None,
             stream_tx: None,
@@ -297,13 +457,41 @@ impl DimseRequest {
     pub fn store(remote_node: RemoteNode, dataset: DatasetStream) -> Self {
         Self {
             id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
             command: DimseCommand::Store,
             remote_node: Some(remote_node),
+            calling_aet: None,
             payload: DimseRequestPayload::Store(dataset),
             response_tx: None,
             stream_tx: None,
         }
     }
+
+    /// Create a C-STORE request for an instance received from `calling_aet`
+    pub fn received_store(calling_aet: impl Into<String>, dataset: DatasetStream) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            correlation_id: Uuid::new_v4(),
+            command: DimseCommand::Store,
+            remote_node: None,
+            calling_aet: Some(calling_aet.into()),
+            payload: DimseRequestPayload::Store(dataset),
+            response_tx: None,
+            stream_tx: None,
+        }
+    }
+
+    /// Set the AE title of the peer the request came from
+    pub fn with_calling_aet(mut self, calling_aet: impl Into<String>) -> Self {
+        self.calling_aet = Some(calling_aet.into());
+        self
+    }
+
+    /// Attach the request to an existing association or operation
+    pub fn with_correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
 }
 
 impl DimseResponse {
@@ -311,6 +499,7 @@ impl DimseResponse {
     pub fn echo(request_id: Uuid, success: bool) -> Self {
         Self {
             request_id,
+            correlation_id: Uuid::nil(),
             payload: DimseResponsePayload::Echo { success },
             is_final: true,
         }
@@ -320,6 +509,7 @@ impl DimseResponse {
     pub fn find(request_id: Uuid, dataset: Option<DatasetStream>, is_final: bool) -> Self {
         Self {
             request_id,
+            correlation_id: Uuid::nil(),
             payload: DimseResponsePayload::Find { dataset },
             is_final,
         }
@@ -337,6 +527,7 @@ impl DimseResponse {
     ) -> Self {
         Self {
             request_id,
+            correlation_id: Uuid::nil(),
             payload: DimseResponsePayload::Move {
                 dataset,
                 remaining,
@@ -350,9 +541,19 @@ impl DimseResponse {
 
     /// Create a new C-STORE response
     pub fn store(request_id: Uuid, success: bool) -> Self {
+        Self::store_status(request_id, if success { 0x0000 } else { 0xA700 })
+    }
+
+    /// Create a C-STORE response carrying a specific status code
+    pub fn store_status(request_id: Uuid, status: u16) -> Self {
+        let success = matches!(
+            DimseStatus::from_code(status),
+            DimseStatus::Success | DimseStatus::Warning(_)
+        );
         Self {
             request_id,
-            payload: DimseResponsePayload::Store { success },
+            correlation_id: Uuid::nil(),
+            payload: DimseResponsePayload::Store { success, status },
             is_final: true,
         }
     }
@@ -361,10 +562,17 @@ impl DimseResponse {
     pub fn error(request_id: Uuid, error: String) -> Self {
         Self {
             request_id,
+            correlation_id: Uuid::nil(),
             payload: DimseResponsePayload::Error { error },
             is_final: true,
         }
     }
+
+    /// Carry the correlation ID of the request being answered
+    pub fn with_correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -419,4 +627,102 @@ mod tests {
         let move_req = DimseRequest::move_request(remote_node, move_query);
         assert_eq!(move_req.command, DimseCommand::Move);
     }
+
+    /// Handler that answers every request with its own name
+    struct Named(String);
+
+    #[async_trait]
+    impl Router for Named {
+        async fn send_request(&self, request: DimseRequest) -> Result<DimseResponse> {
+            Ok(DimseResponse::error(request.id, self.0.clone()))
+        }
+
+        async fn send_streaming_request(
+            &self,
+            _request: DimseRequest,
+        ) -> Result<BoxStream<'static, DimseResponse>> {
+            Err(DimseError::operation_failed("not streaming"))
+        }
+
+        async fn next_request(&mut self) -> Result<DimseRequest> {
+            Err(DimseError::operation_failed("not receiving"))
+        }
+
+        async fn send_response(&self, _response: DimseResponse) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    async fn handled_by(router: &AetRouter, calling_aet: &str) -> String {
+        let dataset = DatasetStream::from_bytes(bytes::Bytes::from_static(b"DICM"));
+        let request = DimseRequest::received_store(calling_aet, dataset);
+        match router.send_request(request).await.unwrap().payload {
+            DimseResponsePayload::Error { error } => error,
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aet_router_dispatches_by_calling_aet() {
+        let router = AetRouter::new()
+            .route("MODALITY_A", Arc::new(Named("a".to_string())))
+            .route("MODALITY_B", Arc::new(Named("b".to_string())))
+            .with_default(Arc::new(Named("default".to_string())));
+
+        assert_eq!(handled_by(&router, "MODALITY_A").await, "a");
+        assert_eq!(handled_by(&router, "MODALITY_B").await, "b");
+        assert_eq!(handled_by(&router, "UNKNOWN").await, "default");
+    }
+
+    #[tokio::test]
+    async fn test_aet_router_from_config_prefers_specific_patterns() {
+        let routes: HashMap<String, String> = [
+            ("C*", "c"),
+            ("CT_*", "ct"),
+            ("CT_ROOM?", "room"),
+            ("*_ROOM1", "any_room1"),
+        ]
+        .into_iter()
+        .map(|(aet, name)| (aet.to_string(), name.to_string()))
+        .collect();
+        // The map iterates differently from run to run; the outcome must not
+        for _ in 0..16 {
+            let shuffled: HashMap<String, String> = routes.clone().into_iter().collect();
+            let router = AetRouter::from_config(&shuffled, |name| {
+                Ok(Arc::new(Named(name.to_string())) as Arc<dyn Router>)
+            })
+            .unwrap();
+            assert_eq!(handled_by(&router, "CT_ROOM1").await, "room");
+            assert_eq!(handled_by(&router, "CT_ROOM12").await, "ct");
+            assert_eq!(handled_by(&router, "MR_ROOM1").await, "any_room1");
+            assert_eq!(handled_by(&router, "CR1").await, "c");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aet_router_from_config_with_patterns() {
+        let routes: HashMap<String, String> = [
+            ("CT_*", "ct"),
+            ("CT_ROOM1", "room1"),
+            ("MR?", "mr"),
+            ("*", "fallback"),
+        ]
+        .into_iter()
+        .map(|(aet, name)| (aet.to_string(), name.to_string()))
+        .collect();
+        let router = AetRouter::from_config(&routes, |name| {
+            Ok(Arc::new(Named(name.to_string())) as Arc<dyn Router>)
+        })
+        .unwrap();
+
+        assert_eq!(handled_by(&router, "CT_ROOM1").await, "room1");
+        assert_eq!(handled_by(&router, "CT_ROOM2").await, "ct");
+        assert_eq!(handled_by(&router, "MR1").await, "mr");
+        assert_eq!(handled_by(&router, "MR12").await, "fallback");
+
+        let unrouted = AetRouter::new().route("MODALITY_A", Arc::new(Named("a".to_string())));
+        let dataset = DatasetStream::from_bytes(bytes::Bytes::from_static(b"DICM"));
+        let request = DimseRequest::received_store("MODALITY_B", dataset);
+        assert!(unrouted.send_request(request).await.is_err());
+    }
 }
@@ -4,13 +4,47 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpListener;
 use tokio::sync::RwLock;
-use tracing::{debug, error, info, span, warn, Level};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, span, warn, Instrument, Level};
 
-use crate::config::DimseConfig;
-use crate::router::{DimseRequest, DimseRequestPayload, DimseResponse, Router};
-use crate::types::{DatasetStream, QueryLevel};
+use crate::association::{
+    abort_pdu, advertised_max_pdu, associate_rq_len_ok, encode_associate_ac, encode_p_data,
+    negotiate_roles, parse_p_data, parse_pdu_header, proposed_contexts, release_rp_pdu,
+    release_rq_pdu, role_selections, select_transfer_syntax, variable_items_well_formed,
+    AssociateRequestHeader, AssociationInfo, ContextResult, Negotiation, ProposedContext,
+    RejectReason, ABORT_INVALID_PARAMETER, ABORT_SOURCE_PROVIDER, ABORT_UNEXPECTED_PDU,
+    ASSOCIATE_RQ_FIXED_LEN, ASSOCIATE_RQ_HEADER_LEN, CONTEXT_ABSTRACT_SYNTAX_NOT_SUPPORTED,
+    CONTEXT_ACCEPTED, CONTEXT_TRANSFER_SYNTAXES_NOT_SUPPORTED, IMPLEMENTATION_CLASS_UID,
+    IMPLEMENTATION_VERSION_NAME, PDU_ABORT, PDU_ASSOCIATE_RQ, PDU_HEADER_LEN, PDU_P_DATA_TF,
+    PDU_RELEASE_RP, PDU_RELEASE_RQ,
+};
+use crate::audit::{self, AuditEvent, AuditEventKind, AuditOutcome};
+use crate::command::{
+    decode_data_set, encode_data_set, Command, Message, MessageAssembler, C_CANCEL_RQ, C_ECHO_RQ,
+    C_FIND_RQ, C_STORE_RQ, N_ACTION_RQ, N_CREATE_RQ, N_EVENT_REPORT_RQ, N_SET_RQ, RESPONSE_BIT,
+    STATUS_IDENTIFIER_DOES_NOT_MATCH, STATUS_INVALID_ATTRIBUTE_VALUE, STATUS_NO_SUCH_ACTION_TYPE,
+    STATUS_PENDING, STATUS_PROCESSING_FAILURE, STATUS_SOP_CLASS_NOT_SUPPORTED, STATUS_SUCCESS,
+    STATUS_UNABLE_TO_PROCESS, STATUS_UNRECOGNIZED_OPERATION,
+};
+use crate::commitment::{
+    self, CommitmentRequest, CommitmentResult, FailedReference, ACTION_REQUEST_COMMIT,
+    FAILURE_CLASS_INSTANCE_CONFLICT, FAILURE_NO_SUCH_OBJECT_INSTANCE, STORAGE_COMMITMENT_PUSH,
+    STORAGE_COMMITMENT_PUSH_INSTANCE,
+};
+use crate::config::{DimseConfig, StorePolicy};
+use crate::metrics;
+use crate::mpps::{MppsEvent, MppsOperation, MODALITY_PERFORMED_PROCEDURE_STEP};
+use crate::router::{
+    DimseRequest, DimseRequestPayload, DimseResponse, DimseResponsePayload, Router,
+};
+use crate::throughput::ThroughputMonitor;
+use crate::transport::Connection;
+use crate::types::{DatasetStream, DimseStatus, QueryLevel};
+use crate::worklist::{WorklistQuery, MODALITY_WORKLIST_FIND};
 use crate::{DimseError, Result};
 
 /// Trait for providing query capabilities to the SCP
@@ -31,17 +65,63 @@ pub trait QueryProvider: Send + Sync {
         parameters: &std::collections::HashMap<String, String>,
     ) -> Result<Vec<DatasetStream>>;
 
+    /// Answer a Modality Worklist C-FIND with the matching scheduled procedure steps
+    ///
+    /// Providers without a worklist source report no matches.
+    async fn worklist(&self, _query: &WorklistQuery) -> Result<Vec<DatasetStream>> {
+        Ok(vec![])
+    }
+
     /// Store a dataset (for C-STORE operations)
     async fn store(&self, dataset: DatasetStream) -> Result<()>;
+
+    /// Called for each Modality Performed Procedure Step N-CREATE or N-SET
+    ///
+    /// An error fails the request with a processing failure status.
+    async fn on_mpps(&self, _step: MppsEvent) -> Result<()> {
+        Ok(())
+    }
+
+    /// Look up a single instance, for storage commitment
+    ///
+    /// The default asks `locate` at image level and takes the first match.
+    async fn locate_instance(&self, sop_instance_uid: &str) -> Result<Option<DatasetStream>> {
+        let mut parameters = std::collections::HashMap::new();
+        parameters.insert("00080018".to_string(), sop_instance_uid.to_string());
+        let found = self.locate(QueryLevel::Image, &parameters).await?;
+        Ok(found.into_iter().next())
+    }
+
+    /// Pass a dataset on without keeping it (C-STORE under the `forward` policy)
+    ///
+    /// Providers without a forwarding path store the dataset instead.
+    async fn forward(&self, dataset: DatasetStream) -> Result<()> {
+        self.store(dataset).await
+    }
+
+    /// Called once an instance received by C-STORE has been stored or forwarded
+    ///
+    /// Failures are logged; the C-STORE has already succeeded by then.
+    async fn on_store(&self, _instance: DatasetStream) -> Result<()> {
+        Ok(())
+    }
 }
 
+/// Verification SOP Class UID
+pub(crate) const VERIFICATION: &str = "1.2.840.10008.1.1";
+/// Prefix shared by the Query/Retrieve information model SOP classes
+const QUERY_RETRIEVE_PREFIX: &str = "1.2.840.10008.5.1.4.1.2.";
+/// How long an association released for shutdown waits for the peer's A-RELEASE-RP
+const SHUTDOWN_RELEASE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// DIMSE Service Class Provider
 pub struct DimseScp {
     config: DimseConfig,
-    #[allow(dead_code)]
-    query_provider: Arc<dyn QueryProvider>, // TODO: Used for database queries
+    query_provider: Arc<dyn QueryProvider>,
     router: Option<Arc<dyn Router>>,
     active_associations: Arc<RwLock<u32>>,
+    /// Cancelled when the SCP stops; open associations close after their current operation
+    shutdown: CancellationToken,
 }
 
 impl DimseScp {
@@ -52,17 +132,36 @@ impl DimseScp {
             query_provider,
             router: None,
             active_associations: Arc::new(RwLock::new(0)),
+            shutdown: CancellationToken::new(),
         }
     }
 
     /// Set the router for handling requests
+    ///
+    /// Instances received over the wire that the store policy does not refuse are sent through
+    /// the router, tagged with the calling AE title, instead of going to the query provider. An
+    /// [`crate::AetRouter`] lets one listener hand each peer's instances to a different handler.
     pub fn with_router(mut self, router: Arc<dyn Router>) -> Self {
         self.router = Some(router);
         self
     }
 
-    /// Start the SCP listener
-    pub async fn run(self) -> Result<()> {
+    /// Start the SCP listener, serving until `shutdown` is cancelled
+    ///
+    /// On shutdown no new associations are accepted. Open ones get `shutdown_grace_ms` to finish
+    /// the operation they are running and are then released; any still busy after the grace
+    /// period are closed.
+    pub async fn run(self, shutdown: CancellationToken) -> Result<()> {
+        let listener = self.bind()?;
+        self.serve(listener, shutdown).await
+    }
+
+    /// Bind the listening socket without accepting associations yet
+    ///
+    /// A port that is still held by another listener fails with a [`DimseError::Network`]
+    /// error of kind `AddrInUse`, which callers may retry before handing the listener to
+    /// [`DimseScp::serve`].
+    pub fn bind(&self) -> Result<TcpListener> {
         let addr = SocketAddr::new(self.config.bind_addr, self.config.port);
         // Use socket2 for reliable bind with SO_REUSEADDR (helps with ephemeral port races in tests)
         let socket = socket2::Socket::new(
@@ -78,8 +177,12 @@ impl DimseScp {
         socket.listen(128)?;
         let std_listener: std::net::TcpListener = socket.into();
         std_listener.set_nonblocking(true)?;
-        let listener = TcpListener::from_std(std_listener)?;
+        Ok(TcpListener::from_std(std_listener)?)
+    }
 
+    /// Accept associations on a listener from [`DimseScp::bind`] until `shutdown` is cancelled
+    pub async fn serve(mut self, listener: TcpListener, shutdown: CancellationToken) -> Result<()> {
+        let addr = listener.local_addr()?;
         info!(
             "Starting DIMSE SCP on {} (AET: {})",
             addr, self.config.local_aet
@@ -88,29 +191,62 @@ impl DimseScp {
         // Validate configuration
         self.config.validate()?;
 
+        #[cfg(feature = "tls")]
+        let acceptor = self
+            .config
+            .tls
+            .as_ref()
+            .map(crate::tls::server_config)
+            .transpose()?
+            .map(tokio_rustls::TlsAcceptor::from);
+        #[cfg(not(feature = "tls"))]
+        if self.config.tls.is_some() {
+            return Err(DimseError::NotSupported(
+                "TLS on the SCP requires feature 'tls'".into(),
+            ));
+        }
+
+        self.shutdown = shutdown.clone();
         let scp = Arc::new(self);
+        let mut associations = JoinSet::new();
 
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                Some(_) = associations.join_next(), if !associations.is_empty() => continue,
+                accepted = listener.accept() => accepted,
+            };
+            match accepted {
                 Ok((stream, peer_addr)) => {
                     debug!("Accepted connection from {}", peer_addr);
 
                     // Check association limit
-                    {
-                        let active = scp.active_associations.read().await;
-                        if *active >= scp.config.max_associations {
+                    let at_limit =
+                        *scp.active_associations.read().await >= scp.config.max_associations;
+
+                    let scp_clone = Arc::clone(&scp);
+                    #[cfg(feature = "tls")]
+                    let acceptor = acceptor.clone();
+                    associations.spawn(async move {
+                        #[cfg(feature = "tls")]
+                        let connection = scp_clone.secure(stream, peer_addr, acceptor).await;
+                        #[cfg(not(feature = "tls"))]
+                        let connection = Some(Connection::Plain(stream));
+                        let Some(mut connection) = connection else {
+                            return;
+                        };
+
+                        if at_limit {
                             warn!(
                                 "Maximum associations reached, rejecting connection from {}",
                                 peer_addr
                             );
-                            drop(stream);
-                            continue;
+                            scp_clone
+                                .reject(&mut connection, RejectReason::AssociationLimit)
+                                .await;
+                            return;
                         }
-                    }
-
-                    let scp_clone = Arc::clone(&scp);
-                    tokio::spawn(async move {
-                        if let Err(e) = scp_clone.handle_association(stream, peer_addr).await {
+                        if let Err(e) = scp_clone.handle_association(connection, peer_addr).await {
                             error!("Error handling association from {}: {}", peer_addr, e);
                         }
                     });
@@ -120,21 +256,93 @@ impl DimseScp {
                 }
             }
         }
+
+        drop(listener);
+        scp.drain(associations).await;
+        Ok(())
     }
 
-    /// Handle a single association
-    async fn handle_association(
+    /// Wait up to the shutdown grace period for open associations, then close the rest
+    async fn drain(&self, mut associations: JoinSet<()>) {
+        let open = associations.len();
+        if open == 0 {
+            info!("DIMSE SCP {} stopped", self.config.local_aet);
+            return;
+        }
+        let grace = self.config.shutdown_grace();
+        info!(
+            "DIMSE SCP {} stopping; draining {} open association(s) for up to {:?}",
+            self.config.local_aet, open, grace
+        );
+
+        let mut drained = 0;
+        let _ = tokio::time::timeout(grace, async {
+            while associations.join_next().await.is_some() {
+                drained += 1;
+            }
+        })
+        .await;
+        let closed = associations.len();
+        associations.shutdown().await;
+
+        if closed > 0 {
+            warn!(
+                "DIMSE SCP {} stopped: {} association(s) drained, {} forcibly closed",
+                self.config.local_aet, drained, closed
+            );
+        } else {
+            info!(
+                "DIMSE SCP {} stopped: {} association(s) drained",
+                self.config.local_aet, drained
+            );
+        }
+    }
+
+    /// Complete the TLS handshake when the SCP has TLS configured
+    ///
+    /// Client certificates are verified during the handshake, so a peer without an acceptable
+    /// one is dropped before it can send an A-ASSOCIATE-RQ.
+    #[cfg(feature = "tls")]
+    async fn secure(
         &self,
         stream: tokio::net::TcpStream,
         peer_addr: SocketAddr,
-    ) -> Result<()> {
+        acceptor: Option<tokio_rustls::TlsAcceptor>,
+    ) -> Option<Connection> {
+        let Some(acceptor) = acceptor else {
+            return Some(Connection::Plain(stream));
+        };
+        match tokio::time::timeout(self.config.artim_timeout(), acceptor.accept(stream)).await {
+            Ok(Ok(stream)) => Some(Connection::Tls(Box::new(stream.into()))),
+            Ok(Err(e)) => {
+                warn!("TLS handshake with {} failed: {}", peer_addr, e);
+                None
+            }
+            Err(_) => {
+                debug!("TLS handshake with {} timed out", peer_addr);
+                None
+            }
+        }
+    }
+
+    /// Handle a single association
+    async fn handle_association(&self, stream: Connection, peer_addr: SocketAddr) -> Result<()> {
         // Increment active associations
         {
             let mut active = self.active_associations.write().await;
             *active += 1;
         }
 
-        let result = self.handle_association_inner(stream, peer_addr).await;
+        // The correlation ID is recorded once the association is accepted
+        let span = tracing::info_span!(
+            "dimse.association",
+            net.peer.addr = %peer_addr,
+            correlation_id = tracing::field::Empty
+        );
+        let result = self
+            .handle_association_inner(stream, peer_addr)
+            .instrument(span)
+            .await;
 
         // Decrement active associations
         {
@@ -145,26 +353,863 @@ impl DimseScp {
         result
     }
 
+    /// Send an A-ASSOCIATE-RJ with the configured codes for `reason` and close the stream
+    async fn reject(&self, stream: &mut Connection, reason: RejectReason) {
+        let codes = self.config.association_rejections.for_reason(reason);
+        debug!(
+            "Sending A-ASSOCIATE-RJ ({:?}): result={} source={} reason={}",
+            reason, codes.result, codes.source, codes.reason
+        );
+        if let Err(e) = stream.write_all(&codes.to_pdu()).await {
+            debug!("Failed to send A-ASSOCIATE-RJ: {}", e);
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    /// Decide whether an association request should be rejected
+    fn check_association(&self, header: &AssociateRequestHeader) -> Option<RejectReason> {
+        if header.called_aet != self.config.local_aet {
+            return Some(RejectReason::UnknownCalledAet);
+        }
+        if !self.config.allowed_calling_aets.is_empty()
+            && !self
+                .config
+                .allowed_calling_aets
+                .iter()
+                .any(|aet| aet == &header.calling_aet)
+        {
+            return Some(RejectReason::AclDenied);
+        }
+        None
+    }
+
     /// Inner association handler
     async fn handle_association_inner(
         &self,
-        _stream: tokio::net::TcpStream,
+        mut stream: Connection,
         peer_addr: SocketAddr,
     ) -> Result<()> {
-        info!("Starting association with {}", peer_addr);
+        // Read the PDU header first so the declared length is checked before anything is buffered
+        let mut buf = [0u8; ASSOCIATE_RQ_HEADER_LEN];
+        let (pdu_header, fixed) = buf.split_at_mut(PDU_HEADER_LEN);
+        // ARTIM: a connection that never sends an RQ is closed once the timer expires
+        match tokio::time::timeout(self.config.artim_timeout(), stream.read_exact(pdu_header)).await
+        {
+            Ok(Ok(_)) => {}
+            // Readiness probes and peers that hang up before sending an RQ
+            Ok(Err(_)) | Err(_) => {
+                debug!("Connection from {} closed before A-ASSOCIATE-RQ", peer_addr);
+                return Ok(());
+            }
+        }
+
+        let (pdu_type, pdu_len) =
+            parse_pdu_header((&*pdu_header).try_into().expect("6-byte PDU header"));
+        if pdu_type != PDU_ASSOCIATE_RQ {
+            warn!("Unexpected PDU type 0x{:02X} from {}", pdu_type, peer_addr);
+            send_abort(&mut stream, ABORT_SOURCE_PROVIDER, ABORT_UNEXPECTED_PDU).await;
+            return Ok(());
+        }
+        if !associate_rq_len_ok(pdu_len) {
+            warn!(
+                "Aborting association from {}: A-ASSOCIATE-RQ length {} out of range",
+                peer_addr, pdu_len
+            );
+            send_abort(&mut stream, ABORT_SOURCE_PROVIDER, ABORT_INVALID_PARAMETER).await;
+            return Ok(());
+        }
 
-        // TODO: Implement actual DICOM UL association handling
-        // This is a stub implementation that will be expanded with actual DICOM protocol handling
+        // Then the fixed fields carrying the AE titles
+        match tokio::time::timeout(self.config.artim_timeout(), stream.read_exact(fixed)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) | Err(_) => {
+                debug!("Connection from {} closed mid A-ASSOCIATE-RQ", peer_addr);
+                return Ok(());
+            }
+        }
+
+        let Some(header) = AssociateRequestHeader::parse(&buf) else {
+            warn!("Malformed A-ASSOCIATE-RQ from {}", peer_addr);
+            return Ok(());
+        };
+
+        if let Some(reason) = self.check_association(&header) {
+            warn!(
+                "Rejecting association from {} (calling={}, called={}): {:?}",
+                peer_addr, header.calling_aet, header.called_aet, reason
+            );
+            self.reject(&mut stream, reason).await;
+            return Ok(());
+        }
+
+        // Rest of the RQ: reserved fields and the variable items, bounded by the length check
+        let remaining = pdu_len as usize - (ASSOCIATE_RQ_HEADER_LEN - PDU_HEADER_LEN);
+        let mut rest = vec![0u8; remaining];
+        match tokio::time::timeout(self.idle_limit(), stream.read_exact(&mut rest)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(_)) => {
+                debug!("Connection from {} closed mid A-ASSOCIATE-RQ", peer_addr);
+                return Ok(());
+            }
+            Err(_) => {
+                warn!(
+                    "Aborting association with {}: A-ASSOCIATE-RQ incomplete after {:?}",
+                    peer_addr,
+                    self.idle_limit()
+                );
+                send_abort(&mut stream, 0, 0).await;
+                return Ok(());
+            }
+        }
+        let reserved =
+            (ASSOCIATE_RQ_FIXED_LEN as usize) - (ASSOCIATE_RQ_HEADER_LEN - PDU_HEADER_LEN);
+        if !variable_items_well_formed(&rest[reserved..]) {
+            warn!(
+                "Aborting association from {}: malformed A-ASSOCIATE-RQ items",
+                peer_addr
+            );
+            send_abort(&mut stream, ABORT_SOURCE_PROVIDER, ABORT_INVALID_PARAMETER).await;
+            return Ok(());
+        }
+
+        info!(
+            remote_aet = %header.calling_aet,
+            "Starting association with {} (calling={})",
+            peer_addr,
+            header.calling_aet
+        );
+        let association = AssociationInfo::new(
+            header.calling_aet.as_str(),
+            header.called_aet.as_str(),
+            self.config.max_pdu,
+            advertised_max_pdu(&rest[reserved..]),
+        );
+        tracing::Span::current().record(
+            "correlation_id",
+            tracing::field::display(association.correlation_id),
+        );
+        debug!(
+            remote_aet = %association.calling_aet,
+            "Negotiated max PDU length {} with {} (local {}, remote {:?})",
+            association.max_pdu_length,
+            peer_addr,
+            association.local_max_pdu,
+            association.remote_max_pdu
+        );
+
+        // Accept the first transfer syntax the peer proposed for each context that we allow
+        let allowed = self.config.transfer_syntax_proposal();
+        let contexts: Vec<ContextResult> = proposed_contexts(&rest[reserved..])
+            .into_iter()
+            .map(|context| self.answer_context(context, &allowed, peer_addr))
+            .collect();
+        let roles = negotiate_roles(
+            &role_selections(&rest[reserved..]),
+            &self.config.role_selection,
+        );
+        let ac = encode_associate_ac(
+            &header,
+            &contexts,
+            &Negotiation::roles(&roles),
+            self.config.max_pdu,
+        );
+        if let Err(e) = write_pdu(&mut stream, &ac).await {
+            debug!("Failed to send A-ASSOCIATE-AC to {}: {}", peer_addr, e);
+            return Ok(());
+        }
+        metrics::association_opened(metrics::SCP);
+        let peer_host = peer_addr.ip().to_string();
+        let association_event = |kind| {
+            AuditEvent::new(kind, AuditOutcome::Success)
+                .with_participant(&header.calling_aet, Some(&peer_host), true)
+                .with_participant(&self.config.local_aet, None, false)
+        };
+        self.audit(|| association_event(AuditEventKind::AssociationOpened))
+            .await;
 
-        // For now, just simulate handling requests via the router if available
         if let Some(router) = self.router.clone() {
             self.handle_router_requests(router).await?;
         }
 
-        info!("Association with {} completed", peer_addr);
+        self.serve_until_released(&mut stream, peer_addr, &association, &contexts)
+            .await;
+        self.audit(|| association_event(AuditEventKind::AssociationClosed))
+            .await;
+
+        info!(
+            remote_aet = %header.calling_aet,
+            "Association with {} completed",
+            peer_addr
+        );
         Ok(())
     }
 
+    /// Decide the result for one proposed presentation context
+    ///
+    /// Query/retrieve requests reach the SCP through the router rather than over the wire, so
+    /// their contexts are refused. Storage contexts are accepted, and verification and
+    /// Modality Worklist contexts when C-ECHO and C-FIND are enabled.
+    fn answer_context(
+        &self,
+        context: ProposedContext,
+        allowed: &[String],
+        peer_addr: SocketAddr,
+    ) -> ContextResult {
+        let supported = if context.abstract_syntax == VERIFICATION {
+            self.config.enable_echo
+        } else if context.abstract_syntax == MODALITY_WORKLIST_FIND {
+            self.config.enable_find
+        } else {
+            !context.abstract_syntax.starts_with(QUERY_RETRIEVE_PREFIX)
+        };
+        let transfer_syntax = select_transfer_syntax(&context.transfer_syntaxes, allowed);
+        let result = match (supported, transfer_syntax) {
+            (false, _) => {
+                debug!(
+                    "Presentation context {} ({}) from {}: abstract syntax not supported",
+                    context.id, context.abstract_syntax, peer_addr
+                );
+                CONTEXT_ABSTRACT_SYNTAX_NOT_SUPPORTED
+            }
+            (true, Some(ts)) => {
+                debug!(
+                    "Presentation context {} ({}) from {}: accepting transfer syntax {}",
+                    context.id, context.abstract_syntax, peer_addr, ts
+                );
+                CONTEXT_ACCEPTED
+            }
+            (true, None) => {
+                debug!(
+                    "Presentation context {} ({}) from {}: no acceptable transfer syntax in {:?}",
+                    context.id, context.abstract_syntax, peer_addr, context.transfer_syntaxes
+                );
+                CONTEXT_TRANSFER_SYNTAXES_NOT_SUPPORTED
+            }
+        };
+        ContextResult {
+            id: context.id,
+            transfer_syntax: transfer_syntax
+                .filter(|_| result == CONTEXT_ACCEPTED)
+                .map(str::to_string),
+            abstract_syntax: context.abstract_syntax,
+            result,
+        }
+    }
+
+    /// Keep the association open until the peer releases, aborts or hangs up
+    ///
+    /// Every PDU received restarts the clock, so long transfers are not cut off. A peer that
+    /// stays silent for longer than `max_association_lifetime` (or `association_timeout` when
+    /// no lifetime is configured) gets an A-ABORT and the connection is closed.
+    ///
+    /// DIMSE messages arriving in P-DATA-TF PDUs are reassembled and answered as they complete;
+    /// fragments that do not parse, or that use a context not accepted in `contexts`, abort
+    /// the association.
+    async fn serve_until_released(
+        &self,
+        stream: &mut Connection,
+        peer_addr: SocketAddr,
+        association: &AssociationInfo,
+        contexts: &[ContextResult],
+    ) {
+        let limit = self.idle_limit();
+        let mut throughput = self.config.slow_transfer.map(|c| {
+            ThroughputMonitor::new(format!("association with {}", peer_addr), c)
+                .with_role(metrics::SCP)
+        });
+        let mut assembler = MessageAssembler::default();
+        // Message IDs of requests this side sends, such as N-EVENT-REPORTs
+        let mut next_message_id: u16 = 1;
+
+        loop {
+            // Shutdown ends the association between messages; a partly received message is
+            // read to the end and answered first
+            let idle = assembler.is_idle();
+            if idle && self.shutdown.is_cancelled() {
+                self.release_for_shutdown(stream, peer_addr).await;
+                return;
+            }
+            let read = tokio::time::timeout(limit, read_pdu(stream, self.config.max_pdu));
+            let received = if idle {
+                tokio::select! {
+                    received = read => received,
+                    _ = self.shutdown.cancelled() => continue,
+                }
+            } else {
+                read.await
+            };
+            match received {
+                Ok(Ok((PDU_P_DATA_TF, body))) => {
+                    if let Some(monitor) = throughput.as_mut() {
+                        monitor.record(body.len() as u64);
+                    }
+                    let messages = match reassemble(&body, &mut assembler) {
+                        Ok(messages) => messages,
+                        Err(e) => {
+                            warn!(
+                                "Aborting association with {}: malformed P-DATA-TF ({})",
+                                peer_addr, e
+                            );
+                            send_abort(stream, ABORT_SOURCE_PROVIDER, ABORT_INVALID_PARAMETER)
+                                .await;
+                            return;
+                        }
+                    };
+                    for (context_id, mut command, data_set) in messages {
+                        let Some(transfer_syntax) = contexts
+                            .iter()
+                            .find(|c| c.id == context_id && c.result == CONTEXT_ACCEPTED)
+                            .and_then(|c| c.transfer_syntax.as_deref())
+                        else {
+                            warn!(
+                                "Aborting association with {}: message on unaccepted context {}",
+                                peer_addr, context_id
+                            );
+                            send_abort(stream, ABORT_SOURCE_PROVIDER, ABORT_INVALID_PARAMETER)
+                                .await;
+                            return;
+                        };
+                        if command.command_field & RESPONSE_BIT != 0 {
+                            debug!(
+                                "Response 0x{:04X} from {} with status {:?}",
+                                command.command_field, peer_addr, command.status
+                            );
+                            continue;
+                        }
+                        // Worklist matches are all sent as soon as they are found, so a
+                        // C-CANCEL-RQ has nothing left to stop, and it gets no response
+                        if command.command_field == C_CANCEL_RQ {
+                            debug!("C-CANCEL-RQ from {}", peer_addr);
+                            continue;
+                        }
+                        // An N-CREATE may leave the new instance's UID to the SCP, which then
+                        // returns it in the response
+                        if command.command_field == N_CREATE_RQ
+                            && command.affected_sop_instance_uid.is_none()
+                        {
+                            command.affected_sop_instance_uid =
+                                Some(format!("2.25.{}", uuid::Uuid::new_v4().as_u128()));
+                        }
+                        let started = std::time::Instant::now();
+                        // A C-FIND is answered by a pending response per match before its final one
+                        let (status, matches, follow_up) = if command.command_field == C_FIND_RQ {
+                            let (status, matches) = self
+                                .find_worklist(&command, data_set, transfer_syntax, peer_addr)
+                                .await;
+                            (status, matches, None)
+                        } else {
+                            let (status, follow_up) = self
+                                .dispatch_message(
+                                    &command,
+                                    data_set,
+                                    transfer_syntax,
+                                    association,
+                                    peer_addr,
+                                )
+                                .await;
+                            (status, Vec::new(), follow_up)
+                        };
+                        let operation = metrics::operation_name(command.command_field);
+                        metrics::operation(operation, metrics::SCP, started);
+                        if let DimseStatus::Failure(code) = DimseStatus::from_code(status) {
+                            metrics::failure(operation, Some(code));
+                        }
+                        let mut outgoing: Vec<(Command, Option<Vec<u8>>)> = matches
+                            .into_iter()
+                            .map(|identifier| {
+                                let mut pending = command.response(STATUS_PENDING);
+                                pending.has_data_set = true;
+                                (pending, Some(identifier))
+                            })
+                            .collect();
+                        outgoing.push((command.response(status), None));
+                        if let Some((mut request, data_set)) = follow_up {
+                            request.message_id = next_message_id;
+                            next_message_id = next_message_id.wrapping_add(1);
+                            outgoing.push((request, data_set));
+                        }
+                        for (command, data_set) in outgoing {
+                            let sent = send_message(
+                                stream,
+                                context_id,
+                                &command,
+                                data_set.as_deref(),
+                                association.max_pdu_length,
+                            )
+                            .await;
+                            if let Err(e) = sent {
+                                debug!("Failed to send to {}: {}", peer_addr, e);
+                                return;
+                            }
+                        }
+                    }
+                }
+                Ok(Ok((PDU_RELEASE_RQ, _))) => {
+                    debug!("A-RELEASE-RQ from {}", peer_addr);
+                    let _ = stream.write_all(&release_rp_pdu()).await;
+                    let _ = stream.shutdown().await;
+                    return;
+                }
+                Ok(Ok((PDU_ABORT, _))) => {
+                    debug!("A-ABORT from {}", peer_addr);
+                    return;
+                }
+                Ok(Ok(_)) => continue,
+                Ok(Err(e)) if e.kind() == std::io::ErrorKind::InvalidData => {
+                    warn!("Aborting association with {}: {}", peer_addr, e);
+                    send_abort(stream, ABORT_SOURCE_PROVIDER, ABORT_INVALID_PARAMETER).await;
+                    return;
+                }
+                Ok(Err(_)) => {
+                    debug!("Connection from {} closed", peer_addr);
+                    return;
+                }
+                Err(_) => {
+                    warn!(
+                        "Aborting association with {}: no activity for {:?}",
+                        peer_addr, limit
+                    );
+                    send_abort(stream, 0, 0).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Act on one complete DIMSE message
+    ///
+    /// Returns the status to respond with and, for requests answered by a request of our own
+    /// (storage commitment), the command and data set to send after the response.
+    async fn dispatch_message(
+        &self,
+        command: &Command,
+        data_set: Option<Vec<u8>>,
+        transfer_syntax: &str,
+        association: &AssociationInfo,
+        peer_addr: SocketAddr,
+    ) -> (u16, Option<(Command, Option<Vec<u8>>)>) {
+        let calling_aet = association.calling_aet.as_str();
+        match (command.command_field, data_set) {
+            (C_ECHO_RQ, _) => {
+                debug!("C-ECHO from {}", peer_addr);
+                (STATUS_SUCCESS, None)
+            }
+            (C_STORE_RQ, Some(data_set)) => {
+                // The audit record names the patient and study, read before the data set moves on
+                let subject = self
+                    .config
+                    .audit
+                    .as_ref()
+                    .and_then(|_| decode_data_set(&data_set, transfer_syntax).ok());
+                let status = self
+                    .store_received(command, data_set, transfer_syntax, association)
+                    .await;
+                self.audit(|| {
+                    let event = AuditEvent::new(
+                        AuditEventKind::InstancesTransferred,
+                        AuditOutcome::from_status(status),
+                    )
+                    .with_participant(calling_aet, Some(&peer_addr.ip().to_string()), true)
+                    .with_participant(&self.config.local_aet, None, false)
+                    .with_sop_class(command.affected_sop_class_uid.as_deref());
+                    match &subject {
+                        Some(object) => event.with_subject_of(object),
+                        None => event,
+                    }
+                })
+                .await;
+                (status, None)
+            }
+            (N_ACTION_RQ, Some(data_set))
+                if command.requested_sop_class_uid.as_deref() == Some(STORAGE_COMMITMENT_PUSH) =>
+            {
+                self.commit(command, &data_set, transfer_syntax, peer_addr)
+                    .await
+            }
+            (N_EVENT_REPORT_RQ, Some(data_set))
+                if command.affected_sop_class_uid.as_deref() == Some(STORAGE_COMMITMENT_PUSH) =>
+            {
+                let result = decode_data_set(&data_set, transfer_syntax)
+                    .and_then(|object| CommitmentResult::from_object(&object));
+                match result {
+                    Ok(result) => {
+                        info!(
+                            "Storage commitment report for {} from {}: {} committed, {} failed",
+                            result.transaction_uid,
+                            peer_addr,
+                            result.committed.len(),
+                            result.failed.len()
+                        );
+                        if !commitment::deliver(result) {
+                            debug!("No request was waiting for this commitment report");
+                        }
+                        (STATUS_SUCCESS, None)
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Malformed storage commitment report from {}: {}",
+                            peer_addr, e
+                        );
+                        (STATUS_PROCESSING_FAILURE, None)
+                    }
+                }
+            }
+            (N_CREATE_RQ, Some(data_set))
+                if command.affected_sop_class_uid.as_deref()
+                    == Some(MODALITY_PERFORMED_PROCEDURE_STEP) =>
+            {
+                let uid = command.affected_sop_instance_uid.as_deref();
+                let status = self
+                    .receive_mpps(MppsOperation::Create, uid, &data_set, transfer_syntax)
+                    .await;
+                (status, None)
+            }
+            (N_SET_RQ, Some(data_set))
+                if command.requested_sop_class_uid.as_deref()
+                    == Some(MODALITY_PERFORMED_PROCEDURE_STEP) =>
+            {
+                let uid = command.requested_sop_instance_uid.as_deref();
+                let status = self
+                    .receive_mpps(MppsOperation::Set, uid, &data_set, transfer_syntax)
+                    .await;
+                (status, None)
+            }
+            (C_STORE_RQ | N_ACTION_RQ | N_EVENT_REPORT_RQ | N_CREATE_RQ | N_SET_RQ, None) => {
+                warn!(
+                    "DIMSE command 0x{:04X} from {} carried no data set",
+                    command.command_field, peer_addr
+                );
+                (STATUS_PROCESSING_FAILURE, None)
+            }
+            (command_field, _) => {
+                warn!(
+                    "Unsupported DIMSE command 0x{:04X} from {}",
+                    command_field, peer_addr
+                );
+                (STATUS_UNRECOGNIZED_OPERATION, None)
+            }
+        }
+    }
+
+    /// Answer a Modality Worklist C-FIND from [`QueryProvider::worklist`]
+    ///
+    /// Returns the final status and the matches encoded in `transfer_syntax`, each to be sent
+    /// in its own pending response. Only the worklist context is accepted for C-FIND, so any
+    /// other SOP class is refused.
+    async fn find_worklist(
+        &self,
+        command: &Command,
+        data_set: Option<Vec<u8>>,
+        transfer_syntax: &str,
+        peer_addr: SocketAddr,
+    ) -> (u16, Vec<Vec<u8>>) {
+        if command.affected_sop_class_uid.as_deref() != Some(MODALITY_WORKLIST_FIND) {
+            return (STATUS_SOP_CLASS_NOT_SUPPORTED, Vec::new());
+        }
+        let identifier = match data_set.map(|data| decode_data_set(&data, transfer_syntax)) {
+            Some(Ok(identifier)) => identifier,
+            Some(Err(e)) => {
+                warn!(
+                    "Malformed worklist C-FIND identifier from {}: {}",
+                    peer_addr, e
+                );
+                return (STATUS_IDENTIFIER_DOES_NOT_MATCH, Vec::new());
+            }
+            None => return (STATUS_IDENTIFIER_DOES_NOT_MATCH, Vec::new()),
+        };
+        let query = WorklistQuery::from_identifier(&identifier);
+        debug!(
+            "Worklist C-FIND from {}: step keys {:?}",
+            peer_addr, query.scheduled_step
+        );
+        let items = match self.query_provider.worklist(&query).await {
+            Ok(items) => items,
+            Err(e) => {
+                warn!("Worklist query from {} failed: {}", peer_addr, e);
+                return (STATUS_UNABLE_TO_PROCESS, Vec::new());
+            }
+        };
+        let mut matches = Vec::with_capacity(items.len());
+        for item in items {
+            let encoded = match item.to_object().await {
+                Ok(object) => {
+                    encode_data_set(&object, transfer_syntax).map_err(DimseError::operation_failed)
+                }
+                Err(e) => Err(e),
+            };
+            match encoded {
+                Ok(data) => matches.push(data),
+                Err(e) => {
+                    warn!("Cannot send worklist item to {}: {}", peer_addr, e);
+                    return (STATUS_UNABLE_TO_PROCESS, Vec::new());
+                }
+            }
+        }
+        debug!(
+            "Worklist C-FIND from {}: {} match(es)",
+            peer_addr,
+            matches.len()
+        );
+        (STATUS_SUCCESS, matches)
+    }
+
+    /// Hand an MPPS N-CREATE or N-SET to the query provider and return the response status
+    async fn receive_mpps(
+        &self,
+        operation: MppsOperation,
+        sop_instance_uid: Option<&str>,
+        data_set: &[u8],
+        transfer_syntax: &str,
+    ) -> u16 {
+        let Some(sop_instance_uid) = sop_instance_uid else {
+            warn!("MPPS {:?} without a SOP Instance UID", operation);
+            return STATUS_INVALID_ATTRIBUTE_VALUE;
+        };
+        let object = match decode_data_set(data_set, transfer_syntax) {
+            Ok(object) => object,
+            Err(e) => {
+                warn!("Cannot parse MPPS {:?} data set: {}", operation, e);
+                return STATUS_PROCESSING_FAILURE;
+            }
+        };
+        let event = match MppsEvent::from_object(operation, sop_instance_uid, &object) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!(
+                    "Invalid MPPS {:?} for {}: {}",
+                    operation, sop_instance_uid, e
+                );
+                return STATUS_INVALID_ATTRIBUTE_VALUE;
+            }
+        };
+        info!(
+            "MPPS {:?} for {}: status {}",
+            operation,
+            sop_instance_uid,
+            event.status.map(|s| s.as_str()).unwrap_or("unchanged")
+        );
+        match self.query_provider.on_mpps(event).await {
+            Ok(()) => STATUS_SUCCESS,
+            Err(e) => {
+                warn!("on_mpps callback failed for {}: {}", sop_instance_uid, e);
+                STATUS_PROCESSING_FAILURE
+            }
+        }
+    }
+
+    /// Answer a storage commitment N-ACTION
+    ///
+    /// Each referenced instance is looked up through the query provider; the outcome goes back
+    /// to the requester as an N-EVENT-REPORT on the same association.
+    async fn commit(
+        &self,
+        command: &Command,
+        data_set: &[u8],
+        transfer_syntax: &str,
+        peer_addr: SocketAddr,
+    ) -> (u16, Option<(Command, Option<Vec<u8>>)>) {
+        if command.action_type_id != Some(ACTION_REQUEST_COMMIT) {
+            return (STATUS_NO_SUCH_ACTION_TYPE, None);
+        }
+        let request = match decode_data_set(data_set, transfer_syntax)
+            .and_then(|object| CommitmentRequest::from_object(&object))
+        {
+            Ok(request) => request,
+            Err(e) => {
+                warn!(
+                    "Malformed storage commitment request from {}: {}",
+                    peer_addr, e
+                );
+                return (STATUS_PROCESSING_FAILURE, None);
+            }
+        };
+        info!(
+            "Storage commitment request {} from {} for {} instance(s)",
+            request.transaction_uid,
+            peer_addr,
+            request.references.len()
+        );
+
+        let mut result = CommitmentResult {
+            transaction_uid: request.transaction_uid,
+            committed: vec![],
+            failed: vec![],
+        };
+        for (sop_class_uid, sop_instance_uid) in request.references {
+            let failure_reason = match self.query_provider.locate_instance(&sop_instance_uid).await
+            {
+                Ok(Some(instance)) => match instance.metadata().sop_class_uid.as_deref() {
+                    Some(held) if held.trim_end_matches('\0') != sop_class_uid => {
+                        Some(FAILURE_CLASS_INSTANCE_CONFLICT)
+                    }
+                    _ => None,
+                },
+                Ok(None) => Some(FAILURE_NO_SUCH_OBJECT_INSTANCE),
+                Err(e) => {
+                    warn!(
+                        "Cannot look up {} for storage commitment: {}",
+                        sop_instance_uid, e
+                    );
+                    Some(STATUS_PROCESSING_FAILURE)
+                }
+            };
+            match failure_reason {
+                None => result.committed.push((sop_class_uid, sop_instance_uid)),
+                Some(failure_reason) => result.failed.push(FailedReference {
+                    sop_class_uid,
+                    sop_instance_uid,
+                    failure_reason,
+                }),
+            }
+        }
+
+        let report = match encode_data_set(&result.to_object(), transfer_syntax) {
+            Ok(report) => report,
+            Err(e) => {
+                warn!("Cannot encode storage commitment report: {}", e);
+                return (STATUS_PROCESSING_FAILURE, None);
+            }
+        };
+        let event = Command {
+            command_field: N_EVENT_REPORT_RQ,
+            affected_sop_class_uid: Some(STORAGE_COMMITMENT_PUSH.to_string()),
+            affected_sop_instance_uid: Some(STORAGE_COMMITMENT_PUSH_INSTANCE.to_string()),
+            has_data_set: true,
+            event_type_id: Some(result.event_type_id()),
+            ..Default::default()
+        };
+        (STATUS_SUCCESS, Some((event, Some(report))))
+    }
+
+    /// Apply the store policy to an instance received over the wire and notify the provider
+    async fn store_received(
+        &self,
+        command: &Command,
+        data_set: Vec<u8>,
+        transfer_syntax: &str,
+        association: &AssociationInfo,
+    ) -> u16 {
+        let calling_aet = association.calling_aet.as_str();
+        metrics::store_bytes(metrics::SCP, data_set.len() as u64);
+        let sop_class_uid = command.affected_sop_class_uid.as_deref();
+        let policy = self.config.store_policy(sop_class_uid);
+        debug!(
+            operation = "C-STORE",
+            sop_class_uid = sop_class_uid.unwrap_or(""),
+            "Processing C-STORE: policy={:?}",
+            policy
+        );
+        if let StorePolicy::Refuse { status } = policy {
+            info!(
+                "Refusing C-STORE of SOP class {} with status 0x{:04X}",
+                sop_class_uid.unwrap_or("<unknown>"),
+                status
+            );
+            return status;
+        }
+
+        let file = match encode_part10(command, transfer_syntax, &data_set) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Cannot build file meta group for C-STORE: {}", e);
+                return STATUS_PROCESSING_FAILURE;
+            }
+        };
+        let size = file.len() as u64;
+        let mut instance = DatasetStream::from_bytes(file.into());
+        let metadata = instance.metadata_mut();
+        metadata.sop_class_uid = command.affected_sop_class_uid.clone();
+        metadata.sop_instance_uid = command.affected_sop_instance_uid.clone();
+        metadata.transfer_syntax = Some(transfer_syntax.to_string());
+        metadata.size_bytes = Some(size);
+
+        if let Some(router) = &self.router {
+            let request = DimseRequest::received_store(calling_aet, instance)
+                .with_correlation_id(association.correlation_id);
+            return match router.send_request(request).await {
+                Ok(DimseResponse {
+                    payload: DimseResponsePayload::Store { status, .. },
+                    ..
+                }) => status,
+                Ok(response) => {
+                    warn!(
+                        "C-STORE from {} was not stored by the router: {:?}",
+                        calling_aet, response.payload
+                    );
+                    STATUS_PROCESSING_FAILURE
+                }
+                Err(e) => {
+                    warn!("Routing C-STORE from {} failed: {}", calling_aet, e);
+                    STATUS_PROCESSING_FAILURE
+                }
+            };
+        }
+
+        let result = match policy {
+            StorePolicy::Forward => self.query_provider.forward(instance.clone()).await,
+            _ => self.query_provider.store(instance.clone()).await,
+        };
+        match result {
+            Ok(()) => {
+                if let Err(e) = self.query_provider.on_store(instance).await {
+                    warn!("on_store callback failed: {}", e);
+                }
+                STATUS_SUCCESS
+            }
+            Err(e) => {
+                warn!(
+                    "C-STORE of {} failed: {}",
+                    command
+                        .affected_sop_instance_uid
+                        .as_deref()
+                        .unwrap_or("<unknown>"),
+                    e
+                );
+                STATUS_PROCESSING_FAILURE
+            }
+        }
+    }
+
+    /// Write the audit record built by `event` when auditing is configured
+    async fn audit(&self, event: impl FnOnce() -> AuditEvent) {
+        if let Some(config) = &self.config.audit {
+            audit::emit(config, &self.config.local_aet, &event()).await;
+        }
+    }
+
+    /// Release an association with no operation in progress because the SCP is stopping
+    async fn release_for_shutdown(&self, stream: &mut Connection, peer_addr: SocketAddr) {
+        debug!("Releasing association with {} for shutdown", peer_addr);
+        if stream.write_all(&release_rq_pdu()).await.is_err() {
+            return;
+        }
+        let released = tokio::time::timeout(SHUTDOWN_RELEASE_TIMEOUT, async {
+            loop {
+                match read_pdu(stream, self.config.max_pdu).await {
+                    Ok((PDU_RELEASE_RP, _)) | Ok((PDU_ABORT, _)) | Err(_) => return,
+                    // Release collision: the peer asked at the same time
+                    Ok((PDU_RELEASE_RQ, _)) => {
+                        let _ = stream.write_all(&release_rp_pdu()).await;
+                        return;
+                    }
+                    Ok(_) => continue,
+                }
+            }
+        })
+        .await;
+        if released.is_err() {
+            debug!("No A-RELEASE-RP from {} before closing", peer_addr);
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    /// How long a peer may stay silent before the association is aborted
+    fn idle_limit(&self) -> std::time::Duration {
+        self.config
+            .max_association_lifetime()
+            .unwrap_or_else(|| self.config.association_timeout())
+    }
+
     /// Handle requests from the router (for testing and HTTP integration)
     async fn handle_router_requests(&self, _router: Arc<dyn Router>) -> Result<()> {
         // This is a placeholder - in a real implementation, we would need a different approach
@@ -178,9 +1223,14 @@ impl DimseScp {
         router: &Arc<dyn Router>,
     ) -> Result<()> {
         let request_id = request.id;
-        let _span =
-            span!(Level::DEBUG, "dimse_request", id = %request_id, command = ?request.command)
-                .entered();
+        let _span = span!(
+            Level::DEBUG,
+            "dimse_request",
+            id = %request_id,
+            correlation_id = %request.correlation_id,
+            command = ?request.command
+        )
+        .entered();
 
         match request.payload {
             DimseRequestPayload::Echo => {
@@ -196,8 +1246,15 @@ impl DimseScp {
 
             DimseRequestPayload::Find(ref query) => {
                 debug!(
-                    "Processing C-FIND request: level={}, params={:?}",
-                    query.query_level, query.parameters
+                    operation = "C-FIND",
+                    study_uid = query
+                        .parameters
+                        .get("0020000D")
+                        .map(|s| s.as_str())
+                        .unwrap_or(""),
+                    "Processing C-FIND request: level={}, keys={:?}",
+                    query.query_level,
+                    query.parameters.keys().collect::<Vec<_>>()
                 );
 
                 if !self.config.enable_find {
@@ -219,7 +1276,8 @@ impl DimseScp {
                         for (i, dataset) in datasets.iter().enumerate() {
                             let is_final = i == datasets.len() - 1;
                             let response =
-                                DimseResponse::find(request_id, Some(dataset.clone()), is_final);
+                                DimseResponse::find(request_id, Some(dataset.clone()), is_final)
+                                    .with_correlation_id(request.correlation_id);
 
                             if let Some(ref stream_tx) = request.stream_tx {
                                 stream_tx.send(response).await.map_err(|_| {
@@ -243,8 +1301,15 @@ impl DimseScp {
 
             DimseRequestPayload::Move(ref query) => {
                 debug!(
+                    operation = "C-MOVE",
+                    study_uid = query
+                        .parameters
+                        .get("0020000D")
+                        .map(|s| s.as_str())
+                        .unwrap_or(""),
                     "Processing C-MOVE request: level={}, dest={}",
-                    query.query_level, query.destination_aet
+                    query.query_level,
+                    query.destination_aet
                 );
 
                 if !self.config.enable_move {
@@ -283,15 +1348,40 @@ impl DimseScp {
             }
 
             DimseRequestPayload::Store(ref dataset) => {
-                debug!("Processing C-STORE request");
+                let sop_class_uid = dataset.metadata().sop_class_uid.as_deref();
+                let policy = self.config.store_policy(sop_class_uid);
+                debug!(
+                    operation = "C-STORE",
+                    sop_class_uid = sop_class_uid.unwrap_or(""),
+                    "Processing C-STORE request: policy={:?}",
+                    policy
+                );
 
-                match self.query_provider.store(dataset.clone()).await {
-                    Ok(()) => {
-                        let response = DimseResponse::store(request_id, true);
+                let result = match policy {
+                    StorePolicy::Store => self.query_provider.store(dataset.clone()).await,
+                    StorePolicy::Forward => self.query_provider.forward(dataset.clone()).await,
+                    StorePolicy::Refuse { status } => {
+                        info!(
+                            "Refusing C-STORE of SOP class {} with status 0x{:04X}",
+                            sop_class_uid.unwrap_or("<unknown>"),
+                            status
+                        );
+                        let response = DimseResponse::store_status(request_id, status);
                         self.send_response(request, response, router).await?;
+                        return Ok(());
                     }
-                    Err(e) => {
-                        let response = DimseResponse::error(request_id, e.to_string());
+                };
+
+                match result {
+                    Ok(()) => {
+                        if let Err(e) = self.query_provider.on_store(dataset.clone()).await {
+                            warn!("on_store callback failed: {}", e);
+                        }
+                        let response = DimseResponse::store(request_id, true);
+                        self.send_response(request, response, router).await?;
+                    }
+                    Err(e) => {
+                        let response = DimseResponse::error(request_id, e.to_string());
                         self.send_response(request, response, router).await?;
                     }
                 }
@@ -301,7 +1391,8 @@ impl DimseScp {
         Ok(())
     }
 
-    /// Send a response back through the appropriate channel
+    /// Send a response, tagged with the request's correlation ID, back through the appropriate
+    /// channel
     #[allow(dead_code)]
     async fn send_response(
         &self,
@@ -309,6 +1400,7 @@ impl DimseScp {
         response: DimseResponse,
         router: &Arc<dyn Router>,
     ) -> Result<()> {
+        let response = response.with_correlation_id(request.correlation_id);
         if let Some(response_tx) = request.response_tx {
             response_tx
                 .send(response)
@@ -320,6 +1412,108 @@ impl DimseScp {
     }
 }
 
+/// Write a whole PDU, flushing it through any TLS buffering
+pub(crate) async fn write_pdu<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    pdu: &[u8],
+) -> std::io::Result<()> {
+    stream.write_all(pdu).await?;
+    stream.flush().await
+}
+
+/// Send an A-ABORT and close the connection
+async fn send_abort<S: AsyncWrite + Unpin>(stream: &mut S, source: u8, reason: u8) {
+    let _ = stream.write_all(&abort_pdu(source, reason)).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Read one PDU and return its type and body
+///
+/// PDUs declaring more than `max_len` body bytes fail with `InvalidData` before any of the
+/// body is read.
+pub(crate) async fn read_pdu<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    max_len: u32,
+) -> std::io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; PDU_HEADER_LEN];
+    stream.read_exact(&mut header).await?;
+    let (pdu_type, len) = parse_pdu_header(&header);
+    if len > max_len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "PDU type 0x{:02X} declares {} bytes (max {})",
+                pdu_type, len, max_len
+            ),
+        ));
+    }
+
+    let mut body = vec![0u8; len as usize];
+    stream.read_exact(&mut body).await?;
+    Ok((pdu_type, body))
+}
+
+/// Send a command, followed by its data set if any, as P-DATA-TF PDUs
+pub(crate) async fn send_message<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    context_id: u8,
+    command: &Command,
+    data_set: Option<&[u8]>,
+    max_pdu: u32,
+) -> std::io::Result<()> {
+    for pdu in encode_p_data(context_id, true, &command.encode(), max_pdu) {
+        stream.write_all(&pdu).await?;
+    }
+    if let Some(data_set) = data_set {
+        for pdu in encode_p_data(context_id, false, data_set, max_pdu) {
+            stream.write_all(&pdu).await?;
+        }
+    }
+    stream.flush().await
+}
+
+/// Feed the PDVs of a P-DATA-TF body to `assembler` and return the messages it completes
+pub(crate) fn reassemble(
+    body: &[u8],
+    assembler: &mut MessageAssembler,
+) -> std::result::Result<Vec<Message>, String> {
+    let pdvs = parse_p_data(body).ok_or_else(|| "PDV lengths do not fit".to_string())?;
+    let mut messages = Vec::new();
+    for pdv in pdvs {
+        if let Some(message) =
+            assembler.push(pdv.context_id, pdv.is_command, pdv.is_last, pdv.data)?
+        {
+            messages.push(message);
+        }
+    }
+    Ok(messages)
+}
+
+/// Wrap a received data set in a DICOM Part 10 file: preamble, `DICM` and the file meta group
+pub(crate) fn encode_part10(
+    command: &Command,
+    transfer_syntax: &str,
+    data_set: &[u8],
+) -> std::result::Result<Vec<u8>, String> {
+    let meta = dicom_object::meta::FileMetaTableBuilder::new()
+        .media_storage_sop_class_uid(command.affected_sop_class_uid.clone().unwrap_or_default())
+        .media_storage_sop_instance_uid(
+            command
+                .affected_sop_instance_uid
+                .clone()
+                .unwrap_or_default(),
+        )
+        .transfer_syntax(transfer_syntax)
+        .implementation_class_uid(IMPLEMENTATION_CLASS_UID)
+        .implementation_version_name(IMPLEMENTATION_VERSION_NAME)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut file = vec![0u8; 128];
+    meta.write(&mut file).map_err(|e| e.to_string())?;
+    file.extend_from_slice(data_set);
+    Ok(file)
+}
+
 /// Default query provider implementation (for testing)
 pub struct DefaultQueryProvider {
     storage_dir: std::path::PathBuf,
@@ -383,6 +1577,1101 @@ mod tests {
         assert_eq!(scp.config.local_aet, "TEST_SCP");
     }
 
+    #[tokio::test]
+    async fn test_acl_denial_sends_configured_rejection() {
+        use crate::association::AssociateRejection;
+
+        // Reserve a free port (DimseConfig::validate rejects port 0)
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let mut config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            allowed_calling_aets: vec!["TRUSTED".to_string()],
+            ..Default::default()
+        };
+        // Transient rather than the default permanent so the configured value is observable
+        config.association_rejections.acl_denied = AssociateRejection::new(2, 1, 3);
+
+        let query_provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let scp = DimseScp::new(config, query_provider);
+        let server = tokio::spawn(scp.run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        let mut rq = vec![0x01, 0x00, 0, 0, 0, 0x44, 0x00, 0x01, 0x00, 0x00];
+        rq.extend_from_slice(b"TEST_SCP        ");
+        rq.extend_from_slice(b"UNTRUSTED       ");
+        stream.write_all(&rq).await.unwrap();
+
+        let mut rj = [0u8; 10];
+        stream.read_exact(&mut rj).await.unwrap();
+        assert_eq!(rj[0], 0x03, "expected A-ASSOCIATE-RJ");
+        assert_eq!(&rj[7..10], &[2, 1, 3], "result/source/reason");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_idle_association_aborted_after_lifetime() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            max_association_lifetime_ms: Some(200),
+            ..Default::default()
+        };
+        let query_provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let server =
+            tokio::spawn(DimseScp::new(config, query_provider).run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        let mut rq = vec![0x01, 0x00, 0, 0, 0, 0x44, 0x00, 0x01, 0x00, 0x00];
+        rq.extend_from_slice(b"TEST_SCP        ");
+        rq.extend_from_slice(b"IDLE_PEER       ");
+        stream.write_all(&rq).await.unwrap();
+        let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, crate::association::PDU_ASSOCIATE_AC);
+
+        // Stay silent: the SCP must abort well before this outer guard fires
+        let mut abort = [0u8; 10];
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            stream.read_exact(&mut abort),
+        )
+        .await
+        .expect("association should be terminated after its lifetime")
+        .unwrap();
+        assert_eq!(abort[0], PDU_ABORT, "expected A-ABORT");
+
+        let mut rest = [0u8; 1];
+        assert_eq!(
+            stream.read(&mut rest).await.unwrap(),
+            0,
+            "connection closed"
+        );
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_silent_connection_closed_after_artim_timeout() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            artim_timeout_ms: Some(200),
+            ..Default::default()
+        };
+        let query_provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let server =
+            tokio::spawn(DimseScp::new(config, query_provider).run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        // Never send an A-ASSOCIATE-RQ; the 5 minute association timeout must not apply
+        let mut buf = [0u8; 1];
+        let read = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut buf))
+            .await
+            .expect("connection should be closed by the ARTIM timer");
+        assert_eq!(read.unwrap_or(0), 0, "connection closed");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_associate_rq_aborted() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let query_provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let server =
+            tokio::spawn(DimseScp::new(config, query_provider).run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        // Claims a ~4 GiB body; the SCP must refuse it from the header alone
+        stream
+            .write_all(&[0x01, 0x00, 0xFF, 0xFF, 0xFF, 0xFF])
+            .await
+            .unwrap();
+
+        let mut abort = [0u8; 10];
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            stream.read_exact(&mut abort),
+        )
+        .await
+        .expect("oversized RQ should be aborted immediately")
+        .unwrap();
+        assert_eq!(
+            abort,
+            abort_pdu(ABORT_SOURCE_PROVIDER, ABORT_INVALID_PARAMETER)
+        );
+
+        let mut rest = [0u8; 1];
+        assert_eq!(
+            stream.read(&mut rest).await.unwrap(),
+            0,
+            "connection closed"
+        );
+
+        // The listener survives and keeps serving
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok());
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_store_policy_refuses_configured_sop_class() {
+        use crate::types::DimseCommand;
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        const SECONDARY_CAPTURE: &str = "1.2.840.10008.5.1.4.1.1.7";
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut config = DimseConfig {
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.store_policies.insert(
+            SECONDARY_CAPTURE.to_string(),
+            StorePolicy::Refuse { status: 0xA700 },
+        );
+        let query_provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let scp = DimseScp::new(config, query_provider);
+        let (sender, _receiver) = crate::InMemoryRouter::new().split();
+        let router: Arc<dyn Router> = Arc::new(sender);
+
+        let store = |sop_class_uid: &str| {
+            let mut dataset = DatasetStream::from_bytes(bytes::Bytes::from_static(b"DICM"));
+            dataset.metadata_mut().sop_class_uid = Some(sop_class_uid.to_string());
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            let request = DimseRequest {
+                id: uuid::Uuid::new_v4(),
+                correlation_id: uuid::Uuid::new_v4(),
+                command: DimseCommand::Store,
+                remote_node: None,
+                calling_aet: None,
+                payload: DimseRequestPayload::Store(dataset),
+                response_tx: Some(tx),
+                stream_tx: None,
+            };
+            (request, rx)
+        };
+
+        let (request, rx) = store(SECONDARY_CAPTURE);
+        scp.handle_dimse_request(request, &router).await.unwrap();
+        match rx.await.unwrap().payload {
+            DimseResponsePayload::Store { success, status } => {
+                assert!(!success);
+                assert_eq!(status, 0xA700);
+            }
+            other => panic!("expected C-STORE response, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        let (request, rx) = store(CT_IMAGE);
+        scp.handle_dimse_request(request, &router).await.unwrap();
+        match rx.await.unwrap().payload {
+            DimseResponsePayload::Store { success, status } => {
+                assert!(success);
+                assert_eq!(status, 0x0000);
+            }
+            other => panic!("expected C-STORE response, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_native_c_store_lands_in_storage_and_calls_on_store() {
+        use crate::association::{PDU_ASSOCIATE_AC, PDU_RELEASE_RP};
+        use crate::command::STATUS_SUCCESS;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        const IMPLICIT_VR_LE: &str = "1.2.840.10008.1.2";
+
+        /// Stores like the default provider and counts on_store calls
+        struct Recording {
+            inner: DefaultQueryProvider,
+            stored: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl QueryProvider for Recording {
+            async fn find(
+                &self,
+                query_level: QueryLevel,
+                parameters: &std::collections::HashMap<String, String>,
+                max_results: u32,
+            ) -> Result<Vec<DatasetStream>> {
+                self.inner.find(query_level, parameters, max_results).await
+            }
+
+            async fn locate(
+                &self,
+                query_level: QueryLevel,
+                parameters: &std::collections::HashMap<String, String>,
+            ) -> Result<Vec<DatasetStream>> {
+                self.inner.locate(query_level, parameters).await
+            }
+
+            async fn store(&self, dataset: DatasetStream) -> Result<()> {
+                self.inner.store(dataset).await
+            }
+
+            async fn on_store(&self, instance: DatasetStream) -> Result<()> {
+                assert_eq!(
+                    instance.metadata().sop_instance_uid.as_deref(),
+                    Some("1.2.3.4")
+                );
+                assert_eq!(instance.transfer_syntax(), Some(IMPLICIT_VR_LE));
+                self.stored.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let provider = Arc::new(Recording {
+            inner: DefaultQueryProvider::new(temp_dir.path().to_path_buf()),
+            stored: AtomicUsize::new(0),
+        });
+        let server =
+            tokio::spawn(DimseScp::new(config, provider.clone()).run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        let item = |item_type: u8, value: &[u8]| {
+            let mut item = vec![item_type, 0x00];
+            item.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            item.extend_from_slice(value);
+            item
+        };
+        let mut context = vec![0x01, 0x00, 0x00, 0x00];
+        context.extend(item(0x30, CT_IMAGE.as_bytes()));
+        context.extend(item(0x40, IMPLICIT_VR_LE.as_bytes()));
+        let mut items = item(0x10, b"1.2.840.10008.3.1.1.1");
+        items.extend(item(0x20, &context));
+        items.extend(item(0x50, &item(0x51, &16384u32.to_be_bytes())));
+
+        let mut rq = vec![0x01, 0x00];
+        rq.extend_from_slice(&(68 + items.len() as u32).to_be_bytes());
+        rq.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        rq.extend_from_slice(b"TEST_SCP        ");
+        rq.extend_from_slice(b"STORE_SCU       ");
+        rq.extend_from_slice(&[0u8; 32]);
+        rq.extend_from_slice(&items);
+        stream.write_all(&rq).await.unwrap();
+
+        let (pdu_type, ac) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_ASSOCIATE_AC);
+        // First item after the application context is the presentation context result
+        let context_ac = &ac[68 + 4 + 21..];
+        assert_eq!(context_ac[0], 0x21);
+        assert_eq!(context_ac[4], 0x01, "context id");
+        assert_eq!(context_ac[6], CONTEXT_ACCEPTED);
+
+        let request = Command {
+            command_field: C_STORE_RQ,
+            message_id: 1,
+            affected_sop_class_uid: Some(CT_IMAGE.to_string()),
+            affected_sop_instance_uid: Some("1.2.3.4".to_string()),
+            has_data_set: true,
+            ..Default::default()
+        };
+        // SOP Instance UID (0008,0018) in Implicit VR Little Endian
+        let mut data_set = vec![0x08, 0x00, 0x18, 0x00, 0x08, 0x00, 0x00, 0x00];
+        data_set.extend_from_slice(b"1.2.3.4\0");
+        // A tiny max PDU splits the command across fragments
+        for pdu in encode_p_data(1, true, &request.encode(), 16)
+            .into_iter()
+            .chain(encode_p_data(1, false, &data_set, 16384))
+        {
+            stream.write_all(&pdu).await.unwrap();
+        }
+
+        let (pdu_type, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_P_DATA_TF);
+        let pdvs = parse_p_data(&body).unwrap();
+        assert!(pdvs[0].is_command && pdvs[0].is_last);
+        let response = Command::parse(pdvs[0].data).unwrap();
+        assert_eq!(response.message_id, 1);
+        assert_eq!(response.status, Some(STATUS_SUCCESS));
+
+        stream
+            .write_all(&[0x05, 0x00, 0, 0, 0, 4, 0, 0, 0, 0])
+            .await
+            .unwrap();
+        let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_RELEASE_RP);
+
+        assert_eq!(provider.stored.load(Ordering::SeqCst), 1);
+        let stored: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(stored.len(), 1);
+        let file = std::fs::read(&stored[0]).unwrap();
+        assert_eq!(&file[128..132], b"DICM");
+        assert!(file.ends_with(&data_set));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_lets_in_progress_store_finish() {
+        use crate::association::{PDU_ASSOCIATE_AC, PDU_RELEASE_RQ};
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        const IMPLICIT_VR_LE: &str = "1.2.840.10008.1.2";
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            shutdown_grace_ms: 5_000,
+            ..Default::default()
+        };
+        let provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(DimseScp::new(config, provider).run(shutdown.clone()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        let item = |item_type: u8, value: &[u8]| {
+            let mut item = vec![item_type, 0x00];
+            item.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            item.extend_from_slice(value);
+            item
+        };
+        let mut context = vec![0x01, 0x00, 0x00, 0x00];
+        context.extend(item(0x30, CT_IMAGE.as_bytes()));
+        context.extend(item(0x40, IMPLICIT_VR_LE.as_bytes()));
+        let mut items = item(0x10, b"1.2.840.10008.3.1.1.1");
+        items.extend(item(0x20, &context));
+        items.extend(item(0x50, &item(0x51, &16384u32.to_be_bytes())));
+
+        let mut rq = vec![0x01, 0x00];
+        rq.extend_from_slice(&(68 + items.len() as u32).to_be_bytes());
+        rq.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        rq.extend_from_slice(b"TEST_SCP        ");
+        rq.extend_from_slice(b"STORE_SCU       ");
+        rq.extend_from_slice(&[0u8; 32]);
+        rq.extend_from_slice(&items);
+        stream.write_all(&rq).await.unwrap();
+        let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_ASSOCIATE_AC);
+
+        let request = Command {
+            command_field: C_STORE_RQ,
+            message_id: 1,
+            affected_sop_class_uid: Some(CT_IMAGE.to_string()),
+            affected_sop_instance_uid: Some("1.2.3.4".to_string()),
+            has_data_set: true,
+            ..Default::default()
+        };
+        let mut data_set = vec![0x08, 0x00, 0x18, 0x00, 0x08, 0x00, 0x00, 0x00];
+        data_set.extend_from_slice(b"1.2.3.4\0");
+        let mut pdus = encode_p_data(1, true, &request.encode(), 16384);
+        let mut data_pdus = encode_p_data(1, false, &data_set, 16);
+        assert!(data_pdus.len() > 1, "data set should span several PDUs");
+        let last = data_pdus.pop().unwrap();
+        pdus.extend(data_pdus);
+        for pdu in pdus {
+            stream.write_all(&pdu).await.unwrap();
+        }
+
+        // Shutdown arrives while the C-STORE is half received
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        shutdown.cancel();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!server.is_finished(), "SCP waits for the open association");
+        stream.write_all(&last).await.unwrap();
+
+        let (pdu_type, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_P_DATA_TF);
+        let pdvs = parse_p_data(&body).unwrap();
+        let response = Command::parse(pdvs[0].data).unwrap();
+        assert_eq!(response.status, Some(STATUS_SUCCESS));
+
+        // With the operation answered, the SCP releases the association itself
+        let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_RELEASE_RQ);
+        stream.write_all(&release_rp_pdu()).await.unwrap();
+
+        tokio::time::timeout(std::time::Duration::from_secs(2), server)
+            .await
+            .expect("SCP stops once drained")
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_storage_commitment_reports_committed_and_missing_instances() {
+        use crate::commitment::FAILURE_NO_SUCH_OBJECT_INSTANCE;
+        use crate::{DimseScu, RemoteNode};
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+        /// Holds a single CT instance
+        struct Archive;
+
+        #[async_trait]
+        impl QueryProvider for Archive {
+            async fn find(
+                &self,
+                _query_level: QueryLevel,
+                _parameters: &std::collections::HashMap<String, String>,
+                _max_results: u32,
+            ) -> Result<Vec<DatasetStream>> {
+                Ok(vec![])
+            }
+
+            async fn locate(
+                &self,
+                _query_level: QueryLevel,
+                parameters: &std::collections::HashMap<String, String>,
+            ) -> Result<Vec<DatasetStream>> {
+                if parameters.get("00080018").map(String::as_str) != Some("1.2.3.1") {
+                    return Ok(vec![]);
+                }
+                let mut instance = DatasetStream::from_bytes(bytes::Bytes::new());
+                instance.metadata_mut().sop_class_uid = Some(CT_IMAGE.to_string());
+                Ok(vec![instance])
+            }
+
+            async fn store(&self, _dataset: DatasetStream) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let server =
+            tokio::spawn(DimseScp::new(config, Arc::new(Archive)).run(CancellationToken::new()));
+        for _ in 0..40 {
+            if tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .is_ok()
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "COMMIT_SCU".to_string(),
+            dimse_timeout_ms: Some(5000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("TEST_SCP", "127.0.0.1", port);
+        let result = scu
+            .request_storage_commitment(
+                &node,
+                vec![
+                    (CT_IMAGE.to_string(), "1.2.3.1".to_string()),
+                    (CT_IMAGE.to_string(), "1.2.3.2".to_string()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.committed,
+            vec![(CT_IMAGE.to_string(), "1.2.3.1".to_string())]
+        );
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].sop_instance_uid, "1.2.3.2");
+        assert_eq!(
+            result.failed[0].failure_reason,
+            FAILURE_NO_SUCH_OBJECT_INSTANCE
+        );
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_mpps_create_and_set_reach_on_mpps() {
+        use crate::association::PDU_ASSOCIATE_AC;
+        use crate::command::{N_CREATE_RQ, N_SET_RQ};
+        use crate::mpps::{MppsStatus, MODALITY_PERFORMED_PROCEDURE_STEP};
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+
+        const IMPLICIT_VR_LE: &str = "1.2.840.10008.1.2";
+
+        /// Records every MPPS event
+        struct Ris {
+            events: std::sync::Mutex<Vec<MppsEvent>>,
+        }
+
+        #[async_trait]
+        impl QueryProvider for Ris {
+            async fn find(
+                &self,
+                _query_level: QueryLevel,
+                _parameters: &std::collections::HashMap<String, String>,
+                _max_results: u32,
+            ) -> Result<Vec<DatasetStream>> {
+                Ok(vec![])
+            }
+
+            async fn locate(
+                &self,
+                _query_level: QueryLevel,
+                _parameters: &std::collections::HashMap<String, String>,
+            ) -> Result<Vec<DatasetStream>> {
+                Ok(vec![])
+            }
+
+            async fn store(&self, _dataset: DatasetStream) -> Result<()> {
+                Ok(())
+            }
+
+            async fn on_mpps(&self, step: MppsEvent) -> Result<()> {
+                self.events.lock().unwrap().push(step);
+                Ok(())
+            }
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let provider = Arc::new(Ris {
+            events: std::sync::Mutex::new(vec![]),
+        });
+        let server =
+            tokio::spawn(DimseScp::new(config, provider.clone()).run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        let item = |item_type: u8, value: &[u8]| {
+            let mut item = vec![item_type, 0x00];
+            item.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            item.extend_from_slice(value);
+            item
+        };
+        let mut context = vec![0x01, 0x00, 0x00, 0x00];
+        context.extend(item(0x30, MODALITY_PERFORMED_PROCEDURE_STEP.as_bytes()));
+        context.extend(item(0x40, IMPLICIT_VR_LE.as_bytes()));
+        let mut items = item(0x10, b"1.2.840.10008.3.1.1.1");
+        items.extend(item(0x20, &context));
+
+        let mut rq = vec![0x01, 0x00];
+        rq.extend_from_slice(&(68 + items.len() as u32).to_be_bytes());
+        rq.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        rq.extend_from_slice(b"TEST_SCP        ");
+        rq.extend_from_slice(b"MODALITY        ");
+        rq.extend_from_slice(&[0u8; 32]);
+        rq.extend_from_slice(&items);
+        stream.write_all(&rq).await.unwrap();
+        let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_ASSOCIATE_AC);
+
+        let step = |status: &str| {
+            let mut object = dicom_object::InMemDicomObject::new_empty();
+            object.put(DataElement::new(
+                tags::PERFORMED_PROCEDURE_STEP_STATUS,
+                VR::CS,
+                PrimitiveValue::from(status),
+            ));
+            encode_data_set(&object, IMPLICIT_VR_LE).unwrap()
+        };
+
+        // N-CREATE without a SOP Instance UID: the SCP assigns one
+        let create = Command {
+            command_field: N_CREATE_RQ,
+            message_id: 1,
+            affected_sop_class_uid: Some(MODALITY_PERFORMED_PROCEDURE_STEP.to_string()),
+            has_data_set: true,
+            ..Default::default()
+        };
+        send_message(&mut stream, 1, &create, Some(&step("IN PROGRESS")), 16384)
+            .await
+            .unwrap();
+        let (_, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        let response = Command::parse(parse_p_data(&body).unwrap()[0].data).unwrap();
+        assert_eq!(response.command_field, N_CREATE_RQ | RESPONSE_BIT);
+        assert_eq!(response.status, Some(STATUS_SUCCESS));
+        let uid = response.affected_sop_instance_uid.unwrap();
+
+        let set = Command {
+            command_field: N_SET_RQ,
+            message_id: 2,
+            requested_sop_class_uid: Some(MODALITY_PERFORMED_PROCEDURE_STEP.to_string()),
+            requested_sop_instance_uid: Some(uid.clone()),
+            has_data_set: true,
+            ..Default::default()
+        };
+        send_message(&mut stream, 1, &set, Some(&step("COMPLETED")), 16384)
+            .await
+            .unwrap();
+        let (_, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        let response = Command::parse(parse_p_data(&body).unwrap()[0].data).unwrap();
+        assert_eq!(response.status, Some(STATUS_SUCCESS));
+
+        // A status outside the defined terms is refused without reaching the provider
+        send_message(&mut stream, 1, &set, Some(&step("PAUSED")), 16384)
+            .await
+            .unwrap();
+        let (_, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        let response = Command::parse(parse_p_data(&body).unwrap()[0].data).unwrap();
+        assert_eq!(response.status, Some(STATUS_INVALID_ATTRIBUTE_VALUE));
+
+        let events = provider.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].operation, MppsOperation::Create);
+        assert_eq!(events[0].status, Some(MppsStatus::InProgress));
+        assert_eq!(events[1].operation, MppsOperation::Set);
+        assert_eq!(events[1].sop_instance_uid, uid);
+        assert_eq!(events[1].status, Some(MppsStatus::Completed));
+        assert_eq!(events[1].attributes["00400252"]["Value"][0], "COMPLETED");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_worklist_find_answered_from_provider() {
+        use crate::association::{PDU_ASSOCIATE_AC, PDU_P_DATA_TF};
+        use dicom_core::value::DataSetSequence;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+
+        const IMPLICIT_VR_LE: &str = "1.2.840.10008.1.2";
+
+        /// Answers with one scheduled step and records the query it was asked
+        struct Ris {
+            queries: std::sync::Mutex<Vec<WorklistQuery>>,
+        }
+
+        #[async_trait]
+        impl QueryProvider for Ris {
+            async fn find(
+                &self,
+                _query_level: QueryLevel,
+                _parameters: &std::collections::HashMap<String, String>,
+                _max_results: u32,
+            ) -> Result<Vec<DatasetStream>> {
+                Ok(vec![])
+            }
+
+            async fn locate(
+                &self,
+                _query_level: QueryLevel,
+                _parameters: &std::collections::HashMap<String, String>,
+            ) -> Result<Vec<DatasetStream>> {
+                Ok(vec![])
+            }
+
+            async fn worklist(&self, query: &WorklistQuery) -> Result<Vec<DatasetStream>> {
+                self.queries.lock().unwrap().push(query.clone());
+                let mut step = dicom_object::InMemDicomObject::new_empty();
+                step.put(DataElement::new(
+                    tags::SCHEDULED_STATION_AE_TITLE,
+                    VR::AE,
+                    PrimitiveValue::from("CT01"),
+                ));
+                let mut item = dicom_object::InMemDicomObject::new_empty();
+                item.put(DataElement::new(
+                    tags::PATIENT_ID,
+                    VR::LO,
+                    PrimitiveValue::from("12345"),
+                ));
+                item.put(DataElement::new(
+                    tags::SCHEDULED_PROCEDURE_STEP_SEQUENCE,
+                    VR::SQ,
+                    DataSetSequence::from(vec![step]),
+                ));
+                Ok(vec![DatasetStream::from_object(item)])
+            }
+
+            async fn store(&self, _dataset: DatasetStream) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let provider = Arc::new(Ris {
+            queries: std::sync::Mutex::new(vec![]),
+        });
+        let server =
+            tokio::spawn(DimseScp::new(config, provider.clone()).run(CancellationToken::new()));
+
+        let mut stream = None;
+        for _ in 0..40 {
+            if let Ok(s) = tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+        let mut stream = stream.expect("SCP should accept connections");
+
+        let item = |item_type: u8, value: &[u8]| {
+            let mut item = vec![item_type, 0x00];
+            item.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            item.extend_from_slice(value);
+            item
+        };
+        let mut context = vec![0x01, 0x00, 0x00, 0x00];
+        context.extend(item(0x30, MODALITY_WORKLIST_FIND.as_bytes()));
+        context.extend(item(0x40, IMPLICIT_VR_LE.as_bytes()));
+        let mut items = item(0x10, b"1.2.840.10008.3.1.1.1");
+        items.extend(item(0x20, &context));
+
+        let mut rq = vec![0x01, 0x00];
+        rq.extend_from_slice(&(68 + items.len() as u32).to_be_bytes());
+        rq.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+        rq.extend_from_slice(b"TEST_SCP        ");
+        rq.extend_from_slice(b"CT01            ");
+        rq.extend_from_slice(&[0u8; 32]);
+        rq.extend_from_slice(&items);
+        stream.write_all(&rq).await.unwrap();
+        let (pdu_type, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+        assert_eq!(pdu_type, PDU_ASSOCIATE_AC);
+        let answered = crate::association::accepted_contexts(
+            &body[ASSOCIATE_RQ_FIXED_LEN as usize..],
+            &[ProposedContext {
+                id: 1,
+                abstract_syntax: MODALITY_WORKLIST_FIND.to_string(),
+                transfer_syntaxes: vec![IMPLICIT_VR_LE.to_string()],
+            }],
+        );
+        assert_eq!(answered[0].result, CONTEXT_ACCEPTED);
+
+        let identifier = WorklistQuery::new()
+            .with_station_aet("CT01")
+            .to_identifier()
+            .unwrap();
+        let find = Command {
+            command_field: C_FIND_RQ,
+            message_id: 7,
+            affected_sop_class_uid: Some(MODALITY_WORKLIST_FIND.to_string()),
+            has_data_set: true,
+            ..Default::default()
+        };
+        send_message(
+            &mut stream,
+            1,
+            &find,
+            Some(&encode_data_set(&identifier, IMPLICIT_VR_LE).unwrap()),
+            16384,
+        )
+        .await
+        .unwrap();
+
+        let mut assembler = MessageAssembler::default();
+        let mut responses = Vec::new();
+        while responses.len() < 2 {
+            let (pdu_type, body) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            assert_eq!(pdu_type, PDU_P_DATA_TF);
+            responses.extend(reassemble(&body, &mut assembler).unwrap());
+        }
+
+        let (_, pending, data_set) = &responses[0];
+        assert_eq!(pending.command_field, C_FIND_RQ | RESPONSE_BIT);
+        assert_eq!(pending.message_id, 7);
+        assert_eq!(pending.status, Some(STATUS_PENDING));
+        let matched = decode_data_set(data_set.as_ref().unwrap(), IMPLICIT_VR_LE).unwrap();
+        assert_eq!(
+            matched.element(tags::PATIENT_ID).unwrap().to_str().unwrap(),
+            "12345"
+        );
+        let (_, last, _) = &responses[1];
+        assert_eq!(last.status, Some(STATUS_SUCCESS));
+
+        let queries = provider.queries.lock().unwrap().clone();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].scheduled_step["00400001"], "CT01");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_configured_aet_routes_dispatch_stores_through_scp() {
+        use crate::config::RemoteNode;
+        use crate::router::AetRouter;
+        use crate::scu::DimseScu;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use futures::stream::BoxStream;
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+        /// Handler that records the calling AE title of every C-STORE under its own name
+        struct Pipeline {
+            name: String,
+            seen: Arc<std::sync::Mutex<Vec<(String, String)>>>,
+        }
+
+        #[async_trait]
+        impl Router for Pipeline {
+            async fn send_request(&self, request: DimseRequest) -> Result<DimseResponse> {
+                self.seen.lock().unwrap().push((
+                    self.name.clone(),
+                    request.calling_aet.clone().unwrap_or_default(),
+                ));
+                Ok(DimseResponse::store_status(request.id, STATUS_SUCCESS))
+            }
+
+            async fn send_streaming_request(
+                &self,
+                _request: DimseRequest,
+            ) -> Result<BoxStream<'static, DimseResponse>> {
+                Err(DimseError::operation_failed("not streaming"))
+            }
+
+            async fn next_request(&mut self) -> Result<DimseRequest> {
+                Err(DimseError::operation_failed("not receiving"))
+            }
+
+            async fn send_response(&self, _response: DimseResponse) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let routes: std::collections::HashMap<String, String> = [
+            ("C*", "c"),
+            ("CT_*", "ct"),
+            ("CT_ROOM1", "room1"),
+            ("*", "fallback"),
+        ]
+        .into_iter()
+        .map(|(aet, name)| (aet.to_string(), name.to_string()))
+        .collect();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let router = AetRouter::from_config(&routes, |name| {
+            Ok(Arc::new(Pipeline {
+                name: name.to_string(),
+                seen: seen.clone(),
+            }) as Arc<dyn Router>)
+        })
+        .unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = DimseConfig {
+            local_aet: "TEST_SCP".to_string(),
+            bind_addr: std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            port,
+            storage_dir: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn(
+            DimseScp::new(config, provider)
+                .with_router(Arc::new(router))
+                .run(shutdown.clone()),
+        );
+        for _ in 0..40 {
+            if tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .is_ok()
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+        }
+
+        let node = RemoteNode::new("TEST_SCP", "127.0.0.1", port);
+        for (i, calling_aet) in ["CT_ROOM1", "CT_ROOM2", "CR1", "MR1"].iter().enumerate() {
+            let mut object = dicom_object::InMemDicomObject::new_empty();
+            object.put(DataElement::new(
+                tags::SOP_CLASS_UID,
+                VR::UI,
+                PrimitiveValue::from(CT_IMAGE),
+            ));
+            object.put(DataElement::new(
+                tags::SOP_INSTANCE_UID,
+                VR::UI,
+                PrimitiveValue::from(format!("1.2.3.{}", i)),
+            ));
+            let scu = DimseScu::new(DimseConfig {
+                local_aet: calling_aet.to_string(),
+                ..Default::default()
+            });
+            let stored = scu
+                .store(&node, DatasetStream::from_object(object))
+                .await
+                .unwrap();
+            assert!(stored, "store from {} should succeed", calling_aet);
+        }
+
+        let seen = seen.lock().unwrap().clone();
+        let handled = |aet: &str| {
+            seen.iter()
+                .find(|(_, calling)| calling == aet)
+                .map(|(name, _)| name.clone())
+        };
+        assert_eq!(handled("CT_ROOM1").as_deref(), Some("room1"));
+        assert_eq!(handled("CT_ROOM2").as_deref(), Some("ct"));
+        assert_eq!(handled("CR1").as_deref(), Some("c"));
+        assert_eq!(handled("MR1").as_deref(), Some("fallback"));
+
+        shutdown.cancel();
+        let _ = server.await;
+    }
+
     #[test]
     fn test_default_query_provider() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -3,25 +3,87 @@
 use std::time::Duration;
 
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
-use crate::config::{DimseConfig, RemoteNode};
-use crate::types::{DatasetStream, FindQuery, MoveQuery};
+use crate::association::{
+    release_rp_pdu, AssociationInfo, ExtendedNegotiation, Negotiation, ProposedContext,
+    CONTEXT_ACCEPTED, PDU_ABORT, PDU_P_DATA_TF, PDU_RELEASE_RQ,
+};
+use crate::audit::{self, AuditEvent, AuditEventKind, AuditOutcome};
+use crate::breaker::{self, Permit};
+use crate::coercion::apply_rules;
+use crate::command::{
+    decode_data_set, encode_data_set, Command, MessageAssembler, SubOperations, C_CANCEL_RQ,
+    C_FIND_RQ, C_GET_RQ, C_STORE_RQ, N_ACTION_RQ, N_EVENT_REPORT_RQ, PRIORITY_MEDIUM, RESPONSE_BIT,
+    STATUS_PROCESSING_FAILURE, STATUS_SUCCESS,
+};
+use crate::commitment::{
+    self, CommitmentRequest, CommitmentResult, ACTION_REQUEST_COMMIT, STORAGE_COMMITMENT_PUSH,
+    STORAGE_COMMITMENT_PUSH_INSTANCE,
+};
+use crate::config::{
+    ClientTlsConfig, DimseConfig, DropBehavior, RemoteNode, RetryPolicy, DEFAULT_MAX_PDU,
+    MAX_PRESENTATION_CONTEXTS,
+};
+use crate::metrics;
+use crate::pool::{self, ScuAssociation};
+use crate::scp::{encode_part10, reassemble, VERIFICATION};
+#[cfg(feature = "dcmtk_cli")]
+use crate::throughput::ThroughputMonitor;
+use crate::types::{
+    DatasetStream, DimseStatus, FindQuery, GetReport, MoveQuery, MoveStream, NegotiatedContext,
+    PresentationContextProposal, QueryFeatures, SopClassSupport, PATIENT_ROOT_FIND,
+    STUDY_ROOT_FIND,
+};
+use crate::worklist::MODALITY_WORKLIST_FIND;
 use crate::{DimseError, Result};
 
+/// Patient Root Query/Retrieve Information Model - GET
+const PATIENT_ROOT_GET: &str = "1.2.840.10008.5.1.4.1.2.1.3";
+/// Study Root Query/Retrieve Information Model - GET
+const STUDY_ROOT_GET: &str = "1.2.840.10008.5.1.4.1.2.2.3";
+
 /// DIMSE Service Class User
 pub struct DimseScu {
     #[allow(dead_code)]
     config: DimseConfig, // TODO: Used for connection configuration
+    /// Correlation ID logged with every operation, see [`DimseScu::with_correlation_id`]
+    correlation_id: Option<uuid::Uuid>,
 }
 
 impl DimseScu {
     /// Create a new SCU with the given configuration
     pub fn new(config: DimseConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            correlation_id: None,
+        }
+    }
+
+    /// Log every operation under `correlation_id`
+    ///
+    /// Without one, each operation generates its own when it starts. Callers that already
+    /// carry an ID for the request, such as a pipeline, pass it here so the SCU's log lines
+    /// share it.
+    pub fn with_correlation_id(mut self, correlation_id: uuid::Uuid) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
+    /// The ID recorded on the span of an operation that is starting
+    fn correlation_id(&self) -> uuid::Uuid {
+        self.correlation_id.unwrap_or_else(uuid::Uuid::new_v4)
     }
 
     /// Send a C-ECHO request to a remote node
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "C-ECHO",
+        dimse.remote_ae = %node.ae_title,
+        net.peer.name = %node.host,
+        net.peer.port = node.port,
+        correlation_id = %self.correlation_id()
+    ))]
     pub async fn echo(&self, node: &RemoteNode) -> Result<bool> {
         info!(
             "Sending C-ECHO to {}@{}:{}",
@@ -31,6 +93,22 @@ impl DimseScu {
         // Validate the remote node configuration
         node.validate()?;
 
+        let permit = self.admit(node)?;
+        let started = std::time::Instant::now();
+        let result = with_retry(self.retry_policy(node), "C-ECHO", || self.echo_once(node)).await;
+        if let Some(permit) = permit {
+            permit.record(result.as_ref().err());
+        }
+        metrics::operation("C-ECHO", metrics::SCU, started);
+        match result {
+            Ok(_) => metrics::association_opened(metrics::SCU),
+            Err(_) => metrics::failure("C-ECHO", None),
+        }
+        result
+    }
+
+    /// One C-ECHO attempt
+    async fn echo_once(&self, node: &RemoteNode) -> Result<bool> {
         #[cfg(feature = "dcmtk_cli")]
         {
             use tokio::process::Command;
@@ -40,14 +118,24 @@ impl DimseScu {
                 .arg(&self.config.local_aet)
                 .arg("-aec")
                 .arg(&node.ae_title)
+                .args(self.association_args(node))
                 .arg(&node.host)
                 .arg(node.port.to_string());
             debug!(
                 "Running: echoscu -aet {} -aec {} {} {}",
                 self.config.local_aet, node.ae_title, node.host, node.port
             );
-            let output = cmd.output().await.map_err(|e| {
-                DimseError::operation_failed(format!("Failed to spawn echoscu: {}", e))
+            let output = run_dcmtk(
+                cmd,
+                self.config.max_association_lifetime(),
+                None,
+                None,
+                None,
+            )
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::TimedOut => DimseError::Timeout(e.to_string()),
+                _ => DimseError::operation_failed(format!("Failed to run echoscu: {}", e)),
             })?;
             if output.status.success() {
                 info!("C-ECHO completed successfully");
@@ -61,11 +149,15 @@ impl DimseScu {
                     stdout,
                     stderr
                 );
-                Err(DimseError::operation_failed(format!(
-                    "echoscu failed: {:?} {}",
-                    output.status.code(),
-                    stderr
-                )))
+                Err(dcmtk_association_failure("echoscu", &output)
+                    .or_else(|| dcmtk_timeout("echoscu", &output))
+                    .unwrap_or_else(|| {
+                        DimseError::operation_failed(format!(
+                            "echoscu failed: {:?} {}",
+                            output.status.code(),
+                            stderr
+                        ))
+                    }))
             }
         }
 
@@ -83,14 +175,265 @@ impl DimseScu {
         node: &RemoteNode,
         query: FindQuery,
     ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
+        self.find_with_cancel(node, query, CancellationToken::new())
+            .await
+    }
+
+    /// Send a C-FIND request to a remote node, abandoning it once `cancel` fires
+    ///
+    /// On a native association a cancelled query, or one whose result stream was dropped,
+    /// sends a C-CANCEL-FIND-RQ and discards the matches still arriving until the final
+    /// response; the association then goes back to the pool. With the DCMTK CLI the result
+    /// stream ends and the tool is handled according to the configured [`DropBehavior`].
+    pub async fn find_with_cancel(
+        &self,
+        node: &RemoteNode,
+        query: FindQuery,
+        cancel: CancellationToken,
+    ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
+        self.find_negotiated(node, query, cancel)
+            .await
+            .map(|(_, stream)| stream)
+    }
+
+    /// Send a C-FIND request, reporting which of the query's [`QueryFeatures`] the peer accepted
+    ///
+    /// Relational queries, combined date-time matching, fuzzy person name matching and
+    /// timezone adjustment requested on the [`FindQuery`] are proposed through SOP Class
+    /// Extended Negotiation for its information model. DCMTK `findscu` cannot negotiate them,
+    /// so a query asking for any of them runs over a native association: a pooled one with
+    /// `association_pool_ttl_ms` set, otherwise one opened for this query alone. Queries
+    /// without features and no pool go through `findscu`, which reports no accepted features.
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "C-FIND",
+        dimse.remote_ae = %node.ae_title,
+        net.peer.name = %node.host,
+        net.peer.port = node.port,
+        correlation_id = %self.correlation_id()
+    ))]
+    pub async fn find_negotiated(
+        &self,
+        node: &RemoteNode,
+        query: FindQuery,
+        cancel: CancellationToken,
+    ) -> Result<(
+        QueryFeatures,
+        tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>,
+    )> {
+        let level = match &query.worklist {
+            Some(_) => "WORKLIST".to_string(),
+            None => query.query_level.to_string(),
+        };
         info!(
             "Sending C-FIND to {}@{}:{} (level: {}, max_results: {})",
-            node.ae_title, node.host, node.port, query.query_level, query.max_results
+            node.ae_title, node.host, node.port, level, query.max_results
         );
 
         node.validate()?;
-        debug!("C-FIND query parameters: {:?}", query.parameters);
-        self.find_impl(node, query).await
+        match &query.worklist {
+            Some(worklist) => {
+                // Catch unknown keys before DCMTK does
+                worklist.to_identifier().map_err(DimseError::config)?;
+                // Keys only: values may be patient identifiers
+                debug!(
+                    "C-FIND worklist keys: {:?}, scheduled step keys: {:?}",
+                    worklist.parameters.keys().collect::<Vec<_>>(),
+                    worklist.scheduled_step.keys().collect::<Vec<_>>()
+                );
+            }
+            None => debug!(
+                "C-FIND query keys: {:?}",
+                query.parameters.keys().collect::<Vec<_>>()
+            ),
+        }
+        let permit = self.admit(node)?;
+        let event = self
+            .config
+            .audit
+            .is_some()
+            .then(|| self.query_audit_event(node, &query));
+        let ttl = self.config.association_pool_ttl();
+        let result = if ttl.is_some() || !query.features().is_empty() {
+            self.find_native(node, query, ttl, cancel).await
+        } else {
+            self.find_impl(node, query).await.map(|stream| {
+                (
+                    QueryFeatures::default(),
+                    forward_until_cancelled(stream, cancel),
+                )
+            })
+        };
+        if let Some(mut event) = event {
+            if result.is_err() {
+                event.outcome = AuditOutcome::SeriousFailure;
+            }
+            self.audit(|| event).await;
+        }
+        match permit {
+            Some(permit) => {
+                let features = result.as_ref().map(|(f, _)| *f).unwrap_or_default();
+                permit
+                    .record_stream(result.map(|(_, stream)| stream))
+                    .map(|stream| (features, stream))
+            }
+            None => result,
+        }
+    }
+
+    /// C-FIND over a native association
+    ///
+    /// With a pool `ttl` the association comes from the pool, opening one when the pool has
+    /// none for `node`; without one a new association is opened and released after the final
+    /// response. New associations propose both find models so worklist and query/retrieve
+    /// queries to the same peer share them, plus extended negotiation of the query's features
+    /// for its model; pooled associations are only reused for queries asking for the same
+    /// features. With a keep-alive interval the Verification context is proposed too. A pooled
+    /// association goes back to the pool once the final response arrives, even if the result
+    /// stream was dropped in the meantime.
+    async fn find_native(
+        &self,
+        node: &RemoteNode,
+        query: FindQuery,
+        ttl: Option<Duration>,
+        cancel: CancellationToken,
+    ) -> Result<(
+        QueryFeatures,
+        tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>,
+    )> {
+        let model = query.information_model();
+        let identifier = query.to_identifier().map_err(DimseError::config)?;
+        let local_aet = self.config.local_aet.clone();
+        let timeout = self.get_connection_timeout(node);
+        let requested = query.features();
+        let information = requested.to_bytes();
+        let extended = (!requested.is_empty()).then_some(information.as_slice());
+        let keepalive = self.config.association_keepalive();
+        let pooled = ttl.and_then(|ttl| pool::checkout(&local_aet, node, model, extended, ttl));
+        let mut association = match pooled {
+            Some(association) => association,
+            None => {
+                let transfer_syntaxes = self.config.transfer_syntax_proposal();
+                // The query's own model first, so a capped proposal still carries it
+                let mut models = vec![model];
+                models.extend(
+                    [PATIENT_ROOT_FIND, STUDY_ROOT_FIND, MODALITY_WORKLIST_FIND]
+                        .into_iter()
+                        .filter(|uid| *uid != model),
+                );
+                // Verification last, for the keep-alive echoes while the association is idle
+                if keepalive.is_some() {
+                    models.push(VERIFICATION);
+                }
+                let proposed: Vec<ProposedContext> = models
+                    .iter()
+                    .enumerate()
+                    .map(|(i, uid)| ProposedContext {
+                        id: (2 * i + 1) as u8,
+                        abstract_syntax: uid.to_string(),
+                        transfer_syntaxes: transfer_syntaxes.clone(),
+                    })
+                    .collect();
+                let negotiation = Negotiation {
+                    roles: Vec::new(),
+                    extended: extended
+                        .map(|information| ExtendedNegotiation {
+                            sop_class_uid: model.to_string(),
+                            application_information: information.to_vec(),
+                        })
+                        .into_iter()
+                        .collect(),
+                };
+                let max_pdu = self.get_max_pdu(node);
+                let tls = self.client_tls(node);
+                with_retry(self.retry_policy(node), "C-FIND association", || {
+                    ScuAssociation::open(
+                        &local_aet,
+                        node,
+                        &proposed,
+                        &negotiation,
+                        max_pdu,
+                        timeout,
+                        tls,
+                    )
+                })
+                .await?
+            }
+        };
+        let Some((context_id, transfer_syntax)) = association
+            .accepted(model)
+            .map(|(id, ts)| (id, ts.to_string()))
+        else {
+            association.release(timeout).await;
+            return Err(DimseError::AssociationRejected(format!(
+                "{} not accepted",
+                model
+            )));
+        };
+        let accepted =
+            QueryFeatures::from_bytes(association.extended_negotiation(model).unwrap_or_default());
+        if accepted != requested {
+            debug!(
+                "{} accepted {:?} of the requested {:?}",
+                node.ae_title, accepted, requested
+            );
+        }
+
+        let message_id = association.next_message_id();
+        let request = Command {
+            command_field: C_FIND_RQ,
+            message_id,
+            affected_sop_class_uid: Some(model.to_string()),
+            priority: Some(PRIORITY_MEDIUM),
+            has_data_set: true,
+            ..Default::default()
+        };
+        let data_set =
+            encode_data_set(&identifier, &transfer_syntax).map_err(DimseError::operation_failed)?;
+        association
+            .send(context_id, &request, Some(data_set.as_slice()))
+            .await?;
+
+        let (tx, rx) = mpsc::channel(100);
+        let wait = self
+            .config
+            .dimse_timeout()
+            .unwrap_or_else(|| self.config.association_timeout());
+        let node = node.clone();
+        let started = std::time::Instant::now();
+        tokio::spawn(async move {
+            let cancel = FindCancel {
+                token: cancel,
+                context_id,
+                message_id,
+            };
+            let result = receive_find_responses(&mut association, &tx, wait, cancel).await;
+            metrics::operation("C-FIND", metrics::SCU, started);
+            match result {
+                Ok(status) => {
+                    if let DimseStatus::Failure(code) = DimseStatus::from_code(status) {
+                        metrics::failure("C-FIND", Some(code));
+                        let _ = tx
+                            .send(Err(DimseError::operation_failed(format!(
+                                "C-FIND failed with status 0x{:04X}",
+                                code
+                            ))))
+                            .await;
+                    }
+                    match ttl {
+                        Some(ttl) => pool::checkin(&local_aet, &node, association, ttl, keepalive),
+                        None => association.release(timeout).await,
+                    }
+                }
+                Err(e) => {
+                    warn!("C-FIND to {} failed: {}", node.ae_title, e);
+                    metrics::failure("C-FIND", None);
+                    let _ = tx.send(Err(e)).await;
+                }
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+        Ok((accepted, stream))
     }
 
     #[cfg(feature = "dcmtk_cli")]
@@ -103,23 +446,39 @@ impl DimseScu {
         use uuid::Uuid;
 
         let mut args: Vec<String> = vec![
+            // Verbose output so per-response statuses (e.g. pending with warning) can be read back
+            "-v".into(),
             "-aet".into(),
             self.config.local_aet.clone(),
             "-aec".into(),
             node.ae_title.clone(),
-            // Use Patient Root (default) unless specified otherwise
-            "-P".into(),
         ];
+        args.extend(self.association_args(node));
 
-        // Set QueryRetrieveLevel via -k
-        let level_str = match query.query_level {
-            crate::types::QueryLevel::Patient => "PATIENT",
-            crate::types::QueryLevel::Study => "STUDY",
-            crate::types::QueryLevel::Series => "SERIES",
-            crate::types::QueryLevel::Image => "IMAGE",
-        };
-        args.push("-k".into());
-        args.push(format!("QueryRetrieveLevel={}", level_str));
+        if let Some(worklist) = &query.worklist {
+            // Modality Worklist information model; no QueryRetrieveLevel
+            args.push("-W".into());
+            for key in worklist.dcmtk_keys() {
+                args.push("-k".into());
+                args.push(key);
+            }
+        } else {
+            // Patient Root, or Study Root for relational queries below PATIENT level
+            args.push(match query.information_model() {
+                STUDY_ROOT_FIND => "-S".into(),
+                _ => "-P".into(),
+            });
+
+            // Set QueryRetrieveLevel via -k
+            let level_str = match query.query_level {
+                crate::types::QueryLevel::Patient => "PATIENT",
+                crate::types::QueryLevel::Study => "STUDY",
+                crate::types::QueryLevel::Series => "SERIES",
+                crate::types::QueryLevel::Image => "IMAGE",
+            };
+            args.push("-k".into());
+            args.push(format!("QueryRetrieveLevel={}", level_str));
+        }
 
         // Add keys from parameters
         for (k, v) in query.parameters.iter() {
@@ -158,12 +517,50 @@ impl DimseScu {
         debug!("Running findscu args: {:?}", args);
         let tx_clone = tx.clone();
         let out_dir_clone = out_dir.clone();
+        let lifetime = self.config.max_association_lifetime();
+        let (drop_behavior, drop_grace) = (self.config.drop_behavior, self.config.drop_grace());
+        let retry = self.retry_policy(node).clone();
+        let started = std::time::Instant::now();
         tokio::spawn(async move {
             let cleanup_dir;
-            match Command::new("findscu").args(&args).output().await {
-                Ok(out) => {
+            let (args, tx, out_dir) = (&args, &tx_clone, out_dir_clone.as_path());
+            let run = with_retry(&retry, "C-FIND", || async move {
+                let mut cmd = Command::new("findscu");
+                cmd.args(args);
+                let on_drop = DropWatch {
+                    tx,
+                    behavior: drop_behavior,
+                    grace: drop_grace,
+                };
+                let output = run_dcmtk(cmd, lifetime, Some(out_dir), None, Some(on_drop)).await;
+                match output
+                    .as_ref()
+                    .ok()
+                    .and_then(|out| dcmtk_association_failure("findscu", out))
+                {
+                    Some(e) => Err(e),
+                    None => Ok(output),
+                }
+            })
+            .await;
+            metrics::operation("C-FIND", metrics::SCU, started);
+            match run {
+                Err(e) => {
+                    warn!("findscu could not establish an association: {}", e);
+                    metrics::failure("C-FIND", None);
+                    let _ = tx_clone.send(Err(e)).await;
+                    cleanup_dir = out_dir_clone.clone();
+                }
+                Ok(Ok(out)) => {
                     if out.status.success() {
                         info!("C-FIND completed (findscu success)");
+                        metrics::association_opened(metrics::SCU);
+                        let log = format!(
+                            "{}\n{}",
+                            String::from_utf8_lossy(&out.stdout),
+                            String::from_utf8_lossy(&out.stderr)
+                        );
+                        let statuses = parse_find_response_statuses(&log);
                         // Read produced files and convert to in-memory streams immediately
                         if let Ok(mut rd) = tokio::fs::read_dir(&out_dir_clone).await {
                             while let Ok(Some(entry)) = rd.next_entry().await {
@@ -173,9 +570,15 @@ impl DimseScu {
                                     // Read file contents immediately to avoid race condition with cleanup
                                     if let Ok(bytes) = tokio::fs::read(&path).await {
                                         use bytes::Bytes;
-                                        let _ = tx_clone
-                                            .send(Ok(DatasetStream::from_bytes(Bytes::from(bytes))))
-                                            .await;
+                                        let mut ds = DatasetStream::from_bytes(Bytes::from(bytes));
+                                        // findscu -X names extracted responses rsp0001.dcm, rsp0002.dcm, ...
+                                        ds.metadata_mut().dimse_status = response_index(&path)
+                                            .and_then(|idx| statuses.get(&idx).copied());
+                                        metrics::find_matches(1);
+                                        if tx_clone.send(Ok(ds)).await.is_err() {
+                                            debug!("C-FIND stream dropped; discarding remaining results");
+                                            break;
+                                        }
                                     } else {
                                         warn!("Failed to read C-FIND result file: {:?}", path);
                                     }
@@ -192,11 +595,19 @@ impl DimseScu {
                             stdout,
                             stderr
                         );
+                        metrics::failure("C-FIND", None);
+                        if let Some(e) = dcmtk_timeout("findscu", &out) {
+                            let _ = tx_clone.send(Err(e)).await;
+                        }
                         cleanup_dir = out_dir_clone.clone();
                     }
                 }
-                Err(e) => {
-                    warn!("Failed to spawn findscu: {}", e);
+                Ok(Err(e)) => {
+                    warn!("Failed to run findscu: {}", e);
+                    metrics::failure("C-FIND", None);
+                    if e.kind() == std::io::ErrorKind::TimedOut {
+                        let _ = tx_clone.send(Err(DimseError::Timeout(e.to_string()))).await;
+                    }
                     cleanup_dir = out_dir_clone.clone();
                 }
             }
@@ -231,20 +642,65 @@ impl DimseScu {
     }
 
     /// Send a C-MOVE request to a remote node
+    ///
+    /// Retrieved instances are yielded one at a time as file-backed datasets through a bounded
+    /// channel, so a consumer that persists each instance before taking the next keeps memory
+    /// flat however many instances the move brings back. The sub-operation counts of the final
+    /// C-MOVE response arrive separately on [`MoveStream::sub_operations`].
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "C-MOVE",
+        dimse.remote_ae = %node.ae_title,
+        net.peer.name = %node.host,
+        net.peer.port = node.port,
+        correlation_id = %self.correlation_id()
+    ))]
     pub async fn move_request(
         &self,
         node: &RemoteNode,
         query: MoveQuery,
         output_dir: Option<std::path::PathBuf>,
-    ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
+    ) -> Result<MoveStream> {
         info!(
             "Sending C-MOVE to {}@{}:{} (level: {}, dest: {})",
             node.ae_title, node.host, node.port, query.query_level, query.destination_aet
         );
 
         node.validate()?;
-        debug!("C-MOVE query parameters: {:?}", query.parameters);
-        self.move_impl(node, query, output_dir).await
+        debug!(
+            "C-MOVE query keys: {:?}",
+            query.parameters.keys().collect::<Vec<_>>()
+        );
+        let permit = self.admit(node)?;
+        let (patient_id, study_uid) = audit_subject(&query.parameters);
+        let destination_aet = query.destination_aet.clone();
+        let (report_tx, sub_operations) = tokio::sync::oneshot::channel();
+        let result = self.move_impl(node, query, output_dir, report_tx).await;
+        self.audit(|| {
+            let outcome = match &result {
+                Ok(_) => AuditOutcome::Success,
+                Err(_) => AuditOutcome::SeriousFailure,
+            };
+            let mut event = AuditEvent::new(AuditEventKind::InstancesTransferred, outcome)
+                .with_participant(&node.ae_title, Some(&node.host), false)
+                .with_participant(
+                    &destination_aet,
+                    None,
+                    destination_aet == self.config.local_aet,
+                );
+            if destination_aet != self.config.local_aet {
+                event = event.with_participant(&self.config.local_aet, None, true);
+            }
+            event.with_subject(patient_id.as_deref(), study_uid.as_deref())
+        })
+        .await;
+        let instances = match permit {
+            Some(permit) => permit.record_stream(result),
+            None => result,
+        }?;
+        Ok(MoveStream {
+            instances,
+            sub_operations,
+        })
     }
 
     #[cfg(feature = "dcmtk_cli")]
@@ -253,6 +709,7 @@ impl DimseScu {
         node: &RemoteNode,
         query: MoveQuery,
         output_dir: Option<std::path::PathBuf>,
+        report: tokio::sync::oneshot::Sender<SubOperations>,
     ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
         use std::path::PathBuf;
         use tokio::process::Command;
@@ -269,10 +726,9 @@ impl DimseScu {
             self.config.local_aet.clone(),
             "-aec".into(),
             node.ae_title.clone(),
-            // Move destination AET (default to our local AET)
-            "-aem".into(),
-            query.destination_aet.clone(),
         ];
+        args.extend(self.association_args(node));
+        args.extend(self.retrieve_transfer_syntax_args());
 
         // QueryRetrieveLevel via tag form 0008,0052
         let level_str = match query.query_level {
@@ -325,12 +781,28 @@ impl DimseScu {
         }
 
         // Incoming C-STORE handling
-        // If using an external persistent Store SCP, do not open a transient listener (+P)
+        // If using an external persistent Store SCP, do not open a transient listener (+P).
+        // The lease keeps the port from other moves until this one has finished.
+        let mut store_port = None;
+        let mut destination_aet = query.destination_aet.clone();
         if !self.config.external_store_scp {
-            let listen_port: u16 = self.config.incoming_store_port;
+            let lease = crate::store_port::acquire(&self.config)?;
+            // A move to ourselves names the AE title the QR SCP knows at the leased port
+            if destination_aet == self.config.local_aet {
+                destination_aet = lease.ae_title().to_string();
+            }
+            info!(
+                "C-MOVE destination {} listens on port {}",
+                destination_aet,
+                lease.port()
+            );
             args.push("+P".into());
-            args.push(listen_port.to_string());
+            args.push(lease.port().to_string());
+            store_port = Some(lease);
         }
+        // Move destination AET (default to our local AET)
+        args.push("-aem".into());
+        args.push(destination_aet);
 
         // Host and port at the end
         args.push(node.host.clone());
@@ -344,10 +816,47 @@ impl DimseScu {
         let out_dir_clone = out_dir_opt.clone();
         let args_for_debug = args.clone();
         let storage_dir = self.config.storage_dir.clone();
+        let lifetime = self.config.max_association_lifetime();
+        let slow_transfer = self.config.slow_transfer;
+        let monitor_label = format!("C-MOVE from {}", node.ae_title);
+        let (drop_behavior, drop_grace) = (self.config.drop_behavior, self.config.drop_grace());
+        let retry = self.retry_policy(node).clone();
+        let started = std::time::Instant::now();
         tokio::spawn(async move {
             let mut cleanup_dir: Option<std::path::PathBuf> = None;
-            match Command::new("movescu").args(&args).output().await {
-                Ok(out) => {
+            let (args, tx, out_dir) = (&args, &tx_clone, out_dir_clone.as_deref());
+            let monitor_label = monitor_label.as_str();
+            // movescu only fails this way before the peer accepted the C-MOVE, so a retry
+            // never repeats sub-operations that already ran
+            let run = with_retry(&retry, "C-MOVE", || async move {
+                let mut cmd = Command::new("movescu");
+                cmd.args(args);
+                let throughput = slow_transfer.map(|c| ThroughputMonitor::new(monitor_label, c));
+                let on_drop = DropWatch {
+                    tx,
+                    behavior: drop_behavior,
+                    grace: drop_grace,
+                };
+                let output = run_dcmtk(cmd, lifetime, out_dir, throughput, Some(on_drop)).await;
+                match output
+                    .as_ref()
+                    .ok()
+                    .and_then(|out| dcmtk_association_failure("movescu", out))
+                {
+                    Some(e) => Err(e),
+                    None => Ok(output),
+                }
+            })
+            .await;
+            metrics::operation("C-MOVE", metrics::SCU, started);
+            match run {
+                Err(e) => {
+                    warn!("movescu could not establish an association: {}", e);
+                    metrics::failure("C-MOVE", None);
+                    let _ = tx_clone.send(Err(e)).await;
+                    cleanup_dir = out_dir_clone.clone();
+                }
+                Ok(Ok(out)) => {
                     let stdout = String::from_utf8_lossy(&out.stdout).to_string();
                     let stderr = String::from_utf8_lossy(&out.stderr).to_string();
                     // Write a debug artifact to storage_dir/dcmtk for test introspection
@@ -371,6 +880,12 @@ impl DimseScu {
 
                     if out.status.success() {
                         info!("C-MOVE completed (movescu success)");
+                        metrics::association_opened(metrics::SCU);
+                        if let Some(counts) =
+                            final_move_sub_operations(&format!("{}\n{}", stdout, stderr))
+                        {
+                            let _ = report.send(counts);
+                        }
                         // Enumerate received files only when we used a transient out_dir
                         if let Some(ref dir) = out_dir_clone {
                             if let Ok(mut rd) = tokio::fs::read_dir(dir).await {
@@ -378,13 +893,21 @@ impl DimseScu {
                                     let path = entry.path();
                                     if let Ok(meta) = tokio::fs::metadata(&path).await {
                                         if meta.is_file() {
-                                            // Only auto-cleanup files when using our own temp directory
-                                            let _ = tx_clone
-                                                .send(Ok(DatasetStream::from_file(
+                                            metrics::retrieved_instances("C-MOVE", 1);
+                                            // Only auto-cleanup files when using our own temp directory.
+                                            // The bounded channel holds enumeration back until the
+                                            // consumer has taken the previous instances.
+                                            if tx_clone
+                                                .send(Ok(DatasetStream::from_received_file(
                                                     path,
                                                     should_cleanup_move,
                                                 )))
-                                                .await;
+                                                .await
+                                                .is_err()
+                                            {
+                                                debug!("C-MOVE stream dropped; stopping");
+                                                break;
+                                            }
                                         }
                                     }
                                 }
@@ -400,15 +923,25 @@ impl DimseScu {
                             stdout,
                             stderr
                         );
+                        metrics::failure("C-MOVE", None);
+                        if let Some(e) = dcmtk_timeout("movescu", &out) {
+                            let _ = tx_clone.send(Err(e)).await;
+                        }
                         cleanup_dir = out_dir_clone.clone();
                     }
                 }
-                Err(e) => {
-                    warn!("Failed to spawn movescu: {}", e);
+                Ok(Err(e)) => {
+                    warn!("Failed to run movescu: {}", e);
+                    metrics::failure("C-MOVE", None);
+                    if e.kind() == std::io::ErrorKind::TimedOut {
+                        let _ = tx_clone.send(Err(DimseError::Timeout(e.to_string()))).await;
+                    }
                     cleanup_dir = out_dir_clone.clone();
                 }
             }
 
+            drop(store_port);
+
             // Only clean up directories that we created ourselves
             if should_cleanup_move {
                 if let Some(dir) = cleanup_dir {
@@ -432,6 +965,8 @@ impl DimseScu {
         &self,
         _node: &RemoteNode,
         _query: MoveQuery,
+        _output_dir: Option<std::path::PathBuf>,
+        _report: tokio::sync::oneshot::Sender<SubOperations>,
     ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
         // No CLI available; return empty stream
         let (_tx, rx) = mpsc::channel(0);
@@ -440,176 +975,245 @@ impl DimseScu {
     }
 
     /// Send a C-GET request to a remote node
+    ///
+    /// Proposes the GET model for the query level together with the storage SOP classes that
+    /// `role_selection` gives this node the SCP role for, offering those roles through SCP/SCU
+    /// Role Selection so the peer can send the matches back as C-STORE sub-operations on the
+    /// same association. Each instance is
+    /// written to `output_dir` as `<SOP Instance UID>.dcm`.
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "C-GET",
+        dimse.remote_ae = %node.ae_title,
+        net.peer.name = %node.host,
+        net.peer.port = node.port,
+        correlation_id = %self.correlation_id()
+    ))]
     pub async fn get_request(
         &self,
         node: &RemoteNode,
         query: crate::types::GetQuery,
-        output_dir: Option<std::path::PathBuf>,
-    ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
+        output_dir: &std::path::Path,
+    ) -> Result<GetReport> {
         info!(
             "Sending C-GET to {}@{}:{} (level: {})",
             node.ae_title, node.host, node.port, query.query_level,
         );
 
         node.validate()?;
-        debug!("C-GET query parameters: {:?}", query.parameters);
-        self.get_impl(node, query, output_dir).await
+        debug!(
+            "C-GET query keys: {:?}",
+            query.parameters.keys().collect::<Vec<_>>()
+        );
+        tokio::fs::create_dir_all(output_dir).await?;
+
+        let permit = self.admit(node)?;
+        let (patient_id, study_uid) = audit_subject(&query.parameters);
+        let started = std::time::Instant::now();
+        let result = match self.config.max_association_lifetime() {
+            Some(limit) => tokio::time::timeout(limit, self.get_impl(node, query, output_dir))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(DimseError::Timeout(format!(
+                        "C-GET association exceeded {:?}",
+                        limit
+                    )))
+                }),
+            None => self.get_impl(node, query, output_dir).await,
+        };
+        metrics::operation("C-GET", metrics::SCU, started);
+        if let Some(permit) = permit {
+            permit.record(result.as_ref().err());
+        }
+        self.audit(|| {
+            let outcome = match &result {
+                Ok(report) => AuditOutcome::from_status(report.status),
+                Err(_) => AuditOutcome::SeriousFailure,
+            };
+            AuditEvent::new(AuditEventKind::InstancesTransferred, outcome)
+                .with_participant(&node.ae_title, Some(&node.host), false)
+                .with_participant(&self.config.local_aet, None, true)
+                .with_subject(patient_id.as_deref(), study_uid.as_deref())
+        })
+        .await;
+        let report = result.inspect_err(|_| metrics::failure("C-GET", None))?;
+        metrics::retrieved_instances("C-GET", report.completed as u64);
+        if let DimseStatus::Failure(code) = DimseStatus::from_code(report.status) {
+            metrics::failure("C-GET", Some(code));
+        }
+        info!(
+            "C-GET finished with status 0x{:04X}: {} completed, {} failed, {} warning",
+            report.status, report.completed, report.failed, report.warning
+        );
+        Ok(report)
     }
 
-    #[cfg(feature = "dcmtk_cli")]
     async fn get_impl(
         &self,
         node: &RemoteNode,
         query: crate::types::GetQuery,
-        output_dir: Option<std::path::PathBuf>,
-    ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
-        use tokio::process::Command;
-        use uuid::Uuid;
-
-        let mut args: Vec<String> = Vec::new();
-
-        // Use Patient Root by default or Study Root as per query level
-        match query.query_level {
-            crate::types::QueryLevel::Patient => args.push("-P".into()),
-            crate::types::QueryLevel::Study
-            | crate::types::QueryLevel::Series
-            | crate::types::QueryLevel::Image => args.push("-S".into()),
-        }
-
-        // Calling and called AETs
-        args.push("-aet".into());
-        args.push(self.config.local_aet.clone());
-        args.push("-aec".into());
-        args.push(node.ae_title.clone());
-
-        // QueryRetrieveLevel
-        let level_str = match query.query_level {
-            crate::types::QueryLevel::Patient => "PATIENT",
-            crate::types::QueryLevel::Study => "STUDY",
-            crate::types::QueryLevel::Series => "SERIES",
-            crate::types::QueryLevel::Image => "IMAGE",
+        output_dir: &std::path::Path,
+    ) -> Result<GetReport> {
+        let identifier = query.to_identifier().map_err(DimseError::config)?;
+        let model = match query.query_level {
+            crate::types::QueryLevel::Patient => PATIENT_ROOT_GET,
+            _ => STUDY_ROOT_GET,
         };
-        args.push("-k".into());
-        args.push(format!("QueryRetrieveLevel={}", level_str));
-
-        // Add keys from parameters
-        for (k, v) in query.parameters.iter() {
-            let tag = if k.len() == 8 {
-                format!("{},{}", &k[0..4], &k[4..8])
-            } else {
-                k.clone()
-            };
-            args.push("-k".into());
-            if v.is_empty() {
-                args.push(format!("{}=", tag));
-            } else {
-                args.push(format!("{}={}", tag, v));
-            }
-        }
+        let transfer_syntaxes = self.config.transfer_syntax_proposal();
+        let cap = node
+            .max_presentation_contexts
+            .unwrap_or(MAX_PRESENTATION_CONTEXTS);
+        // The GET model takes one context; storage classes get the rest, odd IDs from 3
+        let storage_classes: Vec<String> = self
+            .config
+            .scp_role_sop_classes()
+            .into_iter()
+            .take(cap.saturating_sub(1))
+            .collect();
+        let mut proposed = vec![ProposedContext {
+            id: 1,
+            abstract_syntax: model.to_string(),
+            transfer_syntaxes: transfer_syntaxes.clone(),
+        }];
+        proposed.extend(
+            storage_classes
+                .iter()
+                .enumerate()
+                .map(|(i, uid)| ProposedContext {
+                    id: (2 * i + 3) as u8,
+                    abstract_syntax: uid.clone(),
+                    transfer_syntaxes: transfer_syntaxes.clone(),
+                }),
+        );
+        let timeout = self.get_connection_timeout(node);
+        let wait = self
+            .config
+            .dimse_timeout()
+            .unwrap_or_else(|| self.config.association_timeout());
+        let max_pdu = self.get_max_pdu(node);
+        let mut association = with_retry(self.retry_policy(node), "C-GET association", || {
+            ScuAssociation::open(
+                &self.config.local_aet,
+                node,
+                &proposed,
+                &Negotiation::roles(&self.config.role_selection),
+                max_pdu,
+                timeout,
+                self.client_tls(node),
+            )
+        })
+        .await?;
+        let (get_context, get_syntax) = association
+            .accepted(model)
+            .map(|(id, ts)| (id, ts.to_string()))
+            .ok_or_else(|| DimseError::AssociationRejected(format!("{} not accepted", model)))?;
+        debug!(
+            "C-GET association: {} of {} storage context(s) accepted, SCP role accepted for {}, max PDU {}",
+            association
+                .contexts
+                .iter()
+                .filter(|c| c.id != get_context && c.result == CONTEXT_ACCEPTED)
+                .count(),
+            storage_classes.len(),
+            association.roles.iter().filter(|role| role.scp).count(),
+            association.max_pdu
+        );
 
-        // Output directory for received objects
-        let (out_dir, should_cleanup) = if let Some(dir) = output_dir {
-            // Use caller-provided directory - don't clean up after use
-            if let Err(e) = tokio::fs::create_dir_all(&dir).await {
-                warn!("Failed to ensure output dir {:?}: {}", dir, e);
-            }
-            (dir, false)
-        } else {
-            // Create our own temporary directory - clean up after use
-            let dcmtk_base = self.config.storage_dir.join("dcmtk");
-            let tmp = dcmtk_base.join(format!("get_{}", Uuid::new_v4()));
-            if let Err(e) = tokio::fs::create_dir_all(&tmp).await {
-                warn!("Failed to create get output dir {:?}: {}", tmp, e);
-            }
-            (tmp, true)
+        let request = Command {
+            command_field: C_GET_RQ,
+            message_id: association.next_message_id(),
+            affected_sop_class_uid: Some(model.to_string()),
+            priority: Some(PRIORITY_MEDIUM),
+            has_data_set: true,
+            ..Default::default()
         };
-        args.push("-od".into());
-        args.push(out_dir.to_string_lossy().to_string());
-
-        // Host and port at the end
-        args.push(node.host.clone());
-        args.push(node.port.to_string());
-
-        // Prepare streaming channel
-        let (tx, rx) = mpsc::channel(100);
+        let data_set =
+            encode_data_set(&identifier, &get_syntax).map_err(DimseError::operation_failed)?;
+        association
+            .send(get_context, &request, Some(data_set.as_slice()))
+            .await?;
 
-        debug!("Running getscu args: {:?}", args);
-        let tx_clone = tx.clone();
-        let out_dir_clone = out_dir.clone();
-        tokio::spawn(async move {
-            let cleanup_dir;
-            match Command::new("getscu").args(&args).output().await {
-                Ok(out) => {
-                    if out.status.success() {
-                        info!("C-GET completed (getscu success)");
-                        // Enumerate received files and stream them back
-                        if let Ok(mut rd) = tokio::fs::read_dir(&out_dir_clone).await {
-                            while let Ok(Some(entry)) = rd.next_entry().await {
-                                let path = entry.path();
-                                if let Ok(meta) = tokio::fs::metadata(&path).await {
-                                    if meta.is_file() {
-                                        // Only auto-cleanup files when using our own temp directory
-                                        let _ = tx_clone
-                                            .send(Ok(DatasetStream::from_file(
-                                                path,
-                                                should_cleanup,
-                                            )))
-                                            .await;
+        let mut report = GetReport::default();
+        let mut assembler = MessageAssembler::default();
+        loop {
+            let (pdu_type, body) = tokio::time::timeout(wait, association.receive())
+                .await
+                .map_err(|_| {
+                    DimseError::Timeout(format!("No C-GET response from {}", node.ae_title))
+                })??;
+            match pdu_type {
+                PDU_P_DATA_TF => {}
+                PDU_RELEASE_RQ => {
+                    let _ = association.write_pdu(&release_rp_pdu()).await;
+                    return Err(DimseError::operation_failed(
+                        "Peer released before the final C-GET response",
+                    ));
+                }
+                PDU_ABORT => {
+                    return Err(DimseError::operation_failed("Peer aborted the C-GET"));
+                }
+                _ => continue,
+            }
+            let messages =
+                reassemble(&body, &mut assembler).map_err(DimseError::operation_failed)?;
+            for (context_id, command, data_set) in messages {
+                match command.command_field {
+                    C_STORE_RQ => {
+                        let status = match (association.transfer_syntax(context_id), data_set) {
+                            (Some(ts), Some(data_set)) => {
+                                match write_retrieved(output_dir, &command, ts, &data_set).await {
+                                    Ok(path) => {
+                                        report
+                                            .instances
+                                            .push(DatasetStream::from_received_file(path, false));
+                                        STATUS_SUCCESS
+                                    }
+                                    Err(e) => {
+                                        warn!("Failed to write C-GET instance: {}", e);
+                                        STATUS_PROCESSING_FAILURE
                                     }
                                 }
                             }
+                            _ => STATUS_PROCESSING_FAILURE,
+                        };
+                        let response = command.response(status);
+                        association.send(context_id, &response, None).await?;
+                    }
+                    field if field == C_GET_RQ | RESPONSE_BIT => {
+                        let status = command.status.unwrap_or(STATUS_SUCCESS);
+                        let counts = command.sub_operations.unwrap_or_default();
+                        if DimseStatus::from_code(status) == DimseStatus::Pending {
+                            debug!(
+                                "C-GET pending: {} remaining, {} completed",
+                                counts.remaining, counts.completed
+                            );
+                            continue;
                         }
-                        cleanup_dir = out_dir_clone.clone();
-                    } else {
-                        let stderr = String::from_utf8_lossy(&out.stderr);
-                        let stdout = String::from_utf8_lossy(&out.stdout);
-                        warn!(
-                            "getscu failed: status={:?}, stdout={}, stderr={}",
-                            out.status.code(),
-                            stdout,
-                            stderr
-                        );
-                        cleanup_dir = out_dir_clone.clone();
+                        report.status = status;
+                        report.completed = counts.completed;
+                        report.failed = counts.failed;
+                        report.warning = counts.warning;
+                        association.release(timeout).await;
+                        return Ok(report);
                     }
-                }
-                Err(e) => {
-                    warn!("Failed to spawn getscu: {}", e);
-                    cleanup_dir = out_dir_clone.clone();
+                    field => debug!("Ignoring DIMSE command 0x{:04X}", field),
                 }
             }
-
-            // Only clean up directories that we created ourselves
-            if should_cleanup {
-                if let Err(e) = tokio::fs::remove_dir_all(&cleanup_dir).await {
-                    warn!(
-                        "Failed to cleanup C-GET temp directory {:?}: {}",
-                        cleanup_dir, e
-                    );
-                } else {
-                    debug!("🧹 Cleaned up C-GET temp directory: {:?}", cleanup_dir);
-                }
-            }
-
-            // drop sender to close stream
-        });
-
-        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-        Ok(stream)
-    }
-
-    #[cfg(not(feature = "dcmtk_cli"))]
-    async fn get_impl(
-        &self,
-        _node: &RemoteNode,
-        _query: crate::types::GetQuery,
-    ) -> Result<tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>> {
-        // No CLI available; return empty stream
-        let (_tx, rx) = mpsc::channel(0);
-        let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-        Ok(stream)
+        }
     }
 
     /// Send a C-STORE request to a remote node
+    ///
+    /// The configured `store_coercion` rules are applied first. The instance is proposed in
+    /// the transfer syntax it was received in, when known, ahead of the configured ones and
+    /// encoded in whichever the peer accepts. Returns whether the peer reported success or a
+    /// warning.
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "C-STORE",
+        dimse.remote_ae = %node.ae_title,
+        net.peer.name = %node.host,
+        net.peer.port = node.port,
+        correlation_id = %self.correlation_id()
+    ))]
     pub async fn store(&self, node: &RemoteNode, dataset: DatasetStream) -> Result<bool> {
         info!(
             "Sending C-STORE to {}@{}:{}",
@@ -620,64 +1224,1223 @@ impl DimseScu {
         node.validate()?;
 
         debug!("C-STORE dataset: id={}", dataset.metadata().id);
+        let dataset = self.coerce_for_store(dataset).await?;
 
-        // TODO: Implement actual DICOM association and C-STORE
-        // This is a stub implementation
-
-        // Simulate sending the dataset
-        tokio::time::sleep(Duration::from_millis(300)).await;
-
-        info!("C-STORE completed successfully");
+        let permit = self.admit(node)?;
+        let started = std::time::Instant::now();
+        let result = self.store_impl(node, &dataset).await;
+        metrics::operation("C-STORE", metrics::SCU, started);
+        if let Some(permit) = permit {
+            permit.record(result.as_ref().err());
+        }
+        self.audit(|| {
+            let metadata = dataset.metadata();
+            let outcome = match &result {
+                Ok(status) => AuditOutcome::from_status(*status),
+                Err(_) => AuditOutcome::SeriousFailure,
+            };
+            AuditEvent::new(AuditEventKind::InstancesTransferred, outcome)
+                .with_participant(&self.config.local_aet, None, true)
+                .with_participant(&node.ae_title, Some(&node.host), false)
+                .with_sop_class(metadata.sop_class_uid.as_deref())
+                .with_subject(
+                    metadata.patient_id.as_deref(),
+                    metadata.study_instance_uid.as_deref(),
+                )
+        })
+        .await;
+        let status = result.inspect_err(|_| metrics::failure("C-STORE", None))?;
+        if let DimseStatus::Failure(code) = DimseStatus::from_code(status) {
+            metrics::failure("C-STORE", Some(code));
+            warn!(
+                "C-STORE to {} failed with status 0x{:04X}",
+                node.ae_title, code
+            );
+            return Ok(false);
+        }
+        info!("C-STORE completed with status 0x{:04X}", status);
         Ok(true)
     }
 
-    /// Test connectivity to a remote node with retry logic
-    pub async fn test_connection(&self, node: &RemoteNode, max_retries: u32) -> Result<bool> {
-        let mut retries = 0;
+    async fn store_impl(&self, node: &RemoteNode, dataset: &DatasetStream) -> Result<u16> {
+        use dicom_dictionary_std::tags;
 
-        while retries <= max_retries {
-            if retries > 0 {
-                info!("Connection test retry {} of {}", retries, max_retries);
-                tokio::time::sleep(Duration::from_secs(1 << retries)).await; // Exponential backoff
+        let object = dataset.to_object().await?;
+        let uid = |tag| {
+            object
+                .element(tag)
+                .ok()
+                .and_then(|e| e.to_str().ok())
+                .map(|s| s.trim_end_matches(['\0', ' ']).to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let metadata = dataset.metadata();
+        let missing =
+            |what: &str| DimseError::operation_failed(format!("C-STORE dataset has no {}", what));
+        let sop_class = uid(tags::SOP_CLASS_UID)
+            .or_else(|| metadata.sop_class_uid.clone())
+            .ok_or_else(|| missing("SOP Class UID"))?;
+        let sop_instance = uid(tags::SOP_INSTANCE_UID)
+            .or_else(|| metadata.sop_instance_uid.clone())
+            .ok_or_else(|| missing("SOP Instance UID"))?;
+
+        let mut transfer_syntaxes: Vec<String> = dataset
+            .transfer_syntax()
+            .map(str::to_string)
+            .into_iter()
+            .collect();
+        for ts in self.config.transfer_syntax_proposal() {
+            if !transfer_syntaxes.contains(&ts) {
+                transfer_syntaxes.push(ts);
             }
+        }
+        let proposed = [ProposedContext {
+            id: 1,
+            abstract_syntax: sop_class.clone(),
+            transfer_syntaxes,
+        }];
+        let timeout = self.get_connection_timeout(node);
+        let wait = self
+            .config
+            .dimse_timeout()
+            .unwrap_or_else(|| self.config.association_timeout());
+        let max_pdu = self.get_max_pdu(node);
+        let mut association = with_retry(self.retry_policy(node), "C-STORE association", || {
+            ScuAssociation::open(
+                &self.config.local_aet,
+                node,
+                &proposed,
+                &Negotiation::default(),
+                max_pdu,
+                timeout,
+                self.client_tls(node),
+            )
+        })
+        .await?;
+        let Some((context_id, transfer_syntax)) = association
+            .accepted(&sop_class)
+            .map(|(id, ts)| (id, ts.to_string()))
+        else {
+            association.release(timeout).await;
+            return Err(DimseError::AssociationRejected(format!(
+                "{} not accepted",
+                sop_class
+            )));
+        };
 
-            match self.echo(node).await {
-                Ok(_) => {
-                    info!("Connection test successful");
-                    return Ok(true);
+        let data_set =
+            encode_data_set(&object, &transfer_syntax).map_err(DimseError::operation_failed)?;
+        let request = Command {
+            command_field: C_STORE_RQ,
+            message_id: association.next_message_id(),
+            affected_sop_class_uid: Some(sop_class),
+            affected_sop_instance_uid: Some(sop_instance),
+            priority: Some(PRIORITY_MEDIUM),
+            has_data_set: true,
+            ..Default::default()
+        };
+        association
+            .send(context_id, &request, Some(data_set.as_slice()))
+            .await?;
+        metrics::store_bytes(metrics::SCU, data_set.len() as u64);
+
+        let mut assembler = MessageAssembler::default();
+        loop {
+            let (pdu_type, body) = tokio::time::timeout(wait, association.receive())
+                .await
+                .map_err(|_| {
+                    DimseError::Timeout(format!("No C-STORE response from {}", node.ae_title))
+                })??;
+            match pdu_type {
+                PDU_P_DATA_TF => {}
+                PDU_RELEASE_RQ => {
+                    let _ = association.write_pdu(&release_rp_pdu()).await;
+                    return Err(DimseError::operation_failed(
+                        "Peer released before the C-STORE response",
+                    ));
                 }
-                Err(e) if e.is_recoverable() && retries < max_retries => {
-                    warn!("Connection test failed (attempt {}): {}", retries + 1, e);
-                    retries += 1;
+                PDU_ABORT => return Err(DimseError::operation_failed("Peer aborted the C-STORE")),
+                _ => continue,
+            }
+            let messages =
+                reassemble(&body, &mut assembler).map_err(DimseError::operation_failed)?;
+            for (_, command, _) in messages {
+                if command.command_field != C_STORE_RQ | RESPONSE_BIT {
+                    debug!("Ignoring DIMSE command 0x{:04X}", command.command_field);
                     continue;
                 }
-                Err(e) => {
-                    error!("Connection test failed permanently: {}", e);
-                    return Err(e);
-                }
+                association.release(timeout).await;
+                return Ok(command.status.unwrap_or(STATUS_SUCCESS));
             }
         }
-
-        Err(DimseError::operation_failed(
-            "Connection test failed after all retries",
-        ))
     }
 
-    /// Get connection timeout for a node (uses node-specific or global setting)
-    #[allow(dead_code)]
-    fn get_connection_timeout(&self, node: &RemoteNode) -> Duration {
-        node.connect_timeout_ms
-            .map(Duration::from_millis)
-            .unwrap_or_else(|| self.config.connect_timeout())
-    }
+    /// Apply the configured `store_coercion` rules to a dataset about to be sent
+    ///
+    /// Datasets held as bytes or files are parsed first; the result is always an in-memory
+    /// object. Without rules the dataset is returned untouched.
+    async fn coerce_for_store(&self, dataset: DatasetStream) -> Result<DatasetStream> {
+        let rules = &self.config.store_coercion;
+        if rules.is_empty() {
+            return Ok(dataset);
+        }
 
-    /// Get maximum PDU size for a node (uses node-specific or global setting)
-    #[allow(dead_code)]
+        let coerce_failed = |e: String| {
+            DimseError::operation_failed(format!("Cannot coerce C-STORE dataset: {}", e))
+        };
+        let mut metadata = dataset.metadata().clone();
+        let mut object = match dataset {
+            DatasetStream::Object { object, .. } => object,
+            other => other
+                .to_object()
+                .await
+                .map_err(|e| coerce_failed(e.to_string()))?,
+        };
+        apply_rules(rules, &mut object).map_err(coerce_failed)?;
+
+        // A `map` rule may have changed these
+        let text = |name: &str| {
+            object
+                .element_by_name(name)
+                .ok()
+                .and_then(|e| e.to_str().ok())
+                .map(|s| s.trim_end_matches('\0').trim().to_string())
+        };
+        metadata.sop_instance_uid = text("SOPInstanceUID").or(metadata.sop_instance_uid);
+        metadata.patient_id = text("PatientID").or(metadata.patient_id);
+        debug!(
+            "Applied {} coercion rule(s) to C-STORE dataset {}",
+            rules.len(),
+            metadata.id
+        );
+
+        Ok(DatasetStream::Object { object, metadata })
+    }
+
+    /// Probe which presentation contexts a remote node accepts, without sending any data
+    ///
+    /// Opens an association proposing `contexts`, records which were accepted and with which
+    /// transfer syntax, then releases. Fails if the association itself is rejected, which
+    /// includes the remote node accepting none of the proposed contexts.
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "A-ASSOCIATE",
+        dimse.remote_ae = %node.ae_title,
+        net.peer.name = %node.host,
+        net.peer.port = node.port,
+        correlation_id = %self.correlation_id()
+    ))]
+    pub async fn verify_sop_class_support(
+        &self,
+        node: &RemoteNode,
+        contexts: &[PresentationContextProposal],
+    ) -> Result<SopClassSupport> {
+        info!(
+            "Probing {} presentation context(s) on {}@{}:{}",
+            contexts.len(),
+            node.ae_title,
+            node.host,
+            node.port
+        );
+
+        node.validate()?;
+        reject_tls(node, "SOP class probes")?;
+        if contexts.is_empty() {
+            return Err(DimseError::config(
+                "At least one presentation context must be proposed",
+            ));
+        }
+
+        let cap = node
+            .max_presentation_contexts
+            .unwrap_or(MAX_PRESENTATION_CONTEXTS);
+        let selected = select_proposals(contexts, cap);
+        if selected.len() < contexts.len() {
+            warn!(
+                "Proposing {} of {} presentation context(s) to {} (max_presentation_contexts={})",
+                selected.len(),
+                contexts.len(),
+                node.ae_title,
+                cap
+            );
+        }
+
+        let proposals: Vec<(String, Vec<String>)> = selected
+            .iter()
+            .map(|&i| &contexts[i])
+            .map(|c| {
+                let transfer_syntaxes = if c.transfer_syntaxes.is_empty() {
+                    self.config.transfer_syntax_proposal()
+                } else {
+                    c.transfer_syntaxes.clone()
+                };
+                (c.abstract_syntax.clone(), transfer_syntaxes)
+            })
+            .collect();
+        let all_syntaxes: Vec<String> =
+            contexts.iter().map(|c| c.abstract_syntax.clone()).collect();
+        let local_aet = self.config.local_aet.clone();
+        let called_aet = node.ae_title.clone();
+        let host = node.host.clone();
+        let port = node.port;
+        let max_pdu = self.get_max_pdu(node);
+        let timeout = self.get_connection_timeout(node);
+
+        // dicom-ul associations are blocking
+        tokio::task::spawn_blocking(move || {
+            use dicom_ul::association::client::ClientAssociationOptions;
+            use dicom_ul::pdu::PresentationContextResultReason;
+
+            let mut options = ClientAssociationOptions::new()
+                .calling_ae_title(local_aet.clone())
+                .called_ae_title(called_aet.clone())
+                .max_pdu_length(max_pdu)
+                .connection_timeout(timeout)
+                .read_timeout(timeout);
+            for (abstract_syntax, transfer_syntaxes) in &proposals {
+                options = options
+                    .with_presentation_context(abstract_syntax.clone(), transfer_syntaxes.clone());
+            }
+            let association = options.establish((host.as_str(), port)).map_err(|e| {
+                let message = e.to_string();
+                if message.to_lowercase().contains("timed out") {
+                    DimseError::Timeout(message)
+                } else {
+                    DimseError::AssociationRejected(message)
+                }
+            })?;
+
+            let info = AssociationInfo::new(
+                local_aet.as_str(),
+                called_aet.as_str(),
+                max_pdu,
+                Some(association.acceptor_max_pdu_length()),
+            );
+            debug!(
+                "Negotiated max PDU length {} with {} (local {}, remote {:?})",
+                info.max_pdu_length, info.called_aet, info.local_max_pdu, info.remote_max_pdu
+            );
+
+            let negotiated = association.presentation_contexts();
+            let contexts = all_syntaxes
+                .iter()
+                .enumerate()
+                .map(|(i, abstract_syntax)| {
+                    // Proposed context IDs are the odd numbers 1, 3, 5, ... in proposal order
+                    let position = selected.iter().position(|&s| s == i);
+                    let accepted = position.and_then(|p| {
+                        let id = (2 * p + 1) as u8;
+                        negotiated.iter().find(|pc| {
+                            pc.id == id
+                                && matches!(pc.reason, PresentationContextResultReason::Acceptance)
+                        })
+                    });
+                    NegotiatedContext {
+                        abstract_syntax: abstract_syntax.clone(),
+                        proposed: position.is_some(),
+                        accepted: accepted.is_some(),
+                        transfer_syntax: accepted
+                            .map(|pc| pc.transfer_syntax.trim_end_matches(['\0', ' ']).to_string()),
+                    }
+                })
+                .collect();
+
+            if let Err(e) = association.release() {
+                debug!("A-RELEASE after negotiation probe failed: {}", e);
+            }
+            Ok::<_, DimseError>(SopClassSupport {
+                contexts,
+                association: Some(info),
+            })
+        })
+        .await
+        .map_err(|e| DimseError::internal(format!("Negotiation probe task failed: {}", e)))?
+    }
+
+    /// Ask a remote node to commit to keeping the given instances
+    ///
+    /// `sop_refs` are (SOP Class UID, SOP Instance UID) pairs. Sends an N-ACTION on the Storage
+    /// Commitment Push Model and waits for the N-EVENT-REPORT with the outcome. Peers that
+    /// release before reporting send the report on a new association, which this process's
+    /// SCP hands over. The wait is bounded by `dimse_timeout_ms`, or the association timeout
+    /// when unset.
+    #[tracing::instrument(name = "dimse.scu", skip_all, fields(
+        dimse.operation = "N-ACTION",
+        dimse.remote_ae = %remote.ae_title,
+        net.peer.name = %remote.host,
+        net.peer.port = remote.port,
+        correlation_id = %self.correlation_id()
+    ))]
+    pub async fn request_storage_commitment(
+        &self,
+        remote: &RemoteNode,
+        sop_refs: Vec<(String, String)>,
+    ) -> Result<CommitmentResult> {
+        info!(
+            "Requesting storage commitment of {} instance(s) from {}@{}:{}",
+            sop_refs.len(),
+            remote.ae_title,
+            remote.host,
+            remote.port
+        );
+
+        remote.validate()?;
+        if sop_refs.is_empty() {
+            return Err(DimseError::config(
+                "At least one instance must be referenced",
+            ));
+        }
+
+        let request = CommitmentRequest::new(sop_refs);
+        let transaction_uid = request.transaction_uid.clone();
+        let wait = self
+            .config
+            .dimse_timeout()
+            .unwrap_or_else(|| self.config.association_timeout());
+
+        // Registered first so a report on a new association cannot arrive unclaimed
+        let mut reported_elsewhere = commitment::await_result(&transaction_uid);
+        let result = self
+            .commit_on_association(remote, &request, wait, &mut reported_elsewhere)
+            .await;
+        let result = match result {
+            Ok(Some(result)) => Ok(result),
+            // Released or aborted before reporting: the report comes on a new association
+            Ok(None) => match tokio::time::timeout(wait, reported_elsewhere).await {
+                Ok(Ok(result)) => Ok(result),
+                Ok(Err(_)) => Err(DimseError::internal("Storage commitment waiter dropped")),
+                Err(_) => Err(DimseError::Timeout(format!(
+                    "No storage commitment report for {} within {:?}",
+                    transaction_uid, wait
+                ))),
+            },
+            Err(e) => Err(e),
+        };
+        commitment::forget(&transaction_uid);
+
+        let result = result?;
+        info!(
+            "Storage commitment {}: {} committed, {} failed",
+            result.transaction_uid,
+            result.committed.len(),
+            result.failed.len()
+        );
+        Ok(result)
+    }
+
+    /// Send the N-ACTION for `request` and read its N-EVENT-REPORT from the same association
+    ///
+    /// Each wait for a PDU races `reported_elsewhere`, so a report delivered to our SCP on a
+    /// new association ends the exchange while this one is still open. Returns `None` when the
+    /// peer releases or aborts before reporting.
+    async fn commit_on_association(
+        &self,
+        remote: &RemoteNode,
+        request: &CommitmentRequest,
+        wait: Duration,
+        reported_elsewhere: &mut tokio::sync::oneshot::Receiver<CommitmentResult>,
+    ) -> Result<Option<CommitmentResult>> {
+        let proposed = [ProposedContext {
+            id: 1,
+            abstract_syntax: STORAGE_COMMITMENT_PUSH.to_string(),
+            transfer_syntaxes: self.config.transfer_syntax_proposal(),
+        }];
+        let timeout = self.get_connection_timeout(remote);
+        let mut association = ScuAssociation::open(
+            &self.config.local_aet,
+            remote,
+            &proposed,
+            &Negotiation::default(),
+            self.get_max_pdu(remote),
+            timeout,
+            self.client_tls(remote),
+        )
+        .await?;
+        let Some((context_id, transfer_syntax)) = association
+            .accepted(STORAGE_COMMITMENT_PUSH)
+            .map(|(id, ts)| (id, ts.to_string()))
+        else {
+            association.release(timeout).await;
+            return Err(DimseError::AssociationRejected(
+                "Storage Commitment Push Model not accepted".to_string(),
+            ));
+        };
+
+        let action = Command {
+            command_field: N_ACTION_RQ,
+            message_id: association.next_message_id(),
+            requested_sop_class_uid: Some(STORAGE_COMMITMENT_PUSH.to_string()),
+            requested_sop_instance_uid: Some(STORAGE_COMMITMENT_PUSH_INSTANCE.to_string()),
+            has_data_set: true,
+            action_type_id: Some(ACTION_REQUEST_COMMIT),
+            ..Default::default()
+        };
+        let data_set = encode_data_set(&request.to_object(), &transfer_syntax)
+            .map_err(DimseError::operation_failed)?;
+        association
+            .send(context_id, &action, Some(data_set.as_slice()))
+            .await?;
+
+        let mut assembler = MessageAssembler::default();
+        loop {
+            let received = tokio::select! {
+                received = tokio::time::timeout(wait, association.receive()) => received,
+                reported = &mut *reported_elsewhere => {
+                    debug!("Storage commitment report arrived on another association");
+                    association.release(timeout).await;
+                    return reported
+                        .map(Some)
+                        .map_err(|_| DimseError::internal("Storage commitment waiter dropped"));
+                }
+            };
+            let (pdu_type, body) = received.map_err(|_| {
+                DimseError::Timeout(format!(
+                    "No storage commitment report for {} within {:?}",
+                    request.transaction_uid, wait
+                ))
+            })??;
+            match pdu_type {
+                PDU_P_DATA_TF => {}
+                PDU_RELEASE_RQ => {
+                    debug!("Peer released before sending the commitment report");
+                    let _ = association.write_pdu(&release_rp_pdu()).await;
+                    return Ok(None);
+                }
+                PDU_ABORT => {
+                    debug!("Peer aborted before sending the commitment report");
+                    return Ok(None);
+                }
+                _ => continue,
+            }
+            let messages =
+                reassemble(&body, &mut assembler).map_err(DimseError::operation_failed)?;
+            for (_, command, data_set) in messages {
+                match command.command_field {
+                    field if field == N_ACTION_RQ | RESPONSE_BIT => {
+                        let status = command.status.unwrap_or(STATUS_SUCCESS);
+                        if status != STATUS_SUCCESS {
+                            association.release(timeout).await;
+                            return Err(DimseError::operation_failed(format!(
+                                "Storage commitment N-ACTION failed with status 0x{:04X}",
+                                status
+                            )));
+                        }
+                    }
+                    N_EVENT_REPORT_RQ => {
+                        let result = data_set
+                            .ok_or_else(|| "no data set".to_string())
+                            .and_then(|d| decode_data_set(&d, &transfer_syntax))
+                            .and_then(|object| CommitmentResult::from_object(&object))
+                            .map_err(|e| {
+                                DimseError::operation_failed(format!(
+                                    "Malformed storage commitment report: {}",
+                                    e
+                                ))
+                            })?;
+                        association
+                            .send(context_id, &command.response(STATUS_SUCCESS), None)
+                            .await?;
+                        association.release(timeout).await;
+                        return Ok(Some(result));
+                    }
+                    field => debug!("Ignoring DIMSE command 0x{:04X}", field),
+                }
+            }
+        }
+    }
+
+    /// Test connectivity to a remote node with retry logic
+    pub async fn test_connection(&self, node: &RemoteNode, max_retries: u32) -> Result<bool> {
+        let mut retries = 0;
+
+        while retries <= max_retries {
+            if retries > 0 {
+                info!("Connection test retry {} of {}", retries, max_retries);
+                tokio::time::sleep(Duration::from_secs(1 << retries)).await; // Exponential backoff
+            }
+
+            match self.echo(node).await {
+                Ok(_) => {
+                    info!("Connection test successful");
+                    return Ok(true);
+                }
+                Err(e) if e.is_recoverable() && retries < max_retries => {
+                    warn!("Connection test failed (attempt {}): {}", retries + 1, e);
+                    retries += 1;
+                    continue;
+                }
+                Err(e) => {
+                    error!("Connection test failed permanently: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(DimseError::operation_failed(
+            "Connection test failed after all retries",
+        ))
+    }
+
+    /// Client TLS settings for a node (uses node-specific or global setting)
+    fn client_tls<'a>(&'a self, node: &'a RemoteNode) -> Option<&'a ClientTlsConfig> {
+        node.tls.as_ref().or(self.config.client_tls.as_ref())
+    }
+
+    /// Write the audit record built by `event` when auditing is configured
+    async fn audit(&self, event: impl FnOnce() -> AuditEvent) {
+        if let Some(config) = &self.config.audit {
+            audit::emit(config, &self.config.local_aet, &event()).await;
+        }
+    }
+
+    /// Audit record of a C-FIND sent to `node`, carrying the encoded identifier
+    fn query_audit_event(&self, node: &RemoteNode, query: &FindQuery) -> AuditEvent {
+        let (patient_id, study_uid) = audit_subject(&query.parameters);
+        let mut event = AuditEvent::new(AuditEventKind::Query, AuditOutcome::Success)
+            .with_participant(&self.config.local_aet, None, true)
+            .with_participant(&node.ae_title, Some(&node.host), false)
+            .with_sop_class(Some(query.information_model()))
+            .with_subject(patient_id.as_deref(), study_uid.as_deref());
+        let encoded = query.to_identifier().and_then(|identifier| {
+            encode_data_set(&identifier, crate::config::IMPLICIT_VR_LITTLE_ENDIAN)
+        });
+        if let Ok(encoded) = encoded {
+            event = event.with_query(encoded);
+        }
+        event
+    }
+
+    /// Permission from the node's circuit breaker to run an operation, when one is configured
+    fn admit(&self, node: &RemoteNode) -> Result<Option<Permit>> {
+        self.config
+            .circuit_breaker
+            .as_ref()
+            .map(|config| breaker::admit(config, node))
+            .transpose()
+    }
+
+    /// Retry policy for a node (uses node-specific or global setting)
+    fn retry_policy(&self, node: &RemoteNode) -> &RetryPolicy {
+        node.retry.as_ref().unwrap_or(&self.config.retry)
+    }
+
+    /// Get connection timeout for a node (uses node-specific or global setting)
+    fn get_connection_timeout(&self, node: &RemoteNode) -> Duration {
+        node.connect_timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or_else(|| self.config.connect_timeout())
+    }
+
+    /// DCMTK options for an association with `node`: maximum PDU length and timeouts
+    ///
+    /// `-pdu` is only passed when a maximum PDU length other than the default is configured,
+    /// so the tools keep their own default otherwise. DCMTK advertises it in the
+    /// A-ASSOCIATE-RQ and sends PDUs no larger than the smaller of it and the peer's maximum.
+    /// Timeouts are whole seconds: the connect timeout bounds both the TCP connect (`-to`) and
+    /// the association negotiation (`-ta`), and `-td` is only passed when a DIMSE timeout is
+    /// configured.
+    #[cfg(feature = "dcmtk_cli")]
+    fn association_args(&self, node: &RemoteNode) -> Vec<String> {
+        let secs = |d: Duration| d.as_millis().div_ceil(1000).max(1).to_string();
+        let connect = secs(self.get_connection_timeout(node));
+        let mut args = Vec::new();
+        let max_pdu = self.get_max_pdu(node);
+        if node.max_pdu.is_some() || max_pdu != DEFAULT_MAX_PDU {
+            args.extend(["-pdu".into(), max_pdu.to_string()]);
+        }
+        args.extend(["-to".into(), connect.clone(), "-ta".into(), connect]);
+        if let Some(dimse) = self.config.dimse_timeout() {
+            args.extend(["-td".into(), secs(dimse)]);
+        }
+        if node.use_tls {
+            args.extend(self.tls_args(node));
+        }
+        args
+    }
+
+    /// DCMTK TLS options for `node`: `+tls` with the client key and certificate for mutual TLS,
+    /// otherwise `+tla`, and `+cf` for the CA bundle the server is verified against
+    #[cfg(feature = "dcmtk_cli")]
+    fn tls_args(&self, node: &RemoteNode) -> Vec<String> {
+        let path = |p: &std::path::Path| p.to_string_lossy().to_string();
+        let Some(tls) = self.client_tls(node) else {
+            return vec!["+tla".into()];
+        };
+        let mut args = match (&tls.key_path, &tls.cert_path) {
+            (Some(key), Some(cert)) => vec!["+tls".into(), path(key), path(cert)],
+            _ => vec!["+tla".into()],
+        };
+        args.extend(["+cf".into(), path(&tls.ca_bundle_path)]);
+        args
+    }
+
+    /// DCMTK preference flag for the instances a C-MOVE brings back
+    ///
+    /// DCMTK takes a single preferred syntax rather than a list, so the first entry of
+    /// `preferred_transfer_syntaxes` that it has a flag for is used. Implicit VR Little Endian
+    /// needs none: the tools always accept it, and `+xi` would refuse everything else.
+    #[cfg(feature = "dcmtk_cli")]
+    fn retrieve_transfer_syntax_args(&self) -> Vec<String> {
+        self.config
+            .preferred_transfer_syntaxes
+            .iter()
+            .take_while(|ts| ts.as_str() != crate::config::IMPLICIT_VR_LITTLE_ENDIAN)
+            .find_map(|ts| dcmtk_transfer_syntax_flag(ts))
+            .map(|flag| vec![flag.to_string()])
+            .unwrap_or_default()
+    }
+
+    /// Get maximum PDU size for a node (uses node-specific or global setting)
     fn get_max_pdu(&self, node: &RemoteNode) -> u32 {
         node.max_pdu.unwrap_or(self.config.max_pdu)
     }
 }
 
+/// Patient ID and Study Instance UID among query parameters, by tag or keyword
+fn audit_subject(
+    parameters: &std::collections::HashMap<String, String>,
+) -> (Option<String>, Option<String>) {
+    let value = |tag: &str, keyword: &str| {
+        parameters
+            .get(tag)
+            .or_else(|| parameters.get(keyword))
+            .cloned()
+    };
+    (
+        value("00100020", "PatientID"),
+        value("0020000D", "StudyInstanceUID"),
+    )
+}
+
+/// Refuse TLS nodes for operations that run over dicom-ul, which only speaks plain TCP
+fn reject_tls(node: &RemoteNode, operation: &str) -> Result<()> {
+    if node.use_tls {
+        return Err(DimseError::NotSupported(format!(
+            "{} over TLS ({})",
+            operation, node.ae_title
+        )));
+    }
+    Ok(())
+}
+
+/// Run `attempt` until it succeeds or fails in a way `policy` does not retry
+///
+/// A retry is only started when its backoff ends before the policy's deadline.
+async fn with_retry<T, F, Fut>(policy: &RetryPolicy, what: &str, mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let started = std::time::Instant::now();
+    let mut attempts = 1;
+    loop {
+        let error = match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        if attempts >= policy.max_attempts || !policy.retries(&error) {
+            return Err(error);
+        }
+        let delay = policy.backoff(attempts);
+        if policy
+            .deadline()
+            .is_some_and(|deadline| started.elapsed() + delay >= deadline)
+        {
+            debug!("{} not retried: the retry deadline would pass", what);
+            return Err(error);
+        }
+        warn!(
+            "{} attempt {} of {} failed, retrying in {:?}: {}",
+            what, attempts, policy.max_attempts, delay, error
+        );
+        tokio::time::sleep(delay).await;
+        attempts += 1;
+    }
+}
+
+/// Outstanding C-FIND to cancel when its token fires
+struct FindCancel {
+    token: CancellationToken,
+    context_id: u8,
+    message_id: u16,
+}
+
+/// Forward `stream` until `cancel` fires, then drop it
+fn forward_until_cancelled(
+    stream: tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>>,
+    cancel: CancellationToken,
+) -> tokio_stream::wrappers::ReceiverStream<Result<DatasetStream>> {
+    let mut inner = stream.into_inner();
+    let (tx, rx) = mpsc::channel(100);
+    tokio::spawn(async move {
+        loop {
+            let item = tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tx.closed() => break,
+                item = inner.recv() => item,
+            };
+            match item {
+                Some(item) if tx.send(item).await.is_ok() => {}
+                _ => break,
+            }
+        }
+    });
+    tokio_stream::wrappers::ReceiverStream::new(rx)
+}
+
+/// Read C-FIND responses until the final one and return its status
+///
+/// Each match is sent to `tx` as a Part 10 file in memory. Once `cancel` fires or the receiver
+/// is dropped a C-CANCEL-FIND-RQ is sent at once, even while a quiet peer has yet to send its
+/// next response, and the remaining matches are discarded. Responses keep being read until the final one, leaving the association idle
+/// and fit for reuse.
+async fn receive_find_responses(
+    association: &mut ScuAssociation,
+    tx: &mpsc::Sender<Result<DatasetStream>>,
+    wait: Duration,
+    cancel: FindCancel,
+) -> Result<u16> {
+    let mut assembler = MessageAssembler::default();
+    let mut cancelled = false;
+    loop {
+        if !cancelled && (cancel.token.is_cancelled() || tx.is_closed()) {
+            debug!(
+                "C-FIND abandoned; sending C-CANCEL for message {}",
+                cancel.message_id
+            );
+            let request = Command {
+                command_field: C_CANCEL_RQ,
+                message_id: cancel.message_id,
+                ..Default::default()
+            };
+            association.send(cancel.context_id, &request, None).await?;
+            cancelled = true;
+        }
+        // Receiving is cancel safe, so the wait for the next response can be cut short
+        let receive = tokio::time::timeout(wait, association.receive());
+        let received = if cancelled {
+            receive.await
+        } else {
+            tokio::select! {
+                received = receive => received,
+                _ = cancel.token.cancelled() => continue,
+                _ = tx.closed() => continue,
+            }
+        };
+        let (pdu_type, body) = received
+            .map_err(|_| DimseError::Timeout(format!("No C-FIND response within {:?}", wait)))??;
+        match pdu_type {
+            PDU_P_DATA_TF => {}
+            PDU_RELEASE_RQ => {
+                let _ = association.write_pdu(&release_rp_pdu()).await;
+                return Err(DimseError::operation_failed(
+                    "Peer released before the final C-FIND response",
+                ));
+            }
+            PDU_ABORT => return Err(DimseError::operation_failed("Peer aborted the C-FIND")),
+            _ => continue,
+        }
+        let messages = reassemble(&body, &mut assembler).map_err(DimseError::operation_failed)?;
+        for (context_id, command, data_set) in messages {
+            if command.command_field != C_FIND_RQ | RESPONSE_BIT {
+                debug!("Ignoring DIMSE command 0x{:04X}", command.command_field);
+                continue;
+            }
+            let status = command.status.unwrap_or(STATUS_SUCCESS);
+            if DimseStatus::from_code(status) != DimseStatus::Pending {
+                return Ok(status);
+            }
+            if cancelled || cancel.token.is_cancelled() {
+                continue;
+            }
+            let (Some(transfer_syntax), Some(data_set)) =
+                (association.transfer_syntax(context_id), data_set)
+            else {
+                continue;
+            };
+            let file = encode_part10(&command, transfer_syntax, &data_set)
+                .map_err(DimseError::operation_failed)?;
+            let mut ds = DatasetStream::from_bytes(bytes::Bytes::from(file));
+            ds.metadata_mut().transfer_syntax = Some(transfer_syntax.to_string());
+            ds.metadata_mut().dimse_status = Some(status);
+            metrics::find_matches(1);
+            if tx.send(Ok(ds)).await.is_err() {
+                debug!("C-FIND stream dropped; draining the remaining responses");
+            }
+        }
+    }
+}
+
+/// Write an instance received in a C-GET sub-operation to `dir` as a Part 10 file
+async fn write_retrieved(
+    dir: &std::path::Path,
+    command: &Command,
+    transfer_syntax: &str,
+    data_set: &[u8],
+) -> std::result::Result<std::path::PathBuf, String> {
+    // Only a well-formed UID is used as a file name
+    let name = command
+        .affected_sop_instance_uid
+        .clone()
+        .filter(|uid| !uid.is_empty() && uid.chars().all(|c| c.is_ascii_digit() || c == '.'))
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let path = dir.join(format!("{}.dcm", name));
+    let file = encode_part10(command, transfer_syntax, data_set)?;
+    tokio::fs::write(&path, file)
+        .await
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// movescu option preferring `uid` for received instances, if DCMTK has one
+#[cfg(feature = "dcmtk_cli")]
+fn dcmtk_transfer_syntax_flag(uid: &str) -> Option<&'static str> {
+    Some(match uid {
+        "1.2.840.10008.1.2.1" => "+xe",
+        "1.2.840.10008.1.2.2" => "+xb",
+        "1.2.840.10008.1.2.1.99" => "+xd",
+        "1.2.840.10008.1.2.4.50" => "+xy",
+        "1.2.840.10008.1.2.4.51" => "+xx",
+        "1.2.840.10008.1.2.4.70" => "+xs",
+        "1.2.840.10008.1.2.4.90" => "+xv",
+        "1.2.840.10008.1.2.4.91" => "+xw",
+        "1.2.840.10008.1.2.5" => "+xr",
+        _ => return None,
+    })
+}
+
+/// Result stream whose receiver `run_dcmtk` watches, and what to do once it is dropped
+#[cfg(feature = "dcmtk_cli")]
+struct DropWatch<'a> {
+    tx: &'a mpsc::Sender<Result<DatasetStream>>,
+    behavior: DropBehavior,
+    grace: Duration,
+}
+
+/// Run a DCMTK tool, killing it once the association has been idle for longer than `lifetime`
+///
+/// Activity is measured by new files appearing in `activity_dir` (received datasets or
+/// extracted responses), so a slow but progressing transfer keeps running. Killing the tool
+/// drops its TCP connection, which the remote side sees as an aborted association. When a
+/// `throughput` monitor is given, the bytes landing in `activity_dir` are fed to it on
+/// every tick; without an `activity_dir` nothing is measured and the monitor is dropped rather
+/// than fed zeros that would read as a stalled transfer. When `on_drop` is given and its stream's receiver goes away, the tool is
+/// aborted, left to finish, or given a grace period according to its [`DropBehavior`].
+#[cfg(feature = "dcmtk_cli")]
+async fn run_dcmtk(
+    mut cmd: tokio::process::Command,
+    lifetime: Option<Duration>,
+    activity_dir: Option<&std::path::Path>,
+    mut throughput: Option<ThroughputMonitor>,
+    on_drop: Option<DropWatch<'_>>,
+) -> std::io::Result<std::process::Output> {
+    use std::process::Stdio;
+
+    if throughput.is_some() && activity_dir.is_none() {
+        // Instances go to an external Store SCP, out of sight of this process
+        debug!(
+            "Not tracking {:?} throughput: no output directory to measure",
+            cmd.as_std().get_program()
+        );
+        throughput = None;
+    }
+    if lifetime.is_none() && throughput.is_none() && on_drop.is_none() {
+        return cmd.output().await;
+    }
+
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let output = child.wait_with_output();
+    tokio::pin!(output);
+
+    let grace = on_drop
+        .as_ref()
+        .filter(|w| w.behavior == DropBehavior::CancelThenRelease)
+        .map(|w| w.grace);
+    let tick = [lifetime, grace]
+        .into_iter()
+        .flatten()
+        .map(|d| (d / 4).clamp(Duration::from_millis(10), Duration::from_secs(1)))
+        .min()
+        .unwrap_or(Duration::from_secs(1));
+    let mut ticker = tokio::time::interval(tick);
+    let (mut seen, mut bytes_seen) = dir_usage(activity_dir).await;
+    let mut last_activity = tokio::time::Instant::now();
+
+    let stream_dropped = async {
+        match on_drop.as_ref() {
+            Some(watch) => watch.tx.closed().await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(stream_dropped);
+    let mut dropped_at: Option<tokio::time::Instant> = None;
+
+    loop {
+        tokio::select! {
+            result = &mut output => return result,
+            _ = &mut stream_dropped, if dropped_at.is_none() => {
+                let behavior = on_drop.as_ref().map(|w| w.behavior);
+                if behavior == Some(DropBehavior::Abort) {
+                    warn!(
+                        "Aborting {:?}: result stream dropped",
+                        cmd.as_std().get_program()
+                    );
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        "result stream dropped before completion",
+                    ));
+                }
+                // DCMTK tools cannot be sent a C-CANCEL, so the operation runs on and its
+                // results are discarded once it completes
+                debug!(
+                    "Result stream dropped; letting {:?} finish ({:?})",
+                    cmd.as_std().get_program(),
+                    behavior
+                );
+                dropped_at = Some(tokio::time::Instant::now());
+            }
+            _ = ticker.tick() => {
+                let overdue = |g: &Duration| dropped_at.is_some_and(|at| at.elapsed() >= *g);
+                if let Some(grace) = grace.filter(overdue) {
+                    warn!(
+                        "Aborting {:?}: still running {:?} after result stream was dropped",
+                        cmd.as_std().get_program(),
+                        grace
+                    );
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        format!("operation did not release within {:?} of stream drop", grace),
+                    ));
+                }
+                let (entries, bytes) = dir_usage(activity_dir).await;
+                if let Some(monitor) = throughput.as_mut() {
+                    monitor.record(bytes.saturating_sub(bytes_seen));
+                }
+                bytes_seen = bytes;
+                if entries != seen {
+                    seen = entries;
+                    last_activity = tokio::time::Instant::now();
+                } else if let Some(lifetime) = lifetime.filter(|l| last_activity.elapsed() >= *l) {
+                    // Dropping the pinned future drops the child, which kills it
+                    warn!(
+                        "Aborting {:?}: association idle for longer than {:?}",
+                        cmd.as_std().get_program(),
+                        lifetime
+                    );
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("association exceeded max lifetime of {:?}", lifetime),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// dcmnet condition codes (module `0006`) that decide how a failed DCMTK tool run is reported
+#[cfg(feature = "dcmtk_cli")]
+mod dcmnet {
+    /// DIMSE No data available (timeout in non-blocking mode): `-td` expired
+    pub const DIMSE_NO_DATA_AVAILABLE: u16 = 0x0207;
+    /// DUL network read timeout: `-ta` expired waiting for the A-ASSOCIATE-AC
+    pub const DUL_READ_TIMEOUT: u16 = 0x031a;
+    /// Failed to establish association
+    pub const DUL_REQUEST_ASSOCIATION_FAILED: u16 = 0x031b;
+    /// TCP Initialization Error, with the socket error as its text
+    pub const DUL_TCP_INIT_ERROR: u16 = 0x031c;
+}
+
+/// The dcmnet conditions a DCMTK tool logged, as code and text in log order
+///
+/// Conditions are logged as `0006:031b Failed to establish association`, the module and code
+/// in hex.
+#[cfg(feature = "dcmtk_cli")]
+fn dcmtk_conditions(log: &str) -> Vec<(u16, &str)> {
+    log.lines()
+        .filter_map(|line| {
+            let at = line.find("0006:")?;
+            let code = line.get(at + 5..at + 9)?;
+            if !code.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            Some((u16::from_str_radix(code, 16).ok()?, line[at + 9..].trim()))
+        })
+        .collect()
+}
+
+/// Whether a dcmnet condition means one of the tool's network timeouts expired
+///
+/// A TCP initialization error is a timeout only when its socket error is, as when `-to`
+/// expires before the connect completes.
+#[cfg(feature = "dcmtk_cli")]
+fn is_dcmtk_timeout((code, text): (u16, &str)) -> bool {
+    match code {
+        dcmnet::DIMSE_NO_DATA_AVAILABLE | dcmnet::DUL_READ_TIMEOUT => true,
+        dcmnet::DUL_TCP_INIT_ERROR => text.to_lowercase().contains("timed out"),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "dcmtk_cli")]
+fn dcmtk_log(output: &std::process::Output) -> String {
+    format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// A timeout error when a failed DCMTK tool reports that one of its network timeouts expired
+#[cfg(feature = "dcmtk_cli")]
+fn dcmtk_timeout(tool: &str, output: &std::process::Output) -> Option<DimseError> {
+    let log = dcmtk_log(output);
+    let (_, text) = dcmtk_conditions(&log)
+        .into_iter()
+        .find(|c| is_dcmtk_timeout(*c))?;
+    Some(DimseError::Timeout(format!("{}: {}", tool, text)))
+}
+
+/// Error for a DCMTK tool that never got an association accepted, if its log shows one
+///
+/// DCMTK reports a refused connection and a connect timeout alike as
+/// [`dcmnet::DUL_REQUEST_ASSOCIATION_FAILED`], so the conditions logged with it decide which
+/// error it maps to. A rejection carries no condition code: the tools log it as
+/// `Association Rejected:` followed by the reject parameters.
+#[cfg(feature = "dcmtk_cli")]
+fn dcmtk_association_failure(tool: &str, output: &std::process::Output) -> Option<DimseError> {
+    if output.status.success() {
+        return None;
+    }
+    let log = dcmtk_log(output);
+    let conditions = dcmtk_conditions(&log);
+    let rejected = log.lines().any(|l| l.contains("Association Rejected:"));
+    if !rejected
+        && !conditions
+            .iter()
+            .any(|(code, _)| *code == dcmnet::DUL_REQUEST_ASSOCIATION_FAILED)
+    {
+        return None;
+    }
+    let detail = format!("{}: {}", tool, log.trim().replace('\n', " "));
+    Some(if rejected {
+        DimseError::AssociationRejected(detail)
+    } else if conditions.iter().any(|c| is_dcmtk_timeout(*c)) {
+        DimseError::Timeout(detail)
+    } else {
+        DimseError::Network(std::io::Error::other(detail))
+    })
+}
+
+/// Sub-operation counts of the last C-MOVE response in a `movescu -d` log
+///
+/// Each incoming C-MOVE RSP is dumped with its Remaining/Completed/Failed/Warning Suboperations
+/// lines; a count the response left out is dumped as "none" and taken as zero.
+#[cfg(feature = "dcmtk_cli")]
+fn final_move_sub_operations(log: &str) -> Option<SubOperations> {
+    let last = log.rfind("C-MOVE RSP")?;
+    let mut counts = SubOperations::default();
+    for line in log[last..].lines() {
+        let Some((name, value)) = line
+            .split_once(':')
+            .and_then(|(_, rest)| rest.split_once(':'))
+        else {
+            continue;
+        };
+        let value = value.trim().parse().unwrap_or(0);
+        match name.trim() {
+            "Remaining Suboperations" => counts.remaining = value,
+            "Completed Suboperations" => counts.completed = value,
+            "Failed Suboperations" => counts.failed = value,
+            "Warning Suboperations" => counts.warning = value,
+            _ => {}
+        }
+    }
+    Some(counts)
+}
+
+/// Number of entries in `dir` and the total size of the files among them
+#[cfg(feature = "dcmtk_cli")]
+async fn dir_usage(dir: Option<&std::path::Path>) -> (usize, u64) {
+    let Some(dir) = dir else { return (0, 0) };
+    let Ok(mut rd) = tokio::fs::read_dir(dir).await else {
+        return (0, 0);
+    };
+    let (mut count, mut bytes) = (0, 0);
+    while let Ok(Some(entry)) = rd.next_entry().await {
+        count += 1;
+        if let Ok(meta) = entry.metadata().await {
+            if meta.is_file() {
+                bytes += meta.len();
+            }
+        }
+    }
+    (count, bytes)
+}
+
+/// Indices of the contexts to propose when at most `cap` fit, in proposal order
+///
+/// The first context for each abstract syntax is kept ahead of further contexts for an
+/// abstract syntax already covered, so a cap drops alternative encodings before whole SOP
+/// classes.
+fn select_proposals(contexts: &[PresentationContextProposal], cap: usize) -> Vec<usize> {
+    let mut seen = std::collections::HashSet::new();
+    let (first, repeats): (Vec<usize>, Vec<usize>) =
+        (0..contexts.len()).partition(|&i| seen.insert(contexts[i].abstract_syntax.as_str()));
+    let mut selected: Vec<usize> = first.into_iter().chain(repeats).take(cap).collect();
+    selected.sort_unstable();
+    selected
+}
+
+/// Extract per-response C-FIND statuses from verbose findscu output.
+///
+/// Returns a map of 1-based response index to DIMSE status code. Responses reported as
+/// "pending with warning" (optional keys not supported) map to 0xFF01, all other pending
+/// responses map to 0xFF00.
+#[cfg_attr(not(feature = "dcmtk_cli"), allow(dead_code))]
+fn parse_find_response_statuses(log: &str) -> std::collections::HashMap<usize, u16> {
+    use crate::types::DimseStatus;
+
+    let mut statuses = std::collections::HashMap::new();
+    for line in log.lines() {
+        let Some(pos) = line.find("Received Find Response") else {
+            continue;
+        };
+        let rest = &line[pos + "Received Find Response".len()..];
+        let index = rest
+            .trim_start()
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .and_then(|n| n.parse::<usize>().ok());
+        let Some(index) = index else { continue };
+
+        let lower = rest.to_lowercase();
+        let status = if lower.contains("0xff01")
+            || lower.contains("optional key")
+            || lower.contains("optionalkey")
+            || lower.contains("warning")
+        {
+            DimseStatus::PENDING_WARNING
+        } else {
+            DimseStatus::PENDING
+        };
+        statuses.insert(index, status);
+    }
+    statuses
+}
+
+/// Response index encoded in a findscu extraction filename (`rsp0001.dcm` -> 1)
+#[cfg_attr(not(feature = "dcmtk_cli"), allow(dead_code))]
+fn response_index(path: &std::path::Path) -> Option<usize> {
+    let stem = path.file_stem()?.to_str()?;
+    stem.trim_start_matches(|c: char| !c.is_ascii_digit())
+        .parse::<usize>()
+        .ok()
+}
+
 /// Builder for creating SCU instances with custom configurations
 pub struct ScuBuilder {
     config: DimseConfig,
@@ -725,7 +2488,12 @@ impl Default for ScuBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::association::ASSOCIATE_RQ_FIXED_LEN;
+    use crate::scp::{read_pdu, send_message};
     use futures::stream::StreamExt;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::AsyncWriteExt;
 
     #[tokio::test]
     async fn test_scu_creation() {
@@ -764,10 +2532,39 @@ mod tests {
         assert!(first_result.is_none());
     }
 
-    #[tokio::test]
-    async fn test_connection_timeout_selection() {
+    #[cfg(feature = "dcmtk_cli")]
+    #[test]
+    fn test_pdu_arg_only_when_configured() {
+        let scu = DimseScu::new(DimseConfig::default());
+        assert_eq!(scu.config.max_pdu, 16384);
+        let node = RemoteNode::new("PACS", "localhost", 11112);
+        assert!(!scu.association_args(&node).contains(&"-pdu".to_string()));
+
+        let mut legacy = node.clone();
+        legacy.max_pdu = Some(4096);
+        let args = scu.association_args(&legacy);
+        let at = args
+            .iter()
+            .position(|a| a == "-pdu")
+            .expect("-pdu for node max");
+        assert_eq!(args[at + 1], "4096");
+
         let scu = DimseScu::new(DimseConfig {
-            connect_timeout_ms: 5000,
+            max_pdu: 65536,
+            ..Default::default()
+        });
+        let args = scu.association_args(&node);
+        let at = args
+            .iter()
+            .position(|a| a == "-pdu")
+            .expect("-pdu for global max");
+        assert_eq!(args[at + 1], "65536");
+    }
+
+    #[tokio::test]
+    async fn test_connection_timeout_selection() {
+        let scu = DimseScu::new(DimseConfig {
+            connect_timeout_ms: 5000,
             ..Default::default()
         });
 
@@ -786,6 +2583,1739 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_verify_sop_class_support_reports_accepted_contexts() {
+        use dicom_ul::association::server::ServerAssociationOptions;
+        use dicom_ul::pdu::Pdu;
+
+        const VERIFICATION: &str = "1.2.840.10008.1.1";
+        const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        const EXPLICIT_VR_LE: &str = "1.2.840.10008.1.2.1";
+
+        // Peer that only supports Verification
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut association = ServerAssociationOptions::new()
+                .accept_any()
+                .with_abstract_syntax(VERIFICATION)
+                .establish(stream)
+                .unwrap();
+            if let Ok(Pdu::ReleaseRQ) = association.receive() {
+                let _ = association.send(&Pdu::ReleaseRP);
+            }
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "PROBE_SCU".to_string(),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("ANY_SCP", "127.0.0.1", port);
+        let support = scu
+            .verify_sop_class_support(
+                &node,
+                &[
+                    PresentationContextProposal::new(
+                        VERIFICATION,
+                        vec![EXPLICIT_VR_LE.to_string()],
+                    ),
+                    PresentationContextProposal::new(CT_IMAGE_STORAGE, vec![]),
+                ],
+            )
+            .await
+            .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(support.contexts.len(), 2);
+        assert_eq!(
+            support.accepted_transfer_syntax(VERIFICATION),
+            Some(EXPLICIT_VR_LE)
+        );
+        assert_eq!(support.contexts[1].abstract_syntax, CT_IMAGE_STORAGE);
+        assert!(!support.contexts[1].accepted);
+        assert!(!support.all_accepted());
+    }
+
+    #[tokio::test]
+    async fn test_proposed_contexts_capped_per_node() {
+        use dicom_ul::association::server::ServerAssociationOptions;
+        use dicom_ul::pdu::Pdu;
+
+        const VERIFICATION: &str = "1.2.840.10008.1.1";
+        const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+        const MR_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.4";
+        const IMPLICIT_VR_LE: &str = "1.2.840.10008.1.2";
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut association = ServerAssociationOptions::new()
+                .accept_any()
+                .with_abstract_syntax(VERIFICATION)
+                .with_abstract_syntax(CT_IMAGE_STORAGE)
+                .with_abstract_syntax(MR_IMAGE_STORAGE)
+                .establish(stream)
+                .unwrap();
+            let seen = association.presentation_contexts().len();
+            if let Ok(Pdu::ReleaseRQ) = association.receive() {
+                let _ = association.send(&Pdu::ReleaseRP);
+            }
+            seen
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "PROBE_SCU".to_string(),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("OLD_PACS", "127.0.0.1", port).with_max_presentation_contexts(2);
+        let support = scu
+            .verify_sop_class_support(
+                &node,
+                &[
+                    PresentationContextProposal::new(CT_IMAGE_STORAGE, vec![]),
+                    // Alternative encoding of a class already proposed: dropped first
+                    PresentationContextProposal::new(
+                        CT_IMAGE_STORAGE,
+                        vec![IMPLICIT_VR_LE.to_string()],
+                    ),
+                    PresentationContextProposal::new(MR_IMAGE_STORAGE, vec![]),
+                ],
+            )
+            .await
+            .unwrap();
+        let seen_by_peer = server.join().unwrap();
+
+        assert_eq!(seen_by_peer, 2, "peer should see only the capped proposal");
+        let proposed: Vec<bool> = support.contexts.iter().map(|c| c.proposed).collect();
+        assert_eq!(proposed, vec![true, false, true]);
+        assert!(support.contexts[0].accepted);
+        assert!(support.contexts[2].accepted);
+        assert!(!support.contexts[1].accepted);
+    }
+
+    #[test]
+    fn test_parse_find_response_statuses() {
+        let log = "I: Received Find Response 1 (Pending)\n\
+                   I: Received Find Response 2 (Pending: Warning - Optional Keys Not Supported)\n\
+                   I: Received Final Find Response (Success)\n";
+        let statuses = parse_find_response_statuses(log);
+        assert_eq!(statuses.get(&1), Some(&0xFF00));
+        assert_eq!(statuses.get(&2), Some(&0xFF01));
+        assert_eq!(statuses.len(), 2);
+
+        assert_eq!(
+            response_index(std::path::Path::new("/tmp/find/rsp0002.dcm")),
+            Some(2)
+        );
+        assert_eq!(response_index(std::path::Path::new("other.dcm")), None);
+    }
+
+    #[cfg(feature = "dcmtk_cli")]
+    #[test]
+    fn test_final_move_sub_operations_from_last_response() {
+        let log = "D: Message Type                  : C-MOVE RSP\n\
+                   D: Remaining Suboperations       : 2\n\
+                   D: Completed Suboperations       : 1\n\
+                   D: Failed Suboperations          : 0\n\
+                   D: Warning Suboperations         : 0\n\
+                   D: Message Type                  : C-MOVE RSP\n\
+                   D: Remaining Suboperations       : none\n\
+                   D: Completed Suboperations       : 2\n\
+                   D: Failed Suboperations          : 1\n\
+                   D: Warning Suboperations         : 0\n\
+                   D: DIMSE Status                  : 0xb000: Warning\n";
+        let counts = final_move_sub_operations(log).expect("a C-MOVE response");
+        assert_eq!(
+            counts,
+            SubOperations {
+                remaining: 0,
+                completed: 2,
+                failed: 1,
+                warning: 0,
+            }
+        );
+
+        assert_eq!(
+            final_move_sub_operations("E: Association Request Failed"),
+            None
+        );
+    }
+
+    #[cfg(all(feature = "dcmtk_cli", unix))]
+    #[tokio::test]
+    async fn test_idle_tool_killed_after_lifetime() {
+        let mut cmd = tokio::process::Command::new("sleep");
+        cmd.arg("30");
+
+        let started = std::time::Instant::now();
+        let err = run_dcmtk(cmd, Some(Duration::from_millis(200)), None, None, None)
+            .await
+            .expect_err("idle process should be terminated");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Run `sleep secs` as the tool and drop its result stream after 100ms
+    #[cfg(all(feature = "dcmtk_cli", unix))]
+    async fn run_and_drop_stream(
+        behavior: DropBehavior,
+        grace: Duration,
+        secs: &str,
+    ) -> (std::io::Result<std::process::Output>, Duration) {
+        let (tx, rx) = mpsc::channel::<Result<DatasetStream>>(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            drop(rx);
+        });
+
+        let mut cmd = tokio::process::Command::new("sleep");
+        cmd.arg(secs);
+        let on_drop = DropWatch {
+            tx: &tx,
+            behavior,
+            grace,
+        };
+        let started = std::time::Instant::now();
+        let result = run_dcmtk(cmd, None, None, None, Some(on_drop)).await;
+        (result, started.elapsed())
+    }
+
+    #[cfg(all(feature = "dcmtk_cli", unix))]
+    #[tokio::test]
+    async fn test_dropped_find_stream_follows_drop_behavior() {
+        let grace = Duration::from_millis(300);
+
+        // abort: killed as soon as the stream goes away
+        let (result, elapsed) = run_and_drop_stream(DropBehavior::Abort, grace, "30").await;
+        let err = result.expect_err("tool should be aborted");
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert!(elapsed < Duration::from_secs(5));
+
+        // release: left to finish on its own
+        let (result, elapsed) = run_and_drop_stream(DropBehavior::Release, grace, "1").await;
+        assert!(result.expect("tool should finish").status.success());
+        assert!(elapsed >= Duration::from_millis(900));
+
+        // cancel_then_release: finishes within the grace period...
+        let (result, _) =
+            run_and_drop_stream(DropBehavior::CancelThenRelease, Duration::from_secs(5), "1").await;
+        assert!(result.expect("tool should finish").status.success());
+
+        // ...or is aborted once the grace period runs out
+        let (result, elapsed) =
+            run_and_drop_stream(DropBehavior::CancelThenRelease, grace, "30").await;
+        let err = result.expect_err("tool should be aborted after the grace period");
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert!(elapsed < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_commitment_report_on_new_association_ends_open_request() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::command::decode_data_set;
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+        // Answers the N-ACTION, then keeps the association open and reports on "another
+        // association" instead, by handing the result to the SCP-side waiter directly
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "COMMIT_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, action, data_set) = next_message(&mut stream, &mut assembler).await;
+            let request = decode_data_set(&data_set.unwrap(), IMPLICIT_VR_LITTLE_ENDIAN)
+                .and_then(|object| CommitmentRequest::from_object(&object))
+                .unwrap();
+            send_message(
+                &mut stream,
+                context_id,
+                &action.response(STATUS_SUCCESS),
+                None,
+                16384,
+            )
+            .await
+            .unwrap();
+            assert!(commitment::deliver(CommitmentResult {
+                transaction_uid: request.transaction_uid,
+                committed: request.references,
+                failed: vec![],
+            }));
+
+            let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            assert_eq!(pdu_type, PDU_RELEASE_RQ);
+            stream.write_all(&release_rp_pdu()).await.unwrap();
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "COMMIT_SCU".to_string(),
+            dimse_timeout_ms: Some(30_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+
+        let started = std::time::Instant::now();
+        let result = scu
+            .request_storage_commitment(
+                &node,
+                vec![(CT_IMAGE.to_string(), "1.2.3.4.5".to_string())],
+            )
+            .await
+            .unwrap();
+
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "should not wait out the DIMSE timeout on the open association"
+        );
+        assert_eq!(
+            result.committed,
+            vec![(CT_IMAGE.to_string(), "1.2.3.4.5".to_string())]
+        );
+        peer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_store_coercion_applied_to_outgoing_dataset() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::coercion::{CoercionAction, CoercionRule};
+        use crate::command::decode_data_set;
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+
+        const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+        // Store SCP that accepts the association and keeps the instance it receives
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "STORE_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, store, data_set) = next_message(&mut stream, &mut assembler).await;
+            let response = store.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &response, None, 16384)
+                .await
+                .unwrap();
+            let object = decode_data_set(&data_set.unwrap(), IMPLICIT_VR_LITTLE_ENDIAN).unwrap();
+            (store, object)
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "STORE_SCU".to_string(),
+            store_coercion: vec![CoercionRule {
+                tag: "InstitutionName".to_string(),
+                action: CoercionAction::Overwrite {
+                    value: "Harmony Relay".to_string(),
+                },
+                vr: None,
+            }],
+            ..Default::default()
+        });
+
+        let mut object = dicom_object::InMemDicomObject::new_empty();
+        object.put(DataElement::new(
+            tags::SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from(CT_IMAGE),
+        ));
+        object.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4.5"),
+        ));
+        object.put(DataElement::new(
+            tags::INSTITUTION_NAME,
+            VR::LO,
+            PrimitiveValue::from("Origin Hospital"),
+        ));
+
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let stored = scu
+            .store(&node, DatasetStream::from_object(object))
+            .await
+            .unwrap();
+        assert!(stored);
+
+        let (request, received) = peer.await.unwrap();
+        assert_eq!(request.command_field, C_STORE_RQ);
+        assert_eq!(request.affected_sop_class_uid.as_deref(), Some(CT_IMAGE));
+        assert_eq!(
+            request.affected_sop_instance_uid.as_deref(),
+            Some("1.2.3.4.5")
+        );
+        let value = |tag| {
+            received
+                .element(tag)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .trim_end_matches(['\0', ' '])
+                .to_string()
+        };
+        assert_eq!(value(tags::INSTITUTION_NAME), "Harmony Relay");
+        assert_eq!(value(tags::SOP_INSTANCE_UID), "1.2.3.4.5");
+    }
+
+    /// Read P-DATA-TF PDUs until a DIMSE message completes
+    async fn next_message(
+        stream: &mut tokio::net::TcpStream,
+        assembler: &mut MessageAssembler,
+    ) -> crate::command::Message {
+        loop {
+            let (pdu_type, body) = read_pdu(stream, u32::MAX).await.unwrap();
+            assert_eq!(pdu_type, PDU_P_DATA_TF);
+            if let Some(message) = reassemble(&body, assembler).unwrap().pop() {
+                return message;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_receives_instances_over_one_association() {
+        use crate::association::{
+            encode_associate_ac, negotiate_roles, proposed_contexts, role_selections,
+            AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::{RoleSelection, IMPLICIT_VR_LITTLE_ENDIAN};
+        use crate::types::{GetQuery, QueryLevel};
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::InMemDicomObject;
+
+        const CT_IMAGE_STORAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+        // Peer that answers the C-GET with two CT instances on the same association
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let items = &rq[ASSOCIATE_RQ_FIXED_LEN as usize..];
+            let proposed = proposed_contexts(items);
+            // The SCP role is offered for each proposed storage class, and only for those
+            let roles = role_selections(items);
+            assert!(roles.contains(&RoleSelection::new(CT_IMAGE_STORAGE, false, true)));
+            assert_eq!(roles.len(), proposed.len() - 1);
+            assert!(roles.iter().all(|role| proposed
+                .iter()
+                .any(|c| c.abstract_syntax == role.sop_class_uid)));
+            let storage = proposed
+                .iter()
+                .find(|c| c.abstract_syntax == CT_IMAGE_STORAGE)
+                .expect("CT Image Storage proposed")
+                .id;
+            let contexts: Vec<ContextResult> = proposed
+                .iter()
+                .map(|c| ContextResult {
+                    id: c.id,
+                    abstract_syntax: c.abstract_syntax.clone(),
+                    result: CONTEXT_ACCEPTED,
+                    transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                })
+                .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "GET_SCU".to_string(),
+            };
+            let answered =
+                negotiate_roles(&roles, &[RoleSelection::new(CT_IMAGE_STORAGE, true, false)]);
+            let ac = encode_associate_ac(&header, &contexts, &Negotiation::roles(&answered), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (get_context, get, identifier) = next_message(&mut stream, &mut assembler).await;
+            assert_eq!(get.command_field, C_GET_RQ);
+            let identifier =
+                decode_data_set(&identifier.unwrap(), IMPLICIT_VR_LITTLE_ENDIAN).unwrap();
+            let level = identifier.element(tags::QUERY_RETRIEVE_LEVEL).unwrap();
+            assert_eq!(level.to_str().unwrap().trim(), "STUDY");
+
+            for (i, sop_instance_uid) in ["1.2.3.4.1", "1.2.3.4.2"].iter().enumerate() {
+                let mut instance = InMemDicomObject::new_empty();
+                instance.put(DataElement::new(
+                    tags::SOP_CLASS_UID,
+                    VR::UI,
+                    PrimitiveValue::from(CT_IMAGE_STORAGE),
+                ));
+                instance.put(DataElement::new(
+                    tags::SOP_INSTANCE_UID,
+                    VR::UI,
+                    PrimitiveValue::from(*sop_instance_uid),
+                ));
+                let data_set = encode_data_set(&instance, IMPLICIT_VR_LITTLE_ENDIAN).unwrap();
+                let store = Command {
+                    command_field: C_STORE_RQ,
+                    message_id: i as u16 + 2,
+                    affected_sop_class_uid: Some(CT_IMAGE_STORAGE.to_string()),
+                    affected_sop_instance_uid: Some(sop_instance_uid.to_string()),
+                    priority: Some(PRIORITY_MEDIUM),
+                    has_data_set: true,
+                    ..Default::default()
+                };
+                send_message(
+                    &mut stream,
+                    storage,
+                    &store,
+                    Some(data_set.as_slice()),
+                    16384,
+                )
+                .await
+                .unwrap();
+                let (_, response, _) = next_message(&mut stream, &mut assembler).await;
+                assert_eq!(response.command_field, C_STORE_RQ | RESPONSE_BIT);
+                assert_eq!(response.status, Some(STATUS_SUCCESS));
+            }
+
+            let mut done = get.response(STATUS_SUCCESS);
+            done.sub_operations = Some(SubOperations {
+                completed: 2,
+                ..Default::default()
+            });
+            send_message(&mut stream, get_context, &done, None, 16384)
+                .await
+                .unwrap();
+            let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            assert_eq!(pdu_type, PDU_RELEASE_RQ);
+            stream.write_all(&release_rp_pdu()).await.unwrap();
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "GET_SCU".to_string(),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = GetQuery::new(QueryLevel::Study).with_parameter("0020000D", "1.2.3.4");
+        let output_dir = tempfile::tempdir().unwrap();
+        let report = scu
+            .get_request(&node, query, output_dir.path())
+            .await
+            .unwrap();
+        peer.await.unwrap();
+
+        assert_eq!(report.status, STATUS_SUCCESS);
+        assert_eq!((report.completed, report.failed, report.warning), (2, 0, 0));
+        assert_eq!(report.instances.len(), 2);
+        assert_eq!(
+            report.instances[1].metadata().sop_instance_uid.as_deref(),
+            Some("1.2.3.4.2")
+        );
+        assert!(output_dir.path().join("1.2.3.4.1.dcm").is_file());
+        assert!(output_dir.path().join("1.2.3.4.2.dcm").is_file());
+    }
+
+    #[tokio::test]
+    async fn test_sequential_finds_reuse_one_association() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::InMemDicomObject;
+
+        // Peer that accepts a single association and answers every C-FIND with one match
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "POOL_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            for patient_id in ["P1", "P2"] {
+                let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+                assert_eq!(find.command_field, C_FIND_RQ);
+                let mut identifier = InMemDicomObject::new_empty();
+                identifier.put(DataElement::new(
+                    tags::PATIENT_ID,
+                    VR::LO,
+                    PrimitiveValue::from(patient_id),
+                ));
+                let data_set = encode_data_set(&identifier, IMPLICIT_VR_LITTLE_ENDIAN).unwrap();
+                let mut pending = find.response(DimseStatus::PENDING);
+                pending.has_data_set = true;
+                send_message(
+                    &mut stream,
+                    context_id,
+                    &pending,
+                    Some(data_set.as_slice()),
+                    16384,
+                )
+                .await
+                .unwrap();
+                let done = find.response(STATUS_SUCCESS);
+                send_message(&mut stream, context_id, &done, None, 16384)
+                    .await
+                    .unwrap();
+            }
+            let second = tokio::time::timeout(Duration::from_millis(200), listener.accept()).await;
+            assert!(second.is_err(), "second association opened");
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "POOL_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let hits = pool::stats().hits;
+        for patient_id in ["P1", "P2"] {
+            let query = FindQuery::patient(Some(patient_id.to_string()));
+            let results: Vec<_> = scu.find(&node, query).await.unwrap().collect().await;
+            assert_eq!(results.len(), 1);
+            // Matches are delivered in memory, without a temporary file
+            let ds = results[0].as_ref().unwrap();
+            assert!(matches!(ds, DatasetStream::Memory { .. }));
+            let identifier = ds.to_object().await.unwrap();
+            assert_eq!(
+                identifier
+                    .element(tags::PATIENT_ID)
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                patient_id
+            );
+        }
+        peer.await.unwrap();
+
+        assert!(pool::stats().hits > hits);
+    }
+
+    #[tokio::test]
+    async fn test_pooled_find_proposal_capped_per_node() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let proposed = proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..]);
+            let contexts: Vec<ContextResult> = proposed
+                .iter()
+                .map(|c| ContextResult {
+                    id: c.id,
+                    abstract_syntax: c.abstract_syntax.clone(),
+                    result: CONTEXT_ACCEPTED,
+                    transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                })
+                .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "POOL_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+            proposed
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "POOL_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port).with_max_presentation_contexts(1);
+        let mut query = FindQuery::patient(None).with_relational(true);
+        query.query_level = crate::types::QueryLevel::Study;
+        let model = query.information_model();
+        assert_eq!(model, STUDY_ROOT_FIND);
+        let results: Vec<_> = scu.find(&node, query).await.unwrap().collect().await;
+        assert!(results.is_empty());
+
+        let proposed = peer.await.unwrap();
+        assert_eq!(proposed.len(), 1);
+        assert_eq!(proposed[0].abstract_syntax, model);
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_echoes_keep_pooled_association() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::command::C_ECHO_RQ;
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+
+        // Peer that accepts a single association and answers C-ECHOs until the second C-FIND
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let proposed = proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..]);
+            assert!(proposed.iter().any(|c| c.abstract_syntax == VERIFICATION));
+            let contexts: Vec<ContextResult> = proposed
+                .iter()
+                .map(|c| ContextResult {
+                    id: c.id,
+                    abstract_syntax: c.abstract_syntax.clone(),
+                    result: CONTEXT_ACCEPTED,
+                    transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                })
+                .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "KEEPALIVE_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let mut finds = 0;
+            let mut echoes = 0;
+            while finds < 2 {
+                let (context_id, command, _) = next_message(&mut stream, &mut assembler).await;
+                match command.command_field {
+                    C_FIND_RQ => finds += 1,
+                    C_ECHO_RQ => echoes += 1,
+                    other => panic!("unexpected command 0x{:04X}", other),
+                }
+                let done = command.response(STATUS_SUCCESS);
+                send_message(&mut stream, context_id, &done, None, 16384)
+                    .await
+                    .unwrap();
+            }
+            echoes
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "KEEPALIVE_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            association_keepalive_ms: Some(50),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::patient(None);
+        let results: Vec<_> = scu
+            .find(&node, query.clone())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(results.is_empty());
+        // Idle across several keep-alive intervals
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let hits = pool::stats().hits;
+        let results: Vec<_> = scu.find(&node, query).await.unwrap().collect().await;
+        assert!(results.is_empty());
+        assert!(pool::stats().hits > hits);
+
+        let echoes = peer.await.unwrap();
+        assert!(echoes >= 2, "only {} keep-alive echoes", echoes);
+    }
+
+    #[tokio::test]
+    async fn test_failed_keepalive_evicts_pooled_association() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::command::C_ECHO_RQ;
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+        use tokio::io::AsyncReadExt;
+
+        // Peer that answers a C-FIND, then refuses the C-ECHO on that association
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "KEEPALIVE_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+            let (context_id, echo, _) = next_message(&mut stream, &mut assembler).await;
+            assert_eq!(echo.command_field, C_ECHO_RQ);
+            let refused = echo.response(STATUS_PROCESSING_FAILURE);
+            send_message(&mut stream, context_id, &refused, None, 16384)
+                .await
+                .unwrap();
+            // Evicting the association closes the connection
+            let mut rest = Vec::new();
+            tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut rest))
+                .await
+                .expect("association not evicted")
+                .unwrap();
+            assert!(rest.is_empty());
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "KEEPALIVE_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            association_keepalive_ms: Some(50),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let results: Vec<_> = scu
+            .find(&node, FindQuery::patient(None))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(results.is_empty());
+        peer.await.unwrap();
+        assert!(pool::checkout(
+            "KEEPALIVE_SCU",
+            &node,
+            PATIENT_ROOT_FIND,
+            None,
+            Duration::from_secs(60)
+        )
+        .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_relational_study_find_negotiates_study_root() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::{FindQuery, QueryLevel};
+
+        // Peer that accepts every proposed context and reports which one the C-FIND used
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let proposed = proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..]);
+            let contexts: Vec<ContextResult> = proposed
+                .iter()
+                .map(|c| ContextResult {
+                    id: c.id,
+                    abstract_syntax: c.abstract_syntax.clone(),
+                    result: CONTEXT_ACCEPTED,
+                    transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                })
+                .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "REL_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+            let context = proposed.into_iter().find(|c| c.id == context_id).unwrap();
+            (context.abstract_syntax, find.affected_sop_class_uid)
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "REL_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::new(QueryLevel::Study).with_relational(true);
+        let results: Vec<_> = scu.find(&node, query).await.unwrap().collect().await;
+        assert!(results.is_empty());
+
+        let (abstract_syntax, sop_class) = peer.await.unwrap();
+        assert_eq!(abstract_syntax, STUDY_ROOT_FIND);
+        assert_eq!(sop_class.as_deref(), Some(STUDY_ROOT_FIND));
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_matching_proposes_extended_negotiation() {
+        use crate::association::{
+            encode_associate_ac, extended_negotiations, proposed_contexts, AssociateRequestHeader,
+            ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+
+        // Peer that accepts fuzzy matching, answering with the first three feature bytes only
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let items = &rq[ASSOCIATE_RQ_FIXED_LEN as usize..];
+            // Relational, date-time, fuzzy semantic and timezone bytes for Patient Root
+            assert_eq!(
+                extended_negotiations(items),
+                vec![ExtendedNegotiation {
+                    sop_class_uid: PATIENT_ROOT_FIND.to_string(),
+                    application_information: vec![0, 0, 1, 0],
+                }]
+            );
+            let contexts: Vec<ContextResult> = proposed_contexts(items)
+                .iter()
+                .map(|c| ContextResult {
+                    id: c.id,
+                    abstract_syntax: c.abstract_syntax.clone(),
+                    result: CONTEXT_ACCEPTED,
+                    transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                })
+                .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "FUZZY_SCU".to_string(),
+            };
+            let negotiation = Negotiation {
+                roles: Vec::new(),
+                extended: vec![ExtendedNegotiation {
+                    sop_class_uid: PATIENT_ROOT_FIND.to_string(),
+                    application_information: vec![0, 0, 1],
+                }],
+            };
+            let ac = encode_associate_ac(&header, &contexts, &negotiation, 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "FUZZY_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::patient(None)
+            .with_parameter("PatientName", "SMITH^JOHN")
+            .with_fuzzy_matching(true);
+        let (features, stream) = scu
+            .find_negotiated(&node, query, CancellationToken::new())
+            .await
+            .unwrap();
+        let results: Vec<_> = stream.collect().await;
+        assert!(results.is_empty());
+        peer.await.unwrap();
+
+        assert_eq!(
+            features,
+            QueryFeatures {
+                fuzzy_matching: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_features_without_pool_use_one_shot_native_association() {
+        use crate::association::{
+            encode_associate_ac, extended_negotiations, proposed_contexts, AssociateRequestHeader,
+            ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+
+        // Peer that accepts every feature, answers the C-FIND and expects a release
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let items = &rq[ASSOCIATE_RQ_FIXED_LEN as usize..];
+            let extended = extended_negotiations(items);
+            let contexts: Vec<ContextResult> = proposed_contexts(items)
+                .iter()
+                .map(|c| ContextResult {
+                    id: c.id,
+                    abstract_syntax: c.abstract_syntax.clone(),
+                    result: CONTEXT_ACCEPTED,
+                    transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                })
+                .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "ONE_SHOT_SCU".to_string(),
+            };
+            let negotiation = Negotiation {
+                roles: Vec::new(),
+                extended: extended.clone(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &negotiation, 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+
+            let (pdu_type, _) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            stream.write_all(&release_rp_pdu()).await.unwrap();
+            (extended, pdu_type)
+        });
+
+        // No association_pool_ttl_ms: the features still reach the peer
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "ONE_SHOT_SCU".to_string(),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::patient(None)
+            .with_parameter("PatientName", "SMITH^JOHN")
+            .with_fuzzy_matching(true);
+        let (features, stream) = scu
+            .find_negotiated(&node, query, CancellationToken::new())
+            .await
+            .unwrap();
+        let results: Vec<_> = stream.collect().await;
+        assert!(results.is_empty());
+
+        let (extended, pdu_type) = peer.await.unwrap();
+        assert_eq!(
+            extended,
+            vec![ExtendedNegotiation {
+                sop_class_uid: PATIENT_ROOT_FIND.to_string(),
+                application_information: vec![0, 0, 1, 0],
+            }]
+        );
+        assert_eq!(pdu_type, PDU_RELEASE_RQ);
+        assert!(features.fuzzy_matching);
+    }
+
+    #[tokio::test]
+    async fn test_find_retries_a_refused_association() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+
+        // Peer that drops the first connection and answers a C-FIND on the second
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (refused, _) = listener.accept().await.unwrap();
+            drop(refused);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "RETRY_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            assert_eq!(find.command_field, C_FIND_RQ);
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "RETRY_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            retry: RetryPolicy {
+                max_attempts: 3,
+                base_backoff_ms: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::patient(Some("P1".to_string()));
+        let results: Vec<_> = scu.find(&node, query).await.unwrap().collect().await;
+        peer.await.unwrap();
+
+        assert!(results.is_empty(), "{:?}", results);
+
+        // Without retries the dropped connection fails the query
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (refused, _) = listener.accept().await.unwrap();
+            drop(refused);
+        });
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "RETRY_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::patient(Some("P1".to_string()));
+        let err = scu.find(&node, query).await.expect_err("no retry");
+        assert!(matches!(err, DimseError::Network(_)), "got {:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_fails_fast_without_connecting() {
+        use crate::breaker::{BreakerState, CircuitBreakerConfig};
+        use crate::types::FindQuery;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Peer that drops every connection, counting them
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((dropped, _)) = listener.accept().await {
+                counted.fetch_add(1, Ordering::SeqCst);
+                drop(dropped);
+            }
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "BREAKER_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            circuit_breaker: Some(CircuitBreakerConfig {
+                failure_threshold: 3,
+                cooldown_ms: 60_000,
+            }),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("DOWN_PACS", "127.0.0.1", port);
+        for _ in 0..3 {
+            let query = FindQuery::patient(Some("P1".to_string()));
+            let err = scu.find(&node, query).await.expect_err("peer drops us");
+            assert!(matches!(err, DimseError::Network(_)), "got {:?}", err);
+        }
+        assert_eq!(breaker::state(&node), BreakerState::Open);
+
+        let started = std::time::Instant::now();
+        let query = FindQuery::patient(Some("P1".to_string()));
+        let err = scu.find(&node, query).await.expect_err("breaker is open");
+        assert!(matches!(err, DimseError::CircuitOpen(_)), "got {:?}", err);
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_find_writes_an_audit_record() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::audit::{AuditConfig, AuditDestination};
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "AUDIT_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let done = find.response(STATUS_SUCCESS);
+            send_message(&mut stream, context_id, &done, None, 16384)
+                .await
+                .unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("atna.log");
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "AUDIT_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            audit: Some(AuditConfig {
+                destination: AuditDestination::File { path: log.clone() },
+                source_id: None,
+            }),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let query = FindQuery::patient(Some("P1".to_string()));
+        let results: Vec<_> = scu.find(&node, query).await.unwrap().collect().await;
+        peer.await.unwrap();
+        assert!(results.is_empty(), "{:?}", results);
+
+        let record = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(record.lines().count(), 1);
+        assert!(record.contains("csd-code=\"110112\""), "{}", record);
+        assert!(record.contains("EventOutcomeIndicator=\"0\""), "{}", record);
+        assert!(record.contains("UserID=\"AUDIT_SCU\""), "{}", record);
+        assert!(record.contains("UserID=\"PACS\""), "{}", record);
+        assert!(record.contains("P1"), "{}", record);
+    }
+
+    #[tokio::test]
+    async fn test_refused_store_audited_as_serious_failure() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::audit::{AuditConfig, AuditDestination};
+        use crate::command::STATUS_UNABLE_TO_PROCESS;
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+
+        // Store SCP that accepts the association and refuses the instance
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "AUDIT_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, store, _) = next_message(&mut stream, &mut assembler).await;
+            let refused = store.response(STATUS_UNABLE_TO_PROCESS);
+            send_message(&mut stream, context_id, &refused, None, 16384)
+                .await
+                .unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("atna.log");
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "AUDIT_SCU".to_string(),
+            audit: Some(AuditConfig {
+                destination: AuditDestination::File { path: log.clone() },
+                source_id: None,
+            }),
+            ..Default::default()
+        });
+
+        let mut object = dicom_object::InMemDicomObject::new_empty();
+        object.put(DataElement::new(
+            tags::SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.2"),
+        ));
+        object.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.3.4.5"),
+        ));
+
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let stored = scu
+            .store(&node, DatasetStream::from_object(object))
+            .await
+            .unwrap();
+        peer.await.unwrap();
+        assert!(!stored);
+
+        let record = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(record.lines().count(), 1);
+        assert!(record.contains("csd-code=\"110104\""), "{}", record);
+        assert!(record.contains("EventOutcomeIndicator=\"8\""), "{}", record);
+        assert!(record.contains("UserID=\"PACS\""), "{}", record);
+    }
+
+    #[tokio::test]
+    async fn test_dropping_cancel_handle_sends_c_cancel() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::InMemDicomObject;
+
+        // Peer that keeps sending matches until it sees a C-CANCEL-FIND-RQ
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "CANCEL_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            assert_eq!(find.command_field, C_FIND_RQ);
+            let mut identifier = InMemDicomObject::new_empty();
+            identifier.put(DataElement::new(
+                tags::PATIENT_ID,
+                VR::LO,
+                PrimitiveValue::from("P1"),
+            ));
+            let data_set = encode_data_set(&identifier, IMPLICIT_VR_LITTLE_ENDIAN).unwrap();
+            let mut pending = find.response(DimseStatus::PENDING);
+            pending.has_data_set = true;
+            for _ in 0..2 {
+                send_message(
+                    &mut stream,
+                    context_id,
+                    &pending,
+                    Some(data_set.as_slice()),
+                    16384,
+                )
+                .await
+                .unwrap();
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+
+            let (cancel_context, cancel, _) = tokio::time::timeout(
+                Duration::from_secs(5),
+                next_message(&mut stream, &mut assembler),
+            )
+            .await
+            .expect("no C-CANCEL received");
+            assert_eq!(cancel.command_field, C_CANCEL_RQ);
+            assert_eq!(cancel.message_id, find.message_id);
+            assert_eq!(cancel_context, context_id);
+            let cancelled = find.response(0xFE00);
+            send_message(&mut stream, context_id, &cancelled, None, 16384)
+                .await
+                .unwrap();
+        });
+
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "CANCEL_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let token = CancellationToken::new();
+        let handle = token.clone().drop_guard();
+        let query = FindQuery::patient(Some("P1".to_string()));
+        let mut results = scu.find_with_cancel(&node, query, token).await.unwrap();
+
+        assert!(results.next().await.unwrap().is_ok());
+        drop(handle);
+        peer.await.unwrap();
+
+        // The match that arrived after cancelling is discarded and the stream ends cleanly
+        assert!(results.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_reaches_a_quiet_peer_at_once() {
+        use crate::association::{
+            encode_associate_ac, proposed_contexts, AssociateRequestHeader, ContextResult,
+        };
+        use crate::config::IMPLICIT_VR_LITTLE_ENDIAN;
+        use crate::types::FindQuery;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::InMemDicomObject;
+
+        // Peer that sends one match and then goes quiet until it sees a C-CANCEL-FIND-RQ
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (_, rq) = read_pdu(&mut stream, u32::MAX).await.unwrap();
+            let contexts: Vec<ContextResult> =
+                proposed_contexts(&rq[ASSOCIATE_RQ_FIXED_LEN as usize..])
+                    .iter()
+                    .map(|c| ContextResult {
+                        id: c.id,
+                        abstract_syntax: c.abstract_syntax.clone(),
+                        result: CONTEXT_ACCEPTED,
+                        transfer_syntax: Some(IMPLICIT_VR_LITTLE_ENDIAN.to_string()),
+                    })
+                    .collect();
+            let header = AssociateRequestHeader {
+                called_aet: "PACS".to_string(),
+                calling_aet: "CANCEL_SCU".to_string(),
+            };
+            let ac = encode_associate_ac(&header, &contexts, &Default::default(), 16384);
+            stream.write_all(&ac).await.unwrap();
+
+            let mut assembler = MessageAssembler::default();
+            let (context_id, find, _) = next_message(&mut stream, &mut assembler).await;
+            let mut identifier = InMemDicomObject::new_empty();
+            identifier.put(DataElement::new(
+                tags::PATIENT_ID,
+                VR::LO,
+                PrimitiveValue::from("P1"),
+            ));
+            let data_set = encode_data_set(&identifier, IMPLICIT_VR_LITTLE_ENDIAN).unwrap();
+            let mut pending = find.response(DimseStatus::PENDING);
+            pending.has_data_set = true;
+            send_message(
+                &mut stream,
+                context_id,
+                &pending,
+                Some(data_set.as_slice()),
+                16384,
+            )
+            .await
+            .unwrap();
+
+            let (_, cancel, _) = tokio::time::timeout(
+                Duration::from_secs(5),
+                next_message(&mut stream, &mut assembler),
+            )
+            .await
+            .expect("no C-CANCEL received from a quiet peer's SCU");
+            assert_eq!(cancel.command_field, C_CANCEL_RQ);
+            assert_eq!(cancel.message_id, find.message_id);
+            let cancelled = find.response(0xFE00);
+            send_message(&mut stream, context_id, &cancelled, None, 16384)
+                .await
+                .unwrap();
+        });
+
+        // Far longer than the test takes, so only an immediate C-CANCEL lets it finish in time
+        let scu = DimseScu::new(DimseConfig {
+            local_aet: "CANCEL_SCU".to_string(),
+            association_pool_ttl_ms: Some(60_000),
+            dimse_timeout_ms: Some(60_000),
+            ..Default::default()
+        });
+        let node = RemoteNode::new("PACS", "127.0.0.1", port);
+        let token = CancellationToken::new();
+        let query = FindQuery::patient(Some("P1".to_string()));
+        let mut results = scu
+            .find_with_cancel(&node, query, token.clone())
+            .await
+            .unwrap();
+
+        assert!(results.next().await.unwrap().is_ok());
+        let started = std::time::Instant::now();
+        token.cancel();
+        peer.await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(results.next().await.is_none());
+    }
+
+    /// A local listener whose accept queue is full, so further SYNs are dropped and a connect
+    /// to it hangs like one to a blackholed address. Keep the returned streams alive.
+    async fn blackholed_listener() -> (tokio::net::TcpListener, u16, Vec<tokio::net::TcpStream>) {
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(0).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut queued = Vec::new();
+        while queued.len() < 8 {
+            match tokio::time::timeout(
+                Duration::from_millis(200),
+                tokio::net::TcpStream::connect(("127.0.0.1", port)),
+            )
+            .await
+            {
+                Ok(Ok(stream)) => queued.push(stream),
+                _ => break,
+            }
+        }
+        (listener, port, queued)
+    }
+
+    #[tokio::test]
+    async fn test_connect_times_out_against_blackholed_listener() {
+        let (_listener, port, _queued) = blackholed_listener().await;
+        let node = RemoteNode::new("BLACKHOLE", "127.0.0.1", port);
+
+        let started = std::time::Instant::now();
+        let err = ScuAssociation::open(
+            "HARMONY_SCU",
+            &node,
+            &[],
+            &Negotiation::default(),
+            16384,
+            Duration::from_millis(500),
+            None,
+        )
+        .await
+        .map(|_| ())
+        .expect_err("connect should time out");
+
+        assert!(matches!(err, DimseError::Timeout(_)), "got {:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "dcmtk_cli")]
+    #[tokio::test]
+    async fn test_echo_times_out_against_blackholed_listener() {
+        if std::process::Command::new("echoscu")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("echoscu not on PATH, skipping");
+            return;
+        }
+        let (_listener, port, _queued) = blackholed_listener().await;
+
+        let scu = DimseScu::new(DimseConfig {
+            connect_timeout_ms: 1_000,
+            ..Default::default()
+        });
+        let node = RemoteNode::new("BLACKHOLE", "127.0.0.1", port);
+
+        let started = std::time::Instant::now();
+        let err = scu.echo(&node).await.expect_err("echo should time out");
+
+        assert!(matches!(err, DimseError::Timeout(_)), "got {:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "dcmtk_cli")]
+    fn failed_output(stderr: &str) -> std::process::Output {
+        use std::os::unix::process::ExitStatusExt;
+        std::process::Output {
+            status: std::process::ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[cfg(feature = "dcmtk_cli")]
+    #[test]
+    fn test_dcmtk_failures_classified_by_condition_code() {
+        let refused = failed_output(
+            "F: Association Request Failed: 0006:031b Failed to establish association\n\
+             F: 0006:031c TCP Initialization Error: Connection refused\n",
+        );
+        assert!(matches!(
+            dcmtk_association_failure("echoscu", &refused),
+            Some(DimseError::Network(_))
+        ));
+        assert!(dcmtk_timeout("echoscu", &refused).is_none());
+
+        let connect_timeout = failed_output(
+            "F: Association Request Failed: 0006:031b Failed to establish association\n\
+             F: 0006:031c TCP Initialization Error: Connection timed out\n",
+        );
+        assert!(matches!(
+            dcmtk_association_failure("echoscu", &connect_timeout),
+            Some(DimseError::Timeout(_))
+        ));
+
+        let acse_timeout = failed_output(
+            "F: Association Request Failed: 0006:031b Failed to establish association\n\
+             F: 0006:031a DUL network read timeout\n",
+        );
+        assert!(matches!(
+            dcmtk_association_failure("findscu", &acse_timeout),
+            Some(DimseError::Timeout(_))
+        ));
+
+        let rejected = failed_output(
+            "F: Association Rejected:\n\
+             F: Result: Rejected Permanent, Source: Service User\n\
+             F: Reason: Called AE Title Not Recognized\n",
+        );
+        assert!(matches!(
+            dcmtk_association_failure("echoscu", &rejected),
+            Some(DimseError::AssociationRejected(_))
+        ));
+
+        // Once associated, the DIMSE timeout is the only timeout left to report
+        let dimse_timeout = failed_output(
+            "E: Find Failed, query keys:\n\
+             E: 0006:0207 DIMSE No data available (timeout in non-blocking mode)\n",
+        );
+        assert!(dcmtk_association_failure("findscu", &dimse_timeout).is_none());
+        assert!(matches!(
+            dcmtk_timeout("findscu", &dimse_timeout),
+            Some(DimseError::Timeout(msg)) if msg.contains("DIMSE No data available")
+        ));
+
+        // Words alone, without a condition code, no longer count
+        let prose = failed_output("E: Failed: the worklist query timed out upstream\n");
+        assert!(dcmtk_association_failure("findscu", &prose).is_none());
+        assert!(dcmtk_timeout("findscu", &prose).is_none());
+    }
+
+    /// Captures the name and recorded fields of every span opened while installed
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<(String, HashMap<String, String>)>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Fields(HashMap<String, String>);
+            impl tracing::field::Visit for Fields {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    self.0
+                        .insert(field.name().to_string(), format!("{:?}", value));
+                }
+            }
+
+            let mut fields = Fields(HashMap::new());
+            attrs.record(&mut fields);
+            self.0
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), fields.0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_echo_emits_span_with_operation_and_remote_ae() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+        // Port 0 fails validation inside the span, so no DCMTK binary or peer is needed
+        let correlation_id = uuid::Uuid::new_v4();
+        let scu = DimseScu::new(DimseConfig::default()).with_correlation_id(correlation_id);
+        let node = RemoteNode::new("ECHO_SCP", "pacs.example", 0);
+        assert!(scu.echo(&node).await.is_err());
+
+        let spans = recorder.0.lock().unwrap();
+        let (_, fields) = spans
+            .iter()
+            .find(|(name, _)| name == "dimse.scu")
+            .expect("C-ECHO should open a dimse.scu span");
+        assert_eq!(fields["dimse.operation"], "\"C-ECHO\"");
+        assert_eq!(fields["dimse.remote_ae"], "ECHO_SCP");
+        assert_eq!(fields["net.peer.name"], "pacs.example");
+        assert_eq!(fields["net.peer.port"], "0");
+        assert_eq!(fields["correlation_id"], correlation_id.to_string());
+    }
+
     #[test]
     fn test_invalid_config_validation() {
         let result = ScuBuilder::new()
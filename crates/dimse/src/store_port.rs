@@ -0,0 +1,173 @@
+//! Ports for the transient Store SCP a C-MOVE listens on
+//!
+//! `movescu +P` opens a Store SCP for the duration of a move. With a single
+//! `incoming_store_port`, two concurrent moves would both try to listen on it. With
+//! `incoming_store_port_range` set, each move leases a port from the range that no other move
+//! in the process holds and that can currently be bound, and gives it back when it finishes.
+//! The QR SCP finds a move destination's port by its AE title, so each port in the range is
+//! paired with an AE title of its own, which the move names as its destination. Like the
+//! association pool the leases are process-wide, since the services build a fresh `DimseScu`
+//! per request.
+
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, TcpListener};
+use std::sync::{Mutex, OnceLock};
+
+use tracing::debug;
+
+use crate::config::DimseConfig;
+use crate::{DimseError, Result};
+
+fn leased() -> &'static Mutex<HashSet<u16>> {
+    static LEASED: OnceLock<Mutex<HashSet<u16>>> = OnceLock::new();
+    LEASED.get_or_init(Default::default)
+}
+
+/// A port held for one C-MOVE's Store SCP, released when dropped
+#[derive(Debug)]
+pub struct StorePortLease {
+    port: u16,
+    /// AE title the QR SCP knows at `port`
+    ae_title: String,
+    /// Whether the port came from the range and must be handed back
+    from_range: bool,
+}
+
+impl StorePortLease {
+    /// Port the Store SCP listens on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Move destination AE title that reaches this port
+    pub fn ae_title(&self) -> &str {
+        &self.ae_title
+    }
+}
+
+/// `<local_aet>_<port>`, with the local AE title shortened to keep within 16 characters
+fn port_ae_title(local_aet: &str, port: u16) -> String {
+    let suffix = format!("_{}", port);
+    let prefix: String = local_aet.chars().take(16 - suffix.len()).collect();
+    format!("{}{}", prefix, suffix)
+}
+
+impl Drop for StorePortLease {
+    fn drop(&mut self) {
+        if self.from_range {
+            leased()
+                .lock()
+                .expect("store port leases poisoned")
+                .remove(&self.port);
+            debug!("Released incoming store port {}", self.port);
+        }
+    }
+}
+
+/// Lease the port for a C-MOVE's Store SCP
+///
+/// Without `incoming_store_port_range` this is always `incoming_store_port`, reached at the
+/// local AE title. Otherwise the lowest port in the range that is neither leased nor bound by
+/// another process is taken, with the AE title `incoming_store_aets` pairs with it; the move
+/// fails when every port is in use.
+pub fn acquire(config: &DimseConfig) -> Result<StorePortLease> {
+    let Some((first, last)) = config.incoming_store_port_range else {
+        return Ok(StorePortLease {
+            port: config.incoming_store_port,
+            ae_title: config.local_aet.clone(),
+            from_range: false,
+        });
+    };
+    let mut leases = leased().lock().expect("store port leases poisoned");
+    let port = (first..=last)
+        .filter(|port| !leases.contains(port))
+        .find(|port| TcpListener::bind((Ipv4Addr::UNSPECIFIED, *port)).is_ok())
+        .ok_or_else(|| {
+            DimseError::operation_failed(format!(
+                "No free incoming store port in {}-{}",
+                first, last
+            ))
+        })?;
+    leases.insert(port);
+    let ae_title = config
+        .incoming_store_aets
+        .get(&port)
+        .cloned()
+        .unwrap_or_else(|| port_ae_title(&config.local_aet, port));
+    debug!("Leased incoming store port {} for {}", port, ae_title);
+    Ok(StorePortLease {
+        port,
+        ae_title,
+        from_range: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(range: (u16, u16)) -> DimseConfig {
+        DimseConfig {
+            local_aet: "HARMONY_MOVE".to_string(),
+            incoming_store_port_range: Some(range),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_moves_lease_distinct_ports() {
+        let range = (42_310, 42_319);
+        let (a, b) = tokio::join!(
+            tokio::spawn(async move { acquire(&config(range)) }),
+            tokio::spawn(async move { acquire(&config(range)) }),
+        );
+        let (a, b) = (a.unwrap().unwrap(), b.unwrap().unwrap());
+        assert_ne!(a.port(), b.port());
+        for lease in [&a, &b] {
+            assert!((range.0..=range.1).contains(&lease.port()));
+            assert_eq!(lease.ae_title(), format!("HARMONY_MO_{}", lease.port()));
+        }
+
+        // A released port can be leased again
+        let port = a.port();
+        drop(a);
+        assert!(!leased().lock().unwrap().contains(&port));
+    }
+
+    #[test]
+    fn test_busy_and_exhausted_ranges() {
+        // A port bound by someone else is skipped
+        let busy = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = busy.local_addr().unwrap().port();
+        assert!(acquire(&config((port, port))).is_err());
+
+        drop(busy);
+        let lease = acquire(&config((port, port))).unwrap();
+        assert_eq!(lease.port(), port);
+        // The only port in the range is leased
+        assert!(acquire(&config((port, port))).is_err());
+    }
+
+    #[test]
+    fn test_configured_ae_title_paired_with_port() {
+        let busy = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = busy.local_addr().unwrap().port();
+        drop(busy);
+        let mut config = config((port, port));
+        config.incoming_store_aets = [(port, "HARMONY_B".to_string())].into();
+        let lease = acquire(&config).unwrap();
+        assert_eq!(lease.ae_title(), "HARMONY_B");
+    }
+
+    #[test]
+    fn test_single_port_without_range() {
+        let config = DimseConfig {
+            local_aet: "HARMONY_MOVE".to_string(),
+            incoming_store_port: 11_190,
+            ..Default::default()
+        };
+        let (a, b) = (acquire(&config).unwrap(), acquire(&config).unwrap());
+        assert_eq!((a.port(), b.port()), (11_190, 11_190));
+        assert_eq!(a.ae_title(), "HARMONY_MOVE");
+    }
+}
@@ -0,0 +1,180 @@
+//! Throughput tracking for DIMSE transfers
+//!
+//! A [`ThroughputMonitor`] is fed the bytes moved by one transfer as they arrive. It keeps
+//! a sliding window of samples, records the windowed rate in the
+//! `dimse_transfer_throughput_bytes_per_second` histogram (see [`crate::metrics`]) and as a
+//! `dimse::metrics` tracing event, and logs a warning once the rate has stayed below the
+//! configured minimum for the configured period.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::config::SlowTransferConfig;
+use crate::metrics;
+
+/// Sliding-window throughput tracker for a single transfer
+#[derive(Debug)]
+pub struct ThroughputMonitor {
+    label: String,
+    role: &'static str,
+    config: SlowTransferConfig,
+    started: Instant,
+    samples: VecDeque<(Instant, u64)>,
+    below_since: Option<Instant>,
+    warned: bool,
+}
+
+impl ThroughputMonitor {
+    /// Start tracking a transfer; `label` identifies it in logs and metrics
+    pub fn new(label: impl Into<String>, config: SlowTransferConfig) -> Self {
+        Self::starting_at(label, config, Instant::now())
+    }
+
+    /// Start tracking a transfer from an explicit instant
+    pub fn starting_at(label: impl Into<String>, config: SlowTransferConfig, now: Instant) -> Self {
+        Self {
+            label: label.into(),
+            role: metrics::SCU,
+            config,
+            started: now,
+            samples: VecDeque::new(),
+            below_since: None,
+            warned: false,
+        }
+    }
+
+    /// Side of the association the transfer runs on, [`metrics::SCU`] unless set
+    pub fn with_role(mut self, role: &'static str) -> Self {
+        self.role = role;
+        self
+    }
+
+    /// Record `bytes` received just now; returns true when this sample raised the warning
+    pub fn record(&mut self, bytes: u64) -> bool {
+        self.record_at(Instant::now(), bytes)
+    }
+
+    /// Record `bytes` received at `now`; returns true when this sample raised the warning
+    ///
+    /// Recording zero bytes is how a stalled transfer is noticed, so callers polling on a
+    /// timer should record every tick.
+    pub fn record_at(&mut self, now: Instant, bytes: u64) -> bool {
+        let window = self.config.window();
+        self.samples.push_back((now, bytes));
+        while let Some(&(at, _)) = self.samples.front() {
+            if now.duration_since(at) >= window {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        // The first window has too little history to judge
+        if now.duration_since(self.started) < window {
+            return false;
+        }
+
+        let rate = self.bytes_per_sec();
+        metrics::transfer_throughput(self.role, rate);
+        debug!(
+            target: "dimse::metrics",
+            transfer = %self.label,
+            bytes_per_sec = rate,
+            "dimse_transfer_throughput"
+        );
+
+        if rate >= self.config.min_bytes_per_sec as f64 {
+            if self.warned {
+                info!("{} throughput recovered to {:.0} B/s", self.label, rate);
+            }
+            self.below_since = None;
+            self.warned = false;
+            return false;
+        }
+
+        let below_since = *self.below_since.get_or_insert(now);
+        if self.warned || now.duration_since(below_since) < self.config.sustained() {
+            return false;
+        }
+        self.warned = true;
+        warn!(
+            transfer = %self.label,
+            bytes_per_sec = rate,
+            min_bytes_per_sec = self.config.min_bytes_per_sec,
+            "Slow transfer: {} at {:.0} B/s for {:?} (minimum {} B/s)",
+            self.label,
+            rate,
+            now.duration_since(below_since),
+            self.config.min_bytes_per_sec
+        );
+        true
+    }
+
+    /// Average rate over the current window
+    pub fn bytes_per_sec(&self) -> f64 {
+        let total: u64 = self.samples.iter().map(|&(_, bytes)| bytes).sum();
+        total as f64 / self.config.window().as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Whether the slow-transfer warning is currently raised
+    pub fn is_slow(&self) -> bool {
+        self.warned
+    }
+}
+
+impl SlowTransferConfig {
+    /// Sliding window length as Duration
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window_ms)
+    }
+
+    /// How long throughput must stay low before warning, as Duration
+    pub fn sustained(&self) -> Duration {
+        Duration::from_millis(self.sustained_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SlowTransferConfig {
+        SlowTransferConfig {
+            min_bytes_per_sec: 1_000,
+            window_ms: 2_000,
+            sustained_ms: 3_000,
+        }
+    }
+
+    /// Feed `bytes_per_tick` every 100ms; returns the ticks on which the warning fired
+    fn feed(monitor: &mut ThroughputMonitor, start: Instant, bytes_per_tick: u64) -> Vec<u64> {
+        (1..=100)
+            .filter(|i| monitor.record_at(start + Duration::from_millis(i * 100), bytes_per_tick))
+            .collect()
+    }
+
+    #[test]
+    fn test_throttled_transfer_triggers_slow_warning() {
+        let start = Instant::now();
+        let mut monitor = ThroughputMonitor::starting_at("C-MOVE", config(), start);
+
+        // 10 bytes per 100ms = 100 B/s, well under the 1000 B/s minimum. Judging starts once
+        // the first 2s window is full and the warning follows 3s later, once only.
+        assert_eq!(feed(&mut monitor, start, 10), vec![50]);
+        assert!(monitor.is_slow());
+        assert!((monitor.bytes_per_sec() - 100.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_healthy_transfer_does_not_warn() {
+        let start = Instant::now();
+        let mut monitor = ThroughputMonitor::starting_at("C-STORE", config(), start);
+
+        // 500 bytes per 100ms = 5000 B/s
+        assert!(feed(&mut monitor, start, 500).is_empty());
+        assert!(!monitor.is_slow());
+        assert!((monitor.bytes_per_sec() - 5_000.0).abs() < 1.0);
+    }
+}
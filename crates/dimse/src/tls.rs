@@ -1,22 +1,260 @@
 //! TLS support for DIMSE connections
 //!
-//! This module provides TLS/SSL encryption support for DIMSE connections.
-//! Currently under development.
+//! Builds rustls configurations from PEM files: the SCP's server identity, optionally
+//! verifying client certificates against a CA bundle, and the SCU's client side, which
+//! verifies the server against a CA bundle and can present its own certificate for mutual TLS.
 
-/// TLS configuration for DIMSE connections
-pub struct TlsConfig {
-    // TODO: Implement TLS configuration
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+use crate::config::{ClientTlsConfig, TlsConfig};
+use crate::{DimseError, Result};
+
+/// Server configuration for the SCP
+///
+/// With a CA bundle, client certificates signed by it are verified; they are mandatory when
+/// `require_client_cert` is set and optional otherwise.
+pub fn server_config(tls: &TlsConfig) -> Result<Arc<ServerConfig>> {
+    let certs = load_certs(&tls.cert_path)?;
+    let key = load_private_key(&tls.key_path)?;
+    let builder =
+        ServerConfig::builder_with_provider(provider()).with_safe_default_protocol_versions()?;
+
+    let builder = match &tls.ca_bundle_path {
+        Some(ca) => {
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(load_roots(ca)?), provider());
+            let verifier = if tls.require_client_cert {
+                verifier
+            } else {
+                verifier.allow_unauthenticated()
+            };
+            let verifier = verifier
+                .build()
+                .map_err(|e| DimseError::config(format!("Invalid client CA bundle: {}", e)))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None if tls.require_client_cert => {
+            return Err(DimseError::config(
+                "require_client_cert needs a ca_bundle_path to verify client certificates",
+            ))
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Arc::new(builder.with_single_cert(certs, key)?))
+}
+
+/// Client configuration for the SCU
+pub fn client_config(tls: &ClientTlsConfig) -> Result<Arc<ClientConfig>> {
+    let builder = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(load_roots(&tls.ca_bundle_path)?);
+    let config = match (&tls.cert_path, &tls.key_path) {
+        (Some(cert), Some(key)) => {
+            builder.with_client_auth_cert(load_certs(cert)?, load_private_key(key)?)?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(DimseError::config(
+                "Client TLS needs both cert_path and key_path, or neither",
+            ))
+        }
+    };
+    Ok(Arc::new(config))
+}
+
+/// Pin the provider: the workspace links both ring and aws-lc-rs, so there is no default
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn read_pem(path: &Path, what: &str) -> Result<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| DimseError::config(format!("Failed to read {} {:?}: {}", what, path, e)))
 }
 
-impl TlsConfig {
-    /// Create a new TLS configuration
-    pub fn new() -> Self {
-        Self {}
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = read_pem(path, "TLS certificate")?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| DimseError::config(format!("Invalid PEM in {:?}: {}", path, e)))?;
+    if certs.is_empty() {
+        return Err(DimseError::config(format!(
+            "No certificates found in {:?}",
+            path
+        )));
     }
+    Ok(certs)
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let pem = read_pem(path, "TLS private key")?;
+    rustls_pemfile::private_key(&mut pem.as_slice())
+        .map_err(|e| DimseError::config(format!("Invalid PEM in {:?}: {}", path, e)))?
+        .ok_or_else(|| DimseError::config(format!("No private key found in {:?}", path)))
 }
 
-impl Default for TlsConfig {
-    fn default() -> Self {
-        Self::new()
+fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert)?;
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+    /// Self-signed CA that issues certificates into `dir`
+    struct TestCa {
+        dir: tempfile::TempDir,
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    impl TestCa {
+        fn new() -> Self {
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, "Harmony Test CA");
+            let key = KeyPair::generate().unwrap();
+            let cert = params.self_signed(&key).unwrap();
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::write(dir.path().join("ca.pem"), cert.pem()).unwrap();
+            Self { dir, cert, key }
+        }
+
+        fn ca_path(&self) -> std::path::PathBuf {
+            self.dir.path().join("ca.pem")
+        }
+
+        /// Issue a leaf certificate and return its (certificate, key) paths
+        fn issue(
+            &self,
+            name: &str,
+            usage: ExtendedKeyUsagePurpose,
+        ) -> (std::path::PathBuf, std::path::PathBuf) {
+            let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            params.extended_key_usages = vec![usage];
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            let cert_path = self.dir.path().join(format!("{}.pem", name));
+            let key_path = self.dir.path().join(format!("{}.key", name));
+            std::fs::write(&cert_path, cert.pem()).unwrap();
+            std::fs::write(&key_path, key.serialize_pem()).unwrap();
+            (cert_path, key_path)
+        }
+    }
+
+    /// Handshake with a server requiring client certificates and echo one byte back
+    async fn mutual_tls_round_trip(ca: &TestCa, client: ClientTlsConfig) -> std::io::Result<u8> {
+        let (cert_path, key_path) = ca.issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+        let server = server_config(&TlsConfig {
+            cert_path,
+            key_path,
+            ca_bundle_path: Some(ca.ca_path()),
+            require_client_cert: true,
+        })
+        .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = TlsAcceptor::from(server);
+        let peer = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = acceptor.accept(stream).await?;
+            let mut byte = [0u8; 1];
+            stream.read_exact(&mut byte).await?;
+            stream.write_all(&byte).await?;
+            stream.flush().await
+        });
+
+        let connector = TlsConnector::from(client_config(&client).unwrap());
+        let stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let server_name = ServerName::try_from("localhost").unwrap();
+        let result = async {
+            let mut stream = connector.connect(server_name, stream).await?;
+            stream.write_all(&[0x2A]).await?;
+            stream.flush().await?;
+            let mut byte = [0u8; 1];
+            stream.read_exact(&mut byte).await?;
+            Ok::<_, std::io::Error>(byte[0])
+        }
+        .await;
+        let _ = peer.await.unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_accepts_client_certificate_from_ca() {
+        let ca = TestCa::new();
+        let (cert_path, key_path) = ca.issue("harmony-scu", ExtendedKeyUsagePurpose::ClientAuth);
+        let client = ClientTlsConfig {
+            ca_bundle_path: ca.ca_path(),
+            cert_path: Some(cert_path),
+            key_path: Some(key_path),
+            server_name: None,
+        };
+
+        assert_eq!(mutual_tls_round_trip(&ca, client).await.unwrap(), 0x2A);
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_rejects_client_without_certificate() {
+        let ca = TestCa::new();
+        let client = ClientTlsConfig {
+            ca_bundle_path: ca.ca_path(),
+            cert_path: None,
+            key_path: None,
+            server_name: None,
+        };
+
+        assert!(mutual_tls_round_trip(&ca, client).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_rejects_certificate_from_another_ca() {
+        let ca = TestCa::new();
+        let other = TestCa::new();
+        let (cert_path, key_path) = other.issue("harmony-scu", ExtendedKeyUsagePurpose::ClientAuth);
+        let client = ClientTlsConfig {
+            ca_bundle_path: ca.ca_path(),
+            cert_path: Some(cert_path),
+            key_path: Some(key_path),
+            server_name: None,
+        };
+
+        assert!(mutual_tls_round_trip(&ca, client).await.is_err());
+    }
+
+    #[test]
+    fn test_required_client_cert_needs_ca_bundle() {
+        let ca = TestCa::new();
+        let (cert_path, key_path) = ca.issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+        let tls = TlsConfig {
+            cert_path,
+            key_path,
+            ca_bundle_path: None,
+            require_client_cert: true,
+        };
+
+        assert!(matches!(server_config(&tls), Err(DimseError::Config(_))));
     }
 }
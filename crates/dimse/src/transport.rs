@@ -0,0 +1,115 @@
+//! Connections that DIMSE associations run over: plain TCP, or TLS with feature "tls"
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+use crate::config::{ClientTlsConfig, RemoteNode};
+use crate::{DimseError, Result};
+
+/// An accepted or established connection, encrypted or not
+#[derive(Debug)]
+pub(crate) enum Connection {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<tokio_rustls::TlsStream<TcpStream>>),
+}
+
+impl Connection {
+    /// The TCP socket underneath, for checks that must not go through TLS
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            Connection::Plain(stream) => stream,
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream.get_ref().0,
+        }
+    }
+}
+
+/// Connect to `node`, with TLS when the node has it enabled
+///
+/// `tls` supplies the CA bundle the server is verified against and, for mutual TLS, the
+/// client certificate to present.
+pub(crate) async fn connect(
+    node: &RemoteNode,
+    tls: Option<&ClientTlsConfig>,
+) -> Result<Connection> {
+    let stream = TcpStream::connect((node.host.as_str(), node.port)).await?;
+    if !node.use_tls {
+        return Ok(Connection::Plain(stream));
+    }
+    let Some(tls) = tls else {
+        return Err(DimseError::config(format!(
+            "TLS to {} needs a client TLS configuration with a CA bundle",
+            node.ae_title
+        )));
+    };
+
+    #[cfg(feature = "tls")]
+    {
+        use tokio_rustls::rustls::pki_types::ServerName;
+
+        let config = crate::tls::client_config(tls)?;
+        let name = tls.server_name.as_deref().unwrap_or(&node.host).to_string();
+        let server_name = ServerName::try_from(name)
+            .map_err(|e| DimseError::config(format!("Invalid TLS server name: {}", e)))?;
+        let stream = tokio_rustls::TlsConnector::from(config)
+            .connect(server_name, stream)
+            .await?;
+        Ok(Connection::Tls(Box::new(stream.into())))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    {
+        let _ = (stream, tls);
+        Err(DimseError::NotSupported(
+            "TLS connections require feature 'tls'".into(),
+        ))
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
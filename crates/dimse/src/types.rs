@@ -6,6 +6,11 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
 
+/// Patient Root Query/Retrieve Information Model - FIND
+pub const PATIENT_ROOT_FIND: &str = "1.2.840.10008.5.1.4.1.2.1.1";
+/// Study Root Query/Retrieve Information Model - FIND
+pub const STUDY_ROOT_FIND: &str = "1.2.840.10008.5.1.4.1.2.2.1";
+
 /// Represents a DICOM dataset as either in-memory bytes or a file path
 #[derive(Debug, Clone)]
 pub enum DatasetStream {
@@ -63,6 +68,10 @@ pub struct DatasetMetadata {
 
     /// Size of the dataset in bytes
     pub size_bytes: Option<u64>,
+
+    /// DIMSE status of the response that carried this dataset (e.g. 0xFF00/0xFF01 for C-FIND)
+    #[serde(default)]
+    pub dimse_status: Option<u16>,
 }
 
 /// DIMSE command types
@@ -89,6 +98,148 @@ pub struct FindQuery {
 
     /// Maximum number of results to return (0 = unlimited)
     pub max_results: u32,
+
+    /// Modality Worklist query; when set it replaces `query_level` and `parameters`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worklist: Option<crate::worklist::WorklistQuery>,
+
+    /// Relational rather than hierarchical matching: the unique keys of the levels above
+    /// `query_level` may be left out, and below PATIENT the query uses the Study Root model
+    #[serde(default)]
+    pub relational: bool,
+
+    /// Ask the peer to match date and time keys as combined date-time values
+    #[serde(default)]
+    pub date_time_matching: bool,
+
+    /// Ask the peer for fuzzy semantic matching of person names
+    #[serde(default)]
+    pub fuzzy_matching: bool,
+
+    /// Ask the peer to adjust date and time keys by the Timezone Offset From UTC in the query
+    #[serde(default)]
+    pub timezone_adjustment: bool,
+}
+
+/// Query/Retrieve C-FIND features negotiated with SOP Class Extended Negotiation
+/// (PS3.4 C.5.1.1.1)
+///
+/// Proposed by the SCU for the query's information model; the peer's answer says which it
+/// supports. A peer that does not answer supports none of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryFeatures {
+    /// Relational queries
+    pub relational: bool,
+    /// Combined date and time range matching
+    pub date_time_matching: bool,
+    /// Fuzzy semantic matching of person names
+    pub fuzzy_matching: bool,
+    /// Timezone query adjustment
+    pub timezone_adjustment: bool,
+}
+
+impl QueryFeatures {
+    /// Whether no feature is set, so there is nothing to negotiate
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Service-class-application-information bytes: one per feature, 1 when set
+    pub fn to_bytes(&self) -> Vec<u8> {
+        vec![
+            self.relational as u8,
+            self.date_time_matching as u8,
+            self.fuzzy_matching as u8,
+            self.timezone_adjustment as u8,
+        ]
+    }
+
+    /// Features from service-class-application-information bytes; missing bytes are unset
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let set = |i: usize| bytes.get(i) == Some(&1);
+        Self {
+            relational: set(0),
+            date_time_matching: set(1),
+            fuzzy_matching: set(2),
+            timezone_adjustment: set(3),
+        }
+    }
+}
+
+/// How a C-FIND key is matched, with the value(s) it is matched against (PS3.4 C.2.2.2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchType {
+    /// Single value matching
+    Exact(String),
+    /// Wild card matching with `*` and `?`
+    Wildcard(String),
+    /// Range matching of dates and times; an open end is `None`
+    Range {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// List of UID matching, or any of several values
+    List(Vec<String>),
+    /// Universal matching: the key is only returned
+    Universal,
+}
+
+impl MatchType {
+    /// Match from a query metadata `match_type` name and the key's values in the identifier
+    ///
+    /// A RANGE takes either its two ends as separate values, with an empty value for an open
+    /// end, or a single value already written as `from-to`. SEQUENCE matching is not supported.
+    pub fn from_metadata(match_type: &str, values: &[String]) -> Result<Self, String> {
+        let single = || match values {
+            [value] => Ok(value.clone()),
+            _ => Err(format!(
+                "{} matching takes one value, got {}",
+                match_type,
+                values.len()
+            )),
+        };
+        let end = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        match match_type.to_ascii_uppercase().as_str() {
+            "EXACT" => Ok(MatchType::Exact(single()?)),
+            "WILDCARD" => Ok(MatchType::Wildcard(single()?)),
+            "RANGE" => match values {
+                [from, to] => Ok(MatchType::Range {
+                    from: end(from),
+                    to: end(to),
+                }),
+                [value] if value.matches('-').count() == 1 => {
+                    let (from, to) = value.split_once('-').unwrap_or_default();
+                    Ok(MatchType::Range {
+                        from: end(from),
+                        to: end(to),
+                    })
+                }
+                _ => Err(format!("Invalid RANGE values {:?}", values)),
+            },
+            "LIST" if !values.is_empty() => Ok(MatchType::List(values.to_vec())),
+            "LIST" => Err("LIST matching needs at least one value".to_string()),
+            "RETURN_KEY" | "UNIVERSAL" => Ok(MatchType::Universal),
+            other => Err(format!("Unsupported match type '{}'", other)),
+        }
+    }
+
+    /// The key's value in the identifier
+    pub fn to_value(&self) -> String {
+        match self {
+            MatchType::Exact(value) | MatchType::Wildcard(value) => value.clone(),
+            MatchType::Range {
+                from: None,
+                to: None,
+            }
+            | MatchType::Universal => String::new(),
+            MatchType::Range { from, to } => format!(
+                "{}-{}",
+                from.as_deref().unwrap_or(""),
+                to.as_deref().unwrap_or("")
+            ),
+            MatchType::List(values) => values.join("\\"),
+        }
+    }
 }
 
 /// Query parameters for C-MOVE operations
@@ -117,6 +268,95 @@ pub struct GetQuery {
     pub parameters: std::collections::HashMap<String, String>,
 }
 
+/// Presentation context to propose when probing a remote node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresentationContextProposal {
+    /// Abstract syntax (SOP Class UID)
+    pub abstract_syntax: String,
+
+    /// Transfer syntaxes to offer (empty uses the SCU's preferred transfer syntaxes)
+    #[serde(default)]
+    pub transfer_syntaxes: Vec<String>,
+}
+
+impl PresentationContextProposal {
+    pub fn new(abstract_syntax: impl Into<String>, transfer_syntaxes: Vec<String>) -> Self {
+        Self {
+            abstract_syntax: abstract_syntax.into(),
+            transfer_syntaxes,
+        }
+    }
+}
+
+/// Negotiation outcome for one proposed presentation context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiatedContext {
+    /// Abstract syntax (SOP Class UID) that was proposed
+    pub abstract_syntax: String,
+
+    /// Whether the context was sent at all; contexts beyond the node's
+    /// `max_presentation_contexts` are left out of the proposal
+    pub proposed: bool,
+
+    /// Whether the remote node accepted the context
+    pub accepted: bool,
+
+    /// Transfer syntax chosen by the remote node, when accepted
+    pub transfer_syntax: Option<String>,
+}
+
+/// Result of a presentation-context negotiation probe
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SopClassSupport {
+    /// One entry per proposed context, in proposal order
+    pub contexts: Vec<NegotiatedContext>,
+
+    /// Maximum PDU lengths agreed for the probe association
+    #[serde(default)]
+    pub association: Option<crate::association::AssociationInfo>,
+}
+
+impl SopClassSupport {
+    /// Transfer syntax accepted for `abstract_syntax`, if any context for it was accepted
+    pub fn accepted_transfer_syntax(&self, abstract_syntax: &str) -> Option<&str> {
+        self.contexts
+            .iter()
+            .find(|c| c.accepted && c.abstract_syntax == abstract_syntax)
+            .and_then(|c| c.transfer_syntax.as_deref())
+    }
+
+    /// Whether every proposed context was accepted
+    pub fn all_accepted(&self) -> bool {
+        self.contexts.iter().all(|c| c.accepted)
+    }
+}
+
+/// Outcome of a C-GET
+#[derive(Debug, Clone, Default)]
+pub struct GetReport {
+    /// Status of the final C-GET response
+    pub status: u16,
+
+    /// Sub-operation counts from the final C-GET response
+    pub completed: u16,
+    pub failed: u16,
+    pub warning: u16,
+
+    /// Instances received in C-STORE sub-operations, as files in the output directory
+    pub instances: Vec<DatasetStream>,
+}
+
+/// Instances retrieved by a C-MOVE, together with the outcome of its final response
+#[derive(Debug)]
+pub struct MoveStream {
+    /// Instances received at the move destination, yielded as they are taken off disk
+    pub instances: tokio_stream::wrappers::ReceiverStream<crate::Result<DatasetStream>>,
+
+    /// Sub-operation counts from the final C-MOVE response, sent before the instances are
+    /// yielded; closed without a value when the move failed or the peer reported none
+    pub sub_operations: tokio::sync::oneshot::Receiver<crate::command::SubOperations>,
+}
+
 /// DICOM query/retrieve levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QueryLevel {
@@ -156,6 +396,24 @@ pub enum DimseStatus {
     Warning(u16), // DICOM status code
 }
 
+impl DimseStatus {
+    /// Pending: matches are continuing
+    pub const PENDING: u16 = 0xFF00;
+    /// Pending: matches are continuing, but one or more optional keys were not supported
+    pub const PENDING_WARNING: u16 = 0xFF01;
+
+    /// Map a raw DIMSE status code onto a status category
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            0x0000 => DimseStatus::Success,
+            Self::PENDING | Self::PENDING_WARNING => DimseStatus::Pending,
+            0xFE00 => DimseStatus::Cancel,
+            0x0001 | 0x0107 | 0x0116 | 0xB000..=0xBFFF => DimseStatus::Warning(code),
+            _ => DimseStatus::Failure(code),
+        }
+    }
+}
+
 impl DatasetStream {
     /// Create a new in-memory dataset
     pub fn from_bytes(data: Bytes) -> Self {
@@ -174,6 +432,43 @@ impl DatasetStream {
         }
     }
 
+    /// Create a file-based dataset for an instance retrieved over the network
+    ///
+    /// The file meta group records the transfer syntax the instance was received in, which is
+    /// the one negotiated for its presentation context; it is copied to
+    /// `metadata.transfer_syntax` together with the instance identifiers.
+    pub fn from_received_file(path: PathBuf, delete_on_drop: bool) -> Self {
+        let mut metadata = DatasetMetadata::new();
+        let header = dicom_object::OpenFileOptions::new()
+            .read_until(dicom_dictionary_std::tags::PIXEL_DATA)
+            .open_file(&path);
+        if let Ok(obj) = header {
+            let text = |tag| {
+                obj.element_opt(tag)
+                    .ok()
+                    .flatten()
+                    .and_then(|e| e.to_str().ok())
+                    .map(|s| s.trim_end_matches(['\0', ' ']).to_string())
+            };
+            metadata.transfer_syntax = Some(
+                obj.meta()
+                    .transfer_syntax()
+                    .trim_end_matches(['\0', ' '])
+                    .to_string(),
+            );
+            metadata.sop_class_uid = text(dicom_dictionary_std::tags::SOP_CLASS_UID);
+            metadata.sop_instance_uid = text(dicom_dictionary_std::tags::SOP_INSTANCE_UID);
+            metadata.study_instance_uid = text(dicom_dictionary_std::tags::STUDY_INSTANCE_UID);
+            metadata.series_instance_uid = text(dicom_dictionary_std::tags::SERIES_INSTANCE_UID);
+            metadata.patient_id = text(dicom_dictionary_std::tags::PATIENT_ID);
+        }
+        Self::File {
+            path,
+            metadata,
+            delete_on_drop,
+        }
+    }
+
     /// Create a new dataset from a parsed DICOM object
     pub fn from_object(object: InMemDicomObject) -> Self {
         let mut metadata = DatasetMetadata::new();
@@ -203,6 +498,11 @@ impl DatasetStream {
         }
     }
 
+    /// Transfer syntax the dataset was received in, when known
+    pub fn transfer_syntax(&self) -> Option<&str> {
+        self.metadata().transfer_syntax.as_deref()
+    }
+
     /// Get mutable metadata for this dataset
     pub fn metadata_mut(&mut self) -> &mut DatasetMetadata {
         match self {
@@ -229,14 +529,27 @@ impl DatasetStream {
     }
 
     /// Convert to a parsed DICOM object
+    ///
+    /// In-memory data is read as a Part 10 file, with or without the 128-byte preamble, which
+    /// is how C-FIND matches are delivered; the file meta group is dropped.
     pub async fn to_object(&self) -> crate::error::Result<InMemDicomObject> {
+        let parse_failed = |e: String| {
+            crate::error::DimseError::DicomParsing(format!("Cannot read dataset: {}", e))
+        };
         match self {
             Self::Object { object, .. } => Ok(object.clone()),
-            _ => {
-                // TODO: Implement proper DICOM object parsing
-                // For now, return empty object as placeholder
-                Ok(dicom_object::InMemDicomObject::new_empty())
+            Self::Memory { data, .. } => {
+                let data = match data.get(128..132) {
+                    Some(b"DICM") => &data[128..],
+                    _ => &data[..],
+                };
+                dicom_object::from_reader(data)
+                    .map(|obj| obj.into_inner())
+                    .map_err(|e| parse_failed(e.to_string()))
             }
+            Self::File { path, .. } => dicom_object::open_file(path)
+                .map(|obj| obj.into_inner())
+                .map_err(|e| parse_failed(e.to_string())),
         }
     }
 
@@ -271,8 +584,15 @@ impl DatasetMetadata {
             patient_id: None,
             timestamp: chrono::Utc::now(),
             size_bytes: None,
+            dimse_status: None,
         }
     }
+
+    /// Whether the response carrying this dataset was "pending with warning"
+    /// (0xFF01: one or more optional keys were not supported by the peer)
+    pub fn is_pending_warning(&self) -> bool {
+        self.dimse_status == Some(DimseStatus::PENDING_WARNING)
+    }
 }
 
 impl Default for DatasetMetadata {
@@ -293,6 +613,25 @@ impl FindQuery {
             query_level: QueryLevel::Patient,
             parameters,
             max_results: 0,
+            worklist: None,
+            relational: false,
+            date_time_matching: false,
+            fuzzy_matching: false,
+            timezone_adjustment: false,
+        }
+    }
+
+    /// Create a query at `query_level` without any keys
+    pub fn new(query_level: QueryLevel) -> Self {
+        Self {
+            query_level,
+            parameters: std::collections::HashMap::new(),
+            max_results: 0,
+            worklist: None,
+            relational: false,
+            date_time_matching: false,
+            fuzzy_matching: false,
+            timezone_adjustment: false,
         }
     }
 
@@ -307,6 +646,25 @@ impl FindQuery {
             query_level: QueryLevel::Study,
             parameters,
             max_results: 0,
+            worklist: None,
+            relational: false,
+            date_time_matching: false,
+            fuzzy_matching: false,
+            timezone_adjustment: false,
+        }
+    }
+
+    /// Create a Modality Worklist query
+    pub fn worklist(query: crate::worklist::WorklistQuery) -> Self {
+        Self {
+            query_level: QueryLevel::Patient,
+            parameters: std::collections::HashMap::new(),
+            max_results: query.max_results,
+            worklist: Some(query),
+            relational: false,
+            date_time_matching: false,
+            fuzzy_matching: false,
+            timezone_adjustment: false,
         }
     }
 
@@ -321,6 +679,88 @@ impl FindQuery {
         self.max_results = max;
         self
     }
+
+    /// Add a query key matched as `match_type` describes
+    pub fn with_match(mut self, tag: impl Into<String>, match_type: MatchType) -> Self {
+        self.parameters.insert(tag.into(), match_type.to_value());
+        self
+    }
+
+    /// Ask for relational instead of hierarchical matching
+    ///
+    /// This and the other feature builders below are proposed through extended negotiation,
+    /// which DCMTK `findscu` cannot do: [`crate::DimseScu::find_negotiated`] sends a query
+    /// asking for any of them over a native association, pooled when
+    /// `DimseConfig::association_pool_ttl_ms` is set and opened for the query alone otherwise.
+    pub fn with_relational(mut self, relational: bool) -> Self {
+        self.relational = relational;
+        self
+    }
+
+    /// Ask for combined date-time matching of date and time keys
+    pub fn with_date_time_matching(mut self, enabled: bool) -> Self {
+        self.date_time_matching = enabled;
+        self
+    }
+
+    /// Ask for fuzzy semantic matching of person names
+    pub fn with_fuzzy_matching(mut self, enabled: bool) -> Self {
+        self.fuzzy_matching = enabled;
+        self
+    }
+
+    /// Ask for timezone adjustment of date and time keys
+    pub fn with_timezone_adjustment(mut self, enabled: bool) -> Self {
+        self.timezone_adjustment = enabled;
+        self
+    }
+
+    /// Features to propose through SOP Class Extended Negotiation; none for worklist queries
+    pub fn features(&self) -> QueryFeatures {
+        if self.worklist.is_some() {
+            return QueryFeatures::default();
+        }
+        QueryFeatures {
+            relational: self.relational,
+            date_time_matching: self.date_time_matching,
+            fuzzy_matching: self.fuzzy_matching,
+            timezone_adjustment: self.timezone_adjustment,
+        }
+    }
+
+    /// SOP Class UID of the information model the query is sent under
+    ///
+    /// Hierarchical queries use Patient Root. Relational queries below PATIENT level use Study
+    /// Root, where no patient key is needed either.
+    pub fn information_model(&self) -> &'static str {
+        match (&self.worklist, self.relational, self.query_level) {
+            (Some(_), _, _) => crate::worklist::MODALITY_WORKLIST_FIND,
+            (None, true, level) if level != QueryLevel::Patient => STUDY_ROOT_FIND,
+            _ => PATIENT_ROOT_FIND,
+        }
+    }
+
+    /// Build the C-FIND identifier: the worklist keys, or Query/Retrieve Level, plus the
+    /// parameters
+    pub fn to_identifier(&self) -> Result<InMemDicomObject, String> {
+        use crate::worklist::{element, resolve_tag};
+
+        let mut identifier = match &self.worklist {
+            Some(worklist) => worklist.to_identifier()?,
+            None => {
+                let mut identifier = InMemDicomObject::new_empty();
+                identifier.put(element(
+                    dicom_dictionary_std::tags::QUERY_RETRIEVE_LEVEL,
+                    &self.query_level.to_string(),
+                ));
+                identifier
+            }
+        };
+        for (key, value) in &self.parameters {
+            identifier.put(element(resolve_tag(key)?, value));
+        }
+        Ok(identifier)
+    }
 }
 
 impl MoveQuery {
@@ -361,6 +801,21 @@ impl GetQuery {
         self.parameters.insert(tag.into(), value.into());
         self
     }
+
+    /// Build the C-GET identifier: the parameters plus Query/Retrieve Level
+    pub fn to_identifier(&self) -> Result<InMemDicomObject, String> {
+        use crate::worklist::{element, resolve_tag};
+
+        let mut identifier = InMemDicomObject::new_empty();
+        for (key, value) in &self.parameters {
+            identifier.put(element(resolve_tag(key)?, value));
+        }
+        identifier.put(element(
+            dicom_dictionary_std::tags::QUERY_RETRIEVE_LEVEL,
+            &self.query_level.to_string(),
+        ));
+        Ok(identifier)
+    }
 }
 
 impl std::fmt::Display for QueryLevel {
@@ -425,6 +880,49 @@ mod tests {
         assert!(!metadata.id.is_nil());
     }
 
+    #[tokio::test]
+    async fn test_memory_dataset_parses_part10_bytes() {
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+
+        let mut identifier = InMemDicomObject::new_empty();
+        identifier.put(DataElement::new(
+            tags::PATIENT_ID,
+            VR::LO,
+            PrimitiveValue::from("P1"),
+        ));
+        let mut bytes = Vec::new();
+        identifier
+            .with_meta(
+                dicom_object::meta::FileMetaTableBuilder::new()
+                    .media_storage_sop_class_uid(PATIENT_ROOT_FIND)
+                    .media_storage_sop_instance_uid("1.2.3")
+                    .transfer_syntax("1.2.840.10008.1.2.1"),
+            )
+            .unwrap()
+            .write_all(&mut bytes)
+            .unwrap();
+
+        // With and without the preamble
+        for data in [bytes.clone(), bytes[128..].to_vec()] {
+            let object = DatasetStream::from_bytes(Bytes::from(data))
+                .to_object()
+                .await
+                .unwrap();
+            assert_eq!(
+                object.element(tags::PATIENT_ID).unwrap().to_str().unwrap(),
+                "P1"
+            );
+            assert!(object
+                .element_opt(tags::TRANSFER_SYNTAX_UID)
+                .unwrap()
+                .is_none());
+        }
+
+        let garbage = DatasetStream::from_bytes(Bytes::from_static(b"not dicom"));
+        assert!(garbage.to_object().await.is_err());
+    }
+
     #[test]
     fn test_find_query_builder() {
         let query = FindQuery::patient(Some("12345".to_string()))
@@ -443,6 +941,60 @@ mod tests {
         assert_eq!(query.max_results, 100);
     }
 
+    #[test]
+    fn test_date_range_study_query_builds_range_value() {
+        use dicom_dictionary_std::tags;
+
+        let values = ["20230101".to_string(), "20231231".to_string()];
+        let range = MatchType::from_metadata("RANGE", &values).unwrap();
+        let query = FindQuery::study(None).with_match("00080020", range);
+        let identifier = query.to_identifier().unwrap();
+        let date = identifier.element(tags::STUDY_DATE).unwrap();
+        assert_eq!(date.to_str().unwrap(), "20230101-20231231");
+
+        let open = MatchType::from_metadata("range", &["20230101-".to_string()]).unwrap();
+        assert_eq!(
+            open,
+            MatchType::Range {
+                from: Some("20230101".to_string()),
+                to: None
+            }
+        );
+        assert_eq!(open.to_value(), "20230101-");
+        let until = MatchType::from_metadata("RANGE", &[String::new(), "20231231".to_string()]);
+        assert_eq!(until.unwrap().to_value(), "-20231231");
+    }
+
+    #[test]
+    fn test_list_wildcard_and_unsupported_matches() {
+        let uids = ["1.2.3".to_string(), "1.2.4".to_string()];
+        let list = MatchType::from_metadata("LIST", &uids).unwrap();
+        assert_eq!(list.to_value(), "1.2.3\\1.2.4");
+        let wildcard = MatchType::from_metadata("WILDCARD", &["DOE*".to_string()]).unwrap();
+        assert_eq!(wildcard.to_value(), "DOE*");
+        assert_eq!(
+            MatchType::from_metadata("RETURN_KEY", &[]).unwrap(),
+            MatchType::Universal
+        );
+
+        assert!(MatchType::from_metadata("EXACT", &uids).is_err());
+        assert!(MatchType::from_metadata("SEQUENCE", &[]).is_err());
+    }
+
+    #[test]
+    fn test_dimse_status_from_code() {
+        assert_eq!(DimseStatus::from_code(0x0000), DimseStatus::Success);
+        assert_eq!(DimseStatus::from_code(0xFF00), DimseStatus::Pending);
+        assert_eq!(DimseStatus::from_code(0xFF01), DimseStatus::Pending);
+        assert_eq!(DimseStatus::from_code(0xB000), DimseStatus::Warning(0xB000));
+        assert_eq!(DimseStatus::from_code(0xA700), DimseStatus::Failure(0xA700));
+
+        let mut metadata = DatasetMetadata::new();
+        assert!(!metadata.is_pending_warning());
+        metadata.dimse_status = Some(0xFF01);
+        assert!(metadata.is_pending_warning());
+    }
+
     #[test]
     fn test_query_level_parsing() {
         assert_eq!(
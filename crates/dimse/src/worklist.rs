@@ -0,0 +1,275 @@
+//! Modality Worklist C-FIND
+//!
+//! Worklist queries use their own information model rather than a query/retrieve level. Most
+//! matching keys live inside the Scheduled Procedure Step Sequence, so a [`WorklistQuery`]
+//! keeps those apart from the top-level (patient, visit and request) keys and builds the
+//! nested identifier from both.
+
+use std::collections::HashMap;
+
+use dicom_core::dictionary::{DataDictionary, VirtualVr};
+use dicom_core::value::DataSetSequence;
+use dicom_core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom_dictionary_std::{tags, StandardDataDictionary};
+use dicom_object::InMemDicomObject;
+use serde::{Deserialize, Serialize};
+
+/// Modality Worklist Information Model - FIND SOP Class UID
+pub const MODALITY_WORKLIST_FIND: &str = "1.2.840.10008.5.1.4.31";
+
+/// Top-level keys returned by default
+const DEFAULT_RETURN_KEYS: &[&str] = &[
+    "PatientName",
+    "PatientID",
+    "PatientBirthDate",
+    "PatientSex",
+    "AccessionNumber",
+    "StudyInstanceUID",
+    "RequestedProcedureID",
+    "RequestedProcedureDescription",
+];
+
+/// Scheduled Procedure Step Sequence keys returned by default
+const DEFAULT_STEP_RETURN_KEYS: &[&str] = &[
+    "ScheduledStationAETitle",
+    "ScheduledProcedureStepStartDate",
+    "ScheduledProcedureStepStartTime",
+    "Modality",
+    "ScheduledPerformingPhysicianName",
+    "ScheduledProcedureStepDescription",
+    "ScheduledProcedureStepID",
+];
+
+/// Query parameters for a Modality Worklist C-FIND
+///
+/// Keys are tag keywords (`PatientID`) or eight hex digits (`00100020`); an empty value makes
+/// the key a return key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorklistQuery {
+    /// Top-level matching and return keys
+    pub parameters: HashMap<String, String>,
+
+    /// Keys inside the Scheduled Procedure Step Sequence item
+    pub scheduled_step: HashMap<String, String>,
+
+    /// Maximum number of results to return (0 = unlimited)
+    pub max_results: u32,
+}
+
+impl WorklistQuery {
+    /// Create a query returning the usual worklist attributes for every scheduled step
+    pub fn new() -> Self {
+        let empty = |keys: &[&str]| {
+            keys.iter()
+                .map(|k| (k.to_string(), String::new()))
+                .collect()
+        };
+        Self {
+            parameters: empty(DEFAULT_RETURN_KEYS),
+            scheduled_step: empty(DEFAULT_STEP_RETURN_KEYS),
+            max_results: 0,
+        }
+    }
+
+    /// Only match steps scheduled on the given station
+    pub fn with_station_aet(self, aet: impl Into<String>) -> Self {
+        self.with_step_parameter("ScheduledStationAETitle", aet)
+    }
+
+    /// Only match steps for the given modality
+    pub fn with_modality(self, modality: impl Into<String>) -> Self {
+        self.with_step_parameter("Modality", modality)
+    }
+
+    /// Only match steps starting on the given date or date range (`YYYYMMDD[-YYYYMMDD]`)
+    pub fn with_scheduled_date(self, date: impl Into<String>) -> Self {
+        self.with_step_parameter("ScheduledProcedureStepStartDate", date)
+    }
+
+    /// Add a top-level query parameter
+    pub fn with_parameter(mut self, tag: impl Into<String>, value: impl Into<String>) -> Self {
+        self.parameters.insert(tag.into(), value.into());
+        self
+    }
+
+    /// Add a Scheduled Procedure Step Sequence parameter
+    pub fn with_step_parameter(mut self, tag: impl Into<String>, value: impl Into<String>) -> Self {
+        self.scheduled_step.insert(tag.into(), value.into());
+        self
+    }
+
+    /// Set maximum number of results
+    pub fn with_max_results(mut self, max: u32) -> Self {
+        self.max_results = max;
+        self
+    }
+
+    /// Build the C-FIND identifier, nesting the step keys in one sequence item
+    pub fn to_identifier(&self) -> Result<InMemDicomObject, String> {
+        let mut step = InMemDicomObject::new_empty();
+        for (key, value) in &self.scheduled_step {
+            step.put(element(resolve_tag(key)?, value));
+        }
+        let mut identifier = InMemDicomObject::new_empty();
+        for (key, value) in &self.parameters {
+            identifier.put(element(resolve_tag(key)?, value));
+        }
+        identifier.put(DataElement::new(
+            tags::SCHEDULED_PROCEDURE_STEP_SEQUENCE,
+            VR::SQ,
+            DataSetSequence::from(vec![step]),
+        ));
+        Ok(identifier)
+    }
+
+    /// Read the query out of a received C-FIND identifier
+    ///
+    /// Keys are named by their tag as eight hex digits. The first Scheduled Procedure Step
+    /// Sequence item supplies the step keys; other sequences are not matched on.
+    pub fn from_identifier(identifier: &InMemDicomObject) -> Self {
+        let scheduled_step = identifier
+            .element(tags::SCHEDULED_PROCEDURE_STEP_SEQUENCE)
+            .ok()
+            .and_then(|sequence| sequence.items()?.first().map(query_keys))
+            .unwrap_or_default();
+        Self {
+            parameters: query_keys(identifier),
+            scheduled_step,
+            max_results: 0,
+        }
+    }
+
+    /// `-k` keys for `findscu -W`, using DCMTK's `Sequence[0].Key` path syntax for step keys
+    pub fn dcmtk_keys(&self) -> Vec<String> {
+        let key = |k: &str| {
+            if k.len() == 8 && k.chars().all(|c| c.is_ascii_hexdigit()) {
+                format!("({},{})", &k[0..4], &k[4..8])
+            } else {
+                k.to_string()
+            }
+        };
+        let mut keys: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|(k, v)| format!("{}={}", key(k), v))
+            .collect();
+        keys.extend(
+            self.scheduled_step
+                .iter()
+                .map(|(k, v)| format!("ScheduledProcedureStepSequence[0].{}={}", key(k), v)),
+        );
+        keys.sort();
+        keys
+    }
+}
+
+impl Default for WorklistQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tag for a query key given as `GGGGEEEE` hex or a dictionary keyword
+pub(crate) fn resolve_tag(name: &str) -> Result<Tag, String> {
+    let name = name.trim();
+    if name.len() == 8 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+        let group = u16::from_str_radix(&name[0..4], 16).map_err(|e| e.to_string())?;
+        let element = u16::from_str_radix(&name[4..8], 16).map_err(|e| e.to_string())?;
+        return Ok(Tag(group, element));
+    }
+    StandardDataDictionary
+        .by_name(name)
+        .map(|entry| entry.tag.inner())
+        .ok_or_else(|| format!("Unknown tag '{}' in query", name))
+}
+
+/// The non-sequence keys of an identifier, by hex tag, with their values as sent
+fn query_keys(object: &InMemDicomObject) -> HashMap<String, String> {
+    object
+        .iter()
+        .filter(|element| element.vr() != VR::SQ && element.tag() != tags::SPECIFIC_CHARACTER_SET)
+        .map(|element| {
+            let tag = element.tag();
+            let value = element
+                .to_str()
+                .map(|v| v.trim_end_matches([' ', '\0']).to_string())
+                .unwrap_or_default();
+            (format!("{:04X}{:04X}", tag.group(), tag.element()), value)
+        })
+        .collect()
+}
+
+/// Query key element, empty when `value` is (a return key)
+pub(crate) fn element(tag: Tag, value: &str) -> DataElement<InMemDicomObject> {
+    let vr = match StandardDataDictionary.by_tag(tag).map(|entry| entry.vr) {
+        Some(VirtualVr::Exact(vr)) => vr,
+        _ => VR::LO,
+    };
+    if value.is_empty() {
+        DataElement::new(tag, vr, PrimitiveValue::Empty)
+    } else {
+        DataElement::new(tag, vr, PrimitiveValue::from(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_station_aet_filter_builds_step_sequence() {
+        let query = WorklistQuery::new()
+            .with_station_aet("CT01")
+            .with_parameter("PatientID", "12345");
+        let identifier = query.to_identifier().unwrap();
+
+        let patient_id = identifier.element(tags::PATIENT_ID).unwrap();
+        assert_eq!(patient_id.to_str().unwrap(), "12345");
+        assert!(identifier
+            .element(tags::ACCESSION_NUMBER)
+            .unwrap()
+            .is_empty());
+        // Step keys are not at the top level
+        assert!(identifier
+            .element(tags::SCHEDULED_STATION_AE_TITLE)
+            .is_err());
+
+        let sequence = identifier
+            .element(tags::SCHEDULED_PROCEDURE_STEP_SEQUENCE)
+            .unwrap();
+        assert_eq!(sequence.vr(), VR::SQ);
+        let items = sequence.items().unwrap();
+        assert_eq!(items.len(), 1);
+        let station = items[0].element(tags::SCHEDULED_STATION_AE_TITLE).unwrap();
+        assert_eq!(station.vr(), VR::AE);
+        assert_eq!(station.to_str().unwrap(), "CT01");
+        assert!(items[0].element(tags::MODALITY).unwrap().is_empty());
+
+        let keys = query.dcmtk_keys();
+        assert!(keys.contains(
+            &"ScheduledProcedureStepSequence[0].ScheduledStationAETitle=CT01".to_string()
+        ));
+        assert!(keys.contains(&"PatientID=12345".to_string()));
+
+        assert!(WorklistQuery::new()
+            .with_step_parameter("NotATag", "x")
+            .to_identifier()
+            .is_err());
+    }
+
+    #[test]
+    fn test_query_read_back_from_identifier() {
+        let identifier = WorklistQuery::new()
+            .with_station_aet("CT01")
+            .with_parameter("PatientID", "12345")
+            .to_identifier()
+            .unwrap();
+        let query = WorklistQuery::from_identifier(&identifier);
+
+        assert_eq!(query.parameters["00100020"], "12345");
+        assert_eq!(query.parameters["00080050"], "");
+        assert!(!query.parameters.contains_key("00400100"));
+        assert_eq!(query.scheduled_step["00400001"], "CT01");
+        assert_eq!(query.scheduled_step["00080060"], "");
+    }
+}
@@ -1,7 +1,10 @@
+use arc_swap::ArcSwap;
 use fluvio_jolt::{transform, TransformSpec};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::Deserialize;
 use serde_json::Value;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -10,19 +13,64 @@ pub enum TransformError {
     FileRead(#[from] std::io::Error),
     #[error("Failed to parse JOLT spec JSON: {0}")]
     SpecParse(#[from] serde_json::Error),
-    #[error("JOLT transformation failed: {0}")]
-    TransformFailed(String),
+    #[error("Failed to parse JOLT spec YAML: {0}")]
+    SpecParseYaml(#[from] serde_yaml::Error),
+    #[error("JOLT spec is neither valid JSON ({json}) nor valid YAML ({yaml})")]
+    SpecParseAmbiguous {
+        json: serde_json::Error,
+        yaml: serde_yaml::Error,
+    },
+    #[error("Failed to load JOLT spec_paths[{index}] ({path}): {source}")]
+    SpecLoad {
+        index: usize,
+        path: String,
+        #[source]
+        source: Box<TransformError>,
+    },
+    #[error("JOLT transformation failed at spec_paths[{index}]: {message}")]
+    TransformFailed { index: usize, message: String },
+    #[error("Invalid JOLT transform config: {0}")]
+    InvalidConfig(String),
+    #[error("Failed to watch JOLT spec file: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("Invalid subtree JSON Pointer '{0}': must be empty or start with '/'")]
+    InvalidPointer(String),
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct TransformConfig {
+    /// Single spec file; shorthand for a one-element `spec_paths`
+    #[serde(default)]
     pub spec_path: String,
+    /// Spec files applied in order, each one transforming the output of the previous
+    #[serde(default)]
+    pub spec_paths: Vec<String>,
     /// Apply transform on which direction: "left", "right", or "both" (default)
     #[serde(default = "default_apply")]
     pub apply: String,
     /// Whether to fail the request on transform errors
     #[serde(default = "default_fail_on_error")]
     pub fail_on_error: bool,
+    /// JSON Pointer (RFC 6901) to the subtree to transform; the rest of the document is
+    /// left as-is. Unset or empty transforms the whole document.
+    #[serde(default)]
+    pub subtree_pointer: Option<String>,
+}
+
+impl TransformConfig {
+    /// The spec files to apply, in order
+    pub fn spec_paths(&self) -> Result<Vec<&str>, TransformError> {
+        match (self.spec_path.is_empty(), self.spec_paths.is_empty()) {
+            (false, true) => Ok(vec![self.spec_path.as_str()]),
+            (true, false) => Ok(self.spec_paths.iter().map(String::as_str).collect()),
+            (false, false) => Err(TransformError::InvalidConfig(
+                "set either 'spec_path' or 'spec_paths', not both".to_string(),
+            )),
+            (true, true) => Err(TransformError::InvalidConfig(
+                "one of 'spec_path' or 'spec_paths' is required".to_string(),
+            )),
+        }
+    }
 }
 
 fn default_apply() -> String {
@@ -33,35 +81,144 @@ fn default_fail_on_error() -> bool {
     true
 }
 
+/// A spec file and its current parsed contents
+struct LoadedSpec {
+    path: PathBuf,
+    spec: Arc<ArcSwap<TransformSpec>>,
+}
+
 pub struct JoltTransformEngine {
-    spec: TransformSpec,
+    specs: Vec<LoadedSpec>,
     config: TransformConfig,
+    /// Keeps the spec file watch alive for engines built with `new_watched`
+    _watcher: Option<RecommendedWatcher>,
 }
 
 impl JoltTransformEngine {
     /// Create a new transform engine from a config
     pub fn new(config: TransformConfig) -> Result<Self, TransformError> {
-        let spec_content = std::fs::read_to_string(&config.spec_path)?;
-        let spec: TransformSpec = serde_json::from_str(&spec_content)?;
-
-        tracing::info!("Loaded JOLT transform spec from: {}", config.spec_path);
+        if let Some(pointer) = config.subtree_pointer.as_deref() {
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                return Err(TransformError::InvalidPointer(pointer.to_string()));
+            }
+        }
+
+        let mut specs = Vec::new();
+        for (index, path) in config.spec_paths()?.into_iter().enumerate() {
+            let spec = std::fs::read_to_string(path)
+                .map_err(TransformError::from)
+                .and_then(|content| parse_spec(Path::new(path), &content))
+                .map_err(|e| TransformError::SpecLoad {
+                    index,
+                    path: path.to_string(),
+                    source: Box::new(e),
+                })?;
+            tracing::info!("Loaded JOLT transform spec from: {}", path);
+            specs.push(LoadedSpec {
+                path: PathBuf::from(path),
+                spec: Arc::new(ArcSwap::from_pointee(spec)),
+            });
+        }
+
+        Ok(Self {
+            specs,
+            config,
+            _watcher: None,
+        })
+    }
 
-        Ok(Self { spec, config })
+    /// Create a transform engine that reloads its specs whenever their files change on disk
+    ///
+    /// A new spec applies to `transform` calls that start after the reload. A reload that
+    /// fails to read or parse is logged and the last good spec stays in use.
+    pub fn new_watched(config: TransformConfig) -> Result<Self, TransformError> {
+        let mut engine = Self::new(config)?;
+
+        let watched: Vec<(usize, PathBuf, Arc<ArcSwap<TransformSpec>>)> = engine
+            .specs
+            .iter()
+            .enumerate()
+            .map(|(index, loaded)| (index, loaded.path.clone(), loaded.spec.clone()))
+            .collect();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for (index, path, spec) in &watched {
+                        if event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name() == path.file_name())
+                        {
+                            reload_spec(*index, path, spec);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("JOLT spec watch error: {}", e),
+            })?;
+
+        // Watch directories rather than files so editors that save by rename are seen
+        let mut dirs: Vec<&Path> = Vec::new();
+        for loaded in &engine.specs {
+            let dir = match loaded.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            if !dirs.contains(&dir) {
+                watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                dirs.push(dir);
+            }
+            tracing::info!(
+                "Watching JOLT transform spec for changes: {}",
+                loaded.path.display()
+            );
+        }
+
+        engine._watcher = Some(watcher);
+        Ok(engine)
     }
 
     /// Create a new transform engine from a spec path (for backwards compatibility)
     pub fn from_spec_path<P: AsRef<Path>>(spec_path: P) -> Result<Self, TransformError> {
         let config = TransformConfig {
             spec_path: spec_path.as_ref().to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
             apply: default_apply(),
             fail_on_error: default_fail_on_error(),
+            subtree_pointer: None,
         };
         Self::new(config)
     }
 
     /// Apply the JOLT transform to input JSON
-    pub fn transform(&self, input: Value) -> Result<Value, TransformError> {
-        transform(input, &self.spec).map_err(|e| TransformError::TransformFailed(e.to_string()))
+    ///
+    /// With a `subtree_pointer` only that subtree is transformed and spliced back in place;
+    /// input that does not contain the subtree is returned unchanged.
+    pub fn transform(&self, mut input: Value) -> Result<Value, TransformError> {
+        let pointer = match self.config.subtree_pointer.as_deref() {
+            Some(p) if !p.is_empty() => p,
+            _ => return self.apply_spec(input),
+        };
+
+        let Some(subtree) = input.pointer_mut(pointer) else {
+            tracing::debug!("JOLT subtree '{}' not found; input left unchanged", pointer);
+            return Ok(input);
+        };
+        *subtree = self.apply_spec(subtree.take())?;
+        Ok(input)
+    }
+
+    /// Run `input` through every spec in order
+    fn apply_spec(&self, input: Value) -> Result<Value, TransformError> {
+        self.specs
+            .iter()
+            .enumerate()
+            .try_fold(input, |value, (index, loaded)| {
+                transform(value, &loaded.spec.load()).map_err(|e| TransformError::TransformFailed {
+                    index,
+                    message: e.to_string(),
+                })
+            })
     }
 
     /// Check if transform should be applied on the left side (request to backend)
@@ -80,6 +237,40 @@ impl JoltTransformEngine {
     }
 }
 
+fn reload_spec(index: usize, path: &Path, spec: &ArcSwap<TransformSpec>) {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(TransformError::from)
+        .and_then(|content| parse_spec(path, &content));
+    match parsed {
+        Ok(new_spec) => {
+            spec.store(Arc::new(new_spec));
+            tracing::info!("Reloaded JOLT transform spec from: {}", path.display());
+        }
+        Err(e) => tracing::error!(
+            "Keeping previous JOLT spec_paths[{}]; reload of {} failed: {}",
+            index,
+            path.display(),
+            e
+        ),
+    }
+}
+
+/// Parse a spec as JSON or YAML by file extension; other extensions try JSON, then YAML
+fn parse_spec(path: &Path, content: &str) -> Result<TransformSpec, TransformError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("json") => Ok(serde_json::from_str(content)?),
+        Some("yaml" | "yml") => Ok(serde_yaml::from_str(content)?),
+        _ => serde_json::from_str(content).or_else(|json| {
+            serde_yaml::from_str(content)
+                .map_err(|yaml| TransformError::SpecParseAmbiguous { json, yaml })
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,25 +349,319 @@ mod tests {
         assert_eq!(output, expected);
     }
 
+    const SHIFT_YAML: &str = "\
+- operation: shift
+  spec:
+    name: data.name
+    account: data.account
+";
+
+    fn write_spec(suffix: &str, contents: &str) -> NamedTempFile {
+        let file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        fs::write(&file, contents).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_yaml_spec_matches_json_spec() {
+        let json_spec = json!([{
+            "operation": "shift",
+            "spec": {
+                "name": "data.name",
+                "account": "data.account"
+            }
+        }]);
+        let json_file = write_spec(".json", &json_spec.to_string());
+        let input = json!({"id": 1, "name": "John Smith", "account": {"id": 1000}});
+        let expected = JoltTransformEngine::from_spec_path(json_file.path())
+            .unwrap()
+            .transform(input.clone())
+            .unwrap();
+
+        for suffix in [".yaml", ".yml", ".spec"] {
+            let yaml_file = write_spec(suffix, SHIFT_YAML);
+            let engine = JoltTransformEngine::from_spec_path(yaml_file.path()).unwrap();
+            assert_eq!(
+                engine.transform(input.clone()).unwrap(),
+                expected,
+                "{}",
+                suffix
+            );
+        }
+    }
+
+    /// The underlying error from loading a single spec
+    fn load_error(path: &Path) -> TransformError {
+        match JoltTransformEngine::from_spec_path(path) {
+            Err(TransformError::SpecLoad {
+                index: 0, source, ..
+            }) => *source,
+            Err(other) => panic!("unexpected error: {}", other),
+            Ok(_) => panic!("spec should not load"),
+        }
+    }
+
+    #[test]
+    fn test_spec_parse_errors_follow_extension() {
+        let yaml_file = write_spec(".yaml", "- operation: [shift");
+        assert!(matches!(
+            load_error(yaml_file.path()),
+            TransformError::SpecParseYaml(_)
+        ));
+
+        // YAML content in a .json file is not second-guessed
+        let json_file = write_spec(".json", SHIFT_YAML);
+        assert!(matches!(
+            load_error(json_file.path()),
+            TransformError::SpecParse(_)
+        ));
+
+        let other_file = write_spec(".spec", "- operation: [shift");
+        assert!(matches!(
+            load_error(other_file.path()),
+            TransformError::SpecParseAmbiguous { .. }
+        ));
+    }
+
+    #[test]
+    fn test_chained_specs_apply_in_order() {
+        let shift = write_spec(
+            ".json",
+            &json!([{"operation": "shift", "spec": {"name": "data.name"}}]).to_string(),
+        );
+        let defaults = write_spec(
+            ".yaml",
+            "- operation: default\n  spec:\n    data:\n      status: active\n",
+        );
+        let path = |f: &NamedTempFile| f.path().to_string_lossy().to_string();
+
+        let engine = JoltTransformEngine::new(TransformConfig {
+            spec_path: String::new(),
+            spec_paths: vec![path(&shift), path(&defaults)],
+            apply: default_apply(),
+            fail_on_error: true,
+            subtree_pointer: None,
+        })
+        .unwrap();
+
+        let output = engine
+            .transform(json!({"id": 1, "name": "Doe^John"}))
+            .unwrap();
+        assert_eq!(
+            output,
+            json!({"data": {"name": "Doe^John", "status": "active"}})
+        );
+    }
+
+    #[test]
+    fn test_spec_errors_identify_spec_index() {
+        let good = write_spec(
+            ".json",
+            &json!([{"operation": "shift", "spec": {"*": "&"}}]).to_string(),
+        );
+        let bad = write_spec(".json", "not a spec");
+        let config = |paths: Vec<&NamedTempFile>| TransformConfig {
+            spec_path: String::new(),
+            spec_paths: paths
+                .iter()
+                .map(|f| f.path().to_string_lossy().to_string())
+                .collect(),
+            apply: default_apply(),
+            fail_on_error: true,
+            subtree_pointer: None,
+        };
+
+        let result = JoltTransformEngine::new(config(vec![&good, &bad]));
+        assert!(matches!(
+            result,
+            Err(TransformError::SpecLoad { index: 1, .. })
+        ));
+
+        // Exactly one of spec_path and spec_paths must be set
+        let mut both = config(vec![&good]);
+        both.spec_path = both.spec_paths[0].clone();
+        assert!(matches!(
+            JoltTransformEngine::new(both),
+            Err(TransformError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            JoltTransformEngine::new(config(vec![])),
+            Err(TransformError::InvalidConfig(_))
+        ));
+    }
+
+    /// Poll `engine` until `input` transforms to `expected`, or give up after five seconds
+    fn wait_for_output(engine: &JoltTransformEngine, input: &Value, expected: &Value) -> bool {
+        for _ in 0..100 {
+            if engine.transform(input.clone()).ok().as_ref() == Some(expected) {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_watched_engine_reloads_changed_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let spec_path = dir.path().join("spec.json");
+        let shift_to = |target: &str| json!([{"operation": "shift", "spec": {"name": target}}]);
+        fs::write(&spec_path, shift_to("first.name").to_string()).unwrap();
+
+        let engine = JoltTransformEngine::new_watched(TransformConfig {
+            spec_path: spec_path.to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
+            apply: default_apply(),
+            fail_on_error: true,
+            subtree_pointer: None,
+        })
+        .unwrap();
+        let input = json!({"name": "Doe^John"});
+        let first = json!({"first": {"name": "Doe^John"}});
+        assert_eq!(engine.transform(input.clone()).unwrap(), first);
+
+        fs::write(&spec_path, shift_to("second.name").to_string()).unwrap();
+        let second = json!({"second": {"name": "Doe^John"}});
+        assert!(wait_for_output(&engine, &input, &second));
+
+        // A malformed spec is ignored and the last good one keeps serving
+        fs::write(&spec_path, "[{\"operation\": ").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert_eq!(engine.transform(input.clone()).unwrap(), second);
+
+        fs::write(&spec_path, shift_to("third.name").to_string()).unwrap();
+        let third = json!({"third": {"name": "Doe^John"}});
+        assert!(wait_for_output(&engine, &input, &third));
+    }
+
     #[test]
     fn test_config_apply_directions() {
         let config = TransformConfig {
             spec_path: "test.json".to_string(),
+            spec_paths: Vec::new(),
             apply: "left".to_string(),
             fail_on_error: true,
+            subtree_pointer: None,
         };
 
         assert!(config.apply == "left");
 
         let config_both = TransformConfig {
             spec_path: "test.json".to_string(),
+            spec_paths: Vec::new(),
             apply: "both".to_string(),
             fail_on_error: false,
+            subtree_pointer: None,
         };
 
         assert!(config_both.apply == "both");
     }
 
+    #[test]
+    fn test_subtree_pointer_leaves_siblings_untouched() {
+        let spec = json!([{
+            "operation": "shift",
+            "spec": {
+                "name": "patient.name"
+            }
+        }]);
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(&temp_file, serde_json::to_string_pretty(&spec).unwrap()).unwrap();
+
+        let engine = JoltTransformEngine::new(TransformConfig {
+            spec_path: temp_file.path().to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
+            apply: default_apply(),
+            fail_on_error: true,
+            subtree_pointer: Some("/normalized_data/matches/0".to_string()),
+        })
+        .unwrap();
+
+        let input = json!({
+            "normalized_data": {
+                "operation": "find",
+                "matches": [{"name": "Doe^John"}, {"name": "Roe^Jane"}]
+            },
+            "metadata": {"dimse_op": "find"}
+        });
+        let output = engine.transform(input).unwrap();
+
+        let expected = json!({
+            "normalized_data": {
+                "operation": "find",
+                "matches": [{"patient": {"name": "Doe^John"}}, {"name": "Roe^Jane"}]
+            },
+            "metadata": {"dimse_op": "find"}
+        });
+        assert_eq!(output, expected);
+
+        // Documents without the subtree pass through unchanged
+        let other = json!({"metadata": {"dimse_op": "find"}});
+        assert_eq!(engine.transform(other.clone()).unwrap(), other);
+    }
+
+    #[test]
+    fn test_subtree_pointer_to_array_transforms_each_element() {
+        let spec = json!([{
+            "operation": "shift",
+            "spec": {
+                "*": {
+                    "name": "[&(1)].patient.name",
+                    "id": "[&(1)].patient.id"
+                }
+            }
+        }]);
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(&temp_file, serde_json::to_string_pretty(&spec).unwrap()).unwrap();
+
+        let engine = JoltTransformEngine::new(TransformConfig {
+            spec_path: temp_file.path().to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
+            apply: default_apply(),
+            fail_on_error: true,
+            subtree_pointer: Some("/normalized_data/matches".to_string()),
+        })
+        .unwrap();
+
+        let input = json!({
+            "normalized_data": {
+                "operation": "find",
+                "matches": [
+                    {"name": "Doe^John", "id": "P1"},
+                    {"name": "Roe^Jane", "id": "P2"},
+                    {"name": "Poe^Edgar", "id": "P3"}
+                ]
+            },
+            "metadata": {"dimse_op": "find"}
+        });
+        let output = engine.transform(input).unwrap();
+
+        let expected = json!({
+            "normalized_data": {
+                "operation": "find",
+                "matches": [
+                    {"patient": {"name": "Doe^John", "id": "P1"}},
+                    {"patient": {"name": "Roe^Jane", "id": "P2"}},
+                    {"patient": {"name": "Poe^Edgar", "id": "P3"}}
+                ]
+            },
+            "metadata": {"dimse_op": "find"}
+        });
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_invalid_subtree_pointer_rejected() {
+        let result = JoltTransformEngine::new(TransformConfig {
+            spec_path: "unused.json".to_string(),
+            spec_paths: Vec::new(),
+            apply: default_apply(),
+            fail_on_error: true,
+            subtree_pointer: Some("normalized_data".to_string()),
+        });
+        assert!(matches!(result, Err(TransformError::InvalidPointer(_))));
+    }
+
     #[test]
     #[ignore] // Spec file moved; no longer in examples
     fn test_parse_real_metadata_set_dimse_op_spec() {
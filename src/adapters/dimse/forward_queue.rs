@@ -0,0 +1,406 @@
+//! Durable retry queue for C-STOREs that could not be forwarded
+//!
+//! When the pipeline behind a `forward` store policy fails, the instance is written to the
+//! storage backend under `forward_queue/{endpoint}/` as a Part 10 file next to a JSON entry,
+//! and the C-STORE still succeeds. A background task retries due entries with exponential
+//! backoff until one is delivered or outlives `max_age_secs`, so a downstream that is down for
+//! a while receives the instances once it is back.
+
+use crate::storage::{StorageBackend, StorageError, StorageResult};
+use dimse::types::DatasetStream;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// Retry schedule of a forward queue (the `forward_retry` endpoint option)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForwardRetryConfig {
+    /// How often due entries are retried, in milliseconds
+    #[serde(default = "default_interval")]
+    pub interval_ms: u64,
+    /// Delay before the first retry, in milliseconds; it doubles for each retry after that
+    #[serde(default = "default_base_backoff")]
+    pub base_backoff_ms: u64,
+    /// Longest delay between two retries, in milliseconds
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff_ms: u64,
+    /// Entries still undelivered this long after their first failure are dropped
+    #[serde(default = "default_max_age")]
+    pub max_age_secs: u64,
+}
+
+impl Default for ForwardRetryConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: default_interval(),
+            base_backoff_ms: default_base_backoff(),
+            max_backoff_ms: default_max_backoff(),
+            max_age_secs: default_max_age(),
+        }
+    }
+}
+
+fn default_interval() -> u64 {
+    5_000
+}
+
+fn default_base_backoff() -> u64 {
+    10_000
+}
+
+fn default_max_backoff() -> u64 {
+    600_000
+}
+
+/// One day
+fn default_max_age() -> u64 {
+    86_400
+}
+
+/// A queued forward, stored as `{id}.json` next to the instance in `{id}.dcm`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedForward {
+    id: Uuid,
+    /// Pipeline the instance is delivered through
+    pipeline: String,
+    correlation_id: Option<Uuid>,
+    sop_class_uid: Option<String>,
+    sop_instance_uid: Option<String>,
+    transfer_syntax: Option<String>,
+    /// Unix time of the first failure, in milliseconds
+    queued_at_ms: u64,
+    /// Delivery attempts so far, including the one that queued the entry
+    attempts: u32,
+    /// Unix time of the next retry, in milliseconds
+    next_attempt_ms: u64,
+}
+
+/// Failed forwards of one DIMSE endpoint, kept in the storage backend
+#[derive(Debug)]
+pub struct ForwardQueue {
+    storage: Arc<dyn StorageBackend>,
+    endpoint: String,
+    config: ForwardRetryConfig,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl ForwardQueue {
+    pub fn new(
+        storage: Arc<dyn StorageBackend>,
+        endpoint: impl Into<String>,
+        config: ForwardRetryConfig,
+    ) -> Self {
+        Self {
+            storage,
+            endpoint: endpoint.into(),
+            config,
+        }
+    }
+
+    fn dir(&self) -> String {
+        format!("forward_queue/{}", self.endpoint)
+    }
+
+    /// Wait before retry number `attempt` (1 for the first)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(
+            self.config
+                .base_backoff_ms
+                .saturating_mul(factor)
+                .min(self.config.max_backoff_ms),
+        )
+    }
+
+    /// Persist an instance whose forward through `pipeline` failed
+    pub async fn enqueue(
+        &self,
+        pipeline: &str,
+        dataset: &DatasetStream,
+        correlation_id: Option<Uuid>,
+    ) -> StorageResult<()> {
+        let bytes = dataset
+            .to_bytes()
+            .await
+            .map_err(|e| StorageError::Path(format!("read dataset: {}", e)))?;
+        let metadata = dataset.metadata();
+        let now = now_ms();
+        let entry = QueuedForward {
+            id: Uuid::new_v4(),
+            pipeline: pipeline.to_string(),
+            correlation_id,
+            sop_class_uid: metadata.sop_class_uid.clone(),
+            sop_instance_uid: metadata.sop_instance_uid.clone(),
+            transfer_syntax: metadata.transfer_syntax.clone(),
+            queued_at_ms: now,
+            attempts: 1,
+            next_attempt_ms: now + self.backoff(1).as_millis() as u64,
+        };
+        // The instance goes first, so every entry found on disk has its data set
+        self.storage
+            .write_file_str(&format!("{}/{}.dcm", self.dir(), entry.id), &bytes)
+            .await?;
+        self.write_entry(&entry).await?;
+        tracing::warn!(
+            sop_instance_uid = entry.sop_instance_uid.as_deref().unwrap_or(""),
+            "Queued C-STORE forward through pipeline '{}' for retry",
+            pipeline
+        );
+        self.record_depth().await;
+        Ok(())
+    }
+
+    async fn write_entry(&self, entry: &QueuedForward) -> StorageResult<()> {
+        let json = serde_json::to_vec(entry).map_err(|e| StorageError::Path(e.to_string()))?;
+        self.storage
+            .write_file_str(&format!("{}/{}.json", self.dir(), entry.id), &json)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, id: Uuid) {
+        for ext in ["json", "dcm"] {
+            let path = format!("{}/{}.{}", self.dir(), id, ext);
+            if let Err(e) = self.storage.remove_str(&path).await {
+                tracing::warn!("Cannot remove queued forward {}: {}", path, e);
+            }
+        }
+    }
+
+    /// Queued entries, oldest file name first; an empty or missing queue has none
+    async fn entries(&self) -> Vec<QueuedForward> {
+        let names = self
+            .storage
+            .list_dir_str(&self.dir())
+            .await
+            .unwrap_or_default();
+        let mut entries = Vec::new();
+        for name in names.iter().filter(|n| n.ends_with(".json")) {
+            let path = format!("{}/{}", self.dir(), name);
+            match self.storage.read_file_str(&path).await.map(|bytes| {
+                serde_json::from_slice::<QueuedForward>(&bytes).map_err(|e| e.to_string())
+            }) {
+                Ok(Ok(entry)) => entries.push(entry),
+                Ok(Err(e)) => tracing::warn!("Skipping unreadable queued forward {}: {}", path, e),
+                Err(e) => tracing::warn!("Skipping unreadable queued forward {}: {}", path, e),
+            }
+        }
+        entries
+    }
+
+    /// Number of instances waiting for delivery
+    pub async fn depth(&self) -> usize {
+        self.entries().await.len()
+    }
+
+    async fn record_depth(&self) {
+        crate::metrics::forward_queue_depth(&self.endpoint, self.depth().await);
+    }
+
+    /// Retry every entry that is due once, through `deliver(pipeline, dataset, correlation_id)`
+    ///
+    /// Delivered entries and those past `max_age_secs` leave the queue; the rest are
+    /// rescheduled with a longer backoff.
+    pub async fn retry_due<F, Fut>(&self, deliver: &F)
+    where
+        F: Fn(String, DatasetStream, Option<Uuid>) -> Fut,
+        Fut: Future<Output = dimse::Result<()>>,
+    {
+        let now = now_ms();
+        for mut entry in self.entries().await {
+            if now.saturating_sub(entry.queued_at_ms) > self.config.max_age_secs * 1000 {
+                tracing::error!(
+                    sop_instance_uid = entry.sop_instance_uid.as_deref().unwrap_or(""),
+                    "Dropping queued C-STORE forward after {} attempts: older than {}s",
+                    entry.attempts,
+                    self.config.max_age_secs
+                );
+                self.remove(entry.id).await;
+                continue;
+            }
+            if entry.next_attempt_ms > now {
+                continue;
+            }
+
+            let path = format!("{}/{}.dcm", self.dir(), entry.id);
+            let bytes = match self.storage.read_file_str(&path).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    tracing::error!("Dropping queued forward {} without data set: {}", path, e);
+                    self.remove(entry.id).await;
+                    continue;
+                }
+            };
+            let mut dataset = DatasetStream::from_bytes(bytes.into());
+            let metadata = dataset.metadata_mut();
+            metadata.sop_class_uid = entry.sop_class_uid.clone();
+            metadata.sop_instance_uid = entry.sop_instance_uid.clone();
+            metadata.transfer_syntax = entry.transfer_syntax.clone();
+
+            entry.attempts += 1;
+            match deliver(entry.pipeline.clone(), dataset, entry.correlation_id).await {
+                Ok(()) => {
+                    tracing::info!(
+                        sop_instance_uid = entry.sop_instance_uid.as_deref().unwrap_or(""),
+                        "Delivered queued C-STORE forward on attempt {}",
+                        entry.attempts
+                    );
+                    self.remove(entry.id).await;
+                }
+                Err(e) => {
+                    entry.next_attempt_ms = now + self.backoff(entry.attempts).as_millis() as u64;
+                    tracing::warn!(
+                        sop_instance_uid = entry.sop_instance_uid.as_deref().unwrap_or(""),
+                        "Queued C-STORE forward failed on attempt {}: {}",
+                        entry.attempts,
+                        e
+                    );
+                    if let Err(e) = self.write_entry(&entry).await {
+                        tracing::error!("Cannot reschedule queued forward {}: {}", entry.id, e);
+                    }
+                }
+            }
+        }
+        self.record_depth().await;
+    }
+
+    /// Retry due entries every `interval_ms` until `shutdown` is cancelled
+    pub async fn run<F, Fut>(self: Arc<Self>, deliver: F, shutdown: CancellationToken)
+    where
+        F: Fn(String, DatasetStream, Option<Uuid>) -> Fut,
+        Fut: Future<Output = dimse::Result<()>>,
+    {
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.interval_ms));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => self.retry_due(&deliver).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn queue(storage: Arc<MemoryStorage>, config: ForwardRetryConfig) -> Arc<ForwardQueue> {
+        Arc::new(ForwardQueue::new(storage, "dimse_in", config))
+    }
+
+    fn instance() -> DatasetStream {
+        let mut dataset = DatasetStream::from_bytes(b"DICM instance".to_vec().into());
+        let metadata = dataset.metadata_mut();
+        metadata.sop_class_uid = Some("1.2.840.10008.5.1.4.1.1.7".to_string());
+        metadata.sop_instance_uid = Some("1.2.3.709".to_string());
+        dataset
+    }
+
+    #[tokio::test]
+    async fn test_queued_forward_delivered_once_downstream_recovers() {
+        let staging = tempfile::tempdir().unwrap();
+        let storage = Arc::new(MemoryStorage::new(staging.path()).unwrap());
+        let queue = queue(
+            storage.clone(),
+            ForwardRetryConfig {
+                interval_ms: 10,
+                base_backoff_ms: 10,
+                max_backoff_ms: 20,
+                max_age_secs: 60,
+            },
+        );
+        let correlation_id = Uuid::new_v4();
+        queue
+            .enqueue("modality_in", &instance(), Some(correlation_id))
+            .await
+            .unwrap();
+        assert_eq!(queue.depth().await, 1);
+
+        // The downstream refuses the first three retries, then takes the instance
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let deliver = {
+            let (attempts, delivered) = (attempts.clone(), delivered.clone());
+            move |pipeline: String, dataset: DatasetStream, id: Option<Uuid>| {
+                let (attempts, delivered) = (attempts.clone(), delivered.clone());
+                async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 3 {
+                        return Err(dimse::DimseError::operation_failed("downstream down"));
+                    }
+                    let bytes = dataset.to_bytes().await.unwrap();
+                    let uid = dataset.metadata().sop_instance_uid.clone();
+                    delivered.lock().unwrap().push((pipeline, uid, id, bytes));
+                    Ok(())
+                }
+            }
+        };
+        let shutdown = CancellationToken::new();
+        let worker = tokio::spawn(queue.clone().run(deliver, shutdown.clone()));
+        for _ in 0..200 {
+            if queue.depth().await == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        shutdown.cancel();
+        worker.await.unwrap();
+
+        assert_eq!(queue.depth().await, 0, "instance left in the queue");
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+        let delivered = delivered.lock().unwrap();
+        assert_eq!(delivered.len(), 1, "delivered exactly once");
+        let (pipeline, uid, id, bytes) = &delivered[0];
+        assert_eq!(pipeline, "modality_in");
+        assert_eq!(uid.as_deref(), Some("1.2.3.709"));
+        assert_eq!(*id, Some(correlation_id));
+        assert_eq!(&bytes[..], b"DICM instance");
+        assert!(!storage.exists_str("forward_queue/dimse_in"));
+    }
+
+    #[tokio::test]
+    async fn test_expired_forward_is_dropped() {
+        let staging = tempfile::tempdir().unwrap();
+        let storage = Arc::new(MemoryStorage::new(staging.path()).unwrap());
+        let queue = queue(
+            storage,
+            ForwardRetryConfig {
+                max_age_secs: 0,
+                ..Default::default()
+            },
+        );
+        queue
+            .enqueue("modality_in", &instance(), None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let deliver = |_: String, _: DatasetStream, _: Option<Uuid>| async {
+            panic!("an expired forward is not retried")
+        };
+        queue.retry_due(&deliver).await;
+        assert_eq!(queue.depth().await, 0);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let staging = tempfile::tempdir().unwrap();
+        let storage = Arc::new(MemoryStorage::new(staging.path()).unwrap());
+        let queue = queue(storage, ForwardRetryConfig::default());
+        assert_eq!(queue.backoff(1), Duration::from_secs(10));
+        assert_eq!(queue.backoff(3), Duration::from_secs(40));
+        assert_eq!(queue.backoff(30), Duration::from_secs(600));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod forward_queue;
 pub mod query_provider;
 mod status_mapper;
 
@@ -15,6 +16,39 @@ use tokio_util::sync::CancellationToken;
 /// Key format: "{local_aet}@{bind_addr}:{port}#{endpoint_name}"
 static STARTED_SCP: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
+/// Longest wait between two bind attempts of an SCP whose port is taken
+const MAX_BIND_BACKOFF: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often an SCP retries binding a port that is still in use
+///
+/// Covers a previous listener releasing the port during a restart. Any other bind failure,
+/// such as a privileged port, fails at once.
+#[derive(Debug, Clone, Copy)]
+struct BindRetry {
+    /// Retries after the first attempt
+    retries: u32,
+    /// Wait before the first retry, doubled for each further one
+    backoff: std::time::Duration,
+}
+
+impl BindRetry {
+    fn from_options(options: &HashMap<String, serde_json::Value>) -> Self {
+        Self {
+            retries: options
+                .get("bind_retries")
+                .and_then(|v| v.as_u64())
+                .map(|n| n as u32)
+                .unwrap_or(5),
+            backoff: std::time::Duration::from_millis(
+                options
+                    .get("bind_retry_backoff_ms")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(200),
+            ),
+        }
+    }
+}
+
 /// DIMSE protocol adapter
 /// 
 /// Handles DICOM DIMSE protocol via SCP (Service Class Provider) listeners.
@@ -130,7 +164,7 @@ impl ProtocolAdapter for DimseAdapter {
 
             // Start each SCP
             for (pipeline_name, endpoint_name, options) in scp_configs {
-                match Self::start_scp(&pipeline_name, &endpoint_name, &options).await {
+                match Self::start_scp(&pipeline_name, &endpoint_name, &options, &shutdown).await {
                     Ok(scp_handle) => {
                         scp_handles.push(scp_handle);
                     }
@@ -171,7 +205,9 @@ impl DimseAdapter {
         pipeline_name: &str,
         endpoint_name: &str,
         options: &std::collections::HashMap<String, serde_json::Value>,
+        shutdown: &CancellationToken,
     ) -> anyhow::Result<JoinHandle<()>> {
+        use dimse::config::{MAX_MAX_PDU, MIN_MAX_PDU};
         use dimse::{DimseConfig, DEFAULT_DIMSE_PORT};
         use std::net::IpAddr;
 
@@ -239,25 +275,150 @@ impl DimseAdapter {
             dimse_config.enable_move = b;
         }
 
+        // Association access control and A-ASSOCIATE-RJ codes
+        if let Some(aets) = options.get("allowed_calling_aets").and_then(|v| v.as_array()) {
+            dimse_config.allowed_calling_aets = aets
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect();
+        }
+        if let Some(codes) = options.get("association_rejections") {
+            dimse_config.association_rejections = serde_json::from_value(codes.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid association_rejections: {}", e))?;
+        }
+
+        // Roles agreed to when a requestor proposes SCP/SCU Role Selection
+        if let Some(roles) = options.get("role_selection") {
+            dimse_config.role_selection = serde_json::from_value(roles.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid role_selection: {}", e))?;
+        }
+
+        if let Some(ms) = options
+            .get("max_association_lifetime_ms")
+            .and_then(|v| v.as_u64())
+        {
+            dimse_config.max_association_lifetime_ms = Some(ms);
+        }
+
+        // Time open associations get to finish their current operation on shutdown
+        if let Some(ms) = options.get("shutdown_grace_ms").and_then(|v| v.as_u64()) {
+            dimse_config.shutdown_grace_ms = ms;
+        }
+
+        // Idle and pre-association timers
+        if let Some(ms) = options
+            .get("association_timeout_ms")
+            .and_then(|v| v.as_u64())
+        {
+            dimse_config.association_timeout_ms = ms;
+        }
+        if let Some(ms) = options.get("artim_timeout_ms").and_then(|v| v.as_u64()) {
+            dimse_config.artim_timeout_ms = Some(ms);
+        }
+
+        // Largest PDU accepted from peers; the negotiated minimum is logged per association
+        if let Some(max) = options
+            .get("max_pdu_length")
+            .or_else(|| options.get("max_pdu"))
+            .and_then(|v| v.as_u64())
+        {
+            dimse_config.max_pdu = u32::try_from(max)
+                .ok()
+                .filter(|max| (MIN_MAX_PDU..=MAX_MAX_PDU).contains(max))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Invalid max_pdu_length: {} (must be {}..={})",
+                        max,
+                        MIN_MAX_PDU,
+                        MAX_MAX_PDU
+                    )
+                })?;
+        }
+
+        // TLS server identity and, for mutual TLS, the CA that client certificates must chain to
+        if let Some(tls) = options.get("tls") {
+            dimse_config.tls = Some(
+                serde_json::from_value(tls.clone())
+                    .map_err(|e| anyhow::anyhow!("Invalid tls: {}", e))?,
+            );
+        }
+
+        if let Some(slow) = options.get("slow_transfer") {
+            dimse_config.slow_transfer = Some(
+                serde_json::from_value(slow.clone())
+                    .map_err(|e| anyhow::anyhow!("Invalid slow_transfer: {}", e))?,
+            );
+        }
+
+        // ATNA audit records for associations and received instances
+        if let Some(audit) = options.get("audit") {
+            dimse_config.audit = Some(
+                serde_json::from_value(audit.clone())
+                    .map_err(|e| anyhow::anyhow!("Invalid audit: {}", e))?,
+            );
+        }
+
+        // Transfer syntaxes the SCP accepts; the peer's order decides among them
+        if let Some(syntaxes) = options.get("preferred_transfer_syntaxes") {
+            dimse_config.preferred_transfer_syntaxes = serde_json::from_value(syntaxes.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid preferred_transfer_syntaxes: {}", e))?;
+        }
+
+        // Per-SOP-class C-STORE handling
+        if let Some(policies) = options.get("store_policies") {
+            dimse_config.store_policies = serde_json::from_value(policies.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid store_policies: {}", e))?;
+        }
+        if let Some(policy) = options.get("default_store_policy") {
+            dimse_config.default_store_policy = serde_json::from_value(policy.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid default_store_policy: {}", e))?;
+        }
+
+        // Calling AE title (or pattern) -> pipeline serving that peer's C-STOREs
+        let aet_routes: HashMap<String, String> = match options.get("aet_routes") {
+            Some(routes) => serde_json::from_value(routes.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid aet_routes: {}", e))?,
+            None => HashMap::new(),
+        };
+
+        // Failed forwards are kept in storage and retried instead of failing the C-STORE
+        let forward_queue = match options.get("forward_retry") {
+            Some(retry) => {
+                let retry: forward_queue::ForwardRetryConfig =
+                    serde_json::from_value(retry.clone())
+                        .map_err(|e| anyhow::anyhow!("Invalid forward_retry: {}", e))?;
+                let storage = crate::globals::get_storage()
+                    .ok_or_else(|| anyhow::anyhow!("forward_retry requires a storage backend"))?;
+                Some(Arc::new(forward_queue::ForwardQueue::new(
+                    storage,
+                    endpoint_name,
+                    retry,
+                )))
+            }
+            None => None,
+        };
+
         let pipeline = pipeline_name.to_string();
         let endpoint = endpoint_name.to_string();
 
-        // Determine if we should use DCMTK storescp or internal SCP
-        let is_persistent_backend = options
-            .get("persistent_store_scp")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
+        // The internal SCP handles C-STORE natively; DCMTK storescp is only a fallback
         let use_dcmtk_store = options
             .get("use_dcmtk_store")
             .and_then(|v| v.as_bool())
-            .unwrap_or(is_persistent_backend);
+            .unwrap_or(false);
+
+        let bind_retry = BindRetry::from_options(options);
 
         if use_dcmtk_store {
+            tracing::warn!(
+                "DIMSE SCP {}: use_dcmtk_store is deprecated; the internal SCP handles C-STORE",
+                key
+            );
             // Spawn DCMTK storescp process
-            Self::start_dcmtk_scp(key, local_aet, port, dimse_config, pipeline, endpoint).await
+            Self::start_dcmtk_scp(key, local_aet, port, dimse_config, pipeline, endpoint, shutdown.clone()).await
         } else {
             // Use internal SCP with pipeline query provider
-            Self::start_internal_scp(key, local_aet, bind_addr, port, dimse_config, pipeline, endpoint).await
+            Self::start_internal_scp(key, local_aet, bind_addr, port, dimse_config, pipeline, endpoint, aet_routes, forward_queue, bind_retry, shutdown.clone()).await
         }
     }
 
@@ -269,6 +430,7 @@ impl DimseAdapter {
         dimse_config: dimse::DimseConfig,
         pipeline: String,
         endpoint: String,
+        shutdown: CancellationToken,
     ) -> anyhow::Result<JoinHandle<()>> {
         use tokio::process::Command;
 
@@ -311,7 +473,7 @@ impl DimseAdapter {
                     let provider: Arc<dyn dimse::scp::QueryProvider> =
                         Arc::new(query_provider::PipelineQueryProvider::new(pipeline, endpoint));
                     let scp = dimse::DimseScp::new(dimse_config, provider);
-                    if let Err(e2) = scp.run().await {
+                    if let Err(e2) = scp.run(shutdown).await {
                         tracing::error!("DIMSE SCP '{}' failed: {}", local_aet, e2);
                     } else {
                         tracing::info!("DIMSE SCP '{}' stopped gracefully", local_aet);
@@ -328,6 +490,34 @@ impl DimseAdapter {
         Ok(handle)
     }
 
+    /// Route C-STOREs to a pipeline per calling AE title, falling back to the endpoint's own
+    ///
+    /// Returns `None` when no routes are configured, so every peer uses the endpoint pipeline.
+    fn aet_router(
+        aet_routes: &HashMap<String, String>,
+        pipeline: &str,
+        endpoint: &str,
+        dimse_config: &dimse::DimseConfig,
+        forward_queue: Option<&Arc<forward_queue::ForwardQueue>>,
+    ) -> Option<Arc<dyn dimse::Router>> {
+        if aet_routes.is_empty() {
+            return None;
+        }
+        let handler = |name: &str| -> Arc<dyn dimse::Router> {
+            let router = query_provider::PipelineRouter::new(name, endpoint, dimse_config.clone());
+            match forward_queue {
+                Some(queue) => Arc::new(router.with_forward_queue(queue.clone())),
+                None => Arc::new(router),
+            }
+        };
+        let mut routes = aet_routes.clone();
+        routes
+            .entry("*".to_string())
+            .or_insert_with(|| pipeline.to_string());
+        let router = dimse::AetRouter::from_config(&routes, |name| Ok(handler(name))).ok()?;
+        Some(Arc::new(router))
+    }
+
     /// Start internal DIMSE SCP with pipeline query provider
     async fn start_internal_scp(
         key: String,
@@ -337,20 +527,64 @@ impl DimseAdapter {
         dimse_config: dimse::DimseConfig,
         pipeline: String,
         endpoint: String,
+        aet_routes: HashMap<String, String>,
+        forward_queue: Option<Arc<forward_queue::ForwardQueue>>,
+        bind_retry: BindRetry,
+        shutdown: CancellationToken,
     ) -> anyhow::Result<JoinHandle<()>> {
-        let handle = tokio::spawn(async move {
-            let provider: Arc<dyn dimse::scp::QueryProvider> =
-                Arc::new(query_provider::PipelineQueryProvider::new(pipeline, endpoint));
-            let scp = dimse::DimseScp::new(dimse_config, provider);
-            
-            tracing::info!(
-                "Starting internal DIMSE SCP AET='{}' on {}:{}",
-                local_aet,
-                bind_addr,
-                port
-            );
+        let router = Self::aet_router(
+            &aet_routes,
+            &pipeline,
+            &endpoint,
+            &dimse_config,
+            forward_queue.as_ref(),
+        );
+        let mut provider = query_provider::PipelineQueryProvider::new(&pipeline, &endpoint);
+        if let Some(queue) = &forward_queue {
+            provider = provider.with_forward_queue(queue.clone());
+        }
+        let provider: Arc<dyn dimse::scp::QueryProvider> = Arc::new(provider);
+        let mut scp = dimse::DimseScp::new(dimse_config, provider);
+        if let Some(router) = router {
+            scp = scp.with_router(router);
+        }
+
+        tracing::info!(
+            "Starting internal DIMSE SCP AET='{}' on {}:{}",
+            local_aet,
+            bind_addr,
+            port
+        );
+
+        // Bound before spawning, so the port accepts connections once this returns
+        let listener = match Self::bind_with_retry(&scp, &local_aet, bind_retry).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                Self::unregister_scp(&key);
+                return Err(anyhow::anyhow!(
+                    "DIMSE SCP '{}' could not bind {}:{}: {}",
+                    local_aet,
+                    bind_addr,
+                    port,
+                    e
+                ));
+            }
+        };
+
+        // Queued forwards are retried through the pipeline they failed on, for as long as the
+        // SCP runs
+        if let Some(queue) = forward_queue {
+            let deliver = move |pipeline: String,
+                                dataset: dimse::types::DatasetStream,
+                                correlation_id: Option<uuid::Uuid>| {
+                let provider = query_provider::PipelineQueryProvider::new(pipeline, &endpoint);
+                async move { provider.deliver(&dataset, correlation_id).await }
+            };
+            tokio::spawn(queue.run(deliver, shutdown.clone()));
+        }
 
-            if let Err(e) = scp.run().await {
+        let handle = tokio::spawn(async move {
+            if let Err(e) = scp.serve(listener, shutdown).await {
                 tracing::error!("DIMSE SCP '{}' failed: {}", local_aet, e);
             } else {
                 tracing::info!("DIMSE SCP '{}' stopped gracefully", local_aet);
@@ -359,12 +593,39 @@ impl DimseAdapter {
             Self::unregister_scp(&key);
         });
 
-        // Readiness loop
-        Self::wait_for_scp_ready(bind_addr, port).await;
-
         Ok(handle)
     }
 
+    /// Bind the SCP's port, retrying with backoff while another listener still holds it
+    async fn bind_with_retry(
+        scp: &dimse::DimseScp,
+        local_aet: &str,
+        retry: BindRetry,
+    ) -> dimse::Result<tokio::net::TcpListener> {
+        let mut backoff = retry.backoff;
+        let mut attempt = 0;
+        loop {
+            match scp.bind() {
+                Ok(listener) => return Ok(listener),
+                Err(dimse::DimseError::Network(e))
+                    if e.kind() == std::io::ErrorKind::AddrInUse && attempt < retry.retries =>
+                {
+                    attempt += 1;
+                    tracing::warn!(
+                        "DIMSE SCP '{}' port in use; retrying bind in {:?} ({}/{})",
+                        local_aet,
+                        backoff,
+                        attempt,
+                        retry.retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BIND_BACKOFF);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// Wait for SCP to be ready by attempting TCP connection
     async fn wait_for_scp_ready(bind_addr: std::net::IpAddr, port: u16) {
         let target = if bind_addr.is_unspecified() {
@@ -452,4 +713,83 @@ mod tests {
         DimseAdapter::unregister_scp(&key1);
         DimseAdapter::unregister_scp(&key2);
     }
+
+    fn internal_scp_config(port: u16, storage_dir: &std::path::Path) -> dimse::DimseConfig {
+        dimse::DimseConfig {
+            local_aet: "BIND_TEST".to_string(),
+            bind_addr: std::net::IpAddr::from([127, 0, 0, 1]),
+            port,
+            storage_dir: storage_dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_internal_scp_retries_bind_until_port_is_released() {
+        let occupant = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = occupant.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+            drop(occupant);
+        });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key = format!("BIND_TEST@127.0.0.1:{}#retry", port);
+        assert!(DimseAdapter::register_scp(key.clone()));
+        let handle = DimseAdapter::start_internal_scp(
+            key.clone(),
+            "BIND_TEST".to_string(),
+            std::net::IpAddr::from([127, 0, 0, 1]),
+            port,
+            internal_scp_config(port, temp_dir.path()),
+            "pipeline".to_string(),
+            "endpoint".to_string(),
+            HashMap::new(),
+            BindRetry {
+                retries: 5,
+                backoff: std::time::Duration::from_millis(100),
+            },
+            CancellationToken::new(),
+        )
+        .await
+        .expect("bind succeeds once the port is released");
+
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok());
+        handle.abort();
+        DimseAdapter::unregister_scp(&key);
+    }
+
+    #[tokio::test]
+    async fn test_internal_scp_gives_up_and_unregisters_after_retries() {
+        let occupant = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = occupant.local_addr().unwrap().port();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key = format!("BIND_TEST@127.0.0.1:{}#exhausted", port);
+        assert!(DimseAdapter::register_scp(key.clone()));
+        let result = DimseAdapter::start_internal_scp(
+            key.clone(),
+            "BIND_TEST".to_string(),
+            std::net::IpAddr::from([127, 0, 0, 1]),
+            port,
+            internal_scp_config(port, temp_dir.path()),
+            "pipeline".to_string(),
+            "endpoint".to_string(),
+            HashMap::new(),
+            BindRetry {
+                retries: 2,
+                backoff: std::time::Duration::from_millis(10),
+            },
+            CancellationToken::new(),
+        )
+        .await;
+
+        assert!(result.is_err());
+        // The failed SCP no longer holds its registry entry
+        assert!(DimseAdapter::register_scp(key.clone()));
+        DimseAdapter::unregister_scp(&key);
+        drop(occupant);
+    }
 }
@@ -1,17 +1,22 @@
+use crate::adapters::dimse::forward_queue::ForwardQueue;
 use crate::adapters::dimse::status_mapper;
-use crate::globals::get_config;
+use crate::globals::{get_config, get_storage};
+use crate::log_context::CORRELATION_ID;
 use crate::models::envelope::envelope::ResponseEnvelope;
 use crate::models::protocol::{Protocol, ProtocolCtx};
 use crate::pipeline::executor::PipelineExecutor;
 use async_trait::async_trait;
 use dicom_json_tool as tool;
 use dimse::error::DimseError;
+use dimse::mpps::{MppsEvent, MppsOperation};
+use dimse::router::{DimseRequest, DimseRequestPayload, DimseResponse};
 use dimse::types::{DatasetStream, QueryLevel};
 use dimse::Result as DimseResult;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
 
 static CURRENT_STORE_DIR: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
 
@@ -32,6 +37,7 @@ fn get_current_store_dir() -> Option<PathBuf> {
 pub struct PipelineQueryProvider {
     pipeline: String,
     endpoint: String,
+    forward_queue: Option<Arc<ForwardQueue>>,
 }
 
 impl PipelineQueryProvider {
@@ -39,9 +45,16 @@ impl PipelineQueryProvider {
         Self {
             pipeline: pipeline.into(),
             endpoint: endpoint.into(),
+            forward_queue: None,
         }
     }
 
+    /// Queue forwards the pipeline fails on for retry instead of failing the C-STORE
+    pub fn with_forward_queue(mut self, queue: Arc<ForwardQueue>) -> Self {
+        self.forward_queue = Some(queue);
+        self
+    }
+
     /// Build DICOM JSON identifier from DIMSE query parameters
     fn build_identifier_json(&self, parameters: &HashMap<String, String>) -> serde_json::Value {
         let mut map = serde_json::Map::new();
@@ -304,31 +317,246 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
     }
 
     async fn store(&self, dataset: DatasetStream) -> DimseResult<()> {
-        // Write incoming dataset into the current per-move directory if set, otherwise default
-        let target_dir = get_current_store_dir().unwrap_or_else(|| PathBuf::from("./tmp/dimse"));
-        if let Err(e) = tokio::fs::create_dir_all(&target_dir).await {
-            return Err(DimseError::operation_failed(format!(
-                "ensure store dir: {}",
-                e
-            )));
-        }
-        let _temp = dataset
-            .to_temp_file(&target_dir)
+        let bytes = dataset
+            .to_bytes()
             .await
-            .map_err(|e| DimseError::operation_failed(format!("store dataset: {}", e)))?;
+            .map_err(|e| DimseError::operation_failed(format!("read dataset: {}", e)))?;
+        let metadata = dataset.metadata();
+        let file_name = format!(
+            "{}.dcm",
+            metadata
+                .sop_instance_uid
+                .clone()
+                .unwrap_or_else(|| metadata.id.to_string())
+        );
+
+        // A C-MOVE in progress collects its instances in its own directory; everything else
+        // goes through the configured storage backend
+        let path = match (get_current_store_dir(), get_storage()) {
+            (None, Some(storage)) => storage
+                .write_file_str(&format!("dimse/{}", file_name), &bytes)
+                .await
+                .map_err(|e| DimseError::operation_failed(format!("store dataset: {}", e)))?,
+            (dir, _) => {
+                let dir = dir.unwrap_or_else(|| PathBuf::from("./tmp/dimse"));
+                tokio::fs::create_dir_all(&dir).await.map_err(|e| {
+                    DimseError::operation_failed(format!("ensure store dir: {}", e))
+                })?;
+                let path = dir.join(&file_name);
+                tokio::fs::write(&path, &bytes)
+                    .await
+                    .map_err(|e| DimseError::operation_failed(format!("store dataset: {}", e)))?;
+                path
+            }
+        };
+        tracing::debug!("Stored C-STORE instance at {}", path.display());
+        Ok(())
+    }
+
+    async fn on_store(&self, instance: DatasetStream) -> DimseResult<()> {
+        self.notify_stored(instance, None).await
+    }
+
+    async fn on_mpps(&self, step: MppsEvent) -> DimseResult<()> {
+        // MPPS is only forwarded; a pipeline failure fails the N-CREATE/N-SET
+        let op = match step.operation {
+            MppsOperation::Create => "N-CREATE",
+            MppsOperation::Set => "N-SET",
+        };
+        let mut meta = HashMap::new();
+        meta.insert("dicom.operation".into(), op.into());
+        meta.insert(
+            "dicom.sop_instance_uid".into(),
+            step.sop_instance_uid.clone(),
+        );
+        let body = serde_json::to_value(&step)
+            .map_err(|e| DimseError::operation_failed(format!("encode MPPS event: {}", e)))?;
+        self.run(op, body, meta).await?;
+        Ok(())
+    }
 
-        // Also emit a pipeline event for observability (optional)
+    async fn forward(&self, dataset: DatasetStream) -> DimseResult<()> {
+        self.forward_instance(dataset, None).await
+    }
+}
+
+impl PipelineQueryProvider {
+    /// C-STORE metadata, carrying the request's correlation ID into the envelope when known
+    fn store_meta(correlation_id: Option<Uuid>) -> HashMap<String, String> {
         let mut meta = HashMap::new();
         meta.insert("dicom.operation".into(), "C-STORE".into());
+        if let Some(id) = correlation_id {
+            meta.insert(CORRELATION_ID.into(), id.to_string());
+        }
+        meta
+    }
+
+    /// Let the pipeline see a stored instance
+    async fn notify_stored(
+        &self,
+        instance: DatasetStream,
+        correlation_id: Option<Uuid>,
+    ) -> DimseResult<()> {
+        let metadata = instance.metadata();
         let body = serde_json::json!({
             "operation": "store",
-            "dir": target_dir.to_string_lossy(),
+            "sop_class_uid": metadata.sop_class_uid,
+            "sop_instance_uid": metadata.sop_instance_uid,
+            "transfer_syntax": metadata.transfer_syntax,
+        });
+        self.run("C-STORE", body, Self::store_meta(correlation_id))
+            .await?;
+        Ok(())
+    }
+
+    /// Hand an instance to the pipeline without writing it locally
+    ///
+    /// With a forward queue, a failed forward is queued for retry and the C-STORE succeeds.
+    async fn forward_instance(
+        &self,
+        dataset: DatasetStream,
+        correlation_id: Option<Uuid>,
+    ) -> DimseResult<()> {
+        let Err(e) = self.deliver(&dataset, correlation_id).await else {
+            return Ok(());
+        };
+        let Some(queue) = &self.forward_queue else {
+            return Err(e);
+        };
+        tracing::warn!("C-STORE forward through '{}' failed: {}", self.pipeline, e);
+        queue
+            .enqueue(&self.pipeline, &dataset, correlation_id)
+            .await
+            .map_err(|qe| {
+                DimseError::operation_failed(format!("{}; queueing for retry failed: {}", e, qe))
+            })
+    }
+
+    /// Run the forward pipeline once; a 5xx response counts as a failed delivery
+    pub(crate) async fn deliver(
+        &self,
+        dataset: &DatasetStream,
+        correlation_id: Option<Uuid>,
+    ) -> DimseResult<()> {
+        use base64::Engine;
+
+        let bytes = dataset
+            .to_bytes()
+            .await
+            .map_err(|e| DimseError::operation_failed(format!("read dataset: {}", e)))?;
+        let metadata = dataset.metadata();
+        let body = serde_json::json!({
+            "operation": "forward",
+            "sop_class_uid": metadata.sop_class_uid,
+            "sop_instance_uid": metadata.sop_instance_uid,
+            "dataset": base64::engine::general_purpose::STANDARD.encode(&bytes),
         });
-        let _ = self.run("C-STORE", body, meta).await;
+        let response = self
+            .run("C-STORE", body, Self::store_meta(correlation_id))
+            .await?;
+        let status = response.response_details.status;
+        if status >= 500 {
+            return Err(DimseError::operation_failed(format!(
+                "Pipeline returned status {}",
+                status
+            )));
+        }
         Ok(())
     }
 }
 
+/// Router handler that stores routed C-STOREs through one pipeline
+///
+/// Used as an `AetRouter` target so each calling AE title can be served by its own pipeline.
+/// The SCP has already refused instances its store policy rejects; the rest are stored or
+/// forwarded according to the same policy. The pipeline runs under the request's correlation
+/// ID, so a DICOM backend's SCU logs with the same ID as the association it came from.
+pub struct PipelineRouter {
+    provider: PipelineQueryProvider,
+    config: dimse::DimseConfig,
+}
+
+impl PipelineRouter {
+    pub fn new(
+        pipeline: impl Into<String>,
+        endpoint: impl Into<String>,
+        config: dimse::DimseConfig,
+    ) -> Self {
+        Self {
+            provider: PipelineQueryProvider::new(pipeline, endpoint),
+            config,
+        }
+    }
+
+    /// Queue forwards the pipeline fails on for retry, as [`PipelineQueryProvider`] does
+    pub fn with_forward_queue(mut self, queue: Arc<ForwardQueue>) -> Self {
+        self.provider = self.provider.with_forward_queue(queue);
+        self
+    }
+}
+
+#[async_trait]
+impl dimse::Router for PipelineRouter {
+    async fn send_request(&self, request: DimseRequest) -> DimseResult<DimseResponse> {
+        use dimse::scp::QueryProvider;
+
+        let correlation_id = request.correlation_id;
+        let DimseRequestPayload::Store(dataset) = request.payload else {
+            return Ok(DimseResponse::error(
+                request.id,
+                format!("{:?} is not routed to pipelines", request.command),
+            )
+            .with_correlation_id(correlation_id));
+        };
+        let policy = self
+            .config
+            .store_policy(dataset.metadata().sop_class_uid.as_deref());
+        let result = match policy {
+            dimse::config::StorePolicy::Forward => {
+                self.provider
+                    .forward_instance(dataset.clone(), Some(correlation_id))
+                    .await
+            }
+            _ => self.provider.store(dataset.clone()).await,
+        };
+        if let Err(e) = result {
+            return Ok(
+                DimseResponse::error(request.id, e.to_string()).with_correlation_id(correlation_id)
+            );
+        }
+        if let Err(e) = self
+            .provider
+            .notify_stored(dataset, Some(correlation_id))
+            .await
+        {
+            tracing::warn!("on_store callback failed: {}", e);
+        }
+        Ok(DimseResponse::store(request.id, true).with_correlation_id(correlation_id))
+    }
+
+    async fn send_streaming_request(
+        &self,
+        request: DimseRequest,
+    ) -> DimseResult<futures_util::stream::BoxStream<'static, DimseResponse>> {
+        Err(DimseError::NotSupported(format!(
+            "{:?} is not routed to pipelines",
+            request.command
+        )))
+    }
+
+    async fn next_request(&mut self) -> DimseResult<DimseRequest> {
+        Err(DimseError::operation_failed(
+            "PipelineRouter cannot receive requests",
+        ))
+    }
+
+    async fn send_response(&self, _response: DimseResponse) -> DimseResult<()> {
+        Err(DimseError::operation_failed(
+            "PipelineRouter cannot send responses",
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
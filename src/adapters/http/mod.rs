@@ -1,11 +1,12 @@
 use crate::adapters::ProtocolAdapter;
 use crate::config::config::Config;
 use crate::models::envelope::envelope::ResponseEnvelope;
+use crate::models::network::config::HttpTlsConfig;
 use crate::models::protocol::{Protocol, ProtocolCtx};
 use crate::utils::Error;
 use async_trait::async_trait;
 use axum::body::Body;
-use axum::extract::Request;
+use axum::extract::{ConnectInfo, Request};
 use axum::response::Response;
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -15,6 +16,7 @@ use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
 pub mod router;
+pub mod tls;
 
 /// HTTP Protocol Adapter
 /// 
@@ -23,6 +25,8 @@ pub mod router;
 pub struct HttpAdapter {
     pub network_name: String,
     pub bind_addr: SocketAddr,
+    /// Serve HTTPS when set; plain HTTP otherwise
+    pub tls: Option<HttpTlsConfig>,
 }
 
 impl HttpAdapter {
@@ -30,10 +34,20 @@ impl HttpAdapter {
         Self {
             network_name,
             bind_addr,
+            tls: None,
         }
     }
 
+    pub fn with_tls(mut self, tls: Option<HttpTlsConfig>) -> Self {
+        self.tls = tls;
+        self
+    }
+
     /// Convert Axum HTTP Request to ProtocolCtx
+    #[tracing::instrument(name = "http.request", skip_all, fields(
+        http.method = %req.method(),
+        http.target = %req.uri().path()
+    ))]
     pub async fn http_request_to_protocol_ctx(
         req: &mut Request,
         options: &HashMap<String, serde_json::Value>,
@@ -128,6 +142,14 @@ impl HttpAdapter {
         meta_map.insert("protocol".to_string(), "http".to_string());
         meta_map.insert("path".to_string(), subpath);
         meta_map.insert("full_path".to_string(), full_path_with_query);
+        if let Some(ConnectInfo(peer)) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+            meta_map.insert("client_ip".to_string(), peer.ip().to_string());
+        }
+        for key in [crate::telemetry::TRACEPARENT, crate::telemetry::TRACESTATE] {
+            if let Some(value) = req.headers().get(key).and_then(|v| v.to_str().ok()) {
+                meta_map.insert(key.to_string(), value.to_string());
+            }
+        }
 
         // attrs object
         let mut attrs = serde_json::Map::new();
@@ -194,6 +216,17 @@ impl ProtocolAdapter for HttpAdapter {
         let bind_addr = self.bind_addr;
         let network_name = self.network_name.clone();
 
+        // Load certificates up front so a bad TLS config fails the start instead of the task
+        let tls_config = match &self.tls {
+            Some(tls) => Some(tls::load_server_config(tls)?),
+            None => None,
+        };
+        let redirect_addr = self
+            .tls
+            .as_ref()
+            .and_then(|tls| tls.redirect_http_port)
+            .map(|port| SocketAddr::new(bind_addr.ip(), port));
+
         // Build the router using the router module
         let app = router::build_network_router(config.clone(), &network_name).await;
 
@@ -211,21 +244,58 @@ impl ProtocolAdapter for HttpAdapter {
                 }
             };
 
+            if let Some(redirect_addr) = redirect_addr {
+                spawn_https_redirect(
+                    network_name.clone(),
+                    redirect_addr,
+                    bind_addr.port(),
+                    shutdown.clone(),
+                );
+            }
+
+            let scheme = if tls_config.is_some() { "HTTPS" } else { "HTTP" };
             tracing::info!(
-                "🚀 HTTP adapter started for network '{}' on {}",
+                "🚀 {} adapter started for network '{}' on {}",
+                scheme,
                 network_name,
                 bind_addr
             );
 
             // Create a future for graceful shutdown
-            let graceful_shutdown = async move {
-                shutdown.cancelled().await;
+            let graceful_shutdown = {
+                let shutdown = shutdown.clone();
+                async move {
+                    shutdown.cancelled().await;
+                }
             };
 
-            if let Err(e) = axum::serve(listener, app)
-                .with_graceful_shutdown(graceful_shutdown)
-                .await
-            {
+            // The peer address reaches the pipeline as `client_ip` request metadata
+            let service = app.into_make_service_with_connect_info::<SocketAddr>();
+            let result = match tls_config {
+                Some(tls_config) => {
+                    let listener = match tls::TlsListener::new(listener, tls_config, shutdown) {
+                        Ok(l) => l,
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to start HTTPS listener for network '{}': {}",
+                                network_name,
+                                e
+                            );
+                            return;
+                        }
+                    };
+                    axum::serve(listener, service)
+                        .with_graceful_shutdown(graceful_shutdown)
+                        .await
+                }
+                None => {
+                    axum::serve(listener, service)
+                        .with_graceful_shutdown(graceful_shutdown)
+                        .await
+                }
+            };
+
+            if let Err(e) = result {
                 tracing::error!(
                     "HTTP adapter for network '{}' encountered error: {}",
                     network_name,
@@ -239,8 +309,48 @@ impl ProtocolAdapter for HttpAdapter {
 
     fn summary(&self) -> String {
         format!(
-            "HttpAdapter(network={}, bind={})",
-            self.network_name, self.bind_addr
+            "HttpAdapter(network={}, bind={}, tls={})",
+            self.network_name,
+            self.bind_addr,
+            self.tls.is_some()
         )
     }
 }
+
+/// Serve the plain-HTTP → HTTPS redirect listener until shutdown
+fn spawn_https_redirect(
+    network_name: String,
+    addr: SocketAddr,
+    https_port: u16,
+    shutdown: CancellationToken,
+) {
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to bind HTTPS redirect for network '{}' to {}: {}",
+                    network_name,
+                    addr,
+                    e
+                );
+                return;
+            }
+        };
+        tracing::info!(
+            "HTTP → HTTPS redirect for network '{}' listening on {}",
+            network_name,
+            addr
+        );
+        if let Err(e) = axum::serve(listener, tls::redirect_router(https_port))
+            .with_graceful_shutdown(async move { shutdown.cancelled().await })
+            .await
+        {
+            tracing::error!(
+                "HTTPS redirect for network '{}' encountered error: {}",
+                network_name,
+                e
+            );
+        }
+    });
+}
@@ -1,6 +1,6 @@
 use super::HttpAdapter;
 use crate::config::config::Config;
-use crate::models::middleware::AuthFailure;
+use crate::models::middleware::{AccessDenied, AuthFailure, RateLimited};
 use crate::pipeline::{PipelineError, PipelineExecutor};
 use axum::body::Body;
 use axum::extract::Request;
@@ -14,6 +14,7 @@ use std::sync::Arc;
 ///
 /// This replaces the old router/dispatcher logic but uses PipelineExecutor
 pub async fn build_network_router(config: Arc<Config>, network_name: &str) -> Router {
+    crate::metrics::install();
     let mut app = Router::new();
     let mut route_registry: HashSet<(Method, String)> = HashSet::new();
     
@@ -118,7 +119,31 @@ pub async fn build_network_router(config: Arc<Config>, network_name: &str) -> Ro
                         let pipeline_name = pipeline_name2.clone();
                         let config_ref = config_ref.clone();
                         async move {
-                            handle_request(&mut req, config_ref, endpoint_name, pipeline_name).await
+                            let started = std::time::Instant::now();
+                            let method = req.method().clone();
+                            let path = req.uri().path().to_string();
+                            let result = handle_request(
+                                &mut req,
+                                config_ref,
+                                &endpoint_name,
+                                pipeline_name.clone(),
+                            )
+                            .await;
+                            let status = match &result {
+                                Ok(response) => response.status(),
+                                Err(status) => *status,
+                            };
+                            crate::metrics::http_request(
+                                crate::metrics::HttpRequest {
+                                    method: method.as_str(),
+                                    endpoint: &endpoint_name,
+                                    pipeline: &pipeline_name,
+                                    path: &path,
+                                    status: status.as_u16(),
+                                },
+                                started,
+                            );
+                            result
                         }
                     };
 
@@ -156,13 +181,13 @@ pub async fn build_network_router(config: Arc<Config>, network_name: &str) -> Ro
 async fn handle_request(
     req: &mut Request,
     config: Arc<Config>,
-    endpoint_name: String,
+    endpoint_name: &str,
     pipeline_name: String,
 ) -> Result<Response<Body>, StatusCode> {
     // Look up the endpoint and pipeline from config
     let endpoint = config
         .endpoints
-        .get(&endpoint_name)
+        .get(endpoint_name)
         .ok_or(StatusCode::NOT_FOUND)?;
 
     let service = endpoint
@@ -189,12 +214,17 @@ async fn handle_request(
         .map_err(|_| StatusCode::BAD_REQUEST)?;
 
     // 3. Execute pipeline (NEW: using PipelineExecutor!)
-    let response_envelope = PipelineExecutor::execute(envelope, pipeline, &config, &ctx)
-        .await
-        .map_err(|err| {
+    let response_envelope = match PipelineExecutor::execute(envelope, pipeline, &config, &ctx).await
+    {
+        Ok(response_envelope) => response_envelope,
+        Err(err) => {
+            if let Some(response) = rate_limited_response(&err) {
+                return Ok(response);
+            }
             tracing::error!("Pipeline execution failed: {}", err);
-            map_pipeline_error_to_status(&err)
-        })?;
+            return Err(map_pipeline_error_to_status(&err));
+        }
+    };
 
     // 4. Convert ResponseEnvelope → HTTP Response
     let response = service
@@ -208,6 +238,19 @@ async fn handle_request(
     Ok(response)
 }
 
+/// 429 with `Retry-After` when a middleware rate limited the request
+fn rate_limited_response(err: &PipelineError) -> Option<Response<Body>> {
+    let PipelineError::MiddlewareError(middleware_err) = err else {
+        return None;
+    };
+    let limited = middleware_err.downcast_ref::<RateLimited>()?;
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(http::header::RETRY_AFTER, limited.retry_after_secs)
+        .body(Body::empty())
+        .ok()
+}
+
 /// Map pipeline errors to HTTP status codes
 fn map_pipeline_error_to_status(err: &PipelineError) -> StatusCode {
     match err {
@@ -215,6 +258,10 @@ fn map_pipeline_error_to_status(err: &PipelineError) -> StatusCode {
             // Check if it's an AuthFailure
             if let Some(_auth_failure) = middleware_err.downcast_ref::<AuthFailure>() {
                 StatusCode::UNAUTHORIZED
+            } else if middleware_err.downcast_ref::<AccessDenied>().is_some() {
+                StatusCode::FORBIDDEN
+            } else if middleware_err.downcast_ref::<RateLimited>().is_some() {
+                StatusCode::TOO_MANY_REQUESTS
             } else {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
@@ -224,3 +271,37 @@ fn map_pipeline_error_to_status(err: &PipelineError) -> StatusCode {
         PipelineError::ServiceError(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_middleware_auth_errors_map_to_status() {
+        let unauthenticated = PipelineError::MiddlewareError(AuthFailure("missing token").into());
+        assert_eq!(
+            map_pipeline_error_to_status(&unauthenticated),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let denied = PipelineError::MiddlewareError(AccessDenied("denied".to_string()).into());
+        assert_eq!(map_pipeline_error_to_status(&denied), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_rate_limited_maps_to_429_with_retry_after() {
+        let limited = PipelineError::MiddlewareError(
+            RateLimited {
+                retry_after_secs: 3,
+            }
+            .into(),
+        );
+
+        let response = rate_limited_response(&limited).expect("429 response");
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[http::header::RETRY_AFTER], "3");
+
+        let denied = PipelineError::MiddlewareError(AccessDenied("denied".to_string()).into());
+        assert!(rate_limited_response(&denied).is_none());
+    }
+}
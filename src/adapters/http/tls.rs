@@ -0,0 +1,194 @@
+use crate::models::network::config::HttpTlsConfig;
+use anyhow::Context;
+use axum::extract::Request;
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_rustls::rustls;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+
+/// Handshakes that take longer than this are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Build a rustls server configuration from the PEM certificate chain and private key
+pub fn load_server_config(tls: &HttpTlsConfig) -> anyhow::Result<Arc<rustls::ServerConfig>> {
+    let cert_pem = std::fs::read(&tls.cert_path)
+        .with_context(|| format!("Failed to read TLS certificate '{}'", tls.cert_path))?;
+    let certs = rustls_pemfile::certs(&mut cert_pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid PEM in TLS certificate '{}'", tls.cert_path))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in '{}'", tls.cert_path);
+    }
+
+    let key_pem = std::fs::read(&tls.key_path)
+        .with_context(|| format!("Failed to read TLS private key '{}'", tls.key_path))?;
+    let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+        .with_context(|| format!("Invalid PEM in TLS private key '{}'", tls.key_path))?
+        .ok_or_else(|| anyhow::anyhow!("No private key found in '{}'", tls.key_path))?;
+
+    // Pin the provider: both ring and aws-lc-rs are linked, so there is no process default
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("TLS certificate and private key do not match")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// TCP listener that completes the TLS handshake before handing connections to axum
+///
+/// Handshakes run on their own tasks so a slow client cannot hold up other connections.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    accepted: mpsc::Receiver<(TlsStream<tokio::net::TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(
+        listener: TcpListener,
+        config: Arc<rustls::ServerConfig>,
+        shutdown: CancellationToken,
+    ) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (tx, accepted) = mpsc::channel(64);
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    res = listener.accept() => match res {
+                        Ok(conn) => conn,
+                        Err(e) => {
+                            tracing::warn!("HTTPS accept error: {}", e);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            continue;
+                        }
+                    },
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(tls_stream)) => {
+                            let _ = tx.send((tls_stream, peer)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", peer, e),
+                        Err(_) => tracing::debug!("TLS handshake with {} timed out", peer),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            local_addr,
+            accepted,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<tokio::net::TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.accepted.recv().await {
+            Some(conn) => conn,
+            // Accept loop stopped (shutdown); park until graceful shutdown completes
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Router that answers every plain-HTTP request with a permanent redirect to HTTPS
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |req: Request| async move { https_redirect(&req, https_port) })
+}
+
+fn https_redirect(req: &Request, https_port: u16) -> Response {
+    let host = req
+        .headers()
+        .get(http::header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| req.uri().host())
+        .unwrap_or("localhost");
+    // Drop any port from the Host header; IPv6 literals keep their brackets
+    let host = match host.rfind(':') {
+        Some(idx) if !host[idx..].contains(']') => &host[..idx],
+        _ => host,
+    };
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let location = if https_port == 443 {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path)
+    };
+
+    (
+        http::StatusCode::PERMANENT_REDIRECT,
+        [(http::header::LOCATION, location)],
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect_location(host: &str, uri: &str, https_port: u16) -> String {
+        let req = Request::builder()
+            .uri(uri)
+            .header("host", host)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let resp = https_redirect(&req, https_port);
+        assert_eq!(resp.status(), http::StatusCode::PERMANENT_REDIRECT);
+        resp.headers()[http::header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_redirect_preserves_path_and_query() {
+        assert_eq!(
+            redirect_location("pacs.example.org:8080", "/dicomweb/studies?limit=5", 8443),
+            "https://pacs.example.org:8443/dicomweb/studies?limit=5"
+        );
+        assert_eq!(
+            redirect_location("pacs.example.org", "/dicomweb/studies", 443),
+            "https://pacs.example.org/dicomweb/studies"
+        );
+        assert_eq!(
+            redirect_location("[::1]:8080", "/", 8443),
+            "https://[::1]:8443/"
+        );
+    }
+
+    #[test]
+    fn test_missing_certificate_is_an_error() {
+        let tls = HttpTlsConfig {
+            cert_path: "/nonexistent/cert.pem".to_string(),
+            key_path: "/nonexistent/key.pem".to_string(),
+            redirect_http_port: None,
+        };
+        assert!(load_server_config(&tls).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod dimse;
 pub mod http;
+pub mod shutdown;
 
 use crate::config::config::Config;
 use crate::models::protocol::Protocol;
@@ -0,0 +1,250 @@
+use crate::config::shutdown_config::ShutdownConfig;
+use crate::models::protocol::Protocol;
+use crate::storage::DatabaseManager;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Phases run by [`ShutdownCoordinator::shutdown`], in the default order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownPhase {
+    /// Listeners of the current stage stop accepting new connections
+    StopAccepting,
+    /// In-flight HTTP requests are given `http_grace_secs` to finish
+    DrainHttp,
+    /// Open DIMSE associations are given `dimse_grace_secs` to finish
+    DrainDimse,
+    /// Shared database handles are released
+    ClosePools,
+}
+
+/// Coordinates ordered adapter shutdown
+///
+/// Each adapter group gets its own token, linked as a child of a root token. Stages are
+/// cancelled one at a time in the configured order, waiting for the previous stage to drain,
+/// while cancelling the root still tears everything down at once.
+pub struct ShutdownCoordinator {
+    root: CancellationToken,
+    http: CancellationToken,
+    dimse: CancellationToken,
+    config: ShutdownConfig,
+}
+
+impl ShutdownCoordinator {
+    pub fn new(config: ShutdownConfig) -> Self {
+        let root = CancellationToken::new();
+        Self {
+            http: root.child_token(),
+            dimse: root.child_token(),
+            root,
+            config,
+        }
+    }
+
+    /// Token to hand to an adapter for the given protocol
+    pub fn token_for(&self, protocol: Protocol) -> CancellationToken {
+        match protocol {
+            Protocol::Http => self.http.clone(),
+            Protocol::Dimse => self.dimse.clone(),
+            // Adapters without an ordered stage stop together at the end
+            _ => self.root.child_token(),
+        }
+    }
+
+    /// Root token; cancelling it aborts every stage immediately
+    pub fn root_token(&self) -> CancellationToken {
+        self.root.clone()
+    }
+
+    /// Run the shutdown phases and wait for every adapter task to finish
+    pub async fn shutdown(self, handles: Vec<(Protocol, JoinHandle<()>)>) {
+        let mut http_handles = Vec::new();
+        let mut dimse_handles = Vec::new();
+        let mut other_handles = Vec::new();
+        for (protocol, handle) in handles {
+            match protocol {
+                Protocol::Http => http_handles.push(handle),
+                Protocol::Dimse => dimse_handles.push(handle),
+                _ => other_handles.push(handle),
+            }
+        }
+
+        for stage in &self.config.order {
+            match stage.as_str() {
+                "http" => {
+                    tracing::info!("⏳ Shutdown: {:?} (http)", ShutdownPhase::StopAccepting);
+                    self.http.cancel();
+                    tracing::info!("⏳ Shutdown: {:?}", ShutdownPhase::DrainHttp);
+                    drain("HTTP", http_handles.drain(..), self.config.http_grace_secs).await;
+                }
+                "dimse" => {
+                    tracing::info!("⏳ Shutdown: {:?} (dimse)", ShutdownPhase::StopAccepting);
+                    self.dimse.cancel();
+                    tracing::info!("⏳ Shutdown: {:?}", ShutdownPhase::DrainDimse);
+                    drain(
+                        "DIMSE",
+                        dimse_handles.drain(..),
+                        self.config.dimse_grace_secs,
+                    )
+                    .await;
+                }
+                other => tracing::warn!("Ignoring unknown shutdown stage '{}'", other),
+            }
+        }
+
+        // Anything not covered by an ordered stage stops with the root token
+        self.root.cancel();
+        for handle in other_handles {
+            let _ = handle.await;
+        }
+
+        tracing::info!("⏳ Shutdown: {:?}", ShutdownPhase::ClosePools);
+        match DatabaseManager::global().clear_all_databases() {
+            Ok(count) => tracing::debug!("Closed {} shared database handles", count),
+            Err(e) => tracing::warn!("Failed to close shared databases: {}", e),
+        }
+    }
+}
+
+/// Wait for adapter tasks to finish, aborting any still running after the grace period
+async fn drain(label: &str, handles: impl Iterator<Item = JoinHandle<()>>, grace_secs: u64) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(grace_secs);
+    for mut handle in handles {
+        if tokio::time::timeout_at(deadline, &mut handle)
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                "{} adapter did not drain within {}s; aborting",
+                label,
+                grace_secs
+            );
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn spawn_recorder(
+        token: CancellationToken,
+        events: Arc<Mutex<Vec<&'static str>>>,
+        event: &'static str,
+        drain_for: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            token.cancelled().await;
+            events.lock().unwrap().push(event);
+            tokio::time::sleep(drain_for).await;
+        })
+    }
+
+    #[tokio::test]
+    async fn http_stops_accepting_before_dimse_is_aborted() {
+        let coordinator = ShutdownCoordinator::new(ShutdownConfig::default());
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let dimse_token = coordinator.token_for(Protocol::Dimse);
+        let http_token = coordinator.token_for(Protocol::Http);
+        let http_events = events.clone();
+        // HTTP keeps draining for a while; DIMSE must still be live throughout
+        let http = tokio::spawn(async move {
+            http_token.cancelled().await;
+            http_events.lock().unwrap().push("http_stopped");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(
+                !dimse_token.is_cancelled(),
+                "DIMSE cancelled while HTTP draining"
+            );
+            http_events.lock().unwrap().push("http_drained");
+        });
+        let dimse = spawn_recorder(
+            coordinator.token_for(Protocol::Dimse),
+            events.clone(),
+            "dimse_aborted",
+            Duration::ZERO,
+        );
+
+        coordinator
+            .shutdown(vec![(Protocol::Dimse, dimse), (Protocol::Http, http)])
+            .await;
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["http_stopped", "http_drained", "dimse_aborted"]
+        );
+    }
+
+    #[tokio::test]
+    async fn order_is_configurable() {
+        let config = ShutdownConfig {
+            order: vec!["dimse".to_string(), "http".to_string()],
+            ..ShutdownConfig::default()
+        };
+        let coordinator = ShutdownCoordinator::new(config);
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let http = spawn_recorder(
+            coordinator.token_for(Protocol::Http),
+            events.clone(),
+            "http",
+            Duration::ZERO,
+        );
+        let dimse = spawn_recorder(
+            coordinator.token_for(Protocol::Dimse),
+            events.clone(),
+            "dimse",
+            Duration::from_millis(20),
+        );
+
+        coordinator
+            .shutdown(vec![(Protocol::Http, http), (Protocol::Dimse, dimse)])
+            .await;
+
+        assert_eq!(*events.lock().unwrap(), vec!["dimse", "http"]);
+    }
+
+    #[tokio::test]
+    async fn stage_exceeding_grace_is_aborted() {
+        let config = ShutdownConfig {
+            http_grace_secs: 0,
+            ..ShutdownConfig::default()
+        };
+        let coordinator = ShutdownCoordinator::new(config);
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let http = spawn_recorder(
+            coordinator.token_for(Protocol::Http),
+            events.clone(),
+            "http",
+            Duration::from_secs(60),
+        );
+        let dimse = spawn_recorder(
+            coordinator.token_for(Protocol::Dimse),
+            events.clone(),
+            "dimse",
+            Duration::ZERO,
+        );
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            coordinator.shutdown(vec![(Protocol::Http, http), (Protocol::Dimse, dimse)]),
+        )
+        .await
+        .expect("shutdown should not wait past the grace period");
+        assert_eq!(events.lock().unwrap().last(), Some(&"dimse"));
+    }
+
+    #[test]
+    fn root_cancellation_reaches_every_stage() {
+        let coordinator = ShutdownCoordinator::new(ShutdownConfig::default());
+        let http = coordinator.token_for(Protocol::Http);
+        let dimse = coordinator.token_for(Protocol::Dimse);
+        coordinator.root_token().cancel();
+        assert!(http.is_cancelled());
+        assert!(dimse.is_cancelled());
+    }
+}
@@ -1,5 +1,9 @@
+use crate::config::env_vars::expand_env_vars;
 use crate::config::logging_config::LoggingConfig;
+use crate::config::metrics_config::MetricsConfig;
 use crate::config::proxy_config::ProxyConfig;
+use crate::config::shutdown_config::ShutdownConfig;
+use crate::config::telemetry_config::TelemetryConfig;
 use crate::config::Cli;
 use crate::models::backends::backends::Backend;
 use crate::models::endpoints::endpoint::Endpoint;
@@ -50,6 +54,12 @@ pub struct Config {
     #[serde(default)]
     pub storage: StorageConfig,
     #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
     pub transforms: (),
     /// Resolved absolute path to transforms directory (not serialized)
     #[serde(skip)]
@@ -155,6 +165,15 @@ impl Config {
     }
 
     pub fn from_args(cli: Cli) -> Self {
+        let config = Self::load(cli);
+
+        // Validate the final, merged configuration
+        config.validate().expect("Configuration validation failed");
+        config
+    }
+
+    /// Read, merge and register the configuration without validating it
+    pub fn load(cli: Cli) -> Self {
         // Verify the config file has a .toml extension
         let config_path = Path::new(&cli.config_path);
         if config_path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
@@ -167,7 +186,8 @@ impl Config {
         // Load the base configuration file
         let contents =
             std::fs::read_to_string(&cli.config_path).expect("Failed to read config file");
-        let mut config: Config = toml::from_str(&contents).expect("Failed to parse config");
+        let mut config = Self::parse_with_env(&contents, &|name| std::env::var(name).ok())
+            .expect("Failed to parse config");
 
         // Resolve transforms_path relative to config file directory
         let base_dir = config_path
@@ -181,8 +201,9 @@ impl Config {
         );
 
         // Attempt to load additional configs and merge them into the current config.
-        if let Ok(additional_configs) = Self::load_additional_configs(&config, &cli.config_path) {
-            config = Self::merge_configs(config, additional_configs);
+        match Self::load_additional_configs(&config, &cli.config_path) {
+            Ok(additional_configs) => config = Self::merge_configs(config, additional_configs),
+            Err(e) => panic!("Failed to load pipeline configuration: {}", e),
         }
 
         // Inject management service if enabled
@@ -191,12 +212,22 @@ impl Config {
         // Initialize both registries
         config.initialize_service_registry();
         config.initialize_middleware_registry();
-
-        // Validate the final, merged configuration
-        config.validate().expect("Configuration validation failed");
         config
     }
 
+    /// Parse TOML, expanding `${NAME}` / `${NAME:-default}` in string values first
+    pub(crate) fn parse_with_env(
+        contents: &str,
+        lookup: &impl Fn(&str) -> Option<String>,
+    ) -> Result<Config, ConfigError> {
+        let invalid = |e: toml::de::Error| ConfigError::InvalidToml {
+            reason: e.to_string(),
+        };
+        let mut value: toml::Value = toml::from_str(contents).map_err(invalid)?;
+        expand_env_vars(&mut value, lookup)?;
+        value.try_into().map_err(invalid)
+    }
+
     fn initialize_service_registry(&self) {
         initialise_service_registry(self);
     }
@@ -238,7 +269,8 @@ impl Config {
             let path = entry?.path();
             if path.is_file() && path.extension().is_some_and(|ext| ext == "toml") {
                 let contents = fs::read_to_string(&path)?;
-                let config: Config = toml::from_str(&contents)?;
+                let config = Self::parse_with_env(&contents, &|name| std::env::var(name).ok())
+                    .map_err(|e| format!("{}: {:?}", path.display(), e))?;
                 configs.push(config);
             }
         }
@@ -277,10 +309,109 @@ impl Config {
         self.validate_backends()?;
         self.validate_targets()?;
         self.validate_storage()?;
+        self.validate_shutdown()?;
+        self.validate_metrics()?;
 
         Ok(())
     }
 
+    /// Every problem found in the configuration, for `--validate`
+    ///
+    /// Stricter than [`Config::validate`]: pipelines must reference existing networks,
+    /// endpoints and backends, bind addresses must parse, and backend service validation
+    /// failures are errors rather than warnings. Opens no sockets.
+    pub fn check(&self) -> Vec<ConfigError> {
+        let mut errors: Vec<ConfigError> = [
+            self.validate_proxy(),
+            self.validate_networks(),
+            self.validate_management(),
+            self.validate_middleware_types(),
+            self.validate_targets(),
+            self.validate_storage(),
+            self.validate_shutdown(),
+            self.validate_metrics(),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+
+        let mut networks: Vec<_> = self.network.iter().collect();
+        networks.sort_by_key(|(name, _)| *name);
+        for (name, network) in networks {
+            let bind = format!("{}:{}", network.http.bind_address, network.http.bind_port);
+            if bind.parse::<std::net::SocketAddr>().is_err() {
+                errors.push(ConfigError::InvalidNetwork {
+                    name: name.clone(),
+                    reason: format!("invalid HTTP bind address '{}'", bind),
+                });
+            }
+        }
+
+        let mut pipelines: Vec<_> = self.pipelines.iter().collect();
+        pipelines.sort_by_key(|(name, _)| *name);
+        for (name, pipeline) in pipelines {
+            let dangling = pipeline
+                .networks
+                .iter()
+                .filter(|n| !self.network.contains_key(*n))
+                .map(|n| format!("unknown network '{}'", n))
+                .chain(
+                    pipeline
+                        .endpoints
+                        .iter()
+                        .filter(|e| !self.endpoints.contains_key(*e))
+                        .map(|e| format!("unknown endpoint '{}'", e)),
+                )
+                .chain(
+                    pipeline
+                        .backends
+                        .iter()
+                        .filter(|b| !self.backends.contains_key(*b))
+                        .map(|b| format!("unknown backend '{}'", b)),
+                );
+            errors.extend(dangling.map(|reason| ConfigError::InvalidPipeline {
+                name: name.clone(),
+                reason,
+            }));
+        }
+
+        let mut endpoints: Vec<_> = self.endpoints.iter().collect();
+        endpoints.sort_by_key(|(name, _)| *name);
+        for (name, endpoint) in endpoints {
+            let options = endpoint.options.as_ref().unwrap_or(&DEFAULT_OPTIONS);
+            let result = endpoint.resolve_service().and_then(|service| {
+                service
+                    .validate(options)
+                    .map_err(|err| format!("Service validation failed: {:?}", err))
+            });
+            if let Err(reason) = result {
+                errors.push(ConfigError::InvalidEndpoint {
+                    name: name.clone(),
+                    reason,
+                });
+            }
+        }
+
+        let mut backends: Vec<_> = self.backends.iter().collect();
+        backends.sort_by_key(|(name, _)| *name);
+        for (name, backend) in backends {
+            let options = backend.options.as_ref().unwrap_or(&DEFAULT_OPTIONS);
+            let result = backend.resolve_service().and_then(|service| {
+                service
+                    .validate(options)
+                    .map_err(|err| format!("Service validation failed: {:?}", err))
+            });
+            if let Err(reason) = result {
+                errors.push(ConfigError::InvalidBackend {
+                    name: name.clone(),
+                    reason,
+                });
+            }
+        }
+
+        errors
+    }
+
     fn validate_proxy(&self) -> Result<(), ConfigError> {
         if self.proxy.id.trim().is_empty() {
             return Err(ConfigError::InvalidProxy {
@@ -318,6 +449,21 @@ impl Config {
                     reason: "invalid bind port for Wireguard".to_string(),
                 });
             }
+            if let Some(tls) = &network.http.tls {
+                if tls.cert_path.trim().is_empty() || tls.key_path.trim().is_empty() {
+                    return Err(ConfigError::InvalidNetwork {
+                        name: name.clone(),
+                        reason: "http.tls requires cert_path and key_path".to_string(),
+                    });
+                }
+                if tls.redirect_http_port == Some(network.http.bind_port) {
+                    return Err(ConfigError::InvalidNetwork {
+                        name: name.clone(),
+                        reason: "http.tls.redirect_http_port must differ from bind_port"
+                            .to_string(),
+                    });
+                }
+            }
         }
         Ok(())
     }
@@ -456,7 +602,8 @@ impl Config {
                 match name.as_str() {
                     "jwtauth" | "basic_auth" | "connect" | "passthru" | "json_extractor"
                     | "json" | "jmix_builder" | "dicomweb_bridge" | "dicomweb" | "transform"
-                    | "metadata_transform" | "path_filter" => {}
+                    | "metadata_transform" | "path_filter" | "study_cache" | "policy_authz"
+                    | "rate_limit" | "cache" => {}
                     _ => {
                         return Err(ConfigError::InvalidMiddleware {
                             name: name.clone(),
@@ -475,6 +622,18 @@ impl Config {
             .map_err(|err| ConfigError::InvalidManagement { reason: err })
     }
 
+    fn validate_shutdown(&self) -> Result<(), ConfigError> {
+        self.shutdown
+            .validate()
+            .map_err(|err| ConfigError::InvalidShutdown { reason: err })
+    }
+
+    fn validate_metrics(&self) -> Result<(), ConfigError> {
+        self.metrics
+            .validate()
+            .map_err(|reason| ConfigError::InvalidMetrics { reason })
+    }
+
     fn validate_storage(&self) -> Result<(), ConfigError> {
         match self.storage.backend.as_str() {
             "filesystem" => {
@@ -494,6 +653,12 @@ impl Config {
                         });
                     }
                 }
+                crate::storage::retention::parse_config(&self.storage).map_err(|reason| {
+                    ConfigError::InvalidStorage {
+                        backend: self.storage.backend.clone(),
+                        reason,
+                    }
+                })?;
                 // Path is optional and defaults to "./tmp"
                 Ok(())
             }
@@ -516,4 +681,42 @@ pub enum ConfigError {
     InvalidPipeline { name: String, reason: String },
     InvalidMiddleware { name: String, reason: String }, // Added for middleware validation
     InvalidStorage { backend: String, reason: String }, // Added for storage validation
+    InvalidShutdown { reason: String },
+    InvalidMetrics { reason: String },
+    InvalidToml { reason: String },
+    InvalidEnvVar { key: String, reason: String }, // Unexpandable `${NAME}` at TOML key `key`
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::InvalidProxy { name, reason } => write!(f, "proxy '{}': {}", name, reason),
+            ConfigError::MissingTargets { name, reason } => {
+                write!(f, "target '{}': {}", name, reason)
+            }
+            ConfigError::InvalidManagement { reason } => write!(f, "management: {}", reason),
+            ConfigError::InvalidEndpoint { name, reason } => {
+                write!(f, "endpoint '{}': {}", name, reason)
+            }
+            ConfigError::InvalidBackend { name, reason } => {
+                write!(f, "backend '{}': {}", name, reason)
+            }
+            ConfigError::InvalidNetwork { name, reason } => {
+                write!(f, "network '{}': {}", name, reason)
+            }
+            ConfigError::InvalidPipeline { name, reason } => {
+                write!(f, "pipeline '{}': {}", name, reason)
+            }
+            ConfigError::InvalidMiddleware { name, reason } => {
+                write!(f, "middleware '{}': {}", name, reason)
+            }
+            ConfigError::InvalidStorage { backend, reason } => {
+                write!(f, "storage '{}': {}", backend, reason)
+            }
+            ConfigError::InvalidShutdown { reason } => write!(f, "shutdown: {}", reason),
+            ConfigError::InvalidMetrics { reason } => write!(f, "metrics: {}", reason),
+            ConfigError::InvalidToml { reason } => write!(f, "TOML: {}", reason),
+            ConfigError::InvalidEnvVar { key, reason } => write!(f, "'{}': {}", key, reason),
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use crate::config::config::ConfigError;
+
+/// Expand `${NAME}` and `${NAME:-default}` in every string value of a parsed TOML document
+///
+/// Keys are left untouched. `lookup` resolves a variable name, normally from the process
+/// environment; a reference with no value and no default fails with the TOML key path.
+pub(crate) fn expand_env_vars(
+    value: &mut toml::Value,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    expand_at(value, "", lookup)
+}
+
+fn expand_at(
+    value: &mut toml::Value,
+    path: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    match value {
+        toml::Value::String(s) => {
+            if s.contains("${") {
+                *s = expand_str(s, path, lookup)?;
+            }
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                expand_at(item, &format!("{}[{}]", path, i), lookup)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                expand_at(item, &child, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_str(
+    input: &str,
+    path: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find('}').ok_or_else(|| ConfigError::InvalidEnvVar {
+            key: path.to_string(),
+            reason: format!("Unterminated '${{' in \"{}\"", input),
+        })?;
+        let reference = &after[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if name.is_empty() {
+            return Err(ConfigError::InvalidEnvVar {
+                key: path.to_string(),
+                reason: format!("Empty variable name in \"{}\"", input),
+            });
+        }
+        // As in the shell, `:-` also replaces a variable that is set but empty
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => out.push_str(default),
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {
+                return Err(ConfigError::InvalidEnvVar {
+                    key: path.to_string(),
+                    reason: format!(
+                        "Environment variable '{}' is not set and has no default",
+                        name
+                    ),
+                })
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
@@ -1,7 +1,39 @@
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize)]
 pub struct LoggingConfig {
     pub log_to_file: bool,
     pub log_file_path: String,
+    /// Hash PHI fields (patient IDs) attached to operation logs
+    #[serde(default = "default_hash_phi")]
+    pub hash_phi: bool,
+    /// Output format of the stdout and file appenders
+    #[serde(default)]
+    pub format: LogFormat,
+}
+
+/// Log line format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with event fields and the enclosing spans
+    Json,
+}
+
+fn default_hash_phi() -> bool {
+    true
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            log_to_file: false,
+            log_file_path: String::new(),
+            hash_phi: default_hash_phi(),
+            format: LogFormat::default(),
+        }
+    }
 }
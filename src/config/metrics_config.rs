@@ -0,0 +1,26 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Prometheus label sets; only takes effect when built with feature "metrics"
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MetricsConfig {
+    /// Labels to record, by metric name; metrics not listed keep their default labels
+    #[serde(default)]
+    pub labels: HashMap<String, Vec<String>>,
+}
+
+impl MetricsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (metric, labels) in &self.labels {
+            let available = crate::metrics::available_labels(metric)
+                .ok_or_else(|| format!("Unknown metric '{}'", metric))?;
+            if let Some(label) = labels.iter().find(|l| !available.contains(&l.as_str())) {
+                return Err(format!(
+                    "Metric '{}' has no label '{}'. Valid labels are: {:?}",
+                    metric, label, available
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
 #[allow(clippy::module_inception)]
 pub mod config;
+mod env_vars;
 mod logging_config;
+pub mod metrics_config;
 mod proxy_config;
+pub mod shutdown_config;
+pub mod telemetry_config;
 mod tests;
 
 /// Structure representing application startup arguments or metadata.
@@ -0,0 +1,60 @@
+use serde::Deserialize;
+
+/// Adapter groups that can be drained in a configurable order
+pub const SHUTDOWN_STAGES: &[&str] = &["http", "dimse"];
+
+/// Represents the ordered shutdown configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShutdownConfig {
+    /// Order in which adapter groups stop accepting work and drain
+    #[serde(default = "default_order")]
+    pub order: Vec<String>,
+    /// Seconds to wait for in-flight HTTP requests before aborting the listeners
+    #[serde(default = "default_grace_secs")]
+    pub http_grace_secs: u64,
+    /// Seconds to wait for open DIMSE associations before aborting the SCPs
+    #[serde(default = "default_grace_secs")]
+    pub dimse_grace_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            order: default_order(),
+            http_grace_secs: default_grace_secs(),
+            dimse_grace_secs: default_grace_secs(),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for stage in &self.order {
+            if !SHUTDOWN_STAGES.contains(&stage.as_str()) {
+                return Err(format!(
+                    "Unknown shutdown stage '{}'. Valid stages are: {:?}",
+                    stage, SHUTDOWN_STAGES
+                ));
+            }
+        }
+        for stage in SHUTDOWN_STAGES {
+            let count = self.order.iter().filter(|s| s.as_str() == *stage).count();
+            if count != 1 {
+                return Err(format!(
+                    "Shutdown order must list '{}' exactly once (found {})",
+                    stage, count
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// HTTP drains before DIMSE so new DICOMweb requests cannot open fresh SCU associations
+fn default_order() -> Vec<String> {
+    vec!["http".to_string(), "dimse".to_string()]
+}
+
+fn default_grace_secs() -> u64 {
+    30
+}
@@ -71,3 +71,123 @@ fn test_basic_config() {
     assert_eq!(config.network["default"].interface, "wg0");
     assert_eq!(config.network["default"].http.bind_address, "127.0.0.1");
 }
+
+/// Variables visible to `Config::parse_with_env` in the tests below
+fn test_env(name: &str) -> Option<String> {
+    match name {
+        "PACS_HOST" => Some("pacs.internal".to_string()),
+        "PACS_AET" => Some("".to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_env_var_substitution() {
+    let toml = r#"
+        [proxy]
+        id = "${PROXY_ID:-env-test}"
+
+        [backends.pacs]
+        service = "dicom"
+        [backends.pacs.options]
+        host = "${PACS_HOST}"
+        port = 104
+        aet = "${PACS_AET:-ORTHANC}"
+        note = "${PACS_HOST}:104 via ${PACS_HOST}"
+        "${PACS_HOST}" = "keys are not expanded"
+    "#;
+
+    let config = Config::parse_with_env(toml, &test_env).expect("config should parse");
+
+    assert_eq!(config.proxy.id, "env-test");
+    let options = config.backends["pacs"].options.as_ref().unwrap();
+    assert_eq!(options["host"], "pacs.internal");
+    assert_eq!(options["aet"], "ORTHANC");
+    assert_eq!(options["note"], "pacs.internal:104 via pacs.internal");
+    assert!(options.contains_key("${PACS_HOST}"));
+}
+
+#[test]
+fn test_env_var_missing_is_descriptive_error() {
+    let toml = r#"
+        [backends.pacs]
+        service = "dicom"
+        [backends.pacs.options]
+        host = "${MISSING_PACS_HOST}"
+    "#;
+
+    match Config::parse_with_env(toml, &test_env) {
+        Err(ConfigError::InvalidEnvVar { key, reason }) => {
+            assert_eq!(key, "backends.pacs.options.host");
+            assert!(reason.contains("MISSING_PACS_HOST"), "{}", reason);
+        }
+        other => panic!("expected InvalidEnvVar, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_check_reports_dangling_references() {
+    let toml = r#"
+        [proxy]
+        id = "check-test"
+
+        [network.default]
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "not an address"
+        bind_port = 8080
+
+        [pipelines.core]
+        networks = ["default", "missing_net"]
+        endpoints = ["basic", "missing_endpoint"]
+        backends = []
+        middleware = []
+
+        [endpoints.basic]
+        service = "http"
+        [endpoints.basic.options]
+        path_prefix = "/basic"
+    "#;
+    let config: Config = toml::from_str(toml).expect("TOML parse error");
+
+    let errors: Vec<String> = config.check().iter().map(|e| e.to_string()).collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            "network 'default': invalid HTTP bind address 'not an address:8080'",
+            "pipeline 'core': unknown network 'missing_net'",
+            "pipeline 'core': unknown endpoint 'missing_endpoint'",
+        ]
+    );
+}
+
+#[test]
+fn test_metric_label_sets_must_name_known_labels() {
+    let config = |labels: &str| {
+        let toml = format!(
+            r#"
+            [proxy]
+            id = "metrics-test"
+
+            [metrics.labels]
+            {}
+            "#,
+            labels
+        );
+        load_config_from_str(&toml)
+    };
+
+    assert!(config(r#"harmony_http_requests_total = ["method", "status"]"#).is_ok());
+    assert!(config(r#"dimse_failures_total = []"#).is_ok());
+    let err = config(r#"dimse_failures_total = ["study_uid"]"#).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("metrics: Metric 'dimse_failures_total' has no label 'study_uid'"));
+    let err = config(r#"dimse_unknown_total = ["role"]"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "metrics: Unknown metric 'dimse_unknown_total'"
+    );
+}
@@ -2,9 +2,11 @@ use crate::config::config::Config;
 use crate::storage::StorageBackend;
 use once_cell::sync::Lazy;
 use std::sync::{Arc, RwLock};
+use tokio_util::sync::CancellationToken;
 
 static CONFIG_CELL: Lazy<RwLock<Option<Arc<Config>>>> = Lazy::new(|| RwLock::new(None));
 static STORAGE_CELL: Lazy<RwLock<Option<Arc<dyn StorageBackend>>>> = Lazy::new(|| RwLock::new(None));
+static SHUTDOWN_CELL: Lazy<RwLock<Option<CancellationToken>>> = Lazy::new(|| RwLock::new(None));
 
 pub fn set_config(config: Arc<Config>) {
     let mut cell = CONFIG_CELL.write().unwrap();
@@ -30,3 +32,14 @@ pub fn reset_storage() {
     let mut cell = STORAGE_CELL.write().unwrap();
     *cell = None;
 }
+
+pub fn set_shutdown_token(token: CancellationToken) {
+    let mut cell = SHUTDOWN_CELL.write().unwrap();
+    *cell = Some(token);
+}
+
+/// Application-wide shutdown token for long-running request work.
+/// Returns a token that is never cancelled when none has been registered.
+pub fn shutdown_token() -> CancellationToken {
+    SHUTDOWN_CELL.read().unwrap().clone().unwrap_or_default()
+}
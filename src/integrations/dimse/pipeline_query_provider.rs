@@ -1,12 +1,15 @@
 use crate::globals::get_config;
+use crate::log_context::OperationContext;
 use crate::models::envelope::envelope::ResponseEnvelope;
 use crate::pipeline::executor::PipelineExecutor;
 use async_trait::async_trait;
 use dicom_json_tool as tool;
 use dimse::error::DimseError;
 use dimse::types::{DatasetStream, QueryLevel};
+use dimse::worklist::{WorklistQuery, MODALITY_WORKLIST_FIND};
 use dimse::Result as DimseResult;
 use std::collections::HashMap;
+use tracing::Instrument;
 
 use once_cell::sync::Lazy;
 use std::path::PathBuf;
@@ -82,6 +85,40 @@ impl PipelineQueryProvider {
         tool::model::QueryMetadata(out)
     }
 
+    /// Datasets in a pipeline's C-FIND response
+    ///
+    /// The body is the `matches` array a DICOM backend answers a C-FIND with, or a bare array
+    /// of DICOM JSON datasets; a backend that reports `success: false` fails the query.
+    fn response_matches(response: &ResponseEnvelope<Vec<u8>>) -> DimseResult<Vec<DatasetStream>> {
+        let body = response.normalized_data.clone().unwrap_or_else(|| {
+            serde_json::from_slice(&response.original_data).unwrap_or(serde_json::Value::Null)
+        });
+        if body.get("success").and_then(|v| v.as_bool()) == Some(false) {
+            return Err(DimseError::operation_failed(format!(
+                "Pipeline query failed: {}",
+                body.get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error")
+            )));
+        }
+        let matches = match &body {
+            serde_json::Value::Array(items) => items.as_slice(),
+            _ => body
+                .get("matches")
+                .and_then(|m| m.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        };
+        matches
+            .iter()
+            .map(|item| {
+                tool::json_value_to_identifier(item)
+                    .map(DatasetStream::from_object)
+                    .map_err(|e| DimseError::operation_failed(format!("Match from JSON: {}", e)))
+            })
+            .collect()
+    }
+
     async fn run(
         &self,
         op: &str,
@@ -163,8 +200,12 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
         let body = serde_json::to_value(&wrapper)
             .map_err(|e| DimseError::operation_failed(format!("Wrapper serialize: {}", e)))?;
 
-        let response_envelope = self.run("C-FIND", body, meta).await?;
-        
+        let log_ctx = OperationContext::new("C-FIND", "").with_parameters(parameters);
+        let response_envelope = self
+            .run("C-FIND", body, meta)
+            .instrument(log_ctx.span())
+            .await?;
+
         // TODO(Phase 3C): Map ResponseEnvelope to C-FIND datasets
         // - Extract normalized_data from response
         // - Convert JSON results to DICOM datasets
@@ -172,14 +213,66 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
         // - Send final Success status
         // For now, log the response and return empty (stub)
         tracing::debug!(
+            operation = %log_ctx.operation,
+            study_uid = %log_ctx.study_uid,
+            patient_id = %log_ctx.patient_id,
             "C-FIND response status: {}, payload size: {} bytes",
             response_envelope.response_details.status,
             response_envelope.original_data.len()
         );
-        
+
         Ok(vec![])
     }
 
+    async fn worklist(&self, query: &WorklistQuery) -> DimseResult<Vec<DatasetStream>> {
+        let mut meta = HashMap::new();
+        meta.insert("dicom.operation".into(), "C-FIND".into());
+        meta.insert("dicom.query_level".into(), "WORKLIST".into());
+        meta.insert("dicom.max_results".into(), query.max_results.to_string());
+
+        let cmd = tool::model::CommandMeta {
+            message_id: Some(1),
+            sop_class_uid: Some(MODALITY_WORKLIST_FIND.into()),
+            priority: Some("MEDIUM".into()),
+            direction: Some("REQUEST".into()),
+        };
+        // The scheduled procedure step keys are nested, so encode the full identifier
+        let identifier = query
+            .to_identifier()
+            .map_err(DimseError::config)
+            .and_then(|obj| {
+                tool::identifier_to_json_value(&obj)
+                    .map_err(|e| DimseError::operation_failed(format!("Identifier to JSON: {}", e)))
+            })?;
+        let wrapper = tool::model::Wrapper {
+            command: Some(cmd),
+            identifier,
+            query_metadata: Some(self.build_query_metadata(&query.parameters)),
+        };
+        let body = serde_json::to_value(&wrapper)
+            .map_err(|e| DimseError::operation_failed(format!("Wrapper serialize: {}", e)))?;
+
+        let log_ctx = OperationContext::new("C-FIND", "").with_parameters(&query.parameters);
+        let response_envelope = self
+            .run("C-FIND", body, meta)
+            .instrument(log_ctx.span())
+            .await?;
+
+        tracing::debug!(
+            operation = %log_ctx.operation,
+            patient_id = %log_ctx.patient_id,
+            "MWL C-FIND response status: {}, payload size: {} bytes",
+            response_envelope.response_details.status,
+            response_envelope.original_data.len()
+        );
+
+        let mut items = Self::response_matches(&response_envelope)?;
+        if query.max_results > 0 {
+            items.truncate(query.max_results as usize);
+        }
+        Ok(items)
+    }
+
     async fn locate(
         &self,
         query_level: QueryLevel,
@@ -205,8 +298,12 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
         let body = serde_json::to_value(&wrapper)
             .map_err(|e| DimseError::operation_failed(format!("Wrapper serialize: {}", e)))?;
 
-        let response_envelope = self.run("C-MOVE", body, meta).await?;
-        
+        let log_ctx = OperationContext::new("C-MOVE", "").with_parameters(parameters);
+        let response_envelope = self
+            .run("C-MOVE", body, meta)
+            .instrument(log_ctx.span())
+            .await?;
+
         // TODO(Phase 3C): Map ResponseEnvelope to C-MOVE progress
         // - Parse response for move progress (remaining/completed sub-ops)
         // - Stream Pending responses with counters
@@ -214,15 +311,21 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
         // - Send final Success/Failure status
         // For now, log the response and return empty (stub)
         tracing::debug!(
+            operation = %log_ctx.operation,
+            study_uid = %log_ctx.study_uid,
+            patient_id = %log_ctx.patient_id,
             "C-MOVE response status: {}, payload size: {} bytes",
             response_envelope.response_details.status,
             response_envelope.original_data.len()
         );
-        
+
         Ok(vec![])
     }
 
     async fn store(&self, dataset: DatasetStream) -> DimseResult<()> {
+        let log_ctx = OperationContext::new("C-STORE", "")
+            .with_study_uid(dataset.metadata().study_instance_uid.as_deref())
+            .with_patient_id(dataset.metadata().patient_id.as_deref());
         // Write incoming dataset into the current per-move directory if set, otherwise default
         let target_dir = get_current_store_dir().unwrap_or_else(|| PathBuf::from("./tmp/dimse"));
         if let Err(e) = tokio::fs::create_dir_all(&target_dir).await {
@@ -249,9 +352,16 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
         // - Map 2xx → DIMSE Success (0x0000)
         // - Map 4xx/5xx → appropriate DIMSE failure statuses
         // - Return status via DimseResult
-        match self.run("C-STORE", body, meta).await {
+        match self
+            .run("C-STORE", body, meta)
+            .instrument(log_ctx.span())
+            .await
+        {
             Ok(response) => {
                 tracing::debug!(
+                    operation = %log_ctx.operation,
+                    study_uid = %log_ctx.study_uid,
+                    patient_id = %log_ctx.patient_id,
                     "C-STORE pipeline response: status={}",
                     response.response_details.status
                 );
@@ -259,7 +369,13 @@ impl dimse::scp::QueryProvider for PipelineQueryProvider {
                 Ok(())
             }
             Err(e) => {
-                tracing::error!("C-STORE pipeline failed: {}", e);
+                tracing::error!(
+                    operation = %log_ctx.operation,
+                    study_uid = %log_ctx.study_uid,
+                    patient_id = %log_ctx.patient_id,
+                    "C-STORE pipeline failed: {}",
+                    e
+                );
                 Err(e)
             }
         }
@@ -3,21 +3,24 @@ pub mod config;
 mod file;
 pub mod globals;
 pub mod integrations;
+mod log_context;
+pub mod metrics;
 pub mod models;
 pub mod pipeline;
 pub mod router;
 pub mod runbeam_api;
 pub mod storage;
+pub mod telemetry;
 mod utils;
 
 use crate::adapters::dimse::DimseAdapter;
 use crate::adapters::http::HttpAdapter;
+use crate::adapters::shutdown::ShutdownCoordinator;
 use crate::adapters::ProtocolAdapter;
 use crate::config::config::Config;
 use crate::storage::create_storage_backend;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio_util::sync::CancellationToken;
 use tracing_subscriber::{self, prelude::*};
 
 pub async fn run(config: Config) {
@@ -29,39 +32,43 @@ pub async fn run(config: Config) {
         create_storage_backend(&config.storage).expect("Failed to create storage backend");
     crate::globals::set_storage(storage);
 
+    // Metrics recorded by the adapters are collected from here on, with the configured labels
+    crate::metrics::install();
+    crate::metrics::configure(&config.metrics);
+
     // Initialise logging
+    let format = config.logging.format;
     if config.logging.log_to_file {
-        let file_appender = tracing_subscriber::fmt::layer()
-            .with_file(true)
-            .with_line_number(true)
-            .with_writer(std::fs::File::create(&config.logging.log_file_path).unwrap());
-
-        let stdout_appender = tracing_subscriber::fmt::layer()
-            .with_file(true)
-            .with_line_number(true);
+        let file = std::fs::File::create(&config.logging.log_file_path).unwrap();
+        let file_appender = log_context::fmt_layer(format, std::sync::Mutex::new(file));
+        let stdout_appender = log_context::fmt_layer(format, std::io::stdout);
 
         tracing_subscriber::registry()
             .with(file_appender)
             .with(stdout_appender)
+            .with(crate::telemetry::layer(&config.telemetry))
             .try_init()
             .expect("Failed to initialise logging");
     } else {
-        tracing_subscriber::fmt()
-            .with_file(true)
-            .with_line_number(true)
+        // Same INFO default as the plain `tracing_subscriber::fmt()` subscriber
+        let stdout_appender = log_context::fmt_layer(format, std::io::stdout)
+            .with_filter(tracing_subscriber::filter::LevelFilter::INFO);
+
+        tracing_subscriber::registry()
+            .with(stdout_appender)
+            .with(crate::telemetry::layer(&config.telemetry))
             .init();
     }
 
     tracing::info!("🔧 Starting Harmony '{}'", config.proxy.id);
 
-    // Create shared shutdown token
-    let shutdown = CancellationToken::new();
+    // Create the shutdown coordinator; each protocol gets its own linked token
+    let shutdown = ShutdownCoordinator::new(config.shutdown.clone());
     let mut adapter_handles = Vec::new();
 
     // Start protocol adapters for each network
     for (network_name, network) in config.network.clone() {
         let config_clone = Arc::clone(&config);
-        let shutdown_clone = shutdown.clone();
 
         // Create and start all adapters for this network
         let adapters: Vec<Box<dyn ProtocolAdapter>> = vec![
@@ -73,6 +80,7 @@ pub async fn run(config: Config) {
                         panic!("Invalid bind address or port for network {}", network_name)
                     });
                 HttpAdapter::new(network_name.clone(), bind_addr)
+                    .with_tls(network.http.tls.clone())
             }),
             // DIMSE adapter
             Box::new(DimseAdapter::new(network_name.clone())),
@@ -80,14 +88,15 @@ pub async fn run(config: Config) {
 
         // Start each adapter
         for adapter in adapters {
-            match adapter.start(config_clone.clone(), shutdown_clone.clone()).await {
+            let token = shutdown.token_for(adapter.protocol());
+            match adapter.start(config_clone.clone(), token).await {
                 Ok(handle) => {
                     tracing::info!(
                         "🚀 Started {} for network '{}'",
                         adapter.summary(),
                         network_name
                     );
-                    adapter_handles.push(handle);
+                    adapter_handles.push((adapter.protocol(), handle));
                 }
                 Err(e) => {
                     tracing::error!(
@@ -101,20 +110,36 @@ pub async fn run(config: Config) {
         }
     }
 
+    // Long-running request work (e.g. JMIX builds) observes the root shutdown token
+    crate::globals::set_shutdown_token(shutdown.root_token());
+
+    // Background study cache sweeps stop with the root shutdown token
+    let _sweepers = crate::models::middleware::types::study_cache::spawn_sweepers(
+        config.clone(),
+        shutdown.root_token(),
+    );
+
+    // Storage retention sweeps also stop with the root shutdown token
+    let _retention_sweeper =
+        crate::storage::retention::spawn_sweeper(&config.storage, shutdown.root_token());
+
     // Wait for ctrl-c signal
     tracing::info!("✓ All adapters started. Press Ctrl+C to shutdown.");
     tokio::signal::ctrl_c()
         .await
         .expect("Failed to listen for ctrl-c signal");
 
-    // Trigger shutdown
+    // Trigger ordered shutdown; a second ctrl-c aborts every stage at once
     tracing::info!("⏳ Shutting down...");
-    shutdown.cancel();
-
-    // Wait for all adapters to complete
-    for handle in adapter_handles {
-        let _ = handle.await;
+    let root = shutdown.root_token();
+    tokio::select! {
+        _ = shutdown.shutdown(adapter_handles) => {}
+        _ = tokio::signal::ctrl_c() => {
+            tracing::warn!("Second interrupt received; aborting remaining shutdown phases");
+            root.cancel();
+        }
     }
 
     tracing::info!("✓ Harmony shut down gracefully.");
+    crate::telemetry::shutdown();
 }
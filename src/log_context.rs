@@ -0,0 +1,269 @@
+//! Study/patient context for per-operation log spans and events
+//!
+//! Fields are emitted under fixed names (`operation`, `study_uid`, `patient_id`, `remote_aet`) so
+//! log queries such as "everything for study X" work across the DIMSE backend, the SCP handlers
+//! and the JMIX builder. Patient IDs are hashed unless `logging.hash_phi = false`.
+//! Each pipeline run, SCP association and SCU operation also carries a `correlation_id`
+//! span field, so one request's lines can be picked out even when they name no study.
+//! With `logging.format = "json"` these fields, and those of the enclosing spans, come out as
+//! JSON properties.
+
+use crate::config::logging_config::LogFormat;
+use crate::globals::get_config;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const STUDY_INSTANCE_UID: &str = "0020000D";
+const PATIENT_ID: &str = "00100020";
+const PATIENT_NAME: &str = "00100010";
+
+/// Request metadata key and span field for the ID shared by every log line of one request
+pub(crate) const CORRELATION_ID: &str = "correlation_id";
+
+/// Context carried by operation-level log events
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OperationContext {
+    pub operation: String,
+    pub study_uid: String,
+    /// Already redacted when PHI hashing is enabled
+    pub patient_id: String,
+    pub remote_aet: String,
+}
+
+impl OperationContext {
+    pub fn new(operation: impl Into<String>, remote_aet: impl Into<String>) -> Self {
+        Self {
+            operation: operation.into(),
+            remote_aet: remote_aet.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Pick StudyInstanceUID and PatientID out of a DICOM JSON identifier
+    pub fn with_identifier(self, identifier: &Value) -> Self {
+        let first = |tag: &str| {
+            identifier
+                .get(tag)
+                .and_then(|e| e.get("Value"))
+                .and_then(|v| v.get(0))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+        let study_uid = first(STUDY_INSTANCE_UID);
+        let patient_id = first(PATIENT_ID);
+        self.with_study_uid(study_uid.as_deref())
+            .with_patient_id(patient_id.as_deref())
+    }
+
+    /// Pick StudyInstanceUID and PatientID out of flattened tag → value query parameters
+    pub fn with_parameters(self, parameters: &HashMap<String, String>) -> Self {
+        self.with_study_uid(parameters.get(STUDY_INSTANCE_UID).map(|s| s.as_str()))
+            .with_patient_id(parameters.get(PATIENT_ID).map(|s| s.as_str()))
+    }
+
+    pub fn with_study_uid(mut self, study_uid: Option<&str>) -> Self {
+        if let Some(uid) = study_uid.map(str::trim).filter(|s| !s.is_empty()) {
+            self.study_uid = uid.to_string();
+        }
+        self
+    }
+
+    pub fn with_patient_id(mut self, patient_id: Option<&str>) -> Self {
+        if let Some(id) = patient_id.map(str::trim).filter(|s| !s.is_empty()) {
+            self.patient_id = redact_phi(id);
+        }
+        self
+    }
+
+    /// Span carrying the context for everything logged while the operation runs
+    pub fn span(&self) -> tracing::Span {
+        tracing::info_span!(
+            "dicom_operation",
+            operation = %self.operation,
+            study_uid = %self.study_uid,
+            patient_id = %self.patient_id,
+            remote_aet = %self.remote_aet,
+        )
+    }
+}
+
+/// Formatting layer writing events to `writer` in the configured log format
+pub(crate) fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_file(true)
+        .with_line_number(true)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// Whether PHI fields are hashed before logging (on unless the config turns it off)
+fn hash_phi_enabled() -> bool {
+    get_config().map(|c| c.logging.hash_phi).unwrap_or(true)
+}
+
+/// Hash a PHI value for logging, or pass it through when hashing is disabled
+pub(crate) fn redact_phi(value: &str) -> String {
+    if hash_phi_enabled() {
+        hash_value(value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Query parameters as they may be logged, with PatientID and PatientName redacted
+pub(crate) fn redact_parameters(parameters: &HashMap<String, String>) -> HashMap<String, String> {
+    parameters
+        .iter()
+        .map(|(tag, value)| match tag.as_str() {
+            PATIENT_ID | PATIENT_NAME if !value.is_empty() => (tag.clone(), redact_phi(value)),
+            _ => (tag.clone(), value.clone()),
+        })
+        .collect()
+}
+
+/// Stable, truncated SHA-256 so the same patient correlates across log lines
+fn hash_value(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let hex: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_from_identifier_hashes_patient_id() {
+        let ident = serde_json::json!({
+            "0020000D": {"vr": "UI", "Value": ["1.2.3.4"]},
+            "00100020": {"vr": "LO", "Value": ["PID-001"]},
+        });
+        let ctx = OperationContext::new("find", "PACS").with_identifier(&ident);
+
+        assert_eq!(ctx.study_uid, "1.2.3.4");
+        assert_eq!(ctx.remote_aet, "PACS");
+        assert!(ctx.patient_id.starts_with("sha256:"));
+        assert!(!ctx.patient_id.contains("PID-001"));
+        assert_eq!(ctx.patient_id, hash_value("PID-001"));
+    }
+
+    #[test]
+    fn test_redact_parameters_hashes_patient_keys_only() {
+        let params = HashMap::from([
+            (PATIENT_ID.to_string(), "PID-001".to_string()),
+            (PATIENT_NAME.to_string(), "DOE^JANE".to_string()),
+            (STUDY_INSTANCE_UID.to_string(), "1.2.3.4".to_string()),
+        ]);
+        let redacted = redact_parameters(&params);
+
+        assert_eq!(redacted[PATIENT_ID], hash_value("PID-001"));
+        assert_eq!(redacted[PATIENT_NAME], hash_value("DOE^JANE"));
+        assert_eq!(redacted[STUDY_INSTANCE_UID], "1.2.3.4");
+    }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_event_carries_study_uid() {
+        use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::mock_dicom::{MockDicomData, MockDicomEndpoint};
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let study_uid = MockDicomData::instance().study_uid.clone();
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .metadata_entry("dimse_op", "find")
+            .original_data(Vec::new())
+            .normalized_data(Some(serde_json::json!({
+                "dimse_identifier": {
+                    "0020000D": {"vr": "UI", "Value": [study_uid]},
+                    "00100020": {"vr": "LO", "Value": ["PID-001"]}
+                }
+            })))
+            .build()
+            .unwrap();
+        MockDicomEndpoint
+            .backend_outgoing_request(envelope, &HashMap::new())
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let event = output
+            .lines()
+            .find(|l| l.contains("operation=find"))
+            .expect("find event logged");
+        assert!(event.contains(&format!("study_uid={}", study_uid)), "{}", event);
+        assert!(!event.contains("PID-001"), "patient ID must be hashed: {}", event);
+    }
+
+    #[test]
+    fn test_json_format_emits_fields_and_spans() {
+        use tracing_subscriber::prelude::*;
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(fmt_layer(LogFormat::Json, move || writer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let ctx = OperationContext::new("find", "PACS").with_study_uid(Some("1.2.3"));
+            let _span = ctx.span().entered();
+            tracing::info!(matches = 2, "find complete");
+        });
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.lines().next().expect("one line"))
+            .expect("log line is JSON");
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["fields"]["message"], "find complete");
+        assert_eq!(line["fields"]["matches"], 2);
+        assert_eq!(line["span"]["name"], "dicom_operation");
+        assert_eq!(line["span"]["study_uid"], "1.2.3");
+        assert_eq!(line["spans"][0]["remote_aet"], "PACS");
+        assert!(line["filename"].as_str().unwrap().ends_with("log_context.rs"));
+    }
+
+    #[test]
+    fn test_return_keys_leave_fields_empty() {
+        let mut params = HashMap::new();
+        params.insert("0020000D".to_string(), String::new());
+        let ctx = OperationContext::new("find", "").with_parameters(&params);
+        assert!(ctx.study_uid.is_empty());
+        assert!(ctx.patient_id.is_empty());
+    }
+}
@@ -21,11 +21,42 @@ fn parse_cli_config_path() -> String {
     config_path.unwrap_or_else(|| "./config/config.toml".to_string())
 }
 
+/// Load and check the configuration, print a report, and exit without starting anything
+fn validate_and_exit(cli: Cli) -> ! {
+    let path = cli.config_path.clone();
+    let config = Config::load(cli);
+    let errors = config.check();
+
+    println!("Configuration: {}", path);
+    println!(
+        "  {} network(s), {} pipeline(s), {} endpoint(s), {} backend(s)",
+        config.network.len(),
+        config.pipelines.len(),
+        config.endpoints.len(),
+        config.backends.len()
+    );
+    if errors.is_empty() {
+        println!("OK");
+        std::process::exit(0);
+    }
+    for error in &errors {
+        println!("  error: {}", error);
+    }
+    println!("{} error(s)", errors.len());
+    std::process::exit(1);
+}
+
 #[tokio::main]
 async fn main() {
     // Parse --config/-c from CLI or fall back to ./config/config.toml
     let config_path = parse_cli_config_path();
     let cli = Cli::new(config_path);
+
+    // --validate checks the configuration and exits without opening any sockets
+    if env::args().skip(1).any(|arg| arg == "--validate") {
+        validate_and_exit(cli);
+    }
+
     let config = Config::from_args(cli);
 
     // Pass the Config into your application logic
@@ -0,0 +1,251 @@
+//! Prometheus metrics for the HTTP and DIMSE adapters
+//!
+//! With feature "metrics" a process-wide Prometheus recorder collects the HTTP request metrics
+//! below together with the `dimse_*` metrics recorded by the dimse crate (see
+//! [`dimse::metrics`]); the management endpoint serves them at `/{base_path}/metrics`. Without
+//! the feature nothing is recorded and [`render`] returns `None`.
+//!
+//! - `harmony_http_requests_total{method, endpoint, status}`
+//! - `harmony_http_request_duration_seconds{method, endpoint}`
+//! - `harmony_forward_queue_depth{endpoint}`: failed C-STORE forwards waiting for a retry
+//!
+//! The HTTP metrics can also carry `pipeline` and the request `path`. These are off unless the `[metrics]`
+//! config lists them, since every distinct path, UIDs included, would become its own series.
+
+use crate::config::metrics_config::MetricsConfig;
+use std::time::Instant;
+
+#[cfg(feature = "metrics")]
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+#[cfg(feature = "metrics")]
+use once_cell::sync::Lazy;
+
+/// HTTP requests handled, by response status
+pub const HTTP_REQUESTS: &str = "harmony_http_requests_total";
+/// Time to handle an HTTP request
+pub const HTTP_REQUEST_DURATION: &str = "harmony_http_request_duration_seconds";
+/// Instances in a DIMSE endpoint's forward retry queue
+pub const FORWARD_QUEUE_DEPTH: &str = "harmony_forward_queue_depth";
+
+/// Labels each metric recorded here can carry
+const METRIC_LABELS: &[(&str, &[&str])] = &[
+    (
+        HTTP_REQUESTS,
+        &["method", "endpoint", "status", "pipeline", "path"],
+    ),
+    (
+        HTTP_REQUEST_DURATION,
+        &["method", "endpoint", "pipeline", "path"],
+    ),
+    (FORWARD_QUEUE_DEPTH, &["endpoint"]),
+];
+
+/// HTTP labels recorded only when configured
+#[cfg(feature = "metrics")]
+const OPTIONAL_HTTP_LABELS: &[&str] = &["pipeline", "path"];
+
+/// Histogram buckets for every `*_duration_seconds` metric, from 5 ms to 5 minutes
+#[cfg(feature = "metrics")]
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0,
+];
+
+#[cfg(feature = "metrics")]
+static PROMETHEUS: Lazy<Option<PrometheusHandle>> = Lazy::new(|| {
+    let recorder = match PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("duration_seconds".into()), LATENCY_BUCKETS)
+    {
+        Ok(builder) => builder.build_recorder(),
+        Err(e) => {
+            tracing::warn!("Prometheus metrics disabled: {}", e);
+            return None;
+        }
+    };
+    let handle = recorder.handle();
+    match ::metrics::set_global_recorder(recorder) {
+        Ok(()) => Some(handle),
+        Err(e) => {
+            tracing::warn!("Prometheus metrics disabled: {}", e);
+            None
+        }
+    }
+});
+
+/// Install the Prometheus recorder; metrics recorded before this are lost
+///
+/// Safe to call more than once.
+pub fn install() {
+    #[cfg(feature = "metrics")]
+    Lazy::force(&PROMETHEUS);
+}
+
+/// Labels `metric` can carry, or `None` for a metric Harmony does not record
+pub fn available_labels(metric: &str) -> Option<&'static [&'static str]> {
+    METRIC_LABELS
+        .iter()
+        .chain(dimse::metrics::METRIC_LABELS)
+        .find(|(name, _)| *name == metric)
+        .map(|(_, labels)| *labels)
+}
+
+/// Apply the configured label sets to every metric, HTTP and DIMSE alike
+pub fn configure(config: &MetricsConfig) {
+    dimse::metrics::set_label_sets(config.labels.clone());
+}
+
+/// Current metrics in the Prometheus text exposition format
+pub fn render() -> Option<String> {
+    #[cfg(feature = "metrics")]
+    {
+        PROMETHEUS.as_ref().map(|handle| handle.render())
+    }
+    #[cfg(not(feature = "metrics"))]
+    {
+        None
+    }
+}
+
+/// One HTTP request handled by the HTTP adapter
+pub(crate) struct HttpRequest<'a> {
+    pub method: &'a str,
+    pub endpoint: &'a str,
+    pub pipeline: &'a str,
+    pub path: &'a str,
+    pub status: u16,
+}
+
+/// Record one HTTP request served through `request.endpoint`
+pub(crate) fn http_request(request: HttpRequest<'_>, started: Instant) {
+    #[cfg(feature = "metrics")]
+    {
+        use dimse::metrics::labels;
+
+        let values = || {
+            vec![
+                ("method", request.method.to_string()),
+                ("endpoint", request.endpoint.to_string()),
+                ("pipeline", request.pipeline.to_string()),
+                ("path", request.path.to_string()),
+            ]
+        };
+        let mut counted = values();
+        counted.push(("status", request.status.to_string()));
+        ::metrics::counter!(
+            HTTP_REQUESTS,
+            labels(HTTP_REQUESTS, counted, OPTIONAL_HTTP_LABELS)
+        )
+        .increment(1);
+        ::metrics::histogram!(
+            HTTP_REQUEST_DURATION,
+            labels(HTTP_REQUEST_DURATION, values(), OPTIONAL_HTTP_LABELS)
+        )
+        .record(started.elapsed().as_secs_f64());
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (request, started);
+}
+
+/// Record how many failed forwards of `endpoint` are waiting for a retry
+pub(crate) fn forward_queue_depth(endpoint: &str, depth: usize) {
+    #[cfg(feature = "metrics")]
+    ::metrics::gauge!(
+        FORWARD_QUEUE_DEPTH,
+        dimse::metrics::labels(
+            FORWARD_QUEUE_DEPTH,
+            vec![("endpoint", endpoint.to_string())],
+            &[]
+        )
+    )
+    .set(depth as f64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (endpoint, depth);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Exposition after recording one request into a recorder of its own
+    fn exposition_after_request() -> String {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        ::metrics::with_local_recorder(&recorder, || {
+            http_request(
+                HttpRequest {
+                    method: "GET",
+                    endpoint: "dicomweb",
+                    pipeline: "imaging",
+                    path: "/dicomweb/studies/1.2.826.0.1.3680043.10.5432.1",
+                    status: 200,
+                },
+                Instant::now(),
+            )
+        });
+        handle.render()
+    }
+
+    #[test]
+    fn test_label_sets_control_exposition() {
+        // By default the request path, a UID per study, is not a label
+        let output = exposition_after_request();
+        assert!(output.contains(r#"endpoint="dicomweb""#), "{}", output);
+        assert!(!output.contains("path="), "{}", output);
+        assert!(
+            !output.contains("1.2.826.0.1.3680043.10.5432.1"),
+            "{}",
+            output
+        );
+
+        // A configured set replaces the defaults, dropping labels it leaves out
+        configure(&MetricsConfig {
+            labels: HashMap::from([(
+                HTTP_REQUESTS.to_string(),
+                vec!["pipeline".to_string(), "status".to_string()],
+            )]),
+        });
+        let output = exposition_after_request();
+        configure(&MetricsConfig::default());
+        let requests = output
+            .lines()
+            .find(|line| line.starts_with(HTTP_REQUESTS))
+            .expect("request counter exported");
+        assert_eq!(
+            requests,
+            format!(r#"{}{{pipeline="imaging",status="200"}} 1"#, HTTP_REQUESTS)
+        );
+    }
+
+    #[test]
+    fn test_forward_queue_depth_is_a_gauge_per_endpoint() {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        ::metrics::with_local_recorder(&recorder, || {
+            forward_queue_depth("dimse_in", 3);
+            forward_queue_depth("dimse_in", 2);
+        });
+        let output = handle.render();
+        assert!(
+            output.contains(&format!(
+                r#"{}{{endpoint="dimse_in"}} 2"#,
+                FORWARD_QUEUE_DEPTH
+            )),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_available_labels_cover_http_and_dimse_metrics() {
+        assert!(available_labels(HTTP_REQUESTS).unwrap().contains(&"path"));
+        assert_eq!(
+            available_labels(FORWARD_QUEUE_DEPTH),
+            Some(&["endpoint"][..])
+        );
+        assert_eq!(
+            available_labels(dimse::metrics::FAILURES),
+            Some(&["operation", "status"][..])
+        );
+        assert_eq!(available_labels("unknown_total"), None);
+    }
+}
@@ -510,6 +510,63 @@ impl ResponseEnvelope<Vec<u8>> {
     }
 }
 
+/// Which of `original_data` and `normalized_data` an endpoint sends when they disagree.
+///
+/// Configured per endpoint with the `body_precedence` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyPrecedence {
+    /// `normalized_data` is sent once it no longer matches `original_data`, so updates made by
+    /// middleware or a backend are never hidden behind stale bytes
+    #[default]
+    Normalized,
+    /// `original_data` is sent whenever it is non-empty
+    Original,
+}
+
+impl BodyPrecedence {
+    /// Reads `body_precedence` from endpoint options, defaulting to `Normalized`
+    pub fn from_options(options: &HashMap<String, serde_json::Value>) -> Self {
+        match options.get("body_precedence").and_then(|v| v.as_str()) {
+            None | Some("normalized") => BodyPrecedence::Normalized,
+            Some("original") => BodyPrecedence::Original,
+            Some(other) => {
+                tracing::warn!(
+                    "Unknown body_precedence '{}'; expected 'normalized' or 'original'",
+                    other
+                );
+                BodyPrecedence::Normalized
+            }
+        }
+    }
+}
+
+impl ResponseEnvelope<Vec<u8>> {
+    /// Whether `normalized_data` has been changed since `original_data` was captured.
+    ///
+    /// Only JSON bodies can be compared; binary or non-JSON bodies are never considered stale.
+    pub fn normalized_diverged(&self) -> bool {
+        let Some(normalized) = &self.normalized_data else {
+            return false;
+        };
+        match serde_json::from_slice::<serde_json::Value>(&self.original_data) {
+            Ok(original) => &original != normalized,
+            Err(_) => false,
+        }
+    }
+
+    /// Consumes the envelope and returns the bytes to send to the client.
+    ///
+    /// An empty `original_data` always falls back to the serialized `normalized_data`.
+    pub fn into_body(self, precedence: BodyPrecedence) -> Result<Vec<u8>, serde_json::Error> {
+        let use_normalized = self.original_data.is_empty()
+            || (precedence == BodyPrecedence::Normalized && self.normalized_diverged());
+        match self.normalized_data {
+            Some(normalized) if use_normalized => serde_json::to_vec(&normalized),
+            _ => Ok(self.original_data),
+        }
+    }
+}
+
 impl ResponseEnvelope<serde_json::Value> {
     /// Converts back to byte-level envelope, serializing JSON if needed
     pub fn to_bytes(mut self) -> Result<ResponseEnvelope<Vec<u8>>, crate::utils::Error> {
@@ -676,6 +733,36 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn test_into_body_keeps_non_json_original() {
+        let mut envelope = ResponseEnvelope::from_backend(
+            create_test_envelope().request_details,
+            200,
+            HashMap::new(),
+            vec![0x44, 0x49, 0x43, 0x4d],
+            None,
+        );
+        envelope.normalized_data = Some(serde_json::json!({ "operation": "get" }));
+        assert!(!envelope.normalized_diverged());
+        assert_eq!(
+            envelope.into_body(BodyPrecedence::Normalized).unwrap(),
+            vec![0x44, 0x49, 0x43, 0x4d]
+        );
+
+        let mut envelope = ResponseEnvelope::from_backend(
+            create_test_envelope().request_details,
+            200,
+            HashMap::new(),
+            Vec::new(),
+            None,
+        );
+        envelope.normalized_data = Some(serde_json::json!({ "operation": "get" }));
+        assert_eq!(
+            envelope.into_body(BodyPrecedence::Original).unwrap(),
+            br#"{"operation":"get"}"#.to_vec()
+        );
+    }
+
     #[test]
     fn test_set_target_base_url_initializes() {
         let mut envelope = create_test_envelope();
@@ -94,12 +94,26 @@ fn create_builtin_middleware_type(
         "json_extractor" | "json" => Ok(Box::new(
             crate::models::middleware::types::json_extractor::JsonExtractorMiddleware::new(),
         )),
-        "jmix_builder" => Ok(Box::new(
-            crate::models::middleware::types::jmix_builder::JmixBuilderMiddleware::new(),
-        )),
-        "dicomweb_bridge" | "dicomweb" => Ok(Box::new(
-            crate::models::middleware::types::dicomweb_bridge::DicomwebBridgeMiddleware::new(),
-        )),
+        "jmix_builder" => {
+            let config = crate::models::middleware::types::jmix_builder::parse_config(options)?;
+            Ok(Box::new(
+                crate::models::middleware::types::jmix_builder::JmixBuilderMiddleware::with_config(
+                    config,
+                ),
+            ))
+        }
+        "dicomweb_bridge" | "dicomweb" => {
+            let config = crate::models::middleware::types::dicomweb_bridge::parse_config(options)?;
+            Ok(Box::new(
+                crate::models::middleware::types::dicomweb_bridge::DicomwebBridgeMiddleware::with_config(config),
+            ))
+        }
+        "study_cache" => {
+            let config = crate::models::middleware::types::study_cache::parse_config(options)?;
+            Ok(Box::new(
+                crate::models::middleware::types::study_cache::StudyCacheMiddleware::new(config),
+            ))
+        }
         "transform" => {
             let config = crate::models::middleware::types::transform::parse_config(options, transforms_path)?;
             Ok(Box::new(JoltTransformMiddleware::new(config)?))
@@ -108,6 +122,24 @@ fn create_builtin_middleware_type(
             let config = crate::models::middleware::types::path_filter::parse_config(options)?;
             Ok(Box::new(PathFilterMiddleware::new(config)?))
         }
+        "policy_authz" => {
+            let config = crate::models::middleware::types::policy_authz::parse_config(options)?;
+            Ok(Box::new(
+                crate::models::middleware::types::policy_authz::PolicyAuthzMiddleware::new(config),
+            ))
+        }
+        "cache" => {
+            let config = crate::models::middleware::types::cache::parse_config(options)?;
+            Ok(Box::new(
+                crate::models::middleware::types::cache::CacheMiddleware::new(config),
+            ))
+        }
+        "rate_limit" => {
+            let config = crate::models::middleware::types::rate_limit::parse_config(options)?;
+            Ok(Box::new(
+                crate::models::middleware::types::rate_limit::RateLimitMiddleware::new(config),
+            ))
+        }
         "metadata_transform" => {
             let config =
                 crate::models::middleware::types::metadata_transform::parse_config(options, transforms_path)?;
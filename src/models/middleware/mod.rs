@@ -6,7 +6,8 @@ pub mod middleware;
 pub mod types;
 
 // Re-export AuthFailure for easier access
-pub use types::auth_error::AuthFailure;
+pub use types::auth_error::{AccessDenied, AuthFailure};
+pub use types::rate_limit::RateLimited;
 
 use crate::models::middleware::config::*;
 use axum::response::Response;
@@ -6,3 +6,8 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 #[error("{0}")]
 pub struct AuthFailure(pub &'static str);
+
+/// Authorization failure: the request was understood but a policy refused it (HTTP 403)
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct AccessDenied(pub String);
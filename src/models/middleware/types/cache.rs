@@ -0,0 +1,468 @@
+use crate::models::envelope::envelope::{RequestDetails, RequestEnvelope, ResponseEnvelope};
+use crate::models::middleware::middleware::Middleware;
+use crate::utils::Error;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Cached responses keyed by scope and request, shared by every pipeline
+static ENTRIES: Lazy<Mutex<HashMap<String, CachedResponse>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Metadata key marking a request answered from the cache
+const CACHE_METADATA: &str = "response_cache";
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// How long a cached response is served
+    pub ttl: Duration,
+    /// Responses whose normalized body serializes larger than this are not cached
+    pub max_body_bytes: usize,
+    /// Entries kept per process before the oldest are evicted
+    pub max_entries: usize,
+    /// Pipelines sharing a scope share cached responses
+    pub scope: String,
+}
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    body: Value,
+    /// When the backend produced the body (RFC 3339), for the `X-Source-Retrieved-At` header
+    retrieved_at: String,
+    expires_at: Instant,
+}
+
+/// Serves repeated identical QIDO queries from an in-memory cache.
+///
+/// Only GET requests that `dicomweb_bridge` turned into a C-FIND (`dimse_op=find`) are cached.
+/// The cache key is the caller's subject, the path and the sorted query parameters, so one
+/// user's matches are never served to another. On the `right` pass a
+/// 200 response that was not served from the cache is stored with its normalized body; on the
+/// `left` pass a live entry sets `skip_backends=true` and `response_cache=hit`, restores the
+/// entry's `retrieved_at` and hands the cached body to the rest of the chain, which formats it
+/// as if the backend had answered.
+/// Requests with `Cache-Control: no-cache` skip the lookup and refresh the entry.
+///
+/// Must run after `jwtauth` and `dicomweb_bridge`, so the key carries the subject and a hit
+/// replaces the backend call the bridge prepared.
+pub struct CacheMiddleware {
+    config: CacheConfig,
+}
+
+impl CacheMiddleware {
+    pub fn new(config: CacheConfig) -> Self {
+        Self { config }
+    }
+
+    /// Cache key of a QIDO request; anything else is never cached
+    fn key(&self, details: &RequestDetails) -> Option<String> {
+        if !details.method.eq_ignore_ascii_case("GET")
+            || details.metadata.get("dimse_op").map(String::as_str) != Some("find")
+        {
+            return None;
+        }
+        let path = details.metadata.get("full_path").unwrap_or(&details.uri);
+        let path = path.split_once('?').map(|(p, _)| p).unwrap_or(path);
+
+        let mut params: Vec<(&String, &String)> = details
+            .query_params
+            .iter()
+            .flat_map(|(name, values)| values.iter().map(move |value| (name, value)))
+            .collect();
+        params.sort();
+        let query = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        Some(format!(
+            "{}|{}|GET {}?{}",
+            self.config.scope,
+            subject(details),
+            path,
+            query
+        ))
+    }
+}
+
+/// Who the request is answered for: the authenticated principal, else a digest of the
+/// credentials the request carries, else anonymous
+fn subject(details: &RequestDetails) -> String {
+    if let Some(principal) = details.metadata.get("principal") {
+        return format!("principal:{}", principal);
+    }
+    let credentials = details
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+        .map(|(_, value)| value);
+    match credentials {
+        Some(value) => {
+            let digest = Sha256::digest(value.as_bytes());
+            let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("credentials:{}", hex)
+        }
+        None => "anonymous".to_string(),
+    }
+}
+
+/// Whether the request asked to bypass cached responses
+fn no_cache(details: &RequestDetails) -> bool {
+    details
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("cache-control"))
+        .flat_map(|(_, value)| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-cache"))
+}
+
+#[async_trait]
+impl Middleware for CacheMiddleware {
+    async fn left(
+        &self,
+        mut envelope: RequestEnvelope<Value>,
+    ) -> Result<RequestEnvelope<Value>, Error> {
+        let Some(key) = self.key(&envelope.request_details) else {
+            return Ok(envelope);
+        };
+        if no_cache(&envelope.request_details) {
+            tracing::debug!(key = %key, "cache: bypassed by Cache-Control: no-cache");
+            return Ok(envelope);
+        }
+
+        let cached = {
+            let mut entries = ENTRIES.lock().expect("response cache poisoned");
+            match entries.get(&key) {
+                Some(entry) if entry.expires_at > Instant::now() => Some(entry.clone()),
+                Some(_) => {
+                    entries.remove(&key);
+                    None
+                }
+                None => None,
+            }
+        };
+        let Some(entry) = cached else {
+            return Ok(envelope);
+        };
+
+        tracing::debug!(key = %key, "cache: serving cached response");
+        let metadata = &mut envelope.request_details.metadata;
+        metadata.insert("skip_backends".to_string(), "true".to_string());
+        metadata.insert(CACHE_METADATA.to_string(), "hit".to_string());
+        metadata.insert("retrieved_at".to_string(), entry.retrieved_at);
+        envelope.normalized_data = Some(entry.body);
+        Ok(envelope)
+    }
+
+    async fn right(
+        &self,
+        envelope: ResponseEnvelope<Value>,
+    ) -> Result<ResponseEnvelope<Value>, Error> {
+        let details = &envelope.request_details;
+        if envelope.response_details.status != 200
+            || details.metadata.get(CACHE_METADATA).map(String::as_str) == Some("hit")
+        {
+            return Ok(envelope);
+        }
+        let (Some(key), Some(body)) = (self.key(details), envelope.normalized_data.as_ref()) else {
+            return Ok(envelope);
+        };
+
+        let size = serde_json::to_vec(body)
+            .map(|b| b.len())
+            .unwrap_or(usize::MAX);
+        if size > self.config.max_body_bytes {
+            tracing::debug!(key = %key, size, "cache: response too large to cache");
+            return Ok(envelope);
+        }
+
+        let now = Instant::now();
+        let mut entries = ENTRIES.lock().expect("response cache poisoned");
+        if entries.len() >= self.config.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires_at > now);
+            while entries.len() >= self.config.max_entries {
+                let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(key, _)| key.clone())
+                else {
+                    break;
+                };
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            CachedResponse {
+                body: body.clone(),
+                retrieved_at: details
+                    .metadata
+                    .get("retrieved_at")
+                    .cloned()
+                    .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                expires_at: now + self.config.ttl,
+            },
+        );
+
+        Ok(envelope)
+    }
+}
+
+pub fn parse_config(options: &HashMap<String, Value>) -> Result<CacheConfig, String> {
+    let ttl_secs = options
+        .get("ttl_secs")
+        .map(|v| {
+            v.as_u64()
+                .filter(|secs| *secs > 0)
+                .ok_or("'ttl_secs' in cache middleware config must be a positive integer")
+        })
+        .transpose()?
+        .unwrap_or(30);
+
+    let max_body_bytes = options
+        .get("max_body_bytes")
+        .map(|v| {
+            v.as_u64()
+                .filter(|bytes| *bytes > 0)
+                .ok_or("'max_body_bytes' in cache middleware config must be a positive integer")
+        })
+        .transpose()?
+        .unwrap_or(1024 * 1024) as usize;
+
+    let max_entries = options
+        .get("max_entries")
+        .map(|v| {
+            v.as_u64()
+                .filter(|entries| *entries > 0)
+                .ok_or("'max_entries' in cache middleware config must be a positive integer")
+        })
+        .transpose()?
+        .unwrap_or(1000) as usize;
+
+    let scope = options
+        .get("scope")
+        .and_then(|v| v.as_str())
+        .unwrap_or("default")
+        .to_string();
+
+    Ok(CacheConfig {
+        ttl: Duration::from_secs(ttl_secs),
+        max_body_bytes,
+        max_entries,
+        scope,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::envelope::envelope::{RequestEnvelopeBuilder, ResponseDetails};
+    use serde_json::json;
+
+    fn cache(scope: &str) -> CacheMiddleware {
+        CacheMiddleware::new(CacheConfig {
+            ttl: Duration::from_secs(60),
+            max_body_bytes: 1024,
+            max_entries: 10,
+            scope: scope.to_string(),
+        })
+    }
+
+    fn qido(patient_id: &str, cache_control: Option<&str>) -> RequestEnvelope<Value> {
+        let mut builder = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri(format!("/dicomweb/studies?PatientID={}", patient_id))
+            .query_param("PatientID", vec![patient_id.to_string()])
+            .metadata_entry(
+                "full_path",
+                format!("/dicomweb/studies?PatientID={}", patient_id),
+            )
+            .metadata_entry("dimse_op", "find");
+        if let Some(value) = cache_control {
+            builder = builder.header("Cache-Control", value);
+        }
+        builder.original_data(Value::Null).build().unwrap()
+    }
+
+    fn response(details: RequestDetails, status: u16, body: Value) -> ResponseEnvelope<Value> {
+        ResponseEnvelope {
+            request_details: details,
+            response_details: ResponseDetails {
+                status,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: body.clone(),
+            normalized_data: Some(body),
+            normalized_snapshot: None,
+        }
+    }
+
+    /// Run a request through the cache around a backend that counts its calls
+    async fn fetch(
+        mw: &CacheMiddleware,
+        request: RequestEnvelope<Value>,
+        backend_hits: &mut u32,
+    ) -> ResponseEnvelope<Value> {
+        let request = mw.left(request).await.unwrap();
+        let skipped =
+            request.request_details.metadata.get("skip_backends") == Some(&"true".to_string());
+        let body = if skipped {
+            request.normalized_data.clone().unwrap()
+        } else {
+            *backend_hits += 1;
+            json!({ "operation": "find", "success": true, "matches": [{ "call": *backend_hits }] })
+        };
+        mw.right(response(request.request_details, 200, body))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_identical_query_served_from_cache() {
+        let mw = cache("test_identical");
+        let mut backend_hits = 0;
+
+        let first = fetch(&mw, qido("P1", None), &mut backend_hits).await;
+        let second = fetch(&mw, qido("P1", None), &mut backend_hits).await;
+
+        assert_eq!(backend_hits, 1);
+        assert_eq!(second.normalized_data, first.normalized_data);
+        assert_eq!(
+            second.request_details.metadata.get(CACHE_METADATA),
+            Some(&"hit".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_different_query_and_no_cache_reach_backend() {
+        let mw = cache("test_different");
+        let mut backend_hits = 0;
+
+        fetch(&mw, qido("P1", None), &mut backend_hits).await;
+        fetch(&mw, qido("P2", None), &mut backend_hits).await;
+        assert_eq!(backend_hits, 2);
+
+        let refreshed = fetch(&mw, qido("P1", Some("no-cache")), &mut backend_hits).await;
+        assert_eq!(backend_hits, 3);
+        assert_eq!(refreshed.normalized_data.unwrap()["matches"][0]["call"], 3);
+
+        // The bypassing request refreshed the entry
+        let cached = fetch(&mw, qido("P1", None), &mut backend_hits).await;
+        assert_eq!(backend_hits, 3);
+        assert_eq!(cached.normalized_data.unwrap()["matches"][0]["call"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_only_small_ok_get_responses_are_cached() {
+        let mw = cache("test_uncacheable");
+        let key = mw.key(&qido("P1", None).request_details).unwrap();
+
+        let details = qido("P1", None).request_details;
+        let failed = response(details.clone(), 502, json!({ "error": "unavailable" }));
+        mw.right(failed).await.unwrap();
+        let large = response(details, 200, json!({ "matches": ["x".repeat(2048)] }));
+        mw.right(large).await.unwrap();
+        assert!(!ENTRIES.lock().unwrap().contains_key(&key));
+
+        let mut post = qido("P1", None);
+        post.request_details.method = "POST".to_string();
+        assert!(mw.key(&post.request_details).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_only_qido_requests_are_cached() {
+        let mw = cache("test_qido_only");
+        let mut backend_hits = 0;
+
+        // A WADO-RS retrieve is a GET too, but the bridge turned it into a C-GET
+        let retrieve = || {
+            let mut request = qido("P1", None);
+            request
+                .request_details
+                .metadata
+                .insert("dimse_op".to_string(), "get".to_string());
+            request
+        };
+        fetch(&mw, retrieve(), &mut backend_hits).await;
+        fetch(&mw, retrieve(), &mut backend_hits).await;
+        assert_eq!(backend_hits, 2);
+
+        let mut passthrough = qido("P1", None);
+        passthrough.request_details.metadata.remove("dimse_op");
+        assert!(mw.key(&passthrough.request_details).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cached_matches_not_shared_between_subjects() {
+        let mw = cache("test_subjects");
+        let mut backend_hits = 0;
+        let as_principal = |principal: &str| {
+            let mut request = qido("P1", None);
+            request
+                .request_details
+                .metadata
+                .insert("principal".to_string(), principal.to_string());
+            request
+        };
+        let with_token = |token: &str| {
+            let mut request = qido("P1", None);
+            request
+                .request_details
+                .headers
+                .insert("authorization".to_string(), format!("Bearer {}", token));
+            request
+        };
+
+        fetch(&mw, as_principal("alice"), &mut backend_hits).await;
+        fetch(&mw, as_principal("bob"), &mut backend_hits).await;
+        assert_eq!(backend_hits, 2);
+        let cached = fetch(&mw, as_principal("alice"), &mut backend_hits).await;
+        assert_eq!(backend_hits, 2);
+        assert_eq!(cached.normalized_data.unwrap()["matches"][0]["call"], 1);
+
+        // Without a principal, callers presenting different credentials are kept apart too
+        fetch(&mw, with_token("one"), &mut backend_hits).await;
+        fetch(&mw, with_token("two"), &mut backend_hits).await;
+        fetch(&mw, qido("P1", None), &mut backend_hits).await;
+        assert_eq!(backend_hits, 5);
+        fetch(&mw, with_token("one"), &mut backend_hits).await;
+        assert_eq!(backend_hits, 5);
+    }
+
+    #[test]
+    fn test_query_parameter_order_does_not_change_key() {
+        let mw = cache("test_order");
+        let mut a = qido("P1", None).request_details;
+        a.query_params.insert(
+            "ModalitiesInStudy".to_string(),
+            vec!["CT".to_string(), "MR".to_string()],
+        );
+        let mut b = a.clone();
+        b.query_params.insert(
+            "ModalitiesInStudy".to_string(),
+            vec!["MR".to_string(), "CT".to_string()],
+        );
+        assert_eq!(mw.key(&a), mw.key(&b));
+
+        b.query_params.remove("ModalitiesInStudy");
+        assert_ne!(mw.key(&a), mw.key(&b));
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(&HashMap::new()).unwrap();
+        assert_eq!(config.ttl, Duration::from_secs(30));
+        assert_eq!(config.max_body_bytes, 1024 * 1024);
+        assert_eq!(config.scope, "default");
+
+        let options: HashMap<String, Value> =
+            serde_json::from_value(json!({ "ttl_secs": 0 })).unwrap();
+        assert!(parse_config(&options).is_err());
+    }
+}
@@ -1,6 +1,7 @@
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{RequestEnvelope, RequestEnvelopeBuilder, ResponseEnvelope};
 use crate::models::middleware::middleware::Middleware;
-use crate::utils::Error;
+use crate::models::services::services::ServiceType;
+use crate::utils::{Error, IdGenerator};
 use base64::Engine;
 use dicom_core::dictionary::{DataDictionary, VirtualVr};
 use dicom_core::Tag;
@@ -11,7 +12,771 @@ use img::ImageEncoder;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Configuration for the DICOMweb bridge middleware
+#[derive(Debug, Clone)]
+pub struct DicomwebBridgeConfig {
+    /// Reject query parameters whose tag is not in the standard data dictionary
+    /// instead of guessing a VR of LO
+    pub strict_vr: bool,
+    /// Expand partial DA/TM match values (e.g. `2024`, `202403`, `10`) into DICOM ranges
+    pub expand_partial_dates: bool,
+    /// Hex tags permitted as QIDO match keys; `None` permits every tag
+    pub allowed_query_tags: Option<Vec<String>>,
+    /// Hex tags that may never be used as QIDO match keys
+    pub denied_query_tags: Vec<String>,
+    /// QIDO level ("study", "series" or "instance") → hex tags of which at least one must be
+    /// supplied as a match key; levels not listed are unrestricted
+    pub required_query_keys: HashMap<String, Vec<String>>,
+    /// Which attributes are passed through in QIDO and metadata responses
+    pub tag_inclusion: TagInclusion,
+    /// Backend used for an instance-level C-FIND before WADO instance/frame retrieval;
+    /// `None` skips the check and always attempts the retrieval
+    pub existence_check_backend: Option<String>,
+    /// Custom C-FIND routes, tried in order before the built-in QIDO/WADO routes
+    pub identifier_templates: Vec<IdentifierTemplate>,
+    /// Viewer the WADO metadata output is shaped for
+    pub viewer_profile: ViewerProfile,
+    /// Base URL (everything before `/studies`) for BulkDataURIs written by a viewer profile;
+    /// `None` reuses the path the request arrived on
+    pub bulkdata_base_url: Option<String>,
+    /// HTTP status returned for each DIMSE failure category, overriding the defaults
+    pub failure_status_codes: HashMap<DimseFailureCategory, u16>,
+    /// Drop empty segments and lowercase fixed route segments (`Studies/` → `studies`)
+    /// before routing
+    pub normalize_paths: bool,
+}
+
+/// Class of a failed C-FIND/C-MOVE/C-GET response, used to pick the DICOMweb HTTP status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DimseFailureCategory {
+    /// 0xA7xx: the remote node could not allocate resources for the operation
+    OutOfResources,
+    /// 0xA801: the C-MOVE destination AE is not known to the remote node
+    MoveDestinationUnknown,
+    /// 0xA900: the identifier does not match the SOP class
+    IdentifierMismatch,
+    /// 0x0122: the SOP class is not supported by the remote node
+    SopClassNotSupported,
+    /// 0xCxxx: the remote node was unable to process the request
+    UnableToProcess,
+}
+
+impl DimseFailureCategory {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "out_of_resources" => Ok(Self::OutOfResources),
+            "move_destination_unknown" => Ok(Self::MoveDestinationUnknown),
+            "identifier_mismatch" => Ok(Self::IdentifierMismatch),
+            "sop_class_not_supported" => Ok(Self::SopClassNotSupported),
+            "unable_to_process" => Ok(Self::UnableToProcess),
+            other => Err(format!(
+                "Invalid failure_status_codes category '{}'; expected 'out_of_resources', \
+                 'move_destination_unknown', 'identifier_mismatch', 'sop_class_not_supported' \
+                 or 'unable_to_process'",
+                other
+            )),
+        }
+    }
+
+    fn from_status(status: u16) -> Option<Self> {
+        match status {
+            0xA700..=0xA7FF => Some(Self::OutOfResources),
+            0xA801 => Some(Self::MoveDestinationUnknown),
+            0xA900 => Some(Self::IdentifierMismatch),
+            0x0122 => Some(Self::SopClassNotSupported),
+            0xC000..=0xCFFF => Some(Self::UnableToProcess),
+            _ => None,
+        }
+    }
+
+    /// Classify a failed backend response from its `status` field, falling back to a
+    /// status code or keyword in the error text
+    fn from_response(nd: &Value) -> Option<Self> {
+        let status = nd.get("status").and_then(|v| match v {
+            Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+            Value::String(s) => u16::from_str_radix(s.trim_start_matches("0x"), 16).ok(),
+            _ => None,
+        });
+        if let Some(category) = status.and_then(Self::from_status) {
+            return Some(category);
+        }
+
+        let error = nd
+            .get("error")
+            .and_then(|v| v.as_str())?
+            .to_ascii_lowercase();
+        let from_code = error.match_indices("0x").find_map(|(idx, _)| {
+            error
+                .get(idx + 2..idx + 6)
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                .and_then(Self::from_status)
+        });
+        from_code.or_else(|| {
+            (error.contains("out of resources") || error.contains("outofresources"))
+                .then_some(Self::OutOfResources)
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::OutOfResources => "out_of_resources",
+            Self::MoveDestinationUnknown => "move_destination_unknown",
+            Self::IdentifierMismatch => "identifier_mismatch",
+            Self::SopClassNotSupported => "sop_class_not_supported",
+            Self::UnableToProcess => "unable_to_process",
+        }
+    }
+
+    /// Status used when no override is configured
+    fn default_http_status(self) -> u16 {
+        match self {
+            Self::OutOfResources => 503,
+            Self::MoveDestinationUnknown => 502,
+            Self::IdentifierMismatch => 400,
+            Self::SopClassNotSupported => 501,
+            Self::UnableToProcess => 502,
+        }
+    }
+}
+
+/// Viewer-specific shaping of WADO metadata responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViewerProfile {
+    /// Metadata is passed through as filtered
+    #[default]
+    None,
+    /// OHIF/Cornerstone: image instances get a PixelData BulkDataURI pointing at their frames
+    /// and a NumberOfFrames value
+    Ohif,
+}
+
+impl ViewerProfile {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "none" => Ok(ViewerProfile::None),
+            "ohif" => Ok(ViewerProfile::Ohif),
+            other => Err(format!(
+                "Invalid viewer_profile '{}'; expected 'none' or 'ohif'",
+                other
+            )),
+        }
+    }
+}
+
+/// Declarative C-FIND mapping for a DICOMweb route the bridge does not know natively
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentifierTemplate {
+    /// Path segments relative to the endpoint prefix
+    pub path: Vec<TemplateSegment>,
+    /// Query parameter name → hex tag it is matched against
+    pub params: HashMap<String, String>,
+    /// Query level for default return keys: "study", "series" or "instance"
+    pub level: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSegment {
+    /// Must equal the request segment
+    Literal(String),
+    /// `{Keyword}` or `{gggg eeee}` segment whose value matches the hex tag
+    Tag(String),
+}
+
+impl IdentifierTemplate {
+    fn parse(value: &Value) -> Result<Self, String> {
+        let path = value
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or("Each identifier_templates entry needs a string 'path'")?;
+        let path = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(
+                |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => parse_template_tag(name).map(TemplateSegment::Tag),
+                    None => Ok(TemplateSegment::Literal(segment.to_string())),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+        if path.is_empty() {
+            return Err("identifier_templates 'path' must not be empty".to_string());
+        }
+
+        let params = match value.get("params") {
+            None => HashMap::new(),
+            Some(v) => v
+                .as_object()
+                .ok_or("identifier_templates 'params' must be a table of parameter = tag")?
+                .iter()
+                .map(|(param, tag)| {
+                    let tag = tag.as_str().ok_or_else(|| {
+                        format!(
+                            "identifier_templates param '{}' must map to a tag name",
+                            param
+                        )
+                    })?;
+                    Ok((param.clone(), parse_template_tag(tag)?))
+                })
+                .collect::<Result<HashMap<_, _>, String>>()?,
+        };
+
+        let level = value
+            .get("level")
+            .map(|v| {
+                v.as_str()
+                    .ok_or("identifier_templates 'level' must be a string")
+            })
+            .transpose()?
+            .unwrap_or("study");
+        if !matches!(level, "study" | "series" | "instance") {
+            return Err(format!(
+                "Invalid identifier_templates level '{}'; expected 'study', 'series' or 'instance'",
+                level
+            ));
+        }
+
+        Ok(Self {
+            path,
+            params,
+            level: level.to_string(),
+        })
+    }
+
+    /// Hex tag → value pairs captured from `parts`, or `None` when the path does not match
+    fn match_path(&self, parts: &[&str]) -> Option<Vec<(String, String)>> {
+        if parts.len() != self.path.len() {
+            return None;
+        }
+        let mut captures = Vec::new();
+        for (segment, part) in self.path.iter().zip(parts) {
+            match segment {
+                TemplateSegment::Literal(literal) if literal == part => {}
+                TemplateSegment::Literal(_) => return None,
+                TemplateSegment::Tag(tag) => captures.push((tag.clone(), (*part).to_string())),
+            }
+        }
+        Some(captures)
+    }
+}
+
+/// Resolve a template tag reference to hex, rejecting names the dictionary does not know
+fn parse_template_tag(name: &str) -> Result<String, String> {
+    let tag_hex = DicomwebBridgeMiddleware::dicom_name_to_hex(name.trim());
+    if tag_parts(&tag_hex).is_none() {
+        return Err(format!(
+            "Unknown DICOM attribute '{}' in identifier_templates",
+            name
+        ));
+    }
+    Ok(tag_hex)
+}
+
+/// PixelData as stored in an instance file
+enum PixelPayload {
+    /// Uncompressed pixel bytes, every frame back to back
+    Native(Vec<u8>),
+    /// Compressed fragments, in file order
+    Encapsulated(Vec<Vec<u8>>),
+}
+
+/// Attribute classes stripped from QIDO and metadata responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagInclusion {
+    /// Pass every attribute through (pixel and overlay data are always excluded)
+    #[default]
+    All,
+    /// Drop private attributes, including private creator elements
+    StripPrivate,
+    /// Drop retired attributes
+    StripRetired,
+}
+
+impl TagInclusion {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "all" => Ok(TagInclusion::All),
+            "strip_private" => Ok(TagInclusion::StripPrivate),
+            "strip_retired" => Ok(TagInclusion::StripRetired),
+            other => Err(format!(
+                "Invalid tag_inclusion '{}'; expected 'all', 'strip_private' or 'strip_retired'",
+                other
+            )),
+        }
+    }
+
+    fn excludes(self, tag_hex: &str) -> bool {
+        match self {
+            TagInclusion::All => false,
+            TagInclusion::StripPrivate => is_private_tag(tag_hex),
+            TagInclusion::StripRetired => is_retired_tag(tag_hex),
+        }
+    }
+}
+
+/// Retired attributes still commonly found in legacy data (PS3.6), with their dictionary
+/// keywords
+///
+/// dicom-dictionary-std marks retired attributes only as deprecated tag constants, so the list
+/// cannot be read from its entries at runtime; a test checks each tag and keyword against them.
+const RETIRED_TAGS: &[(&str, &str)] = &[
+    ("00080010", "RecognitionCode"),
+    ("00080040", "DataSetType"),
+    ("00080041", "DataSetSubtype"),
+    ("00081000", "NetworkID"),
+    ("00084000", "IdentifyingComments"),
+    ("00101000", "OtherPatientIDs"),
+    ("00180030", "Radionuclide"),
+    ("00200030", "ImagePosition"),
+    ("00200035", "ImageOrientation"),
+    ("00200050", "Location"),
+    ("00200070", "ImageGeometryType"),
+    ("00203401", "ModifyingDeviceID"),
+    ("00280005", "ImageDimensions"),
+    ("00280040", "ImageFormat"),
+    ("00280050", "ManipulatedImage"),
+    ("00280060", "CompressionCode"),
+    ("00280200", "ImageLocation"),
+    ("00321000", "ScheduledStudyStartDate"),
+    ("00321030", "ReasonForStudy"),
+];
+
+/// Fixed DICOMweb route segments; everything else (UIDs, frame lists) keeps its case
+const ROUTE_SEGMENTS: [&str; 10] = [
+    "studies",
+    "series",
+    "instances",
+    "metadata",
+    "frames",
+    "rendered",
+    "thumbnail",
+    "pixeldata",
+    "bulkdata",
+    "wado",
+];
+
+/// Drop empty segments (trailing or doubled slashes) and lowercase fixed route segments,
+/// keeping a leading slash if present
+pub(crate) fn normalize_dicomweb_path(path: &str) -> String {
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|segment| {
+            let lower = segment.to_ascii_lowercase();
+            if ROUTE_SEGMENTS.contains(&lower.as_str()) {
+                lower
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect();
+    let joined = segments.join("/");
+    if path.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Dictionary keyword for a hex tag, or the tag itself when unknown
+fn tag_keyword(tag_hex: &str) -> String {
+    tag_parts(tag_hex)
+        .and_then(|(group, element)| StandardDataDictionary.by_tag(Tag(group, element)))
+        .map(|entry| entry.alias.to_string())
+        .unwrap_or_else(|| tag_hex.to_string())
+}
+
+fn tag_parts(tag_hex: &str) -> Option<(u16, u16)> {
+    if tag_hex.len() != 8 {
+        return None;
+    }
+    let group = u16::from_str_radix(&tag_hex[0..4], 16).ok()?;
+    let element = u16::from_str_radix(&tag_hex[4..8], 16).ok()?;
+    Some((group, element))
+}
+
+/// Loose comparison key for one name word: lowercased, vowels (and h/w) dropped after the
+/// first letter and repeated letters collapsed, so `Smith`, `smyth` and `SMITHE` agree
+fn fuzzy_name_key(word: &str) -> String {
+    let mut key = String::new();
+    for (i, c) in word.chars().flat_map(char::to_lowercase).enumerate() {
+        if i > 0 && "aeiouyhw".contains(c) {
+            continue;
+        }
+        if !key.ends_with(c) {
+            key.push(c);
+        }
+    }
+    key
+}
+
+/// Whether every word of `query` is a fuzzy prefix of some word of the person name `name`
+///
+/// The first word is the family name, as in a PN value, and must match the name's first word.
+fn fuzzy_name_matches(query: &str, name: &str) -> bool {
+    let words = |s: &str| {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(fuzzy_name_key)
+            .collect::<Vec<_>>()
+    };
+    let name_words = words(name);
+    let query_words = words(query);
+    let family_matches = match (query_words.first(), name_words.first()) {
+        (Some(q), Some(n)) => n.starts_with(q.as_str()),
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    family_matches
+        && query_words
+            .iter()
+            .all(|q| name_words.iter().any(|n| n.starts_with(q.as_str())))
+}
+
+/// C-FIND match value narrowing a fuzzy person-name query: the family name's first letter
+/// followed by `*`, which [`fuzzy_name_key`] keeps as is
+///
+/// `None` when the values start with different letters or with a wildcard; the name is then
+/// requested as a plain return key.
+fn fuzzy_name_wildcard(values: &[String]) -> Option<String> {
+    let mut letters = values
+        .iter()
+        .map(|v| v.trim_start().chars().next().filter(|c| c.is_alphabetic()));
+    let first = letters.next()??;
+    if !letters.all(|c| c.is_some_and(|c| c.to_lowercase().eq(first.to_lowercase()))) {
+        return None;
+    }
+    Some(format!("{}*", first.to_uppercase()))
+}
+
+/// Transfer syntax named by the `transfer-syntax` parameter of an `application/dicom` media
+/// range in an Accept header; `None` when absent or `*` (as stored)
+fn requested_transfer_syntax(accept: &str) -> Option<String> {
+    accept
+        .split(',')
+        .filter(|range| range.to_ascii_lowercase().contains("application/dicom"))
+        .flat_map(|range| range.split(';').skip(1))
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("transfer-syntax"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        .filter(|uid| uid != "*" && !uid.is_empty())
+}
+
+/// Re-encode Part 10 files in the transfer syntax `ts_uid`; files already in it are kept
+/// byte for byte. Fails with a client-facing message when the transfer syntax is unknown or
+/// a file cannot be transcoded to it.
+fn transcode_parts(parts: Vec<Vec<u8>>, ts_uid: &str) -> Result<Vec<Vec<u8>>, String> {
+    use dicom_encoding::transfer_syntax::TransferSyntaxIndex;
+    use dicom_pixeldata::Transcode;
+
+    let ts = dicom_transfer_syntax_registry::TransferSyntaxRegistry
+        .get(ts_uid)
+        .ok_or_else(|| format!("Transfer syntax {} is not supported", ts_uid))?;
+    parts
+        .into_iter()
+        .map(|bytes| {
+            // Part 10 files carry a 128-byte preamble before the DICM magic
+            let start = if bytes.get(128..132) == Some(b"DICM") {
+                128
+            } else {
+                0
+            };
+            let mut obj = dicom_object::from_reader(&bytes[start..])
+                .map_err(|e| format!("Instance is not a readable DICOM file: {}", e))?;
+            if obj.meta().transfer_syntax().trim_end_matches('\0') == ts_uid {
+                return Ok(bytes);
+            }
+            obj.transcode(ts).map_err(|e| {
+                format!(
+                    "Instance {} cannot be transcoded to {}: {}",
+                    obj.meta().media_storage_sop_instance_uid(),
+                    ts_uid,
+                    e
+                )
+            })?;
+            let mut out = Vec::new();
+            obj.write_all(&mut out)
+                .map_err(|e| format!("Failed to encode transcoded instance: {}", e))?;
+            Ok(out)
+        })
+        .collect()
+}
+
+/// Frame numbers from a `frames/{list}` segment, e.g. `1,3,5`
+fn parse_frame_numbers(list: &str) -> Vec<usize> {
+    list.split(',')
+        .filter_map(|s| s.parse::<usize>().ok())
+        .collect()
+}
+
+/// JPEG quality for rendered frames when the request does not set one
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// Box a thumbnail is scaled to fit when the request has no `viewport`
+const DEFAULT_THUMBNAIL_SIZE: (u32, u32) = (128, 128);
+
+/// Presentation controls of a WADO-RS rendered request (`viewport`, `quality`, `window`)
+#[derive(Debug, Clone, PartialEq)]
+struct RenderOptions {
+    viewport: Option<(u32, u32)>,
+    quality: u8,
+    /// Window center and width
+    window: Option<(f64, f64)>,
+}
+
+impl RenderOptions {
+    fn from_query(qp: &HashMap<String, Vec<String>>) -> Result<Self, String> {
+        let param = |name: &str| qp.get(name).and_then(|v| v.first()).map(|v| v.trim());
+
+        // viewport=vw,vh[,sx,sy,sw,sh]; the source region is not supported and ignored
+        let viewport = match param("viewport") {
+            None => None,
+            Some(v) => {
+                let dims: Vec<Option<u32>> = v.split(',').take(2).map(|d| d.parse().ok()).collect();
+                match dims.as_slice() {
+                    [Some(w), Some(h)] if *w > 0 && *h > 0 => Some((*w, *h)),
+                    _ => return Err(format!("Invalid viewport '{}'; expected vw,vh", v)),
+                }
+            }
+        };
+        let quality = match param("quality") {
+            None => DEFAULT_JPEG_QUALITY,
+            Some(q) => q
+                .parse::<u8>()
+                .ok()
+                .filter(|q| (1..=100).contains(q))
+                .ok_or_else(|| format!("Invalid quality '{}'; expected 1-100", q))?,
+        };
+        // window=center,width[,function]; only the linear function is supported
+        let window = match param("window") {
+            None => None,
+            Some(w) => {
+                let fields: Vec<&str> = w.split(',').map(str::trim).collect();
+                let center = fields.first().and_then(|c| c.parse::<f64>().ok());
+                let width = fields.get(1).and_then(|c| c.parse::<f64>().ok());
+                let linear = fields
+                    .get(2)
+                    .is_none_or(|f| f.eq_ignore_ascii_case("linear"));
+                match (center, width) {
+                    (Some(center), Some(width)) if width > 0.0 && linear => Some((center, width)),
+                    _ => {
+                        return Err(format!(
+                            "Invalid window '{}'; expected center,width[,linear]",
+                            w
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(Self {
+            viewport,
+            quality,
+            window,
+        })
+    }
+
+    /// Decode one frame to 8 bits with the requested window, scaled to fit the viewport
+    fn render(
+        &self,
+        pixel_data: &dicom_pixeldata::DecodedPixelData<'_>,
+        frame: u32,
+    ) -> Result<img::DynamicImage, dicom_pixeldata::Error> {
+        let mut convert = dicom_pixeldata::ConvertOptions::new().force_8bit();
+        if let Some((center, width)) = self.window {
+            convert = convert.with_voi_lut(dicom_pixeldata::VoiLutOption::Custom(
+                dicom_pixeldata::WindowLevel { center, width },
+            ));
+        }
+        let image = pixel_data.to_dynamic_image_with_options(frame, &convert)?;
+        Ok(match self.viewport {
+            Some((width, height)) => {
+                image.resize(width, height, img::imageops::FilterType::Triangle)
+            }
+            None => image,
+        })
+    }
+}
+
+/// A WADO-URI retrieval: `wado?requestType=WADO&studyUID=...&seriesUID=...&objectUID=...`
+#[derive(Debug, Clone, PartialEq)]
+struct WadoUriRequest {
+    study_uid: String,
+    series_uid: String,
+    object_uid: String,
+    /// `application/dicom`, or the image type to render to
+    content_type: &'static str,
+    /// `transferSyntax` the object is re-encoded to, for `application/dicom`
+    transfer_syntax: Option<String>,
+    /// 1-based `frameNumber` rendered as the image
+    frame: u32,
+    render: RenderOptions,
+}
+
+impl WadoUriRequest {
+    fn from_query(qp: &HashMap<String, Vec<String>>) -> Result<Self, String> {
+        let param = |name: &str| {
+            qp.get(name)
+                .and_then(|v| v.first())
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
+        let required = |name: &str| {
+            param(name)
+                .map(str::to_string)
+                .ok_or_else(|| format!("Missing required WADO-URI parameter '{}'", name))
+        };
+        let number = |name: &str, min: u32| -> Result<Option<u32>, String> {
+            param(name)
+                .map(|v| {
+                    v.parse::<u32>()
+                        .ok()
+                        .filter(|n| *n >= min)
+                        .ok_or_else(|| format!("Invalid {} '{}'", name, v))
+                })
+                .transpose()
+        };
+
+        let request_type = required("requestType")?;
+        if request_type != "WADO" {
+            return Err(format!(
+                "Unsupported requestType '{}'; expected WADO",
+                request_type
+            ));
+        }
+        let study_uid = required("studyUID")?;
+        let series_uid = required("seriesUID")?;
+        let object_uid = required("objectUID")?;
+
+        // contentType lists acceptable types in order of preference; images are the default
+        let content_type = match param("contentType") {
+            None => "image/jpeg",
+            Some(list) => list
+                .split(',')
+                .map(|t| t.split(';').next().unwrap_or(t).trim())
+                .find_map(|t| {
+                    ["application/dicom", "image/jpeg", "image/png"]
+                        .into_iter()
+                        .find(|supported| t.eq_ignore_ascii_case(supported))
+                })
+                .ok_or_else(|| format!("Unsupported contentType '{}'", list))?,
+        };
+
+        // rows/columns bound the rendered image; a missing one leaves that side unbounded
+        let (rows, columns) = (number("rows", 1)?, number("columns", 1)?);
+        let viewport = (rows.is_some() || columns.is_some())
+            .then(|| (columns.unwrap_or(u32::MAX), rows.unwrap_or(u32::MAX)));
+        let quality = match number("imageQuality", 1)? {
+            None => DEFAULT_JPEG_QUALITY,
+            Some(q) => u8::try_from(q)
+                .ok()
+                .filter(|q| *q <= 100)
+                .ok_or_else(|| format!("Invalid imageQuality '{}'; expected 1-100", q))?,
+        };
+        let window = match (param("windowCenter"), param("windowWidth")) {
+            (None, None) => None,
+            (Some(center), Some(width)) => match (center.parse::<f64>(), width.parse::<f64>()) {
+                (Ok(center), Ok(width)) if width > 0.0 => Some((center, width)),
+                _ => return Err(format!("Invalid window {},{}", center, width)),
+            },
+            _ => return Err("windowCenter and windowWidth must be given together".to_string()),
+        };
+
+        Ok(Self {
+            study_uid,
+            series_uid,
+            object_uid,
+            content_type,
+            transfer_syntax: param("transferSyntax").map(str::to_string),
+            frame: number("frameNumber", 1)?.unwrap_or(1),
+            render: RenderOptions {
+                viewport,
+                quality,
+                window,
+            },
+        })
+    }
+}
+
+/// Tags named by the last segment of a bulk data URI, e.g. `00420011` or `00420011,7FE00010`
+fn parse_bulkdata_tags(segment: &str) -> Result<Vec<Tag>, String> {
+    let tags = segment
+        .split(',')
+        .map(|t| {
+            tag_parts(t.trim())
+                .map(|(group, element)| Tag(group, element))
+                .ok_or_else(|| format!("Invalid tag '{}' in bulk data URI", t))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if tags.len() > 1 && tags.contains(&dicom_dictionary_std::tags::PIXEL_DATA) {
+        return Err("PixelData cannot be combined with other bulk data attributes".to_string());
+    }
+    Ok(tags)
+}
+
+/// Private attributes live in odd groups above 0008
+fn is_private_tag(tag_hex: &str) -> bool {
+    matches!(tag_parts(tag_hex), Some((group, _)) if group % 2 == 1 && group > 0x0008)
+}
+
+/// Retired attributes: group lengths outside the command/meta groups, curve data (50xx),
+/// the Results (4008) and escape/zonal map (1000, 1010) groups, and the `RETIRED_TAGS` table
+fn is_retired_tag(tag_hex: &str) -> bool {
+    let Some((group, element)) = tag_parts(tag_hex) else {
+        return false;
+    };
+    if group % 2 == 1 {
+        return false;
+    }
+    (element == 0x0000 && group > 0x0002)
+        || (0x5000..=0x50FF).contains(&group)
+        || group == 0x4008
+        || group == 0x1000
+        || group == 0x1010
+        || RETIRED_TAGS
+            .iter()
+            .any(|(t, _)| t.eq_ignore_ascii_case(tag_hex))
+}
+
+impl Default for DicomwebBridgeConfig {
+    fn default() -> Self {
+        Self {
+            strict_vr: false,
+            expand_partial_dates: true,
+            allowed_query_tags: None,
+            denied_query_tags: Vec::new(),
+            required_query_keys: HashMap::new(),
+            tag_inclusion: TagInclusion::All,
+            existence_check_backend: None,
+            identifier_templates: Vec::new(),
+            viewer_profile: ViewerProfile::None,
+            bulkdata_base_url: None,
+            failure_status_codes: HashMap::new(),
+            normalize_paths: true,
+        }
+    }
+}
+
+impl DicomwebBridgeConfig {
+    /// HTTP status for a DIMSE failure category, honouring configured overrides
+    pub fn failure_http_status(&self, category: DimseFailureCategory) -> u16 {
+        self.failure_status_codes
+            .get(&category)
+            .copied()
+            .unwrap_or_else(|| category.default_http_status())
+    }
+
+    /// Whether a hex tag may be used as a QIDO match key
+    fn permits_query_tag(&self, tag_hex: &str) -> bool {
+        if self.denied_query_tags.iter().any(|t| t == tag_hex) {
+            return false;
+        }
+        match &self.allowed_query_tags {
+            Some(allowed) => allowed.iter().any(|t| t == tag_hex),
+            None => true,
+        }
+    }
+}
 
 /// Bridge middleware that maps DICOMweb HTTP requests (QIDO/WADO) into DIMSE operations
 /// and converts DICOM responses back to DICOMweb format.
@@ -19,11 +784,32 @@ use std::path::PathBuf;
 /// LEFT side: Converts DICOMweb requests to DIMSE operations
 /// RIGHT side: Converts DICOM backend responses to DICOMweb JSON/binary
 #[derive(Default, Debug)]
-pub struct DicomwebBridgeMiddleware;
+pub struct DicomwebBridgeMiddleware {
+    config: DicomwebBridgeConfig,
+    /// Multipart boundary ids
+    ids: IdGenerator,
+}
 
 impl DicomwebBridgeMiddleware {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    pub fn with_config(config: DicomwebBridgeConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Replace the generator used for multipart boundaries (e.g. a fixed sequence in tests)
+    pub fn with_id_generator(mut self, ids: IdGenerator) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    fn next_boundary(&self) -> String {
+        format!("dicomweb_{}", self.ids.next_id())
     }
 
     // --- LEFT SIDE HELPERS (DICOMweb → DICOM) ---
@@ -90,48 +876,97 @@ impl DicomwebBridgeMiddleware {
     /// # Arguments
     /// * `matches` - The backend response data (array or single object)
     /// * `includefield` - Optional list of fields to include (for filtering)
+    /// * `inclusion` - Private/retired attribute policy
+    /// * `limit`, `offset` - The page of matches to return; DIMSE C-FIND cannot page, so
+    ///   this is applied to the full match set
     ///
     /// # Returns
-    /// A JSON array containing the QIDO results
-    fn build_qido_json_from_matches(matches: &Value, includefield: Option<&Vec<String>>) -> Value {
+    /// A JSON array containing the requested page of QIDO results, and whether further
+    /// matches exist beyond it
+    fn build_qido_json_from_matches(
+        matches: &Value,
+        includefield: Option<&Vec<String>>,
+        inclusion: TagInclusion,
+        limit: usize,
+        offset: usize,
+    ) -> (Value, bool) {
+        let items: Vec<&Value> = match matches {
+            Value::Array(arr) => arr.iter().collect(),
+            // Single object case - wrap in array per DICOMweb spec
+            other => vec![other],
+        };
+        let total_results = items.len();
+        let start = offset.min(total_results);
+        let end = start.saturating_add(limit).min(total_results);
+        tracing::debug!(
+            total_results = total_results,
+            limit = limit,
+            offset = offset,
+            "Applying pagination to QIDO-RS results"
+        );
+
         // Convert identifier JSON objects to full DICOMweb JSON objects
         // Filter attributes based on includefield parameter if provided
-        match matches {
-            Value::Array(arr) => {
-                let mut dicomweb_objects = Vec::new();
-                for item in arr {
-                    let processed_item = Self::process_item(includefield, item);
-                    dicomweb_objects.push(processed_item);
-                }
-                Value::Array(dicomweb_objects)
-            }
-            other => {
-                // Single object case - wrap in array per DICOMweb spec
-                let processed_item = Self::process_item(includefield, other);
-                Value::Array(vec![processed_item])
-            }
-        }
+        let dicomweb_objects = items[start..end]
+            .iter()
+            .map(|item| Self::process_item(includefield, item, inclusion))
+            .collect();
+        (Value::Array(dicomweb_objects), end < total_results)
+    }
+
+    /// Whether a C-FIND match satisfies every fuzzy person-name criterion
+    ///
+    /// Names may be plain strings or DICOM JSON PN objects; any component group counts.
+    fn matches_fuzzy_names(item: &Value, names: &serde_json::Map<String, Value>) -> bool {
+        names.iter().all(|(tag, queries)| {
+            let values: Vec<&str> = item
+                .get(tag)
+                .and_then(|e| e.get("Value"))
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .flat_map(|v| match v {
+                    Value::String(s) => vec![s.as_str()],
+                    Value::Object(groups) => groups.values().filter_map(|g| g.as_str()).collect(),
+                    _ => vec![],
+                })
+                .collect();
+            queries
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|q| q.as_str())
+                .any(|q| values.iter().any(|name| fuzzy_name_matches(q, name)))
+        })
     }
 
-    fn process_item(includefield: Option<&Vec<String>>, item: &Value) -> Value {
-        
+    fn process_item(
+        includefield: Option<&Vec<String>>,
+        item: &Value,
+        inclusion: TagInclusion,
+    ) -> Value {
         match includefield {
             Some(_fields) => {
                 // If filtering is requested, work with the identifier as-is instead of
                 // converting to full JSON first, to avoid including unwanted attributes
-                Self::filter_dicom_json(item, includefield)
+                Self::filter_dicom_json(item, includefield, inclusion)
             }
             None => {
                 // No filtering requested, convert to full DICOMweb JSON
-                if let Ok(dicom_obj) = dicom_json_tool::json_value_to_identifier(item) {
-                    if let Ok(full_json) = dicom_json_tool::identifier_to_json_value(&dicom_obj) {
-                        full_json
+                let mut full_json =
+                    if let Ok(dicom_obj) = dicom_json_tool::json_value_to_identifier(item) {
+                        if let Ok(full_json) =
+                            dicom_json_tool::identifier_to_json_value(&dicom_obj)
+                        {
+                            full_json
+                        } else {
+                            item.clone()
+                        }
                     } else {
                         item.clone()
-                    }
-                } else {
-                    item.clone()
-                }
+                    };
+                Self::apply_tag_inclusion(&mut full_json, inclusion);
+                full_json
             }
         }
     }
@@ -139,7 +974,12 @@ impl DicomwebBridgeMiddleware {
     /// Filter DICOM JSON attributes based on includefield parameter
     /// If includefield is None, return all attributes (except pixel data)
     /// If includefield is Some, return only the specified attributes
-    fn filter_dicom_json(json: &Value, includefield: Option<&Vec<String>>) -> Value {
+    /// Private or retired attributes are then dropped according to `inclusion`
+    fn filter_dicom_json(
+        json: &Value,
+        includefield: Option<&Vec<String>>,
+        inclusion: TagInclusion,
+    ) -> Value {
         match json {
             Value::Object(obj) => {
                 let mut filtered = serde_json::Map::new();
@@ -163,12 +1003,105 @@ impl DicomwebBridgeMiddleware {
                     }
                 }
 
-                Value::Object(filtered)
+                let mut filtered = Value::Object(filtered);
+                Self::apply_tag_inclusion(&mut filtered, inclusion);
+                filtered
             }
             _ => json.clone(),
         }
     }
 
+    /// Remove attributes excluded by `inclusion`, descending into sequence items
+    fn apply_tag_inclusion(json: &mut Value, inclusion: TagInclusion) {
+        if inclusion == TagInclusion::All {
+            return;
+        }
+        if let Value::Object(obj) = json {
+            obj.retain(|key, _| !inclusion.excludes(key));
+            for entry in obj.values_mut() {
+                if entry.get("vr").and_then(|v| v.as_str()) != Some("SQ") {
+                    continue;
+                }
+                if let Some(items) = entry.get_mut("Value").and_then(|v| v.as_array_mut()) {
+                    for item in items {
+                        Self::apply_tag_inclusion(item, inclusion);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shape WADO metadata for OHIF/Cornerstone
+    ///
+    /// Image instances (those with Rows) get a PixelData element whose BulkDataURI names every
+    /// frame on this server's frames route, and NumberOfFrames defaults to 1. Relative
+    /// BulkDataURIs already present are resolved against `base_url`.
+    fn apply_ohif_profile(json: &mut Value, base_url: &str) {
+        let items: Vec<&mut Value> = match json {
+            Value::Array(arr) => arr.iter_mut().collect(),
+            other => vec![other],
+        };
+        for item in items {
+            let Some(obj) = item.as_object_mut() else {
+                continue;
+            };
+
+            for entry in obj.values_mut() {
+                if let Some(Value::String(uri)) = entry.get_mut("BulkDataURI") {
+                    if !uri.starts_with('/') && !uri.contains("://") {
+                        *uri = format!("{}/{}", base_url, uri);
+                    }
+                }
+            }
+
+            if !obj.contains_key("00280010") {
+                continue;
+            }
+            let first = |tag: &str| {
+                obj.get(tag)
+                    .and_then(|e| e.get("Value"))
+                    .and_then(|v| v.get(0))
+                    .cloned()
+            };
+            let uid = |tag: &str| first(tag).and_then(|v| v.as_str().map(str::to_string));
+            let (Some(study), Some(series), Some(sop)) =
+                (uid("0020000D"), uid("0020000E"), uid("00080018"))
+            else {
+                continue;
+            };
+            // IS/US values may arrive as JSON numbers or strings
+            let number = |tag: &str| {
+                first(tag).and_then(|v| {
+                    v.as_u64()
+                        .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+                })
+            };
+            let frames = number("00280008").unwrap_or(1).max(1);
+            let vr = if number("00280100").is_some_and(|bits| bits <= 8) {
+                "OB"
+            } else {
+                "OW"
+            };
+
+            let frame_list = (1..=frames)
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            obj.entry("00280008")
+                .or_insert_with(|| json!({ "vr": "IS", "Value": [1] }));
+            obj.insert(
+                "7FE00010".to_string(),
+                json!({
+                    "vr": vr,
+                    "BulkDataURI": format!(
+                        "{}/studies/{}/series/{}/instances/{}/frames/{}",
+                        base_url, study, series, sop, frame_list
+                    ),
+                }),
+            );
+        }
+    }
+
     /// Helper function to determine if a DICOM tag should be excluded from JSON responses
     /// This excludes pixel data and other large binary attributes by default
     fn should_exclude_dicom_tag(tag_str: &str) -> bool {
@@ -190,7 +1123,7 @@ impl DicomwebBridgeMiddleware {
 
     /// Convert DICOM tag name or hex string to hex format using dicom-rs StandardDataDictionary
     /// Examples: "PatientName" -> "00100010", "StudyDate" -> "00080020"
-    fn dicom_name_to_hex(name_or_hex: &str) -> String {
+    pub(crate) fn dicom_name_to_hex(name_or_hex: &str) -> String {
         // If it's already in hex format (8 characters), return as-is
         if name_or_hex.len() == 8 && name_or_hex.chars().all(|c| c.is_ascii_hexdigit()) {
             return name_or_hex.to_uppercase();
@@ -206,27 +1139,140 @@ impl DicomwebBridgeMiddleware {
         name_or_hex.to_uppercase()
     }
 
-    /// Infer VR (Value Representation) for a DICOM tag using dicom-rs StandardDataDictionary
-    fn infer_vr_for_tag(tag_hex: &str) -> String {
+    /// Look up the VR (Value Representation) for a DICOM tag using dicom-rs StandardDataDictionary
+    ///
+    /// Returns the plain two-letter VR code ("LO", "UI", ...) as used in DICOM JSON, or `None`
+    /// when the tag is not in the dictionary.
+    fn lookup_vr_for_tag(tag_hex: &str) -> Option<String> {
+        if tag_hex.len() != 8 || !tag_hex.is_ascii() {
+            return None;
+        }
         // Parse hex tag back to Tag struct
-        if let (Ok(group), Ok(element)) = (
-            u16::from_str_radix(&tag_hex[0..4], 16),
-            u16::from_str_radix(&tag_hex[4..8], 16),
-        ) {
-            let tag = Tag(group, element);
-            // Use dicom-rs StandardDataDictionary to get VR
-            if let Some(entry) = StandardDataDictionary.by_tag(tag) {
-                // Convert VirtualVr to string and format consistently
-                let vr_str = match entry.vr {
-                    VirtualVr::Exact(vr) => format!("{:?}", vr),
-                    _ => "LO".to_string(),
-                };
-                return format!("Exact({})", vr_str);
+        let group = u16::from_str_radix(&tag_hex[0..4], 16).ok()?;
+        let element = u16::from_str_radix(&tag_hex[4..8], 16).ok()?;
+        let entry = StandardDataDictionary.by_tag(Tag(group, element))?;
+        match entry.vr {
+            VirtualVr::Exact(vr) => Some(format!("{:?}", vr)),
+            // Context-dependent VRs (e.g. US/SS) have no single answer; LO is a safe match key
+            _ => Some("LO".to_string()),
+        }
+    }
+
+    /// Infer VR for a DICOM tag, falling back to LO for tags missing from the dictionary
+    fn infer_vr_for_tag(tag_hex: &str) -> String {
+        Self::lookup_vr_for_tag(tag_hex).unwrap_or_else(|| "LO".to_string())
+    }
+
+    /// Expand a partial DA/TM match value into a DICOM range
+    ///
+    /// `2024` becomes `20240101-20241231`, `202403` becomes `20240301-20240331` and a time of
+    /// `10` becomes `100000-105959`. Single-sided (`2024-`, `-202403`) and explicit ranges are
+    /// widened on each side. Values that are already complete, or not recognisably partial,
+    /// are returned unchanged.
+    fn expand_partial_date_value(value: &str, vr: &str) -> String {
+        let value = value.trim();
+        match value.split_once('-') {
+            None => match Self::partial_bounds(value, vr) {
+                Some((lower, upper)) => format!("{}-{}", lower, upper),
+                None => value.to_string(),
+            },
+            Some((from, to)) => {
+                let lower = Self::partial_bounds(from, vr)
+                    .map(|(lower, _)| lower)
+                    .unwrap_or_else(|| from.to_string());
+                let upper = Self::partial_bounds(to, vr)
+                    .map(|(_, upper)| upper)
+                    .unwrap_or_else(|| to.to_string());
+                format!("{}-{}", lower, upper)
             }
         }
+    }
+
+    /// Lower and upper bounds covered by a partial date or time, if it is one
+    fn partial_bounds(part: &str, vr: &str) -> Option<(String, String)> {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        match (vr, part.len()) {
+            ("DA", 4) => Some((format!("{}0101", part), format!("{}1231", part))),
+            ("DA", 6) => {
+                let year: u32 = part[0..4].parse().ok()?;
+                let month: u32 = part[4..6].parse().ok()?;
+                let last_day = Self::days_in_month(year, month)?;
+                Some((format!("{}01", part), format!("{}{:02}", part, last_day)))
+            }
+            ("TM", 2) => Some((format!("{}0000", part), format!("{}5959", part))),
+            ("TM", 4) => Some((format!("{}00", part), format!("{}59", part))),
+            _ => None,
+        }
+    }
+
+    fn days_in_month(year: u32, month: u32) -> Option<u32> {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => Some(31),
+            4 | 6 | 9 | 11 => Some(30),
+            2 if leap => Some(29),
+            2 => Some(28),
+            _ => None,
+        }
+    }
+
+    /// Build a short-circuit 400 response for a DICOMweb request the bridge cannot map
+    fn reject_request(envelope: &mut RequestEnvelope<Value>, error: &str, message: String) {
+        envelope
+            .request_details
+            .metadata
+            .insert("skip_backends".to_string(), "true".to_string());
+        envelope.normalized_data = Some(json!({
+            "dicomweb_response_type": "bad_request",
+            "dicomweb_data": Value::Null,
+            "dicomweb_metadata": { "error": error, "message": message },
+        }));
+    }
+
+    /// Build a short-circuit 404 response for an instance the backend does not hold
+    fn reject_missing_instance(envelope: &mut RequestEnvelope<Value>, instance_uid: &str) {
+        envelope
+            .request_details
+            .metadata
+            .insert("skip_backends".to_string(), "true".to_string());
+        envelope.normalized_data = Some(json!({
+            "dicomweb_response_type": "not_found",
+            "dicomweb_data": Value::Null,
+            "dicomweb_metadata": {
+                "error": "NotFound",
+                "message": format!("Instance {} not found", instance_uid),
+            },
+        }));
+    }
 
-        // Fallback to Exact(LO) for unknown tags
-        "Exact(LO)".to_string()
+    /// Resolve the configured existence-check backend and ask it whether the instance exists.
+    /// Returns `None` when no check is configured or the check itself could not run.
+    async fn check_instance(&self, ident: &serde_json::Map<String, Value>) -> Option<bool> {
+        let backend_name = self.config.existence_check_backend.as_ref()?;
+        let config = crate::globals::get_config()?;
+        let Some(backend) = config.backends.get(backend_name) else {
+            tracing::warn!("Existence check backend '{}' not found", backend_name);
+            return None;
+        };
+        let service = match backend.resolve_service() {
+            Ok(service) => service,
+            Err(e) => {
+                tracing::warn!("Existence check backend '{}': {}", backend_name, e);
+                return None;
+            }
+        };
+        let empty = HashMap::new();
+        let options = backend.options.as_ref().unwrap_or(&empty);
+        match instance_exists(service.as_ref(), options, ident).await {
+            Ok(exists) => Some(exists),
+            Err(e) => {
+                // Fail open: a broken check must not block retrievals that would succeed
+                tracing::warn!("Instance existence check failed, retrieving anyway: {}", e);
+                None
+            }
+        }
     }
 
     /// Add a return key to the identifier if not already present
@@ -284,12 +1330,13 @@ impl DicomwebBridgeMiddleware {
         if !base.exists() {
             return Err(format!("folder not found: {}", folder_path));
         }
-        for entry in fs::read_dir(&base).map_err(|e| e.to_string())? {
+        // Walk recursively: a storage_layout nests instances under series folders
+        for entry in walkdir::WalkDir::new(&base) {
             let entry = entry.map_err(|e| e.to_string())?;
             let p = entry.path();
             if p.is_file() {
                 // Include .dcm and other files
-                let bytes = fs::read(&p).map_err(|e| e.to_string())?;
+                let bytes = fs::read(p).map_err(|e| e.to_string())?;
                 parts.push(bytes);
             }
         }
@@ -299,17 +1346,420 @@ impl DicomwebBridgeMiddleware {
         Ok(parts)
     }
 
-    fn build_multipart(parts: Vec<Vec<u8>>) -> (String, Vec<u8>) {
-        let boundary = format!("dicomweb_{}", uuid::Uuid::new_v4());
-        let mut buf: Vec<u8> = Vec::new();
-        for part in parts {
-            buf.extend_from_slice(format!("--{}\r\n", &boundary).as_bytes());
-            buf.extend_from_slice(b"Content-Type: application/dicom\r\n\r\n");
-            buf.extend_from_slice(&part);
-            buf.extend_from_slice(b"\r\n");
+    /// Locate the file holding `instance_uid` under `folder_path`, falling back to the
+    /// first file found when none matches
+    fn find_instance_file(folder_path: &str, instance_uid: &str) -> Option<PathBuf> {
+        let mut chosen: Option<PathBuf> = None;
+        for e in walkdir::WalkDir::new(folder_path).into_iter().flatten() {
+            let p = e.into_path();
+            if !p.is_file() {
+                continue;
+            }
+            if let Ok(obj) = dicom_object::open_file(&p) {
+                if let Ok(el) = obj.element_by_name("SOPInstanceUID") {
+                    if let Ok(uid) = el.to_str() {
+                        if uid == instance_uid {
+                            return Some(p);
+                        }
+                    }
+                }
+            }
+            if chosen.is_none() {
+                chosen = Some(p);
+            }
         }
-        buf.extend_from_slice(format!("--{}--\r\n", &boundary).as_bytes());
-        (boundary, buf)
+        chosen
+    }
+
+    /// Every DICOM file under `folder_path`, ordered by InstanceNumber
+    fn series_instance_files(folder_path: &str) -> Vec<PathBuf> {
+        let mut files: Vec<(i64, PathBuf)> = walkdir::WalkDir::new(folder_path)
+            .into_iter()
+            .flatten()
+            .map(|e| e.into_path())
+            .filter(|p| p.is_file())
+            .filter_map(|p| {
+                let obj = dicom_object::open_file(&p).ok()?;
+                let number = obj
+                    .element(dicom_dictionary_std::tags::INSTANCE_NUMBER)
+                    .ok()
+                    .and_then(|e| e.to_int::<i64>().ok())
+                    .unwrap_or(i64::MAX);
+                Some((number, p))
+            })
+            .collect();
+        files.sort();
+        files.into_iter().map(|(_, p)| p).collect()
+    }
+
+    /// Thumbnail candidates under `folder_path`: the middle instance of the first series
+    /// (by SeriesNumber), then the rest of that series, then the other series
+    fn thumbnail_candidates(folder_path: &str) -> Vec<PathBuf> {
+        let number = |obj: &dicom_object::DefaultDicomObject, tag| {
+            obj.element(tag)
+                .ok()
+                .and_then(|e| e.to_int::<i64>().ok())
+                .unwrap_or(i64::MAX)
+        };
+        let mut series: std::collections::BTreeMap<(i64, String), Vec<(i64, PathBuf)>> =
+            Default::default();
+        for p in walkdir::WalkDir::new(folder_path)
+            .into_iter()
+            .flatten()
+            .map(|e| e.into_path())
+            .filter(|p| p.is_file())
+        {
+            let Ok(obj) = dicom_object::open_file(&p) else {
+                continue;
+            };
+            let series_uid = obj
+                .element(dicom_dictionary_std::tags::SERIES_INSTANCE_UID)
+                .ok()
+                .and_then(|e| e.to_str().ok().map(|s| s.to_string()))
+                .unwrap_or_default();
+            series
+                .entry((
+                    number(&obj, dicom_dictionary_std::tags::SERIES_NUMBER),
+                    series_uid,
+                ))
+                .or_default()
+                .push((number(&obj, dicom_dictionary_std::tags::INSTANCE_NUMBER), p));
+        }
+
+        let mut candidates = Vec::new();
+        for (i, (_, mut instances)) in series.into_iter().enumerate() {
+            instances.sort();
+            if i == 0 {
+                let middle = instances.len() / 2;
+                instances.rotate_left(middle);
+            }
+            candidates.extend(instances.into_iter().map(|(_, p)| p));
+        }
+        candidates
+    }
+
+    /// Read PixelData as stored, without decoding: native pixel bytes for all frames,
+    /// or the encapsulated fragments. Returns the file's transfer syntax alongside.
+    fn read_pixel_data(path: &Path) -> Result<(String, PixelPayload), String> {
+        let obj = dicom_object::open_file(path).map_err(|e| format!("open dicom: {}", e))?;
+        let transfer_syntax = obj
+            .meta()
+            .transfer_syntax()
+            .trim_end_matches('\0')
+            .to_string();
+        let element = obj
+            .element(dicom_dictionary_std::tags::PIXEL_DATA)
+            .map_err(|_| "instance has no pixel data".to_string())?;
+        let payload = match element.value() {
+            dicom_core::DicomValue::PixelSequence(seq) => {
+                PixelPayload::Encapsulated(seq.fragments().to_vec())
+            }
+            dicom_core::DicomValue::Primitive(value) => {
+                PixelPayload::Native(value.to_bytes().into_owned())
+            }
+            _ => return Err("unexpected PixelData value".to_string()),
+        };
+        Ok((transfer_syntax, payload))
+    }
+
+    /// Answer a bulk data request from the retrieved instance
+    ///
+    /// One attribute is returned as a single octet-stream, several as multipart/related.
+    /// PixelData is not served here: the client is redirected to the instance's frames.
+    fn respond_bulkdata(
+        &self,
+        envelope: &mut ResponseEnvelope<Value>,
+        folder_path: &str,
+        request_base: &str,
+        (study_uid, series_uid, instance_uid): (&str, &str, &str),
+        tags: &str,
+    ) {
+        let fail =
+            |envelope: &mut ResponseEnvelope<Value>, response_type: &str, message: String| {
+                let error = if response_type == "not_found" {
+                    "NotFound"
+                } else {
+                    "InvalidBulkDataURI"
+                };
+                let mut metadata = serde_json::Map::new();
+                metadata.insert("error".to_string(), json!(error));
+                metadata.insert("message".to_string(), json!(message));
+                Self::set_dicomweb_data(envelope, response_type, Value::Null, Some(metadata));
+            };
+        let not_found = |envelope: &mut ResponseEnvelope<Value>, message: String| {
+            fail(envelope, "not_found", message)
+        };
+        let tags = match parse_bulkdata_tags(tags) {
+            Ok(tags) => tags,
+            Err(e) => return fail(envelope, "bad_request", e),
+        };
+        let object = match Self::find_instance_file(folder_path, instance_uid)
+            .ok_or_else(|| "instance file not found".to_string())
+            .and_then(|p| dicom_object::open_file(p).map_err(|e| e.to_string()))
+        {
+            Ok(object) => object,
+            Err(e) => return not_found(envelope, format!("Bulk data unavailable: {}", e)),
+        };
+
+        if tags == [dicom_dictionary_std::tags::PIXEL_DATA] {
+            let frames = object
+                .element(dicom_dictionary_std::tags::NUMBER_OF_FRAMES)
+                .ok()
+                .and_then(|e| e.to_int::<u32>().ok())
+                .unwrap_or(1)
+                .max(1);
+            let frame_list = (1..=frames)
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            let mut metadata = serde_json::Map::new();
+            metadata.insert(
+                "location".to_string(),
+                json!(format!(
+                    "{}/studies/{}/series/{}/instances/{}/frames/{}",
+                    request_base, study_uid, series_uid, instance_uid, frame_list
+                )),
+            );
+            Self::set_dicomweb_data(envelope, "redirect", Value::Null, Some(metadata));
+            return;
+        }
+
+        let mut values = Vec::new();
+        for tag in &tags {
+            let bytes = match object.element(*tag).map(|e| e.value()) {
+                Ok(dicom_core::DicomValue::Primitive(value)) => value.to_bytes().into_owned(),
+                Ok(_) => {
+                    return fail(
+                        envelope,
+                        "bad_request",
+                        format!("Attribute {} does not hold bulk data", tag),
+                    )
+                }
+                Err(_) => {
+                    return not_found(
+                        envelope,
+                        format!("Attribute {} not present in instance {}", tag, instance_uid),
+                    )
+                }
+            };
+            values.push(bytes);
+        }
+
+        let mut metadata = serde_json::Map::new();
+        let body = if values.len() == 1 {
+            values.remove(0)
+        } else {
+            let boundary = self.next_boundary();
+            let mut body = Vec::new();
+            for value in values {
+                body.extend_from_slice(format!("--{}\r\n", &boundary).as_bytes());
+                body.extend_from_slice(b"Content-Type: application/octet-stream\r\n\r\n");
+                body.extend_from_slice(&value);
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", &boundary).as_bytes());
+            metadata.insert("boundary".to_string(), Value::String(boundary));
+            body
+        };
+        metadata.insert(
+            "body_b64".to_string(),
+            Value::String(base64::engine::general_purpose::STANDARD.encode(&body)),
+        );
+        Self::set_dicomweb_data(envelope, "wado_bulkdata", Value::Null, Some(metadata));
+    }
+
+    /// Answer a WADO-URI request from the instances retrieved into `folder_path`
+    fn respond_wado_uri(
+        envelope: &mut ResponseEnvelope<Value>,
+        folder_path: &str,
+    ) -> Result<(), Error> {
+        let request = match WadoUriRequest::from_query(&envelope.request_details.query_params) {
+            Ok(request) => request,
+            Err(e) => {
+                Self::set_error_data(envelope, "bad_request", "InvalidWadoUriRequest", e);
+                return Ok(());
+            }
+        };
+        let Some(instance_path) = Self::find_instance_file(folder_path, &request.object_uid) else {
+            Self::set_error_data(
+                envelope,
+                "not_found",
+                "NotFound",
+                format!("Instance {} not found", request.object_uid),
+            );
+            return Ok(());
+        };
+
+        let body = if request.content_type == "application/dicom" {
+            let bytes =
+                fs::read(&instance_path).map_err(|e| Error::from(format!("read dicom: {}", e)))?;
+            match &request.transfer_syntax {
+                Some(ts_uid) => match transcode_parts(vec![bytes], ts_uid) {
+                    Ok(mut parts) => parts.remove(0),
+                    Err(message) => {
+                        Self::set_error_data(
+                            envelope,
+                            "not_acceptable",
+                            "TransferSyntaxNotSupported",
+                            message,
+                        );
+                        return Ok(());
+                    }
+                },
+                None => bytes,
+            }
+        } else {
+            let obj = dicom_object::open_file(&instance_path)
+                .map_err(|e| Error::from(format!("open dicom: {}", e)))?;
+            let frames = obj
+                .element(dicom_dictionary_std::tags::NUMBER_OF_FRAMES)
+                .ok()
+                .and_then(|e| e.to_int::<u32>().ok())
+                .unwrap_or(1)
+                .max(1);
+            if request.frame > frames {
+                Self::set_error_data(
+                    envelope,
+                    "bad_request",
+                    "InvalidWadoUriRequest",
+                    format!(
+                        "frameNumber {} is out of range; instance {} has {} frame(s)",
+                        request.frame, request.object_uid, frames
+                    ),
+                );
+                return Ok(());
+            }
+            let Ok(pixel_data) = obj.decode_pixel_data() else {
+                Self::set_frames_error(envelope);
+                return Ok(());
+            };
+            let image = request
+                .render
+                .render(&pixel_data, request.frame - 1)
+                .map_err(|e| Error::from(format!("to image: {}", e)))?;
+            Self::encode_image(&image, request.content_type, request.render.quality)?
+        };
+
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("content_type".to_string(), json!(request.content_type));
+        metadata.insert(
+            "body_b64".to_string(),
+            Value::String(base64::engine::general_purpose::STANDARD.encode(&body)),
+        );
+        Self::set_dicomweb_data(envelope, "wado_uri", Value::Null, Some(metadata));
+        Ok(())
+    }
+
+    /// Image type for rendered frames: PNG only when asked for without JPEG
+    fn preferred_image_type(headers: &HashMap<String, String>) -> &'static str {
+        let accept = headers
+            .get("accept")
+            .map(|s| s.to_lowercase())
+            .unwrap_or_default();
+        let want_jpeg = accept.contains("image/jpeg") || accept.contains("*/*");
+        if !want_jpeg && accept.contains("image/png") {
+            "image/png"
+        } else {
+            "image/jpeg"
+        }
+    }
+
+    fn encode_image(
+        image: &img::DynamicImage,
+        content_type: &str,
+        quality: u8,
+    ) -> Result<Vec<u8>, Error> {
+        let mut buf: Vec<u8> = Vec::new();
+        if content_type == "image/jpeg" {
+            let mut enc = img::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality);
+            enc.encode_image(image)
+                .map_err(|e| Error::from(format!("jpeg encode: {}", e)))?;
+        } else {
+            let enc = img::codecs::png::PngEncoder::new(&mut buf);
+            enc.write_image(
+                image.as_bytes(),
+                image.width(),
+                image.height(),
+                image.color().into(),
+            )
+            .map_err(|e| Error::from(format!("png encode: {}", e)))?;
+        }
+        Ok(buf)
+    }
+
+    /// Hand encoded images to the endpoint: one image as-is, several as multipart/related
+    fn set_image_data(
+        &self,
+        envelope: &mut ResponseEnvelope<Value>,
+        content_type: &str,
+        images: Vec<Vec<u8>>,
+    ) {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert(
+            "content_type".to_string(),
+            Value::String(content_type.to_string()),
+        );
+        let body = if images.len() == 1 {
+            metadata.insert("is_single_frame".to_string(), Value::Bool(true));
+            images.into_iter().next().unwrap_or_default()
+        } else {
+            let boundary = self.next_boundary();
+            let mut body: Vec<u8> = Vec::new();
+            for img in images {
+                body.extend_from_slice(format!("--{}\r\n", &boundary).as_bytes());
+                body.extend_from_slice(
+                    format!("Content-Type: {}\r\n\r\n", content_type).as_bytes(),
+                );
+                body.extend_from_slice(&img);
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend_from_slice(format!("--{}--\r\n", &boundary).as_bytes());
+            metadata.insert("boundary".to_string(), Value::String(boundary));
+            metadata.insert("is_single_frame".to_string(), Value::Bool(false));
+            body
+        };
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&body);
+        metadata.insert("body_b64".to_string(), Value::String(b64));
+        Self::set_dicomweb_data(envelope, "wado_frames", Value::Null, Some(metadata));
+    }
+
+    /// Answer with an error response type (`bad_request`, `not_found`) and its details
+    fn set_error_data(
+        envelope: &mut ResponseEnvelope<Value>,
+        response_type: &str,
+        error: &str,
+        message: String,
+    ) {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("error".to_string(), json!(error));
+        metadata.insert("message".to_string(), json!(message));
+        Self::set_dicomweb_data(envelope, response_type, Value::Null, Some(metadata));
+    }
+
+    fn set_frames_error(envelope: &mut ResponseEnvelope<Value>) {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert(
+            "error".to_string(),
+            Value::String("UnsupportedTransferSyntax".to_string()),
+        );
+        metadata.insert(
+            "message".to_string(),
+            Value::String("Unable to decode frames for requested instance".to_string()),
+        );
+        Self::set_dicomweb_data(envelope, "wado_frames_error", Value::Null, Some(metadata));
+    }
+
+    fn build_multipart(&self, parts: Vec<Vec<u8>>) -> (String, Vec<u8>) {
+        let boundary = self.next_boundary();
+        let mut buf: Vec<u8> = Vec::new();
+        for part in parts {
+            buf.extend_from_slice(format!("--{}\r\n", &boundary).as_bytes());
+            buf.extend_from_slice(b"Content-Type: application/dicom\r\n\r\n");
+            buf.extend_from_slice(&part);
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(format!("--{}--\r\n", &boundary).as_bytes());
+        (boundary, buf)
     }
 }
 
@@ -320,13 +1770,16 @@ impl Middleware for DicomwebBridgeMiddleware {
         mut envelope: RequestEnvelope<serde_json::Value>,
     ) -> Result<RequestEnvelope<serde_json::Value>, Error> {
         let method = envelope.request_details.method.to_uppercase();
-        let subpath = envelope
+        let mut subpath = envelope
             .request_details
             .metadata
             .get("path")
             .cloned()
             .unwrap_or_default();
-        let qp = &envelope.request_details.query_params;
+        if self.config.normalize_paths {
+            subpath = normalize_dicomweb_path(&subpath);
+        }
+        let qp = envelope.request_details.query_params.clone();
 
         // Only act on GET requests from DICOMweb endpoints
         if method != "GET" {
@@ -339,6 +1792,63 @@ impl Middleware for DicomwebBridgeMiddleware {
             return Ok(envelope);
         }
 
+        // Configured templates take precedence over the built-in routes
+        let matched_template = self
+            .config
+            .identifier_templates
+            .iter()
+            .find_map(|t| t.match_path(&parts).map(|captures| (t, captures)));
+
+        // Refuse unrestricted searches at levels that require a match key
+        let qido_level = match (&matched_template, parts.as_slice()) {
+            (Some((template, _)), _) => Some(template.level.as_str()),
+            (None, ["studies"]) => Some("study"),
+            (None, ["studies", _, "series"]) => Some("series"),
+            (None, ["studies", _, "series", _, "instances"]) => Some("instance"),
+            _ => None,
+        };
+        // fuzzymatching=true: person names are matched here rather than by the C-FIND SCP
+        let fuzzy = qp
+            .get("fuzzymatching")
+            .and_then(|v| v.first())
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+
+        if let Some(required) = qido_level.and_then(|l| self.config.required_query_keys.get(l)) {
+            // A fuzzy name only narrows the C-FIND to a first letter, so it does not count
+            let supplied = qp.iter().any(|(name, values)| {
+                let tag_hex = match matched_template
+                    .as_ref()
+                    .and_then(|(t, _)| t.params.get(name))
+                {
+                    Some(tag_hex) => tag_hex.clone(),
+                    None => Self::dicom_name_to_hex(name),
+                };
+                let fuzzy_name =
+                    fuzzy && Self::lookup_vr_for_tag(&tag_hex).is_some_and(|vr| vr == "PN");
+                values.iter().any(|v| !v.trim().is_empty())
+                    && required.contains(&tag_hex)
+                    && !fuzzy_name
+            });
+            if !supplied {
+                let level = qido_level.unwrap_or_default();
+                let keys: Vec<String> = required.iter().map(|t| tag_keyword(t)).collect();
+                tracing::warn!(
+                    "DICOMweb bridge: rejecting {}-level query without a required match key",
+                    level
+                );
+                Self::reject_request(
+                    &mut envelope,
+                    "MatchKeyRequired",
+                    format!(
+                        "Queries at {} level must include at least one of: {}",
+                        level,
+                        keys.join(", ")
+                    ),
+                );
+                return Ok(envelope);
+            }
+        }
+
         // Build DICOM identifier JSON using hex tags
         let mut ident = serde_json::Map::<String, Value>::new();
 
@@ -372,28 +1882,103 @@ impl Middleware for DicomwebBridgeMiddleware {
             offset
         );
 
+        let mut fuzzy_names = serde_json::Map::new();
+
+        // WADO-URI parameters name the object to retrieve rather than query keys
+        let is_wado_uri = parts.as_slice() == ["wado"];
+
         // Process all query parameters (except special ones like includefield/limit/offset)
-        for (param_name, param_values) in qp {
+        for (param_name, param_values) in &qp {
             // Skip special DICOMweb parameters that aren't DICOM tags
-            if matches!(
-                param_name.as_str(),
-                "includefield" | "limit" | "offset" | "fuzzymatching"
-            ) {
+            if is_wado_uri
+                || matches!(
+                    param_name.as_str(),
+                    "includefield"
+                        | "limit"
+                        | "offset"
+                        | "fuzzymatching"
+                        | "viewport"
+                        | "quality"
+                        | "window"
+                )
+            {
                 continue;
             }
 
             // Convert parameter name to DICOM hex tag
-            let tag_hex = Self::dicom_name_to_hex(param_name);
-            let vr = Self::infer_vr_for_tag(&tag_hex);
+            let tag_hex = match matched_template
+                .as_ref()
+                .and_then(|(t, _)| t.params.get(param_name))
+            {
+                Some(tag_hex) => tag_hex.clone(),
+                None => Self::dicom_name_to_hex(param_name),
+            };
+            if !self.config.permits_query_tag(&tag_hex) {
+                tracing::warn!(
+                    "DICOMweb bridge: query attribute '{}' ({}) is not permitted",
+                    param_name,
+                    tag_hex
+                );
+                Self::reject_request(
+                    &mut envelope,
+                    "AttributeNotPermitted",
+                    format!(
+                        "DICOM attribute '{}' ({}) is not permitted as a query key",
+                        param_name, tag_hex
+                    ),
+                );
+                return Ok(envelope);
+            }
+            let vr = match Self::lookup_vr_for_tag(&tag_hex) {
+                Some(vr) => vr,
+                None if self.config.strict_vr => {
+                    tracing::warn!(
+                        "DICOMweb bridge: rejecting unknown query attribute '{}'",
+                        param_name
+                    );
+                    Self::reject_request(
+                        &mut envelope,
+                        "UnknownAttribute",
+                        format!("Unknown DICOM attribute '{}' in query", param_name),
+                    );
+                    return Ok(envelope);
+                }
+                None => "LO".to_string(),
+            };
+
+            // C-FIND name matching is literal and usually case-sensitive, so for fuzzy
+            // matching only narrow by first letter and filter the matches in right()
+            if fuzzy && vr == "PN" {
+                let narrowed = fuzzy_name_wildcard(param_values).into_iter().collect();
+                Self::add_tag(&mut ident, &tag_hex, &vr, narrowed);
+                fuzzy_names.insert(tag_hex, json!(param_values));
+                continue;
+            }
 
             // Use all values for this parameter (DICOMweb allows multiple values)
-            // Date ranges in DICOM format (YYYYMMDD-YYYYMMDD) are passed through as-is
-            let values: Vec<String> = param_values.to_vec();
+            // Date ranges in DICOM format (YYYYMMDD-YYYYMMDD) are passed through as-is;
+            // partial dates/times are widened into ranges when enabled
+            let values: Vec<String> =
+                if self.config.expand_partial_dates && (vr == "DA" || vr == "TM") {
+                    param_values
+                        .iter()
+                        .map(|v| Self::expand_partial_date_value(v, &vr))
+                        .collect()
+                } else {
+                    param_values.to_vec()
+                };
             if !values.is_empty() {
                 Self::add_tag(&mut ident, &tag_hex, &vr, values);
             }
         }
 
+        if !fuzzy_names.is_empty() {
+            envelope.request_details.metadata.insert(
+                "dicomweb_fuzzy_names".to_string(),
+                Value::Object(fuzzy_names).to_string(),
+            );
+        }
+
         // Parse includefield query parameter for attribute filtering
         // DICOMweb spec allows comma-separated values in a single parameter value
         let includefield: Option<Vec<String>> = qp.get("includefield").map(|values| {
@@ -432,6 +2017,17 @@ impl Middleware for DicomwebBridgeMiddleware {
         // Route-based mapping
         let mut op = None::<&str>;
         match parts.as_slice() {
+            // Configured identifier template
+            _ if matched_template.is_some() => {
+                if let Some((template, captures)) = &matched_template {
+                    op = Some("find");
+                    for (tag_hex, value) in captures {
+                        let vr = Self::infer_vr_for_tag(tag_hex);
+                        Self::add_tag(&mut ident, tag_hex, &vr, vec![value.clone()]);
+                    }
+                    add_return_keys(&mut ident, &template.level);
+                }
+            }
             // QIDO: /studies
             ["studies"] => {
                 op = Some("find");
@@ -574,6 +2170,22 @@ impl Middleware for DicomwebBridgeMiddleware {
                 );
                 add_return_keys(&mut ident, "series");
             }
+            // Thumbnail: /studies/{study}/thumbnail (get at study level)
+            ["studies", study_uid, "thumbnail"] => {
+                op = Some("get");
+                Self::add_tag(&mut ident, "0020000D", "UI", vec![(*study_uid).to_string()]);
+            }
+            // WADO rendered and thumbnail: /studies/{study}/series/{series}/... (series level)
+            ["studies", study_uid, "series", series_uid, "rendered" | "thumbnail"] => {
+                op = Some("get");
+                Self::add_tag(&mut ident, "0020000D", "UI", vec![(*study_uid).to_string()]);
+                Self::add_tag(
+                    &mut ident,
+                    "0020000E",
+                    "UI",
+                    vec![(*series_uid).to_string()],
+                );
+            }
             // WADO metadata: /studies/{study}/series/{series}/instances/{instance}/metadata
             ["studies", study_uid, "series", series_uid, "instances", instance_uid, "metadata"] => {
                 op = Some("find");
@@ -592,8 +2204,11 @@ impl Middleware for DicomwebBridgeMiddleware {
                 );
                 add_return_keys(&mut ident, "instance");
             }
-            // WADO: frames (map to get at instance level)
-            ["studies", study_uid, "series", series_uid, "instances", instance_uid, "frames", _frames] =>
+            // WADO: frames, rendered images and raw pixel data (map to get at instance level)
+            ["studies", study_uid, "series", series_uid, "instances", instance_uid, "frames", _]
+            | ["studies", study_uid, "series", series_uid, "instances", instance_uid, "frames", _, "rendered"]
+            | ["studies", study_uid, "series", series_uid, "instances", instance_uid, "rendered" | "thumbnail"]
+            | ["studies", study_uid, "series", series_uid, "instances", instance_uid, "pixeldata"] =>
             {
                 op = Some("get");
                 Self::add_tag(&mut ident, "0020000D", "UI", vec![(*study_uid).to_string()]);
@@ -610,13 +2225,67 @@ impl Middleware for DicomwebBridgeMiddleware {
                     vec![(*instance_uid).to_string()],
                 );
             }
-            // WADO bulkdata (map to get; actual subresource ignored for now)
-            ["bulkdata", ..] => {
+            // WADO bulkdata: bulkdata/{study}/{series}/{instance}/{tag}[,{tag}...]
+            ["bulkdata", study_uid, series_uid, instance_uid, tags] => {
+                if let Err(e) = parse_bulkdata_tags(tags) {
+                    Self::reject_request(&mut envelope, "InvalidBulkDataURI", e);
+                    return Ok(envelope);
+                }
                 op = Some("get");
+                for (tag, uid) in [
+                    ("0020000D", study_uid),
+                    ("0020000E", series_uid),
+                    ("00080018", instance_uid),
+                ] {
+                    Self::add_tag(&mut ident, tag, "UI", vec![(*uid).to_string()]);
+                }
+            }
+            // WADO-URI: wado?requestType=WADO&studyUID=...&seriesUID=...&objectUID=...
+            ["wado"] => match WadoUriRequest::from_query(&qp) {
+                Ok(request) => {
+                    op = Some("get");
+                    for (tag, uid) in [
+                        ("0020000D", &request.study_uid),
+                        ("0020000E", &request.series_uid),
+                        ("00080018", &request.object_uid),
+                    ] {
+                        Self::add_tag(&mut ident, tag, "UI", vec![uid.clone()]);
+                    }
+                }
+                Err(e) => {
+                    Self::reject_request(&mut envelope, "InvalidWadoUriRequest", e);
+                    return Ok(envelope);
+                }
+            },
+            ["bulkdata", ..] => {
+                Self::reject_request(
+                    &mut envelope,
+                    "InvalidBulkDataURI",
+                    "Bulk data URIs take the form bulkdata/{study}/{series}/{instance}/{tag}"
+                        .to_string(),
+                );
+                return Ok(envelope);
             }
             _ => {}
         }
 
+        // Optional instance-level C-FIND so a missing instance is answered without a C-GET
+        if op == Some("get") {
+            let instance_uid = ident
+                .get("00080018")
+                .and_then(|e| e.get("Value"))
+                .and_then(|v| v.get(0))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            if let Some(instance_uid) = instance_uid {
+                if self.check_instance(&ident).await == Some(false) {
+                    tracing::debug!("Instance {} not found; skipping retrieval", instance_uid);
+                    Self::reject_missing_instance(&mut envelope, &instance_uid);
+                    return Ok(envelope);
+                }
+            }
+        }
+
         if let Some(op_name) = op {
             // Ensure metadata prepared for backend
             Self::set_backend_path(&mut envelope.request_details.metadata, op_name);
@@ -682,6 +2351,16 @@ impl Middleware for DicomwebBridgeMiddleware {
         if let Some((p, _)) = raw_path.split_once('?') {
             raw_path = p.to_string();
         }
+        if self.config.normalize_paths {
+            raw_path = normalize_dicomweb_path(&raw_path);
+        }
+        // Everything before "studies/" (or "bulkdata/") is the DICOMweb root this request
+        // arrived on
+        let request_base = raw_path
+            .find("studies/")
+            .or_else(|| raw_path.find("bulkdata/"))
+            .map(|idx| raw_path[..idx].trim_end_matches('/').to_string())
+            .unwrap_or_default();
         // Extract the DICOMweb subpath segment beginning at "studies/" if available
         let path = if let Some(idx) = raw_path.find("studies/") {
             raw_path[idx..].to_string()
@@ -714,6 +2393,13 @@ impl Middleware for DicomwebBridgeMiddleware {
             .get("dicomweb_includefield")
             .and_then(|json_str| serde_json::from_str(json_str).ok());
 
+        // Person-name criteria left to be matched here (fuzzymatching=true)
+        let fuzzy_names: Option<serde_json::Map<String, Value>> = envelope
+            .request_details
+            .metadata
+            .get("dicomweb_fuzzy_names")
+            .and_then(|json_str| serde_json::from_str(json_str).ok());
+
         // Parse pagination parameters from request metadata (set by left-side middleware)
         let limit = envelope
             .request_details
@@ -729,33 +2415,51 @@ impl Middleware for DicomwebBridgeMiddleware {
             .and_then(|s| s.parse::<usize>().ok())
             .unwrap_or(0);
 
-        // QIDO lists -> DICOMweb JSON data
-        if operation == "find" {
-            let matches_val = nd.get("matches").cloned().unwrap_or(Value::Array(vec![]));
-            let mut json = Self::build_qido_json_from_matches(&matches_val, includefield.as_ref());
-
-            // Apply pagination to QIDO-RS results
-            // Offset is applied here since DIMSE C-FIND doesn't natively support it
-            if let Value::Array(ref mut results) = json {
-                let total_results = results.len();
-                tracing::debug!(
-                    total_results = total_results,
-                    limit = limit,
-                    offset = offset,
-                    "Applying pagination to QIDO-RS results"
+        // Failures with a recognisable DIMSE status map to the configured HTTP status
+        if !success {
+            if let Some(category) = DimseFailureCategory::from_response(&nd) {
+                let message = nd
+                    .get("error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("DIMSE operation failed");
+                let mut metadata = serde_json::Map::new();
+                metadata.insert(
+                    "http_status".to_string(),
+                    json!(self.config.failure_http_status(category)),
+                );
+                metadata.insert("error".to_string(), json!(category.as_str()));
+                metadata.insert("message".to_string(), json!(message));
+                Self::set_dicomweb_data(
+                    &mut envelope,
+                    "dimse_failure",
+                    Value::Null,
+                    Some(metadata),
                 );
+                return Ok(envelope);
+            }
+        }
 
-                // Skip offset items and take limit items
-                let start = offset.min(total_results);
-                let end = (start + limit).min(total_results);
-                *results = results.drain(start..end).collect();
+        // WADO-URI -> the object itself, or one frame of it rendered as an image
+        if operation == "get" && path.rsplit('/').next() == Some("wado") {
+            if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
+                Self::respond_wado_uri(&mut envelope, folder_path)?;
+                return Ok(envelope);
+            }
+        }
 
-                tracing::debug!(
-                    paginated_count = results.len(),
-                    "Pagination applied, returning {} results",
-                    results.len()
-                );
+        // QIDO lists -> DICOMweb JSON data
+        if operation == "find" {
+            let mut matches_val = nd.get("matches").cloned().unwrap_or(Value::Array(vec![]));
+            if let (Some(names), Value::Array(items)) = (&fuzzy_names, &mut matches_val) {
+                items.retain(|item| Self::matches_fuzzy_names(item, names));
             }
+            let (json, truncated) = Self::build_qido_json_from_matches(
+                &matches_val,
+                includefield.as_ref(),
+                self.config.tag_inclusion,
+                limit,
+                offset,
+            );
 
             // Create metadata indicating whether results were found
             let has_results = match &json {
@@ -773,6 +2477,22 @@ impl Middleware for DicomwebBridgeMiddleware {
             let mut metadata = serde_json::Map::new();
             metadata.insert("has_results".to_string(), Value::Bool(has_results));
 
+            // Carry C-FIND warnings (e.g. 0xFF01 optional keys not supported) through to the
+            // endpoint, plus one for a page that stops short of the full match set
+            let mut warnings = nd
+                .get("warnings")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            if truncated {
+                warnings.push(json!({
+                    "message": "There are additional results that can be requested"
+                }));
+            }
+            if !warnings.is_empty() {
+                metadata.insert("warnings".to_string(), Value::Array(warnings));
+            }
+
             Self::set_dicomweb_data(&mut envelope, "qido_json", json, Some(metadata));
             return Ok(envelope);
         }
@@ -784,32 +2504,168 @@ impl Middleware for DicomwebBridgeMiddleware {
             let raw_json = datasets.cloned().unwrap_or(Value::Array(vec![]));
 
             // Apply includefield filtering to metadata responses
-            let filtered_json = match raw_json {
+            let mut filtered_json = match raw_json {
                 Value::Array(arr) => {
                     let filtered_arr: Vec<Value> = arr
                         .iter()
-                        .map(|item| Self::filter_dicom_json(item, includefield.as_ref()))
+                        .map(|item| {
+                            Self::filter_dicom_json(
+                                item,
+                                includefield.as_ref(),
+                                self.config.tag_inclusion,
+                            )
+                        })
                         .collect();
                     Value::Array(filtered_arr)
                 }
-                other => Self::filter_dicom_json(&other, includefield.as_ref()),
+                other => Self::filter_dicom_json(
+                    &other,
+                    includefield.as_ref(),
+                    self.config.tag_inclusion,
+                ),
             };
 
+            if self.config.viewer_profile == ViewerProfile::Ohif {
+                let base_url = self
+                    .config
+                    .bulkdata_base_url
+                    .as_deref()
+                    .map(|b| b.trim_end_matches('/'))
+                    .unwrap_or(&request_base);
+                Self::apply_ohif_profile(&mut filtered_json, base_url);
+            }
+
             Self::set_dicomweb_data(&mut envelope, "wado_metadata", filtered_json, None);
             return Ok(envelope);
         }
 
+        // WADO pixel data -> PixelData bytes as stored, no rendering
+        if operation == "get" && path.ends_with("/pixeldata") {
+            if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
+                let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                let instance_uid = parts.get(5).copied().unwrap_or("");
+                let pixel_data = Self::find_instance_file(folder_path, instance_uid)
+                    .ok_or_else(|| "instance file not found".to_string())
+                    .and_then(|p| Self::read_pixel_data(&p));
+                match pixel_data {
+                    Ok((transfer_syntax, payload)) => {
+                        let mut metadata = serde_json::Map::new();
+                        metadata.insert(
+                            "transfer_syntax".to_string(),
+                            Value::String(transfer_syntax.clone()),
+                        );
+                        let body = match payload {
+                            PixelPayload::Native(bytes) => bytes,
+                            PixelPayload::Encapsulated(fragments) => {
+                                let boundary = self.next_boundary();
+                                let mut body: Vec<u8> = Vec::new();
+                                for fragment in fragments {
+                                    body.extend_from_slice(
+                                        format!("--{}\r\n", &boundary).as_bytes(),
+                                    );
+                                    body.extend_from_slice(
+                                        format!(
+                                            "Content-Type: application/octet-stream; transfer-syntax={}\r\n\r\n",
+                                            transfer_syntax
+                                        )
+                                        .as_bytes(),
+                                    );
+                                    body.extend_from_slice(&fragment);
+                                    body.extend_from_slice(b"\r\n");
+                                }
+                                body.extend_from_slice(format!("--{}--\r\n", &boundary).as_bytes());
+                                metadata.insert("boundary".to_string(), Value::String(boundary));
+                                body
+                            }
+                        };
+                        metadata.insert(
+                            "body_b64".to_string(),
+                            Value::String(base64::engine::general_purpose::STANDARD.encode(&body)),
+                        );
+                        Self::set_dicomweb_data(
+                            &mut envelope,
+                            "wado_pixeldata",
+                            Value::Null,
+                            Some(metadata),
+                        );
+                    }
+                    Err(e) => {
+                        let mut metadata = serde_json::Map::new();
+                        metadata.insert("error".to_string(), Value::String("NotFound".to_string()));
+                        metadata.insert(
+                            "message".to_string(),
+                            Value::String(format!("Pixel data unavailable: {}", e)),
+                        );
+                        Self::set_dicomweb_data(
+                            &mut envelope,
+                            "not_found",
+                            Value::Null,
+                            Some(metadata),
+                        );
+                    }
+                }
+                return Ok(envelope);
+            }
+        }
+
+        // WADO bulk data -> raw attribute values
+        if operation == "get" && path.starts_with("bulkdata/") {
+            if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
+                let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                if let [_, study_uid, series_uid, instance_uid, tags] = parts.as_slice() {
+                    self.respond_bulkdata(
+                        &mut envelope,
+                        folder_path,
+                        &request_base,
+                        (study_uid, series_uid, instance_uid),
+                        tags,
+                    );
+                    return Ok(envelope);
+                }
+            }
+        }
+
         // WADO instance retrieval -> multipart DICOM data
-        if operation == "get" && path.contains("/instances/") && !path.contains("/frames/") {
+        if operation == "get"
+            && path.contains("/instances/")
+            && !path.contains("/frames/")
+            && !path.ends_with("/pixeldata")
+            && !path.ends_with("/rendered")
+            && !path.ends_with("/thumbnail")
+        {
             if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
+                // Accept: ...; transfer-syntax={uid} asks for the instances re-encoded
+                let requested_ts = envelope
+                    .request_details
+                    .headers
+                    .get("accept")
+                    .and_then(|accept| requested_transfer_syntax(accept));
                 match Self::read_instance_bytes(folder_path) {
                     Ok(parts) => {
-                        let (boundary, body_bytes) = Self::build_multipart(parts);
+                        let parts = match &requested_ts {
+                            Some(ts_uid) => match transcode_parts(parts, ts_uid) {
+                                Ok(parts) => parts,
+                                Err(message) => {
+                                    Self::set_error_data(
+                                        &mut envelope,
+                                        "not_acceptable",
+                                        "TransferSyntaxNotSupported",
+                                        message,
+                                    );
+                                    return Ok(envelope);
+                                }
+                            },
+                            None => parts,
+                        };
+                        let (boundary, body_bytes) = self.build_multipart(parts);
                         let b64 = base64::engine::general_purpose::STANDARD.encode(&body_bytes);
 
                         let mut metadata = serde_json::Map::new();
                         metadata.insert("boundary".to_string(), Value::String(boundary));
                         metadata.insert("body_b64".to_string(), Value::String(b64));
+                        if let Some(ts_uid) = requested_ts {
+                            metadata.insert("transfer_syntax".to_string(), Value::String(ts_uid));
+                        }
 
                         Self::set_dicomweb_data(
                             &mut envelope,
@@ -826,64 +2682,148 @@ impl Middleware for DicomwebBridgeMiddleware {
             }
         }
 
+        // Thumbnail -> one small JPEG of a representative instance
+        if operation == "get" && path.ends_with("/thumbnail") {
+            if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
+                let query_params = &envelope.request_details.query_params;
+                let mut options = match RenderOptions::from_query(query_params) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        Self::set_error_data(
+                            &mut envelope,
+                            "bad_request",
+                            "InvalidRenderParameter",
+                            e,
+                        );
+                        return Ok(envelope);
+                    }
+                };
+                options.viewport.get_or_insert(DEFAULT_THUMBNAIL_SIZE);
+
+                let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                let candidates = match parts.as_slice() {
+                    [.., "instances", instance_uid, "thumbnail"] => {
+                        Self::find_instance_file(folder_path, instance_uid)
+                            .into_iter()
+                            .collect()
+                    }
+                    _ => Self::thumbnail_candidates(folder_path),
+                };
+
+                // An instance that cannot be decoded (no pixels, unsupported transfer
+                // syntax) passes the choice on to the next candidate
+                for candidate in candidates {
+                    let rendered = dicom_object::open_file(&candidate)
+                        .map_err(|e| e.to_string())
+                        .and_then(|obj| {
+                            let pixel_data = obj.decode_pixel_data().map_err(|e| e.to_string())?;
+                            options.render(&pixel_data, 0).map_err(|e| e.to_string())
+                        });
+                    match rendered {
+                        Ok(image) => {
+                            let jpeg = Self::encode_image(&image, "image/jpeg", options.quality)?;
+                            self.set_image_data(&mut envelope, "image/jpeg", vec![jpeg]);
+                            return Ok(envelope);
+                        }
+                        Err(e) => tracing::debug!(
+                            "Skipping thumbnail candidate {}: {}",
+                            candidate.display(),
+                            e
+                        ),
+                    }
+                }
+                Self::set_error_data(
+                    &mut envelope,
+                    "not_found",
+                    "NotFound",
+                    "No renderable instance for thumbnail".to_string(),
+                );
+                return Ok(envelope);
+            }
+        }
+
+        // WADO rendered -> consumer-ready images of a series, instance or frames
+        if operation == "get" && path.ends_with("/rendered") {
+            if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
+                let content_type = Self::preferred_image_type(&envelope.request_details.headers);
+                let query_params = &envelope.request_details.query_params;
+                let options = match RenderOptions::from_query(query_params) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        Self::set_error_data(
+                            &mut envelope,
+                            "bad_request",
+                            "InvalidRenderParameter",
+                            e,
+                        );
+                        return Ok(envelope);
+                    }
+                };
+
+                // Instance and frame levels render the named instance; the series level
+                // renders the first frame of every retrieved instance
+                let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                let targets: Vec<(PathBuf, Vec<usize>)> = match parts.as_slice() {
+                    [.., "instances", instance_uid, "frames", frames, "rendered"] => {
+                        Self::find_instance_file(folder_path, instance_uid)
+                            .map(|p| (p, parse_frame_numbers(frames)))
+                            .into_iter()
+                            .collect()
+                    }
+                    [.., "instances", instance_uid, "rendered"] => {
+                        Self::find_instance_file(folder_path, instance_uid)
+                            .map(|p| (p, vec![1]))
+                            .into_iter()
+                            .collect()
+                    }
+                    _ => Self::series_instance_files(folder_path)
+                        .into_iter()
+                        .map(|p| (p, vec![1]))
+                        .collect(),
+                };
+                if targets.is_empty() {
+                    Self::set_error_data(
+                        &mut envelope,
+                        "not_found",
+                        "NotFound",
+                        "No instance to render".to_string(),
+                    );
+                    return Ok(envelope);
+                }
+
+                let mut images = Vec::new();
+                for (instance_path, frames) in targets {
+                    let obj = dicom_object::open_file(&instance_path)
+                        .map_err(|e| Error::from(format!("open dicom: {}", e)))?;
+                    let Ok(pixel_data) = obj.decode_pixel_data() else {
+                        Self::set_frames_error(&mut envelope);
+                        return Ok(envelope);
+                    };
+                    for frame in frames {
+                        let image = options
+                            .render(&pixel_data, frame.saturating_sub(1) as u32)
+                            .map_err(|e| Error::from(format!("to image: {}", e)))?;
+                        images.push(Self::encode_image(&image, content_type, options.quality)?);
+                    }
+                }
+                self.set_image_data(&mut envelope, content_type, images);
+                return Ok(envelope);
+            }
+        }
+
         // WADO frames -> decode and encode image data
         if operation == "get" && (path.contains("/frames/") || path.contains("frames/")) {
             // Accept negotiation - store preferred format for endpoint to use
-            let accept = envelope
-                .request_details
-                .headers
-                .get("accept")
-                .map(|s| s.to_lowercase())
-                .unwrap_or_default();
-            let want_jpeg = accept.contains("image/jpeg") || accept.contains("*/*");
-            let want_png = accept.contains("image/png");
-            let content_type = if want_jpeg {
-                "image/jpeg"
-            } else if want_png {
-                "image/png"
-            } else {
-                "image/jpeg"
-            };
+            let content_type = Self::preferred_image_type(&envelope.request_details.headers);
 
             if let Some(folder_path) = nd.get("folder_path").and_then(|v| v.as_str()) {
                 // Parse instance UID and frame numbers from path
                 let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
                 // expected: studies/{study}/series/{series}/instances/{instance}/frames/{frames}
                 let instance_uid = parts.get(5).copied().unwrap_or("");
-                let frames_str = parts.get(7).copied().unwrap_or("");
-                let frame_numbers: Vec<usize> = frames_str
-                    .split(',')
-                    .filter_map(|s| s.parse::<usize>().ok())
-                    .collect();
-
-                // Pick instance file path from folder_path
-                let instance_path = {
-                    // try to match SOPInstanceUID; fallback to first file
-                    let base = std::path::PathBuf::from(folder_path);
-                    let mut chosen: Option<std::path::PathBuf> = None;
-                    if let Ok(rd) = std::fs::read_dir(&base) {
-                        for e in rd.flatten() {
-                            let p = e.path();
-                            if !p.is_file() {
-                                continue;
-                            }
-                            if let Ok(obj) = dicom_object::open_file(&p) {
-                                if let Ok(el) = obj.element_by_name("SOPInstanceUID") {
-                                    if let Ok(uid) = el.to_str() {
-                                        if uid == instance_uid {
-                                            chosen = Some(p.clone());
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            if chosen.is_none() {
-                                chosen = Some(p.clone());
-                            }
-                        }
-                    }
-                    chosen
-                };
+                let frame_numbers = parse_frame_numbers(parts.get(7).copied().unwrap_or(""));
+
+                let instance_path = Self::find_instance_file(folder_path, instance_uid);
 
                 if let Some(ipath) = instance_path {
                     // Decode frames using dicom-pixeldata 0.9 API
@@ -896,111 +2836,24 @@ impl Middleware for DicomwebBridgeMiddleware {
                                 let idx = f.saturating_sub(1) as u32;
                                 match pixel_data.to_dynamic_image(idx) {
                                     Ok(dyn_img) => {
-                                        let mut buf: Vec<u8> = Vec::new();
-                                        if content_type == "image/jpeg" {
-                                            let mut enc =
-                                                img::codecs::jpeg::JpegEncoder::new_with_quality(
-                                                    &mut buf, 90,
-                                                );
-                                            if let Err(e) = enc.encode_image(&dyn_img) {
-                                                return Err(Error::from(format!(
-                                                    "jpeg encode: {}",
-                                                    e
-                                                )));
-                                            }
-                                        } else {
-                                            let enc = img::codecs::png::PngEncoder::new(&mut buf);
-                                            if let Err(e) = enc.write_image(
-                                                dyn_img.as_bytes(),
-                                                dyn_img.width(),
-                                                dyn_img.height(),
-                                                dyn_img.color().into(),
-                                            ) {
-                                                return Err(Error::from(format!(
-                                                    "png encode: {}",
-                                                    e
-                                                )));
-                                            }
-                                        }
-                                        images.push(buf);
+                                        images.push(Self::encode_image(
+                                            &dyn_img,
+                                            content_type,
+                                            DEFAULT_JPEG_QUALITY,
+                                        )?);
                                     }
                                     Err(e) => return Err(Error::from(format!("to image: {}", e))),
                                 }
                             }
 
-                            if images.len() == 1 {
-                                let b64 =
-                                    base64::engine::general_purpose::STANDARD.encode(&images[0]);
-                                let mut metadata = serde_json::Map::new();
-                                metadata.insert(
-                                    "content_type".to_string(),
-                                    Value::String(content_type.to_string()),
-                                );
-                                metadata.insert("body_b64".to_string(), Value::String(b64));
-                                metadata.insert("is_single_frame".to_string(), Value::Bool(true));
-
-                                Self::set_dicomweb_data(
-                                    &mut envelope,
-                                    "wado_frames",
-                                    Value::Null,
-                                    Some(metadata),
-                                );
-                                return Ok(envelope);
-                            } else if !images.is_empty() {
-                                let boundary = format!("dicomweb_{}", uuid::Uuid::new_v4());
-                                let mut body: Vec<u8> = Vec::new();
-                                for img in images {
-                                    body.extend_from_slice(
-                                        format!("--{}\r\n", &boundary).as_bytes(),
-                                    );
-                                    body.extend_from_slice(
-                                        format!("Content-Type: {}\r\n\r\n", content_type)
-                                            .as_bytes(),
-                                    );
-                                    body.extend_from_slice(&img);
-                                    body.extend_from_slice(b"\r\n");
-                                }
-                                body.extend_from_slice(format!("--{}--\r\n", &boundary).as_bytes());
-                                let b64 = base64::engine::general_purpose::STANDARD.encode(&body);
-
-                                let mut metadata = serde_json::Map::new();
-                                metadata.insert(
-                                    "content_type".to_string(),
-                                    Value::String(content_type.to_string()),
-                                );
-                                metadata.insert("boundary".to_string(), Value::String(boundary));
-                                metadata.insert("body_b64".to_string(), Value::String(b64));
-                                metadata.insert("is_single_frame".to_string(), Value::Bool(false));
-
-                                Self::set_dicomweb_data(
-                                    &mut envelope,
-                                    "wado_frames",
-                                    Value::Null,
-                                    Some(metadata),
-                                );
+                            if !images.is_empty() {
+                                self.set_image_data(&mut envelope, content_type, images);
                                 return Ok(envelope);
                             }
                         }
                         Err(_e) => {
                             // Unsupported TS or decoding error - let endpoint handle the error response
-                            let mut metadata = serde_json::Map::new();
-                            metadata.insert(
-                                "error".to_string(),
-                                Value::String("UnsupportedTransferSyntax".to_string()),
-                            );
-                            metadata.insert(
-                                "message".to_string(),
-                                Value::String(
-                                    "Unable to decode frames for requested instance".to_string(),
-                                ),
-                            );
-
-                            Self::set_dicomweb_data(
-                                &mut envelope,
-                                "wado_frames_error",
-                                Value::Null,
-                                Some(metadata),
-                            );
+                            Self::set_frames_error(&mut envelope);
                             return Ok(envelope);
                         }
                     }
@@ -1040,6 +2893,204 @@ impl Middleware for DicomwebBridgeMiddleware {
     }
 }
 
+/// Parse configuration from HashMap for middleware registry
+pub fn parse_config(options: &HashMap<String, Value>) -> Result<DicomwebBridgeConfig, String> {
+    let strict_vr = match options.get("strict_vr") {
+        None => false,
+        Some(v) => v
+            .as_bool()
+            .ok_or("'strict_vr' in dicomweb_bridge middleware config must be a boolean")?,
+    };
+    let expand_partial_dates = match options.get("expand_partial_dates") {
+        None => true,
+        Some(v) => v.as_bool().ok_or(
+            "'expand_partial_dates' in dicomweb_bridge middleware config must be a boolean",
+        )?,
+    };
+
+    let allowed_query_tags = match options.get("allowed_query_tags") {
+        None => None,
+        Some(v) => Some(parse_tag_list(v, "allowed_query_tags")?),
+    };
+    let denied_query_tags = match options.get("denied_query_tags") {
+        None => Vec::new(),
+        Some(v) => parse_tag_list(v, "denied_query_tags")?,
+    };
+
+    let mut required_query_keys = HashMap::new();
+    if let Some(v) = options.get("required_query_keys") {
+        let levels = v.as_object().ok_or(
+            "'required_query_keys' in dicomweb_bridge middleware config must be a table of level to tags",
+        )?;
+        for (level, tags) in levels {
+            if !matches!(level.as_str(), "study" | "series" | "instance") {
+                return Err(format!(
+                    "Invalid required_query_keys level '{}'; expected 'study', 'series' or 'instance'",
+                    level
+                ));
+            }
+            let tags = parse_tag_list(tags, "required_query_keys")?;
+            if tags.is_empty() {
+                return Err(format!(
+                    "required_query_keys.{} must list at least one attribute",
+                    level
+                ));
+            }
+            required_query_keys.insert(level.clone(), tags);
+        }
+    }
+
+    let tag_inclusion = match options.get("tag_inclusion") {
+        None => TagInclusion::All,
+        Some(v) => TagInclusion::parse(
+            v.as_str()
+                .ok_or("'tag_inclusion' in dicomweb_bridge middleware config must be a string")?,
+        )?,
+    };
+
+    let existence_check_backend = match options.get("existence_check_backend") {
+        None => None,
+        Some(v) => Some(
+            v.as_str()
+                .filter(|s| !s.trim().is_empty())
+                .ok_or(
+                    "'existence_check_backend' in dicomweb_bridge middleware config must be a \
+                     non-empty backend name",
+                )?
+                .to_string(),
+        ),
+    };
+
+    let identifier_templates = match options.get("identifier_templates") {
+        None => Vec::new(),
+        Some(v) => v
+            .as_array()
+            .ok_or("'identifier_templates' in dicomweb_bridge middleware config must be an array")?
+            .iter()
+            .map(IdentifierTemplate::parse)
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let viewer_profile = match options.get("viewer_profile") {
+        None => ViewerProfile::None,
+        Some(v) => ViewerProfile::parse(
+            v.as_str()
+                .ok_or("'viewer_profile' in dicomweb_bridge middleware config must be a string")?,
+        )?,
+    };
+    let bulkdata_base_url = match options.get("bulkdata_base_url") {
+        None => None,
+        Some(v) => Some(
+            v.as_str()
+                .ok_or("'bulkdata_base_url' in dicomweb_bridge middleware config must be a string")?
+                .trim_end_matches('/')
+                .to_string(),
+        ),
+    };
+
+    let mut failure_status_codes = HashMap::new();
+    if let Some(v) = options.get("failure_status_codes") {
+        let entries = v.as_object().ok_or(
+            "'failure_status_codes' in dicomweb_bridge middleware config must be a table of category to HTTP status",
+        )?;
+        for (category, status) in entries {
+            let category = DimseFailureCategory::parse(category)?;
+            let status = status
+                .as_u64()
+                .and_then(|s| u16::try_from(s).ok())
+                .filter(|s| (400..=599).contains(s))
+                .ok_or_else(|| {
+                    format!(
+                        "failure_status_codes.{} must be an HTTP error status between 400 and 599",
+                        category.as_str()
+                    )
+                })?;
+            failure_status_codes.insert(category, status);
+        }
+    }
+
+    let normalize_paths = match options.get("normalize_paths") {
+        None => true,
+        Some(v) => v
+            .as_bool()
+            .ok_or("'normalize_paths' in dicomweb_bridge middleware config must be a boolean")?,
+    };
+
+    Ok(DicomwebBridgeConfig {
+        strict_vr,
+        expand_partial_dates,
+        allowed_query_tags,
+        denied_query_tags,
+        required_query_keys,
+        tag_inclusion,
+        existence_check_backend,
+        identifier_templates,
+        viewer_profile,
+        bulkdata_base_url,
+        failure_status_codes,
+        normalize_paths,
+    })
+}
+
+/// Run an instance-level C-FIND for the Study/Series/SOP Instance UIDs in `ident` and report
+/// whether the backend returned a match
+pub async fn instance_exists(
+    service: &dyn ServiceType<ReqBody = Value>,
+    backend_options: &HashMap<String, Value>,
+    ident: &serde_json::Map<String, Value>,
+) -> Result<bool, String> {
+    let mut query = serde_json::Map::new();
+    for tag in ["0020000D", "0020000E", "00080018"] {
+        if let Some(entry) = ident.get(tag) {
+            query.insert(tag.to_string(), entry.clone());
+        }
+    }
+
+    let envelope = RequestEnvelopeBuilder::new()
+        .method("POST")
+        .uri("/dicomweb_bridge/existence_check")
+        .metadata_entry("dimse_op", "find")
+        .metadata_entry("path", "instances")
+        .original_data(Vec::new())
+        .normalized_data(Some(json!({
+            "dimse_identifier": Value::Object(query),
+            "max_results": 1,
+        })))
+        .build()
+        .map_err(|e| format!("Failed to build existence check: {}", e))?;
+
+    let response = service
+        .backend_outgoing_request(envelope, backend_options)
+        .await
+        .map_err(|e| format!("Existence check C-FIND failed: {:?}", e))?;
+
+    let nd = response.normalized_data.unwrap_or(Value::Null);
+    if !nd.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Err(format!(
+            "Existence check C-FIND unsuccessful: {}",
+            nd.get("error").and_then(|v| v.as_str()).unwrap_or("unknown error")
+        ));
+    }
+    Ok(nd
+        .get("matches")
+        .and_then(|m| m.as_array())
+        .is_some_and(|m| !m.is_empty()))
+}
+
+/// Parse a list of DICOM keywords or hex tags into normalized hex tags
+fn parse_tag_list(value: &Value, key: &str) -> Result<Vec<String>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("'{}' in dicomweb_bridge middleware config must be an array", key))?
+        .iter()
+        .map(|v| {
+            v.as_str()
+                .map(DicomwebBridgeMiddleware::dicom_name_to_hex)
+                .ok_or_else(|| format!("All entries in '{}' must be strings", key))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1118,6 +3169,47 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_trailing_slash_and_case_route_to_studies_query() {
+        async fn left(
+            bridge: &DicomwebBridgeMiddleware,
+            path: &str,
+        ) -> RequestEnvelope<serde_json::Value> {
+            let envelope = RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri(format!("/dicomweb/{}", path))
+                .metadata_entry("path", path)
+                .original_data(serde_json::json!({}))
+                .build()
+                .unwrap();
+            bridge.left(envelope).await.unwrap()
+        }
+
+        let bridge = DicomwebBridgeMiddleware::new();
+        let expected = left(&bridge, "studies").await.normalized_data;
+        for path in ["studies/", "Studies", "/STUDIES/"] {
+            let result = left(&bridge, path).await;
+            assert_eq!(
+                result.request_details.metadata.get("dimse_op"),
+                Some(&"find".to_string()),
+                "{} should route to the studies query",
+                path
+            );
+            assert_eq!(result.normalized_data, expected, "{}", path);
+        }
+
+        assert_eq!(
+            normalize_dicomweb_path("/dicomweb/Studies/1.2.3/Series/4.5.6/Frames/1,2/"),
+            "/dicomweb/studies/1.2.3/series/4.5.6/frames/1,2"
+        );
+
+        let mut options = HashMap::new();
+        options.insert("normalize_paths".to_string(), serde_json::json!(false));
+        let strict = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+        let result = left(&strict, "Studies").await;
+        assert!(result.request_details.metadata.get("dimse_op").is_none());
+    }
+
     #[tokio::test]
     async fn test_left_no_includefield_uses_defaults() {
         let bridge = DicomwebBridgeMiddleware::new();
@@ -1507,6 +3599,222 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_pagination_single_page_from_three_studies() {
+        use crate::models::envelope::envelope::ResponseDetails;
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+
+        let mut query_params = HashMap::new();
+        query_params.insert("limit".to_string(), vec!["1".to_string()]);
+        query_params.insert("offset".to_string(), vec!["1".to_string()]);
+        let request = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies?limit=1&offset=1")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .metadata_entry("full_path", "/dicomweb/studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let bridge = DicomwebBridgeMiddleware::new();
+        let request = bridge.left(request).await.unwrap();
+        let envelope = ResponseEnvelope {
+            request_details: request.request_details.clone(),
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "find",
+                "success": true,
+                "matches": [
+                    {"0020000D": {"vr": "UI", "Value": ["1.1"]}},
+                    {"0020000D": {"vr": "UI", "Value": ["1.2"]}},
+                    {"0020000D": {"vr": "UI", "Value": ["1.3"]}},
+                ],
+            })),
+            normalized_snapshot: None,
+        };
+
+        let bridged = bridge.right(envelope).await.unwrap();
+        let nd = bridged.normalized_data.unwrap();
+        let data = nd["dicomweb_data"].as_array().unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0]["0020000D"]["Value"][0], "1.2");
+
+        let response = DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details: request.request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: Some(nd),
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("warning").unwrap(),
+            "299 harmony \"There are additional results that can be requested\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pagination_last_page_has_no_warning() {
+        let mut metadata = HashMap::new();
+        metadata.insert("full_path".to_string(), "/dicomweb/studies".to_string());
+        metadata.insert("dicomweb_limit".to_string(), "2".to_string());
+        metadata.insert("dicomweb_offset".to_string(), "1".to_string());
+        let envelope = ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: "/dicomweb/studies?limit=2&offset=1".to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata,
+            },
+            response_details: crate::models::envelope::envelope::ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "find",
+                "success": true,
+                "matches": [
+                    {"0020000D": {"vr": "UI", "Value": ["1.1"]}},
+                    {"0020000D": {"vr": "UI", "Value": ["1.2"]}},
+                    {"0020000D": {"vr": "UI", "Value": ["1.3"]}},
+                ],
+            })),
+            normalized_snapshot: None,
+        };
+
+        let result = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_data"].as_array().unwrap().len(), 2);
+        assert!(nd["dicomweb_metadata"].get("warnings").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_name_matches() {
+        assert!(fuzzy_name_matches("smith", "Smith^John"));
+        assert!(fuzzy_name_matches("SMYTH", "Smith^John"));
+        assert!(fuzzy_name_matches("smith john", "Smith^John"));
+        assert!(!fuzzy_name_matches("john smith", "Smith^John"));
+        assert!(fuzzy_name_matches("smi*", "Smith^John"));
+        assert!(!fuzzy_name_matches("smith", "Jones^Bob"));
+        assert!(!fuzzy_name_matches("smith^mary", "Smith^John"));
+    }
+
+    #[test]
+    fn test_fuzzy_name_wildcard_keeps_first_letter() {
+        let values = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(fuzzy_name_wildcard(&values(&["smyth"])), Some("S*".into()));
+        assert_eq!(
+            fuzzy_name_wildcard(&values(&["smith", "Smythe^J"])),
+            Some("S*".into())
+        );
+        assert_eq!(fuzzy_name_wildcard(&values(&["smith", "jones"])), None);
+        assert_eq!(fuzzy_name_wildcard(&values(&["*mith"])), None);
+        assert_eq!(fuzzy_name_wildcard(&values(&[""])), None);
+    }
+
+    fn name_query(params: &[(&str, &str)]) -> RequestEnvelope<Value> {
+        let query_params: HashMap<String, Vec<String>> = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+            .collect();
+        RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .metadata_entry("full_path", "/dicomweb/studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap()
+    }
+
+    async fn name_query_results(
+        bridge: &DicomwebBridgeMiddleware,
+        params: &[(&str, &str)],
+    ) -> Vec<Value> {
+        let request = bridge.left(name_query(params)).await.unwrap();
+        let envelope = ResponseEnvelope {
+            request_details: request.request_details,
+            response_details: crate::models::envelope::envelope::ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "find",
+                "success": true,
+                "matches": [
+                    {"00100010": {"vr": "PN", "Value": ["Smith^John"]}},
+                    {"00100010": {"vr": "PN", "Value": [{"Alphabetic": "SMYTHE^Jane"}]}},
+                    {"00100010": {"vr": "PN", "Value": ["Jones^Bob"]}},
+                ],
+            })),
+            normalized_snapshot: None,
+        };
+        let result = bridge.right(envelope).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        nd["dicomweb_data"].as_array().cloned().unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn test_fuzzymatching_filters_names_loosely() {
+        let bridge = DicomwebBridgeMiddleware::new();
+        let params = [("PatientName", "smith"), ("fuzzymatching", "true")];
+
+        let request = bridge.left(name_query(&params)).await.unwrap();
+        let nd = request.normalized_data.unwrap();
+        let name_key = &nd["dimse_identifier"]["00100010"];
+        assert_eq!(name_key["Value"], serde_json::json!(["S*"]));
+        assert!(!nd["dimse_identifier"].to_string().contains("smith"));
+
+        let results = name_query_results(&bridge, &params).await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["00100010"]["Value"][0], "Smith^John");
+    }
+
+    #[tokio::test]
+    async fn test_exact_name_matching_is_default() {
+        let bridge = DicomwebBridgeMiddleware::new();
+
+        for params in [
+            vec![("PatientName", "smith")],
+            vec![("PatientName", "smith"), ("fuzzymatching", "false")],
+        ] {
+            let request = bridge.left(name_query(&params)).await.unwrap();
+            assert!(!request
+                .request_details
+                .metadata
+                .contains_key("dicomweb_fuzzy_names"));
+            let nd = request.normalized_data.unwrap();
+            assert_eq!(nd["dimse_identifier"]["00100010"]["Value"][0], "smith");
+
+            // The SCP did the matching; every match it returned is passed through
+            assert_eq!(name_query_results(&bridge, &params).await.len(), 3);
+        }
+    }
+
     #[tokio::test]
     async fn test_date_range_query_passed_through() {
         let bridge = DicomwebBridgeMiddleware::new();
@@ -1587,4 +3895,1610 @@ mod tests {
         // Verify max_results = limit + offset
         assert_eq!(nd.get("max_results").and_then(|v| v.as_u64()), Some(15));
     }
+
+    #[tokio::test]
+    async fn test_known_tags_use_plain_vr_codes() {
+        let bridge = DicomwebBridgeMiddleware::new();
+
+        let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+        query_params.insert("PatientID".to_string(), vec!["12345".to_string()]);
+        query_params.insert("StudyDate".to_string(), vec!["20231015".to_string()]);
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        let identifier = nd.get("dimse_identifier").unwrap().as_object().unwrap();
+
+        let vr_of = |tag: &str| identifier.get(tag).unwrap()["vr"].as_str().unwrap().to_string();
+        assert_eq!(vr_of("00100020"), "LO");
+        assert_eq!(vr_of("00080020"), "DA");
+        // Default return keys use the same lookup
+        assert_eq!(vr_of("0020000D"), "UI");
+        assert_eq!(vr_of("00100010"), "PN");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tag_falls_back_to_lo_when_not_strict() {
+        let bridge = DicomwebBridgeMiddleware::new();
+
+        let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+        query_params.insert("NotARealAttribute".to_string(), vec!["x".to_string()]);
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        let identifier = nd.get("dimse_identifier").unwrap().as_object().unwrap();
+        assert_eq!(identifier["NOTAREALATTRIBUTE"]["vr"], "LO");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tag_rejected_in_strict_mode() {
+        let mut options = HashMap::new();
+        options.insert("strict_vr".to_string(), serde_json::json!(true));
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+        query_params.insert("NotARealAttribute".to_string(), vec!["x".to_string()]);
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        assert_eq!(
+            result.request_details.metadata.get("skip_backends"),
+            Some(&"true".to_string())
+        );
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "bad_request");
+        assert!(nd["dicomweb_metadata"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("NotARealAttribute"));
+        assert!(nd.get("dimse_identifier").is_none());
+    }
+
+    #[test]
+    fn test_parse_config_rejects_non_boolean_strict_vr() {
+        let mut options = HashMap::new();
+        options.insert("strict_vr".to_string(), serde_json::json!("yes"));
+        assert!(parse_config(&options).is_err());
+        assert!(!parse_config(&HashMap::new()).unwrap().strict_vr);
+    }
+
+    #[tokio::test]
+    async fn test_partial_study_date_expanded_to_range() {
+        let bridge = DicomwebBridgeMiddleware::new();
+
+        let mut query_params = HashMap::new();
+        query_params.insert("StudyDate".to_string(), vec!["202403".to_string()]);
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies?StudyDate=202403")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        let identifier = nd.get("dimse_identifier").unwrap().as_object().unwrap();
+        assert_eq!(
+            identifier["00080020"]["Value"][0].as_str(),
+            Some("20240301-20240331")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_partial_date_expansion_can_be_disabled() {
+        let mut options = HashMap::new();
+        options.insert("expand_partial_dates".to_string(), serde_json::json!(false));
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let mut query_params = HashMap::new();
+        query_params.insert("StudyDate".to_string(), vec!["2024".to_string()]);
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies?StudyDate=2024")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        let identifier = nd.get("dimse_identifier").unwrap().as_object().unwrap();
+        assert_eq!(identifier["00080020"]["Value"][0].as_str(), Some("2024"));
+    }
+
+    #[test]
+    fn test_expand_partial_date_value_forms() {
+        let expand = DicomwebBridgeMiddleware::expand_partial_date_value;
+        assert_eq!(expand("2024", "DA"), "20240101-20241231");
+        assert_eq!(expand("202402", "DA"), "20240201-20240229");
+        assert_eq!(expand("202302", "DA"), "20230201-20230228");
+        assert_eq!(expand("2024-", "DA"), "20240101-");
+        assert_eq!(expand("-202403", "DA"), "-20240331");
+        assert_eq!(expand("2023-202406", "DA"), "20230101-20240630");
+        assert_eq!(expand("20240315", "DA"), "20240315");
+        assert_eq!(expand("20240101-20240131", "DA"), "20240101-20240131");
+        assert_eq!(expand("202413", "DA"), "202413");
+        assert_eq!(expand("10", "TM"), "100000-105959");
+        assert_eq!(expand("1030-", "TM"), "103000-");
+        assert_eq!(expand("103000", "TM"), "103000");
+    }
+
+    #[tokio::test]
+    async fn test_denied_query_tag_rejected() {
+        let mut options = HashMap::new();
+        options.insert(
+            "denied_query_tags".to_string(),
+            serde_json::json!(["PatientName", "00100030"]),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+        query_params.insert("PatientName".to_string(), vec!["Doe^*".to_string()]);
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        assert_eq!(
+            result.request_details.metadata.get("skip_backends"),
+            Some(&"true".to_string())
+        );
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "bad_request");
+        let message = nd["dicomweb_metadata"]["message"].as_str().unwrap();
+        assert!(message.contains("PatientName"), "{}", message);
+        assert!(message.contains("00100010"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_required_query_keys_reject_unrestricted_study_query() {
+        let mut options = HashMap::new();
+        options.insert(
+            "required_query_keys".to_string(),
+            serde_json::json!({ "study": ["PatientID", "StudyDate"] }),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let study_query = |query_params: HashMap<String, Vec<String>>| {
+            RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri("/dicomweb/studies")
+                .query_params(query_params)
+                .metadata_entry("path", "studies")
+                .original_data(serde_json::json!({}))
+                .build()
+                .unwrap()
+        };
+
+        let mut unrestricted = HashMap::new();
+        unrestricted.insert("limit".to_string(), vec!["10".to_string()]);
+        unrestricted.insert("ModalitiesInStudy".to_string(), vec!["CT".to_string()]);
+        let result = bridge.left(study_query(unrestricted)).await.unwrap();
+        assert_eq!(
+            result.request_details.metadata.get("skip_backends"),
+            Some(&"true".to_string())
+        );
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "bad_request");
+        assert_eq!(nd["dicomweb_metadata"]["error"], "MatchKeyRequired");
+        let message = nd["dicomweb_metadata"]["message"].as_str().unwrap();
+        assert!(message.contains("PatientID, StudyDate"), "{}", message);
+
+        let mut restricted = HashMap::new();
+        restricted.insert(
+            "StudyDate".to_string(),
+            vec!["20240101-20240131".to_string()],
+        );
+        let result = bridge.left(study_query(restricted)).await.unwrap();
+        assert!(result
+            .request_details
+            .metadata
+            .get("skip_backends")
+            .is_none());
+
+        // A fuzzy name narrows the C-FIND to a first letter only
+        let mut options = HashMap::new();
+        options.insert(
+            "required_query_keys".to_string(),
+            serde_json::json!({ "study": ["PatientName"] }),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+        let mut fuzzy = HashMap::new();
+        fuzzy.insert("PatientName".to_string(), vec!["smith".to_string()]);
+        fuzzy.insert("fuzzymatching".to_string(), vec!["true".to_string()]);
+        let result = bridge.left(study_query(fuzzy)).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_metadata"]["error"], "MatchKeyRequired");
+
+        let mut exact = HashMap::new();
+        exact.insert("PatientName".to_string(), vec!["SMITH^JOHN".to_string()]);
+        let result = bridge.left(study_query(exact)).await.unwrap();
+        assert!(result
+            .request_details
+            .metadata
+            .get("skip_backends")
+            .is_none());
+
+        let mut bad = HashMap::new();
+        bad.insert(
+            "required_query_keys".to_string(),
+            serde_json::json!({ "patient": ["PatientID"] }),
+        );
+        assert!(parse_config(&bad).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failure_status_codes_map_out_of_resources() {
+        let mut options = HashMap::new();
+        options.insert(
+            "failure_status_codes".to_string(),
+            serde_json::json!({ "out_of_resources": 429 }),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let failed_find = |error: &str| {
+            let mut metadata: HashMap<String, String> = HashMap::new();
+            metadata.insert("path".to_string(), "studies".to_string());
+            metadata.insert("full_path".to_string(), "/dicomweb/studies".to_string());
+            ResponseEnvelope {
+                request_details: RequestDetails {
+                    method: "GET".to_string(),
+                    uri: "/dicomweb/studies".to_string(),
+                    headers: HashMap::new(),
+                    cookies: HashMap::new(),
+                    query_params: HashMap::new(),
+                    cache_status: None,
+                    metadata,
+                },
+                response_details: crate::models::envelope::envelope::ResponseDetails {
+                    status: 200,
+                    headers: HashMap::new(),
+                    metadata: HashMap::new(),
+                },
+                original_data: serde_json::json!({}),
+                normalized_data: Some(serde_json::json!({
+                    "operation": "find",
+                    "success": false,
+                    "error": error,
+                })),
+                normalized_snapshot: None,
+            }
+        };
+
+        let result = bridge
+            .right(failed_find(
+                "findscu failed: Status: 0xa700: Refused: Out of Resources",
+            ))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "dimse_failure");
+        assert_eq!(nd["dicomweb_metadata"]["http_status"], 429);
+        assert_eq!(nd["dicomweb_metadata"]["error"], "out_of_resources");
+
+        // Categories without an override keep their default
+        let result = bridge
+            .right(failed_find("findscu failed: Status: 0xc001"))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_metadata"]["http_status"], 502);
+
+        let mut bad = HashMap::new();
+        bad.insert(
+            "failure_status_codes".to_string(),
+            serde_json::json!({ "out_of_resources": 200 }),
+        );
+        assert!(parse_config(&bad).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_allowlist_restricts_query_tags() {
+        let mut options = HashMap::new();
+        options.insert(
+            "allowed_query_tags".to_string(),
+            serde_json::json!(["StudyDate", "ModalitiesInStudy"]),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let build = |param: &str| {
+            let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+            query_params.insert(param.to_string(), vec!["x".to_string()]);
+            RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri("/dicomweb/studies")
+                .query_params(query_params)
+                .metadata_entry("path", "studies")
+                .original_data(serde_json::json!({}))
+                .build()
+                .unwrap()
+        };
+
+        let allowed = bridge.left(build("ModalitiesInStudy")).await.unwrap();
+        assert!(allowed
+            .normalized_data
+            .unwrap()
+            .get("dimse_identifier")
+            .is_some());
+
+        let denied = bridge.left(build("PatientID")).await.unwrap();
+        let nd = denied.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "bad_request");
+
+        // Special DICOMweb parameters are not match keys and stay usable
+        let paged = bridge.left(build("limit")).await.unwrap();
+        assert!(paged.normalized_data.unwrap().get("dimse_identifier").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_identifier_template_maps_custom_param() {
+        let mut options = HashMap::new();
+        options.insert(
+            "identifier_templates".to_string(),
+            serde_json::json!([{
+                "path": "sites/{InstitutionName}/studies",
+                "params": { "mrn": "PatientID" },
+                "level": "study"
+            }]),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+
+        let mut query_params: HashMap<String, Vec<String>> = HashMap::new();
+        query_params.insert("mrn".to_string(), vec!["MRN-42".to_string()]);
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/sites/NORTH/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "sites/NORTH/studies")
+            .original_data(serde_json::json!({}))
+            .build()
+            .unwrap();
+
+        let result = bridge.left(envelope).await.unwrap();
+        assert_eq!(
+            result.request_details.metadata.get("dimse_op"),
+            Some(&"find".to_string())
+        );
+        let nd = result.normalized_data.unwrap();
+        let ident = nd["dimse_identifier"].as_object().unwrap();
+        assert_eq!(ident["00100020"]["Value"][0], "MRN-42");
+        assert_eq!(ident["00080080"]["Value"][0], "NORTH");
+        assert!(!ident.contains_key("MRN"), "alias must not become a tag");
+        // Study-level default return keys still apply
+        assert!(ident.contains_key("0020000D"));
+
+        let mut bad = HashMap::new();
+        bad.insert(
+            "identifier_templates".to_string(),
+            serde_json::json!([{ "path": "x/{NotAKeyword}" }]),
+        );
+        assert!(parse_config(&bad).is_err());
+    }
+
+    fn metadata_response(instances: Value) -> ResponseEnvelope<Value> {
+        let mut metadata: HashMap<String, String> = HashMap::new();
+        let path = "/dicomweb/studies/1.2.3/series/4.5.6/metadata";
+        metadata.insert("path".to_string(), path.to_string());
+        metadata.insert("full_path".to_string(), path.to_string());
+
+        ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: path.to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata,
+            },
+            response_details: crate::models::envelope::envelope::ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "get",
+                "success": true,
+                "instances": instances
+            })),
+            normalized_snapshot: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_strip_private_removes_private_elements_from_metadata() {
+        let instances = serde_json::json!([{
+            "0020000D": {"vr": "UI", "Value": ["1.2.3"]},
+            "00100020": {"vr": "LO", "Value": ["12345"]},
+            "00090010": {"vr": "LO", "Value": ["ACME 1.1"]},
+            "00091001": {"vr": "LO", "Value": ["vendor secret"]}
+        }]);
+
+        let default_bridge = DicomwebBridgeMiddleware::new();
+        let result = default_bridge
+            .right(metadata_response(instances.clone()))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert!(nd["dicomweb_data"][0].get("00091001").is_some());
+
+        let mut options = HashMap::new();
+        options.insert("tag_inclusion".to_string(), serde_json::json!("strip_private"));
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+        let result = bridge.right(metadata_response(instances)).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        let item = nd["dicomweb_data"][0].as_object().unwrap();
+
+        assert_eq!(nd["dicomweb_response_type"], "wado_metadata");
+        assert!(!item.contains_key("00090010"));
+        assert!(!item.contains_key("00091001"));
+        assert!(item.contains_key("0020000D"));
+        assert!(item.contains_key("00100020"));
+    }
+
+    #[tokio::test]
+    async fn test_pixeldata_returns_native_pixels_with_transfer_syntax() {
+        use crate::models::envelope::envelope::ResponseDetails;
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::meta::FileMetaTableBuilder;
+        use dicom_object::InMemDicomObject;
+
+        // Single-frame 2x2 8-bit monochrome image
+        let dir = tempfile::tempdir().unwrap();
+        let pixels = vec![0u8, 64, 128, 255];
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("7.8.9"),
+        ));
+        obj.put(DataElement::new(
+            tags::ROWS,
+            VR::US,
+            PrimitiveValue::from(2u16),
+        ));
+        obj.put(DataElement::new(
+            tags::COLUMNS,
+            VR::US,
+            PrimitiveValue::from(2u16),
+        ));
+        obj.put(DataElement::new(
+            tags::BITS_ALLOCATED,
+            VR::US,
+            PrimitiveValue::from(8u16),
+        ));
+        obj.put(DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OB,
+            PrimitiveValue::from(pixels.clone()),
+        ));
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax("1.2.840.10008.1.2.1")
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("7.8.9"),
+        )
+        .unwrap()
+        .write_to_file(dir.path().join("img.dcm"))
+        .unwrap();
+
+        let path = "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9/pixeldata";
+        let mut metadata = HashMap::new();
+        metadata.insert("path".to_string(), path.to_string());
+        metadata.insert("full_path".to_string(), path.to_string());
+        let request_details = RequestDetails {
+            method: "GET".to_string(),
+            uri: path.to_string(),
+            headers: HashMap::new(),
+            cookies: HashMap::new(),
+            query_params: HashMap::new(),
+            cache_status: None,
+            metadata,
+        };
+        let envelope = ResponseEnvelope {
+            request_details: request_details.clone(),
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "get",
+                "success": true,
+                "folder_path": dir.path().to_string_lossy(),
+            })),
+            normalized_snapshot: None,
+        };
+
+        let bridged = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+        let nd = bridged.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "wado_pixeldata");
+
+        let response = DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: Some(nd),
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/octet-stream; transfer-syntax=1.2.840.10008.1.2.1"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(!body.is_empty());
+        assert_eq!(body.as_ref(), pixels.as_slice());
+    }
+
+    /// Write an instance holding an encapsulated PDF and a 2x2 PixelData, and return a
+    /// retrieval response for `path` over the folder it sits in
+    fn bulkdata_response(dir: &Path, path: &str) -> ResponseEnvelope<Value> {
+        use crate::models::envelope::envelope::ResponseDetails;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::meta::FileMetaTableBuilder;
+        use dicom_object::InMemDicomObject;
+
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("7.8.9"),
+        ));
+        obj.put(DataElement::new(
+            tags::NUMBER_OF_FRAMES,
+            VR::IS,
+            PrimitiveValue::from("2"),
+        ));
+        obj.put(DataElement::new(
+            tags::ENCAPSULATED_DOCUMENT,
+            VR::OB,
+            PrimitiveValue::from(b"%PDF-1.4".to_vec()),
+        ));
+        obj.put(DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OB,
+            PrimitiveValue::from(vec![0u8, 64, 128, 255]),
+        ));
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax("1.2.840.10008.1.2.1")
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.104.1")
+                .media_storage_sop_instance_uid("7.8.9"),
+        )
+        .unwrap()
+        .write_to_file(dir.join("doc.dcm"))
+        .unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("full_path".to_string(), path.to_string());
+        ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: path.to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata,
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "get",
+                "success": true,
+                "folder_path": dir.to_string_lossy(),
+            })),
+            normalized_snapshot: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulkdata_returns_binary_attribute_as_octet_stream() {
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+
+        let dir = tempfile::tempdir().unwrap();
+        let envelope =
+            bulkdata_response(dir.path(), "/dicomweb/bulkdata/1.2.3/4.5.6/7.8.9/00420011");
+        let request_details = envelope.request_details.clone();
+
+        let bridged = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+        let nd = bridged.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "wado_bulkdata");
+
+        let response = DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: Some(nd),
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/octet-stream"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), b"%PDF-1.4");
+    }
+
+    #[tokio::test]
+    async fn test_bulkdata_multiple_tags_returns_multipart() {
+        let dir = tempfile::tempdir().unwrap();
+        let bridge =
+            DicomwebBridgeMiddleware::new().with_id_generator(IdGenerator::sequence("test-"));
+        let result = bridge
+            .right(bulkdata_response(
+                dir.path(),
+                "/dicomweb/bulkdata/1.2.3/4.5.6/7.8.9/00420011,00280008",
+            ))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        let meta = &nd["dicomweb_metadata"];
+        let boundary = meta["boundary"].as_str().unwrap();
+        assert!(boundary.starts_with("dicomweb_test-"));
+
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(meta["body_b64"].as_str().unwrap())
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        let part_type = "Content-Type: application/octet-stream";
+        assert_eq!(body.matches(part_type).count(), 2);
+        assert!(body.contains("%PDF-1.4"));
+        assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+    }
+
+    #[tokio::test]
+    async fn test_bulkdata_missing_tag_is_not_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = DicomwebBridgeMiddleware::new()
+            .right(bulkdata_response(
+                dir.path(),
+                "/dicomweb/bulkdata/1.2.3/4.5.6/7.8.9/00660023",
+            ))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "not_found");
+        assert!(nd["dicomweb_metadata"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("(0066,0023)"));
+    }
+
+    #[tokio::test]
+    async fn test_bulkdata_pixeldata_redirects_to_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = DicomwebBridgeMiddleware::new()
+            .right(bulkdata_response(
+                dir.path(),
+                "/dicomweb/bulkdata/1.2.3/4.5.6/7.8.9/7FE00010",
+            ))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "redirect");
+        assert_eq!(
+            nd["dicomweb_metadata"]["location"],
+            "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9/frames/1,2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_left_maps_bulkdata_uri_to_instance_get() {
+        let bridge = DicomwebBridgeMiddleware::new();
+        let request = |path: &str| {
+            RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri(format!("/dicomweb/{}", path))
+                .metadata_entry("path", path)
+                .original_data(serde_json::json!({}))
+                .build()
+                .unwrap()
+        };
+
+        let result = bridge
+            .left(request("bulkdata/1.2.3/4.5.6/7.8.9/00420011"))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.request_details.metadata.get("dimse_op"),
+            Some(&"get".to_string())
+        );
+        let nd = result.normalized_data.unwrap();
+        let identifier = nd["dimse_identifier"].as_object().unwrap();
+        assert_eq!(identifier["0020000D"]["Value"][0], "1.2.3");
+        assert_eq!(identifier["0020000E"]["Value"][0], "4.5.6");
+        assert_eq!(identifier["00080018"]["Value"][0], "7.8.9");
+
+        for bad in [
+            "bulkdata/1.2.3/4.5.6/7.8.9/XYZ",
+            "bulkdata/1.2.3/4.5.6/7.8.9/7FE00010,00420011",
+            "bulkdata/opaque-id",
+        ] {
+            let result = bridge.left(request(bad)).await.unwrap();
+            let nd = result.normalized_data.unwrap();
+            assert_eq!(nd["dicomweb_response_type"], "bad_request", "{}", bad);
+            assert_eq!(nd["dicomweb_metadata"]["error"], "InvalidBulkDataURI");
+        }
+    }
+
+    /// Rendered request over a folder holding one 4x2 8-bit MONOCHROME2 instance `7.8.9`
+    fn rendered_response(
+        dir: &Path,
+        path: &str,
+        query: &[(&str, &str)],
+        accept: Option<&str>,
+    ) -> ResponseEnvelope<Value> {
+        use crate::models::envelope::envelope::ResponseDetails;
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::meta::FileMetaTableBuilder;
+        use dicom_object::InMemDicomObject;
+
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("7.8.9"),
+        ));
+        obj.put(DataElement::new(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            VR::CS,
+            PrimitiveValue::from("MONOCHROME2"),
+        ));
+        for (tag, value) in [
+            (tags::SAMPLES_PER_PIXEL, 1u16),
+            (tags::ROWS, 2),
+            (tags::COLUMNS, 4),
+            (tags::BITS_ALLOCATED, 8),
+            (tags::BITS_STORED, 8),
+            (tags::HIGH_BIT, 7),
+            (tags::PIXEL_REPRESENTATION, 0),
+        ] {
+            obj.put(DataElement::new(tag, VR::US, PrimitiveValue::from(value)));
+        }
+        obj.put(DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OB,
+            PrimitiveValue::from(vec![0u8, 32, 64, 96, 128, 160, 192, 255]),
+        ));
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax("1.2.840.10008.1.2.1")
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7")
+                .media_storage_sop_instance_uid("7.8.9"),
+        )
+        .unwrap()
+        .write_to_file(dir.join("img.dcm"))
+        .unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("full_path".to_string(), path.to_string());
+        let mut headers = HashMap::new();
+        if let Some(accept) = accept {
+            headers.insert("accept".to_string(), accept.to_string());
+        }
+        ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: path.to_string(),
+                headers,
+                cookies: HashMap::new(),
+                query_params: query
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+                    .collect(),
+                cache_status: None,
+                metadata,
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "get",
+                "success": true,
+                "folder_path": dir.to_string_lossy(),
+            })),
+            normalized_snapshot: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rendered_instance_is_scaled_to_viewport() {
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = rendered_response(
+            dir.path(),
+            "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9/rendered",
+            &[
+                ("viewport", "16,8"),
+                ("quality", "75"),
+                ("window", "128,256"),
+            ],
+            None,
+        );
+        let request_details = envelope.request_details.clone();
+
+        let bridged = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+        let nd = bridged.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "wado_frames");
+
+        let response = DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: Some(nd),
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "image/jpeg"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let image = img::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+    }
+
+    #[tokio::test]
+    async fn test_rendered_frame_honours_png_accept() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = DicomwebBridgeMiddleware::new()
+            .right(rendered_response(
+                dir.path(),
+                "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9/frames/1/rendered",
+                &[("viewport", "8,8")],
+                Some("image/png"),
+            ))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        let meta = &nd["dicomweb_metadata"];
+        assert_eq!(meta["content_type"], "image/png");
+
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(meta["body_b64"].as_str().unwrap())
+            .unwrap();
+        // Aspect ratio is kept: a 4x2 frame fits an 8x8 viewport as 8x4
+        let image = img::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+    }
+
+    #[tokio::test]
+    async fn test_rendered_rejects_invalid_parameters() {
+        let dir = tempfile::tempdir().unwrap();
+        for query in [
+            [("viewport", "0,8")],
+            [("quality", "101")],
+            [("window", "40,400,sigmoid")],
+        ] {
+            let result = DicomwebBridgeMiddleware::new()
+                .right(rendered_response(
+                    dir.path(),
+                    "/dicomweb/studies/1.2.3/series/4.5.6/rendered",
+                    &query,
+                    None,
+                ))
+                .await
+                .unwrap();
+            let nd = result.normalized_data.unwrap();
+            assert_eq!(nd["dicomweb_response_type"], "bad_request", "{:?}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_left_maps_rendered_routes_to_get() {
+        let bridge = DicomwebBridgeMiddleware::new();
+        for path in [
+            "studies/1.2.3/series/4.5.6/rendered",
+            "studies/1.2.3/series/4.5.6/instances/7.8.9/rendered",
+            "studies/1.2.3/series/4.5.6/instances/7.8.9/frames/1/rendered",
+        ] {
+            let mut query_params = HashMap::new();
+            query_params.insert("viewport".to_string(), vec!["64,64".to_string()]);
+            let envelope = RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri(format!("/dicomweb/{}", path))
+                .query_params(query_params)
+                .metadata_entry("path", path)
+                .original_data(serde_json::json!({}))
+                .build()
+                .unwrap();
+
+            let result = bridge.left(envelope).await.unwrap();
+            assert_eq!(
+                result.request_details.metadata.get("dimse_op"),
+                Some(&"get".to_string()),
+                "{}",
+                path
+            );
+            let nd = result.normalized_data.unwrap();
+            let identifier = nd["dimse_identifier"].as_object().unwrap();
+            assert_eq!(identifier["0020000E"]["Value"][0], "4.5.6");
+            assert!(!identifier.contains_key("VIEWPORT"));
+        }
+    }
+
+    const WADO_URI_QUERY: [(&str, &str); 4] = [
+        ("requestType", "WADO"),
+        ("studyUID", "1.2.3"),
+        ("seriesUID", "4.5.6"),
+        ("objectUID", "7.8.9"),
+    ];
+
+    /// Run a WADO-URI response through the bridge and the DICOMweb endpoint
+    async fn wado_uri_response(dir: &Path, query: &[(&str, &str)]) -> axum::response::Response {
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+
+        let envelope = rendered_response(dir, "/dicomweb/wado", query, None);
+        let request_details = envelope.request_details.clone();
+        let bridged = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+        DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: bridged.normalized_data,
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wado_uri_retrieves_dicom_and_jpeg() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut query = WADO_URI_QUERY.to_vec();
+        query.push(("contentType", "application/dicom"));
+        let response = wado_uri_response(dir.path(), &query).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/dicom"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.to_vec(), fs::read(dir.path().join("img.dcm")).unwrap());
+
+        let mut query = WADO_URI_QUERY.to_vec();
+        query.extend([("contentType", "image/jpeg"), ("columns", "16")]);
+        let response = wado_uri_response(dir.path(), &query).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "image/jpeg"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(img::guess_format(&body).unwrap(), img::ImageFormat::Jpeg);
+        let image = img::load_from_memory(&body).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+    }
+
+    #[tokio::test]
+    async fn test_wado_uri_frame_past_number_of_frames_is_bad_request() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut query = WADO_URI_QUERY.to_vec();
+        query.push(("frameNumber", "1"));
+        let response = wado_uri_response(dir.path(), &query).await;
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // The instance is single-frame
+        let mut query = WADO_URI_QUERY.to_vec();
+        query.push(("frameNumber", "2"));
+        let response = wado_uri_response(dir.path(), &query).await;
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("frameNumber 2 is out of range"));
+    }
+
+    #[tokio::test]
+    async fn test_wado_uri_missing_parameters_are_rejected() {
+        let bridge = DicomwebBridgeMiddleware::new();
+        let request = |query: &[(&str, &str)]| {
+            RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri("/dicomweb/wado")
+                .query_params(
+                    query
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+                        .collect(),
+                )
+                .metadata_entry("path", "wado")
+                .original_data(serde_json::json!({}))
+                .build()
+                .unwrap()
+        };
+
+        let result = bridge.left(request(&WADO_URI_QUERY)).await.unwrap();
+        assert_eq!(
+            result.request_details.metadata.get("dimse_op"),
+            Some(&"get".to_string())
+        );
+        let nd = result.normalized_data.unwrap();
+        let identifier = nd["dimse_identifier"].as_object().unwrap();
+        assert_eq!(identifier.len(), 3);
+        assert_eq!(identifier["00080018"]["Value"][0], "7.8.9");
+
+        for missing in ["requestType", "studyUID", "seriesUID", "objectUID"] {
+            let query: Vec<_> = WADO_URI_QUERY
+                .into_iter()
+                .filter(|(k, _)| *k != missing)
+                .collect();
+            let result = bridge.left(request(&query)).await.unwrap();
+            assert_eq!(
+                result.request_details.metadata.get("skip_backends"),
+                Some(&"true".to_string())
+            );
+            let nd = result.normalized_data.unwrap();
+            assert_eq!(nd["dicomweb_response_type"], "bad_request");
+            assert!(nd["dicomweb_metadata"]["message"]
+                .as_str()
+                .unwrap()
+                .contains(missing));
+        }
+
+        let mut query = WADO_URI_QUERY.to_vec();
+        query.push(("contentType", "text/html"));
+        let nd = bridge
+            .left(request(&query))
+            .await
+            .unwrap()
+            .normalized_data
+            .unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "bad_request");
+    }
+
+    /// Write an instance without PixelData, which cannot be rendered
+    fn write_unrenderable_instance(path: &Path) {
+        use dicom_core::{DataElement, PrimitiveValue, VR};
+        use dicom_dictionary_std::tags;
+        use dicom_object::meta::FileMetaTableBuilder;
+        use dicom_object::InMemDicomObject;
+
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from("7.8.10"),
+        ));
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax("1.2.840.10008.1.2.1")
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.88.11")
+                .media_storage_sop_instance_uid("7.8.10"),
+        )
+        .unwrap()
+        .write_to_file(path)
+        .unwrap();
+    }
+
+    fn decode_thumbnail(result: ResponseEnvelope<Value>) -> img::DynamicImage {
+        let nd = result.normalized_data.unwrap();
+        let meta = &nd["dicomweb_metadata"];
+        assert_eq!(meta["content_type"], "image/jpeg");
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(meta["body_b64"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(img::guess_format(&body).unwrap(), img::ImageFormat::Jpeg);
+        img::load_from_memory(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_is_jpeg_of_requested_size() {
+        let bridge = DicomwebBridgeMiddleware::new();
+        let dir = tempfile::tempdir().unwrap();
+
+        // Default box is 128x128; the 4x2 image keeps its aspect ratio
+        let result = bridge
+            .right(rendered_response(
+                dir.path(),
+                "/dicomweb/studies/1.2.3/thumbnail",
+                &[],
+                Some("application/json"),
+            ))
+            .await
+            .unwrap();
+        let image = decode_thumbnail(result);
+        assert_eq!((image.width(), image.height()), (128, 64));
+
+        let result = bridge
+            .right(rendered_response(
+                dir.path(),
+                "/dicomweb/studies/1.2.3/series/4.5.6/thumbnail",
+                &[("viewport", "32,32")],
+                None,
+            ))
+            .await
+            .unwrap();
+        let image = decode_thumbnail(result);
+        assert_eq!((image.width(), image.height()), (32, 16));
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_skips_unrenderable_instances() {
+        let bridge = DicomwebBridgeMiddleware::new();
+        let dir = tempfile::tempdir().unwrap();
+        // Sorted after img.dcm, so this is the middle instance and tried first
+        write_unrenderable_instance(&dir.path().join("zz.dcm"));
+
+        let result = bridge
+            .right(rendered_response(
+                dir.path(),
+                "/dicomweb/studies/1.2.3/thumbnail",
+                &[],
+                None,
+            ))
+            .await
+            .unwrap();
+        let image = decode_thumbnail(result);
+        assert_eq!((image.width(), image.height()), (128, 64));
+
+        // Nothing left to fall back to
+        let result = bridge
+            .right(rendered_response(
+                dir.path(),
+                "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.10/thumbnail",
+                &[],
+                None,
+            ))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "not_found");
+    }
+
+    /// The single part of a `wado_instance` multipart body
+    fn single_instance_part(nd: &Value) -> Vec<u8> {
+        let meta = &nd["dicomweb_metadata"];
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(meta["body_b64"].as_str().unwrap())
+            .unwrap();
+        let closing = format!("\r\n--{}--\r\n", meta["boundary"].as_str().unwrap());
+        let start = body.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        body[start..body.len() - closing.len()].to_vec()
+    }
+
+    #[test]
+    fn test_requested_transfer_syntax() {
+        assert_eq!(
+            requested_transfer_syntax(
+                "multipart/related; type=\"application/dicom\"; transfer-syntax=1.2.840.10008.1.2.1"
+            ),
+            Some("1.2.840.10008.1.2.1".to_string())
+        );
+        assert_eq!(
+            requested_transfer_syntax(
+                "image/jpeg, application/dicom; transfer-syntax=\"1.2.840.10008.1.2\""
+            ),
+            Some("1.2.840.10008.1.2".to_string())
+        );
+        assert_eq!(
+            requested_transfer_syntax(
+                "multipart/related; type=\"application/dicom\"; transfer-syntax=*"
+            ),
+            None
+        );
+        assert_eq!(
+            requested_transfer_syntax("multipart/related; type=\"application/dicom\""),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_instance_transcoded_to_requested_transfer_syntax() {
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+        use dicom_dictionary_std::tags;
+
+        // Stored as Explicit VR Little Endian, requested as Implicit VR Little Endian
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = rendered_response(
+            dir.path(),
+            "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9",
+            &[],
+            Some(
+                "multipart/related; type=\"application/dicom\"; transfer-syntax=1.2.840.10008.1.2",
+            ),
+        );
+        let request_details = envelope.request_details.clone();
+
+        let bridged = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+        let nd = bridged.normalized_data.unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "wado_instance");
+
+        let part = single_instance_part(&nd);
+        assert_eq!(&part[128..132], b"DICM");
+        let obj = dicom_object::from_reader(&part[128..]).unwrap();
+        assert_eq!(
+            obj.meta().transfer_syntax().trim_end_matches('\0'),
+            "1.2.840.10008.1.2"
+        );
+        assert_eq!(
+            obj.element(tags::PIXEL_DATA)
+                .unwrap()
+                .to_bytes()
+                .unwrap()
+                .as_ref(),
+            &[0u8, 32, 64, 96, 128, 160, 192, 255]
+        );
+
+        let response = DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: Some(nd),
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+        let content_type = response.headers().get("content-type").unwrap();
+        assert!(content_type
+            .to_str()
+            .unwrap()
+            .contains("transfer-syntax=1.2.840.10008.1.2;"));
+    }
+
+    #[tokio::test]
+    async fn test_instance_as_stored_without_transfer_syntax() {
+        let dir = tempfile::tempdir().unwrap();
+        for accept in [
+            None,
+            Some("multipart/related; type=\"application/dicom\"; transfer-syntax=*"),
+            Some("multipart/related; type=\"application/dicom\"; transfer-syntax=1.2.840.10008.1.2.1"),
+        ] {
+            let result = DicomwebBridgeMiddleware::new()
+                .right(rendered_response(
+                    dir.path(),
+                    "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9",
+                    &[],
+                    accept,
+                ))
+                .await
+                .unwrap();
+            let nd = result.normalized_data.unwrap();
+            let stored = fs::read(dir.path().join("img.dcm")).unwrap();
+            assert_eq!(single_instance_part(&nd), stored, "{:?}", accept);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_transfer_syntax_is_not_acceptable() {
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::dicomweb::DicomwebEndpoint;
+
+        let dir = tempfile::tempdir().unwrap();
+        let envelope = rendered_response(
+            dir.path(),
+            "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9",
+            &[],
+            Some("multipart/related; type=\"application/dicom\"; transfer-syntax=1.2.3.4.5"),
+        );
+        let request_details = envelope.request_details.clone();
+        let bridged = DicomwebBridgeMiddleware::new()
+            .right(envelope)
+            .await
+            .unwrap();
+
+        let response = DicomwebEndpoint {}
+            .endpoint_outgoing_response(
+                ResponseEnvelope {
+                    request_details,
+                    response_details: bridged.response_details,
+                    original_data: vec![],
+                    normalized_data: bridged.normalized_data,
+                    normalized_snapshot: None,
+                },
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::NOT_ACCEPTABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "TransferSyntaxNotSupported");
+        assert!(body["message"].as_str().unwrap().contains("1.2.3.4.5"));
+    }
+
+    #[tokio::test]
+    async fn test_instance_multipart_uses_injected_boundary() {
+        use crate::models::envelope::envelope::ResponseDetails;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("instance.dcm"), b"DICM-BYTES").unwrap();
+
+        let bridge =
+            DicomwebBridgeMiddleware::new().with_id_generator(IdGenerator::sequence("test-"));
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "full_path".to_string(),
+            "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9".to_string(),
+        );
+        let envelope = ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9".to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata,
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(serde_json::json!({
+                "operation": "get",
+                "success": true,
+                "folder_path": dir.path().to_string_lossy(),
+            })),
+            normalized_snapshot: None,
+        };
+
+        let nd = bridge
+            .right(envelope)
+            .await
+            .unwrap()
+            .normalized_data
+            .unwrap();
+        assert_eq!(nd["dicomweb_response_type"], "wado_instance");
+        assert_eq!(nd["dicomweb_metadata"]["boundary"], "dicomweb_test-1");
+        let body = base64::engine::general_purpose::STANDARD
+            .decode(nd["dicomweb_metadata"]["body_b64"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(
+            body,
+            b"--dicomweb_test-1\r\nContent-Type: application/dicom\r\n\r\nDICM-BYTES\r\n--dicomweb_test-1--\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ohif_profile_adds_pixel_data_bulkdata_uri() {
+        let instances = serde_json::json!([{
+            "0020000D": {"vr": "UI", "Value": ["1.2.3"]},
+            "0020000E": {"vr": "UI", "Value": ["4.5.6"]},
+            "00080018": {"vr": "UI", "Value": ["7.8.9"]},
+            "00280010": {"vr": "US", "Value": [512]},
+            "00280011": {"vr": "US", "Value": [512]},
+            "00280100": {"vr": "US", "Value": [16]}
+        }]);
+
+        let default_bridge = DicomwebBridgeMiddleware::new();
+        let result = default_bridge
+            .right(metadata_response(instances.clone()))
+            .await
+            .unwrap();
+        assert!(result.normalized_data.unwrap()["dicomweb_data"][0]
+            .get("7FE00010")
+            .is_none());
+
+        let mut options = HashMap::new();
+        options.insert("viewer_profile".to_string(), serde_json::json!("ohif"));
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+        let result = bridge
+            .right(metadata_response(instances.clone()))
+            .await
+            .unwrap();
+        let nd = result.normalized_data.unwrap();
+        let item = &nd["dicomweb_data"][0];
+        assert_eq!(item["7FE00010"]["vr"], "OW");
+        assert_eq!(
+            item["7FE00010"]["BulkDataURI"],
+            "/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9/frames/1"
+        );
+        assert_eq!(item["00280008"]["Value"][0], 1);
+
+        options.insert(
+            "bulkdata_base_url".to_string(),
+            serde_json::json!("https://pacs.example.org/dicomweb/"),
+        );
+        let bridge = DicomwebBridgeMiddleware::with_config(parse_config(&options).unwrap());
+        let result = bridge.right(metadata_response(instances)).await.unwrap();
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(
+            nd["dicomweb_data"][0]["7FE00010"]["BulkDataURI"],
+            "https://pacs.example.org/dicomweb/studies/1.2.3/series/4.5.6/instances/7.8.9/frames/1"
+        );
+    }
+
+    #[test]
+    fn test_tag_inclusion_classification() {
+        assert!(TagInclusion::StripPrivate.excludes("00291010"));
+        assert!(!TagInclusion::StripPrivate.excludes("00100010"));
+        assert!(TagInclusion::StripRetired.excludes("00200030"));
+        assert!(TagInclusion::StripRetired.excludes("40080100"));
+        assert!(!TagInclusion::StripRetired.excludes("00200032"));
+        assert!(!TagInclusion::All.excludes("00291010"));
+
+        let mut options = HashMap::new();
+        options.insert("tag_inclusion".to_string(), serde_json::json!("strip_everything"));
+        assert!(parse_config(&options).is_err());
+    }
+
+    #[test]
+    fn test_retired_tags_match_standard_dictionary() {
+        use dicom_dictionary_std::tags;
+
+        for (tag_hex, keyword) in RETIRED_TAGS {
+            let (group, element) = tag_parts(tag_hex).unwrap();
+            let entry = StandardDataDictionary
+                .by_tag(Tag(group, element))
+                .unwrap_or_else(|| panic!("{} is not in the dictionary", tag_hex));
+            assert_eq!(entry.alias, *keyword, "{}", tag_hex);
+            assert!(is_retired_tag(tag_hex), "{}", tag_hex);
+        }
+
+        // The current attributes that replaced listed ones are kept
+        for current in [
+            tags::IMAGE_POSITION_PATIENT,
+            tags::IMAGE_ORIENTATION_PATIENT,
+            tags::OTHER_PATIENT_I_DS_SEQUENCE,
+        ] {
+            let tag_hex = format!("{:04X}{:04X}", current.group(), current.element());
+            assert!(!is_retired_tag(&tag_hex), "{}", tag_hex);
+        }
+    }
+
+    fn instance_ident(study: &str, series: &str, instance: &str) -> serde_json::Map<String, Value> {
+        let mut ident = serde_json::Map::new();
+        ident.insert("0020000D".to_string(), json!({"vr": "UI", "Value": [study]}));
+        ident.insert("0020000E".to_string(), json!({"vr": "UI", "Value": [series]}));
+        ident.insert("00080018".to_string(), json!({"vr": "UI", "Value": [instance]}));
+        ident
+    }
+
+    #[tokio::test]
+    async fn test_instance_exists_against_mock() {
+        use crate::models::services::types::mock_dicom::{MockDicomData, MockDicomEndpoint};
+
+        let data = MockDicomData::instance();
+        let series = &data.series[0];
+        let present = instance_ident(
+            &data.study_uid,
+            &series.series_uid,
+            &series.instances[0].instance_uid,
+        );
+        let missing = instance_ident(&data.study_uid, &series.series_uid, "9.9.9.9");
+
+        assert!(instance_exists(&MockDicomEndpoint, &HashMap::new(), &present)
+            .await
+            .unwrap());
+        assert!(!instance_exists(&MockDicomEndpoint, &HashMap::new(), &missing)
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn test_parse_config_existence_check_backend() {
+        assert!(parse_config(&HashMap::new())
+            .unwrap()
+            .existence_check_backend
+            .is_none());
+
+        let mut options = HashMap::new();
+        options.insert("existence_check_backend".to_string(), json!("pacs"));
+        assert_eq!(
+            parse_config(&options).unwrap().existence_check_backend.as_deref(),
+            Some("pacs")
+        );
+
+        options.insert("existence_check_backend".to_string(), json!(""));
+        assert!(parse_config(&options).is_err());
+    }
 }
@@ -0,0 +1,286 @@
+use dicom_dictionary_std::tags;
+use dicom_object::OpenFileOptions;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Default transfer syntax preference: uncompressed first, then lossless compression.
+/// Syntaxes not listed (e.g. lossy JPEG) rank after every listed one.
+pub const DEFAULT_TRANSFER_SYNTAX_PREFERENCE: &[&str] = &[
+    "1.2.840.10008.1.2.1",     // Explicit VR Little Endian
+    "1.2.840.10008.1.2",       // Implicit VR Little Endian
+    "1.2.840.10008.1.2.1.99",  // Deflated Explicit VR Little Endian
+    "1.2.840.10008.1.2.2",     // Explicit VR Big Endian (retired)
+    "1.2.840.10008.1.2.4.70",  // JPEG Lossless, SV1
+    "1.2.840.10008.1.2.4.57",  // JPEG Lossless
+    "1.2.840.10008.1.2.4.80",  // JPEG-LS Lossless
+    "1.2.840.10008.1.2.4.90",  // JPEG 2000 Lossless Only
+    "1.2.840.10008.1.2.5",     // RLE Lossless
+    "1.2.840.10008.1.2.4.201", // HTJ2K Lossless
+];
+
+/// How duplicate instances in a retrieved folder are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupMode {
+    /// Keep every file (default)
+    #[default]
+    None,
+    /// One file per SOPInstanceUID, chosen by transfer syntax preference
+    SopInstance,
+}
+
+impl DedupMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "none" => Ok(DedupMode::None),
+            "sop_instance" => Ok(DedupMode::SopInstance),
+            other => Err(format!(
+                "Invalid dedup mode '{}'; expected 'none' or 'sop_instance'",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupConfig {
+    pub mode: DedupMode,
+    /// Transfer syntax UIDs, most preferred first
+    pub transfer_syntax_preference: Vec<String>,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            mode: DedupMode::None,
+            transfer_syntax_preference: DEFAULT_TRANSFER_SYNTAX_PREFERENCE
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl DedupConfig {
+    /// Parse `dedup` and `transfer_syntax_preference` from middleware options
+    pub fn from_options(options: &HashMap<String, Value>) -> Result<Self, String> {
+        let mut config = DedupConfig::default();
+        if let Some(mode) = options.get("dedup") {
+            let mode = mode
+                .as_str()
+                .ok_or("'dedup' must be a string ('none' or 'sop_instance')")?;
+            config.mode = DedupMode::parse(mode)?;
+        }
+        if let Some(pref) = options.get("transfer_syntax_preference") {
+            let list = pref
+                .as_array()
+                .ok_or("'transfer_syntax_preference' must be an array of transfer syntax UIDs")?;
+            let mut uids = Vec::with_capacity(list.len());
+            for entry in list {
+                let uid = entry
+                    .as_str()
+                    .ok_or("'transfer_syntax_preference' entries must be strings")?;
+                if uid.is_empty() || !uid.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                    return Err(format!("Invalid transfer syntax UID '{}'", uid));
+                }
+                uids.push(uid.to_string());
+            }
+            config.transfer_syntax_preference = uids;
+        }
+        Ok(config)
+    }
+
+    /// Lower is better; unlisted syntaxes share the worst rank
+    fn rank(&self, transfer_syntax: &str) -> usize {
+        self.transfer_syntax_preference
+            .iter()
+            .position(|ts| ts == transfer_syntax)
+            .unwrap_or(self.transfer_syntax_preference.len())
+    }
+}
+
+/// Outcome of a dedup pass over a folder
+#[derive(Debug, Default)]
+pub struct DedupReport {
+    pub kept: usize,
+    pub removed: Vec<PathBuf>,
+}
+
+struct Candidate {
+    path: PathBuf,
+    transfer_syntax: String,
+}
+
+/// Read SOPInstanceUID and transfer syntax from the file meta group without loading pixel data
+fn read_identity(path: &Path) -> Option<(String, String)> {
+    let obj = OpenFileOptions::new()
+        .read_until(tags::PIXEL_DATA)
+        .open_file(path)
+        .ok()?;
+    let transfer_syntax = obj
+        .meta()
+        .transfer_syntax()
+        .trim_end_matches('\0')
+        .to_string();
+    let sop_uid = obj
+        .element(tags::SOP_INSTANCE_UID)
+        .ok()
+        .and_then(|e| e.to_str().ok())
+        .map(|s| s.trim_end_matches('\0').trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| {
+            obj.meta()
+                .media_storage_sop_instance_uid()
+                .trim_end_matches('\0')
+                .to_string()
+        });
+    if sop_uid.is_empty() {
+        return None;
+    }
+    Some((sop_uid, transfer_syntax))
+}
+
+/// Remove alternative encodings of the same SOP Instance from `folder`, keeping the copy whose
+/// transfer syntax ranks best. Files that cannot be parsed as DICOM are left alone.
+pub fn dedup_folder(folder: &Path, config: &DedupConfig) -> Result<DedupReport, String> {
+    let mut report = DedupReport::default();
+    if config.mode == DedupMode::None {
+        return Ok(report);
+    }
+
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    // Deterministic tie-break between equally ranked copies
+    files.sort();
+
+    let mut by_sop: HashMap<String, Vec<Candidate>> = HashMap::new();
+    for path in files {
+        match read_identity(&path) {
+            Some((sop_uid, transfer_syntax)) => {
+                by_sop.entry(sop_uid).or_default().push(Candidate {
+                    path,
+                    transfer_syntax,
+                })
+            }
+            None => report.kept += 1,
+        }
+    }
+
+    for (sop_uid, mut candidates) in by_sop {
+        // Stable sort keeps path order among equal ranks
+        candidates.sort_by_key(|c| config.rank(&c.transfer_syntax));
+        let mut iter = candidates.into_iter();
+        if let Some(winner) = iter.next() {
+            report.kept += 1;
+            for dup in iter {
+                tracing::debug!(
+                    "Dedup {}: keeping {} ({}), removing {} ({})",
+                    sop_uid,
+                    winner.path.display(),
+                    winner.transfer_syntax,
+                    dup.path.display(),
+                    dup.transfer_syntax
+                );
+                std::fs::remove_file(&dup.path).map_err(|e| {
+                    format!("Failed to remove duplicate {}: {}", dup.path.display(), e)
+                })?;
+                report.removed.push(dup.path);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+    use dicom_object::meta::FileMetaTableBuilder;
+    use dicom_object::InMemDicomObject;
+
+    const EXPLICIT_LE: &str = "1.2.840.10008.1.2.1";
+    const JPEG_BASELINE: &str = "1.2.840.10008.1.2.4.50";
+
+    fn write_instance(path: &Path, sop_uid: &str, transfer_syntax: &str) {
+        let mut obj = InMemDicomObject::new_empty();
+        obj.put(DataElement::new(
+            tags::SOP_CLASS_UID,
+            VR::UI,
+            PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7"),
+        ));
+        obj.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(sop_uid),
+        ));
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax(transfer_syntax)
+                .media_storage_sop_class_uid("1.2.840.10008.5.1.4.1.1.7"),
+        )
+        .expect("meta")
+        .write_to_file(path)
+        .expect("write dicom");
+    }
+
+    fn folder_with_two_encodings() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().expect("tempdir");
+        write_instance(&dir.path().join("a.dcm"), "1.2.3.4.5", JPEG_BASELINE);
+        write_instance(&dir.path().join("b.dcm"), "1.2.3.4.5", EXPLICIT_LE);
+        write_instance(&dir.path().join("c.dcm"), "1.2.3.4.6", JPEG_BASELINE);
+        dir
+    }
+
+    #[test]
+    fn test_default_preference_keeps_uncompressed_copy() {
+        let dir = folder_with_two_encodings();
+        let config = DedupConfig {
+            mode: DedupMode::SopInstance,
+            ..DedupConfig::default()
+        };
+
+        let report = dedup_folder(dir.path(), &config).unwrap();
+
+        assert_eq!(report.kept, 2);
+        assert_eq!(report.removed, vec![dir.path().join("a.dcm")]);
+        assert!(dir.path().join("b.dcm").exists());
+        assert!(dir.path().join("c.dcm").exists());
+    }
+
+    #[test]
+    fn test_configured_preference_is_honoured() {
+        let dir = folder_with_two_encodings();
+        let mut options = HashMap::new();
+        options.insert("dedup".to_string(), serde_json::json!("sop_instance"));
+        options.insert(
+            "transfer_syntax_preference".to_string(),
+            serde_json::json!([JPEG_BASELINE, EXPLICIT_LE]),
+        );
+        let config = DedupConfig::from_options(&options).unwrap();
+
+        let report = dedup_folder(dir.path(), &config).unwrap();
+
+        assert_eq!(report.removed, vec![dir.path().join("b.dcm")]);
+        assert!(dir.path().join("a.dcm").exists());
+    }
+
+    #[test]
+    fn test_mode_none_leaves_folder_untouched() {
+        let dir = folder_with_two_encodings();
+        let report = dedup_folder(dir.path(), &DedupConfig::default()).unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_invalid_mode_rejected() {
+        let mut options = HashMap::new();
+        options.insert("dedup".to_string(), serde_json::json!("bytes"));
+        assert!(DedupConfig::from_options(&options).is_err());
+    }
+}
@@ -1,24 +1,141 @@
-use crate::globals::get_storage;
+use crate::globals::{get_storage, shutdown_token};
+use crate::log_context::OperationContext;
 use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
 use crate::models::middleware::middleware::Middleware;
+use crate::models::middleware::types::dicomweb_bridge::DicomwebBridgeMiddleware;
+use crate::models::middleware::types::instance_dedup::{dedup_folder, DedupConfig, DedupMode};
 use crate::models::middleware::types::jmix_index::{
     current_timestamp, get_jmix_index, JmixPackageInfo,
 };
+use crate::storage::{FilesystemStorage, StorageBackend};
 use crate::utils::Error;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 /// Middleware that builds JMIX envelopes from DICOM operation responses
 ///
 /// Right-side behavior:
 /// - Detects DICOM "move"/"get" responses that include folder_path/folder_id and instances
 /// - Creates a JMIX package under storage: jmix-store/<id>.jmix
+/// - Optionally drops alternative encodings of the same SOP Instance (see `dedup`)
+/// - Optionally removes DICOM attributes from the package metadata (see `metadata_filter`)
 /// - Copies DICOM files from the folder into payload/
 /// - Writes a minimal manifest.json and payload/metadata.json
 /// - Sets normalized_data.response.json with the created JMIX envelope IDs so JMIX service can return them
-pub struct JmixBuilderMiddleware;
+/// - Gives up on builds exceeding `build_timeout_secs` (504) or interrupted by shutdown (503),
+///   removing any partially written package
+pub struct JmixBuilderMiddleware {
+    config: JmixBuilderConfig,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JmixBuilderConfig {
+    /// Duplicate handling for retrieved instances before packaging
+    pub dedup: DedupConfig,
+    /// Abandon builds that run longer than this; unbounded when unset
+    pub build_timeout_secs: Option<u64>,
+    /// DICOM attributes kept out of metadata.json
+    pub metadata_filter: MetadataTagFilter,
+}
+
+/// Attributes jmix-rs copies into metadata.json, as hex tags: PatientID, PatientName,
+/// PatientBirthDate, PatientSex, StudyDescription, StudyInstanceUID, SeriesInstanceUID,
+/// Modality and BodyPartExamined
+const METADATA_ATTRIBUTES: [&str; 9] = [
+    "00100020", "00100010", "00100030", "00100040", "00081030", "0020000D", "0020000E", "00080060",
+    "00180015",
+];
+
+/// Which DICOM attributes may appear in a package's metadata.json
+#[derive(Debug, Clone, Default)]
+pub struct MetadataTagFilter {
+    /// Attributes to drop, as uppercase hex tags from [`METADATA_ATTRIBUTES`]
+    pub exclude: Vec<String>,
+}
+
+impl MetadataTagFilter {
+    fn is_active(&self) -> bool {
+        !self.exclude.is_empty()
+    }
+
+    fn excludes(&self, tag_hex: &str) -> bool {
+        self.exclude.iter().any(|t| t == tag_hex)
+    }
+
+    /// Clear the metadata.json fields taken from excluded attributes
+    ///
+    /// jmix-rs fills metadata.json from a fixed set of attributes. Optional fields are
+    /// dropped; the patient ID, series UID and modality are required and left empty instead.
+    fn apply(&self, metadata: &mut jmix_rs::types::Metadata) {
+        let patient = &mut metadata.patient;
+        if self.excludes("00100020") {
+            patient.id.clear();
+        }
+        if self.excludes("00100010") {
+            patient.name = None;
+        }
+        if self.excludes("00100030") {
+            patient.dob = None;
+        }
+        if self.excludes("00100040") {
+            patient.sex = None;
+        }
+        let Some(studies) = metadata.studies.as_mut() else {
+            return;
+        };
+        if self.excludes("00081030") {
+            studies.study_description = None;
+        }
+        if self.excludes("0020000D") {
+            studies.study_uid = None;
+        }
+        for series in studies.series.iter_mut().flatten() {
+            if self.excludes("0020000E") {
+                series.series_uid.clear();
+            }
+            if self.excludes("00080060") {
+                series.modality.clear();
+            }
+            if self.excludes("00180015") {
+                series.body_part = None;
+            }
+        }
+    }
+
+    fn from_options(options: &HashMap<String, Value>) -> Result<Self, String> {
+        let mut filter = MetadataTagFilter::default();
+        if let Some(v) = options.get("metadata_exclude_tags") {
+            let list = v.as_array().ok_or(
+                "'metadata_exclude_tags' in jmix_builder middleware config must be an array of tags",
+            )?;
+            for entry in list {
+                let name = entry
+                    .as_str()
+                    .ok_or("'metadata_exclude_tags' entries must be strings")?;
+                let tag_hex = DicomwebBridgeMiddleware::dicom_name_to_hex(name.trim());
+                if tag_hex.len() != 8 || !tag_hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!(
+                        "Unknown DICOM attribute '{}' in metadata_exclude_tags",
+                        name
+                    ));
+                }
+                if !METADATA_ATTRIBUTES.contains(&tag_hex.as_str()) {
+                    return Err(format!(
+                        "metadata.json has no field for '{}' in metadata_exclude_tags",
+                        name
+                    ));
+                }
+                filter.exclude.push(tag_hex);
+            }
+        }
+        Ok(filter)
+    }
+}
 
 impl Default for JmixBuilderMiddleware {
     fn default() -> Self {
@@ -28,8 +145,110 @@ impl Default for JmixBuilderMiddleware {
 
 impl JmixBuilderMiddleware {
     pub fn new() -> Self {
-        Self
+        Self::with_config(JmixBuilderConfig::default())
+    }
+
+    pub fn with_config(config: JmixBuilderConfig) -> Self {
+        Self { config }
+    }
+}
+
+/// Parse configuration from HashMap for middleware registry
+pub fn parse_config(options: &HashMap<String, Value>) -> Result<JmixBuilderConfig, String> {
+    let build_timeout_secs = match options.get("build_timeout_secs") {
+        None => None,
+        Some(v) => match v.as_u64() {
+            Some(secs) if secs > 0 => Some(secs),
+            _ => return Err(
+                "'build_timeout_secs' in jmix_builder middleware config must be a positive integer"
+                    .to_string(),
+            ),
+        },
+    };
+    Ok(JmixBuilderConfig {
+        dedup: DedupConfig::from_options(options)?,
+        build_timeout_secs,
+        metadata_filter: MetadataTagFilter::from_options(options)?,
+    })
+}
+
+/// What a finished build hands back to the middleware
+struct BuiltPackage {
+    jmix_id: String,
+    pkg_dir: PathBuf,
+    study_uid: Option<String>,
+}
+
+type BuildTask = JoinHandle<Result<BuiltPackage, String>>;
+
+enum BuildOutcome {
+    Finished(Result<BuiltPackage, String>),
+    TimedOut(Duration),
+    Cancelled,
+}
+
+/// Blocking builds cannot be interrupted; once the abandoned task completes,
+/// whatever package it wrote is removed so no half-indexed output lingers
+fn discard_when_finished(task: BuildTask) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    runtime.spawn(async move {
+        if let Ok(Ok(built)) = task.await {
+            match fs::remove_dir_all(&built.pkg_dir) {
+                Ok(_) => tracing::info!(
+                    "🧹 Removed abandoned JMIX package {}",
+                    built.pkg_dir.display()
+                ),
+                Err(e) => tracing::warn!(
+                    "⚠️ Failed to remove abandoned JMIX package {}: {}",
+                    built.pkg_dir.display(),
+                    e
+                ),
+            }
+        }
+    });
+}
+
+/// Owns an in-flight build; dropping it before completion (e.g. the client
+/// went away and the request future was dropped) discards the output
+struct PendingBuild(Option<BuildTask>);
+
+impl Drop for PendingBuild {
+    fn drop(&mut self) {
+        if let Some(task) = self.0.take() {
+            discard_when_finished(task);
+        }
+    }
+}
+
+async fn await_build(
+    task: BuildTask,
+    timeout: Option<Duration>,
+    shutdown: CancellationToken,
+) -> BuildOutcome {
+    let mut pending = PendingBuild(Some(task));
+    let deadline = async {
+        match timeout {
+            Some(limit) => tokio::time::sleep(limit).await,
+            None => std::future::pending().await,
+        }
+    };
+    let outcome = {
+        let task = pending.0.as_mut().expect("build task present");
+        tokio::select! {
+            joined = task => BuildOutcome::Finished(
+                joined.unwrap_or_else(|e| Err(format!("jmix build task failed: {}", e))),
+            ),
+            _ = deadline => BuildOutcome::TimedOut(timeout.unwrap_or_default()),
+            _ = shutdown.cancelled() => BuildOutcome::Cancelled,
+        }
+    };
+    if let BuildOutcome::Finished(_) = outcome {
+        // Nothing left to clean up
+        pending.0 = None;
     }
+    outcome
 }
 
 #[async_trait::async_trait]
@@ -358,6 +577,29 @@ impl Middleware for JmixBuilderMiddleware {
             None => return Ok(envelope),
         };
 
+        // Nothing was retrieved (e.g. the study no longer exists upstream); there is nothing to package
+        if !folder_has_files(Path::new(&folder_path)).await {
+            tracing::warn!(
+                "📦 DICOM {} returned no instances in {}, skipping JMIX build",
+                operation,
+                folder_path
+            );
+            return Ok(envelope);
+        }
+
+        // The same instance may arrive from several sources in different transfer syntaxes
+        if self.config.dedup.mode != DedupMode::None {
+            let report = dedup_folder(Path::new(&folder_path), &self.config.dedup)
+                .map_err(Error::from)?;
+            if !report.removed.is_empty() {
+                tracing::info!(
+                    "📦 Dedup kept {} instance(s), removed {} alternative encoding(s)",
+                    report.kept,
+                    report.removed.len()
+                );
+            }
+        }
+
         // Create JMIX package using jmix-rs builder (manifest.json, metadata.json, files.json)
         let store_root = ensure_store_root().map_err(Error::from)?;
 
@@ -392,43 +634,87 @@ impl Middleware for JmixBuilderMiddleware {
             envelope.request_details.metadata.keys().collect::<Vec<_>>()
         );
 
-        // Build envelope from the DICOM folder path provided by the DIMSE backend
-        let builder = jmix_rs::builder::JmixBuilder::new();
-        let (envelope_built, dicom_files) = builder
-            .build_from_dicom_with_options(&folder_path, &jcfg, skip_hashing, skip_listing)
-            .map_err(|e| Error::from(format!("jmix build error: {}", e)))?;
-
-        // Create package-specific directory for this envelope
-        let jmix_id = envelope_built.manifest.id.clone();
-        let pkg_dir = store_root.join(&jmix_id);
-
-        // Ensure the package directory exists
-        fs::create_dir_all(&pkg_dir).map_err(|e| {
-            Error::from(format!(
-                "Failed to create package dir {}: {}",
-                pkg_dir.display(),
-                e
-            ))
-        })?;
-
-        // Persist envelope to the specific package directory
-        let _saved = builder
-            .save_to_files_with_options(
+        // Build and persist off the async runtime; jmix-rs hashes and zips synchronously
+        let build_folder = folder_path.clone();
+        let build_root = store_root.clone();
+        let metadata_filter = self.config.metadata_filter.clone();
+        let task: BuildTask = tokio::task::spawn_blocking(move || {
+            // Build envelope from the DICOM folder path provided by the DIMSE backend
+            let builder = jmix_rs::builder::JmixBuilder::new();
+            let (mut envelope_built, dicom_files) = builder
+                .build_from_dicom_with_options(&build_folder, &jcfg, skip_hashing, skip_listing)
+                .map_err(|e| format!("jmix build error: {}", e))?;
+
+            // Read before filtering: the package is indexed by study even if metadata.json
+            // leaves the UID out
+            let study_uid = envelope_built
+                .metadata
+                .studies
+                .as_ref()
+                .and_then(|s| s.study_uid.clone());
+
+            // Filter metadata before it is written, so metadata.json and the zip agree
+            if metadata_filter.is_active() {
+                metadata_filter.apply(&mut envelope_built.metadata);
+            }
+
+            // Create package-specific directory for this envelope
+            let jmix_id = envelope_built.manifest.id.clone();
+            let pkg_dir = build_root.join(&jmix_id);
+
+            // Ensure the package directory exists
+            fs::create_dir_all(&pkg_dir).map_err(|e| {
+                format!("Failed to create package dir {}: {}", pkg_dir.display(), e)
+            })?;
+
+            // Persist envelope to the specific package directory
+            if let Err(e) = builder.save_to_files_with_options(
                 &envelope_built,
                 &dicom_files,
                 &pkg_dir,
                 skip_hashing,
                 skip_listing,
-            )
-            .map_err(|e| Error::from(format!("jmix save error: {}", e)))?;
+            ) {
+                let _ = fs::remove_dir_all(&pkg_dir);
+                return Err(format!("jmix save error: {}", e));
+            }
+
+            Ok(BuiltPackage {
+                jmix_id,
+                pkg_dir,
+                study_uid,
+            })
+        });
+
+        let timeout = self.config.build_timeout_secs.map(Duration::from_secs);
+        let built = match await_build(task, timeout, shutdown_token()).await {
+            BuildOutcome::Finished(result) => result.map_err(Error::from)?,
+            BuildOutcome::TimedOut(limit) => {
+                tracing::warn!("⏱️ JMIX build exceeded {}s, abandoning", limit.as_secs());
+                let message = format!("JMIX build timed out after {}s", limit.as_secs());
+                return Ok(build_failure(envelope, 504, &message));
+            }
+            BuildOutcome::Cancelled => {
+                tracing::warn!("🛑 JMIX build cancelled by shutdown");
+                return Ok(build_failure(
+                    envelope,
+                    503,
+                    "JMIX build cancelled: server is shutting down",
+                ));
+            }
+        };
+        let BuiltPackage {
+            jmix_id,
+            pkg_dir,
+            study_uid,
+        } = built;
 
         // Extract study UID from the built envelope
-        let study_uid = envelope_built
-            .metadata
-            .studies
-            .as_ref()
-            .and_then(|s| s.study_uid.clone())
-            .unwrap_or_else(|| extract_study_uid(&instances));
+        let study_uid = study_uid.unwrap_or_else(|| extract_study_uid(&instances));
+
+        let log_ctx = OperationContext::new("jmix_build", "")
+            .with_identifier(instances.get(0).unwrap_or(&Value::Null))
+            .with_study_uid(Some(study_uid.as_str()));
 
         // Index the newly created package
         let index = get_jmix_index(&store_root)
@@ -457,6 +743,10 @@ impl Middleware for JmixBuilderMiddleware {
         match fs::metadata(&zip_file) {
             Ok(metadata) => {
                 tracing::info!(
+                    operation = %log_ctx.operation,
+                    study_uid = %log_ctx.study_uid,
+                    patient_id = %log_ctx.patient_id,
+                    jmix_id = %jmix_id,
                     "✅ JMIX zip file ready: {} ({} bytes)",
                     zip_file.display(),
                     metadata.len()
@@ -527,6 +817,30 @@ fn resolve_store_root(options: &HashMap<String, Value>) -> PathBuf {
     PathBuf::from("./tmp/jmix-store")
 }
 
+/// Whether `folder` holds any file, listed through the filesystem storage backend that
+/// contains it
+///
+/// Folders outside the storage root, such as a custom SCP `storage_dir`, and the staging
+/// folders of other backends are listed on the local filesystem.
+async fn folder_has_files(folder: &Path) -> bool {
+    let listed = match get_storage() {
+        Some(storage) if storage.is_filesystem() && folder.starts_with(storage.base_path()) => {
+            let relative = folder
+                .strip_prefix(storage.base_path())
+                .unwrap_or(folder)
+                .to_string_lossy()
+                .to_string();
+            storage.list_files_recursive_str(&relative).await
+        }
+        _ if folder.is_dir() => match FilesystemStorage::new(folder) {
+            Ok(storage) => storage.list_files_recursive_str("").await,
+            Err(e) => Err(e),
+        },
+        _ => return false,
+    };
+    listed.is_ok_and(|files| !files.is_empty())
+}
+
 /// Get the package directory for a given JMIX envelope ID
 fn package_dir_for(store_root: &Path, id: &str) -> PathBuf {
     store_root.join(id)
@@ -555,6 +869,32 @@ fn query_by_study_uid(store_root: &Path, study_uid: &str) -> Result<Vec<serde_js
     Ok(results)
 }
 
+/// Turn an abandoned build into an error response. The rebuild endpoint reads
+/// its outcome from request metadata, so the status is mirrored there too.
+fn build_failure(
+    mut envelope: ResponseEnvelope<Value>,
+    status: u16,
+    message: &str,
+) -> ResponseEnvelope<Value> {
+    let body = serde_json::json!({ "error": message });
+    envelope.response_details.status = status;
+    envelope
+        .response_details
+        .headers
+        .insert("content-type".to_string(), "application/json".to_string());
+    envelope.original_data = body.clone();
+    envelope.normalized_data = Some(body);
+    envelope
+        .request_details
+        .metadata
+        .insert("jmix_rebuild_status".to_string(), status.to_string());
+    envelope
+        .request_details
+        .metadata
+        .insert("jmix_rebuild_message".to_string(), message.to_string());
+    envelope
+}
+
 fn ensure_store_root() -> Result<PathBuf, String> {
     // Use global storage to derive jmix-store root; fallback to ./tmp/jmix-store
     let store_root = if let Some(storage) = get_storage() {
@@ -836,6 +1176,180 @@ mod tests {
         drop(storage);
     }
 
+    #[test]
+    fn test_parse_config_build_timeout() {
+        let mut options = HashMap::new();
+        assert_eq!(parse_config(&options).unwrap().build_timeout_secs, None);
+
+        options.insert("build_timeout_secs".to_string(), serde_json::json!(30));
+        assert_eq!(parse_config(&options).unwrap().build_timeout_secs, Some(30));
+
+        options.insert("build_timeout_secs".to_string(), serde_json::json!(0));
+        assert!(parse_config(&options).is_err());
+    }
+
+    #[test]
+    fn test_metadata_filter_rejects_attributes_metadata_json_lacks() {
+        let mut options = HashMap::new();
+        options.insert(
+            "metadata_exclude_tags".to_string(),
+            serde_json::json!(["PatientName", "00080060"]),
+        );
+        let filter = parse_config(&options).unwrap().metadata_filter;
+        assert_eq!(filter.exclude, vec!["00100010", "00080060"]);
+
+        for unsupported in ["InstitutionName", "NotARealKeyword"] {
+            options.insert(
+                "metadata_exclude_tags".to_string(),
+                serde_json::json!([unsupported]),
+            );
+            assert!(parse_config(&options).is_err(), "{}", unsupported);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_metadata_filter_applies_to_built_metadata_json() {
+        use std::io::Read;
+
+        crate::globals::reset_storage();
+        let storage = create_test_storage();
+        set_storage(storage.clone());
+        let store_root = storage.subpath_str("jmix-store");
+
+        let src_dir = storage
+            .ensure_dir_str("dimse/test_jmix_metadata_filter")
+            .expect("ensure");
+        let identifier = serde_json::json!({
+            "00080016": { "vr": "UI", "Value": ["1.2.840.10008.5.1.4.1.1.7"] },
+            "00080018": { "vr": "UI", "Value": ["1.2.3.4.1.1"] },
+            "00080060": { "vr": "CS", "Value": ["OT"] },
+            "00081030": { "vr": "LO", "Value": ["Chest"] },
+            "00100010": { "vr": "PN", "Value": [{ "Alphabetic": "DOE^JANE" }] },
+            "00100020": { "vr": "LO", "Value": ["PID-722"] },
+            "00100030": { "vr": "DA", "Value": ["19700101"] },
+            "0020000D": { "vr": "UI", "Value": ["1.2.3.4"] },
+            "0020000E": { "vr": "UI", "Value": ["1.2.3.4.1"] }
+        });
+        let obj = dicom_json_tool::json_value_to_identifier(&identifier).unwrap();
+        dicom_json_tool::write_part10(&src_dir.join("a.dcm"), &obj).unwrap();
+
+        let nd = serde_json::json!({
+            "operation": "get",
+            "success": true,
+            "folder_id": "metadata_filter",
+            "folder_path": src_dir.to_string_lossy(),
+            "file_count": 1,
+            "instances": [identifier]
+        });
+        let env = ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".into(),
+                uri: "".into(),
+                headers: Default::default(),
+                cookies: Default::default(),
+                query_params: Default::default(),
+                cache_status: None,
+                metadata: Default::default(),
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: serde_json::json!({}),
+            normalized_data: Some(nd),
+            normalized_snapshot: None,
+        };
+
+        let mut options = HashMap::new();
+        options.insert(
+            "metadata_exclude_tags".to_string(),
+            serde_json::json!(["PatientName", "PatientBirthDate", "PatientID"]),
+        );
+        let mw = JmixBuilderMiddleware::with_config(parse_config(&options).unwrap());
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let out = rt.block_on(async move { mw.right(env).await.expect("mw") });
+        let jmix_id = out
+            .response_details
+            .metadata
+            .get("jmix_id")
+            .expect("jmix_id");
+
+        let zip_file = store_root.join(jmix_id).join(format!("{}.zip", jmix_id));
+        let mut archive = zip::ZipArchive::new(fs::File::open(&zip_file).unwrap()).unwrap();
+        let name = archive
+            .file_names()
+            .find(|n| n.ends_with("payload/metadata.json"))
+            .expect("metadata.json in zip")
+            .to_string();
+        let mut contents = String::new();
+        archive
+            .by_name(&name)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        let metadata: Value = serde_json::from_str(&contents).unwrap();
+
+        assert!(!contents.contains("DOE"), "{}", contents);
+        assert!(metadata["patient"].get("name").is_none());
+        assert!(metadata["patient"].get("dob").is_none());
+        assert_eq!(metadata["patient"]["id"], "");
+        assert_eq!(metadata["studies"]["study_description"], "Chest");
+        assert_eq!(metadata["studies"]["series"][0]["modality"], "OT");
+        assert_eq!(
+            out.response_details.metadata.get("jmix_study_uid"),
+            Some(&"1.2.3.4".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_exceeding_timeout_is_aborted_and_cleaned_up() {
+        let pkg_dir = std::env::temp_dir().join(format!("jmix-timeout-{}", uuid::Uuid::new_v4()));
+        let task_dir = pkg_dir.clone();
+        let task: BuildTask = tokio::task::spawn_blocking(move || {
+            // Simulate a slow build that has already written part of its package
+            fs::create_dir_all(&task_dir).map_err(|e| e.to_string())?;
+            fs::write(task_dir.join("manifest.json"), b"{}").map_err(|e| e.to_string())?;
+            std::thread::sleep(Duration::from_millis(300));
+            Ok(BuiltPackage {
+                jmix_id: "slow".to_string(),
+                pkg_dir: task_dir,
+                study_uid: None,
+            })
+        });
+
+        let outcome = await_build(
+            task,
+            Some(Duration::from_millis(50)),
+            CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(outcome, BuildOutcome::TimedOut(_)));
+
+        // The abandoned build keeps running in the background, then its output is removed
+        for _ in 0..50 {
+            if !pkg_dir.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!pkg_dir.exists(), "partial package should be cleaned up");
+    }
+
+    #[tokio::test]
+    async fn test_build_cancelled_by_shutdown() {
+        let task: BuildTask = tokio::task::spawn_blocking(|| {
+            std::thread::sleep(Duration::from_millis(200));
+            Err("never observed".to_string())
+        });
+        let shutdown = CancellationToken::new();
+        shutdown.cancel();
+
+        let outcome = await_build(task, None, shutdown).await;
+        assert!(matches!(outcome, BuildOutcome::Cancelled));
+    }
+
     fn create_test_storage() -> Arc<FilesystemStorage> {
         // Always create unique storage directory for each test to avoid database lock contention
         let test_id = uuid::Uuid::new_v4();
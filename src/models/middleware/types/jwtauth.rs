@@ -31,7 +31,6 @@ pub struct JwtAuthMiddleware {
 
 #[derive(Debug, Deserialize)]
 struct Claims {
-    #[allow(dead_code)]
     sub: Option<String>,
     #[allow(dead_code)]
     exp: Option<i64>,
@@ -124,7 +123,13 @@ impl JwtAuthMiddleware {
     }
 
     /// Real token validation: verify signature and claims
+    #[cfg(test)]
     async fn validate_token(&self, token: &str) -> Result<bool, Error> {
+        self.verified_claims(token).map(|_| true)
+    }
+
+    /// Verify the token and return its claims
+    fn verified_claims(&self, token: &str) -> Result<Claims, Error> {
         // Enforce expected algorithm from header
         let header = decode_header(token).map_err(|_| AuthFailure("invalid JWT header"))?;
         if header.alg != self.algorithm {
@@ -155,7 +160,7 @@ impl JwtAuthMiddleware {
             }
         }
 
-        Ok(true)
+        Ok(token_data.claims)
     }
 
     /// Extract JWT token from Authorization header in the envelope
@@ -179,7 +184,7 @@ impl JwtAuthMiddleware {
 impl Middleware for JwtAuthMiddleware {
     async fn left(
         &self,
-        envelope: RequestEnvelope<serde_json::Value>,
+        mut envelope: RequestEnvelope<serde_json::Value>,
     ) -> Result<RequestEnvelope<serde_json::Value>, Error> {
         // Step 1: Extract the JWT token from the envelope's headers
         let token = match self.extract_token_from_envelope(&envelope) {
@@ -191,15 +196,19 @@ impl Middleware for JwtAuthMiddleware {
         };
 
         // Step 2: Validate the token
-        if !self.validate_token(&token).await? {
-            let error_message = "Invalid or expired JWT token";
-            tracing::error!("{}", error_message);
-            return Err(AuthFailure("jwt verify failed").into());
-        }
+        let claims = self.verified_claims(&token).inspect_err(|_| {
+            tracing::error!("Invalid or expired JWT token");
+        })?;
 
         tracing::info!("JWT token validated successfully");
 
-        // Step 3: Pass through the envelope (token is valid)
+        // Step 3: Record the subject for later middleware (e.g. policy_authz) and pass through
+        if let Some(sub) = claims.sub {
+            envelope
+                .request_details
+                .metadata
+                .insert("principal".to_string(), sub);
+        }
         Ok(envelope)
     }
 
@@ -38,8 +38,10 @@ impl From<MetadataTransformConfig> for TransformConfig {
     fn from(config: MetadataTransformConfig) -> Self {
         TransformConfig {
             spec_path: config.spec_path,
+            spec_paths: Vec::new(),
             apply: config.apply,
             fail_on_error: config.fail_on_error,
+            subtree_pointer: None,
         }
     }
 }
@@ -1,7 +1,9 @@
 pub mod auth;
 pub mod auth_error;
+pub mod cache;
 pub mod connect;
 pub mod dicomweb_bridge;
+pub mod instance_dedup;
 pub mod jmix_builder;
 pub mod jmix_index;
 pub mod json_extractor;
@@ -9,4 +11,7 @@ pub mod jwtauth;
 pub mod metadata_transform;
 pub mod passthru;
 pub mod path_filter;
+pub mod policy_authz;
+pub mod rate_limit;
+pub mod study_cache;
 pub mod transform;
@@ -0,0 +1,320 @@
+use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::middleware::middleware::Middleware;
+use crate::models::middleware::AccessDenied;
+use crate::utils::Error;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Recent decisions keyed by policy URL and request context, shared by every pipeline
+static DECISIONS: Lazy<Mutex<HashMap<String, (bool, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// What to do when the policy service cannot be reached or returns no usable decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailMode {
+    /// Allow the request
+    Open,
+    /// Deny the request
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyAuthzConfig {
+    /// Policy decision endpoint the request context is POSTed to
+    pub url: String,
+    /// Per-call timeout for the policy service
+    pub timeout_ms: u64,
+    /// How long a decision is reused for an identical request context (0 disables caching)
+    pub cache_ttl_secs: u64,
+    /// Behaviour when the policy service is unreachable
+    pub on_unavailable: FailMode,
+}
+
+/// Asks an external policy decision point (OPA-style) whether each request may proceed.
+///
+/// The request context is sent as `{"input": {...}}` with the principal, method, path and any
+/// study, series or patient identifiers found in the path or query. Place it after `jwtauth`
+/// so the principal is known. A deny decision fails the request with 403.
+pub struct PolicyAuthzMiddleware {
+    config: PolicyAuthzConfig,
+    client: reqwest::Client,
+}
+
+impl PolicyAuthzMiddleware {
+    pub fn new(config: PolicyAuthzConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .unwrap_or_default();
+        Self { config, client }
+    }
+
+    /// Request context sent to the policy service
+    fn policy_input(envelope: &RequestEnvelope<Value>) -> Value {
+        let details = &envelope.request_details;
+        let path = details
+            .metadata
+            .get("full_path")
+            .or_else(|| details.metadata.get("path"))
+            .map(|p| p.split('?').next().unwrap_or_default().to_string())
+            .unwrap_or_else(|| {
+                details
+                    .uri
+                    .split('?')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            });
+
+        // UIDs follow their resource segment: /studies/{study}/series/{series}
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let after = |name: &str| {
+            segments
+                .iter()
+                .position(|s| s.eq_ignore_ascii_case(name))
+                .and_then(|i| segments.get(i + 1))
+                .map(|s| s.to_string())
+        };
+        let query = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|k| details.query_params.get(*k))
+                .and_then(|values| values.first())
+                .cloned()
+        };
+
+        json!({
+            "principal": details.metadata.get("principal"),
+            "method": details.method,
+            "path": path,
+            "study_uid": after("studies").or_else(|| query(&["StudyInstanceUID", "0020000D"])),
+            "series_uid": after("series").or_else(|| query(&["SeriesInstanceUID", "0020000E"])),
+            "patient_id": query(&["PatientID", "00100020"]),
+        })
+    }
+
+    /// Ask the policy service; `None` when it is unreachable or its answer is unusable
+    async fn query_policy(&self, input: &Value) -> Option<bool> {
+        let response = match self
+            .client
+            .post(&self.config.url)
+            .json(&json!({ "input": input }))
+            .send()
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::warn!("policy_authz: policy service unreachable: {}", e);
+                return None;
+            }
+        };
+        if !response.status().is_success() {
+            tracing::warn!(
+                "policy_authz: policy service returned {}",
+                response.status()
+            );
+            return None;
+        }
+        let body: Value = response.json().await.ok()?;
+        let decision = parse_decision(&body);
+        if decision.is_none() {
+            tracing::warn!(
+                "policy_authz: no allow decision in policy response: {}",
+                body
+            );
+        }
+        decision
+    }
+}
+
+/// Accepts `{"result": true}`, `{"result": {"allow": true}}` and `{"allow": true}`
+fn parse_decision(body: &Value) -> Option<bool> {
+    let result = body.get("result").unwrap_or(body);
+    result
+        .as_bool()
+        .or_else(|| result.get("allow").and_then(|v| v.as_bool()))
+}
+
+#[async_trait]
+impl Middleware for PolicyAuthzMiddleware {
+    async fn left(
+        &self,
+        envelope: RequestEnvelope<Value>,
+    ) -> Result<RequestEnvelope<Value>, Error> {
+        let input = Self::policy_input(&envelope);
+        let cache_key = format!("{} {}", self.config.url, input);
+        let ttl = Duration::from_secs(self.config.cache_ttl_secs);
+
+        let cached = DECISIONS
+            .lock()
+            .expect("policy decision cache poisoned")
+            .get(&cache_key)
+            .filter(|(_, at)| at.elapsed() < ttl)
+            .map(|(allow, _)| *allow);
+
+        let allow = match cached {
+            Some(allow) => allow,
+            None => match self.query_policy(&input).await {
+                Some(allow) => {
+                    if !ttl.is_zero() {
+                        let mut decisions =
+                            DECISIONS.lock().expect("policy decision cache poisoned");
+                        decisions.retain(|_, (_, at)| at.elapsed() < ttl);
+                        decisions.insert(cache_key, (allow, Instant::now()));
+                    }
+                    allow
+                }
+                None => self.config.on_unavailable == FailMode::Open,
+            },
+        };
+
+        if allow {
+            Ok(envelope)
+        } else {
+            tracing::warn!(
+                principal = ?input.get("principal"),
+                path = ?input.get("path"),
+                "policy_authz: request denied"
+            );
+            Err(AccessDenied("Request denied by policy".to_string()).into())
+        }
+    }
+
+    async fn right(
+        &self,
+        envelope: ResponseEnvelope<Value>,
+    ) -> Result<ResponseEnvelope<Value>, Error> {
+        Ok(envelope)
+    }
+}
+
+pub fn parse_config(options: &HashMap<String, Value>) -> Result<PolicyAuthzConfig, String> {
+    let url = options
+        .get("url")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .ok_or("Missing required 'url' in policy_authz middleware config")?
+        .to_string();
+
+    let timeout_ms = match options.get("timeout_ms") {
+        None => 2000,
+        Some(v) => v
+            .as_u64()
+            .filter(|ms| *ms > 0)
+            .ok_or("'timeout_ms' in policy_authz middleware config must be a positive integer")?,
+    };
+
+    let cache_ttl_secs = match options.get("cache_ttl_secs") {
+        None => 30,
+        Some(v) => v
+            .as_u64()
+            .ok_or("'cache_ttl_secs' in policy_authz middleware config must be an integer")?,
+    };
+
+    let on_unavailable = match options.get("on_unavailable").map(|v| v.as_str()) {
+        None | Some(Some("deny")) => FailMode::Closed,
+        Some(Some("allow")) => FailMode::Open,
+        _ => {
+            return Err(
+                "'on_unavailable' in policy_authz middleware config must be 'allow' or 'deny'"
+                    .to_string(),
+            )
+        }
+    };
+
+    Ok(PolicyAuthzConfig {
+        url,
+        timeout_ms,
+        cache_ttl_secs,
+        on_unavailable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+    use axum::{routing::post, Json, Router};
+
+    /// Policy server that allows only `alice`
+    async fn spawn_policy_server() -> (String, tokio::task::JoinHandle<()>) {
+        let app = Router::new().route(
+            "/v1/data/harmony/allow",
+            post(|Json(body): Json<Value>| async move {
+                let allow = body["input"]["principal"] == "alice";
+                Json(json!({ "result": allow }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}/v1/data/harmony/allow", addr), server)
+    }
+
+    fn request_as(principal: &str) -> RequestEnvelope<Value> {
+        RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies/1.2.3/series")
+            .metadata_entry("path", "studies/1.2.3/series")
+            .metadata_entry("principal", principal)
+            .original_data(json!({}))
+            .normalized_data(Some(json!({})))
+            .build()
+            .unwrap()
+    }
+
+    fn options(url: &str) -> HashMap<String, Value> {
+        HashMap::from([
+            ("url".to_string(), json!(url)),
+            ("cache_ttl_secs".to_string(), json!(0)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_policy_deny_is_forbidden_and_allow_passes_through() {
+        let (url, server) = spawn_policy_server().await;
+        let middleware = PolicyAuthzMiddleware::new(parse_config(&options(&url)).unwrap());
+
+        let allowed = middleware.left(request_as("alice")).await.unwrap();
+        assert_eq!(
+            allowed.request_details.uri,
+            "/dicomweb/studies/1.2.3/series"
+        );
+
+        let err = middleware.left(request_as("mallory")).await.unwrap_err();
+        assert!(err.downcast_ref::<AccessDenied>().is_some());
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_policy_service_uses_fail_mode() {
+        // Nothing listens on a port we just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut opts = options(&format!("http://127.0.0.1:{}/allow", port));
+
+        let closed = PolicyAuthzMiddleware::new(parse_config(&opts).unwrap());
+        assert!(closed.left(request_as("alice")).await.is_err());
+
+        opts.insert("on_unavailable".to_string(), json!("allow"));
+        let open = PolicyAuthzMiddleware::new(parse_config(&opts).unwrap());
+        assert!(open.left(request_as("alice")).await.is_ok());
+    }
+
+    #[test]
+    fn test_policy_input_extracts_uids() {
+        let input = PolicyAuthzMiddleware::policy_input(&request_as("alice"));
+        assert_eq!(input["principal"], "alice");
+        assert_eq!(input["study_uid"], "1.2.3");
+        assert!(input["series_uid"].is_null());
+    }
+}
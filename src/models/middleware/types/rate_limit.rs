@@ -0,0 +1,318 @@
+use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::middleware::middleware::Middleware;
+use crate::utils::Error;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token buckets keyed by scope and caller, shared by every pipeline
+static BUCKETS: Lazy<Mutex<Buckets>> = Lazy::new(|| Mutex::new(Buckets::new(Instant::now())));
+
+/// How often buckets that have refilled completely are dropped
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The request exceeded its rate limit (HTTP 429)
+#[derive(Debug, thiserror::Error)]
+#[error("Rate limit exceeded; retry after {retry_after_secs}s")]
+pub struct RateLimited {
+    /// Whole seconds until a request would be admitted, sent as `Retry-After`
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Sustained requests per second allowed for each caller
+    pub requests_per_second: f64,
+    /// Requests a caller may make at once after being idle
+    pub burst: u32,
+    /// Identify callers by this request header instead of the client IP
+    pub key_header: Option<String>,
+    /// Pipelines sharing a scope share each caller's budget
+    pub scope: String,
+}
+
+struct Buckets {
+    entries: HashMap<String, Bucket>,
+    swept_at: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    rate: f64,
+    capacity: f64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+}
+
+impl Buckets {
+    fn new(now: Instant) -> Self {
+        Self {
+            entries: HashMap::new(),
+            swept_at: now,
+        }
+    }
+
+    /// Take one token from the bucket under `key`, creating it full if absent
+    fn take(
+        &mut self,
+        key: String,
+        rate: f64,
+        burst: u32,
+        now: Instant,
+    ) -> Result<(), RateLimited> {
+        if now.duration_since(self.swept_at) >= SWEEP_INTERVAL {
+            // A full bucket is indistinguishable from a new one, so dropping it loses nothing
+            self.entries.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < bucket.capacity
+            });
+            self.swept_at = now;
+        }
+
+        let capacity = burst as f64;
+        let bucket = self.entries.entry(key).or_insert_with(|| Bucket {
+            tokens: capacity,
+            updated: now,
+            rate,
+            capacity,
+        });
+        bucket.refill(now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / bucket.rate;
+            Err(RateLimited {
+                retry_after_secs: (wait.ceil() as u64).max(1),
+            })
+        }
+    }
+}
+
+/// Caps request rates per caller with a token bucket.
+///
+/// Callers are identified by `key_header` when configured and present, otherwise by client
+/// IP. A request that finds its bucket empty fails with [`RateLimited`], which the HTTP
+/// adapter answers with 429 and `Retry-After`; later layers and backends are not called.
+pub struct RateLimitMiddleware {
+    config: RateLimitConfig,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self { config }
+    }
+
+    fn caller(&self, envelope: &RequestEnvelope<Value>) -> String {
+        let details = &envelope.request_details;
+        let header = self.config.key_header.as_ref().and_then(|name| {
+            details
+                .headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.trim())
+                .filter(|v| !v.is_empty())
+        });
+        match header {
+            Some(value) => format!("header:{}", value),
+            None => format!(
+                "ip:{}",
+                details
+                    .metadata
+                    .get("client_ip")
+                    .map(String::as_str)
+                    .unwrap_or("unknown")
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RateLimitMiddleware {
+    async fn left(
+        &self,
+        envelope: RequestEnvelope<Value>,
+    ) -> Result<RequestEnvelope<Value>, Error> {
+        let caller = self.caller(&envelope);
+        let taken = BUCKETS.lock().expect("rate limit buckets poisoned").take(
+            format!("{}|{}", self.config.scope, caller),
+            self.config.requests_per_second,
+            self.config.burst,
+            Instant::now(),
+        );
+        match taken {
+            Ok(()) => Ok(envelope),
+            Err(limited) => {
+                tracing::warn!(
+                    scope = %self.config.scope,
+                    caller = %caller,
+                    retry_after_secs = limited.retry_after_secs,
+                    "rate_limit: request rejected"
+                );
+                Err(limited.into())
+            }
+        }
+    }
+
+    async fn right(
+        &self,
+        envelope: ResponseEnvelope<Value>,
+    ) -> Result<ResponseEnvelope<Value>, Error> {
+        Ok(envelope)
+    }
+}
+
+pub fn parse_config(options: &HashMap<String, Value>) -> Result<RateLimitConfig, String> {
+    let requests_per_second = options
+        .get("requests_per_second")
+        .and_then(|v| v.as_f64())
+        .filter(|rps| *rps > 0.0 && rps.is_finite())
+        .ok_or("'requests_per_second' in rate_limit middleware config must be a positive number")?;
+
+    let burst = match options.get("burst") {
+        None => requests_per_second.ceil().max(1.0) as u32,
+        Some(v) => v
+            .as_u64()
+            .filter(|b| (1..=u32::MAX as u64).contains(b))
+            .ok_or("'burst' in rate_limit middleware config must be a positive integer")?
+            as u32,
+    };
+
+    let key_header = match options.get("key_header") {
+        None => None,
+        Some(v) => Some(
+            v.as_str()
+                .filter(|s| !s.trim().is_empty())
+                .ok_or("'key_header' in rate_limit middleware config must be a header name")?
+                .to_string(),
+        ),
+    };
+
+    let scope = options
+        .get("scope")
+        .and_then(|v| v.as_str())
+        .unwrap_or("default")
+        .to_string();
+
+    Ok(RateLimitConfig {
+        requests_per_second,
+        burst,
+        key_header,
+        scope,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+    use serde_json::json;
+
+    fn limiter(scope: &str, burst: u32, key_header: Option<&str>) -> RateLimitMiddleware {
+        RateLimitMiddleware::new(RateLimitConfig {
+            requests_per_second: 1.0,
+            burst,
+            key_header: key_header.map(str::to_string),
+            scope: scope.to_string(),
+        })
+    }
+
+    fn request_from(ip: &str, api_key: Option<&str>) -> RequestEnvelope<Value> {
+        let mut builder = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .metadata_entry("client_ip", ip);
+        if let Some(key) = api_key {
+            builder = builder.header("X-Api-Key", key);
+        }
+        builder.original_data(Value::Null).build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_over_burst_is_rate_limited() {
+        let mw = limiter("test_over_burst", 3, None);
+
+        for _ in 0..3 {
+            assert!(mw.left(request_from("10.0.0.1", None)).await.is_ok());
+        }
+        let err = mw.left(request_from("10.0.0.1", None)).await.unwrap_err();
+
+        let limited = err
+            .downcast_ref::<RateLimited>()
+            .expect("RateLimited error");
+        assert_eq!(limited.retry_after_secs, 1);
+    }
+
+    #[tokio::test]
+    async fn test_callers_have_separate_buckets() {
+        let mw = limiter("test_separate", 1, None);
+
+        assert!(mw.left(request_from("10.0.0.1", None)).await.is_ok());
+        assert!(mw.left(request_from("10.0.0.2", None)).await.is_ok());
+        assert!(mw.left(request_from("10.0.0.1", None)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_identity_header_keys_bucket() {
+        let mw = limiter("test_header", 1, Some("x-api-key"));
+
+        // Same IP, different identities
+        assert!(mw
+            .left(request_from("10.0.0.1", Some("alice")))
+            .await
+            .is_ok());
+        assert!(mw.left(request_from("10.0.0.1", Some("bob"))).await.is_ok());
+        assert!(mw
+            .left(request_from("10.0.0.9", Some("alice")))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_bucket_refills_and_full_buckets_are_swept() {
+        let start = Instant::now();
+        let mut buckets = Buckets::new(start);
+        let take = |buckets: &mut Buckets, key: &str, at: Instant| {
+            buckets.take(key.to_string(), 0.5, 1, at)
+        };
+
+        assert!(take(&mut buckets, "a", start).is_ok());
+        let limited = take(&mut buckets, "a", start).unwrap_err();
+        assert_eq!(limited.retry_after_secs, 2);
+        assert!(take(&mut buckets, "a", start + Duration::from_secs(2)).is_ok());
+
+        // By the next sweep "a" has refilled and is dropped; "b" was just drained
+        assert!(take(&mut buckets, "b", start + SWEEP_INTERVAL).is_ok());
+        assert!(buckets.entries.contains_key("b"));
+        assert!(!buckets.entries.contains_key("a"));
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let options: HashMap<String, Value> =
+            serde_json::from_value(json!({ "requests_per_second": 2.5 })).unwrap();
+        let config = parse_config(&options).unwrap();
+        assert_eq!(config.burst, 3);
+        assert_eq!(config.scope, "default");
+        assert!(config.key_header.is_none());
+
+        let options: HashMap<String, Value> =
+            serde_json::from_value(json!({ "requests_per_second": 0 })).unwrap();
+        assert!(parse_config(&options).is_err());
+
+        let options: HashMap<String, Value> =
+            serde_json::from_value(json!({ "requests_per_second": 1, "burst": 0 })).unwrap();
+        assert!(parse_config(&options).is_err());
+    }
+}
@@ -0,0 +1,761 @@
+use crate::config::config::Config;
+use crate::globals::{get_config, get_storage};
+use crate::models::envelope::envelope::{RequestEnvelope, RequestEnvelopeBuilder, ResponseEnvelope};
+use crate::models::middleware::middleware::Middleware;
+use crate::models::middleware::types::jmix_index::current_timestamp;
+use crate::models::services::services::ServiceType;
+use crate::storage::{FilesystemStorage, StorageBackend};
+use crate::utils::Error;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Cache keys with a stale-while-revalidate refresh currently running
+static REFRESHING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// StudyDate, the attribute `days_back` limits the sweep by
+const STUDY_DATE: &str = "00080020";
+
+/// Default study-level return keys requested by the sweep (tag, VR)
+const STUDY_RETURN_KEYS: &[(&str, &str)] = &[
+    ("0020000D", "UI"), // StudyInstanceUID
+    ("00080020", "DA"), // StudyDate
+    ("00080030", "TM"), // StudyTime
+    ("00080050", "SH"), // AccessionNumber
+    ("00080061", "CS"), // ModalitiesInStudy
+    ("00081030", "LO"), // StudyDescription
+    ("00100010", "PN"), // PatientName
+    ("00100020", "LO"), // PatientID
+];
+
+#[derive(Debug, Clone)]
+pub struct StudyCacheConfig {
+    /// Backend the sweep C-FIND is sent through
+    pub backend: String,
+    /// Name of the cache entry in the storage backend
+    pub cache_key: String,
+    /// Seconds between background sweeps
+    pub interval_secs: u64,
+    /// Age after which a served entry triggers a background refresh
+    pub stale_after_secs: u64,
+    /// Restrict the sweep to studies from the last N days (StudyDate range)
+    pub days_back: Option<u32>,
+    /// Additional match keys (hex tag -> value) sent with the sweep
+    pub query: HashMap<String, String>,
+}
+
+/// A query the sweep can answer, and the StudyDate range to narrow its matches to
+#[derive(Debug, Clone, PartialEq)]
+struct CachedQuery {
+    study_dates: Option<DateRange>,
+}
+
+/// Inclusive StudyDate bounds (`YYYYMMDD`) of a DICOM date range match value
+#[derive(Debug, Clone, PartialEq)]
+struct DateRange {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl DateRange {
+    /// `A`, `A-`, `-B` or `A-B`; `None` for anything else
+    fn parse(value: &str) -> Option<Self> {
+        let (from, to) = value.split_once('-').unwrap_or((value, value));
+        let bound = |s: &str| match s.trim() {
+            "" => Some(None),
+            s if s.len() == 8 && s.chars().all(|c| c.is_ascii_digit()) => Some(Some(s.to_string())),
+            _ => None,
+        };
+        Some(Self {
+            from: bound(from)?,
+            to: bound(to)?,
+        })
+    }
+
+    /// Whether a study's match falls in the range
+    fn contains(&self, item: &Value) -> bool {
+        let Some(date) = item
+            .get(STUDY_DATE)
+            .and_then(|entry| entry.get("Value"))
+            .and_then(|v| v.get(0))
+            .and_then(|v| v.as_str())
+        else {
+            return false;
+        };
+        self.from.as_deref().is_none_or(|from| date >= from)
+            && self.to.as_deref().is_none_or(|to| date <= to)
+    }
+}
+
+/// Cached sweep result as persisted in the storage backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedStudies {
+    pub swept_at: u64,
+    pub matches: Value,
+}
+
+/// Serves the study-level QIDO query a periodic background C-FIND sweeps from its result.
+///
+/// Must run after `dicomweb_bridge` so the DIMSE operation and identifier are already built.
+pub struct StudyCacheMiddleware {
+    config: StudyCacheConfig,
+}
+
+impl StudyCacheMiddleware {
+    pub fn new(config: StudyCacheConfig) -> Self {
+        Self { config }
+    }
+
+    /// How a study-level find can be answered from the sweep, or `None` when it cannot
+    ///
+    /// The query's match keys must be the sweep's own: the configured `query` values, and a
+    /// StudyDate range inside the `days_back` window, which is then applied to the cached
+    /// matches. Every return key, `includefield` ones included, must be one the sweep fetched.
+    /// `limit` and `offset` are left to `dicomweb_bridge`, which pages the cached matches.
+    fn cached_query(&self, envelope: &RequestEnvelope<Value>) -> Option<CachedQuery> {
+        let metadata = &envelope.request_details.metadata;
+        if metadata.get("dimse_op").map(|s| s.as_str()) != Some("find") {
+            return None;
+        }
+        let path = metadata.get("path").map(|s| s.trim_matches('/')).unwrap_or("");
+        if path != "studies" {
+            return None;
+        }
+        // Fuzzy person names are matched after the C-FIND, so they restrict the query too
+        if metadata.contains_key("dicomweb_fuzzy_names") {
+            return None;
+        }
+        let ident = envelope
+            .normalized_data
+            .as_ref()
+            .and_then(|nd| nd.get("dimse_identifier"))
+            .and_then(|ident| ident.as_object())?;
+
+        let mut study_dates = None;
+        for (tag, entry) in ident {
+            let values: Vec<&str> = entry
+                .get("Value")
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            let swept_value = self.config.query.get(tag);
+            match (values.as_slice(), swept_value) {
+                ([], _) => {
+                    let fetched =
+                        swept_value.is_some() || STUDY_RETURN_KEYS.iter().any(|(t, _)| t == tag);
+                    if !fetched {
+                        return None;
+                    }
+                }
+                ([value], Some(swept)) if *value == swept.as_str() => {}
+                ([value], None) if tag == STUDY_DATE => {
+                    let range = DateRange::parse(value)?;
+                    if let Some(since) = sweep_since(&self.config) {
+                        if range
+                            .from
+                            .as_deref()
+                            .is_none_or(|from| from < since.as_str())
+                        {
+                            return None;
+                        }
+                    }
+                    study_dates = Some(range);
+                }
+                _ => return None,
+            }
+        }
+        // Without a StudyDate the query asks for more than a windowed sweep holds
+        if study_dates.is_none() && self.config.days_back.is_some() {
+            return None;
+        }
+        let configured_keys_sent = self.config.query.iter().all(|(tag, value)| {
+            ident
+                .get(tag)
+                .and_then(|entry| entry.get("Value"))
+                .and_then(|v| v.as_array())
+                .is_some_and(|values| values.len() == 1 && values[0] == value.as_str())
+        });
+        configured_keys_sent.then_some(CachedQuery { study_dates })
+    }
+
+    /// Refresh the cache in the background unless a refresh is already running
+    fn spawn_refresh(&self) {
+        let Some(config) = get_config() else {
+            return;
+        };
+        {
+            let mut guard = REFRESHING.lock().expect("study cache refresh set poisoned");
+            if !guard.insert(self.config.cache_key.clone()) {
+                return;
+            }
+        }
+        let cache = self.config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = sweep(&config, &cache).await {
+                tracing::warn!("Study cache '{}' refresh failed: {}", cache.cache_key, e);
+            }
+            REFRESHING
+                .lock()
+                .expect("study cache refresh set poisoned")
+                .remove(&cache.cache_key);
+        });
+    }
+}
+
+#[async_trait]
+impl Middleware for StudyCacheMiddleware {
+    async fn left(
+        &self,
+        mut envelope: RequestEnvelope<Value>,
+    ) -> Result<RequestEnvelope<Value>, Error> {
+        let Some(query) = self.cached_query(&envelope) else {
+            return Ok(envelope);
+        };
+
+        let Some(cached) = read_cache(&self.config.cache_key).await else {
+            tracing::debug!("Study cache '{}' empty; querying backend", self.config.cache_key);
+            return Ok(envelope);
+        };
+
+        let age = current_timestamp()
+            .saturating_sub(cached.swept_at);
+        let stale = age > self.config.stale_after_secs;
+        if stale {
+            // Stale-while-revalidate: serve what we have and refresh behind the response
+            self.spawn_refresh();
+        }
+
+        tracing::debug!(
+            "Serving study cache '{}' (age={}s, stale={})",
+            self.config.cache_key,
+            age,
+            stale
+        );
+
+        let metadata = &mut envelope.request_details.metadata;
+        metadata.insert("skip_backends".to_string(), "true".to_string());
+        metadata.insert(
+            "study_cache".to_string(),
+            if stale { "stale" } else { "hit" }.to_string(),
+        );
+        if let Some(swept_at) = chrono::DateTime::from_timestamp(cached.swept_at as i64, 0) {
+            metadata.insert("retrieved_at".to_string(), swept_at.to_rfc3339());
+        }
+        let mut matches = cached.matches;
+        if let (Some(range), Value::Array(items)) = (&query.study_dates, &mut matches) {
+            items.retain(|item| range.contains(item));
+        }
+        // Same shape the DICOM backend returns so dicomweb_bridge.right formats it as usual
+        envelope.normalized_data = Some(json!({
+            "operation": "find",
+            "success": true,
+            "matches": matches,
+        }));
+
+        Ok(envelope)
+    }
+
+    async fn right(
+        &self,
+        envelope: ResponseEnvelope<Value>,
+    ) -> Result<ResponseEnvelope<Value>, Error> {
+        Ok(envelope)
+    }
+}
+
+fn cache_storage() -> Result<Arc<dyn StorageBackend>, String> {
+    match get_storage() {
+        Some(storage) => Ok(storage),
+        None => FilesystemStorage::new("./tmp")
+            .map(|s| Arc::new(s) as Arc<dyn StorageBackend>)
+            .map_err(|e| format!("Failed to open study cache storage: {}", e)),
+    }
+}
+
+fn cache_path(cache_key: &str) -> String {
+    format!("study_cache/{}.json", cache_key)
+}
+
+/// Read a cache entry from the storage backend
+pub async fn read_cache(cache_key: &str) -> Option<CachedStudies> {
+    let storage = cache_storage().ok()?;
+    let bytes = storage.read_file_str(&cache_path(cache_key)).await.ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// First StudyDate (`YYYYMMDD`) the sweep covers, when limited by `days_back`
+fn sweep_since(cache: &StudyCacheConfig) -> Option<String> {
+    let days = cache.days_back?;
+    let since = chrono::Local::now().date_naive() - chrono::Duration::days(days as i64);
+    Some(since.format("%Y%m%d").to_string())
+}
+
+/// Build the sweep identifier: configured match keys plus study-level return keys
+fn sweep_identifier(cache: &StudyCacheConfig) -> Value {
+    let mut ident = serde_json::Map::new();
+    for (tag, vr) in STUDY_RETURN_KEYS {
+        ident.insert(tag.to_string(), json!({ "vr": vr, "Value": [] }));
+    }
+    if let Some(since) = sweep_since(cache) {
+        ident.insert(
+            STUDY_DATE.to_string(),
+            json!({ "vr": "DA", "Value": [format!("{}-", since)] }),
+        );
+    }
+    for (tag, value) in &cache.query {
+        let vr = STUDY_RETURN_KEYS
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, vr)| *vr)
+            .unwrap_or("LO");
+        ident.insert(tag.clone(), json!({ "vr": vr, "Value": [value] }));
+    }
+    Value::Object(ident)
+}
+
+/// Run the configured C-FIND once through the cache's backend and store the results
+pub async fn sweep(config: &Config, cache: &StudyCacheConfig) -> Result<usize, String> {
+    let backend = config
+        .backends
+        .get(&cache.backend)
+        .ok_or_else(|| format!("Backend '{}' not found", cache.backend))?;
+    let service = backend.resolve_service()?;
+    let empty_options = HashMap::new();
+    sweep_with(
+        service.as_ref(),
+        backend.options.as_ref().unwrap_or(&empty_options),
+        cache,
+    )
+    .await
+}
+
+/// Run the sweep C-FIND against an already resolved backend service
+pub async fn sweep_with(
+    service: &dyn ServiceType<ReqBody = Value>,
+    backend_options: &HashMap<String, Value>,
+    cache: &StudyCacheConfig,
+) -> Result<usize, String> {
+    let envelope = RequestEnvelopeBuilder::new()
+        .method("POST")
+        .uri("/study_cache/sweep")
+        .metadata_entry("dimse_op", "find")
+        .metadata_entry("path", "studies")
+        .original_data(Vec::new())
+        .normalized_data(Some(json!({ "dimse_identifier": sweep_identifier(cache) })))
+        .build()
+        .map_err(|e| format!("Failed to build sweep request: {}", e))?;
+
+    let response = service
+        .backend_outgoing_request(envelope, backend_options)
+        .await
+        .map_err(|e| format!("Sweep C-FIND failed: {:?}", e))?;
+
+    let nd = response.normalized_data.unwrap_or(Value::Null);
+    if !nd.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return Err(format!(
+            "Sweep C-FIND unsuccessful: {}",
+            nd.get("error").and_then(|v| v.as_str()).unwrap_or("unknown error")
+        ));
+    }
+    let matches = nd.get("matches").cloned().unwrap_or_else(|| json!([]));
+    let count = matches.as_array().map(|a| a.len()).unwrap_or(0);
+
+    let entry = CachedStudies {
+        swept_at: current_timestamp(),
+        matches,
+    };
+    let bytes = serde_json::to_vec(&entry).map_err(|e| e.to_string())?;
+    cache_storage()?
+        .write_file_str(&cache_path(&cache.cache_key), &bytes)
+        .await
+        .map_err(|e| format!("Failed to write study cache: {}", e))?;
+
+    tracing::info!("Study cache '{}' swept: {} studies", cache.cache_key, count);
+    Ok(count)
+}
+
+/// Start a periodic sweeper for every `study_cache` middleware instance in the config
+pub fn spawn_sweepers(config: Arc<Config>, shutdown: CancellationToken) -> Vec<JoinHandle<()>> {
+    let mut handles = Vec::new();
+    for (name, instance) in &config.middleware {
+        if instance.middleware_type != "study_cache" {
+            continue;
+        }
+        let cache = match parse_config(&instance.options) {
+            Ok(cache) => cache,
+            Err(e) => {
+                tracing::error!("Not starting study cache sweeper '{}': {}", name, e);
+                continue;
+            }
+        };
+        let config = config.clone();
+        let shutdown = shutdown.clone();
+        handles.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(cache.interval_secs));
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = interval.tick() => {
+                        if let Err(e) = sweep(&config, &cache).await {
+                            tracing::warn!("Study cache '{}' sweep failed: {}", cache.cache_key, e);
+                        }
+                    }
+                }
+            }
+        }));
+    }
+    handles
+}
+
+/// Parse configuration from HashMap for middleware registry
+pub fn parse_config(options: &HashMap<String, Value>) -> Result<StudyCacheConfig, String> {
+    let backend = options
+        .get("backend")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing required 'backend' in study_cache middleware config")?
+        .to_string();
+    let cache_key = options
+        .get("cache_key")
+        .and_then(|v| v.as_str())
+        .unwrap_or("recent_studies")
+        .to_string();
+    if cache_key.is_empty()
+        || !cache_key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err("'cache_key' must be non-empty and contain only [A-Za-z0-9_-]".to_string());
+    }
+    let interval_secs = options
+        .get("interval_secs")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(300);
+    if interval_secs == 0 {
+        return Err("'interval_secs' must be greater than 0".to_string());
+    }
+    let stale_after_secs = options
+        .get("stale_after_secs")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(interval_secs * 2);
+    let days_back = options
+        .get("days_back")
+        .and_then(|v| v.as_u64())
+        .map(|d| d as u32);
+
+    let mut query = HashMap::new();
+    if let Some(obj) = options.get("query").and_then(|v| v.as_object()) {
+        for (key, value) in obj {
+            let value = value
+                .as_str()
+                .ok_or_else(|| format!("study_cache query value for '{}' must be a string", key))?;
+            query.insert(keyword_to_hex(key), value.to_string());
+        }
+    }
+
+    Ok(StudyCacheConfig {
+        backend,
+        cache_key,
+        interval_secs,
+        stale_after_secs,
+        days_back,
+        query,
+    })
+}
+
+/// Convert a DICOM keyword or hex tag to an 8-digit uppercase hex tag
+fn keyword_to_hex(name_or_hex: &str) -> String {
+    use dicom_core::dictionary::DataDictionary;
+    use dicom_dictionary_std::StandardDataDictionary;
+
+    if name_or_hex.len() == 8 && name_or_hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return name_or_hex.to_uppercase();
+    }
+    if let Some(entry) = StandardDataDictionary.by_name(name_or_hex) {
+        let tag = entry.tag.inner();
+        return format!("{:08X}", (tag.0 as u32) << 16 | tag.1 as u32);
+    }
+    name_or_hex.to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn sweep_mock(cache: &StudyCacheConfig) -> usize {
+        let service = crate::models::services::types::mock_dicom::MockDicomEndpoint;
+        sweep_with(&service, &HashMap::new(), cache)
+            .await
+            .expect("sweep succeeds")
+    }
+
+    fn cache_options(cache_key: &str) -> HashMap<String, Value> {
+        let mut options = HashMap::new();
+        options.insert("backend".to_string(), json!("mock_pacs"));
+        options.insert("cache_key".to_string(), json!(cache_key));
+        options
+    }
+
+    fn broad_study_query() -> RequestEnvelope<Value> {
+        RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .metadata_entry("path", "studies")
+            .metadata_entry("dimse_op", "find")
+            .original_data(json!({}))
+            .normalized_data(Some(json!({
+                "dimse_identifier": {
+                    "0020000D": { "vr": "UI", "Value": [] },
+                    "00100010": { "vr": "PN", "Value": [] }
+                }
+            })))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_name_query_is_not_broad() {
+        use crate::models::middleware::types::dicomweb_bridge::DicomwebBridgeMiddleware;
+
+        let bridge = DicomwebBridgeMiddleware::new();
+        let cache = StudyCacheMiddleware::new(parse_config(&cache_options("fuzzy")).unwrap());
+        for names in [vec!["smith"], vec!["smith", "jones"]] {
+            let query_params = HashMap::from([
+                (
+                    "PatientName".to_string(),
+                    names.iter().map(|n| n.to_string()).collect(),
+                ),
+                ("fuzzymatching".to_string(), vec!["true".to_string()]),
+            ]);
+            let request = RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri("/dicomweb/studies")
+                .query_params(query_params)
+                .metadata_entry("path", "studies")
+                .original_data(json!({}))
+                .build()
+                .unwrap();
+            let request = bridge.left(request).await.unwrap();
+            assert!(
+                cache.cached_query(&request).is_none(),
+                "{:?} served from the sweep",
+                names
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_swept_results_served_without_backend() {
+        let cache_key = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let cache = parse_config(&cache_options(&cache_key)).unwrap();
+
+        let count = sweep_mock(&cache).await;
+        assert!(count > 0, "mock backend should return studies");
+
+        let middleware = StudyCacheMiddleware::new(cache);
+        let result = middleware.left(broad_study_query()).await.unwrap();
+
+        assert_eq!(
+            result.request_details.metadata.get("skip_backends"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(
+            result.request_details.metadata.get("study_cache"),
+            Some(&"hit".to_string())
+        );
+        assert!(result.request_details.metadata.contains_key("retrieved_at"));
+        let nd = result.normalized_data.unwrap();
+        assert_eq!(nd["operation"], "find");
+        assert_eq!(nd["matches"].as_array().unwrap().len(), count);
+
+        let _ = cache_storage()
+            .unwrap()
+            .remove_str(&cache_path(&cache_key))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_filtered_query_bypasses_cache() {
+        let cache_key = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let cache = parse_config(&cache_options(&cache_key)).unwrap();
+        sweep_mock(&cache).await;
+
+        let mut envelope = broad_study_query();
+        envelope.normalized_data = Some(json!({
+            "dimse_identifier": { "00100020": { "vr": "LO", "Value": ["12345"] } }
+        }));
+
+        let middleware = StudyCacheMiddleware::new(cache);
+        let result = middleware.left(envelope).await.unwrap();
+        assert!(!result.request_details.metadata.contains_key("skip_backends"));
+
+        let _ = cache_storage()
+            .unwrap()
+            .remove_str(&cache_path(&cache_key))
+            .await;
+    }
+
+    /// Store a sweep result directly, as if the backend had returned `matches`
+    async fn write_sweep(cache_key: &str, matches: Value) {
+        let entry = CachedStudies {
+            swept_at: current_timestamp(),
+            matches,
+        };
+        cache_storage()
+            .unwrap()
+            .write_file_str(&cache_path(cache_key), &serde_json::to_vec(&entry).unwrap())
+            .await
+            .unwrap();
+    }
+
+    fn study(uid: &str, date: &str) -> Value {
+        json!({
+            "0020000D": { "vr": "UI", "Value": [uid] },
+            "00080020": { "vr": "DA", "Value": [date] }
+        })
+    }
+
+    /// A /studies request as `dicomweb_bridge` prepares it for the backend
+    async fn bridged_study_query(params: &[(&str, &str)]) -> RequestEnvelope<Value> {
+        use crate::models::middleware::types::dicomweb_bridge::DicomwebBridgeMiddleware;
+
+        let query_params: HashMap<String, Vec<String>> = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+            .collect();
+        let request = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicomweb/studies")
+            .query_params(query_params)
+            .metadata_entry("path", "studies")
+            .original_data(json!({}))
+            .build()
+            .unwrap();
+        DicomwebBridgeMiddleware::new().left(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_query_outside_sweep_window_misses_cache() {
+        let cache_key = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let mut options = cache_options(&cache_key);
+        options.insert("days_back".to_string(), json!(30));
+        let cache = parse_config(&options).unwrap();
+        let day = |days_ago: i64| {
+            (chrono::Local::now().date_naive() - chrono::Duration::days(days_ago))
+                .format("%Y%m%d")
+                .to_string()
+        };
+        write_sweep(
+            &cache_key,
+            json!([study("1.2.1", &day(2)), study("1.2.2", &day(20))]),
+        )
+        .await;
+        let middleware = StudyCacheMiddleware::new(cache);
+        let served = |result: &RequestEnvelope<Value>| {
+            result.request_details.metadata.get("skip_backends") == Some(&"true".to_string())
+        };
+
+        // Older than the sweep reaches back, or not limited by date at all
+        let since = format!("{}-", day(60));
+        for params in [
+            vec![("StudyDate", "20000101-20000131")],
+            vec![("StudyDate", since.as_str())],
+            vec![],
+        ] {
+            let request = bridged_study_query(&params).await;
+            let result = middleware.left(request).await.unwrap();
+            assert!(!served(&result), "{:?} served from the sweep", params);
+        }
+
+        // Inside the window the cached matches are narrowed to the range
+        let since = format!("{}-", day(10));
+        let request = bridged_study_query(&[("StudyDate", since.as_str())]).await;
+        let result = middleware.left(request).await.unwrap();
+        assert!(served(&result));
+        let matches = &result.normalized_data.unwrap()["matches"];
+        assert_eq!(matches.as_array().unwrap().len(), 1);
+        assert_eq!(matches[0]["0020000D"]["Value"][0], "1.2.1");
+
+        // Attributes the sweep did not fetch, and filters it did not apply, go to the backend
+        for params in [
+            vec![
+                ("StudyDate", since.as_str()),
+                ("includefield", "ReferringPhysicianName"),
+            ],
+            vec![("StudyDate", since.as_str()), ("PatientID", "12345")],
+        ] {
+            let request = bridged_study_query(&params).await;
+            let result = middleware.left(request).await.unwrap();
+            assert!(!served(&result), "{:?} served from the sweep", params);
+        }
+
+        let _ = cache_storage()
+            .unwrap()
+            .remove_str(&cache_path(&cache_key))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_cached_matches_paged_by_limit_and_offset() {
+        use crate::models::envelope::envelope::ResponseDetails;
+        use crate::models::middleware::types::dicomweb_bridge::DicomwebBridgeMiddleware;
+
+        let cache_key = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let cache = parse_config(&cache_options(&cache_key)).unwrap();
+        write_sweep(
+            &cache_key,
+            json!([
+                study("1.2.1", "20240101"),
+                study("1.2.2", "20240102"),
+                study("1.2.3", "20240103")
+            ]),
+        )
+        .await;
+
+        let request = bridged_study_query(&[("limit", "1"), ("offset", "1")]).await;
+        let request = StudyCacheMiddleware::new(cache)
+            .left(request)
+            .await
+            .unwrap();
+        assert_eq!(
+            request.request_details.metadata.get("study_cache"),
+            Some(&"hit".to_string())
+        );
+        let response = ResponseEnvelope {
+            request_details: request.request_details,
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: json!({}),
+            normalized_data: request.normalized_data,
+            normalized_snapshot: None,
+        };
+        let result = DicomwebBridgeMiddleware::new()
+            .right(response)
+            .await
+            .unwrap();
+        let studies = result.normalized_data.unwrap()["dicomweb_data"].clone();
+        assert_eq!(studies.as_array().unwrap().len(), 1);
+        assert_eq!(studies[0]["0020000D"]["Value"][0], "1.2.2");
+
+        let _ = cache_storage()
+            .unwrap()
+            .remove_str(&cache_path(&cache_key))
+            .await;
+    }
+
+    #[test]
+    fn test_parse_config_requires_backend() {
+        assert!(parse_config(&HashMap::new()).is_err());
+        let cache = parse_config(&cache_options("recent")).unwrap();
+        assert_eq!(cache.interval_secs, 300);
+        assert_eq!(cache.stale_after_secs, 600);
+    }
+}
@@ -9,8 +9,12 @@ use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct JoltTransformMiddlewareConfig {
-    /// Path to the JOLT spec JSON file
+    /// Path to the JOLT spec file
+    #[serde(default)]
     pub spec_path: String,
+    /// JOLT spec files applied in sequence, instead of a single `spec_path`
+    #[serde(default)]
+    pub spec_paths: Vec<String>,
     /// Apply transform on which direction: "left", "right", or "both" (default)
     #[serde(default = "default_apply")]
     pub apply: String,
@@ -20,6 +24,12 @@ pub struct JoltTransformMiddlewareConfig {
     /// Whether to inject envelope context (query_params, headers, target_details) into transform
     #[serde(default = "default_inject_context")]
     pub inject_context: bool,
+    /// JSON Pointer to the part of the transform input to apply the spec to
+    #[serde(default)]
+    pub subtree_pointer: Option<String>,
+    /// Reload the spec whenever `spec_path` changes on disk
+    #[serde(default)]
+    pub watch: bool,
 }
 
 fn default_apply() -> String {
@@ -38,8 +48,10 @@ impl From<JoltTransformMiddlewareConfig> for TransformConfig {
     fn from(config: JoltTransformMiddlewareConfig) -> Self {
         TransformConfig {
             spec_path: config.spec_path,
+            spec_paths: config.spec_paths,
             apply: config.apply,
             fail_on_error: config.fail_on_error,
+            subtree_pointer: config.subtree_pointer,
         }
     }
 }
@@ -52,8 +64,12 @@ pub struct JoltTransformMiddleware {
 impl JoltTransformMiddleware {
     pub fn new(config: JoltTransformMiddlewareConfig) -> Result<Self, String> {
         let transform_config: TransformConfig = config.clone().into();
-        let engine = JoltTransformEngine::new(transform_config)
-            .map_err(|e| format!("Failed to create JOLT transform engine: {}", e))?;
+        let engine = if config.watch {
+            JoltTransformEngine::new_watched(transform_config)
+        } else {
+            JoltTransformEngine::new(transform_config)
+        }
+        .map_err(|e| format!("Failed to create JOLT transform engine: {}", e))?;
 
         tracing::info!(
             "JOLT transform middleware initialized (context injection: {})",
@@ -216,21 +232,41 @@ pub fn parse_config(
     options: &HashMap<String, Value>,
     transforms_path: Option<&str>,
 ) -> Result<JoltTransformMiddlewareConfig, String> {
-    let spec_path_raw = options
+    // Resolve spec paths relative to transforms_path if provided
+    let resolve = |raw: &str| match transforms_path {
+        Some(base_path) => std::path::Path::new(base_path)
+            .join(raw)
+            .to_string_lossy()
+            .to_string(),
+        None => raw.to_string(),
+    };
+
+    let spec_path = options
         .get("spec_path")
         .and_then(|v| v.as_str())
-        .ok_or("Missing required 'spec_path' in transform middleware config")?
-        .to_string();
-
-    // Resolve spec_path relative to transforms_path if provided
-    let spec_path = if let Some(base_path) = transforms_path {
-        use std::path::Path;
-        let full_path = Path::new(base_path).join(&spec_path_raw);
-        full_path.to_string_lossy().to_string()
-    } else {
-        spec_path_raw
+        .map(resolve)
+        .unwrap_or_default();
+
+    let spec_paths = match options.get("spec_paths") {
+        Some(Value::Array(paths)) => paths
+            .iter()
+            .map(|p| {
+                p.as_str()
+                    .map(resolve)
+                    .ok_or("'spec_paths' must be an array of strings")
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        Some(_) => return Err("'spec_paths' must be an array of strings".to_string()),
+        None => Vec::new(),
     };
 
+    if spec_path.is_empty() && spec_paths.is_empty() {
+        return Err(
+            "Missing required 'spec_path' or 'spec_paths' in transform middleware config"
+                .to_string(),
+        );
+    }
+
     let apply = options
         .get("apply")
         .and_then(|v| v.as_str())
@@ -247,11 +283,24 @@ pub fn parse_config(
         .and_then(|v| v.as_bool())
         .unwrap_or_else(default_inject_context);
 
+    let subtree_pointer = options
+        .get("subtree_pointer")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let watch = options
+        .get("watch")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
     Ok(JoltTransformMiddlewareConfig {
         spec_path,
+        spec_paths,
         apply,
         fail_on_error,
         inject_context,
+        subtree_pointer,
+        watch,
     })
 }
 
@@ -303,9 +352,12 @@ mod tests {
 
         let config = JoltTransformMiddlewareConfig {
             spec_path: temp_file.path().to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
             apply: "left".to_string(),
             fail_on_error: true,
             inject_context: false,
+            subtree_pointer: None,
+            watch: false,
         };
 
         let middleware = JoltTransformMiddleware::new(config).unwrap();
@@ -351,9 +403,12 @@ mod tests {
 
         let config = JoltTransformMiddlewareConfig {
             spec_path: temp_file.path().to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
             apply: "right".to_string(),
             fail_on_error: true,
             inject_context: false,
+            subtree_pointer: None,
+            watch: false,
         };
 
         let middleware = JoltTransformMiddleware::new(config).unwrap();
@@ -389,9 +444,12 @@ mod tests {
 
         let config = JoltTransformMiddlewareConfig {
             spec_path: temp_file.path().to_string_lossy().to_string(),
+            spec_paths: Vec::new(),
             apply: "both".to_string(),
             fail_on_error: true,
             inject_context: false,
+            subtree_pointer: None,
+            watch: false,
         };
         let middleware = JoltTransformMiddleware::new(config).unwrap();
 
@@ -474,9 +532,12 @@ mod tests {
         );
         let cfg = JoltTransformMiddlewareConfig {
             spec_path,
+            spec_paths: Vec::new(),
             apply: "left".into(),
             fail_on_error: true,
             inject_context: true,
+            subtree_pointer: None,
+            watch: false,
         };
         let mw = JoltTransformMiddleware::new(cfg).unwrap();
 
@@ -541,9 +602,12 @@ mod tests {
         );
         let cfg = JoltTransformMiddlewareConfig {
             spec_path,
+            spec_paths: Vec::new(),
             apply: "right".into(),
             fail_on_error: true,
             inject_context: false,
+            subtree_pointer: None,
+            watch: false,
         };
         let mw = JoltTransformMiddleware::new(cfg).unwrap();
 
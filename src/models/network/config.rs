@@ -26,6 +26,21 @@ pub struct HttpConfig {
     pub bind_address: String,
     #[serde(default = "default_bind_port")]
     pub bind_port: u16,
+    /// Serve HTTPS on `bind_port` instead of plain HTTP
+    #[serde(default)]
+    pub tls: Option<HttpTlsConfig>,
+}
+
+/// Native TLS for the HTTP listener
+#[derive(Debug, Deserialize, Clone)]
+pub struct HttpTlsConfig {
+    /// PEM certificate chain, leaf first
+    pub cert_path: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+    /// When set, a plain-HTTP listener on this port redirects every request to HTTPS
+    #[serde(default)]
+    pub redirect_http_port: Option<u16>,
 }
 
 fn default_bind_address() -> String {
@@ -41,6 +56,7 @@ impl Default for HttpConfig {
         Self {
             bind_address: default_bind_address(),
             bind_port: default_bind_port(),
+            tls: None,
         }
     }
 }
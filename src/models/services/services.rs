@@ -75,6 +75,9 @@ fn create_builtin_service(
         "fhir" => Ok(Box::new(
             crate::models::services::types::fhir::FhirEndpoint {},
         )),
+        "fhir_imagingstudy" => Ok(Box::new(
+            crate::models::services::types::fhir_imagingstudy::FhirImagingStudyEndpoint {},
+        )),
         "dicom" => Ok(Box::new(
             crate::models::services::types::dicom::DicomEndpoint {
                 local_aet: None,
@@ -82,6 +85,7 @@ fn create_builtin_service(
                 host: None,
                 port: None,
                 use_tls: None,
+                ids: crate::utils::IdGenerator::default(),
             },
         )),
         "dicomweb" => Ok(Box::new(
@@ -1,5 +1,5 @@
 use crate::config::config::ConfigError;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
 use crate::models::services::services::{ServiceHandler, ServiceType};
 use async_trait::async_trait;
 use axum::{body::Body, response::Response};
@@ -8,15 +8,41 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::globals::get_storage;
+use crate::log_context::{OperationContext, CORRELATION_ID};
+use crate::models::services::types::dicom_charset::CharsetTranscoder;
+use crate::models::services::types::dicom_coalesce::QueryCoalescer;
+use crate::models::services::types::dicom_layout::{LayoutTags, StorageLayout};
+use crate::models::services::types::dicom_transfer::MoveTransfer;
 use crate::router::route_config::RouteConfig;
-use crate::utils::Error;
+use crate::storage::{FilesystemStorage, StorageBackend};
+use crate::utils::{Error, IdGenerator};
 use dicom_json_tool as djt;
-use dimse::types::{FindQuery, GetQuery, QueryLevel};
+use dicom_object::InMemDicomObject;
+use dimse::config::{MAX_MAX_PDU, MAX_PRESENTATION_CONTEXTS, MIN_MAX_PDU};
+use dimse::types::{FindQuery, GetQuery, MatchType, QueryLevel};
 use dimse::{DimseConfig, DimseScu, RemoteNode};
+use once_cell::sync::Lazy;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::warn;
-use uuid::Uuid;
+
+/// DIMSE operations the dicom backend understands
+const VALID_DIMSE_OPS: [&str; 5] = ["echo", "find", "get", "move", "store"];
+
+/// Sources consulted when resolving the DIMSE operation for a backend request
+const DIMSE_OP_SOURCES: [&str; 5] = ["target", "request", "operation", "retrieve_mode", "path"];
+
+/// Default resolution order (see `dimse_op_precedence`)
+const DEFAULT_DIMSE_OP_PRECEDENCE: [&str; 4] = ["target", "request", "retrieve_mode", "path"];
+
+/// Request metadata set by a C-FIND that coalesced callers copy from the shared result
+const PROVENANCE_METADATA: [&str; 2] = ["source_aet", "retrieved_at"];
+
+/// In-flight C-FINDs shared by identical queries (see `coalesce_window_ms`)
+static FIND_COALESCER: Lazy<QueryCoalescer<(Value, HashMap<String, String>)>> =
+    Lazy::new(QueryCoalescer::new);
 
 #[derive(Debug, Deserialize)]
 pub struct DicomEndpoint {
@@ -25,6 +51,9 @@ pub struct DicomEndpoint {
     pub host: Option<String>,
     pub port: Option<u16>,
     pub use_tls: Option<bool>,
+    /// Folder ids for move/get retrievals
+    #[serde(skip)]
+    pub ids: IdGenerator,
 }
 
 impl DicomEndpoint {
@@ -45,6 +74,32 @@ impl DicomEndpoint {
             .or_else(|| Some("HARMONY_DICOM".to_string()))
     }
 
+    /// Add the transfer syntax an instance was retrieved in as Transfer Syntax UID (0002,0010),
+    /// which the dataset itself does not carry
+    fn record_transfer_syntax(json: &mut serde_json::Value, transfer_syntax: Option<&str>) {
+        if let (Some(ts), Some(obj)) = (transfer_syntax, json.as_object_mut()) {
+            obj.entry("00020010")
+                .or_insert_with(|| serde_json::json!({ "vr": "UI", "Value": [ts] }));
+        }
+    }
+
+    /// Convert a received dataset to DICOM JSON, trimming value padding and transcoding text to
+    /// UTF-8 when enabled
+    fn dataset_to_json(
+        obj: &InMemDicomObject,
+        normalize_padding: bool,
+        charset: Option<&CharsetTranscoder>,
+    ) -> Option<Value> {
+        let mut json = djt::identifier_to_json_value(obj).ok()?;
+        if normalize_padding {
+            djt::trim_padding(&mut json);
+        }
+        if let Some(charset) = charset {
+            charset.apply(&mut json);
+        }
+        Some(json)
+    }
+
     /// Create a remote node from configuration
     fn create_remote_node(
         &self,
@@ -98,8 +153,378 @@ impl DicomEndpoint {
             node = node.with_tls();
         }
 
+        if let Some(max) = options.get("max_presentation_contexts") {
+            let max = max
+                .as_u64()
+                .filter(|m| (1..=MAX_PRESENTATION_CONTEXTS as u64).contains(m))
+                .ok_or_else(|| ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason: format!(
+                        "max_presentation_contexts must be an integer between 1 and {}",
+                        MAX_PRESENTATION_CONTEXTS
+                    ),
+                })?;
+            node = node.with_max_presentation_contexts(max as usize);
+        }
+
+        // CA bundle and client certificate for TLS to this node
+        if let Some(tls) = options.get("client_tls") {
+            node.tls = Some(serde_json::from_value(tls.clone()).map_err(|e| {
+                ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason: format!("Invalid client_tls: {}", e),
+                }
+            })?);
+        }
+
+        // Retries for associations this node refuses or drops
+        if let Some(retry) = options.get("retry") {
+            let invalid = |e: String| ConfigError::InvalidEndpoint {
+                name: "dicom".to_string(),
+                reason: format!("Invalid retry policy: {}", e),
+            };
+            let policy: dimse::RetryPolicy =
+                serde_json::from_value(retry.clone()).map_err(|e| invalid(e.to_string()))?;
+            policy.validate().map_err(|e| invalid(e.to_string()))?;
+            node = node.with_retry(policy);
+        }
+
         Ok(node)
     }
+
+    /// The request identifier, preferring `normalized_data.dimse_identifier` over the body
+    fn request_identifier(envelope: &RequestEnvelope<Vec<u8>>) -> Value {
+        if let Some(ident) = envelope
+            .normalized_data
+            .as_ref()
+            .and_then(|nd| nd.get("dimse_identifier"))
+            .filter(|ident| ident.is_object())
+        {
+            return ident.clone();
+        }
+        match serde_json::from_slice::<Value>(&envelope.original_data) {
+            Ok(body @ Value::Object(_)) => djt::parse_wrapper_or_identifier(&body).1,
+            _ => Value::Null,
+        }
+    }
+
+    /// Match types from the wrapper's `query_metadata` for the keys present in `identifier`
+    ///
+    /// Keys whose match type is missing or cannot be built keep their literal first value.
+    fn query_matches(
+        identifier: &Value,
+        metadata: &djt::model::QueryMetadata,
+    ) -> Vec<(String, MatchType)> {
+        let mut matches = Vec::new();
+        for (tag, entry) in metadata.0.iter() {
+            let (Some(match_type), Some(element)) = (&entry.match_type, identifier.get(tag)) else {
+                continue;
+            };
+            let values: Vec<String> = element
+                .get("Value")
+                .and_then(|v| v.as_array())
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|v| {
+                            v.as_str()
+                                .or_else(|| v.get("Alphabetic").and_then(|a| a.as_str()))
+                                .map(str::to_string)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            match MatchType::from_metadata(match_type, &values) {
+                Ok(key_match) => matches.push((tag.clone(), key_match)),
+                Err(e) => warn!("Ignoring query metadata for {}: {}", tag, e),
+            }
+        }
+        matches
+    }
+
+    /// The configured storage backend, or filesystem storage under `./tmp` as used when none
+    /// is set
+    fn storage_backend() -> Option<Arc<dyn StorageBackend>> {
+        get_storage().or_else(|| {
+            FilesystemStorage::new("./tmp")
+                .ok()
+                .map(|storage| Arc::new(storage) as Arc<dyn StorageBackend>)
+        })
+    }
+
+    /// Give every file directly under `dir` a `.dcm` extension and return how many there are
+    ///
+    /// Renames in place, so only for filesystem backends.
+    async fn ensure_dcm_extensions(storage: &dyn StorageBackend, dir: &str) -> usize {
+        let mut count = 0;
+        for name in storage.list_dir_str(dir).await.unwrap_or_default() {
+            let p = storage.subpath_str(dir).join(&name);
+            if !p.is_file() {
+                continue;
+            }
+            count += 1;
+            let ext = p
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if ext != "dcm" {
+                let _ = std::fs::rename(&p, p.with_extension("dcm"));
+            }
+        }
+        count
+    }
+
+    /// Key identifying identical queries: remote node, normalized path and sorted query
+    /// parameters, plus the identifier middleware built from them
+    fn coalesce_key(
+        &self,
+        envelope: &RequestEnvelope<Vec<u8>>,
+        options: &HashMap<String, Value>,
+    ) -> String {
+        let details = &envelope.request_details;
+        let remote = self
+            .create_remote_node(options)
+            .map(|node| format!("{}@{}:{}", node.ae_title, node.host, node.port))
+            .unwrap_or_default();
+        let path = details.uri.split('?').next().unwrap_or_default();
+        let mut query: Vec<String> = details
+            .query_params
+            .iter()
+            .map(|(key, values)| format!("{}={}", key, values.join(",")))
+            .collect();
+        query.sort();
+        format!(
+            "{} {}?{} {}",
+            remote,
+            path.trim_end_matches('/'),
+            query.join("&"),
+            Self::request_identifier(envelope)
+        )
+    }
+
+    /// Resolve the DIMSE operation for a backend request.
+    ///
+    /// Sources are consulted in the order given by the `dimse_op_precedence` option
+    /// (default: `["target", "request", "retrieve_mode", "path"]`):
+    /// - `target`: `target_details.metadata["dimse_op"]` (set by middleware)
+    /// - `request`: `request_details.metadata["dimse_op"]`
+    /// - `operation`: `request_details.metadata["operation"]` (set by protocol adapters)
+    /// - `retrieve_mode`: the `dimse_retrieve_mode` backend option
+    /// - `path`: the request path, when it names an operation (direct HTTP->DICOM calls)
+    ///
+    /// Falls back to "get" when no source yields a value. Returns `Err` with the offending
+    /// value when the resolved operation is not one of echo/find/get/move/store.
+    fn resolve_dimse_op(
+        envelope: &RequestEnvelope<Vec<u8>>,
+        options: &HashMap<String, Value>,
+    ) -> Result<String, String> {
+        let precedence: Vec<String> = options
+            .get("dimse_op_precedence")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.to_lowercase())
+                    .collect()
+            })
+            .unwrap_or_else(|| {
+                DEFAULT_DIMSE_OP_PRECEDENCE
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            });
+
+        let resolved = precedence.iter().find_map(|source| match source.as_str() {
+            "target" => envelope
+                .target_details
+                .as_ref()
+                .and_then(|td| td.metadata.get("dimse_op"))
+                .cloned(),
+            "request" => envelope.request_details.metadata.get("dimse_op").cloned(),
+            "operation" => envelope.request_details.metadata.get("operation").cloned(),
+            "retrieve_mode" => options
+                .get("dimse_retrieve_mode")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            "path" => envelope
+                .request_details
+                .metadata
+                .get("path")
+                .map(|s| s.trim_start_matches('/').to_lowercase())
+                .filter(|p| VALID_DIMSE_OPS.contains(&p.as_str())),
+            _ => None,
+        });
+
+        let op = resolved.unwrap_or_else(|| "get".to_string());
+        let normalized = op.trim_start_matches('/').to_lowercase();
+        if VALID_DIMSE_OPS.contains(&normalized.as_str()) {
+            Ok(normalized)
+        } else {
+            Err(op)
+        }
+    }
+
+    /// Whether C-FIND "pending with warning" (0xFF01) responses should be surfaced to clients.
+    ///
+    /// Controlled by the `find_pending_warning` option: "warn" (default) attaches a warning
+    /// to the response, "ignore" treats 0xFF01 exactly like 0xFF00.
+    fn surface_pending_warnings(options: &HashMap<String, Value>) -> bool {
+        options
+            .get("find_pending_warning")
+            .and_then(|v| v.as_str())
+            .map(|s| !s.eq_ignore_ascii_case("ignore"))
+            .unwrap_or(true)
+    }
+
+    /// Warning entry describing matches returned with status 0xFF01
+    pub(crate) fn pending_warning_entry(count: usize) -> Value {
+        serde_json::json!({
+            "status": "FF01",
+            "count": count,
+            "message": "One or more requested optional keys were not supported by the remote node; results may be missing fields"
+        })
+    }
+
+    /// Read the identifier of an instance a C-MOVE brought back and, with `to_storage`, move
+    /// the staged file into the storage backend. Fails when the instance did not end up stored.
+    async fn persist_moved_instance(
+        item: dimse::types::DatasetStream,
+        normalize_padding: bool,
+        charset: Option<&CharsetTranscoder>,
+        to_storage: bool,
+        storage_layout: Option<&StorageLayout>,
+        folder_id: &str,
+    ) -> Result<Option<Value>, String> {
+        let dimse::types::DatasetStream::File {
+            ref path,
+            ref metadata,
+            ..
+        } = item
+        else {
+            return Err("instance was not received into a file".to_string());
+        };
+        // Capture identifier metadata before a non-filesystem backend takes the staged file;
+        // pixel data is not needed
+        let identifier = dicom_object::OpenFileOptions::new()
+            .read_until(dicom_dictionary_std::tags::PIXEL_DATA)
+            .open_file(path)
+            .ok()
+            .and_then(|obj| Self::dataset_to_json(&obj, normalize_padding, charset))
+            .map(|mut json| {
+                Self::record_transfer_syntax(&mut json, metadata.transfer_syntax.as_deref());
+                json
+            });
+
+        // For filesystem backend, files are already in folder_path.
+        // For non-filesystem, stream and persist via storage backend.
+        if to_storage {
+            let storage = get_storage().ok_or("no storage backend configured")?;
+            let bytes = tokio::fs::read(path)
+                .await
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            // Normalize filename to .dcm
+            let base = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("instance");
+            let mut name = base.to_string();
+            if !name.ends_with(".dcm") {
+                name.push_str(".dcm");
+            }
+            let rel_dir = storage_layout
+                .map(|layout| {
+                    layout
+                        .relative_dir(&LayoutTags::from_file(path), folder_id)
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .map(|dir| format!("{}/{}", folder_id, dir))
+                .unwrap_or_else(|| folder_id.to_string());
+            let rel = format!("dimse/{}/{}", rel_dir, name);
+            storage
+                .write_file_str(&rel, &bytes)
+                .await
+                .map_err(|e| format!("{}: {}", rel, e))?;
+            // Cleanup staged file
+            let _ = tokio::fs::remove_file(path).await;
+        } else if !path.is_file() {
+            return Err(format!("{} is missing", path.display()));
+        }
+        Ok(identifier)
+    }
+
+    /// Ports from the `incoming_store_port_range` option, written as `[first, last]`, if set
+    pub(crate) fn incoming_store_port_range(
+        options: &HashMap<String, Value>,
+    ) -> Result<Option<(u16, u16)>, String> {
+        let Some(range) = options.get("incoming_store_port_range") else {
+            return Ok(None);
+        };
+        let port = |v: &Value| v.as_u64().filter(|p| (1..=65535).contains(p)).map(|p| p as u16);
+        match range.as_array().map(Vec::as_slice) {
+            Some([first, last]) => match (port(first), port(last)) {
+                (Some(first), Some(last)) if first <= last => Ok(Some((first, last))),
+                _ => Err("incoming_store_port_range must be two ports, lowest first".to_string()),
+            },
+            _ => Err("incoming_store_port_range must be an array of two ports".to_string()),
+        }
+    }
+
+    /// AE titles from the `incoming_store_aets` option, a table of ports in
+    /// `incoming_store_port_range` to the AE title the PACS knows at each
+    pub(crate) fn incoming_store_aets(
+        options: &HashMap<String, Value>,
+    ) -> Result<HashMap<u16, String>, String> {
+        let Some(aets) = options.get("incoming_store_aets") else {
+            return Ok(HashMap::new());
+        };
+        let table = aets
+            .as_object()
+            .ok_or("incoming_store_aets must be a table of ports to AE titles")?;
+        let range = Self::incoming_store_port_range(options)?
+            .ok_or("incoming_store_aets needs incoming_store_port_range")?;
+        table
+            .iter()
+            .map(|(port, aet)| {
+                let port = port
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|p| (range.0..=range.1).contains(p))
+                    .ok_or_else(|| {
+                        format!(
+                            "incoming_store_aets port '{}' is not in incoming_store_port_range",
+                            port
+                        )
+                    })?;
+                let aet = aet
+                    .as_str()
+                    .filter(|a| (1..=16).contains(&a.len()))
+                    .ok_or_else(|| {
+                        format!(
+                            "incoming_store_aets AE title for port {} must be 1-16 characters",
+                            port
+                        )
+                    })?;
+                Ok((port, aet.to_string()))
+            })
+            .collect()
+    }
+
+    /// Circuit breaker thresholds from the `circuit_breaker` option, if set
+    pub(crate) fn circuit_breaker(
+        options: &HashMap<String, Value>,
+    ) -> Result<Option<dimse::CircuitBreakerConfig>, String> {
+        let Some(breaker) = options.get("circuit_breaker") else {
+            return Ok(None);
+        };
+        let breaker: dimse::CircuitBreakerConfig = serde_json::from_value(breaker.clone())
+            .map_err(|e| format!("Invalid circuit_breaker: {}", e))?;
+        breaker
+            .validate()
+            .map_err(|e| format!("Invalid circuit_breaker: {}", e))?;
+        Ok(Some(breaker))
+    }
 }
 
 #[async_trait]
@@ -109,6 +534,108 @@ impl ServiceType for DicomEndpoint {
             // Backend usage - validate remote connection parameters
             self.create_remote_node(options)?;
             
+            if let Some(precedence) = options.get("dimse_op_precedence") {
+                let sources = precedence.as_array().ok_or_else(|| ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason: "dimse_op_precedence must be an array of strings".to_string(),
+                })?;
+                for source in sources {
+                    let valid = source
+                        .as_str()
+                        .map(|s| DIMSE_OP_SOURCES.contains(&s.to_lowercase().as_str()))
+                        .unwrap_or(false);
+                    if !valid {
+                        return Err(ConfigError::InvalidEndpoint {
+                            name: "dicom".to_string(),
+                            reason: format!(
+                                "Invalid dimse_op_precedence entry {}; expected one of: {}",
+                                source,
+                                DIMSE_OP_SOURCES.join(", ")
+                            ),
+                        });
+                    }
+                }
+            }
+
+            if let Some(mode) = options.get("find_pending_warning") {
+                let valid = mode
+                    .as_str()
+                    .map(|m| matches!(m.to_lowercase().as_str(), "warn" | "ignore"))
+                    .unwrap_or(false);
+                if !valid {
+                    return Err(ConfigError::InvalidEndpoint {
+                        name: "dicom".to_string(),
+                        reason: "find_pending_warning must be either 'warn' or 'ignore'".to_string(),
+                    });
+                }
+            }
+
+            if let Some(window) = options.get("coalesce_window_ms") {
+                if !window.is_u64() {
+                    return Err(ConfigError::InvalidEndpoint {
+                        name: "dicom".to_string(),
+                        reason: "coalesce_window_ms must be a non-negative integer".to_string(),
+                    });
+                }
+            }
+
+            if let Some(normalize) = options.get("normalize_padding") {
+                if !normalize.is_boolean() {
+                    return Err(ConfigError::InvalidEndpoint {
+                        name: "dicom".to_string(),
+                        reason: "normalize_padding must be a boolean".to_string(),
+                    });
+                }
+            }
+
+            if let Err(reason) = CharsetTranscoder::from_options(options) {
+                return Err(ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason,
+                });
+            }
+
+            if let Err(reason) = Self::incoming_store_port_range(options) {
+                return Err(ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason,
+                });
+            }
+
+            if let Err(reason) = Self::incoming_store_aets(options) {
+                return Err(ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason,
+                });
+            }
+
+            if let Err(reason) = MoveTransfer::from_options(options) {
+                return Err(ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason,
+                });
+            }
+
+            if let Err(reason) = Self::circuit_breaker(options) {
+                return Err(ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason,
+                });
+            }
+
+            if let Some(layout) = options.get("storage_layout") {
+                let template = layout
+                    .as_str()
+                    .ok_or_else(|| ConfigError::InvalidEndpoint {
+                        name: "dicom".to_string(),
+                        reason: "storage_layout must be a string".to_string(),
+                    })?;
+                StorageLayout::parse(template).map_err(|reason| ConfigError::InvalidEndpoint {
+                    name: "dicom".to_string(),
+                    reason,
+                })?;
+            }
+
             // Validate dimse_retrieve_mode option if provided
             if let Some(retrieve_mode) = options.get("dimse_retrieve_mode") {
                 if let Some(mode_str) = retrieve_mode.as_str() {
@@ -227,11 +754,67 @@ impl ServiceHandler<Value> for DicomEndpoint {
         mut envelope: RequestEnvelope<Vec<u8>>,
         options: &HashMap<String, Value>,
     ) -> Result<ResponseEnvelope<Vec<u8>>, Error> {
+        // Reject unknown operations up front with a client error rather than a failed 200
+        if let Err(op) = Self::resolve_dimse_op(&envelope, options) {
+            let body = serde_json::json!({
+                "success": false,
+                "error": format!(
+                    "Invalid DIMSE operation: '{}'. Valid operations are: {}",
+                    op,
+                    VALID_DIMSE_OPS.join(", ")
+                )
+            });
+            let mut headers = HashMap::new();
+            headers.insert("content-type".to_string(), "application/json".to_string());
+            let mut response_envelope = ResponseEnvelope::from_backend(
+                envelope.request_details.clone(),
+                400,
+                headers,
+                serde_json::to_vec(&body).unwrap_or_default(),
+                None,
+            );
+            response_envelope.normalized_data = Some(body);
+            return Ok(response_envelope);
+        }
+
+        // Identical concurrent C-FINDs share one association when coalescing is enabled
+        let coalesce_window = options
+            .get("coalesce_window_ms")
+            .and_then(|v| v.as_u64())
+            .map(Duration::from_millis)
+            .filter(|_| Self::resolve_dimse_op(&envelope, options).as_deref() == Ok("find"));
+
         // Backend usage - perform DIMSE SCU operations
-        envelope = self
-            .handle_backend_request(&mut envelope, options)
-            .await
-            .expect("DICOM response failed");
+        envelope = match coalesce_window {
+            Some(window) => {
+                let key = self.coalesce_key(&envelope, options);
+                let leader_envelope = &mut envelope;
+                let (result, provenance) = FIND_COALESCER
+                    .run(&key, window, move || async move {
+                        let done = self
+                            .handle_backend_request(leader_envelope, options)
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        let provenance = PROVENANCE_METADATA
+                            .iter()
+                            .filter_map(|k| {
+                                let value = done.request_details.metadata.get(*k)?;
+                                Some((k.to_string(), value.clone()))
+                            })
+                            .collect();
+                        Ok((done.normalized_data.unwrap_or(Value::Null), provenance))
+                    })
+                    .await
+                    .map_err(Error::from)?;
+                envelope.request_details.metadata.extend(provenance);
+                envelope.normalized_data = Some(result);
+                envelope
+            }
+            None => self
+                .handle_backend_request(&mut envelope, options)
+                .await
+                .expect("DICOM response failed"),
+        };
 
         // Detect error conditions and set appropriate HTTP status
         let status = if let Some(ref normalized) = envelope.normalized_data {
@@ -296,7 +879,7 @@ impl ServiceHandler<Value> for DicomEndpoint {
     async fn endpoint_outgoing_response(
         &self,
         envelope: ResponseEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         // Build response from ResponseEnvelope
         let status = http::StatusCode::from_u16(envelope.response_details.status)
@@ -309,19 +892,13 @@ impl ServiceHandler<Value> for DicomEndpoint {
             builder = builder.header(k.as_str(), v.as_str());
         }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_bytes = serde_json::to_vec(&normalized)
-                .map_err(|_| Error::from("Failed to serialize DICOM response JSON"))?;
-            Body::from(body_bytes)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize DICOM response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct DICOM HTTP response"))
     }
 }
@@ -363,64 +940,170 @@ impl DicomEndpoint {
                 dimse_config.incoming_store_port = port_val as u16;
             }
         }
+        // A range lets concurrent C-MOVEs each listen on a port of their own
+        dimse_config.incoming_store_port_range =
+            Self::incoming_store_port_range(options).map_err(Error::from)?;
+        // Each port in the range is reached at an AE title of its own
+        dimse_config.incoming_store_aets =
+            Self::incoming_store_aets(options).map_err(Error::from)?;
+
+        // Network timeouts for association establishment and each DIMSE response
+        if let Some(ms) = options
+            .get("connect_timeout_ms")
+            .and_then(|v| v.as_u64())
+            .filter(|ms| *ms > 0)
+        {
+            dimse_config.connect_timeout_ms = ms;
+        }
+        if let Some(ms) = options
+            .get("dimse_timeout_ms")
+            .and_then(|v| v.as_u64())
+            .filter(|ms| *ms > 0)
+        {
+            dimse_config.dimse_timeout_ms = Some(ms);
+        }
 
-        // Create SCU client
-        let scu = DimseScu::new(dimse_config);
-
-        // Resolve DIMSE operation with proper precedence:
-        // 1. Check target_details.metadata["dimse_op"] (set by middleware)
-        // 2. Check request_details.metadata["dimse_op"] (fallback for compatibility)
-        // 3. Check dimse_retrieve_mode option (only applies to get/move operations)
-        // 4. Check if path is a valid DIMSE operation name (for direct HTTP->DICOM calls)
-        // 5. Default to "get" for data retrieval
-        let valid_ops = ["echo", "find", "get", "move", "store"];
-        
-        let op = envelope
-            .target_details
-            .as_ref()
-            .and_then(|td| td.metadata.get("dimse_op"))
-            .cloned()
-            .or_else(|| {
-                envelope
-                    .request_details
-                    .metadata
-                    .get("dimse_op")
-                    .cloned()
-            })
-            .or_else(|| {
-                // Only use dimse_retrieve_mode for retrieval operations
-                // This allows backend configuration to override default "get" with "move"
-                options
-                    .get("dimse_retrieve_mode")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-            })
-            .or_else(|| {
-                // Check if path is a valid DIMSE operation (for direct HTTP->DICOM calls)
-                let path = envelope
-                    .request_details
-                    .metadata
-                    .get("path")
-                    .map(|s| s.trim_start_matches('/').to_lowercase())?;
-                
-                if valid_ops.contains(&path.as_str()) {
-                    Some(path)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| "get".to_string());
-
-        // Validate operation is a valid DIMSE operation
-        let normalized_op = op.trim_start_matches('/').to_lowercase();
-        if !valid_ops.contains(&normalized_op.as_str()) {
-            return Err(Error::from(format!(
-                "Invalid DIMSE operation: '{}'. Valid operations are: {}",
-                op,
-                valid_ops.join(", ")
-            )));
+        // Largest PDU we accept, advertised in the A-ASSOCIATE-RQ
+        if let Some(max) = options
+            .get("max_pdu_length")
+            .or_else(|| options.get("max_pdu"))
+            .and_then(|v| v.as_u64())
+        {
+            if !(MIN_MAX_PDU as u64..=MAX_MAX_PDU as u64).contains(&max) {
+                return Err(Error::from(format!(
+                    "Invalid max_pdu_length: {} (must be {}..={})",
+                    max, MIN_MAX_PDU, MAX_MAX_PDU
+                )));
+            }
+            dimse_config.max_pdu = max as u32;
         }
 
+        // Kill DCMTK tools whose association stalls for longer than this
+        if let Some(ms) = options
+            .get("max_association_lifetime_ms")
+            .and_then(|v| v.as_u64())
+            .filter(|ms| *ms > 0)
+        {
+            dimse_config.max_association_lifetime_ms = Some(ms);
+        }
+
+        // Warn when C-MOVE/C-GET throughput stays below a minimum
+        if let Some(slow) = options.get("slow_transfer") {
+            dimse_config.slow_transfer = Some(
+                serde_json::from_value(slow.clone())
+                    .map_err(|e| Error::from(format!("Invalid slow_transfer: {}", e)))?,
+            );
+        }
+
+        // What an SCU operation does when its result stream is dropped early
+        if let Some(behavior) = options.get("drop_behavior") {
+            dimse_config.drop_behavior = serde_json::from_value(behavior.clone())
+                .map_err(|e| Error::from(format!("Invalid drop_behavior: {}", e)))?;
+        }
+        if let Some(ms) = options.get("drop_grace_ms").and_then(|v| v.as_u64()) {
+            dimse_config.drop_grace_ms = ms;
+        }
+
+        // Idle C-FIND associations kept for reuse by later requests to the same peer
+        if let Some(ms) = options
+            .get("association_pool_ttl_ms")
+            .and_then(|v| v.as_u64())
+            .filter(|ms| *ms > 0)
+        {
+            dimse_config.association_pool_ttl_ms = Some(ms);
+        }
+        if let Some(ms) = options
+            .get("association_keepalive_ms")
+            .and_then(|v| v.as_u64())
+            .filter(|ms| *ms > 0)
+        {
+            dimse_config.association_keepalive_ms = Some(ms);
+        }
+
+        // Fail fast while the backend keeps failing instead of waiting out every connect
+        dimse_config.circuit_breaker = Self::circuit_breaker(options).map_err(Error::from)?;
+
+        // ATNA audit records for queries and retrievals against the backend
+        if let Some(audit) = options.get("audit") {
+            dimse_config.audit = Some(
+                serde_json::from_value(audit.clone())
+                    .map_err(|e| Error::from(format!("Invalid audit: {}", e)))?,
+            );
+        }
+
+        // Tag coercions stamped onto every outgoing C-STORE
+        if let Some(rules) = options.get("store_coercion") {
+            let rules: Vec<dimse::CoercionRule> = serde_json::from_value(rules.clone())
+                .map_err(|e| Error::from(format!("Invalid store_coercion: {}", e)))?;
+            for rule in &rules {
+                rule.validate()
+                    .map_err(|e| Error::from(format!("Invalid store_coercion: {}", e)))?;
+            }
+            dimse_config.store_coercion = rules;
+        }
+
+        // Transfer syntaxes to request, in priority order (Implicit VR LE is always appended)
+        if let Some(syntaxes) = options.get("preferred_transfer_syntaxes") {
+            dimse_config.preferred_transfer_syntaxes = serde_json::from_value(syntaxes.clone())
+                .map_err(|e| Error::from(format!("Invalid preferred_transfer_syntaxes: {}", e)))?;
+        }
+
+        // SOP classes and roles offered through SCP/SCU Role Selection, e.g. for C-GET
+        if let Some(roles) = options.get("role_selection") {
+            dimse_config.role_selection = serde_json::from_value(roles.clone())
+                .map_err(|e| Error::from(format!("Invalid role_selection: {}", e)))?;
+        }
+
+        // Create SCU client, logging under the pipeline's correlation ID
+        let mut scu = DimseScu::new(dimse_config);
+        if let Some(id) = envelope
+            .request_details
+            .metadata
+            .get(CORRELATION_ID)
+            .and_then(|id| uuid::Uuid::parse_str(id).ok())
+        {
+            scu = scu.with_correlation_id(id);
+        }
+
+        // Optional per-study folder layout for move/get results
+        let storage_layout = options
+            .get("storage_layout")
+            .and_then(|v| v.as_str())
+            .map(StorageLayout::parse)
+            .transpose()
+            .map_err(Error::from)?;
+
+        // Trim UI/AE/CS padding from received identifiers before they are indexed
+        let normalize_padding = options
+            .get("normalize_padding")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        // Re-decode text in legacy character sets when output_charset asks for UTF-8
+        let charset = CharsetTranscoder::from_options(options).map_err(Error::from)?;
+        // How many C-MOVE instances are persisted at once and described in the response
+        let transfer = MoveTransfer::from_options(options).map_err(Error::from)?;
+
+        let normalized_op = match Self::resolve_dimse_op(envelope, options) {
+            Ok(op) => op,
+            Err(op) => {
+                return Err(Error::from(format!(
+                    "Invalid DIMSE operation: '{}'. Valid operations are: {}",
+                    op,
+                    VALID_DIMSE_OPS.join(", ")
+                )))
+            }
+        };
+
+        let log_ctx = OperationContext::new(normalized_op.as_str(), remote_node.ae_title.as_str())
+            .with_identifier(&Self::request_identifier(envelope));
+        tracing::debug!(
+            operation = %log_ctx.operation,
+            study_uid = %log_ctx.study_uid,
+            patient_id = %log_ctx.patient_id,
+            remote_aet = %log_ctx.remote_aet,
+            "Starting DIMSE operation"
+        );
+
         let result = match normalized_op.as_str() {
             "echo" => {
                 // Perform C-ECHO
@@ -445,12 +1128,12 @@ impl DicomEndpoint {
                     .unwrap_or(serde_json::Value::Null);
 
                 // Extract identifier JSON (allow override from normalized_data.dimse_identifier)
-                let mut identifier_json = match body_json {
+                let (mut identifier_json, query_metadata) = match body_json {
                     serde_json::Value::Object(_) => {
-                        let (_cmd, ident, _qmeta) = djt::parse_wrapper_or_identifier(&body_json);
-                        ident
+                        let (_cmd, ident, qmeta) = djt::parse_wrapper_or_identifier(&body_json);
+                        (ident, qmeta)
                     }
-                    _ => serde_json::json!({}),
+                    _ => (serde_json::json!({}), None),
                 };
                 if let Some(nd) = envelope.normalized_data.as_ref() {
                     if let Some(ident) = nd.get("dimse_identifier") {
@@ -520,52 +1203,84 @@ impl DicomEndpoint {
                 for (k, v) in params.into_iter() {
                     query = query.with_parameter(k, v);
                 }
-
-                // Perform C-FIND and collect results
-                match scu.find(&remote_node, query).await {
-                    Ok(mut stream) => {
+                // Range, list and wildcard keys from query_metadata replace the literal value
+                if let Some(metadata) = query_metadata.as_ref() {
+                    for (tag, key_match) in Self::query_matches(&identifier_json, metadata) {
+                        query = query.with_match(tag, key_match);
+                    }
+                }
+                // Relational matching lets e.g. an IMAGE query omit the SeriesInstanceUID
+                let relational = options
+                    .get("relational_queries")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                query = query.with_relational(relational);
+                // Further matching features proposed through extended negotiation
+                let enabled =
+                    |name: &str| options.get(name).and_then(|v| v.as_bool()) == Some(true);
+                query = query
+                    .with_date_time_matching(enabled("date_time_matching"))
+                    .with_fuzzy_matching(enabled("fuzzy_matching"))
+                    .with_timezone_adjustment(enabled("timezone_adjustment"));
+                let requested_features = query.features();
+                // The SCU logs only the query keys; the patient is logged hashed here
+                tracing::debug!(
+                    operation = %log_ctx.operation,
+                    patient_id = %log_ctx.patient_id,
+                    remote_aet = %log_ctx.remote_aet,
+                    level = %query.query_level,
+                    "Sending C-FIND"
+                );
+
+                // Perform C-FIND and collect results. Dropping this future when the HTTP
+                // client disconnects drops the guard, which cancels the query at the peer.
+                let cancel = tokio_util::sync::CancellationToken::new();
+                let _cancel_on_drop = cancel.clone().drop_guard();
+                match scu.find_negotiated(&remote_node, query, cancel).await {
+                    Ok((accepted_features, mut stream)) => {
                         use futures_util::StreamExt;
                         let mut matches: Vec<serde_json::Value> = Vec::new();
+                        let mut pending_warnings = 0usize;
                         while let Some(item) = stream.next().await {
-                            match item {
-                                Ok(dimse::types::DatasetStream::File { ref path, .. }) => {
-                                    if let Ok(obj) = dicom_object::open_file(path) {
-                                        if let Ok(json) =
-                                            dicom_json_tool::identifier_to_json_value(&obj)
-                                        {
-                                            matches.push(json);
-                                        }
-                                    }
+                            if let Ok(ref ds) = item {
+                                if ds.metadata().is_pending_warning() {
+                                    pending_warnings += 1;
                                 }
-                                Ok(dimse::types::DatasetStream::Memory { ref data, .. }) => {
-                                    // Handle in-memory DICOM data
-                                    if let Ok(obj) = dicom_object::from_reader(&**data) {
-                                        if let Ok(json) =
-                                            dicom_json_tool::identifier_to_json_value(&obj)
-                                        {
+                            }
+                            match item {
+                                // Matches arrive in memory; file-backed ones are read the same way
+                                Ok(ds) => match ds.to_object().await {
+                                    Ok(obj) => {
+                                        if let Some(json) = Self::dataset_to_json(
+                                            &obj,
+                                            normalize_padding,
+                                            charset.as_ref(),
+                                        ) {
                                             matches.push(json);
                                         }
                                     }
-                                }
-                                Ok(dimse::types::DatasetStream::Object { ref object, .. }) => {
-                                    // Handle DICOM object directly
-                                    if let Ok(json) =
-                                        dicom_json_tool::identifier_to_json_value(object)
-                                    {
-                                        matches.push(json);
-                                    }
-                                }
+                                    Err(e) => warn!("Skipping unreadable C-FIND match: {}", e),
+                                },
                                 Err(e) => {
                                     warn!("Error in dataset stream: {}", e);
                                 }
                             }
                         }
 
-                        serde_json::json!({
+                        let mut response = serde_json::json!({
                             "operation": "find",
                             "success": true,
                             "matches": matches
-                        })
+                        });
+                        if pending_warnings > 0 && Self::surface_pending_warnings(options) {
+                            response["warnings"] =
+                                serde_json::json!([Self::pending_warning_entry(pending_warnings)]);
+                        }
+                        if !requested_features.is_empty() {
+                            response["negotiated_features"] =
+                                serde_json::to_value(accepted_features).unwrap_or_default();
+                        }
+                        response
                     }
                     Err(e) => serde_json::json!({
                         "operation": "find",
@@ -616,9 +1331,16 @@ impl DicomEndpoint {
                     }
                 }
 
-                // Destination AE: default to our local AET (download into proxy tmp)
-                let destination_aet = self
-                    .get_local_aet(options)
+                // Destination AE: an explicit move_destination, otherwise our local AET
+                // (download into proxy tmp)
+                let destination_aet = envelope
+                    .normalized_data
+                    .as_ref()
+                    .and_then(|nd| nd.get("move_destination"))
+                    .and_then(|v| v.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string())
+                    .or_else(|| self.get_local_aet(options))
                     .unwrap_or_else(|| "HARMONY_SCU".to_string());
                 let mut move_q = dimse::types::MoveQuery::new(QueryLevel::Study, destination_aet);
                 // Capture requested UID for relocation before consuming params
@@ -640,6 +1362,13 @@ impl DicomEndpoint {
                                 any = true;
                             }
                             if !any {
+                                tracing::info!(
+                                    operation = %log_ctx.operation,
+                                    study_uid = %log_ctx.study_uid,
+                                    patient_id = %log_ctx.patient_id,
+                                    remote_aet = %log_ctx.remote_aet,
+                                    "Study not found; skipping C-MOVE"
+                                );
                                 // Study not found - set error in normalized_data
                                 envelope.normalized_data =
                                     Some(serde_json::json!({"error": "Study not found"}));
@@ -657,7 +1386,7 @@ impl DicomEndpoint {
                 // Determine storage target folder and pass to SCU if filesystem
 
                 // Determine storage target folder and pass to SCU if filesystem
-                let folder_id = Uuid::new_v4().to_string();
+                let folder_id = self.ids.next_id();
                 let (folder_path, is_fs_backend) = if let Some(storage) = get_storage() {
                     let dir = storage
                         .ensure_dir_str(&format!("dimse/{}", folder_id))
@@ -703,67 +1432,47 @@ impl DicomEndpoint {
                     )
                     .await
                 {
-                    Ok(mut stream) => {
-                        use futures_util::StreamExt;
-                        let mut instances: Vec<serde_json::Value> = Vec::new();
-                        let mut file_count = 0usize;
-
-                        while let Some(item) = stream.next().await {
-                            if let Ok(dimse::types::DatasetStream::File { ref path, .. }) = item {
-                                // For filesystem backend, files are already in folder_path.
-                                // For non-filesystem, stream and persist via storage backend.
-                                if !is_fs_backend {
-                                    if let Some(storage) = get_storage() {
-                                        let bytes = tokio::fs::read(path)
-                                            .await
-                                            .unwrap_or_else(|_| Vec::new());
-                                        // Normalize filename to .dcm
-                                        let src = Path::new(path);
-                                        let base = src
-                                            .file_stem()
-                                            .and_then(|s| s.to_str())
-                                            .unwrap_or("instance");
-                                        let mut name = base.to_string();
-                                        if !name.ends_with(".dcm") {
-                                            name.push_str(".dcm");
-                                        }
-                                        let rel = format!("dimse/{}/{}", folder_id, name);
-                                        let _ = storage.write_file_str(&rel, &bytes).await;
-                                        // Cleanup staged file
-                                        let _ = tokio::fs::remove_file(path).await;
-                                    }
-                                }
-                                file_count += 1;
-
-                                // Also capture identifier metadata
-                                if let Ok(obj) = dicom_object::open_file(path) {
-                                    if let Ok(json) =
-                                        dicom_json_tool::identifier_to_json_value(&obj)
-                                    {
-                                        instances.push(json);
-                                    }
-                                }
-                            }
+                    Ok(moved) => {
+                        // Persist each instance as it arrives, keeping only a bounded
+                        // sample of identifiers for the response
+                        let summary = {
+                            let (storage_layout, charset) =
+                                (storage_layout.as_ref(), charset.as_ref());
+                            let folder_id = folder_id.as_str();
+                            transfer
+                                .run(moved.instances, move |item| {
+                                    Self::persist_moved_instance(
+                                        item,
+                                        normalize_padding,
+                                        charset,
+                                        !is_fs_backend,
+                                        storage_layout,
+                                        folder_id,
+                                    )
+                                })
+                                .await
+                        };
+                        let file_count = summary.count;
+                        // Sent before the first instance, so it has resolved once they are drained
+                        let sub_operations = moved.sub_operations.await.ok();
+
+                        // The instances now live in a non-filesystem backend; drop the staging folder
+                        if !is_fs_backend {
+                            let _ = tokio::fs::remove_dir_all(&folder_path).await;
                         }
 
                         // If filesystem backend, ensure .dcm extensions in-place
                         if is_fs_backend {
-                            if let Ok(entries) = std::fs::read_dir(&folder_path) {
-                                for e in entries.flatten() {
-                                    let p = e.path();
-                                    if p.is_file() {
-                                        let ext = p
-                                            .extension()
-                                            .and_then(|e| e.to_str())
-                                            .unwrap_or("")
-                                            .to_lowercase();
-                                        if ext != "dcm" {
-                                            let mut new_p = p.clone();
-                                            new_p.set_extension("dcm");
-                                            let _ = std::fs::rename(&p, &new_p);
-                                        }
-                                    }
-                                }
+                            if let Some(storage) = Self::storage_backend() {
+                                let folder = format!("dimse/{}", folder_id);
+                                Self::ensure_dcm_extensions(storage.as_ref(), &folder).await;
+                            }
+                        }
+
+                        // Persistent mode lays out the per-move directory further down
+                        if let Some(layout) = storage_layout.as_ref() {
+                            if is_fs_backend && !persistent_scp {
+                                layout.apply(&folder_path, &folder_id);
                             }
                         }
 
@@ -771,10 +1480,18 @@ impl DicomEndpoint {
                         let mut response = serde_json::json!({
                             "operation": "move",
                             "success": true,
-                            "instances": instances,
+                            "instances": summary.instances,
                             "folder_id": folder_id,
                             "file_count": file_count
                         });
+                        if summary.truncated {
+                            response["instances_truncated"] = serde_json::json!(true);
+                        }
+                        if let Some(counts) = sub_operations {
+                            response["completed"] = serde_json::json!(counts.completed);
+                            response["failed"] = serde_json::json!(counts.failed);
+                            response["warning"] = serde_json::json!(counts.warning);
+                        }
 
                         if persistent_scp {
                             // In persistent mode, ensure all matching files are under per-move directory
@@ -786,27 +1503,32 @@ impl DicomEndpoint {
                                 .clone()
                                 .unwrap_or_else(|| std::path::Path::new(scp_root).join(&folder_id));
                             let _ = std::fs::create_dir_all(&per_move_dir);
+                            let scp_storage = FilesystemStorage::new(scp_root).ok();
 
                             // Extract requested StudyInstanceUID from parameters (0020000D)
                             let requested_uid =
                                 requested_uid_for_relocate.clone().unwrap_or_default();
                             if !requested_uid.is_empty() {
                                 // Recursively scan scp_root to find matching files, excluding the per-move directory itself
-                                for entry in walkdir::WalkDir::new(scp_root)
-                                    .into_iter()
-                                    .filter_map(|e| e.ok())
-                                {
-                                    let p = entry.path();
-                                    if p.is_dir() {
-                                        continue;
-                                    }
+                                let scp_files = match &scp_storage {
+                                    Some(storage) => storage
+                                        .list_files_recursive_str("")
+                                        .await
+                                        .unwrap_or_default(),
+                                    None => Vec::new(),
+                                };
+                                for file in &scp_files {
+                                    let path = Path::new(scp_root).join(file);
+                                    let p = path.as_path();
                                     if p.starts_with(&per_move_dir) {
                                         continue;
                                     }
                                     if let Ok(obj) = dicom_object::open_file(p) {
-                                        if let Ok(json) =
-                                            dicom_json_tool::identifier_to_json_value(&obj)
-                                        {
+                                        if let Some(json) = Self::dataset_to_json(
+                                            &obj,
+                                            normalize_padding,
+                                            charset.as_ref(),
+                                        ) {
                                             let uid = json
                                                 .get("0020000D")
                                                 .and_then(|v| v.get("Value"))
@@ -831,24 +1553,14 @@ impl DicomEndpoint {
                                 }
                             }
                             // Ensure .dcm extension inside per_move_dir and count files
-                            let mut moved_count = 0usize;
-                            if let Ok(entries) = std::fs::read_dir(&per_move_dir) {
-                                for e in entries.flatten() {
-                                    let p = e.path();
-                                    if p.is_file() {
-                                        moved_count += 1;
-                                        let ext = p
-                                            .extension()
-                                            .and_then(|e| e.to_str())
-                                            .unwrap_or("")
-                                            .to_lowercase();
-                                        if ext != "dcm" {
-                                            let mut new_p = p.clone();
-                                            new_p.set_extension("dcm");
-                                            let _ = std::fs::rename(&p, &new_p);
-                                        }
-                                    }
+                            let moved_count = match &scp_storage {
+                                Some(storage) => {
+                                    Self::ensure_dcm_extensions(storage, &folder_id).await
                                 }
+                                None => 0,
+                            };
+                            if let Some(layout) = storage_layout.as_ref() {
+                                layout.apply(&per_move_dir, &folder_id);
                             }
                             response["folder_path"] =
                                 serde_json::json!(per_move_dir.to_string_lossy());
@@ -867,23 +1579,20 @@ impl DicomEndpoint {
                                         "0020000D".to_string(),
                                         requested_uid.clone(),
                                     );
-                                    if let Ok(mut stream2) = scu
-                                        .get_request(&remote_node, get_q, Some(folder_path.clone()))
-                                        .await
+                                    if let Ok(report) =
+                                        scu.get_request(&remote_node, get_q, &folder_path).await
                                     {
-                                        use futures_util::StreamExt;
-                                        let mut produced = 0usize;
-                                        while let Some(item2) = stream2.next().await {
-                                            if let Ok(dimse::types::DatasetStream::File {
-                                                ref path,
-                                                ..
-                                            }) = item2
-                                            {
-                                                if path.is_file() {
-                                                    produced += 1;
-                                                }
-                                            }
-                                        }
+                                        let produced = report
+                                            .instances
+                                            .iter()
+                                            .filter(|item| {
+                                                matches!(
+                                                    item,
+                                                    dimse::types::DatasetStream::File { path, .. }
+                                                        if path.is_file()
+                                                )
+                                            })
+                                            .count();
                                         response["folder_path"] =
                                             serde_json::json!(folder_path.to_string_lossy());
                                         response["file_count"] = serde_json::json!(produced);
@@ -948,7 +1657,7 @@ impl DicomEndpoint {
                 }
 
                 // Determine storage target folder and pass to SCU if filesystem
-                let folder_id = Uuid::new_v4().to_string();
+                let folder_id = self.ids.next_id();
                 let (folder_path, is_fs_backend) = if let Some(storage) = get_storage() {
                     let dir = storage
                         .ensure_dir_str(&format!("dimse/{}", folder_id))
@@ -966,25 +1675,29 @@ impl DicomEndpoint {
                     (dir, true)
                 };
 
-                match scu
-                    .get_request(
-                        &remote_node,
-                        get_q,
-                        if is_fs_backend {
-                            Some(folder_path.clone())
-                        } else {
-                            None
-                        },
-                    )
-                    .await
-                {
-                    Ok(mut stream) => {
-                        use futures_util::StreamExt;
+                match scu.get_request(&remote_node, get_q, &folder_path).await {
+                    Ok(report) => {
                         let mut instances: Vec<serde_json::Value> = Vec::new();
                         let mut file_count = 0usize;
 
-                        while let Some(item) = stream.next().await {
-                            if let Ok(dimse::types::DatasetStream::File { ref path, .. }) = item {
+                        for item in &report.instances {
+                            if let dimse::types::DatasetStream::File { path, metadata, .. } = item {
+                                // Capture identifier metadata before a non-filesystem
+                                // backend takes the staged file
+                                if let Ok(obj) = dicom_object::open_file(path) {
+                                    if let Some(mut json) = Self::dataset_to_json(
+                                        &obj,
+                                        normalize_padding,
+                                        charset.as_ref(),
+                                    ) {
+                                        Self::record_transfer_syntax(
+                                            &mut json,
+                                            metadata.transfer_syntax.as_deref(),
+                                        );
+                                        instances.push(json);
+                                    }
+                                }
+
                                 if !is_fs_backend {
                                     if let Some(storage) = get_storage() {
                                         let bytes = tokio::fs::read(path)
@@ -999,41 +1712,41 @@ impl DicomEndpoint {
                                         if !name.ends_with(".dcm") {
                                             name.push_str(".dcm");
                                         }
-                                        let rel = format!("dimse/{}/{}", folder_id, name);
+                                        let rel_dir = storage_layout
+                                            .as_ref()
+                                            .map(|layout| {
+                                                layout
+                                                    .relative_dir(
+                                                        &LayoutTags::from_file(path),
+                                                        &folder_id,
+                                                    )
+                                                    .to_string_lossy()
+                                                    .to_string()
+                                            })
+                                            .unwrap_or_else(|| folder_id.clone());
+                                        let rel = format!("dimse/{}/{}", rel_dir, name);
                                         let _ = storage.write_file_str(&rel, &bytes).await;
                                         let _ = tokio::fs::remove_file(path).await;
                                     }
                                 }
                                 file_count += 1;
-
-                                // Also capture identifier metadata
-                                if let Ok(obj) = dicom_object::open_file(path) {
-                                    if let Ok(json) =
-                                        dicom_json_tool::identifier_to_json_value(&obj)
-                                    {
-                                        instances.push(json);
-                                    }
-                                }
                             }
                         }
 
+                        if !is_fs_backend {
+                            let _ = tokio::fs::remove_dir_all(&folder_path).await;
+                        }
+
                         if is_fs_backend {
-                            if let Ok(entries) = std::fs::read_dir(&folder_path) {
-                                for e in entries.flatten() {
-                                    let p = e.path();
-                                    if p.is_file() {
-                                        let ext = p
-                                            .extension()
-                                            .and_then(|e| e.to_str())
-                                            .unwrap_or("")
-                                            .to_lowercase();
-                                        if ext != "dcm" {
-                                            let mut new_p = p.clone();
-                                            new_p.set_extension("dcm");
-                                            let _ = std::fs::rename(&p, &new_p);
-                                        }
-                                    }
-                                }
+                            if let Some(storage) = Self::storage_backend() {
+                                let folder = format!("dimse/{}", folder_id);
+                                Self::ensure_dcm_extensions(storage.as_ref(), &folder).await;
+                            }
+                        }
+
+                        if let Some(layout) = storage_layout.as_ref() {
+                            if is_fs_backend {
+                                layout.apply(&folder_path, &folder_id);
                             }
                         }
 
@@ -1056,17 +1769,262 @@ impl DicomEndpoint {
                     }),
                 }
             }
-            _ => {
-                // This should never be reached due to validation above, but handle it gracefully
+            other => {
+                // "store" is a known operation but has no SCU implementation behind this backend yet
                 serde_json::json!({
-                    "operation": "unknown",
+                    "operation": other,
                     "success": false,
-                    "error": format!("Unsupported DIMSE operation: '{}'. Valid operations are: echo, find, get, move, store", op)
+                    "error": format!("DIMSE operation '{}' is not supported by the dicom backend", other)
                 })
             }
         };
 
+        let success = result
+            .get("success")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let count = result
+            .get("file_count")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .or_else(|| {
+                result
+                    .get("matches")
+                    .or_else(|| result.get("instances"))
+                    .and_then(|v| v.as_array())
+                    .map(|a| a.len())
+            })
+            .unwrap_or(0);
+        tracing::info!(
+            operation = %log_ctx.operation,
+            study_uid = %log_ctx.study_uid,
+            patient_id = %log_ctx.patient_id,
+            remote_aet = %log_ctx.remote_aet,
+            success,
+            count,
+            "DIMSE operation completed"
+        );
+
+        // Provenance for DICOMweb responses (see the dicomweb `provenance_headers` option)
+        let metadata = &mut envelope.request_details.metadata;
+        metadata.insert("source_aet".to_string(), remote_node.ae_title.clone());
+        metadata.insert("retrieved_at".to_string(), chrono::Utc::now().to_rfc3339());
+
         envelope.normalized_data = Some(result);
         Ok(envelope.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+
+    fn backend() -> (DicomEndpoint, HashMap<String, Value>) {
+        let endpoint = DicomEndpoint {
+            local_aet: None,
+            aet: None,
+            host: None,
+            port: None,
+            use_tls: None,
+            ids: IdGenerator::default(),
+        };
+        let mut options = HashMap::new();
+        options.insert("aet".to_string(), serde_json::json!("ORTHANC"));
+        options.insert("host".to_string(), serde_json::json!("localhost"));
+        options.insert("port".to_string(), serde_json::json!(4242));
+        (endpoint, options)
+    }
+
+    fn envelope_with_meta(entries: &[(&str, &str)]) -> RequestEnvelope<Vec<u8>> {
+        let mut builder = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/dicom")
+            .original_data(Vec::new());
+        for (k, v) in entries {
+            builder = builder.metadata_entry(*k, *v);
+        }
+        builder.build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn unknown_dimse_op_returns_400() {
+        let (endpoint, options) = backend();
+        let envelope = envelope_with_meta(&[("dimse_op", "fnid")]);
+
+        let response = endpoint
+            .backend_outgoing_request(envelope, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(response.response_details.status, 400);
+        let nd = response.normalized_data.unwrap();
+        assert_eq!(nd["success"], serde_json::json!(false));
+        assert!(nd["error"].as_str().unwrap().contains("fnid"));
+    }
+
+    #[test]
+    fn query_metadata_range_builds_date_range_key() {
+        let body = serde_json::json!({
+            "identifier": {
+                "00080020": { "vr": "DA", "Value": ["20230101", "20231231"] },
+                "0020000D": { "vr": "UI" },
+                "00100020": { "vr": "LO", "Value": ["P1"] }
+            },
+            "query_metadata": {
+                "00080020": { "match_type": "RANGE" },
+                "00100020": { "match_type": "SEQUENCE" }
+            }
+        });
+        let (_cmd, identifier, metadata) = djt::parse_wrapper_or_identifier(&body);
+        let matches = DicomEndpoint::query_matches(&identifier, &metadata.unwrap());
+        assert_eq!(matches.len(), 1);
+
+        let mut query = FindQuery::study(None);
+        for (tag, key_match) in matches {
+            query = query.with_match(tag, key_match);
+        }
+        assert_eq!(
+            query.parameters.get("00080020").map(String::as_str),
+            Some("20230101-20231231")
+        );
+    }
+
+    #[test]
+    fn dimse_op_precedence_is_configurable() {
+        let (_endpoint, mut options) = backend();
+        let envelope = envelope_with_meta(&[("dimse_op", "find"), ("operation", "move")]);
+
+        // Default precedence prefers dimse_op over the adapter-provided operation
+        assert_eq!(
+            DicomEndpoint::resolve_dimse_op(&envelope, &options),
+            Ok("find".to_string())
+        );
+
+        options.insert(
+            "dimse_op_precedence".to_string(),
+            serde_json::json!(["operation", "request"]),
+        );
+        assert_eq!(
+            DicomEndpoint::resolve_dimse_op(&envelope, &options),
+            Ok("move".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_dimse_op_precedence_is_rejected() {
+        let (endpoint, mut options) = backend();
+        options.insert(
+            "dimse_op_precedence".to_string(),
+            serde_json::json!(["target", "header"]),
+        );
+        assert!(endpoint.validate(&options).is_err());
+    }
+
+    /// Explicit VR little endian element with a 16-bit length, padded to even length
+    fn element(group: u16, elem: u16, vr: &[u8; 2], value: &[u8], pad: u8) -> Vec<u8> {
+        let mut value = value.to_vec();
+        if value.len() % 2 == 1 {
+            value.push(pad);
+        }
+        let mut out = Vec::new();
+        out.extend_from_slice(&group.to_le_bytes());
+        out.extend_from_slice(&elem.to_le_bytes());
+        out.extend_from_slice(vr);
+        out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        out.extend_from_slice(&value);
+        out
+    }
+
+    /// Part 10 identifier with the given Specific Character Set and raw PatientName bytes
+    fn legacy_identifier(charset: &[u8], name: &[u8]) -> InMemDicomObject {
+        let mut meta = Vec::new();
+        // FileMetaInformationVersion (OB, 32-bit length form)
+        meta.extend_from_slice(&[0x02, 0x00, 0x01, 0x00, b'O', b'B', 0, 0, 2, 0, 0, 0, 0, 1]);
+        meta.extend(element(
+            0x0002,
+            0x0002,
+            b"UI",
+            b"1.2.840.10008.5.1.4.1.2.2.1",
+            0,
+        ));
+        meta.extend(element(0x0002, 0x0003, b"UI", b"1.2.3.4", 0));
+        meta.extend(element(0x0002, 0x0010, b"UI", b"1.2.840.10008.1.2.1", 0));
+
+        let mut bytes = b"DICM".to_vec();
+        bytes.extend(element(
+            0x0002,
+            0x0000,
+            b"UL",
+            &(meta.len() as u32).to_le_bytes(),
+            0,
+        ));
+        bytes.extend(meta);
+        bytes.extend(element(0x0008, 0x0005, b"CS", charset, b' '));
+        bytes.extend(element(0x0010, 0x0010, b"PN", name, b' '));
+        dicom_object::from_reader(bytes.as_slice()).unwrap()
+    }
+
+    fn utf8_json(obj: &InMemDicomObject) -> serde_json::Value {
+        let mut options = HashMap::new();
+        options.insert(
+            "output_charset".to_string(),
+            serde_json::json!("ISO_IR 192"),
+        );
+        let charset = CharsetTranscoder::from_options(&options).unwrap();
+        DicomEndpoint::dataset_to_json(obj, true, charset.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn latin1_patient_name_is_transcoded_to_utf8_json() {
+        let obj = legacy_identifier(b"ISO_IR 100", b"M\xfcller^Hans");
+        let json = utf8_json(&obj);
+
+        assert_eq!(
+            json["00100010"]["Value"][0]["Alphabetic"],
+            "M\u{fc}ller^Hans"
+        );
+        assert_eq!(json["00080005"]["Value"][0], "ISO_IR 192");
+        let rendered = serde_json::to_vec(&json).unwrap();
+        assert!(String::from_utf8(rendered).unwrap().contains("Müller^Hans"));
+    }
+
+    #[test]
+    fn single_byte_patient_names_are_transcoded_to_utf8_json() {
+        let cyrillic = legacy_identifier(
+            b"ISO_IR 144",
+            b"\xb8\xd2\xd0\xdd\xde\xd2^\xc1\xd5\xe0\xd3\xd5\xd9",
+        );
+        assert_eq!(
+            utf8_json(&cyrillic)["00100010"]["Value"][0]["Alphabetic"],
+            "Иванов^Сергей"
+        );
+
+        let katakana = legacy_identifier(b"ISO_IR 13", b"\xd4\xcf\xc0\xde^\xc0\xdb\xb3");
+        assert_eq!(
+            utf8_json(&katakana)["00100010"]["Value"][0]["Alphabetic"],
+            "ﾔﾏﾀﾞ^ﾀﾛｳ"
+        );
+    }
+
+    #[test]
+    fn code_extension_patient_name_is_transcoded_to_utf8_json() {
+        // PS3.5 H.3.1: the parser only reads the first (empty) term and leaves mojibake
+        let obj = legacy_identifier(
+            b"\\ISO 2022 IR 87",
+            b"Yamada^Tarou=\x1b$B;3ED\x1b(B^\x1b$BB@O:\x1b(B=\x1b$B$d$^$@\x1b(B^\x1b$B$?$m$&\x1b(B",
+        );
+        let untouched = DicomEndpoint::dataset_to_json(&obj, true, None).unwrap();
+        assert_ne!(
+            untouched["00100010"]["Value"][0]["Ideographic"],
+            "山田^太郎"
+        );
+
+        let json = utf8_json(&obj);
+        let name = &json["00100010"]["Value"][0];
+        assert_eq!(name["Alphabetic"], "Yamada^Tarou");
+        assert_eq!(name["Ideographic"], "山田^太郎");
+        assert_eq!(name["Phonetic"], "やまだ^たろう");
+        assert_eq!(json["00080005"]["Value"], serde_json::json!(["ISO_IR 192"]));
+    }
+}
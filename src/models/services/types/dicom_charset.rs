@@ -0,0 +1,359 @@
+//! UTF-8 output for identifiers received in a legacy character set
+//!
+//! DICOM JSON is always UTF-8, but the text values of a received dataset are encoded in the
+//! repertoire named by its Specific Character Set (0008,0005). The DICOM parser only honours the
+//! first term of 0008,0005 and falls back to the default repertoire, read as Latin-1, for terms
+//! it does not know, so values using ISO 2022 code extensions (such as `\ISO 2022 IR 87` for
+//! Japanese) come out as mojibake. With the `output_charset = "ISO_IR 192"` backend option the
+//! original bytes of every text value are recovered from what the parser decoded and decoded
+//! again from all the declared terms, switching repertoire at each escape sequence, and
+//! 0008,0005 is rewritten to `ISO_IR 192`.
+
+use dicom_encoding::text::{SpecificCharacterSet, TextCodec};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+const SPECIFIC_CHARACTER_SET: &str = "00080005";
+const UTF8_CHARSET: &str = "ISO_IR 192";
+/// VRs whose values are encoded with the specific character set (PS3.5 6.1.2.3)
+const TEXT_VRS: [&str; 7] = ["SH", "LO", "ST", "LT", "UC", "UT", "PN"];
+const ESC: u8 = 0x1B;
+/// Escape sequences of the code extensions DICOM defines and the term each one switches to
+/// (PS3.3 C.12.1.1.2)
+const ESCAPE_SEQUENCES: [(&[u8], &str); 15] = [
+    (b"\x1b(B", "ISO 2022 IR 6"),
+    (b"\x1b(J", "ISO 2022 IR 13"),
+    (b"\x1b)I", "ISO 2022 IR 13"),
+    (b"\x1b$B", "ISO 2022 IR 87"),
+    (b"\x1b-A", "ISO 2022 IR 100"),
+    (b"\x1b-B", "ISO 2022 IR 101"),
+    (b"\x1b-C", "ISO 2022 IR 109"),
+    (b"\x1b-D", "ISO 2022 IR 110"),
+    (b"\x1b-F", "ISO 2022 IR 126"),
+    (b"\x1b-G", "ISO 2022 IR 127"),
+    (b"\x1b-H", "ISO 2022 IR 138"),
+    (b"\x1b-L", "ISO 2022 IR 144"),
+    (b"\x1b-T", "ISO 2022 IR 166"),
+    (b"\x1b$)C", "ISO 2022 IR 149"),
+    (b"\x1b$)A", "ISO 2022 IR 58"),
+];
+
+/// What becomes of a text value that cannot be decoded in its source character set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Undecodable {
+    /// Bytes that fail to decode become U+FFFD
+    #[default]
+    Replace,
+    /// The attribute is kept without a `Value`
+    Omit,
+}
+
+/// Transcodes DICOM JSON datasets to UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharsetTranscoder {
+    pub undecodable: Undecodable,
+}
+
+impl CharsetTranscoder {
+    /// Read `output_charset` and `undecodable_text` from backend options; `None` when no
+    /// transcoding is configured
+    pub fn from_options(options: &HashMap<String, Value>) -> Result<Option<Self>, String> {
+        let Some(target) = options.get("output_charset") else {
+            return Ok(None);
+        };
+        match target.as_str().map(str::trim) {
+            Some(UTF8_CHARSET) => {}
+            _ => {
+                return Err(format!(
+                    "output_charset must be '{}' (the only supported target)",
+                    UTF8_CHARSET
+                ))
+            }
+        }
+
+        let undecodable = match options.get("undecodable_text") {
+            None => Undecodable::Replace,
+            Some(v) => match v.as_str() {
+                Some("replace") => Undecodable::Replace,
+                Some("omit") => Undecodable::Omit,
+                _ => return Err("undecodable_text must be either 'replace' or 'omit'".to_string()),
+            },
+        };
+
+        Ok(Some(Self { undecodable }))
+    }
+
+    /// Transcode a DICOM JSON dataset in place
+    pub fn apply(&self, dataset: &mut Value) {
+        if let Some(obj) = dataset.as_object_mut() {
+            self.apply_to_object(obj, &Source::default());
+        }
+    }
+
+    fn apply_to_object(&self, obj: &mut Map<String, Value>, inherited: &Source) {
+        // Sequence items without their own 0008,0005 use the enclosing dataset's
+        let source = obj
+            .get(SPECIFIC_CHARACTER_SET)
+            .and_then(|e| e.get("Value"))
+            .and_then(|v| v.as_array())
+            .map(|values| Source::new(values))
+            .unwrap_or_else(|| inherited.clone());
+        let legacy = source.is_legacy();
+
+        for (tag, element) in obj.iter_mut() {
+            let vr = element
+                .get("vr")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            if vr == "SQ" {
+                if let Some(items) = element.get_mut("Value").and_then(|v| v.as_array_mut()) {
+                    for item in items.iter_mut().filter_map(|i| i.as_object_mut()) {
+                        self.apply_to_object(item, &source);
+                    }
+                }
+            } else if legacy && TEXT_VRS.contains(&vr.as_str()) {
+                self.transcode_element(tag, element, &source);
+            }
+        }
+
+        if legacy {
+            if let Some(element) = obj.get_mut(SPECIFIC_CHARACTER_SET) {
+                element["Value"] = Value::Array(vec![Value::String(UTF8_CHARSET.to_string())]);
+            }
+        }
+    }
+
+    fn transcode_element(&self, tag: &str, element: &mut Value, source: &Source) {
+        let Some(values) = element.get_mut("Value").and_then(|v| v.as_array_mut()) else {
+            return;
+        };
+        let mut decoded = true;
+        for value in values.iter_mut() {
+            decoded &= self.transcode_value(value, source);
+        }
+        if !decoded {
+            tracing::warn!(tag = %tag, "Text value could not be decoded in its character set");
+            if self.undecodable == Undecodable::Omit {
+                if let Some(element) = element.as_object_mut() {
+                    element.remove("Value");
+                }
+            }
+        }
+    }
+
+    /// Transcode a string value, or each component group of a PN value; returns false if any
+    /// part could not be decoded
+    fn transcode_value(&self, value: &mut Value, source: &Source) -> bool {
+        match value {
+            Value::String(text) => match source.transcode(text) {
+                Ok(decoded) => {
+                    *text = decoded;
+                    true
+                }
+                Err(replaced) => {
+                    *text = replaced;
+                    false
+                }
+            },
+            Value::Object(groups) => groups
+                .values_mut()
+                .fold(true, |ok, group| self.transcode_value(group, source) && ok),
+            _ => true,
+        }
+    }
+}
+
+/// The terms of a dataset's 0008,0005 and the repertoire the parser decoded its text with
+#[derive(Debug, Clone, Default)]
+struct Source {
+    /// Declared terms in order; the first may be empty for the default repertoire
+    terms: Vec<String>,
+    /// The parser's codec: the first term when it knows it, else the default repertoire
+    parsed_with: SpecificCharacterSet,
+}
+
+impl Source {
+    fn new(values: &[Value]) -> Self {
+        let terms: Vec<String> = values
+            .iter()
+            .map(|v| v.as_str().unwrap_or_default().trim().to_string())
+            .collect();
+        let parsed_with = terms
+            .first()
+            .and_then(|term| SpecificCharacterSet::from_code(term))
+            .unwrap_or_default();
+        Self { terms, parsed_with }
+    }
+
+    fn is_legacy(&self) -> bool {
+        match self.terms.as_slice() {
+            [] => false,
+            [only] => !only.is_empty() && only != UTF8_CHARSET,
+            _ => true,
+        }
+    }
+
+    /// Decode the original bytes of a value the parser decoded as `text`. On failure returns
+    /// the text with the bytes that do not decode replaced by U+FFFD.
+    fn transcode(&self, text: &str) -> Result<String, String> {
+        if text.bytes().all(|b| b.is_ascii() && b != ESC && b != b'\\') {
+            return Ok(text.to_string());
+        }
+        let Some(bytes) = original_bytes(text, &self.parsed_with) else {
+            // Not something the parser produced from this dataset's bytes; keep it
+            return Ok(text.to_string());
+        };
+        match self.terms.as_slice() {
+            [only] => decode(&bytes, only),
+            _ => self.decode_extended(&bytes),
+        }
+    }
+
+    /// Decode bytes that may switch repertoire with ISO 2022 escape sequences, starting in the
+    /// first term's (each value and PN component group starts over there)
+    fn decode_extended(&self, bytes: &[u8]) -> Result<String, String> {
+        let mut out = String::new();
+        let mut ok = true;
+        let mut term = self.terms.first().map_or("", String::as_str);
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let end = rest[1..]
+                .iter()
+                .position(|&b| b == ESC)
+                .map_or(rest.len(), |i| i + 1);
+            let mut segment = &rest[..end];
+            if segment.first() == Some(&ESC) {
+                match ESCAPE_SEQUENCES
+                    .iter()
+                    .find(|(sequence, _)| segment.starts_with(sequence))
+                {
+                    Some((sequence, switched)) => {
+                        term = switched;
+                        // ISO-2022-JP decoding needs the escape sequence itself
+                        if term != "ISO 2022 IR 87" {
+                            segment = &segment[sequence.len()..];
+                        }
+                    }
+                    None => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        segment = &segment[1..];
+                        ok = false;
+                    }
+                }
+            }
+            match decode(segment, term) {
+                Ok(decoded) => out.push_str(&decoded),
+                Err(replaced) => {
+                    out.push_str(&replaced);
+                    ok = false;
+                }
+            }
+            rest = &rest[end..];
+        }
+        if ok {
+            Ok(out)
+        } else {
+            Err(out)
+        }
+    }
+}
+
+/// Recover the bytes the parser decoded into `text` with `codec`
+///
+/// Bytes the codec could not decode were written as a backslash and three octal digits, which
+/// no value contains otherwise since a backslash separates values.
+fn original_bytes(text: &str, codec: &SpecificCharacterSet) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut pending = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            pending.push(c);
+            continue;
+        }
+        let digits: String = (0..3).filter_map(|_| chars.next()).collect();
+        let byte = u8::from_str_radix(&digits, 8)
+            .ok()
+            .filter(|_| digits.len() == 3)?;
+        bytes.extend(codec.encode(&std::mem::take(&mut pending)).ok()?);
+        bytes.push(byte);
+    }
+    bytes.extend(codec.encode(&pending).ok()?);
+    Some(bytes)
+}
+
+/// Decode `bytes` in the repertoire named by `term` (the default repertoire when empty). On
+/// failure returns the text with every non-ASCII byte replaced by U+FFFD.
+fn decode(bytes: &[u8], term: &str) -> Result<String, String> {
+    let codec = if term.is_empty() {
+        Some(SpecificCharacterSet::default())
+    } else {
+        SpecificCharacterSet::from_code(term)
+    };
+    match codec.and_then(|c| c.decode(bytes).ok()) {
+        // Undecodable bytes come back escaped, see `original_bytes`
+        Some(decoded) if !decoded.contains('\\') => Ok(decoded),
+        _ => Err(bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii() && b != ESC {
+                    char::from(b)
+                } else {
+                    char::REPLACEMENT_CHARACTER
+                }
+            })
+            .collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcoder(undecodable: Undecodable) -> CharsetTranscoder {
+        CharsetTranscoder { undecodable }
+    }
+
+    #[test]
+    fn test_from_options() {
+        let mut options = HashMap::new();
+        assert_eq!(CharsetTranscoder::from_options(&options), Ok(None));
+
+        options.insert(
+            "output_charset".to_string(),
+            serde_json::json!("ISO_IR 192"),
+        );
+        options.insert("undecodable_text".to_string(), serde_json::json!("omit"));
+        assert_eq!(
+            CharsetTranscoder::from_options(&options),
+            Ok(Some(transcoder(Undecodable::Omit)))
+        );
+
+        options.insert(
+            "output_charset".to_string(),
+            serde_json::json!("ISO_IR 100"),
+        );
+        assert!(CharsetTranscoder::from_options(&options).is_err());
+    }
+
+    #[test]
+    fn test_unknown_charset_applies_replacement_policy() {
+        let dataset = serde_json::json!({
+            "00080005": { "vr": "CS", "Value": ["ISO_IR 999"] },
+            "00100020": { "vr": "LO", "Value": ["PID\u{e9}"] },
+            "0020000D": { "vr": "UI", "Value": ["1.2.3"] }
+        });
+
+        let mut replaced = dataset.clone();
+        transcoder(Undecodable::Replace).apply(&mut replaced);
+        assert_eq!(replaced["00100020"]["Value"][0], "PID\u{fffd}");
+        assert_eq!(replaced["00080005"]["Value"][0], "ISO_IR 192");
+        assert_eq!(replaced["0020000D"]["Value"][0], "1.2.3");
+
+        let mut omitted = dataset;
+        transcoder(Undecodable::Omit).apply(&mut omitted);
+        assert!(omitted["00100020"].get("Value").is_none());
+        assert_eq!(omitted["00100020"]["vr"], "LO");
+    }
+}
@@ -0,0 +1,150 @@
+//! Single-flight coalescing of identical C-FIND queries
+//!
+//! When a cached listing expires, every client polling it misses at once and each would issue
+//! its own C-FIND. With the `coalesce_window_ms` backend option set, the first query for a key
+//! runs and identical queries arriving while it is in flight wait for its result instead. A
+//! successful result is also handed to identical queries that arrive within the window after
+//! it completes; a window of 0 shares only in-flight calls.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+type Outcome<T> = Result<T, String>;
+
+enum Flight<T> {
+    /// A call is running; its result is published on the channel
+    Running(watch::Receiver<Option<Outcome<T>>>),
+    /// A successful result, reusable until the window has passed
+    Done(T, Instant),
+}
+
+/// Shares one in-flight call among concurrent callers with the same key
+pub struct QueryCoalescer<T> {
+    flights: Mutex<HashMap<String, Flight<T>>>,
+}
+
+impl<T: Clone> QueryCoalescer<T> {
+    pub fn new() -> Self {
+        Self {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Run `call` for `key` unless an identical call is in flight or finished within `window`,
+    /// in which case its result is returned instead
+    pub async fn run<F, Fut>(&self, key: &str, window: Duration, call: F) -> Outcome<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Outcome<T>>,
+    {
+        let leader = {
+            let mut flights = self.flights.lock().expect("query coalescer poisoned");
+            // A running entry whose sender is gone belongs to a caller that was cancelled
+            flights.retain(|_, flight| match flight {
+                Flight::Running(rx) => rx.has_changed().is_ok(),
+                Flight::Done(_, at) => at.elapsed() < window,
+            });
+            match flights.get(key) {
+                Some(Flight::Done(value, _)) => return Ok(value.clone()),
+                Some(Flight::Running(rx)) => Err(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    flights.insert(key.to_string(), Flight::Running(rx));
+                    Ok(tx)
+                }
+            }
+        };
+
+        let tx = match leader {
+            Ok(tx) => tx,
+            Err(mut rx) => {
+                tracing::debug!(key = %key, "Joining in-flight query");
+                if let Ok(outcome) = rx.wait_for(Option::is_some).await {
+                    if let Some(outcome) = outcome.as_ref() {
+                        return outcome.clone();
+                    }
+                }
+                // The leading caller was cancelled before it finished
+                return call().await;
+            }
+        };
+
+        let outcome = call().await;
+        {
+            let mut flights = self.flights.lock().expect("query coalescer poisoned");
+            match &outcome {
+                Ok(value) if !window.is_zero() => {
+                    flights.insert(key.to_string(), Flight::Done(value.clone(), Instant::now()));
+                }
+                _ => {
+                    flights.remove(key);
+                }
+            }
+        }
+        let _ = tx.send(Some(outcome.clone()));
+        outcome
+    }
+}
+
+impl<T: Clone> Default for QueryCoalescer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_concurrent_identical_queries_share_one_call() {
+        let coalescer = Arc::new(QueryCoalescer::<String>::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let queries = (0..10).map(|_| {
+            let coalescer = coalescer.clone();
+            let calls = calls.clone();
+            tokio::spawn(async move {
+                coalescer
+                    .run("studies?PatientID=123", Duration::ZERO, || async {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Ok("matches".to_string())
+                    })
+                    .await
+            })
+        });
+        let results = futures_util::future::join_all(queries).await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for result in results {
+            assert_eq!(result.unwrap(), Ok("matches".to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_window_and_failures() {
+        let coalescer = QueryCoalescer::<u32>::new();
+        let window = Duration::from_secs(60);
+
+        assert_eq!(coalescer.run("a", window, || async { Ok(1) }).await, Ok(1));
+        // Within the window the finished result is reused
+        assert_eq!(coalescer.run("a", window, || async { Ok(2) }).await, Ok(1));
+        // Other keys are not
+        assert_eq!(coalescer.run("b", window, || async { Ok(3) }).await, Ok(3));
+
+        // Failures are not kept
+        let failed = coalescer
+            .run("c", window, || async {
+                Err("association rejected".to_string())
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(coalescer.run("c", window, || async { Ok(4) }).await, Ok(4));
+    }
+}
@@ -0,0 +1,245 @@
+//! Per-study folder layout for instances persisted by the dicom backend's move/get
+//!
+//! The `storage_layout` backend option is a `/`-separated template such as
+//! `{patient}/{study}/{series}`. Each received file is placed under the directory the template
+//! resolves to for that file's tags, nested inside the operation's own folder so that cleaning
+//! up one retrieval never removes another's instances. A segment whose tag is missing resolves
+//! to the operation's folder id instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PLACEHOLDERS: [&str; 3] = ["{patient}", "{study}", "{series}"];
+
+/// Parsed `storage_layout` template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageLayout {
+    segments: Vec<String>,
+}
+
+/// Tag values a layout template can refer to
+#[derive(Debug, Clone, Default)]
+pub struct LayoutTags {
+    pub patient_id: Option<String>,
+    pub study_uid: Option<String>,
+    pub series_uid: Option<String>,
+}
+
+impl LayoutTags {
+    /// Read PatientID, StudyInstanceUID and SeriesInstanceUID from a DICOM file
+    pub fn from_file(path: &Path) -> Self {
+        let Ok(obj) = dicom_object::open_file(path) else {
+            return Self::default();
+        };
+        let tag = |name: &str| {
+            obj.element_by_name(name)
+                .ok()
+                .and_then(|e| e.to_str().ok())
+                .map(|s| s.trim_end_matches(['\0', ' ']).trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        Self {
+            patient_id: tag("PatientID"),
+            study_uid: tag("StudyInstanceUID"),
+            series_uid: tag("SeriesInstanceUID"),
+        }
+    }
+}
+
+impl StorageLayout {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let segments: Vec<String> = template
+            .split('/')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        if segments.is_empty() {
+            return Err("storage_layout must contain at least one path segment".to_string());
+        }
+        for segment in &segments {
+            let literal = PLACEHOLDERS
+                .iter()
+                .fold(segment.clone(), |s, p| s.replace(p, ""));
+            if literal.contains(['{', '}']) {
+                return Err(format!(
+                    "Unknown placeholder in storage_layout segment '{}'; expected {}",
+                    segment,
+                    PLACEHOLDERS.join(", ")
+                ));
+            }
+            if segment == "." || segment == ".." {
+                return Err("storage_layout segments cannot be '.' or '..'".to_string());
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// Directory, relative to the dimse storage root, for an instance with `tags`
+    pub fn relative_dir(&self, tags: &LayoutTags, fallback: &str) -> PathBuf {
+        self.segments
+            .iter()
+            .map(|segment| {
+                let mut resolved = segment.clone();
+                for (placeholder, value) in [
+                    ("{patient}", &tags.patient_id),
+                    ("{study}", &tags.study_uid),
+                    ("{series}", &tags.series_uid),
+                ] {
+                    if !resolved.contains(placeholder) {
+                        continue;
+                    }
+                    match value.as_deref().map(sanitize_segment) {
+                        Some(v) if !v.is_empty() => resolved = resolved.replace(placeholder, &v),
+                        _ => return fallback.to_string(),
+                    }
+                }
+                sanitize_segment(&resolved)
+            })
+            .collect()
+    }
+
+    /// Move every file directly in the operation `folder` into its layout directory beneath it
+    pub fn apply(&self, folder: &Path, fallback: &str) {
+        let Ok(entries) = fs::read_dir(folder) else {
+            return;
+        };
+        // Listed up front, as the layout directories are created inside the folder being read
+        let files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+
+        for src in files {
+            let dir = folder.join(self.relative_dir(&LayoutTags::from_file(&src), fallback));
+            if let Err(e) = fs::create_dir_all(&dir) {
+                tracing::warn!("Failed to create layout folder {}: {}", dir.display(), e);
+                continue;
+            }
+            let Some(name) = src.file_name() else {
+                continue;
+            };
+            let dest = unique_path(&dir.join(name));
+            let moved = fs::rename(&src, &dest)
+                .or_else(|_| fs::copy(&src, &dest).and_then(|_| fs::remove_file(&src)));
+            if let Err(e) = moved {
+                tracing::warn!(
+                    "Failed to move {} to {}: {}",
+                    src.display(),
+                    dest.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Keep UID/ID characters that are safe in a single path segment
+fn sanitize_segment(value: &str) -> String {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if cleaned.chars().all(|c| c == '.') {
+        String::new()
+    } else {
+        cleaned
+    }
+}
+
+/// `path`, or `stem-N.ext` for the first N that does not exist yet
+fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .expect("unbounded search finds a free name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_instance(dir: &Path, name: &str, study: &str, series: &str, sop: &str) {
+        let identifier = serde_json::json!({
+            "00080016": { "vr": "UI", "Value": ["1.2.840.10008.5.1.4.1.1.7"] },
+            "00080018": { "vr": "UI", "Value": [sop] },
+            "00100020": { "vr": "LO", "Value": ["PID-1"] },
+            "0020000D": { "vr": "UI", "Value": [study] },
+            "0020000E": { "vr": "UI", "Value": [series] }
+        });
+        let obj = dicom_json_tool::json_value_to_identifier(&identifier).unwrap();
+        dicom_json_tool::write_part10(&dir.join(name), &obj).unwrap();
+    }
+
+    #[test]
+    fn test_two_series_share_study_folder() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = root.path().join("op-1234");
+        fs::create_dir_all(&folder).unwrap();
+        write_instance(&folder, "a.dcm", "1.2.3", "1.2.3.1", "1.2.3.1.1");
+        write_instance(&folder, "b.dcm", "1.2.3", "1.2.3.2", "1.2.3.2.1");
+
+        let layout = StorageLayout::parse("{patient}/{study}/{series}").unwrap();
+        layout.apply(&folder, "op-1234");
+
+        let study_dir = folder.join("PID-1").join("1.2.3");
+        assert!(study_dir.join("1.2.3.1").join("a.dcm").is_file());
+        assert!(study_dir.join("1.2.3.2").join("b.dcm").is_file());
+        assert!(!folder.join("a.dcm").exists());
+    }
+
+    #[test]
+    fn test_removing_one_operation_keeps_another_with_the_same_study() {
+        let root = tempfile::tempdir().unwrap();
+        let layout = StorageLayout::parse("{patient}/{study}/{series}").unwrap();
+        let (first, second) = (root.path().join("op-1"), root.path().join("op-2"));
+        for folder in [&first, &second] {
+            fs::create_dir_all(folder).unwrap();
+            write_instance(folder, "a.dcm", "1.2.3", "1.2.3.1", "1.2.3.1.1");
+            layout.apply(folder, "op");
+        }
+
+        fs::remove_dir_all(&first).unwrap();
+
+        assert!(second
+            .join("PID-1")
+            .join("1.2.3")
+            .join("1.2.3.1")
+            .join("a.dcm")
+            .is_file());
+    }
+
+    #[test]
+    fn test_missing_tags_fall_back_to_folder_id() {
+        let layout = StorageLayout::parse("{patient}/{study}").unwrap();
+        let tags = LayoutTags {
+            patient_id: Some("../etc".to_string()),
+            study_uid: None,
+            series_uid: None,
+        };
+        assert_eq!(
+            layout.relative_dir(&tags, "uuid-1"),
+            PathBuf::from(".._etc").join("uuid-1")
+        );
+        assert!(StorageLayout::parse("{patient}/{modality}").is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Incremental processing of the instances a C-MOVE retrieves
+//!
+//! A C-MOVE of a large study can bring back thousands of instances. The move handler persists
+//! each one as the SCU hands it over instead of collecting them first, with at most
+//! `move_concurrency` (default 4) instances being persisted at a time; the SCU's bounded
+//! channel holds back further instances until one finishes. Every persisted instance is
+//! counted, but identifier JSON is kept only for the first `move_instance_limit` (default 100),
+//! so memory stays flat whatever the size of the study.
+
+use dimse::types::DatasetStream;
+use futures_util::{Stream, StreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// How the instances of one C-MOVE are processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveTransfer {
+    /// Instances persisted at the same time
+    pub concurrency: usize,
+    /// Identifiers kept for the response
+    pub instance_limit: usize,
+}
+
+/// Outcome of a C-MOVE transfer
+#[derive(Debug, Default)]
+pub struct TransferSummary {
+    /// Instances persisted
+    pub count: usize,
+    /// Identifiers of the first instances, up to the instance limit
+    pub instances: Vec<Value>,
+    /// Whether identifiers were left out because of the instance limit
+    pub truncated: bool,
+}
+
+impl Default for MoveTransfer {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            instance_limit: 100,
+        }
+    }
+}
+
+impl MoveTransfer {
+    /// Read `move_concurrency` and `move_instance_limit` from backend options
+    pub fn from_options(options: &HashMap<String, Value>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let concurrency = options
+            .get("move_concurrency")
+            .map(|v| {
+                v.as_u64()
+                    .filter(|n| *n > 0)
+                    .ok_or("move_concurrency must be a positive integer")
+            })
+            .transpose()?
+            .map_or(defaults.concurrency, |n| n as usize);
+        let instance_limit = options
+            .get("move_instance_limit")
+            .map(|v| {
+                v.as_u64()
+                    .ok_or("move_instance_limit must be a non-negative integer")
+            })
+            .transpose()?
+            .map_or(defaults.instance_limit, |n| n as usize);
+        Ok(Self {
+            concurrency,
+            instance_limit,
+        })
+    }
+
+    /// Hand each instance of `stream` to `persist` as it arrives
+    ///
+    /// `persist` stores the instance and returns its identifier JSON, if it could read one, or
+    /// why it could not be stored. Only stored instances are counted; errors in the stream and
+    /// instances that could not be stored are logged and skipped.
+    pub async fn run<S, F, Fut>(&self, stream: S, persist: F) -> TransferSummary
+    where
+        S: Stream<Item = dimse::Result<DatasetStream>>,
+        F: Fn(DatasetStream) -> Fut,
+        Fut: Future<Output = Result<Option<Value>, String>>,
+    {
+        let count = AtomicUsize::new(0);
+        let summary = Mutex::new(TransferSummary::default());
+        let (persist, counter, kept, limit) = (&persist, &count, &summary, self.instance_limit);
+        stream
+            .for_each_concurrent(self.concurrency, move |item| async move {
+                let ds = match item {
+                    Ok(ds) => ds,
+                    Err(e) => {
+                        tracing::warn!("Error in C-MOVE dataset stream: {}", e);
+                        return;
+                    }
+                };
+                let identifier = match persist(ds).await {
+                    Ok(identifier) => identifier,
+                    Err(e) => {
+                        tracing::warn!("C-MOVE instance not persisted: {}", e);
+                        return;
+                    }
+                };
+                let persisted = counter.fetch_add(1, Ordering::Relaxed) + 1;
+                if persisted % 100 == 0 {
+                    tracing::debug!(persisted, "C-MOVE transfer in progress");
+                }
+                let Some(identifier) = identifier else {
+                    return;
+                };
+                let mut summary = kept.lock().expect("transfer summary poisoned");
+                if summary.instances.len() < limit {
+                    summary.instances.push(identifier);
+                } else {
+                    summary.truncated = true;
+                }
+            })
+            .await;
+
+        let mut summary = summary.into_inner().expect("transfer summary poisoned");
+        summary.count = count.into_inner();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dicom_object::InMemDicomObject;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_large_move_is_counted_with_bounded_memory() {
+        let transfer = MoveTransfer {
+            concurrency: 4,
+            instance_limit: 10,
+        };
+        let (in_flight, peak, persisted) = (
+            &AtomicUsize::new(0),
+            &AtomicUsize::new(0),
+            &AtomicUsize::new(0),
+        );
+        let stream = futures_util::stream::iter(
+            (0..500).map(|_| Ok(DatasetStream::from_object(InMemDicomObject::new_empty()))),
+        );
+
+        let summary = transfer
+            .run(stream, move |_ds| async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let n = persisted.fetch_add(1, Ordering::SeqCst);
+                Ok(Some(json!({ "n": n })))
+            })
+            .await;
+
+        // Every instance was counted, but no more than four were ever held at once
+        assert_eq!(summary.count, 500);
+        assert_eq!(persisted.load(Ordering::SeqCst), 500);
+        assert!(peak.load(Ordering::SeqCst) <= 4);
+        assert_eq!(summary.instances.len(), 10);
+        assert!(summary.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_stream_errors_are_skipped() {
+        let stream = futures_util::stream::iter(vec![
+            Ok(DatasetStream::from_object(InMemDicomObject::new_empty())),
+            Err(dimse::DimseError::operation_failed("peer aborted")),
+            Ok(DatasetStream::from_object(InMemDicomObject::new_empty())),
+        ]);
+        let summary = MoveTransfer::default()
+            .run(stream, |_ds| async { Ok(None) })
+            .await;
+        assert_eq!(summary.count, 2);
+        assert!(summary.instances.is_empty());
+        assert!(!summary.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_instances_that_fail_to_persist_are_not_counted() {
+        let stream = futures_util::stream::iter(
+            (0..6).map(|_| Ok(DatasetStream::from_object(InMemDicomObject::new_empty()))),
+        );
+        let attempts = &AtomicUsize::new(0);
+        let summary = MoveTransfer::default()
+            .run(stream, move |_ds| async move {
+                // Every other write fails, like a storage backend that is out of space
+                if attempts.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+                    Err("disk full".to_string())
+                } else {
+                    Ok(Some(json!({})))
+                }
+            })
+            .await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 6);
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.instances.len(), 3);
+    }
+
+    #[test]
+    fn test_from_options() {
+        let options: HashMap<String, Value> = serde_json::from_value(json!({
+            "move_concurrency": 8,
+            "move_instance_limit": 0
+        }))
+        .unwrap();
+        let transfer = MoveTransfer::from_options(&options).unwrap();
+        assert_eq!(transfer.concurrency, 8);
+        assert_eq!(transfer.instance_limit, 0);
+        assert_eq!(
+            MoveTransfer::from_options(&HashMap::new()).unwrap(),
+            MoveTransfer::default()
+        );
+
+        let options: HashMap<String, Value> =
+            serde_json::from_value(json!({ "move_concurrency": 0 })).unwrap();
+        assert!(MoveTransfer::from_options(&options).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 use crate::config::config::ConfigError;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::log_context::CORRELATION_ID;
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
+use crate::models::middleware::types::dicomweb_bridge::normalize_dicomweb_path;
 use crate::models::services::services::{ServiceHandler, ServiceType};
+use crate::models::services::types::dicomweb_stow;
 use crate::router::route_config::RouteConfig;
 use crate::utils::Error;
 use async_trait::async_trait;
@@ -15,11 +18,81 @@ use std::collections::HashMap;
 pub struct DicomwebEndpoint {}
 
 impl DicomwebEndpoint {
+    /// Default response headers configured on the endpoint (`response_headers`, plus
+    /// `qido_response_headers` for QIDO results). Names are lowercased; QIDO entries win.
+    fn default_headers(
+        options: &HashMap<String, Value>,
+        is_qido: bool,
+    ) -> Vec<(String, String)> {
+        let mut keys = vec!["response_headers"];
+        if is_qido {
+            keys.push("qido_response_headers");
+        }
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for key in keys {
+            let Some(table) = options.get(key).and_then(|v| v.as_object()) else {
+                continue;
+            };
+            for (name, value) in table {
+                let Some(value) = value.as_str() else {
+                    continue;
+                };
+                let name = name.to_ascii_lowercase();
+                headers.retain(|(n, _)| n != &name);
+                headers.push((name, value.to_string()));
+            }
+        }
+        headers
+    }
+
+    /// Provenance headers for the response when `provenance_headers` is enabled: `X-Source`
+    /// is `cache` for results served by `study_cache` or `cache`, otherwise the AET of the PACS
+    /// the backend queried; `X-Source-Retrieved-At` is when that data was fetched (RFC 3339)
+    fn provenance_headers(
+        options: &HashMap<String, Value>,
+        request_metadata: &HashMap<String, String>,
+    ) -> Vec<(String, String)> {
+        let enabled = options
+            .get("provenance_headers")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if !enabled {
+            return Vec::new();
+        }
+
+        let cached = request_metadata.contains_key("study_cache")
+            || request_metadata.get("response_cache").map(String::as_str) == Some("hit");
+        let source = if cached {
+            Some("cache".to_string())
+        } else {
+            request_metadata.get("source_aet").cloned()
+        };
+        let mut headers = Vec::new();
+        if let Some(source) = source {
+            headers.push(("x-source".to_string(), source));
+        }
+        if let Some(retrieved_at) = request_metadata.get("retrieved_at") {
+            headers.push(("x-source-retrieved-at".to_string(), retrieved_at.clone()));
+        }
+        headers
+    }
+
+    /// Status for a QIDO query with no matches: 204 per the DICOMweb spec, or 200 with `[]`
+    /// for viewers that cannot handle 204 (`empty_qido_status = 200`)
+    fn empty_qido_status(options: &HashMap<String, Value>) -> http::StatusCode {
+        match options.get("empty_qido_status").and_then(|v| v.as_u64()) {
+            Some(200) => http::StatusCode::OK,
+            _ => http::StatusCode::NO_CONTENT,
+        }
+    }
+
     /// Handle DICOMweb-specific response types with appropriate HTTP semantics
     async fn handle_dicomweb_response(
         &self,
         response_type: &str,
         nd: &Value,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         let data = nd.get("dicomweb_data");
         let metadata = nd.get("dicomweb_metadata").and_then(|v| v.as_object());
@@ -45,15 +118,34 @@ impl DicomwebEndpoint {
                 let status = if has_results {
                     http::StatusCode::OK
                 } else {
-                    http::StatusCode::NO_CONTENT
+                    Self::empty_qido_status(options)
                 };
 
                 let body_str = serde_json::to_string(&json_data)
                     .map_err(|_| Error::from("Failed to serialize QIDO JSON"))?;
 
-                Response::builder()
+                let mut builder = Response::builder()
                     .status(status)
-                    .header("content-type", "application/dicom+json")
+                    .header("content-type", "application/dicom+json");
+
+                // Surface backend warnings (e.g. C-FIND 0xFF01) as HTTP Warning headers
+                if let Some(warnings) = metadata
+                    .and_then(|m| m.get("warnings"))
+                    .and_then(|v| v.as_array())
+                {
+                    for w in warnings {
+                        let message = w
+                            .get("message")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("Some requested optional keys were not supported");
+                        builder = builder.header(
+                            "warning",
+                            format!("299 harmony \"{}\"", message.replace('"', "'")),
+                        );
+                    }
+                }
+
+                builder
                     .body(Body::from(body_str))
                     .map_err(|_| Error::from("Failed to construct QIDO response"))
             }
@@ -94,10 +186,17 @@ impl DicomwebEndpoint {
                             .decode(body_b64)
                             .map_err(|_| Error::from("Failed to decode WADO instance body_b64"))?;
 
-                        let content_type = format!(
-                            "multipart/related; type=\"application/dicom\"; boundary={}",
-                            boundary
-                        );
+                        let transfer_syntax = meta.get("transfer_syntax").and_then(|v| v.as_str());
+                        let content_type = match transfer_syntax {
+                            Some(transfer_syntax) => format!(
+                                "multipart/related; type=\"application/dicom\"; transfer-syntax={}; boundary={}",
+                                transfer_syntax, boundary
+                            ),
+                            None => format!(
+                                "multipart/related; type=\"application/dicom\"; boundary={}",
+                                boundary
+                            ),
+                        };
 
                         return Response::builder()
                             .status(http::StatusCode::OK)
@@ -158,6 +257,183 @@ impl DicomwebEndpoint {
                     .body(Body::from(r#"{"error":"Missing frame data"}"#))
                     .map_err(|_| Error::from("Failed to construct error response"))
             }
+            "wado_pixeldata" => {
+                // Raw PixelData: a single octet-stream for native pixels, or one part per
+                // fragment for encapsulated transfer syntaxes
+                if let Some(meta) = metadata {
+                    if let (Some(transfer_syntax), Some(body_b64)) = (
+                        meta.get("transfer_syntax").and_then(|v| v.as_str()),
+                        meta.get("body_b64").and_then(|v| v.as_str()),
+                    ) {
+                        let bytes = base64::engine::general_purpose::STANDARD
+                            .decode(body_b64)
+                            .map_err(|_| Error::from("Failed to decode pixel data body_b64"))?;
+
+                        let content_type = match meta.get("boundary").and_then(|v| v.as_str()) {
+                            Some(boundary) => format!(
+                                "multipart/related; type=\"application/octet-stream\"; transfer-syntax={}; boundary={}",
+                                transfer_syntax, boundary
+                            ),
+                            None => format!(
+                                "application/octet-stream; transfer-syntax={}",
+                                transfer_syntax
+                            ),
+                        };
+
+                        return Response::builder()
+                            .status(http::StatusCode::OK)
+                            .header("content-type", content_type)
+                            .body(Body::from(bytes))
+                            .map_err(|_| Error::from("Failed to construct pixel data response"));
+                    }
+                }
+                // Fallback to error if metadata is missing
+                Response::builder()
+                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"error":"Missing pixel data"}"#))
+                    .map_err(|_| Error::from("Failed to construct error response"))
+            }
+            "wado_bulkdata" => {
+                // Raw attribute values from a bulk data URI: one octet-stream, or one part per
+                // requested attribute
+                if let Some(body_b64) = metadata
+                    .and_then(|m| m.get("body_b64"))
+                    .and_then(|v| v.as_str())
+                {
+                    let bytes = base64::engine::general_purpose::STANDARD
+                        .decode(body_b64)
+                        .map_err(|_| Error::from("Failed to decode bulk data body_b64"))?;
+                    let content_type = match metadata
+                        .and_then(|m| m.get("boundary"))
+                        .and_then(|v| v.as_str())
+                    {
+                        Some(boundary) => format!(
+                            "multipart/related; type=\"application/octet-stream\"; boundary={}",
+                            boundary
+                        ),
+                        None => "application/octet-stream".to_string(),
+                    };
+
+                    return Response::builder()
+                        .status(http::StatusCode::OK)
+                        .header("content-type", content_type)
+                        .body(Body::from(bytes))
+                        .map_err(|_| Error::from("Failed to construct bulk data response"));
+                }
+                Response::builder()
+                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"error":"Missing bulk data"}"#))
+                    .map_err(|_| Error::from("Failed to construct error response"))
+            }
+            "wado_uri" => {
+                // WADO-URI responses: the object as application/dicom, or a rendered image
+                if let Some(meta) = metadata {
+                    if let (Some(content_type), Some(body_b64)) = (
+                        meta.get("content_type").and_then(|v| v.as_str()),
+                        meta.get("body_b64").and_then(|v| v.as_str()),
+                    ) {
+                        let bytes = base64::engine::general_purpose::STANDARD
+                            .decode(body_b64)
+                            .map_err(|_| Error::from("Failed to decode WADO-URI body_b64"))?;
+
+                        return Response::builder()
+                            .status(http::StatusCode::OK)
+                            .header("content-type", content_type)
+                            .body(Body::from(bytes))
+                            .map_err(|_| Error::from("Failed to construct WADO-URI response"));
+                    }
+                }
+                Response::builder()
+                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"error":"Missing WADO-URI data"}"#))
+                    .map_err(|_| Error::from("Failed to construct error response"))
+            }
+            "redirect" => {
+                // Bulk data URI naming PixelData: send the client to the frames resource
+                let location = metadata
+                    .and_then(|m| m.get("location"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| Error::from("Missing redirect location"))?;
+                Response::builder()
+                    .status(http::StatusCode::SEE_OTHER)
+                    .header("location", location)
+                    .body(Body::empty())
+                    .map_err(|_| Error::from("Failed to construct redirect response"))
+            }
+            "bad_request" | "not_found" | "not_acceptable" => {
+                // Request could not be mapped to a DIMSE query (e.g. unknown attribute), an
+                // existence check found nothing to retrieve, or the requested transfer syntax
+                // cannot be produced
+                let (status, default_error, default_message) = match response_type {
+                    "not_found" => (
+                        http::StatusCode::NOT_FOUND,
+                        "NotFound",
+                        "Requested resource not found",
+                    ),
+                    "not_acceptable" => (
+                        http::StatusCode::NOT_ACCEPTABLE,
+                        "NotAcceptable",
+                        "Requested representation is not available",
+                    ),
+                    _ => (
+                        http::StatusCode::BAD_REQUEST,
+                        "BadRequest",
+                        "Invalid DICOMweb request",
+                    ),
+                };
+                let error = metadata
+                    .and_then(|m| m.get("error"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(default_error);
+                let message = metadata
+                    .and_then(|m| m.get("message"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(default_message);
+
+                let body_str = serde_json::to_string(&serde_json::json!({
+                    "error": error,
+                    "message": message,
+                }))
+                .map_err(|_| Error::from("Failed to serialize error response"))?;
+
+                Response::builder()
+                    .status(status)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body_str))
+                    .map_err(|_| Error::from("Failed to construct error response"))
+            }
+            "dimse_failure" => {
+                // Backend DIMSE failure, already mapped to an HTTP status by the bridge
+                let status = metadata
+                    .and_then(|m| m.get("http_status"))
+                    .and_then(|v| v.as_u64())
+                    .and_then(|s| u16::try_from(s).ok())
+                    .and_then(|s| http::StatusCode::from_u16(s).ok())
+                    .unwrap_or(http::StatusCode::BAD_GATEWAY);
+                let error = metadata
+                    .and_then(|m| m.get("error"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("DimseFailure");
+                let message = metadata
+                    .and_then(|m| m.get("message"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("DIMSE operation failed");
+
+                let body_str = serde_json::to_string(&serde_json::json!({
+                    "error": error,
+                    "message": message,
+                }))
+                .map_err(|_| Error::from("Failed to serialize error response"))?;
+
+                Response::builder()
+                    .status(status)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body_str))
+                    .map_err(|_| Error::from("Failed to construct error response"))
+            }
             "wado_frames_error" => {
                 // Handle frame decoding errors
                 let error_msg = metadata
@@ -208,6 +484,47 @@ impl ServiceType for DicomwebEndpoint {
                 reason: "DICOMweb endpoint requires a non-empty 'path_prefix'".to_string(),
             });
         }
+
+        for key in ["response_headers", "qido_response_headers"] {
+            let Some(table) = options.get(key) else {
+                continue;
+            };
+            let table = table.as_object().ok_or_else(|| ConfigError::InvalidEndpoint {
+                name: "dicomweb".to_string(),
+                reason: format!("'{}' must be a table of header names to values", key),
+            })?;
+            for (name, value) in table {
+                let valid = http::HeaderName::from_bytes(name.as_bytes()).is_ok()
+                    && value
+                        .as_str()
+                        .is_some_and(|v| http::HeaderValue::from_str(v).is_ok());
+                if !valid {
+                    return Err(ConfigError::InvalidEndpoint {
+                        name: "dicomweb".to_string(),
+                        reason: format!("Invalid header '{}' in '{}'", name, key),
+                    });
+                }
+            }
+        }
+
+        if let Some(status) = options.get("empty_qido_status") {
+            if !matches!(status.as_u64(), Some(200) | Some(204)) {
+                return Err(ConfigError::InvalidEndpoint {
+                    name: "dicomweb".to_string(),
+                    reason: "'empty_qido_status' must be 204 or 200".to_string(),
+                });
+            }
+        }
+
+        if options
+            .get("provenance_headers")
+            .is_some_and(|v| !v.is_boolean())
+        {
+            return Err(ConfigError::InvalidEndpoint {
+                name: "dicomweb".to_string(),
+                reason: "'provenance_headers' must be a boolean".to_string(),
+            });
+        }
         Ok(())
     }
 
@@ -220,16 +537,16 @@ impl ServiceType for DicomwebEndpoint {
         let base = path_prefix.trim_end_matches('/');
 
         let routes = vec![
-            // QIDO-RS: Query for studies
+            // QIDO-RS: Query for studies; STOW-RS: Store instances
             RouteConfig {
                 path: format!("{}/studies", base),
-                methods: vec![Method::GET],
+                methods: vec![Method::GET, Method::POST],
                 description: Some("DICOMweb QIDO-RS: Query for studies".to_string()),
             },
-            // QIDO-RS: Query for specific study
+            // QIDO-RS: Query for specific study; STOW-RS: Store instances of a study
             RouteConfig {
                 path: format!("{}/studies/{{study_uid}}", base),
-                methods: vec![Method::GET],
+                methods: vec![Method::GET, Method::POST],
                 description: Some("DICOMweb QIDO-RS: Query for specific study".to_string()),
             },
             // QIDO-RS: Query for series within a study
@@ -280,12 +597,58 @@ impl ServiceType for DicomwebEndpoint {
                 methods: vec![Method::GET],
                 description: Some("DICOMweb WADO-RS: Retrieve rendered frames".to_string()),
             },
+            // WADO-RS: Retrieve rendered series
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/series/{{series_uid}}/rendered", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb WADO-RS: Retrieve rendered series".to_string()),
+            },
+            // WADO-RS: Retrieve rendered instance
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/series/{{series_uid}}/instances/{{instance_uid}}/rendered", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb WADO-RS: Retrieve rendered instance".to_string()),
+            },
+            // WADO-RS: Retrieve rendered frames with presentation parameters
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/series/{{series_uid}}/instances/{{instance_uid}}/frames/{{frame_numbers}}/rendered", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb WADO-RS: Retrieve rendered frames".to_string()),
+            },
+            // Thumbnails of a study, series or instance
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/thumbnail", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb: Retrieve study thumbnail".to_string()),
+            },
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/series/{{series_uid}}/thumbnail", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb: Retrieve series thumbnail".to_string()),
+            },
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/series/{{series_uid}}/instances/{{instance_uid}}/thumbnail", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb: Retrieve instance thumbnail".to_string()),
+            },
+            // WADO-RS: Retrieve raw pixel data
+            RouteConfig {
+                path: format!("{}/studies/{{study_uid}}/series/{{series_uid}}/instances/{{instance_uid}}/pixeldata", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb WADO-RS: Retrieve instance pixel data".to_string()),
+            },
             // WADO-RS: Bulk data retrieval
             RouteConfig {
                 path: format!("{}/bulkdata/{{*bulk_data_uri}}", base),
                 methods: vec![Method::GET],
                 description: Some("DICOMweb WADO-RS: Bulk data retrieval".to_string()),
             },
+            // WADO-URI: Retrieve an object named by query parameters
+            RouteConfig {
+                path: format!("{}/wado", base),
+                methods: vec![Method::GET],
+                description: Some("DICOMweb WADO-URI: Retrieve object".to_string()),
+            },
         ];
 
         // Add OPTIONS for CORS support on all routes
@@ -324,7 +687,7 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
     async fn endpoint_incoming_request(
         &self,
         mut envelope: RequestEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<RequestEnvelope<Vec<u8>>, Error> {
         let method = envelope.request_details.method.to_uppercase();
         let subpath = envelope
@@ -362,12 +725,18 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
             hdrs.insert("access-control-allow-origin".to_string(), "*".to_string());
             hdrs.insert(
                 "access-control-allow-methods".to_string(),
-                "GET, OPTIONS".to_string(),
+                "GET, POST, OPTIONS".to_string(),
             );
             hdrs.insert(
                 "access-control-allow-headers".to_string(),
                 "accept, content-type".to_string(),
             );
+            // Configured CORS policy replaces the permissive defaults
+            for (name, value) in Self::default_headers(options, false) {
+                if name.starts_with("access-control-") {
+                    hdrs.insert(name, value);
+                }
+            }
             set_response(http::StatusCode::OK, hdrs, None, None);
             // Skip backends for OPTIONS requests
             envelope
@@ -377,8 +746,73 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
             return Ok(envelope);
         }
 
-        // Check if this is a QIDO or WADO endpoint that should be processed
-        let parts: Vec<&str> = subpath.split('/').filter(|s| !s.is_empty()).collect();
+        // Check if this is a QIDO or WADO endpoint that should be processed; route segments are
+        // matched case-insensitively unless normalize_paths is turned off
+        let route_path = if options
+            .get("normalize_paths")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+        {
+            normalize_dicomweb_path(&subpath)
+        } else {
+            subpath.clone()
+        };
+        let parts: Vec<&str> = route_path.split('/').filter(|s| !s.is_empty()).collect();
+
+        // STOW-RS is answered here; instances go to the storage backend, not a DIMSE backend
+        if method == "POST" {
+            let study_uid = match parts.as_slice() {
+                ["studies"] => None,
+                ["studies", study_uid] => Some(study_uid.to_string()),
+                _ => return Ok(envelope),
+            };
+            let content_type = envelope
+                .request_details
+                .headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+                .map(|(_, v)| v.clone())
+                .unwrap_or_default();
+            let storage = match crate::globals::get_storage() {
+                Some(storage) => storage,
+                None => std::sync::Arc::new(
+                    crate::storage::FilesystemStorage::with_default_path()
+                        .map_err(|e| Error::from(format!("STOW-RS storage unavailable: {}", e)))?,
+                ),
+            };
+            let mut hdrs = HashMap::new();
+            match dicomweb_stow::store_instances(
+                storage.as_ref(),
+                &content_type,
+                &envelope.original_data,
+                study_uid.as_deref(),
+            )
+            .await
+            {
+                Ok((status, response)) => {
+                    hdrs.insert(
+                        "content-type".to_string(),
+                        "application/dicom+json".to_string(),
+                    );
+                    set_response(status, hdrs, None, Some(response));
+                }
+                Err(e) => {
+                    tracing::warn!("STOW-RS request rejected: {}", e.message());
+                    hdrs.insert("content-type".to_string(), "application/json".to_string());
+                    let error = serde_json::json!({
+                        "error": e.status().canonical_reason().unwrap_or("Bad Request"),
+                        "message": e.message(),
+                    });
+                    set_response(e.status(), hdrs, None, Some(error));
+                }
+            }
+            envelope
+                .request_details
+                .metadata
+                .insert("skip_backends".to_string(), "true".to_string());
+            return Ok(envelope);
+        }
+
         let should_process = match parts.as_slice() {
             // QIDO endpoints
             ["studies"] => true,
@@ -392,7 +826,16 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
             ["studies", _, "series", _, "metadata"] => true,
             ["studies", _, "series", _, "instances", _, "metadata"] => true,
             ["studies", _, "series", _, "instances", _, "frames", _] => true,
+            ["studies", _, "series", _, "instances", _, "pixeldata"] => true,
+            ["studies", _, "series", _, "rendered"] => true,
+            ["studies", _, "series", _, "instances", _, "rendered"] => true,
+            ["studies", _, "series", _, "instances", _, "frames", _, "rendered"] => true,
+            ["studies", _, "thumbnail"] => true,
+            ["studies", _, "series", _, "thumbnail"] => true,
+            ["studies", _, "series", _, "instances", _, "thumbnail"] => true,
             ["bulkdata", ..] => true,
+            // WADO-URI
+            ["wado"] => true,
             _ => false,
         };
 
@@ -507,7 +950,7 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
         &self,
         envelope: &mut ResponseEnvelope<Vec<u8>>,
         ctx: &crate::models::protocol::ProtocolCtx,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<(), Error> {
         envelope
             .response_details
@@ -525,6 +968,20 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
                 .headers
                 .entry("content-type".to_string())
                 .or_insert_with(|| "application/dicom+json".to_string());
+
+            // Configured defaults never override headers already set by a handler
+            let is_qido = envelope
+                .normalized_data
+                .as_ref()
+                .and_then(|nd| nd.get("dicomweb_response_type"))
+                .and_then(|v| v.as_str())
+                == Some("qido_json");
+            for (name, value) in Self::default_headers(options, is_qido) {
+                let headers = &mut envelope.response_details.headers;
+                if !headers.keys().any(|k| k.eq_ignore_ascii_case(&name)) {
+                    headers.insert(name, value);
+                }
+            }
         }
         Ok(())
     }
@@ -532,7 +989,7 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
     async fn endpoint_outgoing_response(
         &self,
         envelope: ResponseEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         // Always check normalized_data first for DICOMweb-specific response types from middleware
         let nd = envelope
@@ -545,9 +1002,48 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
             nd.as_object().map(|o| o.keys().collect::<Vec<_>>())
         );
 
+        let mut extra_headers =
+            Self::provenance_headers(options, &envelope.request_details.metadata);
+        // Lets clients quote the ID the pipeline logged the request under
+        if let Some(id) = envelope.request_details.metadata.get(CORRELATION_ID) {
+            extra_headers.push(("x-correlation-id".to_string(), id.clone()));
+        }
+
         if let Some(response_type) = nd.get("dicomweb_response_type").and_then(|v| v.as_str()) {
             tracing::debug!("Found dicomweb_response_type: {}", response_type);
-            return self.handle_dicomweb_response(response_type, &nd).await;
+            let mut response = self
+                .handle_dicomweb_response(response_type, &nd, options)
+                .await?;
+
+            for (name, value) in extra_headers {
+                if let (Ok(header_name), Ok(header_value)) = (
+                    http::HeaderName::from_bytes(name.as_bytes()),
+                    http::HeaderValue::from_str(&value),
+                ) {
+                    response.headers_mut().insert(header_name, header_value);
+                }
+            }
+
+            // Carry over configured default headers resolved in endpoint_outgoing_protocol,
+            // leaving anything the response handler set (e.g. frame content-type) untouched
+            let is_qido = response_type == "qido_json";
+            for (name, _) in Self::default_headers(options, is_qido) {
+                if response.headers().contains_key(name.as_str()) {
+                    continue;
+                }
+                let value = envelope
+                    .response_details
+                    .headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(&name))
+                    .and_then(|(_, v)| http::HeaderValue::from_str(v).ok());
+                if let (Ok(header_name), Some(header_value)) =
+                    (http::HeaderName::from_bytes(name.as_bytes()), value)
+                {
+                    response.headers_mut().insert(header_name, header_value);
+                }
+            }
+            return Ok(response);
         }
 
         tracing::debug!("No dicomweb_response_type found, using standard handling");
@@ -562,20 +1058,17 @@ impl ServiceHandler<Value> for DicomwebEndpoint {
         for (k, v) in &envelope.response_details.headers {
             builder = builder.header(k.as_str(), v.as_str());
         }
+        for (name, value) in extra_headers {
+            builder = builder.header(name, value);
+        }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_bytes = serde_json::to_vec(&normalized)
-                .map_err(|_| Error::from("Failed to serialize DICOMweb response JSON"))?;
-            Body::from(body_bytes)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize DICOMweb response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct DICOMweb HTTP response"))
     }
 }
@@ -645,6 +1138,240 @@ mod tests {
         assert_eq!(content_type.unwrap(), "application/dicom+json");
     }
 
+    #[tokio::test]
+    async fn test_configured_default_headers_on_qido_response() {
+        use crate::models::protocol::{Protocol, ProtocolCtx};
+
+        let endpoint = DicomwebEndpoint {};
+        let mut options = HashMap::new();
+        options.insert(
+            "response_headers".to_string(),
+            serde_json::json!({
+                "Vary": "Accept",
+                "Access-Control-Allow-Origin": "https://viewer.example.org",
+                "Content-Type": "text/plain"
+            }),
+        );
+        options.insert(
+            "qido_response_headers".to_string(),
+            serde_json::json!({ "Cache-Control": "private, max-age=60" }),
+        );
+        options.insert("path_prefix".to_string(), serde_json::json!("/dicomweb"));
+        assert!(endpoint.validate(&options).is_ok());
+
+        let mut envelope = ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: "/dicomweb/studies".to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata: HashMap::new(),
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: vec![],
+            normalized_data: Some(serde_json::json!({
+                "dicomweb_response_type": "qido_json",
+                "dicomweb_data": [{"0020000D": {"vr": "UI", "Value": ["1.2.3"]}}],
+                "dicomweb_metadata": {"has_results": true}
+            })),
+            normalized_snapshot: None,
+        };
+        let ctx = ProtocolCtx {
+            protocol: Protocol::Http,
+            payload: Vec::new(),
+            meta: HashMap::new(),
+            attrs: serde_json::json!({}),
+        };
+
+        endpoint
+            .endpoint_outgoing_protocol(&mut envelope, &ctx, &options)
+            .await
+            .unwrap();
+        let resp = endpoint
+            .endpoint_outgoing_response(envelope, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(resp.headers()["cache-control"], "private, max-age=60");
+        assert_eq!(resp.headers()["vary"], "Accept");
+        assert_eq!(
+            resp.headers()["access-control-allow-origin"],
+            "https://viewer.example.org"
+        );
+        // Handler-set content type wins over the configured default
+        assert_eq!(resp.headers()["content-type"], "application/dicom+json");
+    }
+
+    #[tokio::test]
+    async fn test_provenance_headers_distinguish_cache_from_live() {
+        let endpoint = DicomwebEndpoint {};
+        let mut options = HashMap::new();
+        options.insert("provenance_headers".to_string(), serde_json::json!(true));
+
+        let qido_envelope = |metadata: HashMap<String, String>| ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: "/dicomweb/studies".to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata,
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: vec![],
+            normalized_data: Some(serde_json::json!({
+                "dicomweb_response_type": "qido_json",
+                "dicomweb_data": [{"0020000D": {"vr": "UI", "Value": ["1.2.3"]}}],
+                "dicomweb_metadata": {"has_results": true}
+            })),
+            normalized_snapshot: None,
+        };
+
+        let cached = HashMap::from([
+            ("study_cache".to_string(), "hit".to_string()),
+            (
+                "retrieved_at".to_string(),
+                "2026-01-01T00:00:00+00:00".to_string(),
+            ),
+        ]);
+        let resp = endpoint
+            .endpoint_outgoing_response(qido_envelope(cached), &options)
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["x-source"], "cache");
+        assert_eq!(
+            resp.headers()["x-source-retrieved-at"],
+            "2026-01-01T00:00:00+00:00"
+        );
+
+        let live = HashMap::from([
+            ("source_aet".to_string(), "ORTHANC".to_string()),
+            (CORRELATION_ID.to_string(), "7f3c2a9e".to_string()),
+        ]);
+        let resp = endpoint
+            .endpoint_outgoing_response(qido_envelope(live.clone()), &options)
+            .await
+            .unwrap();
+        assert_eq!(resp.headers()["x-source"], "ORTHANC");
+
+        // Off unless configured
+        let resp = endpoint
+            .endpoint_outgoing_response(qido_envelope(live), &HashMap::new())
+            .await
+            .unwrap();
+        assert!(resp.headers().get("x-source").is_none());
+        // The correlation ID is always passed back
+        assert_eq!(resp.headers()["x-correlation-id"], "7f3c2a9e");
+    }
+
+    #[tokio::test]
+    async fn test_cached_second_get_reports_cache_provenance() {
+        use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+        use crate::models::middleware::middleware::Middleware;
+        use crate::models::middleware::types::cache::{CacheConfig, CacheMiddleware};
+
+        let endpoint = DicomwebEndpoint {};
+        let mut options = HashMap::new();
+        options.insert("provenance_headers".to_string(), serde_json::json!(true));
+        let cache = CacheMiddleware::new(CacheConfig {
+            ttl: std::time::Duration::from_secs(60),
+            max_body_bytes: 4096,
+            max_entries: 10,
+            scope: "test_provenance".to_string(),
+        });
+        let qido = || {
+            RequestEnvelopeBuilder::new()
+                .method("GET")
+                .uri("/dicomweb/studies?PatientID=P788")
+                .query_param("PatientID", vec!["P788".to_string()])
+                .metadata_entry("full_path", "/dicomweb/studies?PatientID=P788")
+                .metadata_entry("dimse_op", "find")
+                .original_data(serde_json::Value::Null)
+                .build()
+                .unwrap()
+        };
+        let body = serde_json::json!({
+            "dicomweb_response_type": "qido_json",
+            "dicomweb_data": [{"0020000D": {"vr": "UI", "Value": ["1.2.3"]}}],
+            "dicomweb_metadata": {"has_results": true}
+        });
+
+        // Through the cache and out of the endpoint, with the backend run on a miss
+        let (cache, endpoint, options, body) = (&cache, &endpoint, &options, &body);
+        let get = move || async move {
+            let mut request = cache.left(qido()).await.unwrap();
+            let metadata = &mut request.request_details.metadata;
+            if !metadata.contains_key("skip_backends") {
+                metadata.insert("source_aet".to_string(), "ORTHANC".to_string());
+                metadata.insert(
+                    "retrieved_at".to_string(),
+                    "2026-01-01T00:00:00+00:00".to_string(),
+                );
+                request.normalized_data = Some(body.clone());
+            }
+            let response = cache
+                .right(ResponseEnvelope {
+                    request_details: request.request_details,
+                    response_details: ResponseDetails {
+                        status: 200,
+                        headers: HashMap::new(),
+                        metadata: HashMap::new(),
+                    },
+                    original_data: serde_json::Value::Null,
+                    normalized_data: request.normalized_data,
+                    normalized_snapshot: None,
+                })
+                .await
+                .unwrap();
+            endpoint
+                .endpoint_outgoing_response(
+                    ResponseEnvelope {
+                        request_details: response.request_details,
+                        response_details: response.response_details,
+                        original_data: vec![],
+                        normalized_data: response.normalized_data,
+                        normalized_snapshot: None,
+                    },
+                    options,
+                )
+                .await
+                .unwrap()
+        };
+
+        let live = get().await;
+        assert_eq!(live.headers()["x-source"], "ORTHANC");
+        let cached = get().await;
+        assert_eq!(cached.headers()["x-source"], "cache");
+        // The cached body is as old as the backend call that produced it
+        assert_eq!(
+            cached.headers()["x-source-retrieved-at"],
+            "2026-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_invalid_response_headers_rejected() {
+        let endpoint = DicomwebEndpoint {};
+        let mut options = HashMap::new();
+        options.insert("path_prefix".to_string(), serde_json::json!("/dicomweb"));
+        options.insert(
+            "response_headers".to_string(),
+            serde_json::json!({ "Cache-Control": 60 }),
+        );
+        assert!(endpoint.validate(&options).is_err());
+    }
+
     #[tokio::test]
     async fn test_qido_response_empty_results() {
         let endpoint = DicomwebEndpoint {};
@@ -695,6 +1422,51 @@ mod tests {
         assert_eq!(content_type.unwrap(), "application/dicom+json");
     }
 
+    #[tokio::test]
+    async fn test_empty_qido_compat_status_returns_empty_array() {
+        let endpoint = DicomwebEndpoint {};
+        let mut options = HashMap::new();
+        options.insert("path_prefix".to_string(), serde_json::json!("/dicomweb"));
+        options.insert("empty_qido_status".to_string(), serde_json::json!(200));
+        assert!(endpoint.validate(&options).is_ok());
+
+        let envelope = ResponseEnvelope {
+            request_details: RequestDetails {
+                method: "GET".to_string(),
+                uri: "/dicomweb/studies".to_string(),
+                headers: HashMap::new(),
+                cookies: HashMap::new(),
+                query_params: HashMap::new(),
+                cache_status: None,
+                metadata: HashMap::new(),
+            },
+            response_details: ResponseDetails {
+                status: 200,
+                headers: HashMap::new(),
+                metadata: HashMap::new(),
+            },
+            original_data: vec![],
+            normalized_data: Some(serde_json::json!({
+                "dicomweb_response_type": "qido_json",
+                "dicomweb_data": [],
+                "dicomweb_metadata": {"has_results": false}
+            })),
+            normalized_snapshot: None,
+        };
+
+        let resp = endpoint
+            .endpoint_outgoing_response(envelope, &options)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        assert_eq!(resp.headers()["content-type"], "application/dicom+json");
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"[]");
+
+        options.insert("empty_qido_status".to_string(), serde_json::json!(404));
+        assert!(endpoint.validate(&options).is_err());
+    }
+
     #[tokio::test]
     async fn test_wado_frames_single_image() {
         let endpoint = DicomwebEndpoint {};
@@ -825,4 +1597,39 @@ mod tests {
             "Unable to decode frames for requested instance"
         );
     }
+
+    #[tokio::test]
+    async fn test_stow_rejects_non_multipart_body() {
+        let endpoint = DicomwebEndpoint {};
+        let mut metadata = HashMap::new();
+        metadata.insert("path".to_string(), "/studies".to_string());
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "application/json".to_string());
+        let envelope = RequestEnvelope::builder()
+            .method("POST")
+            .uri("/dicomweb/studies")
+            .headers(headers)
+            .metadata(metadata)
+            .original_data(b"{}".to_vec())
+            .build()
+            .unwrap();
+
+        let mut options = HashMap::new();
+        options.insert("path_prefix".to_string(), serde_json::json!("/dicomweb"));
+        let envelope = endpoint
+            .endpoint_incoming_request(envelope, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            envelope.request_details.metadata.get("skip_backends"),
+            Some(&"true".to_string())
+        );
+        let response = &envelope.normalized_data.unwrap()["response"];
+        assert_eq!(response["status"], 415);
+        assert!(response["json"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("multipart/related"));
+    }
 }
@@ -0,0 +1,545 @@
+//! STOW-RS: storing instances posted to the DICOMweb endpoint
+//!
+//! `POST {base}/studies[/{study_uid}]` carries a `multipart/related; type="application/dicom"`
+//! body with one Part 10 instance per part. Each instance is written through the storage
+//! backend under `stow/{study}/{series}/{instance}.dcm` and reported back in a
+//! StoreInstancesResponse (PS3.18 §10.5.3).
+
+use crate::storage::StorageBackend;
+use dicom_dictionary_std::tags;
+use serde_json::{json, Map, Value};
+
+/// Failure reasons used in FailedSOPSequence (PS3.4 Annex B status codes)
+const OUT_OF_RESOURCES: u16 = 0xA700;
+const STUDY_UID_MISMATCH: u16 = 0xA900;
+const CANNOT_UNDERSTAND: u16 = 0xC000;
+
+/// Storage subdirectory for instances received over STOW-RS
+const STOW_DIR: &str = "stow";
+
+/// One body part of a multipart/related request
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BodyPart {
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Why a STOW-RS request was rejected as a whole
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum StowError {
+    /// Not `multipart/related`, or parts that are not `application/dicom` (415)
+    UnsupportedMediaType(String),
+    /// Missing boundary or a body that does not follow it (400)
+    BadRequest(String),
+}
+
+impl StowError {
+    pub(crate) fn status(&self) -> http::StatusCode {
+        match self {
+            StowError::UnsupportedMediaType(_) => http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            StowError::BadRequest(_) => http::StatusCode::BAD_REQUEST,
+        }
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        match self {
+            StowError::UnsupportedMediaType(m) | StowError::BadRequest(m) => m,
+        }
+    }
+}
+
+/// Extract the boundary from a `multipart/related` Content-Type
+///
+/// Parameter names are case-insensitive and values may be quoted. A `type` parameter, when
+/// present, must be `application/dicom`.
+pub(crate) fn multipart_boundary(content_type: &str) -> Result<String, StowError> {
+    let mut params = split_params(content_type).into_iter();
+    let media_type = params.next().unwrap_or_default();
+    if !media_type.eq_ignore_ascii_case("multipart/related") {
+        return Err(StowError::UnsupportedMediaType(format!(
+            "Expected multipart/related, got '{}'",
+            media_type
+        )));
+    }
+
+    let mut boundary = None;
+    for param in params {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        match name.trim().to_ascii_lowercase().as_str() {
+            "boundary" => boundary = Some(value),
+            "type" if !value.eq_ignore_ascii_case("application/dicom") => {
+                return Err(StowError::UnsupportedMediaType(format!(
+                    "Only type=\"application/dicom\" is supported, got '{}'",
+                    value
+                )));
+            }
+            _ => {}
+        }
+    }
+    boundary
+        .filter(|b| !b.is_empty() && b.len() <= 70)
+        .ok_or_else(|| StowError::BadRequest("Missing or invalid multipart boundary".to_string()))
+}
+
+/// Split a header value on `;`, ignoring separators inside quoted strings
+fn split_params(value: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => {
+                escaped = false;
+                current.push(c);
+            }
+            '\\' if quoted => {
+                escaped = true;
+                current.push(c);
+            }
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ';' if !quoted => params.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    params.push(current.trim().to_string());
+    params
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+/// Index just past the line break at `pos`, accepting CRLF or a bare LF
+fn skip_line_break(body: &[u8], pos: usize) -> Option<usize> {
+    match &body[pos..] {
+        [b'\r', b'\n', ..] => Some(pos + 2),
+        [b'\n', ..] => Some(pos + 1),
+        _ => None,
+    }
+}
+
+/// Split a multipart/related body into its parts (RFC 2046 §5.1.1)
+///
+/// Lines may end in CRLF or a bare LF. Any preamble before the first delimiter and epilogue
+/// after the closing delimiter is ignored; the line break before each delimiter belongs to
+/// the delimiter, not the part.
+pub(crate) fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<BodyPart>, StowError> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut line_delimiter = b"\n".to_vec();
+    line_delimiter.extend_from_slice(&delimiter);
+    let malformed =
+        |reason: &str| StowError::BadRequest(format!("Malformed multipart body: {}", reason));
+
+    let mut pos = if body.starts_with(&delimiter) {
+        delimiter.len()
+    } else {
+        find(body, &line_delimiter, 0).ok_or_else(|| malformed("no opening boundary"))?
+            + line_delimiter.len()
+    };
+
+    let mut parts = Vec::new();
+    loop {
+        if body[pos..].starts_with(b"--") {
+            return Ok(parts);
+        }
+        // Transport padding may follow the delimiter before its line break
+        while matches!(body.get(pos), Some(b' ' | b'\t')) {
+            pos += 1;
+        }
+        pos = skip_line_break(body, pos)
+            .ok_or_else(|| malformed("boundary not followed by a line break"))?;
+
+        // Header lines run until the first empty line
+        let mut content_type = None;
+        loop {
+            if let Some(next) = skip_line_break(body, pos) {
+                pos = next;
+                break;
+            }
+            let end =
+                find(body, b"\n", pos).ok_or_else(|| malformed("unterminated part headers"))?;
+            let line = String::from_utf8_lossy(&body[pos..end]);
+            if let Some((name, value)) = line.trim_end_matches('\r').split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-type") {
+                    content_type = Some(value.trim().to_string());
+                }
+            }
+            pos = end + 1;
+        }
+
+        // The empty line's LF may itself start the next delimiter when the part is empty
+        let next = find(body, &line_delimiter, pos - 1)
+            .ok_or_else(|| malformed("missing closing boundary"))?;
+        let mut end = next;
+        if end > pos && body[end - 1] == b'\r' {
+            end -= 1;
+        }
+        parts.push(BodyPart {
+            content_type,
+            body: body[pos..end.max(pos)].to_vec(),
+        });
+        pos = next + line_delimiter.len();
+    }
+}
+
+/// Outcome of storing one instance
+#[derive(Debug, Clone, PartialEq)]
+enum InstanceResult {
+    Stored {
+        sop_class_uid: String,
+        sop_instance_uid: String,
+    },
+    Failed {
+        sop_class_uid: Option<String>,
+        sop_instance_uid: Option<String>,
+        reason: u16,
+    },
+}
+
+/// UIDs are used as path segments, so anything but digits and dots is refused
+fn is_uid(value: &str) -> bool {
+    !value.is_empty() && value.len() <= 64 && value.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+async fn store_instance(
+    storage: &dyn StorageBackend,
+    bytes: &[u8],
+    expected_study_uid: Option<&str>,
+) -> InstanceResult {
+    // Part 10 files start with a 128-byte preamble before the DICM prefix
+    let dataset = if bytes.len() >= 132 && &bytes[128..132] == b"DICM" {
+        &bytes[128..]
+    } else {
+        bytes
+    };
+    let object = match dicom_object::from_reader(dataset) {
+        Ok(object) => object,
+        Err(e) => {
+            tracing::warn!("STOW-RS: cannot parse posted instance: {}", e);
+            return InstanceResult::Failed {
+                sop_class_uid: None,
+                sop_instance_uid: None,
+                reason: CANNOT_UNDERSTAND,
+            };
+        }
+    };
+    let text = |tag| {
+        object
+            .element(tag)
+            .ok()
+            .and_then(|e| e.to_str().ok())
+            .map(|s| s.trim_end_matches('\0').trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let sop_class_uid = text(tags::SOP_CLASS_UID);
+    let sop_instance_uid = text(tags::SOP_INSTANCE_UID);
+    let failed = |reason| InstanceResult::Failed {
+        sop_class_uid: sop_class_uid.clone(),
+        sop_instance_uid: sop_instance_uid.clone(),
+        reason,
+    };
+
+    let (Some(class), Some(instance), Some(study), Some(series)) = (
+        sop_class_uid.clone(),
+        sop_instance_uid.clone(),
+        text(tags::STUDY_INSTANCE_UID),
+        text(tags::SERIES_INSTANCE_UID),
+    ) else {
+        return failed(CANNOT_UNDERSTAND);
+    };
+    if ![&class, &instance, &study, &series]
+        .iter()
+        .all(|uid| is_uid(uid))
+    {
+        return failed(CANNOT_UNDERSTAND);
+    }
+    if expected_study_uid.is_some_and(|expected| expected != study) {
+        tracing::warn!(
+            "STOW-RS: instance {} belongs to study {}, not the study in the request URL",
+            instance,
+            study
+        );
+        return failed(STUDY_UID_MISMATCH);
+    }
+
+    let path = format!("{}/{}/{}/{}.dcm", STOW_DIR, study, series, instance);
+    match storage.write_file_str(&path, bytes).await {
+        Ok(full_path) => {
+            tracing::info!(
+                "STOW-RS: stored instance {} of study {} at {}",
+                instance,
+                study,
+                full_path.display()
+            );
+            InstanceResult::Stored {
+                sop_class_uid: class,
+                sop_instance_uid: instance,
+            }
+        }
+        Err(e) => {
+            tracing::error!("STOW-RS: failed to store instance {}: {}", instance, e);
+            failed(OUT_OF_RESOURCES)
+        }
+    }
+}
+
+fn uid_attr(uid: &str) -> Value {
+    json!({"vr": "UI", "Value": [uid]})
+}
+
+/// Store every instance in a STOW-RS request body
+///
+/// Returns the HTTP status and StoreInstancesResponse: 200 when every instance was stored, 202
+/// when some failed and 409 when none were. The response carries no RetrieveURLs: WADO-RS
+/// requests go to the endpoint's backend, which does not see the STOW-RS store.
+pub(crate) async fn store_instances(
+    storage: &dyn StorageBackend,
+    content_type: &str,
+    body: &[u8],
+    study_uid: Option<&str>,
+) -> Result<(http::StatusCode, Value), StowError> {
+    let boundary = multipart_boundary(content_type)?;
+    let parts = parse_multipart(body, &boundary)?;
+    if parts.is_empty() {
+        return Err(StowError::BadRequest(
+            "Multipart body contains no instances".to_string(),
+        ));
+    }
+    if let Some(other) = parts.iter().find_map(|p| {
+        p.content_type
+            .as_deref()
+            .filter(|ct| !split_params(ct)[0].eq_ignore_ascii_case("application/dicom"))
+    }) {
+        return Err(StowError::UnsupportedMediaType(format!(
+            "Only application/dicom parts are supported, got '{}'",
+            other
+        )));
+    }
+
+    let mut referenced = Vec::new();
+    let mut failed = Vec::new();
+    for part in &parts {
+        match store_instance(storage, &part.body, study_uid).await {
+            InstanceResult::Stored {
+                sop_class_uid,
+                sop_instance_uid,
+            } => {
+                referenced.push(json!({
+                    "00081150": uid_attr(&sop_class_uid),
+                    "00081155": uid_attr(&sop_instance_uid),
+                }));
+            }
+            InstanceResult::Failed {
+                sop_class_uid,
+                sop_instance_uid,
+                reason,
+            } => {
+                let mut item = Map::new();
+                if let Some(uid) = sop_class_uid {
+                    item.insert("00081150".to_string(), uid_attr(&uid));
+                }
+                if let Some(uid) = sop_instance_uid {
+                    item.insert("00081155".to_string(), uid_attr(&uid));
+                }
+                item.insert(
+                    "00081197".to_string(),
+                    json!({"vr": "US", "Value": [reason]}),
+                );
+                failed.push(Value::Object(item));
+            }
+        }
+    }
+
+    let mut response = Map::new();
+    let status = match (referenced.is_empty(), failed.is_empty()) {
+        (false, true) => http::StatusCode::OK,
+        (false, false) => http::StatusCode::ACCEPTED,
+        (true, _) => http::StatusCode::CONFLICT,
+    };
+    if !failed.is_empty() {
+        response.insert("00081198".to_string(), json!({"vr": "SQ", "Value": failed}));
+    }
+    if !referenced.is_empty() {
+        response.insert(
+            "00081199".to_string(),
+            json!({"vr": "SQ", "Value": referenced}),
+        );
+    }
+    tracing::info!(
+        "STOW-RS: stored {} of {} posted instance(s)",
+        parts.len() - failed.len(),
+        parts.len()
+    );
+    Ok((status, Value::Object(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FilesystemStorage;
+    use dicom_core::{DataElement, PrimitiveValue, VR};
+    use dicom_object::meta::FileMetaTableBuilder;
+    use dicom_object::InMemDicomObject;
+
+    const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+
+    fn instance(study_uid: &str, sop_uid: &str) -> Vec<u8> {
+        let mut obj = InMemDicomObject::new_empty();
+        for (tag, uid) in [
+            (tags::SOP_CLASS_UID, CT_IMAGE),
+            (tags::SOP_INSTANCE_UID, sop_uid),
+            (tags::STUDY_INSTANCE_UID, study_uid),
+            (tags::SERIES_INSTANCE_UID, "1.2.3.4"),
+        ] {
+            obj.put(DataElement::new(tag, VR::UI, PrimitiveValue::from(uid)));
+        }
+        let mut bytes = Vec::new();
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax("1.2.840.10008.1.2.1")
+                .media_storage_sop_class_uid(CT_IMAGE)
+                .media_storage_sop_instance_uid(sop_uid),
+        )
+        .unwrap()
+        .write_all(&mut bytes)
+        .unwrap();
+        bytes
+    }
+
+    fn multipart(boundary: &str, parts: &[&[u8]], line_break: &[u8]) -> Vec<u8> {
+        let mut body = b"preamble to ignore".to_vec();
+        for part in parts {
+            body.extend_from_slice(line_break);
+            body.extend_from_slice(format!("--{}", boundary).as_bytes());
+            body.extend_from_slice(line_break);
+            body.extend_from_slice(b"Content-Type: application/dicom");
+            body.extend_from_slice(line_break);
+            body.extend_from_slice(line_break);
+            body.extend_from_slice(part);
+        }
+        body.extend_from_slice(line_break);
+        body.extend_from_slice(format!("--{}--", boundary).as_bytes());
+        body.extend_from_slice(line_break);
+        body.extend_from_slice(b"epilogue");
+        body
+    }
+
+    #[test]
+    fn test_multipart_boundary_parsing() {
+        assert_eq!(
+            multipart_boundary("multipart/related; type=\"application/dicom\"; boundary=abc")
+                .unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            multipart_boundary("Multipart/Related;BOUNDARY=\"a;b c\";Type=application/dicom")
+                .unwrap(),
+            "a;b c"
+        );
+        assert!(matches!(
+            multipart_boundary("application/dicom"),
+            Err(StowError::UnsupportedMediaType(_))
+        ));
+        assert!(matches!(
+            multipart_boundary("multipart/related; type=\"application/dicom+xml\"; boundary=x"),
+            Err(StowError::UnsupportedMediaType(_))
+        ));
+        assert!(matches!(
+            multipart_boundary("multipart/related; type=\"application/dicom\""),
+            Err(StowError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_multipart_with_crlf_and_lf() {
+        let payloads: [&[u8]; 3] = [b"first\r\nline", b"", b"third\n"];
+        for line_break in [&b"\r\n"[..], &b"\n"[..]] {
+            let body = multipart("xyz", &payloads, line_break);
+            let parts = parse_multipart(&body, "xyz").unwrap();
+            let bodies: Vec<&[u8]> = parts.iter().map(|p| p.body.as_slice()).collect();
+            assert_eq!(bodies, payloads.to_vec());
+            assert_eq!(parts[0].content_type.as_deref(), Some("application/dicom"));
+        }
+
+        assert!(parse_multipart(b"--xyz\r\n\r\nunterminated", "xyz").is_err());
+        assert!(parse_multipart(b"no boundary here", "xyz").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store_two_part_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(dir.path()).unwrap();
+        let first = instance("1.2.3", "1.2.3.4.1");
+        let second = instance("1.2.3", "1.2.3.4.2");
+        let body = multipart("stow", &[&first, &second], b"\r\n");
+
+        let (status, response) = store_instances(
+            &storage,
+            "multipart/related; type=\"application/dicom\"; boundary=stow",
+            &body,
+            Some("1.2.3"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, http::StatusCode::OK);
+        // Nothing serves WADO-RS from the STOW-RS store, so no RetrieveURL is promised
+        assert!(response.get("00081190").is_none());
+        let referenced = response["00081199"]["Value"].as_array().unwrap();
+        assert_eq!(referenced.len(), 2);
+        assert_eq!(referenced[1]["00081150"]["Value"][0], CT_IMAGE);
+        assert_eq!(referenced[1]["00081155"]["Value"][0], "1.2.3.4.2");
+        assert!(referenced[1].get("00081190").is_none());
+        assert!(response.get("00081198").is_none());
+
+        let stored = std::fs::read(dir.path().join("stow/1.2.3/1.2.3.4/1.2.3.4.1.dcm")).unwrap();
+        assert_eq!(stored, first);
+    }
+
+    #[tokio::test]
+    async fn test_store_reports_failed_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(dir.path()).unwrap();
+        let other_study = instance("9.9.9", "9.9.9.1");
+        let matching = instance("1.2.3", "1.2.3.4.1");
+        let body = multipart("b", &[&other_study, b"not dicom", &matching], b"\r\n");
+
+        let (status, response) = store_instances(
+            &storage,
+            "multipart/related; type=\"application/dicom\"; boundary=b",
+            &body,
+            Some("1.2.3"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, http::StatusCode::ACCEPTED);
+        let failed = response["00081198"]["Value"].as_array().unwrap();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0]["00081155"]["Value"][0], "9.9.9.1");
+        assert_eq!(failed[0]["00081197"]["Value"][0], STUDY_UID_MISMATCH);
+        assert_eq!(failed[1]["00081197"]["Value"][0], CANNOT_UNDERSTAND);
+        assert_eq!(response["00081199"]["Value"].as_array().unwrap().len(), 1);
+        assert!(!dir.path().join("stow/9.9.9").exists());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::config::config::ConfigError;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
 use crate::models::services::services::{ServiceHandler, ServiceType};
 use crate::router::route_config::RouteConfig;
 use crate::utils::Error;
@@ -189,7 +189,7 @@ impl ServiceHandler<Value> for EchoEndpoint {
     async fn endpoint_outgoing_response(
         &self,
         envelope: ResponseEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         // Build response from ResponseEnvelope
         let status = http::StatusCode::from_u16(envelope.response_details.status)
@@ -202,19 +202,13 @@ impl ServiceHandler<Value> for EchoEndpoint {
             builder = builder.header(k.as_str(), v.as_str());
         }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_str = serde_json::to_string(&normalized)
-                .map_err(|_| Error::from("Failed to serialize Echo response JSON"))?;
-            Body::from(body_str)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize Echo response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct Echo HTTP response"))
     }
 }
@@ -0,0 +1,700 @@
+//! FHIR `ImagingStudy` search answered by a DIMSE C-FIND
+//!
+//! `GET {path_prefix}/ImagingStudy?...` is mapped onto a study-level C-FIND identifier that the
+//! pipeline's `dicom` (or `mock_dicom`) backend executes. The matches are turned into a FHIR
+//! `searchset` Bundle with the JOLT spec given in `spec_path`, e.g. the
+//! `dicom_to_imagingstudy_simple.json` spec from `examples/fhir_dicom`.
+//!
+//! Supported search parameters:
+//! - `patient`: `Patient/{id}` or a bare id, matched against PatientID
+//! - `identifier`: `urn:dicom:uid|{uid}` (or a `urn:oid:` value) matches the StudyInstanceUID,
+//!   any other token the AccessionNumber
+//! - `started`: dates with the `eq`, `ge`, `gt`, `le` and `lt` prefixes, combined into a
+//!   StudyDate range; may be repeated
+//! - `modality`: comma-separated codes, matched against ModalitiesInStudy
+//! - `_count` and `_offset`: page through the matches, which C-FIND returns all at once
+//!
+//! Other parameters are ignored. Invalid values are answered with a 400 `OperationOutcome`
+//! without querying the backend.
+
+use crate::config::config::ConfigError;
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
+use crate::models::services::services::{ServiceHandler, ServiceType};
+use crate::router::route_config::RouteConfig;
+use crate::utils::Error;
+use async_trait::async_trait;
+use axum::{body::Body, response::Response};
+use chrono::{Days, Months, NaiveDate};
+use harmony_transform::JoltTransformEngine;
+use http::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Study-level return keys requested by every search, with their VRs
+const RETURN_KEYS: [(&str, &str); 11] = [
+    ("0020000D", "UI"), // StudyInstanceUID
+    ("00100020", "LO"), // PatientID
+    ("00100010", "PN"), // PatientName
+    ("00080020", "DA"), // StudyDate
+    ("00080030", "TM"), // StudyTime
+    ("00081030", "LO"), // StudyDescription
+    ("00200010", "SH"), // StudyID
+    ("00080050", "SH"), // AccessionNumber
+    ("00080061", "CS"), // ModalitiesInStudy
+    ("00201206", "IS"), // NumberOfStudyRelatedSeries
+    ("00201208", "IS"), // NumberOfStudyRelatedInstances
+];
+
+#[derive(Debug, Deserialize)]
+pub struct FhirImagingStudyEndpoint {}
+
+impl FhirImagingStudyEndpoint {
+    /// The study-level C-FIND identifier for a FHIR search
+    pub fn study_query(params: &HashMap<String, Vec<String>>) -> Result<Value, String> {
+        let mut identifier = serde_json::Map::new();
+        for (tag, vr) in RETURN_KEYS {
+            identifier.insert(tag.to_string(), json!({ "vr": vr, "Value": [] }));
+        }
+        let mut set = |tag: &str, value: String| {
+            if let Some(entry) = identifier.get_mut(tag) {
+                entry["Value"] = json!([value]);
+            }
+        };
+
+        if let Some(patient) = single(params, "patient")? {
+            let id = patient
+                .rsplit_once("Patient/")
+                .map_or(patient, |(_, id)| id);
+            if id.is_empty() {
+                return Err("patient must reference a Patient id".to_string());
+            }
+            set("00100020", id.to_string());
+        }
+
+        if let Some(token) = single(params, "identifier")? {
+            let (system, value) = token.split_once('|').unwrap_or(("", token));
+            if value.is_empty() {
+                return Err("identifier must have a value".to_string());
+            }
+            if system == "urn:dicom:uid" || value.starts_with("urn:oid:") {
+                set("0020000D", value.trim_start_matches("urn:oid:").to_string());
+            } else {
+                set("00080050", value.to_string());
+            }
+        }
+
+        if let Some(values) = params.get("started") {
+            set("00080020", study_date_range(values)?);
+        }
+
+        if let Some(modality) = single(params, "modality")? {
+            let codes: Vec<&str> = modality
+                .split(',')
+                .map(|code| code.rsplit('|').next().unwrap_or(code).trim())
+                .filter(|code| !code.is_empty())
+                .collect();
+            if codes.is_empty() {
+                return Err("modality must list at least one code".to_string());
+            }
+            set("00080061", codes.join("\\"));
+        }
+
+        Ok(Value::Object(identifier))
+    }
+
+    /// `_count` (all matches when absent) and `_offset` (default 0) of a search
+    pub fn paging(params: &HashMap<String, Vec<String>>) -> Result<(Option<usize>, usize), String> {
+        let number = |name: &str| -> Result<Option<usize>, String> {
+            single(params, name)?
+                .map(|v| {
+                    v.parse::<usize>()
+                        .map_err(|_| format!("{} must be a non-negative integer", name))
+                })
+                .transpose()
+        };
+        Ok((number("_count")?, number("_offset")?.unwrap_or(0)))
+    }
+
+    /// JOLT spec from `spec_path`, resolved against the configured transforms path
+    fn spec_path(options: &HashMap<String, Value>) -> Option<PathBuf> {
+        let raw = options.get("spec_path").and_then(|v| v.as_str())?;
+        let transforms_path =
+            crate::globals::get_config().and_then(|c| c.resolved_transforms_path.clone());
+        Some(match transforms_path {
+            Some(base) => PathBuf::from(base).join(raw),
+            None => PathBuf::from(raw),
+        })
+    }
+
+    /// The searchset Bundle for a page of C-FIND matches
+    fn search_bundle(
+        matches: &[Value],
+        params: &HashMap<String, Vec<String>>,
+        uri: &str,
+        options: &HashMap<String, Value>,
+    ) -> Result<Value, Error> {
+        let (count, offset) = Self::paging(params).map_err(Error::from)?;
+        let start = offset.min(matches.len());
+        let end = count.map_or(matches.len(), |c| {
+            start.saturating_add(c).min(matches.len())
+        });
+        let page = &matches[start..end];
+
+        // The example spec defaults a first entry in, so an empty page skips the transform
+        let mut bundle = if page.is_empty() {
+            json!({ "resourceType": "Bundle", "type": "searchset" })
+        } else {
+            let spec_path = Self::spec_path(options)
+                .ok_or_else(|| Error::from("fhir_imagingstudy requires 'spec_path'"))?;
+            let engine = JoltTransformEngine::from_spec_path(&spec_path).map_err(|e| {
+                Error::from(format!(
+                    "Failed to load ImagingStudy spec '{}': {}",
+                    spec_path.display(),
+                    e
+                ))
+            })?;
+            let output = engine
+                .transform(json!({ "data": { "matches": page } }))
+                .map_err(|e| Error::from(format!("ImagingStudy transform failed: {}", e)))?;
+            output.get("data").cloned().unwrap_or_else(|| json!({}))
+        };
+
+        // The spec's defaults only reach the first entry; fill in what every entry needs
+        if let Some(entries) = bundle.get_mut("entry").and_then(|e| e.as_array_mut()) {
+            for (entry, found) in entries.iter_mut().zip(page) {
+                let Some(resource) = entry.get_mut("resource").and_then(|r| r.as_object_mut())
+                else {
+                    continue;
+                };
+                resource.insert("resourceType".to_string(), json!("ImagingStudy"));
+                resource
+                    .entry("status")
+                    .or_insert_with(|| json!("available"));
+                if let Some(patient_id) =
+                    found.pointer("/00100020/Value/0").and_then(|v| v.as_str())
+                {
+                    let subject = resource.entry("subject").or_insert_with(|| json!({}));
+                    subject["reference"] = json!(format!("Patient/{}", patient_id));
+                }
+            }
+        }
+
+        bundle["resourceType"] = json!("Bundle");
+        bundle["type"] = json!("searchset");
+        bundle["total"] = json!(matches.len());
+        let mut links = vec![json!({ "relation": "self", "url": uri })];
+        // `_count=0` asks for the total alone; a next link would page through nothing forever
+        if count != Some(0) && end < matches.len() {
+            links.push(json!({ "relation": "next", "url": page_url(uri, params, end) }));
+        }
+        bundle["link"] = Value::Array(links);
+        Ok(bundle)
+    }
+}
+
+/// The value of a search parameter that may be given at most once
+fn single<'a>(
+    params: &'a HashMap<String, Vec<String>>,
+    name: &str,
+) -> Result<Option<&'a str>, String> {
+    match params.get(name).map(Vec::as_slice) {
+        None | Some([]) => Ok(None),
+        Some([value]) => Ok(Some(value.as_str())),
+        Some(_) => Err(format!(
+            "search parameter '{}' may only be given once",
+            name
+        )),
+    }
+}
+
+/// First and last day covered by a FHIR date (`YYYY`, `YYYY-MM`, `YYYY-MM-DD` or a dateTime)
+fn date_bounds(value: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let date = value.split('T').next()?;
+    let parts: Vec<&str> = date.split('-').collect();
+    let year = parts.first().filter(|y| y.len() == 4)?.parse().ok()?;
+    match parts.as_slice() {
+        [_] => Some((
+            NaiveDate::from_ymd_opt(year, 1, 1)?,
+            NaiveDate::from_ymd_opt(year, 12, 31)?,
+        )),
+        [_, month] => {
+            let first = NaiveDate::from_ymd_opt(year, month.parse().ok()?, 1)?;
+            Some((first, first.checked_add_months(Months::new(1))?.pred_opt()?))
+        }
+        [_, _, _] => {
+            let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some((day, day))
+        }
+        _ => None,
+    }
+}
+
+/// DICOM StudyDate range matching every `started` value
+fn study_date_range(values: &[String]) -> Result<String, String> {
+    let (mut lower, mut upper): (Option<NaiveDate>, Option<NaiveDate>) = (None, None);
+    for value in values {
+        let (prefix, date) = match value.get(..2) {
+            Some(p) if p.chars().all(|c| c.is_ascii_alphabetic()) => (p, &value[2..]),
+            _ => ("eq", value.as_str()),
+        };
+        let (first, last) =
+            date_bounds(date).ok_or_else(|| format!("started has an invalid date: {}", value))?;
+        let (from, to) = match prefix {
+            "eq" => (Some(first), Some(last)),
+            "ge" => (Some(first), None),
+            "gt" => (last.checked_add_days(Days::new(1)), None),
+            "le" => (None, Some(last)),
+            "lt" => (None, first.checked_sub_days(Days::new(1))),
+            other => return Err(format!("started does not support the '{}' prefix", other)),
+        };
+        lower = lower.max(from);
+        upper = match (upper, to) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    let fmt = |d: Option<NaiveDate>| {
+        d.map(|d| d.format("%Y%m%d").to_string())
+            .unwrap_or_default()
+    };
+    Ok(match (lower, upper) {
+        (Some(a), Some(b)) if a == b => fmt(lower),
+        _ => format!("{}-{}", fmt(lower), fmt(upper)),
+    })
+}
+
+/// `uri` with its search parameters and `_offset` set to `offset`
+fn page_url(uri: &str, params: &HashMap<String, Vec<String>>, offset: usize) -> String {
+    let path = uri.split('?').next().unwrap_or(uri);
+    let mut names: Vec<&String> = params.keys().filter(|k| *k != "_offset").collect();
+    names.sort();
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    for name in names {
+        for value in &params[name] {
+            query.append_pair(name, value);
+        }
+    }
+    query.append_pair("_offset", &offset.to_string());
+    format!("{}?{}", path, query.finish())
+}
+
+/// An `OperationOutcome` with a single error issue
+fn operation_outcome(code: &str, diagnostics: &str) -> Value {
+    json!({
+        "resourceType": "OperationOutcome",
+        "issue": [{ "severity": "error", "code": code, "diagnostics": diagnostics }]
+    })
+}
+
+#[async_trait]
+impl ServiceType for FhirImagingStudyEndpoint {
+    fn validate(&self, options: &HashMap<String, Value>) -> Result<(), ConfigError> {
+        let invalid = |reason: &str| ConfigError::InvalidEndpoint {
+            name: "fhir_imagingstudy".to_string(),
+            reason: reason.to_string(),
+        };
+        if let Some(prefix) = options.get("path_prefix") {
+            if prefix.as_str().is_none_or(|p| p.trim().is_empty()) {
+                return Err(invalid("path_prefix must be a non-empty string"));
+            }
+        }
+        match options.get("spec_path").and_then(|v| v.as_str()) {
+            Some(path) if !path.trim().is_empty() => Ok(()),
+            _ => Err(invalid(
+                "fhir_imagingstudy requires a 'spec_path' to a DICOM to ImagingStudy JOLT spec",
+            )),
+        }
+    }
+
+    fn build_router(&self, options: &HashMap<String, Value>) -> Vec<RouteConfig> {
+        let path_prefix = options
+            .get("path_prefix")
+            .and_then(|v| v.as_str())
+            .unwrap_or("/fhir");
+
+        vec![RouteConfig {
+            path: format!("{}/ImagingStudy", path_prefix.trim_end_matches('/')),
+            methods: vec![Method::GET],
+            description: Some("Searches ImagingStudy resources via DIMSE C-FIND".to_string()),
+        }]
+    }
+
+    async fn build_protocol_envelope(
+        &self,
+        ctx: crate::models::protocol::ProtocolCtx,
+        options: &HashMap<String, Value>,
+    ) -> Result<RequestEnvelope<Vec<u8>>, Error> {
+        // Delegate to HttpEndpoint for HTTP variant
+        let http = crate::models::services::types::http::HttpEndpoint {};
+        http.build_protocol_envelope(ctx, options).await
+    }
+}
+
+#[async_trait]
+impl ServiceHandler<Value> for FhirImagingStudyEndpoint {
+    type ReqBody = Value;
+
+    async fn endpoint_incoming_request(
+        &self,
+        mut envelope: RequestEnvelope<Vec<u8>>,
+        _options: &HashMap<String, Value>,
+    ) -> Result<RequestEnvelope<Vec<u8>>, Error> {
+        let params = &envelope.request_details.query_params;
+        let query = Self::paging(params).and_then(|_| Self::study_query(params));
+        let metadata = &mut envelope.request_details.metadata;
+        match query {
+            Ok(identifier) => {
+                metadata.insert("dimse_op".to_string(), "find".to_string());
+                envelope.normalized_data = Some(json!({ "dimse_identifier": identifier }));
+            }
+            Err(reason) => {
+                tracing::debug!("Rejected ImagingStudy search: {}", reason);
+                metadata.insert("skip_backends".to_string(), "true".to_string());
+                envelope.normalized_data = Some(json!({
+                    "fhir_response": {
+                        "status": 400,
+                        "resource": operation_outcome("invalid", &reason),
+                    }
+                }));
+            }
+        }
+        Ok(envelope)
+    }
+
+    async fn backend_outgoing_request(
+        &self,
+        _envelope: RequestEnvelope<Vec<u8>>,
+        _options: &HashMap<String, Value>,
+    ) -> Result<ResponseEnvelope<Vec<u8>>, Error> {
+        Err(Error::from(
+            "fhir_imagingstudy is an endpoint service and cannot be used as a backend",
+        ))
+    }
+
+    async fn endpoint_outgoing_protocol(
+        &self,
+        envelope: &mut ResponseEnvelope<Vec<u8>>,
+        _ctx: &crate::models::protocol::ProtocolCtx,
+        options: &HashMap<String, Value>,
+    ) -> Result<(), Error> {
+        let nd = envelope.normalized_data.take().unwrap_or(Value::Null);
+        let (status, resource) = if let Some(response) = nd.get("fhir_response") {
+            let status = response
+                .get("status")
+                .and_then(|s| s.as_u64())
+                .unwrap_or(200);
+            (status as u16, response["resource"].clone())
+        } else if let (Some(true), Some(matches)) = (
+            nd.get("success").and_then(|v| v.as_bool()),
+            nd.get("matches").and_then(|v| v.as_array()),
+        ) {
+            let bundle = Self::search_bundle(
+                matches,
+                &envelope.request_details.query_params,
+                &envelope.request_details.uri,
+                options,
+            )?;
+            (200, bundle)
+        } else {
+            let reason = nd
+                .get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("C-FIND did not return any results");
+            tracing::warn!("ImagingStudy search failed: {}", reason);
+            (502, operation_outcome("exception", reason))
+        };
+
+        envelope.response_details.status = status;
+        envelope
+            .response_details
+            .headers
+            .retain(|k, _| !k.eq_ignore_ascii_case("content-type"));
+        envelope.response_details.headers.insert(
+            "content-type".to_string(),
+            "application/fhir+json".to_string(),
+        );
+        envelope.original_data = serde_json::to_vec(&resource)
+            .map_err(|e| Error::from(format!("Failed to serialize FHIR response: {}", e)))?;
+        envelope.normalized_data = Some(resource);
+        Ok(())
+    }
+
+    async fn endpoint_outgoing_response(
+        &self,
+        envelope: ResponseEnvelope<Vec<u8>>,
+        options: &HashMap<String, Value>,
+    ) -> Result<Response, Error> {
+        let status = http::StatusCode::from_u16(envelope.response_details.status)
+            .unwrap_or(http::StatusCode::OK);
+
+        let mut builder = Response::builder().status(status);
+        for (k, v) in &envelope.response_details.headers {
+            builder = builder.header(k.as_str(), v.as_str());
+        }
+
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize FHIR response JSON"))?;
+
+        builder
+            .body(Body::from(body))
+            .map_err(|_| Error::from("Failed to construct FHIR HTTP response"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+    use crate::models::protocol::{Protocol, ProtocolCtx};
+    use crate::models::services::types::mock_dicom::MockDicomEndpoint;
+
+    fn options() -> HashMap<String, Value> {
+        let spec_path = format!(
+            "{}/examples/fhir_dicom/transforms/dicom_to_imagingstudy_simple.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        HashMap::from([("spec_path".to_string(), json!(spec_path))])
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, Vec<String>> {
+        let mut params: HashMap<String, Vec<String>> = HashMap::new();
+        for (k, v) in pairs {
+            params.entry(k.to_string()).or_default().push(v.to_string());
+        }
+        params
+    }
+
+    fn http_ctx() -> ProtocolCtx {
+        ProtocolCtx {
+            protocol: Protocol::Http,
+            payload: Vec::new(),
+            meta: HashMap::new(),
+            attrs: json!({}),
+        }
+    }
+
+    /// Run a search through the endpoint and the mock DICOM backend, returning status and body
+    async fn search(pairs: &[(&str, &str)]) -> (u16, Value) {
+        let endpoint = FhirImagingStudyEndpoint {};
+        let options = options();
+        assert!(endpoint.validate(&options).is_ok());
+
+        let request = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/fhir/ImagingStudy")
+            .query_params(params(pairs))
+            .original_data(Vec::new())
+            .build()
+            .unwrap();
+        let request = endpoint
+            .endpoint_incoming_request(request, &options)
+            .await
+            .unwrap();
+        assert!(!request
+            .request_details
+            .metadata
+            .contains_key("skip_backends"));
+
+        let mut response = MockDicomEndpoint {}
+            .backend_outgoing_request(request, &HashMap::new())
+            .await
+            .unwrap();
+        endpoint
+            .endpoint_outgoing_protocol(&mut response, &http_ctx(), &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.response_details.headers["content-type"],
+            "application/fhir+json"
+        );
+        let body = serde_json::from_slice(&response.original_data).unwrap();
+        (response.response_details.status, body)
+    }
+
+    #[tokio::test]
+    async fn test_patient_search_returns_imaging_study_bundle() {
+        let (status, bundle) = search(&[("patient", "Patient/PID156695")]).await;
+
+        assert_eq!(status, 200);
+        assert_eq!(bundle["resourceType"], "Bundle");
+        assert_eq!(bundle["type"], "searchset");
+        assert_eq!(bundle["total"], 1);
+        let entries = bundle["entry"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        let study = &entries[0]["resource"];
+        assert_eq!(study["resourceType"], "ImagingStudy");
+        assert_eq!(study["id"], "1.2.826.0.1.3680043.9.7133.3280065491876470");
+        assert_eq!(study["subject"]["reference"], "Patient/PID156695");
+        assert_eq!(study["description"], "Mock CT Study");
+
+        let (status, bundle) = search(&[("patient", "OTHER")]).await;
+        assert_eq!(status, 200);
+        assert_eq!(bundle["total"], 0);
+        assert!(bundle.get("entry").is_none());
+    }
+
+    #[test]
+    fn test_search_parameters_map_to_study_query() {
+        let query = FhirImagingStudyEndpoint::study_query(&params(&[
+            ("patient", "https://fhir.example.org/Patient/PID1"),
+            ("identifier", "urn:dicom:uid|urn:oid:1.2.3"),
+            ("started", "ge2024-01"),
+            ("started", "lt2024-03-01"),
+            (
+                "modality",
+                "http://dicom.nema.org/resources/ontology/DCM|CT,MR",
+            ),
+            ("_sort", "-started"),
+        ]))
+        .unwrap();
+
+        assert_eq!(query["00100020"]["Value"], json!(["PID1"]));
+        assert_eq!(query["0020000D"]["Value"], json!(["1.2.3"]));
+        assert_eq!(query["00080020"]["Value"], json!(["20240101-20240229"]));
+        assert_eq!(query["00080061"]["Value"], json!(["CT\\MR"]));
+        assert_eq!(query["00080050"]["Value"], json!([]));
+
+        let query = FhirImagingStudyEndpoint::study_query(&params(&[
+            ("identifier", "ACC-7"),
+            ("started", "2024"),
+        ]))
+        .unwrap();
+        assert_eq!(query["00080050"]["Value"], json!(["ACC-7"]));
+        assert_eq!(query["0020000D"]["Value"], json!([]));
+        assert_eq!(query["00080020"]["Value"], json!(["20240101-20241231"]));
+
+        let query =
+            FhirImagingStudyEndpoint::study_query(&params(&[("started", "gt2024-10-15")])).unwrap();
+        assert_eq!(query["00080020"]["Value"], json!(["20241016-"]));
+
+        for bad in [
+            &[("started", "2024-13-01")][..],
+            &[("started", "sa2024")],
+            &[("patient", "a"), ("patient", "b")],
+        ] {
+            assert!(FhirImagingStudyEndpoint::study_query(&params(bad)).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_count_pages_matches() {
+        let endpoint = FhirImagingStudyEndpoint {};
+        let matches: Vec<Value> = (1..=3)
+            .map(|n| {
+                json!({
+                    "0020000D": { "vr": "UI", "Value": [format!("1.2.{}", n)] },
+                    "00100020": { "vr": "LO", "Value": ["PID1"] }
+                })
+            })
+            .collect();
+        let request = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/fhir/ImagingStudy?patient=PID1&_count=2")
+            .query_params(params(&[("patient", "PID1"), ("_count", "2")]))
+            .original_data(Vec::new())
+            .build()
+            .unwrap();
+        let mut response = ResponseEnvelope::from_backend(
+            request.request_details,
+            200,
+            HashMap::new(),
+            Vec::new(),
+            None,
+        );
+        response.normalized_data =
+            Some(json!({ "operation": "find", "success": true, "matches": matches }));
+
+        endpoint
+            .endpoint_outgoing_protocol(&mut response, &http_ctx(), &options())
+            .await
+            .unwrap();
+        let bundle = response.normalized_data.unwrap();
+
+        assert_eq!(bundle["total"], 3);
+        let ids: Vec<&Value> = bundle["entry"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| &e["resource"]["id"])
+            .collect();
+        assert_eq!(ids, [&json!("1.2.1"), &json!("1.2.2")]);
+        assert_eq!(
+            bundle["link"][1],
+            json!({
+                "relation": "next",
+                "url": "/fhir/ImagingStudy?_count=2&patient=PID1&_offset=2"
+            })
+        );
+    }
+
+    #[test]
+    fn test_zero_count_returns_total_without_next_link() {
+        let matches = vec![json!({}); 3];
+        let bundle = FhirImagingStudyEndpoint::search_bundle(
+            &matches,
+            &params(&[("_count", "0")]),
+            "/fhir/ImagingStudy?_count=0",
+            &HashMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(bundle["total"], 3);
+        assert!(bundle.get("entry").is_none());
+        assert_eq!(
+            bundle["link"],
+            json!([{ "relation": "self", "url": "/fhir/ImagingStudy?_count=0" }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_search_returns_operation_outcome() {
+        let endpoint = FhirImagingStudyEndpoint {};
+        let request = RequestEnvelopeBuilder::new()
+            .method("GET")
+            .uri("/fhir/ImagingStudy?_count=ten")
+            .query_params(params(&[("_count", "ten")]))
+            .original_data(Vec::new())
+            .build()
+            .unwrap();
+        let request = endpoint
+            .endpoint_incoming_request(request, &options())
+            .await
+            .unwrap();
+        assert_eq!(
+            request.request_details.metadata.get("skip_backends"),
+            Some(&"true".to_string())
+        );
+
+        let mut response = ResponseEnvelope::from_backend(
+            request.request_details,
+            200,
+            HashMap::new(),
+            Vec::new(),
+            None,
+        );
+        response.normalized_data = request.normalized_data;
+        endpoint
+            .endpoint_outgoing_protocol(&mut response, &http_ctx(), &options())
+            .await
+            .unwrap();
+        let resp = endpoint
+            .endpoint_outgoing_response(response, &options())
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let outcome: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(outcome["resourceType"], "OperationOutcome");
+        assert!(outcome["issue"][0]["diagnostics"]
+            .as_str()
+            .unwrap()
+            .contains("_count"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::config::config::ConfigError;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
 use crate::models::services::services::{ServiceHandler, ServiceType};
 use crate::router::route_config::RouteConfig;
 use crate::utils::Error;
@@ -352,7 +352,7 @@ impl ServiceHandler<Value> for HttpEndpoint {
     async fn endpoint_outgoing_response(
         &self,
         envelope: ResponseEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         // Build response from ResponseEnvelope
         let status = http::StatusCode::from_u16(envelope.response_details.status)
@@ -365,19 +365,64 @@ impl ServiceHandler<Value> for HttpEndpoint {
             builder = builder.header(k.as_str(), v.as_str());
         }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_bytes = serde_json::to_vec(&normalized)
-                .map_err(|_| Error::from("Failed to serialize HTTP response JSON"))?;
-            Body::from(body_bytes)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize HTTP response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct HTTP response"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::envelope::envelope::RequestDetails;
+    use axum::body::to_bytes;
+
+    /// Backend bytes as received, with normalized_data since rewritten by a middleware
+    fn response_with_updated_normalized() -> ResponseEnvelope<Vec<u8>> {
+        let request_details = RequestDetails {
+            method: "GET".to_string(),
+            uri: "/api/patient".to_string(),
+            headers: HashMap::new(),
+            cookies: HashMap::new(),
+            query_params: HashMap::new(),
+            cache_status: None,
+            metadata: HashMap::new(),
+        };
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "application/json".to_string());
+        let mut envelope = ResponseEnvelope::from_backend(
+            request_details,
+            200,
+            headers,
+            br#"{"name":"Doe^John"}"#.to_vec(),
+            None,
+        );
+        envelope.normalized_data = Some(serde_json::json!({ "name": "REDACTED" }));
+        envelope
+    }
+
+    #[tokio::test]
+    async fn test_outgoing_response_reflects_updated_normalized_data() {
+        let response = HttpEndpoint {}
+            .endpoint_outgoing_response(response_with_updated_normalized(), &HashMap::new())
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "name": "REDACTED" }));
+
+        let mut options = HashMap::new();
+        options.insert("body_precedence".to_string(), serde_json::json!("original"));
+        let response = HttpEndpoint {}
+            .endpoint_outgoing_response(response_with_updated_normalized(), &options)
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], br#"{"name":"Doe^John"}"#);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::config::config::ConfigError;
 use crate::file;
 use crate::globals::get_storage;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
+use crate::models::middleware::types::jmix_index::get_jmix_index;
 use crate::models::services::services::{ServiceHandler, ServiceType};
+use crate::models::services::types::management::authorize::require_bearer_token;
 use crate::router::route_config::RouteConfig;
 use crate::utils::Error;
 use async_trait::async_trait;
@@ -92,6 +94,18 @@ impl ServiceType for JmixEndpoint {
                 methods: vec![Method::POST],
                 description: Some("JMIX upload envelope".to_string()),
             },
+            // 3a. POST rebuild an existing package from the upstream study
+            RouteConfig {
+                path: format!("{}/admin/jmix/{{id}}/rebuild", base),
+                methods: vec![Method::POST],
+                description: Some("JMIX rebuild envelope by id".to_string()),
+            },
+            // 3b. POST rebuild by query (studyInstanceUid)
+            RouteConfig {
+                path: format!("{}/admin/jmix/rebuild", base),
+                methods: vec![Method::POST],
+                description: Some("JMIX rebuild envelope by study".to_string()),
+            },
         ];
 
         // Optionally allow OPTIONS preflight and HEAD method automatically on GET routes
@@ -131,6 +145,122 @@ impl ServiceType for JmixEndpoint {
     }
 }
 
+/// Resolve the JMIX store root from endpoint options or the global storage backend
+fn resolve_store_root(options: &HashMap<String, Value>) -> PathBuf {
+    if let Some(p) = options.get("store_dir").and_then(|v| v.as_str()) {
+        PathBuf::from(p)
+    } else if let Some(storage) = get_storage() {
+        storage.subpath_str("jmix-store")
+    } else {
+        PathBuf::from("./tmp/jmix-store")
+    }
+}
+
+/// Answer a rebuild request with `status` without contacting the backend
+fn reject_rebuild(envelope: &mut RequestEnvelope<Vec<u8>>, status: u16, message: &str) {
+    let metadata = &mut envelope.request_details.metadata;
+    metadata.insert("jmix_rebuild".to_string(), "true".to_string());
+    metadata.insert("jmix_rebuild_status".to_string(), status.to_string());
+    metadata.insert("jmix_rebuild_message".to_string(), message.to_string());
+    metadata.insert("skip_backends".to_string(), "true".to_string());
+}
+
+/// Build a JSON response for a rebuild request
+fn rebuild_response(status: http::StatusCode, json: Value) -> Result<Response, Error> {
+    let body = serde_json::to_vec(&json)
+        .map_err(|_| Error::from("Failed to serialize JMIX rebuild response"))?;
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .map_err(|_| Error::from("Failed to construct JMIX rebuild response"))
+}
+
+impl JmixEndpoint {
+    /// Shape the result of a rebuild request: the new package id on success,
+    /// 404 when the study no longer exists upstream
+    fn rebuild_outgoing_response(
+        &self,
+        envelope: &ResponseEnvelope<Vec<u8>>,
+        options: &HashMap<String, Value>,
+    ) -> Result<Response, Error> {
+        let meta = &envelope.request_details.metadata;
+
+        if let Some(status) = meta.get("jmix_rebuild_status") {
+            let status = status
+                .parse::<u16>()
+                .ok()
+                .and_then(|s| http::StatusCode::from_u16(s).ok())
+                .unwrap_or(http::StatusCode::BAD_REQUEST);
+            let message = meta
+                .get("jmix_rebuild_message")
+                .cloned()
+                .unwrap_or_default();
+            return rebuild_response(status, serde_json::json!({ "error": message }));
+        }
+
+        let study_uid = meta.get("jmix_study_uid").cloned().unwrap_or_default();
+        let new_id = envelope
+            .response_details
+            .metadata
+            .get("jmix_zip_ready")
+            .filter(|ready| ready.as_str() == "true")
+            .and_then(|_| envelope.response_details.metadata.get("jmix_id"))
+            .cloned();
+
+        let Some(new_id) = new_id else {
+            if envelope.response_details.status >= 500 {
+                tracing::warn!("📦 JMIX rebuild for study {} failed upstream", study_uid);
+                return rebuild_response(
+                    http::StatusCode::BAD_GATEWAY,
+                    serde_json::json!({
+                        "error": "Upstream retrieval failed",
+                        "studyInstanceUid": study_uid
+                    }),
+                );
+            }
+            tracing::warn!(
+                "📦 JMIX rebuild for study {} produced no instances; study no longer exists upstream",
+                study_uid
+            );
+            return rebuild_response(
+                http::StatusCode::NOT_FOUND,
+                serde_json::json!({
+                    "error": "Study not found upstream",
+                    "studyInstanceUid": study_uid
+                }),
+            );
+        };
+
+        // Drop the stale package so lookups by study resolve to the rebuilt one
+        let previous_id = meta.get("jmix_rebuild_previous_id").cloned();
+        if let Some(prev) = previous_id.as_ref().filter(|p| **p != new_id) {
+            let store_root = resolve_store_root(options);
+            if let Ok(index) = get_jmix_index(&store_root) {
+                if let Err(e) = index.remove_package(prev, &study_uid) {
+                    tracing::warn!(
+                        "Failed to remove stale JMIX package {} from index: {}",
+                        prev,
+                        e
+                    );
+                }
+            }
+            let _ = fs::remove_dir_all(store_root.join(prev));
+        }
+
+        tracing::info!("📦 JMIX package rebuilt for study {}: {}", study_uid, new_id);
+        rebuild_response(
+            http::StatusCode::OK,
+            serde_json::json!({
+                "id": new_id,
+                "previous_id": previous_id,
+                "studyInstanceUid": study_uid,
+                "status": "rebuilt"
+            }),
+        )
+    }
+}
+
 fn find_package_root_and_manifest(
     extracted_root: &Path,
 ) -> Result<(PathBuf, serde_json::Value), Error> {
@@ -183,6 +313,80 @@ impl ServiceHandler<Value> for JmixEndpoint {
         }
 
         // Route matching
+        // POST /admin/jmix/{id}/rebuild or /admin/jmix/rebuild?studyInstanceUid=...
+        // Forces a backend C-GET and JMIX rebuild, bypassing any cached package
+        if method == "POST" && subpath.starts_with("admin/jmix/") {
+            // Same Bearer JWT check as the management admin routes
+            let auth_header = envelope
+                .request_details
+                .headers
+                .get("authorization")
+                .map(|s| s.as_str());
+            if let Err((status, message)) = require_bearer_token(auth_header) {
+                reject_rebuild(&mut envelope, status, &message);
+                return Ok(envelope);
+            }
+
+            let rest = &subpath["admin/jmix/".len()..];
+            let (previous_id, study_uid) = if rest == "rebuild" {
+                let uid = envelope
+                    .request_details
+                    .query_params
+                    .get("studyInstanceUid")
+                    .and_then(|v| v.first())
+                    .cloned();
+                (None, uid)
+            } else if let Some(id) = rest
+                .strip_suffix("/rebuild")
+                .filter(|id| !id.is_empty() && !id.contains('/'))
+            {
+                let store_root = resolve_store_root(options);
+                let info = get_jmix_index(&store_root)
+                    .and_then(|index| index.get_by_id(id))
+                    .map_err(|e| Error::from(format!("JMIX index lookup failed: {}", e)))?;
+                (Some(id.to_string()), info.map(|i| i.study_uid))
+            } else {
+                (None, None)
+            };
+
+            if let Some(id) = previous_id.as_ref() {
+                envelope
+                    .request_details
+                    .metadata
+                    .insert("jmix_rebuild_previous_id".to_string(), id.clone());
+            }
+
+            let Some(uid) = study_uid.filter(|u| !u.is_empty()) else {
+                let message = if previous_id.is_some() {
+                    "JMIX package not found"
+                } else {
+                    "missing studyInstanceUid"
+                };
+                let status = if previous_id.is_some() { 404 } else { 400 };
+                reject_rebuild(&mut envelope, status, message);
+                return Ok(envelope);
+            };
+
+            envelope
+                .request_details
+                .metadata
+                .insert("jmix_rebuild".to_string(), "true".to_string());
+            envelope
+                .request_details
+                .metadata
+                .insert("jmix_study_uid".to_string(), uid.clone());
+            envelope
+                .request_details
+                .metadata
+                .insert("dimse_op".to_string(), "get".to_string());
+            let identifier = serde_json::json!({
+                "0020000D": { "vr": "UI", "Value": [ uid ] }
+            });
+            envelope.original_data = serde_json::to_vec(&identifier)
+                .map_err(|e| Error::from(format!("identifier encode error: {}", e)))?;
+            return Ok(envelope);
+        }
+
         // GET/HEAD /api/jmix/{id}
         if (method == "GET" || method == "HEAD")
             && subpath.starts_with("api/jmix/")
@@ -581,6 +785,15 @@ impl ServiceHandler<Value> for JmixEndpoint {
         envelope: ResponseEnvelope<Vec<u8>>,
         options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
+        if envelope
+            .request_details
+            .metadata
+            .get("jmix_rebuild")
+            .is_some_and(|v| v == "true")
+        {
+            return self.rebuild_outgoing_response(&envelope, options);
+        }
+
         // Check if response has jmix metadata for special zip file handling
         // Only serve zip if BOTH jmix_id and jmix_zip_ready are set
         // Check both response and request metadata (fallback for when backends are skipped)
@@ -615,31 +828,27 @@ impl ServiceHandler<Value> for JmixEndpoint {
                 let zip_file = package_dir.join(format!("{}.zip", jmix_id));
 
                 if zip_file.exists() {
-                    match fs::read(&zip_file) {
-                        Ok(zip_bytes) => {
-                            let filename = format!("{}.zip", jmix_id);
-                            return Response::builder()
-                                .status(http::StatusCode::OK)
-                                .header("content-type", "application/zip")
-                                .header(
-                                    "content-disposition",
-                                    format!("attachment; filename=\"{}\"", filename),
-                                )
-                                .body(Body::from(zip_bytes))
-                                .map_err(|_| Error::from("Failed to construct zip response"));
-                        }
+                    let range = envelope
+                        .request_details
+                        .headers
+                        .iter()
+                        .find(|(k, _)| k.eq_ignore_ascii_case("range"))
+                        .map(|(_, v)| v.as_str());
+                    let filename = format!("{}.zip", jmix_id);
+                    return match serve_zip(&zip_file, &filename, range).await {
+                        Ok(response) => Ok(response),
                         Err(e) => {
                             tracing::error!(
                                 "❌ Failed to read zip file {}: {}",
                                 zip_file.display(),
                                 e
                             );
-                            return Response::builder()
+                            Response::builder()
                                 .status(http::StatusCode::INTERNAL_SERVER_ERROR)
                                 .body(Body::from(format!("Failed to read zip file: {}", e)))
-                                .map_err(|_| Error::from("Failed to construct error response"));
+                                .map_err(|_| Error::from("Failed to construct error response"))
                         }
-                    }
+                    };
                 } else {
                     tracing::error!("⚠️ Zip file not found: {}", zip_file.display());
                     return Response::builder()
@@ -661,19 +870,139 @@ impl ServiceHandler<Value> for JmixEndpoint {
             builder = builder.header(k.as_str(), v.as_str());
         }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_bytes = serde_json::to_vec(&normalized)
-                .map_err(|_| Error::from("Failed to serialize JMIX response JSON"))?;
-            Body::from(body_bytes)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize JMIX response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct JMIX HTTP response"))
     }
 }
+
+/// Stream a package zip from disk, honouring a single `Range: bytes=...` request
+///
+/// Satisfiable ranges get 206 Partial Content so interrupted downloads can resume;
+/// multi-range or malformed headers fall back to the whole file, as RFC 9110 allows.
+async fn serve_zip(path: &Path, filename: &str, range: Option<&str>) -> std::io::Result<Response> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let total = file.metadata().await?.len();
+
+    let builder = Response::builder()
+        .header("content-type", "application/zip")
+        .header(
+            "content-disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .header("accept-ranges", "bytes");
+
+    let response = match range.map(|r| parse_byte_range(r, total)) {
+        Some(ByteRange::Unsatisfiable) => builder
+            .status(http::StatusCode::RANGE_NOT_SATISFIABLE)
+            .header("content-range", format!("bytes */{}", total))
+            .body(Body::empty()),
+        Some(ByteRange::Satisfiable(start, end)) => {
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+            let len = end - start + 1;
+            let stream = tokio_util::io::ReaderStream::new(file.take(len));
+            builder
+                .status(http::StatusCode::PARTIAL_CONTENT)
+                .header(
+                    "content-range",
+                    format!("bytes {}-{}/{}", start, end, total),
+                )
+                .header("content-length", len)
+                .body(Body::from_stream(stream))
+        }
+        Some(ByteRange::Ignored) | None => builder
+            .status(http::StatusCode::OK)
+            .header("content-length", total)
+            .body(Body::from_stream(tokio_util::io::ReaderStream::new(file))),
+    };
+    response.map_err(std::io::Error::other)
+}
+
+/// Outcome of interpreting a `Range` header against a file of known size
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// Inclusive start and end offsets
+    Satisfiable(u64, u64),
+    Unsatisfiable,
+    /// Not a single byte range we understand; serve the full file
+    Ignored,
+}
+
+fn parse_byte_range(header: &str, total: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return ByteRange::Ignored;
+    };
+    if spec.contains(',') {
+        return ByteRange::Ignored;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Ignored;
+    };
+
+    match (start.trim(), end.trim()) {
+        // Suffix range: the last N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(n) if total > 0 => ByteRange::Satisfiable(total.saturating_sub(n), total - 1),
+            Ok(_) => ByteRange::Unsatisfiable,
+            Err(_) => ByteRange::Ignored,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return ByteRange::Ignored;
+            };
+            let end = if end.is_empty() {
+                total.saturating_sub(1)
+            } else {
+                match end.parse::<u64>() {
+                    Ok(e) if e >= start => e.min(total.saturating_sub(1)),
+                    _ => return ByteRange::Ignored,
+                }
+            };
+            if start >= total {
+                ByteRange::Unsatisfiable
+            } else {
+                ByteRange::Satisfiable(start, end)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_range() {
+        assert_eq!(
+            parse_byte_range("bytes=0-99", 1000),
+            ByteRange::Satisfiable(0, 99)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=900-", 1000),
+            ByteRange::Satisfiable(900, 999)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=-100", 1000),
+            ByteRange::Satisfiable(900, 999)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=990-2000", 1000),
+            ByteRange::Satisfiable(990, 999)
+        );
+        assert_eq!(
+            parse_byte_range("bytes=1000-", 1000),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_byte_range("bytes=0-1,5-9", 1000), ByteRange::Ignored);
+        assert_eq!(parse_byte_range("items=0-1", 1000), ByteRange::Ignored);
+        assert_eq!(parse_byte_range("bytes=9-1", 1000), ByteRange::Ignored);
+    }
+}
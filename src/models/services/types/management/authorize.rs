@@ -1,8 +1,14 @@
-use crate::runbeam_api::{jwt, token_storage, RunbeamClient};
 use crate::runbeam_api::token_storage::MachineToken;
+use crate::runbeam_api::types::AuthorizeResponse as CloudAuthorizeResponse;
+use crate::runbeam_api::{
+    authorization_cache, jwt, token_storage, ApiError, AuthorizationKey, RetryPolicy,
+    RunbeamClient, RunbeamError,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
 
 /// Request body for gateway authorization
 #[derive(Debug, Deserialize)]
@@ -18,7 +24,7 @@ pub struct AuthorizeRequest {
 }
 
 /// Response for successful authorization
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AuthorizeResponse {
     /// Success status
     pub success: bool,
@@ -26,48 +32,175 @@ pub struct AuthorizeResponse {
     pub message: String,
     /// Gateway details
     pub gateway: GatewayDetails,
+    /// Abilities granted to the issued machine token
+    #[serde(default)]
+    pub abilities: Vec<String>,
     /// When the machine token expires
     pub expires_at: String,
     /// Seconds until expiry
     pub expires_in: i64,
 }
 
-#[derive(Debug, Serialize)]
+/// Gateway identity
+///
+/// Serialized as `id`/`code`; the `gateway_id`/`gateway_code` names of the request body and
+/// the stored machine token are accepted when reading it back.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GatewayDetails {
+    #[serde(alias = "gateway_id")]
     pub id: String,
+    #[serde(alias = "gateway_code")]
     pub code: String,
     pub name: String,
 }
 
 /// Error response for authorization failures
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
 }
 
+/// Ways the authorize flow can fail, each tied to an HTTP status
+#[derive(Debug)]
+pub enum AuthorizeError {
+    /// No Authorization header was sent
+    MissingAuthorization,
+    /// The Authorization header is not `Bearer <jwt>`
+    MalformedAuthorization(String),
+    /// The body is not a valid authorization request
+    InvalidRequest(String),
+    /// The user token failed validation
+    InvalidToken(String),
+    /// Runbeam Cloud rejected the request or could not be reached
+    Upstream { status: u16, message: String },
+    /// The issued machine token could not be stored
+    Storage(String),
+}
+
+impl AuthorizeError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            AuthorizeError::MissingAuthorization => 401,
+            AuthorizeError::MalformedAuthorization(_) => 400,
+            AuthorizeError::InvalidRequest(_) => 400,
+            AuthorizeError::InvalidToken(_) => 401,
+            AuthorizeError::Upstream { status, .. } => *status,
+            AuthorizeError::Storage(_) => 500,
+        }
+    }
+
+    pub fn to_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            error: http::StatusCode::from_u16(self.status_code())
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("Error")
+                .to_string(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AuthorizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthorizeError::MissingAuthorization => write!(f, "Missing Authorization header"),
+            AuthorizeError::MalformedAuthorization(msg) => {
+                write!(f, "Invalid Authorization header: {}", msg)
+            }
+            AuthorizeError::InvalidRequest(msg) => write!(f, "Invalid request body: {}", msg),
+            AuthorizeError::InvalidToken(msg) => write!(f, "Invalid or expired token: {}", msg),
+            AuthorizeError::Upstream { message, .. } => {
+                write!(f, "Authorization failed: {}", message)
+            }
+            AuthorizeError::Storage(msg) => write!(f, "Failed to save token: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AuthorizeError {}
+
+impl From<RunbeamError> for AuthorizeError {
+    fn from(e: RunbeamError) -> Self {
+        let status = match &e {
+            RunbeamError::JwtValidation(_) => 401,
+            RunbeamError::Api(ApiError::Http { status, .. }) => *status,
+            _ => 500,
+        };
+        AuthorizeError::Upstream {
+            status,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Extract the user token, rejecting anything that is not `Bearer` followed by a
+/// compact JWT (three non-empty base64url segments)
+fn parse_authorization_header(header: &str) -> Result<&str, AuthorizeError> {
+    let token = header
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .ok_or_else(|| {
+            AuthorizeError::MalformedAuthorization("expected 'Bearer <token>'".to_string())
+        })?;
+    let segments: Vec<&str> = token.split('.').collect();
+    let well_formed = segments.len() == 3
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+    if !well_formed {
+        return Err(AuthorizeError::MalformedAuthorization(
+            "token is not a JWT".to_string(),
+        ));
+    }
+    Ok(token)
+}
+
+impl AuthorizeResponse {
+    fn from_cloud(cloud: CloudAuthorizeResponse, message: &str, expires_in: i64) -> Self {
+        AuthorizeResponse {
+            success: true,
+            message: message.to_string(),
+            gateway: GatewayDetails {
+                id: cloud.gateway.id,
+                code: cloud.gateway.code,
+                name: cloud.gateway.name,
+            },
+            abilities: cloud.abilities,
+            expires_at: cloud.expires_at,
+            expires_in,
+        }
+    }
+}
+
 /// Handle gateway authorization request
 ///
 /// This endpoint:
-/// 1. Extracts and validates the JWT token from Authorization header
-/// 2. Calls Runbeam Cloud API to exchange user token for machine token
-/// 3. Stores the machine token locally
-/// 4. Returns success response with gateway details
+/// 1. Checks the Authorization header format and validates the JWT
+/// 2. Reuses a recent authorization for the same gateway and user if one is cached
+/// 3. Otherwise calls Runbeam Cloud API (retrying transient failures) to exchange
+///    the user token for a machine token
+/// 4. Stores the machine token locally
+/// 5. Returns success response with gateway details
 pub async fn handle_authorize(
     auth_header: Option<&str>,
     body: &[u8],
-) -> Result<serde_json::Value, (u16, String)> {
+) -> Result<AuthorizeResponse, AuthorizeError> {
     tracing::info!("Processing gateway authorization request");
 
     // Extract JWT token from Authorization header
     let auth_header = auth_header.ok_or_else(|| {
         tracing::warn!("Missing Authorization header");
-        (401, "Missing Authorization header".to_string())
+        AuthorizeError::MissingAuthorization
     })?;
 
-    let user_token = jwt::extract_bearer_token(auth_header).map_err(|e| {
-        tracing::error!("Failed to extract Bearer token: {}", e);
-        (401, format!("Invalid Authorization header: {}", e))
+    let user_token = parse_authorization_header(auth_header).map_err(|e| {
+        tracing::warn!("Rejected Authorization header: {}", e);
+        e
     })?;
 
     tracing::debug!("Extracted JWT token from Authorization header");
@@ -75,8 +208,13 @@ pub async fn handle_authorize(
     // Parse request body
     let request: AuthorizeRequest = serde_json::from_slice(body).map_err(|e| {
         tracing::error!("Failed to parse request body: {}", e);
-        (400, format!("Invalid request body: {}", e))
+        AuthorizeError::InvalidRequest(e.to_string())
     })?;
+    if request.gateway_code.trim().is_empty() {
+        return Err(AuthorizeError::InvalidRequest(
+            "gateway_code must not be empty".to_string(),
+        ));
+    }
 
     tracing::info!("Authorizing gateway: {}", request.gateway_code);
 
@@ -85,15 +223,47 @@ pub async fn handle_authorize(
     let jwt_secret = get_jwt_secret();
     let claims = jwt::validate_jwt_token(user_token, jwt_secret.as_bytes()).map_err(|e| {
         tracing::error!("JWT validation failed: {}", e);
-        (401, format!("Invalid or expired token: {}", e))
+        AuthorizeError::InvalidToken(e.to_string())
     })?;
 
     // Extract Runbeam API base URL from JWT issuer claim
     let api_base_url = claims.api_base_url();
     tracing::debug!("Runbeam API base URL: {}", api_base_url);
 
+    let settings = crate::globals::get_config()
+        .map(|c| c.management.authorize.clone())
+        .unwrap_or_default();
+    let cache_ttl = Duration::from_secs(settings.cache_ttl_secs);
+    let cache_key = AuthorizationKey {
+        api_base_url: api_base_url.clone(),
+        gateway_code: request.gateway_code.clone(),
+        subject: claims.sub.clone(),
+    };
+
+    // The machine token from a cached authorization is already in storage
+    if !cache_ttl.is_zero() {
+        if let Some(cached) = authorization_cache().get(&cache_key, cache_ttl) {
+            tracing::info!(
+                "Reusing cached authorization: gateway_id={}",
+                cached.gateway.id
+            );
+            let expires_in = chrono::DateTime::parse_from_rfc3339(&cached.expires_at)
+                .map(|t| (t.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds())
+                .unwrap_or(0)
+                .max(0);
+            return Ok(AuthorizeResponse::from_cloud(
+                cached,
+                "Gateway already authorized",
+                expires_in,
+            ));
+        }
+    }
+
     // Create Runbeam Cloud API client
-    let client = RunbeamClient::new(api_base_url);
+    let client = RunbeamClient::new(api_base_url).with_retry(RetryPolicy {
+        max_attempts: settings.retry_attempts.max(1),
+        initial_backoff: Duration::from_millis(settings.retry_backoff_ms),
+    });
 
     // Call Runbeam Cloud API to authorize gateway
     let auth_response = client
@@ -106,20 +276,7 @@ pub async fn handle_authorize(
         .await
         .map_err(|e| {
             tracing::error!("Runbeam Cloud authorization failed: {}", e);
-            
-            // Map error to appropriate HTTP status code
-            let status_code = match &e {
-                crate::runbeam_api::types::RunbeamError::JwtValidation(_) => 401,
-                crate::runbeam_api::types::RunbeamError::Api(api_err) => {
-                    match api_err {
-                        crate::runbeam_api::types::ApiError::Http { status, .. } => *status,
-                        _ => 500,
-                    }
-                }
-                _ => 500,
-            };
-            
-            (status_code, format!("Authorization failed: {}", e))
+            AuthorizeError::from(e)
         })?;
 
     tracing::info!(
@@ -139,35 +296,41 @@ pub async fn handle_authorize(
     // Save machine token to storage
     let storage = crate::globals::get_storage().ok_or_else(|| {
         tracing::error!("Storage backend not initialized");
-        (500, "Internal server error: storage not available".to_string())
+        AuthorizeError::Storage("storage not available".to_string())
     })?;
 
     token_storage::save_token(storage.as_ref(), &machine_token)
         .await
         .map_err(|e| {
             tracing::error!("Failed to save machine token: {}", e);
-            (500, format!("Failed to save token: {}", e))
+            AuthorizeError::Storage(e.to_string())
         })?;
 
     tracing::info!("Machine token saved successfully");
 
-    // Build success response
-    let response = AuthorizeResponse {
-        success: true,
-        message: "Gateway authorized successfully".to_string(),
-        gateway: GatewayDetails {
-            id: auth_response.gateway.id,
-            code: auth_response.gateway.code,
-            name: auth_response.gateway.name,
-        },
-        expires_at: auth_response.expires_at,
-        expires_in: auth_response.expires_in as i64,
-    };
+    if !cache_ttl.is_zero() {
+        authorization_cache().insert(cache_key, auth_response.clone());
+    }
 
-    serde_json::to_value(&response).map_err(|e| {
-        tracing::error!("Failed to serialize response: {}", e);
-        (500, "Internal server error".to_string())
-    })
+    let expires_in = auth_response.expires_in as i64;
+    Ok(AuthorizeResponse::from_cloud(
+        auth_response,
+        "Gateway authorized successfully",
+        expires_in,
+    ))
+}
+
+/// Check the Bearer JWT that guards operator routes such as `/dimse/move`
+///
+/// Tokens are validated with the same secret as `/authorize`. Errors carry the HTTP status
+/// (always 401) and a message for the response body.
+pub(crate) fn require_bearer_token(auth_header: Option<&str>) -> Result<(), (u16, String)> {
+    let auth_header = auth_header.ok_or((401, "Missing Authorization header".to_string()))?;
+    let token = jwt::extract_bearer_token(auth_header)
+        .map_err(|e| (401, format!("Invalid Authorization header: {}", e)))?;
+    jwt::validate_jwt_token(token, get_jwt_secret().as_bytes())
+        .map_err(|e| (401, format!("Invalid or expired token: {}", e)))?;
+    Ok(())
 }
 
 /// Get the JWT secret for validation
@@ -179,7 +342,7 @@ pub async fn handle_authorize(
 /// 1. Read from config file ([runbeam] section)
 /// 2. Or from environment variable
 /// 3. Must match the secret used by Runbeam Cloud to sign JWTs
-fn get_jwt_secret() -> String {
+pub(crate) fn get_jwt_secret() -> String {
     // Check environment variable first
     if let Ok(secret) = std::env::var("RUNBEAM_JWT_SECRET") {
         tracing::debug!("Using JWT secret from RUNBEAM_JWT_SECRET environment variable");
@@ -235,6 +398,7 @@ mod tests {
                 code: "test-gateway".to_string(),
                 name: "Test Gateway".to_string(),
             },
+            abilities: vec![],
             expires_at: "2025-12-31T23:59:59Z".to_string(),
             expires_in: 2592000,
         };
@@ -255,4 +419,200 @@ mod tests {
         assert!(json.contains("\"error\":\"Unauthorized\""));
         assert!(json.contains("\"message\":\"Invalid or expired token\""));
     }
+
+    async fn call_authorize_endpoint(auth_header: &str, body: JsonValue) -> (u16, JsonValue) {
+        use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+        use crate::models::services::services::ServiceHandler;
+        use crate::models::services::types::management::ManagementEndpoint;
+
+        let envelope = RequestEnvelopeBuilder::new()
+            .method("POST")
+            .uri("/admin/authorize")
+            .header("authorization", auth_header)
+            .metadata_entry("path", "/admin/authorize")
+            .original_data(serde_json::to_vec(&body).unwrap())
+            .build()
+            .unwrap();
+        let response = ManagementEndpoint {}
+            .backend_outgoing_request(envelope, &HashMap::new())
+            .await
+            .unwrap();
+        (
+            response.response_details.status,
+            response.normalized_data.unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_authorize_rejects_malformed_auth_header() {
+        let body = serde_json::json!({"gateway_code": "test-gateway"});
+
+        for header in ["Token abc", "Bearer not-a-jwt", "Bearer a..c"] {
+            let (status, value) = call_authorize_endpoint(header, body.clone()).await;
+            assert_eq!(status, 400, "header {:?}", header);
+            let error: ErrorResponse = serde_json::from_value(value).unwrap();
+            assert_eq!(error.error, "Bad Request");
+            assert!(error.message.starts_with("Invalid Authorization header"));
+        }
+    }
+
+    /// Serve `app` as a stand-in for Runbeam Cloud
+    async fn spawn_cloud(app: axum::Router) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), server)
+    }
+
+    /// A user JWT issued by the cloud at `issuer`
+    fn user_token(issuer: &str) -> String {
+        use jsonwebtoken::{encode, EncodingKey, Header};
+
+        let now = chrono::Utc::now().timestamp();
+        encode(
+            &Header::default(),
+            &serde_json::json!({
+                "iss": issuer,
+                "sub": "user-1",
+                "iat": now,
+                "exp": now + 3600
+            }),
+            &EncodingKey::from_secret(get_jwt_secret().as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn cloud_success() -> JsonValue {
+        serde_json::json!({
+            "machine_token": "mt_issued",
+            "expires_in": 2592000.0,
+            "expires_at": "2030-01-01T00:00:00Z",
+            "gateway": {"id": "gw-1", "code": "test-gateway", "name": "Test Gateway"},
+            "abilities": ["harmony:send"]
+        })
+    }
+
+    fn use_temp_storage() -> tempfile::TempDir {
+        use crate::storage::filesystem::FilesystemStorage;
+
+        let storage_dir = tempfile::tempdir().unwrap();
+        crate::globals::set_storage(std::sync::Arc::new(
+            FilesystemStorage::new(storage_dir.path().to_str().unwrap()).unwrap(),
+        ));
+        storage_dir
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_authorize_success_returns_typed_response() {
+        use axum::{routing::post, Json, Router};
+
+        let app = Router::new().route(
+            "/api/harmony/authorize",
+            post(|| async { Json(cloud_success()) }),
+        );
+        let (cloud_url, server) = spawn_cloud(app).await;
+        let _storage = use_temp_storage();
+
+        let (status, value) = call_authorize_endpoint(
+            &format!("Bearer {}", user_token(&cloud_url)),
+            serde_json::json!({"gateway_code": "test-gateway"}),
+        )
+        .await;
+        server.abort();
+
+        assert_eq!(status, 201);
+        let response: AuthorizeResponse = serde_json::from_value(value.clone()).unwrap();
+        assert!(response.success);
+        assert_eq!(response.gateway.id, "gw-1");
+        assert_eq!(response.gateway.code, "test-gateway");
+        assert_eq!(response.abilities, vec!["harmony:send".to_string()]);
+        assert_eq!(response.expires_at, "2030-01-01T00:00:00Z");
+        assert_eq!(response.expires_in, 2592000);
+        assert_eq!(value["gateway"]["id"], "gw-1");
+        assert_eq!(value["gateway"]["code"], "test-gateway");
+
+        let stored = serde_json::json!({"gateway_id": "gw-1", "gateway_code": "gw", "name": "n"});
+        let details: GatewayDetails = serde_json::from_value(stored).unwrap();
+        assert_eq!((details.id.as_str(), details.code.as_str()), ("gw-1", "gw"));
+        assert!(value.get("machine_token").is_none());
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_authorize_retries_transient_failure_and_caches_result() {
+        use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        // The first call hits a cloud blip; later calls succeed
+        let app = Router::new().route(
+            "/api/harmony/authorize",
+            post(move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                        StatusCode::SERVICE_UNAVAILABLE.into_response()
+                    } else {
+                        Json(cloud_success()).into_response()
+                    }
+                }
+            }),
+        );
+        let (cloud_url, server) = spawn_cloud(app).await;
+        let _storage = use_temp_storage();
+        authorization_cache().clear();
+        let header = format!("Bearer {}", user_token(&cloud_url));
+        let body = br#"{"gateway_code": "test-gateway"}"#;
+
+        let first = handle_authorize(Some(&header), body).await.unwrap();
+        assert_eq!(first.gateway.id, "gw-1");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let second = handle_authorize(Some(&header), body).await.unwrap();
+        assert_eq!(second.gateway.id, "gw-1");
+        assert_eq!(
+            hits.load(Ordering::SeqCst),
+            2,
+            "cached result should be reused"
+        );
+        server.abort();
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_authorize_does_not_retry_client_errors() {
+        use axum::{http::StatusCode, routing::post, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/api/harmony/authorize",
+            post(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async {
+                    (
+                        StatusCode::FORBIDDEN,
+                        "This gateway belongs to a different team",
+                    )
+                }
+            }),
+        );
+        let (cloud_url, server) = spawn_cloud(app).await;
+        let header = format!("Bearer {}", user_token(&cloud_url));
+
+        let err = handle_authorize(Some(&header), br#"{"gateway_code": "other-gateway"}"#)
+            .await
+            .unwrap_err();
+        server.abort();
+
+        assert_eq!(err.status_code(), 403);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
 }
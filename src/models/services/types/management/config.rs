@@ -8,6 +8,44 @@ pub struct ManagementConfig {
     #[serde(default = "default_admin_base_path")]
     pub base_path: String,
     pub network: Option<String>,
+    /// Retry and caching for Runbeam Cloud authorization
+    #[serde(default)]
+    pub authorize: AuthorizeSettings,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthorizeSettings {
+    /// Total attempts against Runbeam Cloud, including the first
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    /// Delay before the first retry; doubled for each retry after
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// How long a successful authorization is reused; 0 disables caching
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    200
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    300
+}
+
+impl Default for AuthorizeSettings {
+    fn default() -> Self {
+        Self {
+            retry_attempts: default_retry_attempts(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            cache_ttl_secs: default_cache_ttl_secs(),
+        }
+    }
 }
 
 pub fn default_admin_base_path() -> String {
@@ -20,6 +58,7 @@ impl Default for ManagementConfig {
             enabled: false,
             base_path: default_admin_base_path(),
             network: None,
+            authorize: AuthorizeSettings::default(),
         }
     }
 }
@@ -30,6 +69,9 @@ impl ManagementConfig {
         if self.base_path.trim().is_empty() {
             return Err("base_path cannot be empty".to_string());
         }
+        if self.authorize.retry_attempts == 0 {
+            return Err("authorize.retry_attempts must be at least 1".to_string());
+        }
         Ok(())
     }
 }
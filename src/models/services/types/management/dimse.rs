@@ -0,0 +1,187 @@
+use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+use crate::models::services::services::ServiceType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Request body for an operator-triggered C-MOVE
+#[derive(Debug, Deserialize)]
+pub struct MoveRequest {
+    /// Name of the DIMSE backend to send the C-MOVE to
+    pub remote: String,
+    /// StudyInstanceUID to move
+    pub study_uid: String,
+    /// Move destination AE title
+    pub destination: String,
+}
+
+/// Sub-operation summary returned after the move completes
+#[derive(Debug, Serialize)]
+pub struct MoveResponse {
+    pub success: bool,
+    pub remote: String,
+    pub study_uid: String,
+    pub destination: String,
+    /// Completed, failed and warning sub-operations from the final C-MOVE response
+    pub completed: u64,
+    pub failed: u64,
+    pub warning: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder_id: Option<String>,
+}
+
+/// Handle `POST /{base_path}/dimse/move`
+///
+/// Requires a valid Bearer JWT (same secret as `/authorize`), then resolves the named
+/// backend from the running configuration and issues the C-MOVE through its SCU.
+pub async fn handle_move(auth_header: Option<&str>, body: &[u8]) -> Result<Value, (u16, String)> {
+    super::authorize::require_bearer_token(auth_header)?;
+
+    let request: MoveRequest = serde_json::from_slice(body)
+        .map_err(|e| (400, format!("Invalid request body: {}", e)))?;
+
+    let config = crate::globals::get_config()
+        .ok_or((500, "Internal server error: configuration not available".to_string()))?;
+    let backend = config
+        .backends
+        .get(&request.remote)
+        .ok_or_else(|| (404, format!("Unknown remote '{}'", request.remote)))?;
+    if !matches!(backend.service.as_str(), "dicom" | "mock_dicom") {
+        return Err((
+            400,
+            format!("Remote '{}' is not a DIMSE backend", request.remote),
+        ));
+    }
+    let service = backend.resolve_service().map_err(|e| (500, e))?;
+    let empty = HashMap::new();
+    let options = backend.options.as_ref().unwrap_or(&empty);
+
+    run_move(service.as_ref(), options, request).await
+}
+
+/// Issue the C-MOVE through an already resolved DIMSE service and summarise the result
+pub async fn run_move(
+    service: &dyn ServiceType<ReqBody = Value>,
+    options: &HashMap<String, Value>,
+    request: MoveRequest,
+) -> Result<Value, (u16, String)> {
+    if request.study_uid.trim().is_empty() {
+        return Err((400, "study_uid must not be empty".to_string()));
+    }
+    if request.destination.trim().is_empty() || request.destination.len() > 16 {
+        return Err((
+            400,
+            "destination must be an AE title of 1-16 characters".to_string(),
+        ));
+    }
+
+    tracing::info!(
+        "Admin C-MOVE of study {} from '{}' to {}",
+        request.study_uid,
+        request.remote,
+        request.destination
+    );
+
+    let envelope = RequestEnvelopeBuilder::new()
+        .method("POST")
+        .uri("/admin/dimse/move")
+        .metadata_entry("dimse_op", "move")
+        .original_data(Vec::new())
+        .normalized_data(Some(serde_json::json!({
+            "dimse_identifier": {
+                "0020000D": { "vr": "UI", "Value": [request.study_uid] }
+            },
+            "move_destination": request.destination,
+        })))
+        .build()
+        .map_err(|e| (500, format!("Failed to build move request: {}", e)))?;
+
+    let response = service
+        .backend_outgoing_request(envelope, options)
+        .await
+        .map_err(|e| (502, format!("C-MOVE failed: {:?}", e)))?;
+
+    let nd = response.normalized_data.unwrap_or(Value::Null);
+    if nd.get("error").and_then(|v| v.as_str()) == Some("Study not found") {
+        return Err((
+            404,
+            format!("Study {} not found on '{}'", request.study_uid, request.remote),
+        ));
+    }
+    if !nd.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+        let error = nd
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown error");
+        return Err((502, format!("C-MOVE failed: {}", error)));
+    }
+
+    let count = |key: &str| nd.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let summary = MoveResponse {
+        success: true,
+        remote: request.remote,
+        study_uid: request.study_uid,
+        destination: request.destination,
+        completed: count("completed"),
+        failed: count("failed"),
+        warning: count("warning"),
+        folder_id: nd
+            .get("folder_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+    };
+    serde_json::to_value(&summary).map_err(|_| (500, "Internal server error".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::services::types::mock_dicom::{MockDicomData, MockDicomEndpoint};
+
+    fn move_request(study_uid: &str) -> MoveRequest {
+        MoveRequest {
+            remote: "mock_pacs".to_string(),
+            study_uid: study_uid.to_string(),
+            destination: "ARCHIVE".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_move_against_mock_reports_sub_operations() {
+        let study_uid = MockDicomData::instance().study_uid.clone();
+        let expected: usize = MockDicomData::instance()
+            .series
+            .iter()
+            .map(|s| s.instances.len())
+            .sum();
+
+        let result = run_move(&MockDicomEndpoint, &HashMap::new(), move_request(&study_uid))
+            .await
+            .expect("move succeeds");
+
+        assert_eq!(result["success"], true);
+        assert_eq!(result["destination"], "ARCHIVE");
+        assert_eq!(result["completed"], expected as u64);
+        assert_eq!(result["failed"], 0);
+        assert_eq!(result["warning"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_move_unknown_study_is_404() {
+        let err = run_move(&MockDicomEndpoint, &HashMap::new(), move_request("9.9.9"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0, 404);
+    }
+
+    #[tokio::test]
+    async fn test_move_requires_auth() {
+        let body = br#"{"remote":"mock_pacs","study_uid":"1.2.3","destination":"ARCHIVE"}"#;
+
+        let err = handle_move(None, body).await.unwrap_err();
+        assert_eq!(err.0, 401);
+
+        let err = handle_move(Some("Bearer not-a-jwt"), body).await.unwrap_err();
+        assert_eq!(err.0, 401);
+    }
+}
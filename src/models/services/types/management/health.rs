@@ -0,0 +1,334 @@
+use crate::models::backends::backends::Backend;
+use crate::models::envelope::envelope::RequestEnvelopeBuilder;
+use crate::storage::StorageBackend;
+use dimse::{BreakerState, RemoteNode};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a C-ECHO result is reused before the backend is probed again
+const ECHO_CACHE_TTL: Duration = Duration::from_secs(10);
+/// Upper bound on a single readiness C-ECHO
+const ECHO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Recent C-ECHO results, keyed by backend name
+static ECHO_CACHE: Lazy<Mutex<HashMap<String, (Instant, CheckResult)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Outcome of one readiness check
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    /// `"dimse"` for a backend C-ECHO, `"storage"` for the write probe
+    pub kind: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// State of the backend's circuit breaker, for `"dimse"` checks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit: Option<BreakerState>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    pub ready: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+/// Handle `GET /{base_path}/health`: the process is up and serving requests
+pub fn handle_health() -> Value {
+    serde_json::json!({ "status": "ok" })
+}
+
+/// Handle `GET /{base_path}/ready`
+///
+/// C-ECHOes every configured DICOM backend concurrently and checks the storage root is
+/// writable. Returns 200 when every check passes, otherwise 503 with the same per-check
+/// breakdown.
+pub async fn handle_ready(
+    backends: &HashMap<String, Backend>,
+    storage: Option<Arc<dyn StorageBackend>>,
+) -> (ReadyResponse, u16) {
+    let mut names: Vec<&String> = backends
+        .iter()
+        .filter(|(_, backend)| backend.service == "dicom")
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+
+    // One slow backend must not hold up the others for its whole echo timeout
+    let mut checks = futures_util::future::join_all(
+        names
+            .into_iter()
+            .map(|name| check_backend(name, &backends[name])),
+    )
+    .await;
+    checks.push(check_storage(storage).await);
+
+    let ready = checks.iter().all(|c| c.ok);
+    let status = if ready { 200 } else { 503 };
+    (ReadyResponse { ready, checks }, status)
+}
+
+/// C-ECHO a backend, reusing a result younger than [`ECHO_CACHE_TTL`]
+///
+/// A backend whose circuit breaker is open fails without an echo: every request to it would
+/// fail fast until the cooldown ends anyway.
+async fn check_backend(name: &str, backend: &Backend) -> CheckResult {
+    let circuit = remote_node(backend).map(|node| dimse::breaker::state(&node));
+    if circuit == Some(BreakerState::Open) {
+        return CheckResult {
+            name: name.to_string(),
+            kind: "dimse".to_string(),
+            ok: false,
+            error: Some("Circuit breaker open".to_string()),
+            circuit,
+        };
+    }
+    if let Some((at, result)) = ECHO_CACHE.lock().unwrap().get(name) {
+        if at.elapsed() < ECHO_CACHE_TTL {
+            return result.clone();
+        }
+    }
+
+    let error = match tokio::time::timeout(ECHO_TIMEOUT, echo(backend)).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e),
+        Err(_) => Some(format!(
+            "C-ECHO timed out after {}s",
+            ECHO_TIMEOUT.as_secs()
+        )),
+    };
+    let result = CheckResult {
+        name: name.to_string(),
+        kind: "dimse".to_string(),
+        ok: error.is_none(),
+        error,
+        circuit: remote_node(backend).map(|node| dimse::breaker::state(&node)),
+    };
+    ECHO_CACHE
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), (Instant::now(), result.clone()));
+    result
+}
+
+/// Peer the backend's breaker is keyed by, from its `aet`, `host` and `port` options
+fn remote_node(backend: &Backend) -> Option<RemoteNode> {
+    let options = backend.options.as_ref()?;
+    let aet = options.get("aet")?.as_str()?;
+    let host = options.get("host")?.as_str()?;
+    let port = u16::try_from(options.get("port")?.as_u64()?).ok()?;
+    Some(RemoteNode::new(aet, host, port))
+}
+
+/// Run a C-ECHO through the backend's own service so TLS and timeouts match real traffic
+///
+/// The probe runs without the backend's `circuit_breaker` option, so failed readiness checks
+/// do not count toward opening the breaker that guards real requests.
+async fn echo(backend: &Backend) -> Result<(), String> {
+    let service = backend.resolve_service()?;
+    let mut options = backend.options.clone().unwrap_or_default();
+    options.remove("circuit_breaker");
+
+    let envelope = RequestEnvelopeBuilder::new()
+        .method("GET")
+        .uri("/admin/ready")
+        .metadata_entry("dimse_op", "echo")
+        .original_data(Vec::new())
+        .build()
+        .map_err(|e| format!("Failed to build echo request: {}", e))?;
+
+    let response = service
+        .backend_outgoing_request(envelope, &options)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    let nd = response.normalized_data.unwrap_or(Value::Null);
+    if nd.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+        Ok(())
+    } else {
+        Err(nd
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("C-ECHO failed")
+            .to_string())
+    }
+}
+
+/// Write and remove a probe file under the storage root
+async fn check_storage(storage: Option<Arc<dyn StorageBackend>>) -> CheckResult {
+    let error = match storage {
+        None => Some("Storage is not initialised".to_string()),
+        Some(storage) => {
+            let probe = format!(".ready-{}", uuid::Uuid::new_v4());
+            match storage.write_file_str(&probe, b"").await {
+                Ok(_) => storage
+                    .remove_str(&probe)
+                    .await
+                    .err()
+                    .map(|e| e.to_string()),
+                Err(e) => Some(e.to_string()),
+            }
+        }
+    };
+    CheckResult {
+        name: "storage".to_string(),
+        kind: "storage".to_string(),
+        ok: error.is_none(),
+        error,
+        circuit: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FilesystemStorage;
+
+    /// A DICOM backend pointing at a port nothing listens on
+    fn down_backend() -> Backend {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        backend_at(port)
+    }
+
+    fn backend_at(port: u16) -> Backend {
+        serde_json::from_value(serde_json::json!({
+            "service": "dicom",
+            "options": {
+                "aet": "DOWN_PACS",
+                "host": "127.0.0.1",
+                "port": port,
+                "connect_timeout_ms": 500
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_down_backend_is_not_ready_but_healthy() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(FilesystemStorage::new(dir.path()).unwrap());
+        let mut backends = HashMap::new();
+        backends.insert("ready_test_down_pacs".to_string(), down_backend());
+
+        let (response, status) = handle_ready(&backends, Some(storage)).await;
+
+        assert_eq!(status, 503);
+        assert!(!response.ready);
+        let pacs = &response.checks[0];
+        assert_eq!(pacs.name, "ready_test_down_pacs");
+        assert!(!pacs.ok);
+        assert!(pacs.error.is_some());
+        let storage = &response.checks[1];
+        assert_eq!(storage.name, "storage");
+        assert!(storage.ok, "{:?}", storage.error);
+
+        assert_eq!(handle_health()["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_ready_with_writable_storage_and_no_backends() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage: Arc<dyn StorageBackend> =
+            Arc::new(FilesystemStorage::new(dir.path()).unwrap());
+
+        let (response, status) = handle_ready(&HashMap::new(), Some(storage)).await;
+
+        assert_eq!(status, 200);
+        assert!(response.ready);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_fails_readiness_without_echo() {
+        let backend = down_backend();
+        let node = remote_node(&backend).unwrap();
+        let config = dimse::CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown_ms: 60_000,
+        };
+        let refused = dimse::DimseError::Network(std::io::ErrorKind::ConnectionRefused.into());
+        dimse::breaker::admit(&config, &node)
+            .unwrap()
+            .record(Some(&refused));
+
+        let check = check_backend("ready_test_open_circuit", &backend).await;
+
+        assert!(!check.ok);
+        assert_eq!(check.circuit, Some(BreakerState::Open));
+        assert!(!ECHO_CACHE
+            .lock()
+            .unwrap()
+            .contains_key("ready_test_open_circuit"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_does_not_count_toward_breaker() {
+        let mut backend = down_backend();
+        backend.options.as_mut().unwrap().insert(
+            "circuit_breaker".to_string(),
+            serde_json::json!({ "failure_threshold": 1, "cooldown_ms": 60_000 }),
+        );
+
+        let check = check_backend("ready_test_breaker_untouched", &backend).await;
+
+        assert!(!check.ok);
+        assert_eq!(check.circuit, Some(BreakerState::Closed));
+        assert_eq!(
+            dimse::breaker::state(&remote_node(&backend).unwrap()),
+            BreakerState::Closed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backends_are_checked_concurrently() {
+        // Peers that accept the connection but never answer the association request
+        let listeners: Vec<_> = (0..3)
+            .map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let backends: HashMap<_, _> = listeners
+            .iter()
+            .enumerate()
+            .map(|(i, listener)| {
+                (
+                    format!("ready_test_silent_pacs_{}", i),
+                    backend_at(listener.local_addr().unwrap().port()),
+                )
+            })
+            .collect();
+
+        let started = Instant::now();
+        let (response, status) = handle_ready(&backends, None).await;
+
+        assert_eq!(status, 503);
+        assert!(response.checks[..3].iter().all(|c| !c.ok));
+        assert!(
+            started.elapsed() < ECHO_TIMEOUT + Duration::from_secs(2),
+            "checks took {:?}",
+            started.elapsed()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_echo_result_is_cached() {
+        let backend = down_backend();
+        let first = check_backend("ready_test_cached_pacs", &backend).await;
+        let cached_at = ECHO_CACHE.lock().unwrap()["ready_test_cached_pacs"].0;
+
+        let second = check_backend("ready_test_cached_pacs", &backend).await;
+
+        assert!(!first.ok && !second.ok);
+        assert_eq!(
+            ECHO_CACHE.lock().unwrap()["ready_test_cached_pacs"].0,
+            cached_at
+        );
+    }
+}
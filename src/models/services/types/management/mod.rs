@@ -1,7 +1,7 @@
 pub(crate) use self::config::ManagementConfig;
 use self::info::handle_info;
 use crate::config::config::ConfigError;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
 use crate::models::services::services::{ServiceHandler, ServiceType};
 use crate::router::route_config::RouteConfig;
 use crate::utils::Error;
@@ -14,6 +14,8 @@ use std::collections::HashMap;
 
 pub mod authorize;
 pub mod config;
+pub mod dimse;
+pub mod health;
 pub mod info;
 pub mod pipelines;
 pub mod routes;
@@ -71,6 +73,26 @@ impl ServiceType for ManagementEndpoint {
                 methods: vec![Method::POST],
                 description: Some("Authorize gateway with Runbeam Cloud".to_string()),
             },
+            RouteConfig {
+                path: format!("/{}/dimse/move", base_path),
+                methods: vec![Method::POST],
+                description: Some("Run a C-MOVE against a configured DIMSE backend".to_string()),
+            },
+            RouteConfig {
+                path: format!("/{}/metrics", base_path),
+                methods: vec![Method::GET],
+                description: Some("Prometheus metrics".to_string()),
+            },
+            RouteConfig {
+                path: format!("/{}/health", base_path),
+                methods: vec![Method::GET],
+                description: Some("Liveness probe".to_string()),
+            },
+            RouteConfig {
+                path: format!("/{}/ready", base_path),
+                methods: vec![Method::GET],
+                description: Some("Readiness probe (DICOM backends and storage)".to_string()),
+            },
         ]
     }
 
@@ -129,6 +151,24 @@ impl ServiceHandler<Value> for ManagementEndpoint {
         // Remove leading slash and match the specific endpoint
         let clean_path = path.trim_start_matches('/');
 
+        // Metrics are plain text in the Prometheus exposition format, not JSON
+        if clean_path == "metrics" || clean_path == format!("{}/metrics", base_path) {
+            if let Some(text) = crate::metrics::render() {
+                let mut headers = HashMap::new();
+                headers.insert(
+                    "content-type".to_string(),
+                    "text/plain; version=0.0.4".to_string(),
+                );
+                return Ok(ResponseEnvelope::from_backend(
+                    envelope.request_details.clone(),
+                    200,
+                    headers,
+                    text.into_bytes(),
+                    None,
+                ));
+            }
+        }
+
         let (response_value, status_code) = match clean_path {
             p if p == "info" || p == format!("{}/info", base_path) => {
                 let info = handle_info().await;
@@ -164,16 +204,54 @@ impl ServiceHandler<Value> for ManagementEndpoint {
                 // Handle gateway authorization
                 let auth_header = envelope.request_details.headers.get("authorization").map(|s| s.as_str());
                 match self::authorize::handle_authorize(auth_header, &envelope.original_data).await {
-                    Ok(value) => (value, 201),
+                    Ok(response) => {
+                        let value = serde_json::to_value(response)
+                            .map_err(|_| Error::from("Failed to serialize authorize response"))?;
+                        (value, 201)
+                    }
+                    Err(e) => {
+                        let value = serde_json::to_value(e.to_response())
+                            .map_err(|_| Error::from("Failed to serialize authorize error"))?;
+                        (value, e.status_code())
+                    }
+                }
+            }
+            p if p == "dimse/move" || p == format!("{}/dimse/move", base_path) => {
+                let auth_header = envelope
+                    .request_details
+                    .headers
+                    .get("authorization")
+                    .map(|s| s.as_str());
+                match self::dimse::handle_move(auth_header, &envelope.original_data).await {
+                    Ok(value) => (value, 200),
                     Err((status, message)) => {
                         let error_json = serde_json::json!({
-                            "error": http::StatusCode::from_u16(status).unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR).canonical_reason().unwrap_or("Error"),
+                            "error": http::StatusCode::from_u16(status)
+                                .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR)
+                                .canonical_reason()
+                                .unwrap_or("Error"),
                             "message": message
                         });
                         (error_json, status)
                     }
                 }
             }
+            p if p == "metrics" || p == format!("{}/metrics", base_path) => {
+                (serde_json::json!({"error": "Metrics are not enabled"}), 404)
+            }
+            p if p == "health" || p == format!("{}/health", base_path) => {
+                (self::health::handle_health(), 200)
+            }
+            p if p == "ready" || p == format!("{}/ready", base_path) => {
+                let config = crate::globals::get_config();
+                let no_backends = HashMap::new();
+                let backends = config.as_ref().map_or(&no_backends, |c| &c.backends);
+                let (ready, status) =
+                    self::health::handle_ready(backends, crate::globals::get_storage()).await;
+                let value = serde_json::to_value(ready)
+                    .map_err(|_| Error::from("Failed to serialize readiness response"))?;
+                (value, status)
+            }
             _ => (serde_json::json!({"error": "Not found"}), 404),
         };
 
@@ -216,7 +294,7 @@ impl ServiceHandler<Value> for ManagementEndpoint {
     async fn endpoint_outgoing_response(
         &self,
         envelope: ResponseEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         // Build response from ResponseEnvelope
         let status = http::StatusCode::from_u16(envelope.response_details.status)
@@ -229,19 +307,13 @@ impl ServiceHandler<Value> for ManagementEndpoint {
             builder = builder.header(k.as_str(), v.as_str());
         }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_bytes = serde_json::to_vec(&normalized)
-                .map_err(|_| Error::from("Failed to serialize management response JSON"))?;
-            Body::from(body_bytes)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize management response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct management response"))
     }
 }
@@ -1,5 +1,5 @@
 use crate::config::config::ConfigError;
-use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
+use crate::models::envelope::envelope::{BodyPrecedence, RequestEnvelope, ResponseEnvelope};
 use crate::models::services::services::{ServiceHandler, ServiceType};
 use async_trait::async_trait;
 use axum::{body::Body, response::Response};
@@ -7,6 +7,7 @@ use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::log_context::{redact_parameters, OperationContext};
 use crate::router::route_config::RouteConfig;
 use crate::utils::Error;
 use std::sync::OnceLock;
@@ -159,7 +160,10 @@ impl MockDicomData {
 
     /// Handle a mock C-FIND request
     pub fn handle_find_query(&self, params: &HashMap<String, String>) -> Vec<serde_json::Value> {
-        debug!("[MOCK DICOM] C-FIND query params: {:?}", params);
+        debug!(
+            "[MOCK DICOM] C-FIND query params: {:?}",
+            redact_parameters(params)
+        );
 
         // Determine query level based on present parameters
         let query_level = if params.get("00080018").is_some_and(|v| !v.is_empty()) {
@@ -565,7 +569,7 @@ impl ServiceHandler<Value> for MockDicomEndpoint {
     async fn endpoint_outgoing_response(
         &self,
         envelope: ResponseEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<Response, Error> {
         // Build response from ResponseEnvelope
         let status = http::StatusCode::from_u16(envelope.response_details.status)
@@ -578,19 +582,13 @@ impl ServiceHandler<Value> for MockDicomEndpoint {
             builder = builder.header(k.as_str(), v.as_str());
         }
 
-        // Use original_data if available, otherwise serialize normalized_data
-        let body = if !envelope.original_data.is_empty() {
-            Body::from(envelope.original_data)
-        } else if let Some(normalized) = envelope.normalized_data {
-            let body_bytes = serde_json::to_vec(&normalized)
-                .map_err(|_| Error::from("Failed to serialize mock DICOM response JSON"))?;
-            Body::from(body_bytes)
-        } else {
-            Body::empty()
-        };
+        // normalized_data wins over stale original_data unless the endpoint opts out
+        let body = envelope
+            .into_body(BodyPrecedence::from_options(options))
+            .map_err(|_| Error::from("Failed to serialize mock DICOM response JSON"))?;
 
         builder
-            .body(body)
+            .body(Body::from(body))
             .map_err(|_| Error::from("Failed to construct mock DICOM HTTP response"))
     }
 }
@@ -601,6 +599,7 @@ impl MockDicomEndpoint {
         &self,
         envelope: &RequestEnvelope<Vec<u8>>,
         path: &str,
+        options: &HashMap<String, Value>,
     ) -> serde_json::Value {
         // Parse request body as either wrapper or raw identifier JSON
         let body_json: serde_json::Value =
@@ -651,7 +650,7 @@ impl MockDicomEndpoint {
         if path.contains("/series") || path.contains("/instances") {
             debug!("[MOCK DICOM] C-FIND Query:");
             debug!("[MOCK DICOM]   Path: {}", path);
-            debug!("[MOCK DICOM]   Query: {:?}", params);
+            debug!("[MOCK DICOM]   Query: {:?}", redact_parameters(&params));
         }
 
         // Handle query using mock data
@@ -662,21 +661,41 @@ impl MockDicomEndpoint {
             debug!("[MOCK DICOM]   Matches found: {}", matches.len());
         }
 
-        serde_json::json!({
+        let mut response = serde_json::json!({
             "operation": "find",
             "success": true,
             "matches": matches
-        })
+        });
+
+        // Simulate a PACS answering with 0xFF01 (pending, optional keys not supported)
+        let pending_warning = options
+            .get("find_status")
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim_start_matches("0x").eq_ignore_ascii_case("FF01"))
+            .unwrap_or(false);
+        if pending_warning && !matches.is_empty() {
+            response["warnings"] = serde_json::json!([
+                super::dicom::DicomEndpoint::pending_warning_entry(matches.len())
+            ]);
+        }
+
+        response
     }
 
     /// Handle C-GET operations for WADO-RS instance/frame retrieval
     async fn handle_c_get(
         &self,
-        _envelope: &RequestEnvelope<Vec<u8>>,
+        envelope: &RequestEnvelope<Vec<u8>>,
         path: &str,
+        options: &HashMap<String, Value>,
     ) -> serde_json::Value {
         debug!("[MOCK DICOM] C-GET Operation - Path: {}", path);
 
+        // Serve real DICOM files from a configured directory, filtered by StudyInstanceUID
+        if let Some(source_dir) = options.get("get_source_dir").and_then(|v| v.as_str()) {
+            return Self::get_from_source_dir(envelope, source_dir);
+        }
+
         // Create mock DICOM data directory and file
         let mock_dir = std::path::Path::new("./tmp/mock_dicom_data");
         if let Err(e) = std::fs::create_dir_all(mock_dir) {
@@ -740,11 +759,128 @@ impl MockDicomEndpoint {
         })
     }
 
+    /// Mock C-MOVE: reports one completed sub-operation per instance of the mock study
+    fn handle_c_move(envelope: &RequestEnvelope<Vec<u8>>) -> serde_json::Value {
+        let requested_uid = envelope
+            .normalized_data
+            .as_ref()
+            .and_then(|nd| nd.get("dimse_identifier"))
+            .and_then(|ident| ident.get("0020000D"))
+            .and_then(|v| v.get("Value"))
+            .and_then(|v| v.as_array())
+            .and_then(|arr| arr.first())
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let destination = envelope
+            .normalized_data
+            .as_ref()
+            .and_then(|nd| nd.get("move_destination"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("HARMONY_SCU");
+
+        let data = MockDicomData::instance();
+        if requested_uid != data.study_uid {
+            return serde_json::json!({
+                "operation": "move",
+                "success": false,
+                "error": "Study not found"
+            });
+        }
+
+        let file_count: usize = data.series.iter().map(|s| s.instances.len()).sum();
+        debug!(
+            "[MOCK DICOM] C-MOVE of {} to {} ({} instance(s))",
+            requested_uid, destination, file_count
+        );
+
+        serde_json::json!({
+            "operation": "move",
+            "success": true,
+            "destination": destination,
+            "folder_id": uuid::Uuid::new_v4().to_string(),
+            "file_count": file_count,
+            "completed": file_count,
+            "failed": 0,
+            "warning": 0
+        })
+    }
+
+    /// Mock C-GET backed by a directory of DICOM files: copies every file whose
+    /// StudyInstanceUID matches the requested one into a fresh retrieval folder
+    fn get_from_source_dir(
+        envelope: &RequestEnvelope<Vec<u8>>,
+        source_dir: &str,
+    ) -> serde_json::Value {
+        let body_json: serde_json::Value =
+            serde_json::from_slice(&envelope.original_data).unwrap_or(serde_json::Value::Null);
+        let requested_uid = body_json
+            .get("0020000D")
+            .and_then(|v| v.get("Value"))
+            .and_then(|v| v.as_array())
+            .and_then(|arr| arr.first())
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let folder_id = uuid::Uuid::new_v4().to_string();
+        let folder_path = std::path::Path::new("./tmp/mock_dicom_get").join(&folder_id);
+        if let Err(e) = std::fs::create_dir_all(&folder_path) {
+            return serde_json::json!({
+                "operation": "get",
+                "success": false,
+                "error": format!("Failed to create mock retrieval directory: {}", e)
+            });
+        }
+
+        let mut instances = Vec::new();
+        for entry in walkdir::WalkDir::new(source_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let Ok(obj) = dicom_object::open_file(entry.path()) else {
+                continue;
+            };
+            let Ok(json) = dicom_json_tool::identifier_to_json_value(&obj) else {
+                continue;
+            };
+            let uid = json
+                .get("0020000D")
+                .and_then(|v| v.get("Value"))
+                .and_then(|v| v.as_array())
+                .and_then(|arr| arr.first())
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            if !requested_uid.is_empty() && uid != requested_uid {
+                continue;
+            }
+            let target = folder_path.join(format!("{}.dcm", instances.len() + 1));
+            if std::fs::copy(entry.path(), &target).is_ok() {
+                instances.push(json);
+            }
+        }
+
+        debug!(
+            "[MOCK DICOM] C-GET from {} matched {} instance(s)",
+            source_dir,
+            instances.len()
+        );
+
+        serde_json::json!({
+            "operation": "get",
+            "success": true,
+            "instances": instances,
+            "folder_id": folder_id,
+            "folder_path": folder_path.to_string_lossy(),
+            "file_count": instances.len()
+        })
+    }
+
     /// Handle backend (SCU) request processing for mock DICOM
     async fn handle_backend_request(
         &self,
         envelope: &mut RequestEnvelope<Vec<u8>>,
-        _options: &HashMap<String, Value>,
+        options: &HashMap<String, Value>,
     ) -> Result<RequestEnvelope<Vec<u8>>, Error> {
         // Extract path for context and resolve operation (check normalized_data first)
         let path = envelope
@@ -763,6 +899,15 @@ impl MockDicomEndpoint {
             .or_else(|| envelope.request_details.metadata.get("dimse_op").cloned())
             .unwrap_or_else(|| path.clone());
 
+        let identifier = envelope
+            .normalized_data
+            .as_ref()
+            .and_then(|nd| nd.get("dimse_identifier"))
+            .cloned()
+            .unwrap_or(Value::Null);
+        let log_ctx = OperationContext::new(op.trim_start_matches('/'), "MOCK_DICOM")
+            .with_identifier(&identifier);
+
         let result = match op.as_str() {
             "echo" | "/echo" => {
                 // Mock C-ECHO - always successful
@@ -774,8 +919,9 @@ impl MockDicomEndpoint {
                     "port": 11112
                 })
             }
-            "find" | "/find" => self.handle_c_find(envelope, &path).await,
-            "get" | "/get" => self.handle_c_get(envelope, &path).await,
+            "find" | "/find" => self.handle_c_find(envelope, &path, options).await,
+            "get" | "/get" => self.handle_c_get(envelope, &path, options).await,
+            "move" | "/move" => Self::handle_c_move(envelope),
             _ => {
                 serde_json::json!({
                     "operation": op,
@@ -785,6 +931,15 @@ impl MockDicomEndpoint {
             }
         };
 
+        tracing::info!(
+            operation = %log_ctx.operation,
+            study_uid = %log_ctx.study_uid,
+            patient_id = %log_ctx.patient_id,
+            remote_aet = %log_ctx.remote_aet,
+            success = result.get("success").and_then(|v| v.as_bool()).unwrap_or(false),
+            "[MOCK DICOM] operation completed"
+        );
+
         // Update envelope with mock result
         envelope.normalized_data = Some(result);
         Ok(envelope.clone())
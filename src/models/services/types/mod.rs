@@ -1,8 +1,14 @@
 pub mod custom;
 pub mod dicom;
+pub mod dicom_charset;
+pub mod dicom_coalesce;
+pub mod dicom_layout;
+pub mod dicom_transfer;
 pub mod dicomweb;
+pub mod dicomweb_stow;
 pub mod echo;
 pub mod fhir;
+pub mod fhir_imagingstudy;
 pub mod http;
 pub mod jmix;
 pub mod management;
@@ -1,4 +1,5 @@
 use crate::config::config::Config;
+use crate::log_context::CORRELATION_ID;
 use crate::models::envelope::envelope::{RequestEnvelope, ResponseEnvelope};
 use crate::models::middleware::chain::MiddlewareChain;
 use crate::models::middleware::middleware::build_middleware_instances_for_pipeline;
@@ -67,16 +68,46 @@ impl PipelineExecutor {
     /// ResponseEnvelope on success, PipelineError on failure
     #[tracing::instrument(skip(envelope, pipeline, config, ctx), fields(
         protocol = ?ctx.protocol,
-        pipeline = pipeline.description.as_str()
+        pipeline = pipeline.description.as_str(),
+        correlation_id = tracing::field::Empty
     ))]
     pub async fn execute(
-        envelope: RequestEnvelope<Vec<u8>>,
+        mut envelope: RequestEnvelope<Vec<u8>>,
         pipeline: &Pipeline,
         config: &Config,
         ctx: &ProtocolCtx,
     ) -> Result<ResponseEnvelope<Vec<u8>>, PipelineError> {
         tracing::info!("Executing pipeline for protocol: {:?}", ctx.protocol);
 
+        // Adapter metadata that endpoint services do not copy into the envelope themselves
+        for key in [
+            "client_ip",
+            CORRELATION_ID,
+            crate::telemetry::TRACEPARENT,
+            crate::telemetry::TRACESTATE,
+        ] {
+            if let Some(value) = ctx.meta.get(key) {
+                envelope
+                    .request_details
+                    .metadata
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        // One correlation ID per request, unless the adapter already has one, logged with
+        // everything the pipeline does and passed on to the backends
+        let correlation_id = envelope
+            .request_details
+            .metadata
+            .entry(CORRELATION_ID.to_string())
+            .or_insert_with(|| uuid::Uuid::new_v4().to_string());
+        tracing::Span::current().record(CORRELATION_ID, correlation_id.as_str());
+        // Continue any inbound trace; the resulting context travels in the request metadata
+        crate::telemetry::continue_trace(
+            &tracing::Span::current(),
+            &mut envelope.request_details.metadata,
+        );
+
         // 1. Endpoint service preprocessing
         let envelope = Self::process_endpoint_incoming(envelope, pipeline, config).await?;
 
@@ -0,0 +1,119 @@
+//! Short-lived cache of successful gateway authorizations
+//!
+//! Repeated authorize calls for the same gateway and user within the cache
+//! window are answered locally instead of calling Runbeam Cloud again.
+
+use crate::runbeam_api::types::AuthorizeResponse;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Identifies an authorization: the cloud it came from, the gateway, and the
+/// user (JWT subject) who requested it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuthorizationKey {
+    pub api_base_url: String,
+    pub gateway_code: String,
+    pub subject: String,
+}
+
+struct CachedAuthorization {
+    response: AuthorizeResponse,
+    cached_at: Instant,
+}
+
+#[derive(Default)]
+pub struct AuthorizationCache {
+    entries: RwLock<HashMap<AuthorizationKey, CachedAuthorization>>,
+}
+
+impl AuthorizationCache {
+    /// Return a cached response younger than `ttl` whose machine token has not expired
+    pub fn get(&self, key: &AuthorizationKey, ttl: Duration) -> Option<AuthorizeResponse> {
+        let entries = self.entries.read().unwrap();
+        let entry = entries.get(key)?;
+        if entry.cached_at.elapsed() >= ttl || token_expired(&entry.response.expires_at) {
+            return None;
+        }
+        Some(entry.response.clone())
+    }
+
+    pub fn insert(&self, key: AuthorizationKey, response: AuthorizeResponse) {
+        let mut entries = self.entries.write().unwrap();
+        // Drop anything whose token has since expired
+        entries.retain(|_, e| !token_expired(&e.response.expires_at));
+        entries.insert(
+            key,
+            CachedAuthorization {
+                response,
+                cached_at: Instant::now(),
+            },
+        );
+    }
+
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+}
+
+/// Unparseable expiry timestamps are treated as expired
+fn token_expired(expires_at: &str) -> bool {
+    DateTime::parse_from_rfc3339(expires_at)
+        .map(|t| t.with_timezone(&Utc) <= Utc::now())
+        .unwrap_or(true)
+}
+
+static AUTHORIZATION_CACHE: Lazy<AuthorizationCache> = Lazy::new(AuthorizationCache::default);
+
+/// Process-wide authorization cache
+pub fn authorization_cache() -> &'static AuthorizationCache {
+    &AUTHORIZATION_CACHE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runbeam_api::types::GatewayInfo;
+
+    fn response(expires_at: &str) -> AuthorizeResponse {
+        AuthorizeResponse {
+            machine_token: "mt".to_string(),
+            expires_in: 60.0,
+            expires_at: expires_at.to_string(),
+            gateway: GatewayInfo {
+                id: "gw-1".to_string(),
+                code: "gw".to_string(),
+                name: "Gateway".to_string(),
+                authorized_by: None,
+            },
+            abilities: vec![],
+        }
+    }
+
+    fn key() -> AuthorizationKey {
+        AuthorizationKey {
+            api_base_url: "http://cloud".to_string(),
+            gateway_code: "gw".to_string(),
+            subject: "user-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_cache_respects_ttl_and_token_expiry() {
+        let cache = AuthorizationCache::default();
+        cache.insert(key(), response("2099-01-01T00:00:00Z"));
+        assert!(cache.get(&key(), Duration::from_secs(60)).is_some());
+        assert!(cache.get(&key(), Duration::ZERO).is_none());
+
+        let other_user = AuthorizationKey {
+            subject: "user-2".to_string(),
+            ..key()
+        };
+        assert!(cache.get(&other_user, Duration::from_secs(60)).is_none());
+
+        cache.insert(key(), response("2000-01-01T00:00:00Z"));
+        assert!(cache.get(&key(), Duration::from_secs(60)).is_none());
+    }
+}
@@ -2,6 +2,7 @@ use crate::runbeam_api::types::{ApiError, AuthorizeResponse, RunbeamError};
 use serde::Serialize;
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// HTTP client for Runbeam Cloud API
 ///
@@ -13,6 +14,34 @@ pub struct RunbeamClient {
     base_url: String,
     /// HTTP client for making requests
     client: reqwest::Client,
+    /// How transient failures are retried
+    retry: RetryPolicy,
+}
+
+/// Retry behaviour for Runbeam Cloud calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first (1 disables retries)
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry (1-based)
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
 }
 
 /// Request payload for gateway authorization
@@ -51,15 +80,25 @@ impl RunbeamClient {
         Self {
             base_url,
             client: reqwest::Client::new(),
+            retry: RetryPolicy::default(),
         }
     }
 
+    /// Use the given retry policy for transient failures
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     /// Authorize a gateway and obtain a machine-scoped token
     ///
     /// This method exchanges a user JWT token for a machine-scoped token that
     /// the gateway can use for autonomous API access. The machine token has
     /// a 30-day expiry (configured server-side).
     ///
+    /// Network errors and 5xx responses are retried with backoff according to
+    /// the client's [`RetryPolicy`]; 4xx responses are returned immediately.
+    ///
     /// # Arguments
     ///
     /// * `user_token` - The user's JWT token from CLI authentication
@@ -106,26 +145,51 @@ impl RunbeamClient {
             gateway_code
         );
 
-        // Construct the authorization endpoint URL
-        let url = format!("{}/api/harmony/authorize", self.base_url);
-
         // Build request payload
         let payload = AuthorizeRequest {
-            token: user_token.clone(),
-            gateway_code: gateway_code.clone(),
+            token: user_token,
+            gateway_code,
             machine_public_key,
             metadata,
         };
 
+        let mut attempt = 1;
+        loop {
+            match self.send_authorize(&payload).await {
+                Err(e) if e.is_retryable() && attempt < self.retry.max_attempts => {
+                    let delay = self.retry.backoff(attempt);
+                    tracing::warn!(
+                        "Authorization attempt {}/{} failed ({}); retrying in {:?}",
+                        attempt,
+                        self.retry.max_attempts,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send a single authorization request
+    async fn send_authorize(
+        &self,
+        payload: &AuthorizeRequest,
+    ) -> Result<AuthorizeResponse, RunbeamError> {
+        // Construct the authorization endpoint URL
+        let url = format!("{}/api/harmony/authorize", self.base_url);
+
         tracing::debug!("Sending authorization request to: {}", url);
 
         // Make the request
         let response = self
             .client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", user_token))
+            .header("Authorization", format!("Bearer {}", payload.token))
             .header("Content-Type", "application/json")
-            .json(&payload)
+            .json(payload)
             .send()
             .await
             .map_err(|e| {
@@ -196,6 +260,17 @@ mod tests {
         assert_eq!(client.base_url(), "http://example.com");
     }
 
+    #[test]
+    fn test_retry_backoff_doubles() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(100),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+    }
+
     #[test]
     fn test_authorize_request_serialization() {
         let request = AuthorizeRequest {
@@ -11,12 +11,14 @@
 /// 4. Runbeam Cloud issues machine-scoped token (30-day expiry)
 /// 5. Harmony stores machine token locally for future API calls
 
+pub mod cache;
 pub mod client;
 pub mod jwt;
 pub mod token_storage;
 pub mod types;
 
-pub use client::RunbeamClient;
+pub use cache::{authorization_cache, AuthorizationKey};
+pub use client::{RetryPolicy, RunbeamClient};
 pub use jwt::{validate_jwt_token, JwtClaims};
 pub use token_storage::{clear_token, load_token, save_token, MachineToken};
 pub use types::{ApiError, RunbeamError};
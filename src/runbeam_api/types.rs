@@ -30,6 +30,18 @@ impl fmt::Display for RunbeamError {
 
 impl std::error::Error for RunbeamError {}
 
+impl RunbeamError {
+    /// Whether the failure may be transient: network errors and 5xx responses.
+    /// 4xx responses reflect the request itself and are not worth repeating.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RunbeamError::Api(ApiError::Network(_)) => true,
+            RunbeamError::Api(ApiError::Http { status, .. }) => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
 impl From<ApiError> for RunbeamError {
     fn from(err: ApiError) -> Self {
         RunbeamError::Api(err)
@@ -126,8 +126,7 @@ impl DatabaseManager {
         Ok(removed)
     }
 
-    /// Clear all databases (primarily for testing)
-    #[cfg(test)]
+    /// Clear all databases (used at shutdown and in tests)
     pub fn clear_all_databases(&self) -> Result<usize, String> {
         let mut map = self
             .databases
@@ -158,4 +158,24 @@ mod tests {
             .expect("Failed to remove file");
         assert!(!storage.exists_str(file_path));
     }
+
+    #[tokio::test]
+    async fn test_listing() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let storage = FilesystemStorage::new(temp_dir.path()).expect("Failed to create storage");
+        for path in ["dimse/a/2.dcm", "dimse/a/1.dcm", "dimse/b.dcm"] {
+            storage.write_file_str(path, b"x").await.unwrap();
+        }
+
+        let listed = storage.list_dir_str("dimse").await.unwrap();
+        assert_eq!(listed, vec!["a", "b.dcm"]);
+        let listed = storage.list_files_recursive_str("dimse").await.unwrap();
+        assert_eq!(listed, vec!["a/1.dcm", "a/2.dcm", "b.dcm"]);
+
+        assert!(storage.list_dir_str("missing").await.is_err());
+        assert!(storage
+            .list_files_recursive_str("dimse/b.dcm")
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,200 @@
+use crate::storage::{StorageBackend, StorageError, StorageResult};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// In-memory storage backend for tests and stateless deployments
+///
+/// Files written through the backend are kept in a map keyed by their relative path and are
+/// lost when the process exits. Directories are implied by the paths of the files in them.
+/// Staging directories (`ensure_dir_str`, `tempdir_in_str`) still live on local disk under
+/// the staging root, since DIMSE tools need real paths to write received instances to.
+#[derive(Debug)]
+pub struct MemoryStorage {
+    staging_path: PathBuf,
+    files: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    /// Create an empty in-memory backend staging transfers under `staging_path`
+    pub fn new<P: AsRef<Path>>(staging_path: P) -> StorageResult<Self> {
+        let staging_path = staging_path.as_ref().to_path_buf();
+        std::fs::create_dir_all(&staging_path).map_err(|e| {
+            StorageError::Config(format!(
+                "Failed to create staging directory '{}': {}",
+                staging_path.display(),
+                e
+            ))
+        })?;
+        Ok(Self {
+            staging_path,
+            files: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Paths of the files below `dir` (every file when empty), relative to it and sorted
+    fn files_below(&self, dir: &str) -> StorageResult<Vec<String>> {
+        let dir = key(dir);
+        let files = self.files.read().expect("memory storage poisoned");
+        let mut paths: Vec<String> = files
+            .keys()
+            .filter_map(|path| {
+                if dir.is_empty() {
+                    Some(path.as_str())
+                } else {
+                    path.strip_prefix(&dir)?.strip_prefix('/')
+                }
+            })
+            .map(str::to_string)
+            .collect();
+        if paths.is_empty() {
+            return Err(not_found(&dir));
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// Map key of a relative path: no leading `./` or surrounding slashes
+fn key(path: &str) -> String {
+    path.trim_start_matches("./").trim_matches('/').to_string()
+}
+
+/// Whether `path` is `dir` itself or lies below it
+fn is_under(path: &str, dir: &str) -> bool {
+    path == dir
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn not_found(path: &str) -> StorageError {
+    StorageError::Io(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        format!("{} not found in memory storage", path),
+    ))
+}
+
+#[async_trait]
+impl StorageBackend for MemoryStorage {
+    fn base_path(&self) -> &Path {
+        &self.staging_path
+    }
+
+    async fn write_file_str(&self, path: &str, contents: &[u8]) -> StorageResult<PathBuf> {
+        self.files
+            .write()
+            .expect("memory storage poisoned")
+            .insert(key(path), contents.to_vec());
+        Ok(self.subpath_str(path))
+    }
+
+    async fn read_file_str(&self, path: &str) -> StorageResult<Vec<u8>> {
+        self.files
+            .read()
+            .expect("memory storage poisoned")
+            .get(&key(path))
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn exists_str(&self, path: &str) -> bool {
+        let path = key(path);
+        self.files
+            .read()
+            .expect("memory storage poisoned")
+            .keys()
+            .any(|file| is_under(file, &path))
+    }
+
+    async fn list_dir_str(&self, path: &str) -> StorageResult<Vec<String>> {
+        let mut names: Vec<String> = self
+            .files_below(path)?
+            .iter()
+            .map(|file| file.split('/').next().unwrap_or(file).to_string())
+            .collect();
+        names.dedup();
+        Ok(names)
+    }
+
+    async fn list_files_recursive_str(&self, path: &str) -> StorageResult<Vec<String>> {
+        self.files_below(path)
+    }
+
+    async fn remove_str(&self, path: &str) -> StorageResult<()> {
+        let dir = key(path);
+        let mut files = self.files.write().expect("memory storage poisoned");
+        let before = files.len();
+        files.retain(|file, _| !is_under(file, &dir));
+        if files.len() == before {
+            return Err(not_found(path));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_bytes_round_trip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let storage = MemoryStorage::new(temp_dir.path()).expect("Failed to create storage");
+        assert!(!storage.is_filesystem());
+
+        let contents = b"DICM\x00\x01binary";
+        let written = storage
+            .write_file_str("dimse/abc/1.2.3.dcm", contents)
+            .await
+            .expect("Failed to write file");
+        assert!(written.ends_with("dimse/abc/1.2.3.dcm"));
+        assert!(!written.exists(), "nothing is written to disk");
+
+        assert!(storage.exists_str("dimse/abc/1.2.3.dcm"));
+        assert!(storage.exists_str("./dimse/abc/"));
+        assert!(!storage.exists_str("dimse/ab"));
+        let read = storage
+            .read_file_str("/dimse/abc/1.2.3.dcm")
+            .await
+            .expect("Failed to read file");
+        assert_eq!(read, contents);
+
+        storage
+            .remove_str("dimse/abc/1.2.3.dcm")
+            .await
+            .expect("Failed to remove file");
+        assert!(!storage.exists_str("dimse/abc/1.2.3.dcm"));
+        assert!(storage.read_file_str("dimse/abc/1.2.3.dcm").await.is_err());
+        assert!(storage.remove_str("dimse/abc/1.2.3.dcm").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_listing_and_directory_removal() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let storage = MemoryStorage::new(temp_dir.path()).expect("Failed to create storage");
+        for path in [
+            "dimse/a/2.dcm",
+            "dimse/a/1.dcm",
+            "dimse/ab/3.dcm",
+            "stow/4.dcm",
+        ] {
+            storage.write_file_str(path, b"x").await.unwrap();
+        }
+
+        let listed = storage.list_files_recursive_str("dimse/a").await.unwrap();
+        assert_eq!(listed, vec!["1.dcm", "2.dcm"]);
+        let listed = storage.list_dir_str("dimse").await.unwrap();
+        assert_eq!(listed, vec!["a", "ab"]);
+        let listed = storage.list_dir_str("").await.unwrap();
+        assert_eq!(listed, vec!["dimse", "stow"]);
+        assert_eq!(storage.list_files_recursive_str("").await.unwrap().len(), 4);
+        assert!(storage.list_dir_str("dimse/abc").await.is_err());
+
+        storage.remove_str("dimse/a").await.unwrap();
+        let listed = storage.list_files_recursive_str("dimse").await.unwrap();
+        assert_eq!(listed, vec!["ab/3.dcm"]);
+    }
+}
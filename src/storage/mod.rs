@@ -5,9 +5,12 @@ use std::sync::Arc;
 
 pub mod database_manager;
 pub mod filesystem;
+pub mod memory;
+pub mod retention;
 
 pub use database_manager::{DatabaseBackend, DatabaseManager, DatabaseOperation, DatabaseStats};
 pub use filesystem::FilesystemStorage;
+pub use memory::MemoryStorage;
 
 /// Error type for storage operations
 #[derive(Debug)]
@@ -104,6 +107,41 @@ pub trait StorageBackend: Send + Sync + std::fmt::Debug {
         self.subpath_str(path).exists()
     }
 
+    /// Names of the files and directories directly under the given relative path, sorted
+    async fn list_dir_str(&self, path: &str) -> StorageResult<Vec<String>> {
+        let full_path = self.subpath_str(path);
+        let mut names = Vec::new();
+        let mut entries = tokio::fs::read_dir(&full_path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Paths of every file below the given relative path, relative to it and sorted
+    async fn list_files_recursive_str(&self, path: &str) -> StorageResult<Vec<String>> {
+        let full_path = self.subpath_str(path);
+        if !full_path.is_dir() {
+            return Err(StorageError::Path(format!(
+                "{} is not a directory",
+                full_path.display()
+            )));
+        }
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(&full_path) {
+            let entry = entry.map_err(|e| StorageError::Path(e.to_string()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            if let Ok(relative) = entry.path().strip_prefix(&full_path) {
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
     /// Remove a file or directory at the given relative path
     async fn remove_str(&self, path: &str) -> StorageResult<()> {
         let full_path = self.subpath_str(path);
@@ -157,6 +195,17 @@ pub fn create_storage_backend(config: &StorageConfig) -> StorageResult<Arc<dyn S
             let storage = FilesystemStorage::new(path)?;
             Ok(Arc::new(storage))
         }
+        "memory" => {
+            // Only staging directories for DIMSE transfers are created under the path
+            let path = config
+                .options
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or("./tmp");
+
+            let storage = MemoryStorage::new(path)?;
+            Ok(Arc::new(storage))
+        }
         _ => Err(StorageError::Config(format!(
             "Unknown storage backend: {}",
             config.backend
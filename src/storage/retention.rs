@@ -0,0 +1,301 @@
+use crate::models::middleware::types::jmix_index::get_jmix_index;
+use crate::storage::{StorageConfig, StorageError, StorageResult};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Subdirectories of the storage root whose entries are swept
+const SWEPT_DIRS: &[&str] = &["dimse", "jmix-store"];
+
+/// The JMIX index database, which lives alongside the packages it lists
+const JMIX_INDEX_FILE: &str = "jmix-index.redb";
+
+/// Retention settings for the filesystem storage backend
+///
+/// Parsed from `[storage.options]`; the sweeper only runs when `retention_hours` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionConfig {
+    pub root: PathBuf,
+    pub retention: Duration,
+    pub interval: Duration,
+}
+
+/// What a single sweep removed
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SweepReport {
+    pub removed: usize,
+    pub bytes: u64,
+}
+
+/// Parse retention settings; `Ok(None)` when retention is not configured
+pub fn parse_config(config: &StorageConfig) -> Result<Option<RetentionConfig>, String> {
+    let Some(hours) = config.options.get("retention_hours") else {
+        return Ok(None);
+    };
+    let hours = hours
+        .as_u64()
+        .filter(|h| *h > 0)
+        .ok_or("'retention_hours' must be a positive integer")?;
+    let interval_secs = match config.options.get("cleanup_interval_secs") {
+        Some(v) => v
+            .as_u64()
+            .filter(|s| *s > 0)
+            .ok_or("'cleanup_interval_secs' must be a positive integer")?,
+        None => 3600,
+    };
+    let root = config
+        .options
+        .get("path")
+        .and_then(|v| v.as_str())
+        .unwrap_or("./tmp");
+    Ok(Some(RetentionConfig {
+        root: PathBuf::from(root),
+        retention: Duration::from_secs(hours * 3600),
+        interval: Duration::from_secs(interval_secs),
+    }))
+}
+
+/// Remove entries under `dimse/` and `jmix-store/` last modified more than `retention` ago
+///
+/// Files such as `dimse/<uid>.dcm` age by their own modification time. A directory ages by the
+/// newest modification time anywhere in its subtree, so one still being written to is kept.
+/// JMIX packages still listed in the JMIX index are kept regardless of age, since
+/// `GET /jmix/{id}` serves them from there, and so is the index itself.
+pub async fn sweep(root: &Path, retention: Duration) -> StorageResult<SweepReport> {
+    let cutoff = SystemTime::now()
+        .checked_sub(retention)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut report = SweepReport::default();
+
+    for subdir in SWEPT_DIRS {
+        let dir = root.join(subdir);
+        if !dir.is_dir() {
+            continue;
+        }
+        let index = if *subdir == "jmix-store" && dir.join(JMIX_INDEX_FILE).exists() {
+            Some(get_jmix_index(&dir).map_err(StorageError::Config)?)
+        } else {
+            None
+        };
+
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let metadata = entry.metadata().await?;
+            if entry.file_name() == JMIX_INDEX_FILE {
+                continue;
+            }
+            let modified = if metadata.is_dir() {
+                newest_modified(&path)?
+            } else {
+                metadata.modified()?
+            };
+            if modified > cutoff {
+                continue;
+            }
+            if let Some(index) = &index {
+                let id = entry.file_name().to_string_lossy().to_string();
+                if index.exists(&id).map_err(StorageError::Config)? {
+                    continue;
+                }
+            }
+
+            let (bytes, removed) = if metadata.is_dir() {
+                (dir_size(&path), tokio::fs::remove_dir_all(&path).await)
+            } else {
+                (metadata.len(), tokio::fs::remove_file(&path).await)
+            };
+            match removed {
+                Ok(()) => {
+                    tracing::info!(
+                        "🧹 Removed expired storage entry {} ({} bytes)",
+                        path.display(),
+                        bytes
+                    );
+                    report.removed += 1;
+                    report.bytes += bytes;
+                }
+                Err(e) => tracing::warn!("Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Latest modification time of `path` and everything below it
+fn newest_modified(path: &Path) -> std::io::Result<SystemTime> {
+    let mut newest = std::fs::metadata(path)?.modified()?;
+    for entry in std::fs::read_dir(path)?.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let modified = if metadata.is_dir() {
+            newest_modified(&entry.path())?
+        } else {
+            metadata.modified()?
+        };
+        newest = newest.max(modified);
+    }
+    Ok(newest)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&entry.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Start the periodic retention sweeper when `retention_hours` is configured
+pub fn spawn_sweeper(
+    config: &StorageConfig,
+    shutdown: CancellationToken,
+) -> Option<JoinHandle<()>> {
+    if config.backend != "filesystem" {
+        return None;
+    }
+    let retention = match parse_config(config) {
+        Ok(Some(retention)) => retention,
+        Ok(None) => return None,
+        Err(e) => {
+            tracing::error!("Not starting storage retention sweeper: {}", e);
+            return None;
+        }
+    };
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(retention.interval);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {
+                    match sweep(&retention.root, retention.retention).await {
+                        Ok(report) if report.removed > 0 => tracing::info!(
+                            "Storage retention sweep reclaimed {} entries ({} bytes)",
+                            report.removed,
+                            report.bytes
+                        ),
+                        Ok(_) => {}
+                        Err(e) => tracing::warn!("Storage retention sweep failed: {}", e),
+                    }
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::middleware::types::jmix_index::{current_timestamp, JmixPackageInfo};
+    use tempfile::TempDir;
+
+    fn age(path: &Path, by: Duration) {
+        let file = std::fs::File::open(path).unwrap();
+        file.set_modified(SystemTime::now() - by).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sweep_removes_aged_directories_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let aged = root.join("dimse/aged");
+        let fresh = root.join("dimse/fresh");
+        std::fs::create_dir_all(&aged).unwrap();
+        std::fs::create_dir_all(&fresh).unwrap();
+        std::fs::write(aged.join("1.dcm"), b"DICM").unwrap();
+        age(&aged, Duration::from_secs(3 * 3600));
+
+        let report = sweep(root, Duration::from_secs(3600)).await.unwrap();
+
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.bytes, 4);
+        assert!(!aged.exists());
+        assert!(fresh.exists());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_removes_aged_files_and_keeps_active_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let dimse = root.join("dimse");
+        std::fs::create_dir_all(&dimse).unwrap();
+        let aged_file = dimse.join("1.2.3.dcm");
+        let fresh_file = dimse.join("1.2.4.dcm");
+        std::fs::write(&aged_file, b"DICM").unwrap();
+        std::fs::write(&fresh_file, b"DICM").unwrap();
+        age(&aged_file, Duration::from_secs(3 * 3600));
+
+        // The directory itself is old, but a transfer wrote into it just now
+        let active = dimse.join("move-in-progress");
+        std::fs::create_dir_all(active.join("series")).unwrap();
+        std::fs::write(active.join("series/2.dcm"), b"DICM").unwrap();
+        age(&active, Duration::from_secs(3 * 3600));
+        age(&active.join("series"), Duration::from_secs(3 * 3600));
+
+        let report = sweep(root, Duration::from_secs(3600)).await.unwrap();
+
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.bytes, 4);
+        assert!(!aged_file.exists());
+        assert!(fresh_file.exists());
+        assert!(active.join("series/2.dcm").exists());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_keeps_indexed_jmix_packages() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = temp_dir.path().join("jmix-store");
+        let indexed = store.join("indexed-pkg");
+        let orphan = store.join("orphan-pkg");
+        std::fs::create_dir_all(&indexed).unwrap();
+        std::fs::create_dir_all(&orphan).unwrap();
+        get_jmix_index(&store)
+            .unwrap()
+            .index_package(&JmixPackageInfo {
+                id: "indexed-pkg".to_string(),
+                study_uid: "1.2.3".to_string(),
+                path: indexed.to_string_lossy().to_string(),
+                created_at: current_timestamp(),
+            })
+            .unwrap();
+        age(&indexed, Duration::from_secs(3 * 3600));
+        age(&orphan, Duration::from_secs(3 * 3600));
+        age(&store.join(JMIX_INDEX_FILE), Duration::from_secs(3 * 3600));
+
+        let report = sweep(temp_dir.path(), Duration::from_secs(3600))
+            .await
+            .unwrap();
+
+        assert_eq!(report.removed, 1);
+        assert!(indexed.exists());
+        assert!(!orphan.exists());
+        assert!(store.join(JMIX_INDEX_FILE).exists());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let mut config = StorageConfig::default();
+        assert_eq!(parse_config(&config), Ok(None));
+
+        config
+            .options
+            .insert("retention_hours".to_string(), serde_json::json!(24));
+        let retention = parse_config(&config).unwrap().unwrap();
+        assert_eq!(retention.retention, Duration::from_secs(24 * 3600));
+        assert_eq!(retention.interval, Duration::from_secs(3600));
+        assert_eq!(retention.root, PathBuf::from("./tmp"));
+
+        config
+            .options
+            .insert("retention_hours".to_string(), serde_json::json!(0));
+        assert!(parse_config(&config).is_err());
+    }
+}
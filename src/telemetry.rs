@@ -0,0 +1,121 @@
+//! OpenTelemetry trace export and W3C trace context propagation
+//!
+//! With feature "otel" and `[telemetry] enabled = true`, spans are exported over OTLP/gRPC to
+//! `otlp_endpoint`. An inbound `traceparent`/`tracestate` pair carried in request metadata
+//! becomes the parent of the pipeline span, and the current context is written back to the
+//! metadata so backends can forward it. Without the feature every function here is a no-op.
+
+use std::collections::HashMap;
+
+#[cfg(feature = "otel")]
+use opentelemetry::propagation::{Extractor, Injector};
+#[cfg(feature = "otel")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otel")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::config::telemetry_config::TelemetryConfig;
+
+/// W3C trace context header, also used as the request metadata key
+pub const TRACEPARENT: &str = "traceparent";
+/// Vendor-specific trace state accompanying [`TRACEPARENT`]
+pub const TRACESTATE: &str = "tracestate";
+
+/// Build the OTLP export layer, or `None` when telemetry is disabled or cannot start
+pub fn layer<S>(config: &TelemetryConfig) -> Option<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    #[cfg(feature = "otel")]
+    {
+        if !config.enabled {
+            return None;
+        }
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(config.otlp_endpoint.clone())
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                eprintln!("OpenTelemetry export disabled: {}", e);
+                return None;
+            }
+        };
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(opentelemetry_sdk::Resource::new(vec![
+                opentelemetry::KeyValue::new("service.name", config.service_name.clone()),
+            ]))
+            .build();
+        let tracer = provider.tracer("harmony");
+        opentelemetry::global::set_tracer_provider(provider);
+        opentelemetry::global::set_text_map_propagator(
+            opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+        );
+        Some(tracing_opentelemetry::layer().with_tracer(tracer).boxed())
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        if config.enabled {
+            eprintln!("Telemetry is enabled but Harmony was built without feature \"otel\"");
+        }
+        None
+    }
+}
+
+/// Parent `span` on the trace context carried in `metadata`, then write the resulting
+/// context back so it travels with the request
+pub fn continue_trace(span: &tracing::Span, metadata: &mut HashMap<String, String>) {
+    #[cfg(feature = "otel")]
+    {
+        if metadata.contains_key(TRACEPARENT) {
+            let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+                propagator.extract(&MetadataExtractor(metadata))
+            });
+            span.set_parent(parent);
+        }
+        let context = span.context();
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut MetadataInjector(metadata))
+        });
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = (span, metadata);
+    }
+}
+
+/// Flush buffered spans to the collector
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+#[cfg(feature = "otel")]
+struct MetadataExtractor<'a>(&'a HashMap<String, String>);
+
+#[cfg(feature = "otel")]
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+#[cfg(feature = "otel")]
+struct MetadataInjector<'a>(&'a mut HashMap<String, String>);
+
+#[cfg(feature = "otel")]
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+}
@@ -1,3 +1,47 @@
 use serde::de::StdError;
 
 pub type Error = Box<dyn StdError + Send + Sync>;
+
+/// Source of the random identifiers used for multipart boundaries and retrieval folder ids.
+///
+/// Defaults to UUID v4. Tests can swap in [`IdGenerator::sequence`] so that generated output
+/// is reproducible.
+#[derive(Clone)]
+pub struct IdGenerator(std::sync::Arc<dyn Fn() -> String + Send + Sync>);
+
+impl IdGenerator {
+    pub fn new(generate: impl Fn() -> String + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(generate))
+    }
+
+    /// Random UUID v4 identifiers
+    pub fn uuid() -> Self {
+        Self::new(|| uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Deterministic `{prefix}1`, `{prefix}2`, ... identifiers
+    pub fn sequence(prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into();
+        let counter = std::sync::atomic::AtomicUsize::new(0);
+        Self::new(move || {
+            let n = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+            format!("{}{}", prefix, n)
+        })
+    }
+
+    pub fn next_id(&self) -> String {
+        (self.0)()
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::uuid()
+    }
+}
+
+impl std::fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("IdGenerator")
+    }
+}
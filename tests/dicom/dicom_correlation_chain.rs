@@ -0,0 +1,249 @@
+use async_trait::async_trait;
+use dicom_core::{DataElement, PrimitiveValue, VR};
+use dicom_dictionary_std::tags;
+use dimse::scp::DefaultQueryProvider;
+use dimse::types::DatasetStream;
+use dimse::{DimseConfig, DimseRequest, DimseResponse, DimseScp, DimseScu, RemoteNode, Router};
+use harmony::adapters::dimse::query_provider::PipelineRouter;
+use harmony::config::config::Config;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::span::{Attributes, Id, Record};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+
+/// Every span opened, with its fields as last recorded
+#[derive(Clone, Default)]
+struct SpanRecorder(Arc<Mutex<Recorded>>);
+
+#[derive(Default)]
+struct Recorded {
+    spans: Vec<(String, HashMap<String, String>)>,
+    open: HashMap<u64, usize>,
+}
+
+struct Fields<'a>(&'a mut HashMap<String, String>);
+
+impl tracing::field::Visit for Fields<'_> {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for SpanRecorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        attrs.record(&mut Fields(&mut fields));
+        let mut recorded = self.0.lock().unwrap();
+        let index = recorded.spans.len();
+        recorded.open.insert(id.into_u64(), index);
+        recorded
+            .spans
+            .push((attrs.metadata().name().to_string(), fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut recorded = self.0.lock().unwrap();
+        if let Some(&index) = recorded.open.get(&id.into_u64()) {
+            values.record(&mut Fields(&mut recorded.spans[index].1));
+        }
+    }
+}
+
+impl SpanRecorder {
+    /// Correlation IDs of the spans named `name`, only those for DIMSE operation `operation`
+    /// when one is given
+    fn correlation_ids(&self, name: &str, operation: Option<&str>) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .spans
+            .iter()
+            .filter(|(n, fields)| {
+                n == name
+                    && (operation.is_none()
+                        || fields.get("dimse.operation").map(String::as_str) == operation)
+            })
+            .map(|(_, fields)| fields.get("correlation_id").cloned().unwrap_or_default())
+            .collect()
+    }
+}
+
+/// Passes requests to the pipeline router, keeping the correlation ID of each response
+struct ResponseRecorder {
+    inner: PipelineRouter,
+    correlation_ids: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Router for ResponseRecorder {
+    async fn send_request(&self, request: DimseRequest) -> dimse::Result<DimseResponse> {
+        let response = self.inner.send_request(request).await?;
+        self.correlation_ids
+            .lock()
+            .unwrap()
+            .push(response.correlation_id.to_string());
+        Ok(response)
+    }
+
+    async fn send_streaming_request(
+        &self,
+        request: DimseRequest,
+    ) -> dimse::Result<futures_util::stream::BoxStream<'static, DimseResponse>> {
+        self.inner.send_streaming_request(request).await
+    }
+
+    async fn next_request(&mut self) -> dimse::Result<DimseRequest> {
+        self.inner.next_request().await
+    }
+
+    async fn send_response(&self, response: DimseResponse) -> dimse::Result<()> {
+        self.inner.send_response(response).await
+    }
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[tokio::test]
+async fn routed_store_runs_pipeline_and_backend_scu_under_association_id() {
+    let recorder = SpanRecorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    // The backend's peer is not listening; its SCU only has to open its span
+    let backend_port = free_port();
+    let toml = format!(
+        r#"
+        [proxy]
+        id = "dicom-correlation-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8084
+
+        [pipelines.modality_in]
+        description = "DIMSE SCP -> DICOM backend"
+        networks = ["default"]
+        endpoints = ["dimse_scp"]
+        backends = ["dicom_pacs"]
+        middleware = []
+
+        [endpoints.dimse_scp]
+        service = "dicom"
+        [endpoints.dimse_scp.options]
+        local_aet = "HARMONY_SCP"
+
+        [backends.dicom_pacs]
+        service = "dicom"
+        [backends.dicom_pacs.options]
+        aet = "PACS"
+        host = "127.0.0.1"
+        port = {backend_port}
+        local_aet = "HARMONY_SCU"
+
+        [services.dicom]
+        module = ""
+    "#,
+        backend_port = backend_port
+    );
+    let cfg: Config = toml::from_str(&toml).expect("TOML parse error");
+    harmony::globals::set_config(Arc::new(cfg));
+
+    // Routed C-STOREs go through the pipeline, as with an endpoint's aet_routes
+    let port = free_port();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config = DimseConfig {
+        local_aet: "HARMONY_SCP".to_string(),
+        bind_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        port,
+        storage_dir: temp_dir.path().to_path_buf(),
+        ..Default::default()
+    };
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let router = ResponseRecorder {
+        inner: PipelineRouter::new("modality_in", "dimse_scp", config.clone()),
+        correlation_ids: responses.clone(),
+    };
+    let provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn(
+        DimseScp::new(config, provider)
+            .with_router(Arc::new(router))
+            .run(shutdown.clone()),
+    );
+    for _ in 0..40 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+    }
+
+    let mut object = dicom_object::InMemDicomObject::new_empty();
+    object.put(DataElement::new(
+        tags::SOP_CLASS_UID,
+        VR::UI,
+        PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.7"),
+    ));
+    object.put(DataElement::new(
+        tags::SOP_INSTANCE_UID,
+        VR::UI,
+        PrimitiveValue::from("1.2.826.0.1.3680043.10.5432.804"),
+    ));
+    let scu = DimseScu::new(DimseConfig {
+        local_aet: "MODALITY".to_string(),
+        ..Default::default()
+    });
+    let stored = scu
+        .store(
+            &RemoteNode::new("HARMONY_SCP", "127.0.0.1", port),
+            DatasetStream::from_object(object),
+        )
+        .await
+        .unwrap();
+    assert!(stored);
+    shutdown.cancel();
+    let _ = server.await;
+
+    // SCP association -> pipeline run -> DICOM backend SCU, all under one ID
+    let association = recorder.correlation_ids("dimse.association", None);
+    assert_eq!(association.len(), 1, "one association accepted");
+    assert!(
+        !association[0].is_empty(),
+        "association has a correlation ID"
+    );
+    assert_eq!(recorder.correlation_ids("execute", None), association);
+    // The routed C-STORE's response is tagged with the association's ID
+    assert_eq!(*responses.lock().unwrap(), association);
+    // The pipeline falls back to C-GET, which is enough to start the backend's SCU
+    assert_eq!(
+        recorder.correlation_ids("dimse.scu", Some("C-GET")),
+        association
+    );
+    // The modality's own SCU is not part of the chain
+    assert_ne!(
+        recorder.correlation_ids("dimse.scu", Some("C-STORE")),
+        association
+    );
+}
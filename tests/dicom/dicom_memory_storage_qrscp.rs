@@ -0,0 +1,268 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use harmony::config::config::{Config, ConfigError};
+use harmony::storage::{MemoryStorage, StorageBackend};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn load_config_from_str(toml: &str) -> Result<Config, ConfigError> {
+    let config: Config = toml::from_str(toml).expect("TOML parse error");
+    config.validate()?;
+    Ok(config)
+}
+
+/// POST an identifier to the DICOM bridge and return the JSON response
+async fn post(app: &axum::Router, op: &str, study_uid: &str) -> serde_json::Value {
+    let body = serde_json::json!({
+        "identifier": {
+            "0020000D": { "vr": "UI", "Value": [ study_uid ] }
+        }
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(format!("/dicom/{}", op))
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .expect("router handled request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).expect("json parse")
+}
+
+/// SOP Instance UIDs of the instances a retrieval left in memory storage
+async fn stored_instances(storage: &MemoryStorage, folder_id: &str) -> Vec<String> {
+    let folder = format!("dimse/{}", folder_id);
+    let mut uids = Vec::new();
+    for name in storage
+        .list_files_recursive_str(&folder)
+        .await
+        .expect("retrieval folder in memory storage")
+    {
+        let bytes = storage
+            .read_file_str(&format!("{}/{}", folder, name))
+            .await
+            .expect("read instance back");
+        assert_eq!(&bytes[128..132], b"DICM", "{} is not a Part 10 file", name);
+        let object = dicom_object::from_reader(&bytes[128..]).expect("parse instance");
+        let uid = object
+            .element(dicom_dictionary_std::tags::SOP_INSTANCE_UID)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .trim_end_matches('\0')
+            .to_string();
+        uids.push(uid);
+    }
+    uids
+}
+
+#[tokio::test]
+async fn dicom_get_and_move_into_memory_storage() {
+    // Skip if DCMTK tools are not present
+    for bin in ["dcmqrscp", "storescu", "movescu"].iter() {
+        if std::process::Command::new(bin)
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("Skipping memory storage retrieval test: {} not found", bin);
+            return;
+        }
+    }
+
+    // Pick free ports for the QR SCP and for the instances C-MOVE sends back
+    let free_port = || {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind port");
+        listener.local_addr().unwrap().port()
+    };
+    let port = free_port();
+    let store_port = free_port();
+
+    // Prepare QR storage directory and config
+    let base = PathBuf::from("../../tmp/qrscp_memory");
+    let dbdir = base.join("qrdb");
+    std::fs::create_dir_all(&dbdir).expect("create qr db dir");
+    let cfg_path = base.join("dcmqrscp.cfg");
+    let abs_db = match std::fs::canonicalize(&dbdir) {
+        Ok(p) => p,
+        Err(_) => std::env::current_dir().unwrap().join(&dbdir),
+    };
+    let cfg = format!(
+        "# Minimal dcmqrscp.cfg\nMaxPDUSize = 16384\nMaxAssociations = 16\n\nHostTable BEGIN\nHARMONY_MEM = (HARMONY_MEM, 127.0.0.1, {store_port})\nHostTable END\n\nVendorTable BEGIN\nVendorTable END\n\nAETable BEGIN\nQR_SCP  {db}  RW  (9, 1024mb)  ANY\nAETable END\n",
+        store_port = store_port,
+        db = abs_db.to_string_lossy()
+    );
+    std::fs::write(&cfg_path, cfg).expect("write cfg");
+
+    // Start dcmqrscp (quiet by default; enable verbose with HARMONY_TEST_VERBOSE_DCMTK=1)
+    let verbose = std::env::var("HARMONY_TEST_VERBOSE_DCMTK").ok().as_deref() == Some("1");
+    let mut dcmqr = tokio::process::Command::new("dcmqrscp");
+    if verbose {
+        dcmqr.arg("-d");
+    }
+    let dcmqr = dcmqr.arg("-c").arg(&cfg_path).arg(port.to_string());
+    if !verbose {
+        dcmqr
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+    }
+    let mut qr_child = dcmqr.kill_on_drop(true).spawn().expect("spawn dcmqrscp");
+
+    // Wait for port to be ready
+    for _ in 0..60 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    // Build a minimal identifier and Part 10 file with known StudyInstanceUID
+    let mkuid = |suf: &str| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        format!(
+            "1.2.826.0.1.3680043.10.5432.{}.{}.{}",
+            suf,
+            now.as_secs(),
+            now.subsec_nanos()
+        )
+    };
+    let study_uid = mkuid("study");
+    let sop_uid = mkuid("sop");
+    let identifier = serde_json::json!({
+        // SOP Class: Secondary Capture Image Storage
+        "00080016": { "vr": "UI", "Value": ["1.2.840.10008.5.1.4.1.1.7"] },
+        "00080018": { "vr": "UI", "Value": [ sop_uid ] },
+        "0020000D": { "vr": "UI", "Value": [ study_uid ] },
+        "0020000E": { "vr": "UI", "Value": [ mkuid("series") ] },
+        "00080060": { "vr": "CS", "Value": [ "OT" ] },
+        "00100020": { "vr": "LO", "Value": ["MEM123"] },
+        "00100010": { "vr": "PN", "Value": [{"Alphabetic": "DOE^MEMORY"}] }
+    });
+    let obj = dicom_json_tool::json_value_to_identifier(&identifier).expect("json->obj");
+    let dicom_path = base.join("seed_memory.dcm");
+    dicom_json_tool::write_part10(&dicom_path, &obj).expect("write seed");
+
+    // Send the dataset to QR via storescu
+    let mut st = tokio::process::Command::new("storescu");
+    let st = st
+        .arg("--aetitle")
+        .arg("HARMONY_MEM")
+        .arg("--call")
+        .arg("QR_SCP")
+        .arg("127.0.0.1")
+        .arg(port.to_string())
+        .arg(&dicom_path);
+    if !verbose {
+        st.stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+    }
+    let status = st.status().await.expect("run storescu");
+    if !status.success() {
+        eprintln!("storescu failed; skipping assertions");
+        let _ = qr_child.kill().await;
+        return;
+    }
+
+    // Retrievals go to memory storage; the directory only holds staging folders
+    let staging = tempfile::tempdir().expect("staging dir");
+    let storage = Arc::new(MemoryStorage::new(staging.path()).expect("memory storage"));
+    harmony::globals::set_storage(storage.clone());
+
+    let toml = format!(
+        r#"
+        [proxy]
+        id = "dicom-memory-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8082
+
+        [pipelines.bridge]
+        description = "HTTP -> DICOM backend bridge"
+        networks = ["default"]
+        endpoints = ["http_to_dicom"]
+        backends = ["dicom_pacs"]
+        middleware = []
+
+        [endpoints.http_to_dicom]
+        service = "http"
+        [endpoints.http_to_dicom.options]
+        path_prefix = "/dicom"
+
+        [backends.dicom_pacs]
+        service = "dicom"
+
+        [backends.dicom_pacs.options]
+        aet = "QR_SCP"
+        host = "127.0.0.1"
+        port = {port}
+        local_aet = "HARMONY_MEM"
+        incoming_store_port = {store_port}
+
+        [storage]
+        backend = "memory"
+
+        [services.http]
+        module = ""
+        [services.dicom]
+        module = ""
+    "#,
+        port = port,
+        store_port = store_port
+    );
+
+    let cfg: Config = load_config_from_str(&toml).expect("valid config");
+    let app = harmony::router::build_network_router(Arc::new(cfg), "default").await;
+
+    for op in ["get", "move"] {
+        let json = post(&app, op, &study_uid).await;
+        assert_eq!(json.get("operation").and_then(|v| v.as_str()), Some(op));
+        assert_eq!(
+            json.get("success").and_then(|v| v.as_bool()),
+            Some(true),
+            "{}",
+            json
+        );
+        assert_eq!(json.get("file_count").and_then(|v| v.as_u64()), Some(1));
+        // Nothing is left on disk to point at
+        assert!(json.get("folder_path").is_none(), "{}", json);
+
+        let folder_id = json
+            .get("folder_id")
+            .and_then(|v| v.as_str())
+            .expect("missing folder_id");
+        assert_eq!(
+            stored_instances(&storage, folder_id).await,
+            vec![sop_uid.clone()],
+            "C-{} instances in memory storage",
+            op.to_uppercase()
+        );
+        assert!(
+            !staging.path().join("dimse").join(folder_id).exists(),
+            "C-{} staging folder left behind",
+            op.to_uppercase()
+        );
+    }
+
+    let _ = qr_child.kill().await;
+}
@@ -0,0 +1,240 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use harmony::config::config::{Config, ConfigError};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+fn load_config_from_str(toml: &str) -> Result<Config, ConfigError> {
+    let config: Config = toml::from_str(toml).expect("TOML parse error");
+    config.validate()?;
+    Ok(config)
+}
+
+/// Two adjacent ports that are both free
+fn free_port_pair() -> (u16, u16) {
+    loop {
+        let first = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).expect("bind port");
+        let port = first.local_addr().unwrap().port();
+        if port < u16::MAX && TcpListener::bind((Ipv4Addr::UNSPECIFIED, port + 1)).is_ok() {
+            return (port, port + 1);
+        }
+    }
+}
+
+/// POST a C-MOVE of `study_uid` to the DICOM bridge and return the JSON response
+async fn move_study(app: &axum::Router, study_uid: &str) -> serde_json::Value {
+    let body = serde_json::json!({
+        "identifier": {
+            "0020000D": { "vr": "UI", "Value": [ study_uid ] }
+        }
+    });
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/dicom/move")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .expect("router handled request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).expect("json parse")
+}
+
+#[tokio::test]
+async fn dicom_moves_reach_each_leased_port_by_its_ae_title() {
+    // Skip if DCMTK tools are not present
+    for bin in ["dcmqrscp", "storescu", "movescu"].iter() {
+        if std::process::Command::new(bin)
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("Skipping C-MOVE port range test: {} not found", bin);
+            return;
+        }
+    }
+
+    // Pick a free port for QR SCP and two for the store port range
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind port");
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    let (port_a, port_b) = free_port_pair();
+
+    // The QR SCP knows a different destination AE at each port of the range
+    let base = PathBuf::from("../../tmp/qrscp_port_range");
+    let dbdir = base.join("qrdb");
+    std::fs::create_dir_all(&dbdir).expect("create qr db dir");
+    let cfg_path = base.join("dcmqrscp.cfg");
+    let abs_db = match std::fs::canonicalize(&dbdir) {
+        Ok(p) => p,
+        Err(_) => std::env::current_dir().unwrap().join(&dbdir),
+    };
+    let cfg = format!(
+        "# Minimal dcmqrscp.cfg\nMaxPDUSize = 16384\nMaxAssociations = 16\n\nHostTable BEGIN\nHARMONY_A = (HARMONY_A, 127.0.0.1, {port_a})\nHARMONY_B = (HARMONY_B, 127.0.0.1, {port_b})\nHostTable END\n\nVendorTable BEGIN\nVendorTable END\n\nAETable BEGIN\nQR_SCP  {db}  RW  (9, 1024mb)  ANY\nAETable END\n",
+        port_a = port_a,
+        port_b = port_b,
+        db = abs_db.to_string_lossy()
+    );
+    std::fs::write(&cfg_path, cfg).expect("write cfg");
+
+    // Start dcmqrscp (quiet by default; enable verbose with HARMONY_TEST_VERBOSE_DCMTK=1)
+    let verbose = std::env::var("HARMONY_TEST_VERBOSE_DCMTK").ok().as_deref() == Some("1");
+    let mut dcmqr = tokio::process::Command::new("dcmqrscp");
+    if verbose {
+        dcmqr.arg("-d");
+    }
+    let dcmqr = dcmqr.arg("-c").arg(&cfg_path).arg(port.to_string());
+    if !verbose {
+        dcmqr
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+    }
+    let mut qr_child = dcmqr.kill_on_drop(true).spawn().expect("spawn dcmqrscp");
+
+    // Wait for port to be ready
+    for _ in 0..60 {
+        if tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    // Build a minimal identifier and Part 10 file with known StudyInstanceUID
+    let mkuid = |suf: &str| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        format!(
+            "1.2.826.0.1.3680043.10.5432.{}.{}.{}",
+            suf,
+            now.as_secs(),
+            now.subsec_nanos()
+        )
+    };
+    let study_uid = mkuid("study");
+    let identifier = serde_json::json!({
+        // SOP Class: Secondary Capture Image Storage
+        "00080016": { "vr": "UI", "Value": ["1.2.840.10008.5.1.4.1.1.7"] },
+        "00080018": { "vr": "UI", "Value": [ mkuid("sop") ] },
+        "0020000D": { "vr": "UI", "Value": [ study_uid ] },
+        "0020000E": { "vr": "UI", "Value": [ mkuid("series") ] },
+        "00080060": { "vr": "CS", "Value": [ "OT" ] },
+        "00100020": { "vr": "LO", "Value": ["RANGE123"] },
+        "00100010": { "vr": "PN", "Value": [{"Alphabetic": "DOE^RANGE"}] }
+    });
+    let obj = dicom_json_tool::json_value_to_identifier(&identifier).expect("json->obj");
+    let dicom_path = base.join("seed_port_range.dcm");
+    dicom_json_tool::write_part10(&dicom_path, &obj).expect("write seed");
+
+    // Send the dataset to QR via storescu
+    let mut st = tokio::process::Command::new("storescu");
+    let st = st
+        .arg("--aetitle")
+        .arg("HARMONY_SCU")
+        .arg("--call")
+        .arg("QR_SCP")
+        .arg("127.0.0.1")
+        .arg(port.to_string())
+        .arg(&dicom_path);
+    if !verbose {
+        st.stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null());
+    }
+    let status = st.status().await.expect("run storescu");
+    if !status.success() {
+        eprintln!("storescu failed; skipping assertions");
+        let _ = qr_child.kill().await;
+        return;
+    }
+
+    let toml = format!(
+        r#"
+        [proxy]
+        id = "dicom-move-port-range-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8083
+
+        [pipelines.bridge]
+        description = "HTTP -> DICOM backend bridge"
+        networks = ["default"]
+        endpoints = ["http_to_dicom"]
+        backends = ["dicom_pacs"]
+        middleware = []
+
+        [endpoints.http_to_dicom]
+        service = "http"
+        [endpoints.http_to_dicom.options]
+        path_prefix = "/dicom"
+
+        [backends.dicom_pacs]
+        service = "dicom"
+
+        [backends.dicom_pacs.options]
+        aet = "QR_SCP"
+        host = "127.0.0.1"
+        port = {port}
+        local_aet = "HARMONY_SCU"
+        incoming_store_port_range = [{port_a}, {port_b}]
+
+        [backends.dicom_pacs.options.incoming_store_aets]
+        "{port_a}" = "HARMONY_A"
+        "{port_b}" = "HARMONY_B"
+
+        [services.http]
+        module = ""
+        [services.dicom]
+        module = ""
+    "#,
+        port = port,
+        port_a = port_a,
+        port_b = port_b
+    );
+
+    let cfg: Config = load_config_from_str(&toml).expect("valid config");
+    let app = harmony::router::build_network_router(Arc::new(cfg), "default").await;
+
+    // The first move leases the lowest port and is reached as HARMONY_A
+    let first = move_study(&app, &study_uid).await;
+    assert_eq!(
+        first.get("success").and_then(|v| v.as_bool()),
+        Some(true),
+        "{}",
+        first
+    );
+    assert_eq!(first.get("file_count").and_then(|v| v.as_u64()), Some(1));
+
+    // With the lowest port taken, the second move listens on the other one, and the QR SCP
+    // only delivers there because the move names HARMONY_B as its destination
+    let taken = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port_a)).expect("hold first port");
+    let second = move_study(&app, &study_uid).await;
+    drop(taken);
+    assert_eq!(
+        second.get("success").and_then(|v| v.as_bool()),
+        Some(true),
+        "{}",
+        second
+    );
+    assert_eq!(second.get("file_count").and_then(|v| v.as_u64()), Some(1));
+
+    let _ = qr_child.kill().await;
+}
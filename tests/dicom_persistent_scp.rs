@@ -17,6 +17,7 @@ fn create_test_config_with_backends(
     network.http = HttpConfig {
         bind_address: "127.0.0.1".to_string(),
         bind_port: 8080,
+        ..Default::default()
     };
     config.network.insert("test_network".to_string(), network);
     
@@ -69,6 +70,7 @@ fn create_test_config_with_endpoints(
     network.http = HttpConfig {
         bind_address: "127.0.0.1".to_string(),
         bind_port: 8080,
+        ..Default::default()
     };
     config.network.insert("test_network".to_string(), network);
     
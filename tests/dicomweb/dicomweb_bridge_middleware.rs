@@ -89,3 +89,232 @@ async fn middleware_maps_studies_to_find() {
         "QIDO /studies endpoint should be supported with dicomweb_bridge middleware"
     );
 }
+
+fn mock_pending_warning_cfg() -> &'static str {
+    r#"
+        [proxy]
+        id = "dicomweb-bridge-warning-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8080
+
+        [pipelines.bridge]
+        description = "DICOMweb -> mock DIMSE bridge returning 0xFF01"
+        networks = ["default"]
+        endpoints = ["dicomweb"]
+        middleware = ["dicomweb_bridge"]
+        backends = ["mock_dicom_backend"]
+
+        [endpoints.dicomweb]
+        service = "dicomweb"
+        [endpoints.dicomweb.options]
+        path_prefix = "/dicomweb"
+
+        [backends.mock_dicom_backend]
+        service = "mock_dicom"
+        [backends.mock_dicom_backend.options]
+        find_status = "FF01"
+
+        [services.dicomweb]
+        module = ""
+        [services.mock_dicom]
+        module = ""
+
+        [middleware.dicomweb_bridge]
+        type = "dicomweb_bridge"
+
+        [middleware_types.dicomweb_bridge]
+        module = ""
+    "#
+}
+
+#[tokio::test]
+async fn pending_with_warning_status_sets_warning_header() {
+    let _ = std::fs::create_dir_all("./tmp");
+    let c = load_config_from_str(mock_pending_warning_cfg()).expect("valid config");
+    let app = harmony::router::build_network_router(Arc::new(c), "default").await;
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/dicomweb/studies")
+                .method("GET")
+                .header("Accept", "application/dicom+json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("handled");
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let warning = resp
+        .headers()
+        .get("warning")
+        .expect("Warning header for 0xFF01 responses")
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(warning.starts_with("299 "), "unexpected warning: {}", warning);
+    assert!(warning.contains("optional keys"), "unexpected warning: {}", warning);
+}
+
+fn denied_tag_cfg() -> &'static str {
+    r#"
+        [proxy]
+        id = "dicomweb-bridge-denylist-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8080
+
+        [pipelines.bridge]
+        description = "DICOMweb -> mock DIMSE bridge with a denied match key"
+        networks = ["default"]
+        endpoints = ["dicomweb"]
+        middleware = ["dicomweb_bridge"]
+        backends = ["mock_dicom_backend"]
+
+        [endpoints.dicomweb]
+        service = "dicomweb"
+        [endpoints.dicomweb.options]
+        path_prefix = "/dicomweb"
+
+        [backends.mock_dicom_backend]
+        service = "mock_dicom"
+
+        [services.dicomweb]
+        module = ""
+        [services.mock_dicom]
+        module = ""
+
+        [middleware.dicomweb_bridge]
+        type = "dicomweb_bridge"
+        [middleware.dicomweb_bridge.options]
+        denied_query_tags = ["PatientBirthDate"]
+
+        [middleware_types.dicomweb_bridge]
+        module = ""
+    "#
+}
+
+#[tokio::test]
+async fn denied_query_tag_returns_400() {
+    let _ = std::fs::create_dir_all("./tmp");
+    let c = load_config_from_str(denied_tag_cfg()).expect("valid config");
+    let app = harmony::router::build_network_router(Arc::new(c), "default").await;
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri("/dicomweb/studies?PatientBirthDate=19700101")
+                .method("GET")
+                .header("Accept", "application/dicom+json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("handled");
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["message"]
+        .as_str()
+        .unwrap()
+        .contains("PatientBirthDate"));
+}
+
+fn existence_check_cfg() -> &'static str {
+    r#"
+        [proxy]
+        id = "dicomweb-bridge-existence-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8080
+
+        [pipelines.bridge]
+        description = "DICOMweb -> mock DIMSE bridge with instance existence check"
+        networks = ["default"]
+        endpoints = ["dicomweb"]
+        middleware = ["dicomweb_bridge"]
+        backends = ["mock_dicom_backend"]
+
+        [endpoints.dicomweb]
+        service = "dicomweb"
+        [endpoints.dicomweb.options]
+        path_prefix = "/dicomweb"
+
+        [backends.mock_dicom_backend]
+        service = "mock_dicom"
+
+        [services.dicomweb]
+        module = ""
+        [services.mock_dicom]
+        module = ""
+
+        [middleware.dicomweb_bridge]
+        type = "dicomweb_bridge"
+        [middleware.dicomweb_bridge.options]
+        existence_check_backend = "mock_dicom_backend"
+
+        [middleware_types.dicomweb_bridge]
+        module = ""
+    "#
+}
+
+#[tokio::test]
+async fn missing_instance_returns_404_before_retrieval() {
+    let _ = std::fs::create_dir_all("./tmp");
+    let c = Arc::new(load_config_from_str(existence_check_cfg()).expect("valid config"));
+    // The existence check resolves its backend from the running configuration
+    harmony::globals::set_config(c.clone());
+    let app = harmony::router::build_network_router(c, "default").await;
+
+    let data = harmony::models::services::types::mock_dicom::MockDicomData::instance();
+    let uri = format!(
+        "/dicomweb/studies/{}/series/{}/instances/9.9.9.9/frames/1",
+        data.study_uid, data.series[0].series_uid
+    );
+
+    let resp = app
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .method("GET")
+                .header("Accept", "image/jpeg")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("handled");
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"], "NotFound");
+    assert!(json["message"].as_str().unwrap().contains("9.9.9.9"));
+}
@@ -0,0 +1,140 @@
+use harmony::adapters::http::HttpAdapter;
+use harmony::adapters::ProtocolAdapter;
+use harmony::config::config::{Config, ConfigError};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+fn load_config_from_str(toml: &str) -> Result<Config, ConfigError> {
+    let config: Config = toml::from_str(toml).expect("TOML parse error");
+    config.validate()?;
+    Ok(config)
+}
+
+fn tls_config(port: u16, cert_path: &str, key_path: &str) -> String {
+    format!(
+        r#"
+        [proxy]
+        id = "https-adapter-test"
+        log_level = "info"
+        store_dir = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = {port}
+
+        [network.default.http.tls]
+        cert_path = "{cert_path}"
+        key_path = "{key_path}"
+
+        [pipelines.bridge]
+        description = "DICOMweb over HTTPS -> mock DIMSE"
+        networks = ["default"]
+        endpoints = ["dicomweb"]
+        middleware = ["dicomweb_bridge"]
+        backends = ["mock_dicom_backend"]
+
+        [endpoints.dicomweb]
+        service = "dicomweb"
+        [endpoints.dicomweb.options]
+        path_prefix = "/dicomweb"
+
+        [backends.mock_dicom_backend]
+        service = "mock_dicom"
+
+        [services.dicomweb]
+        module = ""
+        [services.mock_dicom]
+        module = ""
+
+        [middleware.dicomweb_bridge]
+        type = "dicomweb_bridge"
+
+        [middleware_types.dicomweb_bridge]
+        module = ""
+    "#
+    )
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[tokio::test]
+async fn tls_adapter_serves_qido_over_https() {
+    let _ = std::fs::create_dir_all("./tmp");
+    let dir = tempfile::tempdir().unwrap();
+    let rcgen::CertifiedKey { cert, key_pair } =
+        rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert_path = dir.path().join("cert.pem");
+    let key_path = dir.path().join("key.pem");
+    std::fs::write(&cert_path, cert.pem()).unwrap();
+    std::fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+
+    let port = free_port();
+    let config = load_config_from_str(&tls_config(
+        port,
+        cert_path.to_str().unwrap(),
+        key_path.to_str().unwrap(),
+    ))
+    .expect("valid config");
+    let tls = config.network["default"].http.tls.clone();
+    assert!(tls.is_some());
+
+    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let shutdown = CancellationToken::new();
+    let adapter = HttpAdapter::new("default".to_string(), addr).with_tls(tls);
+    let handle = adapter
+        .start(Arc::new(config), shutdown.clone())
+        .await
+        .expect("adapter starts");
+
+    let client = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(cert.pem().as_bytes()).unwrap())
+        .resolve("localhost", addr)
+        .build()
+        .unwrap();
+    let url = format!("https://localhost:{}/dicomweb/studies", port);
+
+    // The listener binds inside the spawned task; retry briefly until it is up
+    let mut response = None;
+    for _ in 0..50 {
+        match client
+            .get(&url)
+            .header("Accept", "application/dicom+json")
+            .send()
+            .await
+        {
+            Ok(resp) => {
+                response = Some(resp);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+    let response = response.expect("HTTPS request succeeds");
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/dicom+json"
+    );
+    let studies: serde_json::Value = response.json().await.unwrap();
+    assert!(!studies.as_array().unwrap().is_empty());
+
+    // Plain HTTP against the TLS port must not be served
+    let plain = reqwest::get(format!("http://127.0.0.1:{}/dicomweb/studies", port)).await;
+    assert!(plain.map(|r| !r.status().is_success()).unwrap_or(true));
+
+    shutdown.cancel();
+    let _ = tokio::time::timeout(Duration::from_secs(5), handle).await;
+}
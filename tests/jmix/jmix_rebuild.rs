@@ -0,0 +1,271 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use harmony::config::config::{Config, ConfigError};
+use harmony::storage::StorageBackend;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn load_config_from_str(toml: &str) -> Result<Config, ConfigError> {
+    let config: Config = toml::from_str(toml).expect("TOML parse error");
+    config.validate()?;
+    Ok(config)
+}
+
+/// Write a minimal Part 10 instance belonging to `study_uid` into `dir`
+fn write_instance(dir: &Path, study_uid: &str, n: u32) {
+    let identifier = serde_json::json!({
+        "00080016": { "vr": "UI", "Value": ["1.2.840.10008.5.1.4.1.1.7"] },
+        "00080018": { "vr": "UI", "Value": [format!("{}.1.{}", study_uid, n)] },
+        "00100010": { "vr": "PN", "Value": [{ "Alphabetic": "Rebuild^Test" }] },
+        "00100020": { "vr": "LO", "Value": ["PID-REBUILD"] },
+        "0020000D": { "vr": "UI", "Value": [study_uid] },
+        "0020000E": { "vr": "UI", "Value": [format!("{}.1", study_uid)] },
+        "00080060": { "vr": "CS", "Value": ["OT"] }
+    });
+    let obj = dicom_json_tool::json_value_to_identifier(&identifier).expect("identifier");
+    dicom_json_tool::write_part10(&dir.join(format!("IM{}.dcm", n)), &obj).expect("write dicom");
+}
+
+fn rebuild_config(source_dir: &Path) -> String {
+    format!(
+        r#"
+        [proxy]
+        id = "jmix-rebuild-test"
+        log_level = "info"
+
+        [storage]
+        backend = "filesystem"
+        path = "./tmp"
+
+        [network.default]
+        enable_wireguard = false
+        interface = "wg0"
+
+        [network.default.http]
+        bind_address = "127.0.0.1"
+        bind_port = 8092
+
+        [pipelines.jmix]
+        description = "JMIX endpoint with mock DICOM backend"
+        networks = ["default"]
+        endpoints = ["jmix_http"]
+        backends = ["mock_pacs"]
+        middleware = ["jmix_builder"]
+
+        [endpoints.jmix_http]
+        service = "jmix"
+        [endpoints.jmix_http.options]
+        path_prefix = "/jmix"
+
+        [backends.mock_pacs]
+        service = "mock_dicom"
+        [backends.mock_pacs.options]
+        get_source_dir = "{}"
+
+        [middleware.jmix_builder]
+        type = "jmix_builder"
+
+        [services.jmix]
+        module = ""
+        [services.mock_dicom]
+        module = ""
+    "#,
+        source_dir.to_string_lossy()
+    )
+}
+
+/// Bearer token accepted by the admin routes (signed with the development secret)
+fn admin_token() -> String {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let secret = std::env::var("RUNBEAM_JWT_SECRET")
+        .unwrap_or_else(|_| "base64:your-secret-key-goes-here".to_string());
+    let now = chrono::Utc::now().timestamp();
+    let token = encode(
+        &Header::default(),
+        &serde_json::json!({
+            "iss": "https://runbeam.example",
+            "sub": "operator-1",
+            "iat": now,
+            "exp": now + 3600
+        }),
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .unwrap();
+    format!("Bearer {}", token)
+}
+
+async fn post(app: &axum::Router<()>, uri: &str) -> (StatusCode, serde_json::Value) {
+    post_as(app, uri, Some(&admin_token())).await
+}
+
+async fn post_as(
+    app: &axum::Router<()>,
+    uri: &str,
+    authorization: Option<&str>,
+) -> (StatusCode, serde_json::Value) {
+    let mut request = Request::builder().uri(uri).method("POST");
+    if let Some(authorization) = authorization {
+        request = request.header("authorization", authorization);
+    }
+    let resp = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .expect("router handled request");
+    let status = resp.status();
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn rebuild_regenerates_deleted_zip() {
+    harmony::globals::reset_storage();
+    use harmony::storage::filesystem::FilesystemStorage;
+    let storage =
+        Arc::new(FilesystemStorage::new("./tmp").expect("Failed to create test storage"));
+    harmony::globals::set_storage(storage.clone());
+    let store_root = storage.subpath_str("jmix-store");
+    fs::create_dir_all(&store_root).expect("create jmix-store dir");
+
+    // Upstream study served by the mock backend
+    let study_uid = format!(
+        "1.2.826.0.1.3680043.10.{}",
+        Uuid::new_v4().as_u128() % 1_000_000_000
+    );
+    let source_dir = PathBuf::from("./tmp/jmix_rebuild_src").join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&source_dir).expect("create source dir");
+    write_instance(&source_dir, &study_uid, 1);
+    write_instance(&source_dir, &study_uid, 2);
+
+    let cfg = load_config_from_str(&rebuild_config(&source_dir)).expect("valid config");
+    let app = harmony::router::build_network_router(Arc::new(cfg), "default").await;
+
+    // Admin routes need the management Bearer token
+    let rebuild_by_study = format!("/jmix/admin/jmix/rebuild?studyInstanceUid={}", study_uid);
+    let (status, _) = post_as(&app, &rebuild_by_study, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_as(&app, &rebuild_by_study, Some("Bearer not-a-jwt")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Initial build via rebuild-by-study
+    let (status, json) = post(&app, &rebuild_by_study).await;
+    assert_eq!(status, StatusCode::OK, "initial build failed: {}", json);
+    let first_id = json["id"].as_str().expect("package id").to_string();
+    let first_zip = store_root.join(&first_id).join(format!("{}.zip", first_id));
+    assert!(first_zip.exists());
+
+    // Simulate the zip going missing, then force a rebuild by package id
+    fs::remove_file(&first_zip).expect("delete zip");
+    let (status, json) = post(&app, &format!("/jmix/admin/jmix/{}/rebuild", first_id)).await;
+    assert_eq!(status, StatusCode::OK, "rebuild failed: {}", json);
+    assert_eq!(json["previous_id"].as_str(), Some(first_id.as_str()));
+    assert_eq!(json["studyInstanceUid"].as_str(), Some(study_uid.as_str()));
+
+    let new_id = json["id"].as_str().expect("new package id");
+    let new_zip = store_root.join(new_id).join(format!("{}.zip", new_id));
+    assert!(new_zip.exists(), "rebuilt zip missing at {}", new_zip.display());
+
+    // Study that no longer exists upstream
+    let (status, _) = post(&app, "/jmix/admin/jmix/rebuild?studyInstanceUid=9.9.9.9").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Unknown package id
+    let unknown = format!("/jmix/admin/jmix/{}/rebuild", Uuid::new_v4());
+    let (status, _) = post(&app, &unknown).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn archive_download_resumes_with_byte_range() {
+    harmony::globals::reset_storage();
+    use harmony::storage::filesystem::FilesystemStorage;
+    let storage =
+        Arc::new(FilesystemStorage::new("./tmp").expect("Failed to create test storage"));
+    harmony::globals::set_storage(storage.clone());
+    let store_root = storage.subpath_str("jmix-store");
+    fs::create_dir_all(&store_root).expect("create jmix-store dir");
+
+    let study_uid = format!(
+        "1.2.826.0.1.3680043.10.{}",
+        Uuid::new_v4().as_u128() % 1_000_000_000
+    );
+    let source_dir = PathBuf::from("./tmp/jmix_rebuild_src").join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&source_dir).expect("create source dir");
+    write_instance(&source_dir, &study_uid, 1);
+
+    let cfg = load_config_from_str(&rebuild_config(&source_dir)).expect("valid config");
+    let app = harmony::router::build_network_router(Arc::new(cfg), "default").await;
+
+    let (status, json) = post(
+        &app,
+        &format!("/jmix/admin/jmix/rebuild?studyInstanceUid={}", study_uid),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "build failed: {}", json);
+    let id = json["id"].as_str().expect("package id").to_string();
+    let zip_bytes = fs::read(store_root.join(&id).join(format!("{}.zip", id))).expect("zip");
+    assert!(zip_bytes.len() > 100, "package too small to slice");
+
+    let get_range = |range: String| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!("/jmix/api/jmix/{}", id))
+                .method("GET")
+                .header("accept", "application/zip")
+                .header("range", range)
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let total = zip_bytes.len();
+
+    let resp = get_range("bytes=10-99".to_string())
+        .await
+        .expect("router handled request");
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.headers()["accept-ranges"], "bytes");
+    assert_eq!(resp.headers()["content-type"], "application/zip");
+    assert_eq!(
+        resp.headers()["content-range"].to_str().unwrap(),
+        format!("bytes 10-99/{}", total)
+    );
+    assert_eq!(resp.headers()["content-length"], "90");
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], &zip_bytes[10..100]);
+
+    // Resuming from an offset returns the rest of the package
+    let resp = get_range(format!("bytes={}-", total - 20))
+        .await
+        .expect("router handled request");
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        resp.headers()["content-range"].to_str().unwrap(),
+        format!("bytes {}-{}/{}", total - 20, total - 1, total)
+    );
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], &zip_bytes[total - 20..]);
+
+    // A range starting past the end cannot be satisfied
+    let resp = get_range(format!("bytes={}-", total))
+        .await
+        .expect("router handled request");
+    assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        resp.headers()["content-range"].to_str().unwrap(),
+        format!("bytes */{}", total)
+    );
+    let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+}
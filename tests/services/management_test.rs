@@ -55,10 +55,14 @@ async fn test_management_service_enabled() {
     // Test router configuration
     let routes = service.build_router(endpoint_options);
     let paths: Vec<_> = routes.iter().map(|r| r.path.as_str()).collect();
-    assert_eq!(routes.len(), 3); // Updated to match actual count
+    assert_eq!(routes.len(), 8); // Updated to match actual count
     assert!(paths.contains(&"/admin/info"));
     assert!(paths.contains(&"/admin/pipelines"));
     assert!(paths.contains(&"/admin/routes"));
+    assert!(paths.contains(&"/admin/dimse/move"));
+    assert!(paths.contains(&"/admin/metrics"));
+    assert!(paths.contains(&"/admin/health"));
+    assert!(paths.contains(&"/admin/ready"));
 }
 
 #[tokio::test]
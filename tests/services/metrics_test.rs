@@ -0,0 +1,146 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use dimse::association::{
+    encode_associate_rq, encode_p_data, parse_p_data, release_rq_pdu, ProposedContext,
+};
+use dimse::command::{Command, C_ECHO_RQ, STATUS_SUCCESS};
+use dimse::scp::DefaultQueryProvider;
+use dimse::{DimseConfig, DimseScp};
+use harmony::config::config::Config;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
+use tower::ServiceExt; // for Router::oneshot
+
+const VERIFICATION: &str = "1.2.840.10008.1.1";
+const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
+
+async fn read_pdu(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut header = [0u8; 6];
+    stream.read_exact(&mut header).await.unwrap();
+    let len = u32::from_be_bytes([header[2], header[3], header[4], header[5]]) as usize;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body).await.unwrap();
+    (header[0], body)
+}
+
+/// Open an association with the SCP, send one C-ECHO and release
+async fn echo(port: u16) {
+    let mut stream = None;
+    for _ in 0..40 {
+        if let Ok(s) = TcpStream::connect(("127.0.0.1", port)).await {
+            stream = Some(s);
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+    }
+    let mut stream = stream.expect("SCP should accept connections");
+
+    let contexts = [ProposedContext {
+        id: 1,
+        abstract_syntax: VERIFICATION.to_string(),
+        transfer_syntaxes: vec![IMPLICIT_VR_LITTLE_ENDIAN.to_string()],
+    }];
+    let rq = encode_associate_rq(
+        "METRICS_SCU",
+        "HARMONY_SCP",
+        &contexts,
+        &Default::default(),
+        16384,
+    );
+    stream.write_all(&rq).await.unwrap();
+    let (pdu_type, _) = read_pdu(&mut stream).await;
+    assert_eq!(pdu_type, 0x02, "expected A-ASSOCIATE-AC");
+
+    let request = Command {
+        command_field: C_ECHO_RQ,
+        message_id: 1,
+        affected_sop_class_uid: Some(VERIFICATION.to_string()),
+        ..Default::default()
+    };
+    for pdu in encode_p_data(1, true, &request.encode(), 16384) {
+        stream.write_all(&pdu).await.unwrap();
+    }
+    let (pdu_type, body) = read_pdu(&mut stream).await;
+    assert_eq!(pdu_type, 0x04, "expected P-DATA-TF");
+    let pdvs = parse_p_data(&body).unwrap();
+    let response = Command::parse(pdvs[0].data).unwrap();
+    assert_eq!(response.status, Some(STATUS_SUCCESS));
+
+    stream.write_all(&release_rq_pdu()).await.unwrap();
+    let (pdu_type, _) = read_pdu(&mut stream).await;
+    assert_eq!(pdu_type, 0x06, "expected A-RELEASE-RP");
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_reports_c_echo() {
+    let mut config = Config::default();
+    let mut network_config = harmony::models::network::config::NetworkConfig::default();
+    network_config.interface = "default".to_string();
+    network_config.http.bind_address = "127.0.0.1".to_string();
+    network_config.http.bind_port = 8080;
+    config.network.insert("default".to_string(), network_config);
+    config.management.enabled = true;
+    config.management.base_path = "admin".to_string();
+    config.management.network = Some("default".to_string());
+    config.inject_management_service();
+
+    // Building the router installs the recorder, so it must come before the C-ECHO
+    let app = harmony::router::build_network_router(Arc::new(config), "default").await;
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dimse_config = DimseConfig {
+        local_aet: "HARMONY_SCP".to_string(),
+        bind_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        port,
+        storage_dir: temp_dir.path().to_path_buf(),
+        ..Default::default()
+    };
+    let provider = Arc::new(DefaultQueryProvider::new(temp_dir.path().to_path_buf()));
+    let server = tokio::spawn(DimseScp::new(dimse_config, provider).run(CancellationToken::new()));
+    echo(port).await;
+    server.abort();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/admin/metrics")
+                .method("GET")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("router handled request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let content_type = response.headers()["content-type"].to_str().unwrap();
+    assert!(content_type.starts_with("text/plain"));
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        text.lines().any(
+            |line| line.starts_with("dimse_associations_opened_total{role=\"scp\"}")
+                && line.ends_with(" 1")
+        ),
+        "no SCP association in:\n{}",
+        text
+    );
+    assert!(
+        text.lines().any(
+            |line| line.starts_with("dimse_operation_duration_seconds_count")
+                && line.contains("operation=\"C-ECHO\"")
+                && line.ends_with(" 1")
+        ),
+        "no C-ECHO latency in:\n{}",
+        text
+    );
+}